@@ -0,0 +1,9 @@
+// nzm_schema/src/lib.rs
+//
+// 主程序 (nzm_cmd) 与图形工具 (tools/UI_tool) 共用的数据文件格式。
+// 字段含义以这里的文档注释为准，改格式时只改这一处，两边同时生效。
+
+pub mod map;      // 界面路由地图 (ui_map.toml)
+pub mod strategy; // 塔防地形 / 策略 / 陷阱配置 (JSON 或 TOML)
+pub mod migrate;  // 版本迁移 (旧格式 -> 当前格式)
+pub mod session;  // 会话录像 (事件 JSONL + 缩小截图)
@@ -0,0 +1,397 @@
+// nzm_schema/src/map.rs
+use serde::{Deserialize, Serialize};
+
+use crate::migrate::{self, Migration};
+
+/// 当前地图格式版本；旧版 tool/ 生成的文件视为 0，读取时自动迁移 (见 migrate)
+pub const MAP_SCHEMA_VERSION: u32 = 1;
+
+/// 每次点击的固定开销 (ms)：人类化移动 + 点击 + 跳转后的稳定等待
+pub const CLICK_COST: u64 = 800;
+
+/// scroll 跳转 delta 的上限 (格)；每格约 0.12 秒，Windows 的 WHEEL_DELTA (120) 写法会滚上十几秒
+pub const MAX_SCROLL_DELTA: i32 = 50;
+
+// ==========================================
+// 1. 地图根
+// ==========================================
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct MapFile {
+    /// 格式版本；不填视为当前版本
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schema_version: Option<u32>,
+    #[serde(default)]
+    pub scenes: Vec<Scene>,
+    /// 全局弹窗场景 (每日奖励、断线提示、公告等)：每一步之前检查，命中则执行其关闭跳转
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub global_scenes: Vec<Scene>,
+    /// 全局默认的场景置信度阈值；场景自身未设置 min_confidence 时生效
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_confidence: Option<f32>,
+    /// 全局文本模糊匹配阈值；不设置时文本锚点必须完整包含 val
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fuzzy_threshold: Option<f32>,
+    /// 地图坐标采集时的分辨率 [宽, 高]；设置后运行时按实际屏幕分辨率缩放所有坐标
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base_resolution: Option<[u32; 2]>,
+    /// 按窗口截图采集时游戏窗口客户区相对显示器的偏移 [x, y] (UI_tool 生成)；坐标在缩放后加上该偏移
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub window_offset: Option<[i32; 2]>,
+    /// 采集时显示器的 Windows DPI 缩放 (1.25 = 125%，UI_tool 生成)；与运行时不一致时给出警告
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dpi_scale: Option<f32>,
+}
+
+impl MapFile {
+    /// 解析地图文本：旧版格式先迁移到当前版本，返回迁移记录 (当前格式时为 None)
+    pub fn from_toml_str(content: &str) -> Result<(Self, Option<Migration>), String> {
+        let mut root: toml::Value = toml::from_str(content).map_err(|e| e.to_string())?;
+        let migration = migrate::migrate_map(&mut root)?;
+        let map = root.try_into().map_err(|e: toml::de::Error| e.to_string())?;
+        Ok((map, migration))
+    }
+}
+
+// ==========================================
+// 2. 场景与锚点
+// ==========================================
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Scene {
+    pub id: String,
+    /// 显示名 (UI_tool 与日志使用)，主程序只认 id
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub name: String,
+    /// "and" (默认) 或 "or"
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub logic: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub anchors: Option<Anchors>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transitions: Option<Vec<Transition>>,
+    /// 处理该界面的业务代号 (例如 "daily", "td")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub handler: Option<String>,
+    /// 置信度阈值 (0.0 ~ 1.0)。设置后按置信度判定，否则沿用 AND/OR 二值判定
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_confidence: Option<f32>,
+    /// 危险场景：一旦识别到立即中止导航，不再发出任何输入
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub danger: bool,
+}
+
+impl Scene {
+    /// 对场景中的所有坐标 (锚点、跳转、条件区域、恢复点击) 做同一个换算，矩形按两个角分别换算
+    pub fn map_points(&mut self, f: impl Fn([i32; 2]) -> [i32; 2]) {
+        let rect = |r: [i32; 4]| {
+            let [x1, y1] = f([r[0], r[1]]);
+            let [x2, y2] = f([r[2], r[3]]);
+            [x1, y1, x2, y2]
+        };
+        if let Some(anchors) = &mut self.anchors {
+            for t in anchors.text.iter_mut().flatten() { t.rect = rect(t.rect); }
+            for c in anchors.color.iter_mut().flatten() { c.pos = f(c.pos); }
+            for h in anchors.hsv.iter_mut().flatten() { h.pos = f(h.pos); }
+            for i in anchors.image.iter_mut().flatten() { i.rect = rect(i.rect); }
+        }
+        for t in self.transitions.iter_mut().flatten() {
+            t.coords = f(t.coords);
+            t.from = t.from.map(&f);
+            t.to = t.to.map(&f);
+            if let Some(cond) = &mut t.condition { cond.rect = rect(cond.rect); }
+            if let Some(RecoveryAction::Click { x, y }) = &mut t.on_fail {
+                [*x, *y] = f([*x, *y]);
+            }
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Anchors {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<Vec<TextAnchor>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<Vec<ColorAnchor>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hsv: Option<Vec<HsvAnchor>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image: Option<Vec<ImageAnchor>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TextAnchor {
+    pub rect: [i32; 4],
+    #[serde(default)]
+    pub val: String,
+    /// 可选：正则匹配 (如 "第\\d+波")，设置后优先于 val
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub val_regex: Option<String>,
+    /// 可选：模糊匹配阈值 (0.0 ~ 1.0)，相似度达到即视为命中；覆盖全局 fuzzy_threshold
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fuzzy: Option<f32>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ColorAnchor {
+    pub pos: [i32; 2],
+    /// 期望颜色 "#RRGGBB"
+    pub val: String,
+    pub tol: u8,
+}
+
+/// 区域颜色锚点：取 pos 为中心、size x size 区域的平均色，在 HSV 空间按色相 / 饱和度 / 亮度分别比较。
+/// 单像素 RGB 锚点遇到发光动画或轻微的伽马变化就会失效，区域平均 + 分量容差更稳定
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct HsvAnchor {
+    pub pos: [i32; 2],
+    /// 期望的区域平均色 "#RRGGBB"
+    pub val: String,
+    /// 采样区域边长 (像素)
+    #[serde(default = "default_hsv_size")]
+    pub size: u32,
+    /// 色相容差 (度)
+    #[serde(default = "default_hue_tol")]
+    pub hue_tol: f32,
+    /// 饱和度容差 (0.0 ~ 1.0)
+    #[serde(default = "default_sat_tol")]
+    pub sat_tol: f32,
+    /// 亮度容差 (0.0 ~ 1.0)
+    #[serde(default = "default_val_tol")]
+    pub val_tol: f32,
+}
+
+pub fn default_hsv_size() -> u32 { 5 }
+pub fn default_hue_tol() -> f32 { 10.0 }
+pub fn default_sat_tol() -> f32 { 0.15 }
+pub fn default_val_tol() -> f32 { 0.15 }
+
+/// 饱和度或亮度低于此值时色相没有意义 (灰、黑)，不比较色相
+const HUE_MIN_CHROMA: f32 = 0.1;
+
+impl HsvAnchor {
+    /// 采样区域 [x1, y1, x2, y2] (右下角不含)
+    pub fn sample_rect(&self) -> [i32; 4] {
+        let size = self.size.max(1) as i32;
+        let (x, y) = (self.pos[0] - size / 2, self.pos[1] - size / 2);
+        [x, y, x + size, y + size]
+    }
+
+    /// 解析 val；格式错误时为 None
+    pub fn expected_rgb(&self) -> Option<[u8; 3]> {
+        parse_hex_rgb(&self.val)
+    }
+
+    /// 平均色与期望色在各分量上的差值与容差之比的最大值：<= 1.0 为命中；val 无效时为无穷大
+    pub fn deviation(&self, avg: [u8; 3]) -> f32 {
+        let Some(expected) = self.expected_rgb() else { return f32::INFINITY };
+        let [h1, s1, v1] = rgb_to_hsv(expected);
+        let [h2, s2, v2] = rgb_to_hsv(avg);
+        let ratio = |diff: f32, tol: f32| if tol > 0.0 { diff / tol } else if diff > 0.0 { f32::INFINITY } else { 0.0 };
+        let mut worst = ratio((s1 - s2).abs(), self.sat_tol).max(ratio((v1 - v2).abs(), self.val_tol));
+        if s1.min(v1) >= HUE_MIN_CHROMA && s2.min(v2) >= HUE_MIN_CHROMA {
+            let dh = (h1 - h2).abs();
+            worst = worst.max(ratio(dh.min(360.0 - dh), self.hue_tol));
+        }
+        worst
+    }
+}
+
+/// "#RRGGBB" -> [r, g, b]
+pub fn parse_hex_rgb(hex: &str) -> Option<[u8; 3]> {
+    let hex = hex.trim().trim_start_matches('#');
+    if hex.len() != 6 || !hex.is_ascii() {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+    Some([channel(0)?, channel(2)?, channel(4)?])
+}
+
+/// RGB -> [色相 (0 ~ 360 度), 饱和度 (0 ~ 1), 亮度 (0 ~ 1)]
+pub fn rgb_to_hsv(rgb: [u8; 3]) -> [f32; 3] {
+    let [r, g, b] = rgb.map(|c| c as f32 / 255.0);
+    let max = r.max(g).max(b);
+    let delta = max - r.min(g).min(b);
+    let hue = if delta == 0.0 {
+        0.0
+    } else if max == r {
+        60.0 * ((g - b) / delta).rem_euclid(6.0)
+    } else if max == g {
+        60.0 * ((b - r) / delta + 2.0)
+    } else {
+        60.0 * ((r - g) / delta + 4.0)
+    };
+    let saturation = if max == 0.0 { 0.0 } else { delta / max };
+    [hue, saturation, max]
+}
+
+/// 图像模板锚点：在 rect 区域内做归一化互相关匹配，适合艺术字与图标
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ImageAnchor {
+    pub rect: [i32; 4],
+    /// 模板 PNG，相对地图文件所在目录
+    pub file: String,
+    #[serde(default = "default_image_threshold")]
+    pub threshold: f32,
+}
+
+pub fn default_image_threshold() -> f32 { 0.9 }
+
+// ==========================================
+// 3. 跳转
+// ==========================================
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Transition {
+    /// 全局弹窗的关闭跳转可不填 target
+    #[serde(default)]
+    pub target: String,
+    /// 点击坐标 (click)；scroll 时为滚动前鼠标停留的位置
+    #[serde(default)]
+    pub coords: [i32; 2],
+    /// 跳转动作，缺省为 click
+    #[serde(default, skip_serializing_if = "is_click")]
+    pub action: TransitionAction,
+    /// action = "key" 时的按键名 (如 "esc")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>,
+    /// action = "drag" 时的起止坐标
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub from: Option<[i32; 2]>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub to: Option<[i32; 2]>,
+    /// action = "scroll" 时的滚轮格数 (notch，正数向上，负数向下；不是 Windows 的 120 单位)，
+    /// 每格约 0.12 秒，绝对值不超过 MAX_SCROLL_DELTA
+    #[serde(default, skip_serializing_if = "is_zero_i32")]
+    pub delta: i32,
+    #[serde(default = "default_delay")]
+    pub post_delay: u64,
+    /// 可选：执行动作前的等待 (ms)，用于按钮出现前的入场动画
+    #[serde(default, skip_serializing_if = "is_zero_u64")]
+    pub pre_delay: u64,
+    /// 可选：等待目标场景超时后重新执行动作的次数 (仍停在原场景时才重按)，用尽后才走 on_fail
+    #[serde(default, skip_serializing_if = "is_zero_u32")]
+    pub retries: u32,
+    /// 可选：路径规划代价。未填写时按 "一次点击 + post_delay" 估算
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cost: Option<u64>,
+    /// 可选：等待目标场景出现的超时 (ms)，缺省为 max(post_delay, 2000)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_ms: Option<u64>,
+    /// 可选：超时后的恢复动作
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub on_fail: Option<RecoveryAction>,
+    /// 可选：OCR 数值条件，不满足时规划路线不走这条边
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub condition: Option<TransitionCondition>,
+}
+
+impl Transition {
+    /// 默认参数的点击跳转
+    pub fn click(target: String, coords: [i32; 2]) -> Self {
+        Self {
+            target,
+            coords,
+            action: TransitionAction::Click,
+            key: None,
+            from: None,
+            to: None,
+            delta: 0,
+            post_delay: default_delay(),
+            pre_delay: 0,
+            retries: 0,
+            cost: None,
+            timeout_ms: None,
+            on_fail: None,
+            condition: None,
+        }
+    }
+
+    /// 路径规划代价
+    pub fn weight(&self) -> u64 {
+        self.cost.unwrap_or(CLICK_COST + self.post_delay)
+    }
+
+    /// 等待目标场景出现的超时 (ms)
+    pub fn timeout(&self) -> u64 {
+        self.timeout_ms.unwrap_or(if self.post_delay < 2000 { 2000 } else { self.post_delay })
+    }
+}
+
+/// 跳转条件：OCR rect 区域，用 regex (取第一个捕获组，否则整个匹配) 提取数值，再与 value 比较
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TransitionCondition {
+    pub rect: [i32; 4],
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub regex: Option<String>,
+    /// 比较运算符: ">=" ">" "<=" "<" "==" "!="
+    #[serde(default = "default_condition_op")]
+    pub op: String,
+    pub value: i64,
+}
+
+fn default_condition_op() -> String { "==".to_string() }
+
+impl TransitionCondition {
+    /// 支持的比较运算符
+    pub const OPS: [&'static str; 6] = [">=", ">", "<=", "<", "==", "!="];
+
+    /// 读到的数值与 value 比较；op 不是支持的运算符时为 None
+    pub fn compare(&self, n: i64) -> Option<bool> {
+        Some(match self.op.as_str() {
+            ">=" => n >= self.value,
+            ">" => n > self.value,
+            "<=" => n <= self.value,
+            "<" => n < self.value,
+            "==" => n == self.value,
+            "!=" => n != self.value,
+            _ => return None,
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum TransitionAction {
+    #[default]
+    Click,
+    Key,
+    Drag,
+    Scroll,
+}
+
+impl TransitionAction {
+    pub const ALL: [TransitionAction; 4] = [TransitionAction::Click, TransitionAction::Key, TransitionAction::Drag, TransitionAction::Scroll];
+
+    /// TOML 中的写法
+    pub fn as_str(self) -> &'static str {
+        match self {
+            TransitionAction::Click => "click",
+            TransitionAction::Key => "key",
+            TransitionAction::Drag => "drag",
+            TransitionAction::Scroll => "scroll",
+        }
+    }
+
+    /// 按名称解析 (不区分大小写)
+    pub fn parse(s: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|a| a.as_str().eq_ignore_ascii_case(s))
+    }
+}
+
+/// 跳转超时后的恢复动作
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "type")]
+pub enum RecoveryAction {
+    /// 按键 (如 "esc") 后重新等待目标场景
+    Key { key: String },
+    /// 点击备用坐标后重新等待目标场景
+    Click { x: i32, y: i32 },
+    /// 从当前界面重新识别并规划整条路线 (每次导航最多一次)
+    Renavigate,
+}
+
+pub fn default_delay() -> u64 { 500 }
+
+fn is_false(v: &bool) -> bool { !*v }
+fn is_click(a: &TransitionAction) -> bool { *a == TransitionAction::Click }
+fn is_zero_i32(v: &i32) -> bool { *v == 0 }
+fn is_zero_u32(v: &u32) -> bool { *v == 0 }
+fn is_zero_u64(v: &u64) -> bool { *v == 0 }
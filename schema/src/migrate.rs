@@ -0,0 +1,216 @@
+// nzm_schema/src/migrate.rs
+//
+// 旧格式在反序列化之前按 toml::Value 转换为当前格式，主程序与 UI_tool 读到的都是同一份结构。
+//
+// 地图版本：
+//   v0  旧版 tool/ 生成：anchors 直接是 [{ rect, text }] 数组，跳转用 trigger_btn (按钮区域或坐标) 代替 coords
+//   v1  当前格式：[scenes.anchors] 表 (text / color / image)，[[scenes.transitions]] 用 coords + action
+use toml::Value;
+
+use crate::map::{TransitionAction, MAP_SCHEMA_VERSION};
+use crate::strategy::STRATEGY_VERSION;
+
+/// 一次迁移的记录：原版本，以及无法原样保留的内容
+#[derive(Debug, Clone)]
+pub struct Migration {
+    pub from: u32,
+    pub notes: Vec<String>,
+}
+
+/// 地图文件版本：优先取 schema_version，否则按结构判断
+pub fn map_version(root: &Value) -> u32 {
+    if let Some(v) = root.get("schema_version").and_then(|v| v.as_integer()) {
+        return v.max(0) as u32;
+    }
+    let scenes = root.get("scenes").and_then(|s| s.as_array()).map(Vec::as_slice).unwrap_or_default();
+    let legacy = scenes.iter().any(|s| {
+        s.get("anchors").is_some_and(|a| a.is_array())
+            || s.get("transitions").and_then(|t| t.as_array()).is_some_and(|ts| ts.iter().any(|t| t.get("trigger_btn").is_some()))
+    });
+    if legacy { 0 } else { MAP_SCHEMA_VERSION }
+}
+
+/// 把地图升级到当前版本；已是当前版本时返回 None，版本高于程序支持时报错
+pub fn migrate_map(root: &mut Value) -> Result<Option<Migration>, String> {
+    let from = map_version(root);
+    if from > MAP_SCHEMA_VERSION {
+        return Err(format!("地图格式版本 {} 高于程序支持的版本 {}，请更新程序", from, MAP_SCHEMA_VERSION));
+    }
+    if from == MAP_SCHEMA_VERSION {
+        return Ok(None);
+    }
+    let mut notes = Vec::new();
+    if let Some(scenes) = root.get_mut("scenes").and_then(|s| s.as_array_mut()) {
+        for scene in scenes.iter_mut().filter_map(|s| s.as_table_mut()) {
+            legacy_scene(scene, &mut notes);
+        }
+    }
+    if let Some(table) = root.as_table_mut() {
+        table.insert("schema_version".into(), Value::Integer(MAP_SCHEMA_VERSION as i64));
+    }
+    Ok(Some(Migration { from, notes }))
+}
+
+/// 取 TOML 数组中的整数 (兼容浮点)
+fn int_list(v: Option<&Value>) -> Option<Vec<i64>> {
+    v?.as_array()?.iter().map(|x| x.as_integer().or_else(|| x.as_float().map(|f| f as i64))).collect()
+}
+
+fn int_array(vals: &[i64]) -> Value {
+    Value::Array(vals.iter().map(|v| Value::Integer(*v)).collect())
+}
+
+/// v0 -> v1：单个场景
+fn legacy_scene(scene: &mut toml::Table, notes: &mut Vec<String>) {
+    let id = scene.get("id").and_then(|v| v.as_str()).unwrap_or_default().to_string();
+    if !scene.contains_key("name") {
+        scene.insert("name".into(), Value::String(id.clone()));
+    }
+
+    // 旧锚点：{ rect, text } 为文字锚点；带 pos 的按颜色锚点处理
+    if let Some(Value::Array(old)) = scene.get("anchors").cloned() {
+        let (mut text, mut color) = (Vec::new(), Vec::new());
+        for a in &old {
+            let val = a.get("text").or(a.get("val")).and_then(|v| v.as_str()).unwrap_or_default().to_string();
+            let mut anchor = toml::Table::new();
+            if let Some(pos) = int_list(a.get("pos")).filter(|p| p.len() == 2) {
+                anchor.insert("pos".into(), int_array(&pos));
+                anchor.insert("val".into(), Value::String(val));
+                anchor.insert("tol".into(), Value::Integer(a.get("tol").and_then(|v| v.as_integer()).unwrap_or(15).clamp(0, 255)));
+                color.push(Value::Table(anchor));
+            } else if let Some(r) = int_list(a.get("rect")).filter(|r| r.len() == 4) {
+                anchor.insert("rect".into(), int_array(&r));
+                anchor.insert("val".into(), Value::String(val));
+                text.push(Value::Table(anchor));
+            } else {
+                notes.push(format!("[{}] 无法识别的锚点已跳过", id));
+            }
+        }
+        let mut anchors = toml::Table::new();
+        if !text.is_empty() {
+            anchors.insert("text".into(), Value::Array(text));
+        }
+        if !color.is_empty() {
+            anchors.insert("color".into(), Value::Array(color));
+        }
+        scene.insert("anchors".into(), Value::Table(anchors));
+    }
+
+    // 旧跳转：trigger_btn 为按钮区域 [x1, y1, x2, y2] (取中心) 或坐标 [x, y]
+    let Some(Value::Array(old)) = scene.get("transitions").cloned() else { return };
+    let mut transitions = Vec::new();
+    for t in old {
+        let Value::Table(mut t) = t else { continue };
+        let target = t.get("target").and_then(|v| v.as_str()).unwrap_or_default().to_string();
+        let point = match int_list(t.get("trigger_btn").or(t.get("coords"))).as_deref() {
+            Some(&[x, y]) => [x, y],
+            Some(&[x1, y1, x2, y2]) => [(x1 + x2) / 2, (y1 + y2) / 2],
+            _ => {
+                notes.push(format!("[{}] → {} 缺少 trigger_btn，已跳过", id, target));
+                continue;
+            }
+        };
+        t.remove("trigger_btn");
+        t.insert("coords".into(), int_array(&point));
+        if let Some(delay) = t.remove("delay") {
+            t.entry("post_delay").or_insert(delay);
+        }
+        if let Some(action) = t.get("action").and_then(|v| v.as_str()).map(String::from) {
+            match TransitionAction::parse(&action) {
+                Some(a) => { t.insert("action".into(), Value::String(a.as_str().into())); }
+                None => {
+                    notes.push(format!("[{}] → {} 的 action = \"{}\" 无法识别，已按点击导入", id, target, action));
+                    t.remove("action");
+                }
+            }
+        }
+        transitions.push(Value::Table(t));
+    }
+    scene.insert("transitions".into(), Value::Array(transitions));
+}
+
+/// 策略文件版本检查：旧版本新增字段均有默认值，可直接读取；高于程序支持的版本时报错
+pub fn check_strategy_version(version: u32) -> Result<(), String> {
+    if version > STRATEGY_VERSION {
+        return Err(format!("策略格式版本 {} 高于程序支持的版本 {}，请更新程序", version, STRATEGY_VERSION));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn migrate(src: &str) -> (Value, Option<Migration>) {
+        let mut root: Value = toml::from_str(src).unwrap();
+        let migration = migrate_map(&mut root).unwrap();
+        (root, migration)
+    }
+
+    fn transition(root: &Value, i: usize) -> &toml::Table {
+        root["scenes"][0]["transitions"][i].as_table().unwrap()
+    }
+
+    const LEGACY: &str = r##"
+        [[scenes]]
+        id = "大厅"
+        anchors = [{ rect = [10, 10, 110, 50], text = "开始" }, { pos = [5, 5], val = "#FF0000" }]
+        transitions = [
+            { target = "副本", trigger_btn = [100, 200, 300, 400], delay = 800 },
+            { target = "设置", trigger_btn = [50, 60] },
+            { target = "商店", trigger_btn = [1, 2], action = "swipe" },
+            { target = "返回", trigger_btn = [1, 2], action = "KEY", key = "esc" },
+        ]
+    "##;
+
+    #[test]
+    fn v0_trigger_btn_becomes_coords() {
+        let (root, migration) = migrate(LEGACY);
+        assert_eq!(migration.unwrap().from, 0);
+        assert_eq!(root["schema_version"].as_integer(), Some(MAP_SCHEMA_VERSION as i64));
+        // 区域取中心，坐标原样保留
+        assert_eq!(int_list(transition(&root, 0).get("coords")), Some(vec![200, 300]));
+        assert_eq!(int_list(transition(&root, 1).get("coords")), Some(vec![50, 60]));
+        assert!(transition(&root, 0).get("trigger_btn").is_none());
+    }
+
+    #[test]
+    fn v0_delay_becomes_post_delay() {
+        let (root, _) = migrate(LEGACY);
+        let t = transition(&root, 0);
+        assert_eq!(t.get("post_delay").and_then(|v| v.as_integer()), Some(800));
+        assert!(t.get("delay").is_none());
+    }
+
+    #[test]
+    fn v0_unknown_action_falls_back_to_click_with_note() {
+        let (root, migration) = migrate(LEGACY);
+        assert!(transition(&root, 2).get("action").is_none());
+        assert_eq!(transition(&root, 3).get("action").and_then(|v| v.as_str()), Some("key"));
+        let notes = migration.unwrap().notes;
+        assert_eq!(notes.len(), 1);
+        assert!(notes[0].contains("swipe"));
+    }
+
+    #[test]
+    fn v0_anchor_array_becomes_table() {
+        let (root, _) = migrate(LEGACY);
+        let anchors = &root["scenes"][0]["anchors"];
+        assert_eq!(anchors["text"][0]["val"].as_str(), Some("开始"));
+        assert_eq!(anchors["color"][0]["tol"].as_integer(), Some(15));
+        let map: crate::map::MapFile = root.clone().try_into().unwrap();
+        assert_eq!(map.scenes[0].transitions.as_ref().map(Vec::len), Some(4));
+    }
+
+    #[test]
+    fn current_version_is_untouched() {
+        let (_, migration) = migrate("schema_version = 1\n[[scenes]]\nid = \"大厅\"\n");
+        assert!(migration.is_none());
+    }
+
+    #[test]
+    fn future_version_is_rejected() {
+        let mut root: Value = toml::from_str(&format!("schema_version = {}\n", MAP_SCHEMA_VERSION + 1)).unwrap();
+        assert!(migrate_map(&mut root).is_err());
+    }
+}
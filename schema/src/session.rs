@@ -0,0 +1,94 @@
+// nzm_schema/src/session.rs
+//
+// 会话录像 (--session)：一次运行一个目录 sessions/<开始时间>/，
+//   events.jsonl   每行一个事件 {"t_ms": 相对会话开始的毫秒数, "type": ..., ...}
+//   frames/*.jpg   定时保存的缩小截图，文件名为 t_ms (9 位补零)
+// 主程序写入，UI_tool 的「会话回放」与 session 子命令读取。
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+pub const EVENTS_FILE: &str = "events.jsonl";
+pub const FRAMES_DIR: &str = "frames";
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SessionEvent {
+    pub t_ms: u64,
+    #[serde(flatten)]
+    pub kind: SessionEventKind,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SessionEventKind {
+    /// 会话开始：本地时间与命令行
+    Start { started_at: String, command: String },
+    /// 一帧缩小截图 (相对会话目录)；scale 为相对屏幕的缩放比例
+    Frame { file: String, scale: f32 },
+    /// 下发的键鼠命令
+    Input { cmd: String },
+    Scene { scene: String, confidence: f32 },
+    Transition { from: String, to: String, coords: [i32; 2] },
+    Ocr { rect: [i32; 4], text: String, elapsed_ms: u64 },
+    Popup { popup: String },
+    Navigation { target: String, result: String, elapsed_ms: u64 },
+    /// 会话结束 (进程被强制结束时没有这一行)
+    End { stopped: bool },
+}
+
+impl SessionEventKind {
+    /// 事件类别 (回放界面的筛选与图例)
+    pub fn label(&self) -> &'static str {
+        match self {
+            SessionEventKind::Start { .. } => "开始",
+            SessionEventKind::Frame { .. } => "截图",
+            SessionEventKind::Input { .. } => "输入",
+            SessionEventKind::Scene { .. } => "场景",
+            SessionEventKind::Transition { .. } => "跳转",
+            SessionEventKind::Ocr { .. } => "OCR",
+            SessionEventKind::Popup { .. } => "弹窗",
+            SessionEventKind::Navigation { .. } => "导航",
+            SessionEventKind::End { .. } => "结束",
+        }
+    }
+}
+
+impl std::fmt::Display for SessionEventKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SessionEventKind::Start { started_at, command } => write!(f, "{} {}", started_at, command),
+            SessionEventKind::Frame { file, .. } => write!(f, "{}", file),
+            SessionEventKind::Input { cmd } => write!(f, "{}", cmd),
+            SessionEventKind::Scene { scene, confidence } => write!(f, "进入 [{}] ({:.2})", scene, confidence),
+            SessionEventKind::Transition { from, to, coords } => write!(f, "[{}] -> [{}] @ ({}, {})", from, to, coords[0], coords[1]),
+            SessionEventKind::Ocr { rect, text, elapsed_ms } => write!(f, "{:?} \"{}\" ({}ms)", rect, text, elapsed_ms),
+            SessionEventKind::Popup { popup } => write!(f, "关闭 [{}]", popup),
+            SessionEventKind::Navigation { target, result, elapsed_ms } => write!(f, "-> [{}] {} ({}ms)", target, result, elapsed_ms),
+            SessionEventKind::End { stopped } => write!(f, "{}", if *stopped { "已停止" } else { "正常结束" }),
+        }
+    }
+}
+
+/// 读取会话目录的事件；无法解析的行 (如被强制结束时写了一半) 跳过，返回 (事件, 跳过行数)
+pub fn load_events(dir: &Path) -> Result<(Vec<SessionEvent>, usize), String> {
+    let path = dir.join(EVENTS_FILE);
+    let file = File::open(&path).map_err(|e| format!("无法打开 {}: {}", path.display(), e))?;
+    let (mut events, mut skipped) = (Vec::new(), 0);
+    for line in BufReader::new(file).lines() {
+        let line = line.map_err(|e| format!("读取 {} 失败: {}", path.display(), e))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str(&line) {
+            Ok(e) => events.push(e),
+            Err(_) => skipped += 1,
+        }
+    }
+    Ok((events, skipped))
+}
+
+/// 截图文件名
+pub fn frame_file(t_ms: u64) -> String {
+    format!("{}/{:09}.jpg", FRAMES_DIR, t_ms)
+}
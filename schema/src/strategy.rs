@@ -0,0 +1,216 @@
+// nzm_schema/src/strategy.rs
+use serde::{Deserialize, Serialize};
+
+/// 当前策略格式版本：v2 增加 priority / only_if / notes (见 migrate::check_strategy_version)
+pub const STRATEGY_VERSION: u32 = 2;
+
+// ==========================================
+// 1. 地形 (地图尺寸、预备动作、界面参数覆盖)
+// ==========================================
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MapTerrainExport {
+    pub map_name: String,
+    pub meta: MapMeta,
+    /// 随地图携带的界面参数与镜头速度，加载地形后覆盖 td_config.toml 的值
+    #[serde(default)]
+    pub overrides: Option<TDConfigOverride>,
+}
+
+// ✨ 修改：MapMeta 增加 prep_actions
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MapMeta {
+    pub grid_pixel_size: f32,
+    pub offset_x: f32,
+    pub offset_y: f32,
+    pub bottom: f32,
+    /// 地图右边界 (像素)；不超过屏幕宽度时不做横向平移
+    #[serde(default)]
+    pub right: f32,
+    #[serde(default)]
+    pub prep_actions: Vec<PrepAction>,
+}
+
+// ✨ 新增：预备阶段动作定义 (用于 MapMeta)
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "type")]
+pub enum PrepAction {
+    KeyDown { key: char },
+    KeyUpAll,
+    Wait { ms: u64 },
+    Log { msg: String },
+}
+
+/// 单张地图的覆盖项 (td_config.toml 的 [maps."地图名"] 或地形 JSON 的 overrides)，未写的字段沿用默认值
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct TDConfigOverride {
+    pub hud_check_rect: Option<[i32; 4]>,
+    pub hud_wave_loop_rect: Option<[i32; 4]>,
+    pub safe_zone: Option<[i32; 4]>,
+    pub screen_width: Option<f32>,
+    pub screen_height: Option<f32>,
+    pub digit_template_dir: Option<String>,
+    pub wave_detector: Option<WaveDetector>,
+    pub hud_wave_digits_rect: Option<[i32; 4]>,
+    pub gold_rect: Option<[i32; 4]>,
+    pub wave_timer_rect: Option<[i32; 4]>,
+    pub base_hp_rect: Option<[i32; 4]>,
+    pub move_speed: Option<f32>,
+    pub camera_mode: Option<CameraMode>,
+    pub drag_ratio: Option<f32>,
+}
+
+/// 战斗中识别波次的方式
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum WaveDetector {
+    /// 按住 TAB 打开计分板后 OCR (默认)
+    #[default]
+    TabOcr,
+    /// 直接在 HUD 上用数字模板匹配，不按 TAB；匹配失败时退回 HUD OCR
+    Template,
+}
+
+/// 镜头平移方式
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum CameraMode {
+    /// 按住 WASD (默认)：先贴边再按校准速度平移
+    #[default]
+    KeyHold,
+    /// 按住鼠标键拖动地图 (禁用 WASD 平移的地图)
+    Drag,
+}
+
+// ==========================================
+// 2. 陷阱配置
+// ==========================================
+// ✨ 修改：TrapConfigItem 增加 b_type 和 grid_index
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TrapConfigItem {
+    pub name: String,
+    #[serde(default)]
+    pub b_type: String, // "Floor", "Wall", "Ceiling"
+    #[serde(default)]
+    pub grid_index: [i32; 2], // [col, row]
+    /// 建造价格 (金币)；0 表示不检查
+    #[serde(default)]
+    pub cost: u32,
+    /// 出售返还金币；0 表示按 cost * sell_refund_ratio 估算
+    #[serde(default)]
+    pub refund: u32,
+    /// 可选：固定快捷键与装备栏页号；不填时按装备顺序依次分配 hotbar_keys，每页排满后换下一页
+    #[serde(default)]
+    pub key: Option<char>,
+    #[serde(default)]
+    pub page: Option<usize>,
+}
+
+// ==========================================
+// 3. 策略 (建造 / 升级 / 拆除 / 技能)
+// ==========================================
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MapBuildingsExport {
+    /// 策略格式版本；旧文件没有此字段 (视为 1)，v2 增加 priority / only_if / notes
+    #[serde(default = "default_strategy_version")]
+    pub version: u32,
+    #[serde(default)]
+    pub notes: Option<String>,
+    pub map_name: String,
+    pub buildings: Vec<BuildingExport>,
+    #[serde(default)]
+    pub upgrades: Vec<UpgradeEvent>,
+    #[serde(default)]
+    pub demolishes: Vec<DemolishEvent>,
+    #[serde(default)]
+    pub abilities: Vec<AbilityEvent>,
+    #[serde(default)]
+    pub emergency: EmergencyPlan,
+}
+
+fn default_strategy_version() -> u32 {
+    1
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BuildingExport {
+    pub uid: usize,
+    pub name: String,
+    pub grid_x: usize,
+    pub grid_y: usize,
+    pub width: usize,
+    pub height: usize,
+    #[serde(default)]
+    pub wave_num: i32,
+    #[serde(default)]
+    pub is_late: bool,
+    /// 备选格子 [grid_x, grid_y]：原位置放置失败 (被占用等) 时依次尝试
+    #[serde(default)]
+    pub alternates: Vec<[usize; 2]>,
+    /// 金币不足时允许卖掉它来凑钱 (需开启 sell_to_afford)
+    #[serde(default)]
+    pub sellable: bool,
+    /// 同一阶段内优先级高的先建 (默认 0)
+    #[serde(default)]
+    pub priority: i32,
+    /// 建造条件，如 "wave >= 10 && resource >= 500"；不满足时顺延到之后的阶段
+    #[serde(default)]
+    pub only_if: Option<String>,
+    /// 备注，程序不读取
+    #[serde(default)]
+    pub notes: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct UpgradeEvent {
+    pub building_name: String,
+    pub wave_num: i32,
+    pub is_late: bool,
+    /// 指定升级哪一座 (建造的 uid)：先移镜头点击选中该塔再长按；为空时沿用旧行为，升级当前选中的塔
+    #[serde(default)]
+    pub uid: Option<usize>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DemolishEvent {
+    pub uid: usize,
+    pub name: String,
+    pub grid_x: usize,
+    pub grid_y: usize,
+    pub width: usize,
+    pub height: usize,
+    pub wave_num: i32,
+    pub is_late: bool,
+}
+
+/// 英雄技能 / 道具：在指定波次阶段按下 key；填写 target 时按键后再点击该屏幕坐标 (需指定目标的技能)
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AbilityEvent {
+    pub key: char,
+    /// 应急技能 (emergency.abilities) 不需要填写
+    #[serde(default)]
+    pub wave_num: i32,
+    #[serde(default)]
+    pub is_late: bool,
+    #[serde(default)]
+    pub target: Option<[i32; 2]>,
+    #[serde(default)]
+    pub notes: Option<String>,
+}
+
+/// 基地血量过低时的应急预案
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct EmergencyPlan {
+    /// 后备建造，uid 不能与 buildings 重复
+    #[serde(default)]
+    pub buildings: Vec<BuildingExport>,
+    /// 应急技能，每次触发都会释放
+    #[serde(default)]
+    pub abilities: Vec<AbilityEvent>,
+}
+
+impl EmergencyPlan {
+    pub fn is_empty(&self) -> bool {
+        self.buildings.is_empty() && self.abilities.is_empty()
+    }
+}
@@ -0,0 +1,207 @@
+// src/api.rs
+use crate::control::RunControl;
+use serde::Deserialize;
+use serde_json::json;
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::thread;
+use std::time::Duration;
+use tracing::{info, warn};
+
+// ==========================================
+// 本地控制接口 (HTTP)
+// ==========================================
+// 主程序内置的极简 HTTP 服务，用手机面板查看状态、远程暂停 / 停止，不必再远程桌面。
+// 响应均为 JSON；必须配置 token，请求需带 ?token=<token> 或 Authorization: Bearer <token>。
+// 响应允许任意来源跨域读取 (手机面板)，所以不设 token 时拒绝启动：否则浏览器里打开的任何网页都能控制本程序。
+//   GET  /status                     目标、场景、波次、已放置的建造、暂停 / 停止状态
+//   POST /pause | /resume | /stop
+//   POST /navigate?target=<场景 ID>   下一轮改为导航到该目标 (也可把目标写在请求体中)
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct ApiConfig {
+    pub enabled: bool,
+    /// 监听地址；需要手机访问时改为 0.0.0.0:8765 并设置 token
+    pub bind: String,
+    /// 访问口令；为空时不启动接口
+    pub token: String,
+}
+
+impl Default for ApiConfig {
+    fn default() -> Self {
+        Self { enabled: false, bind: "127.0.0.1:8765".to_string(), token: String::new() }
+    }
+}
+
+/// 请求体上限 (只接收目标名这类短文本)
+const MAX_BODY: usize = 4096;
+
+pub(crate) struct Request {
+    pub(crate) method: String,
+    pub(crate) path: String,
+    query: HashMap<String, String>,
+    auth: Option<String>,
+    body: String,
+}
+
+/// 在后台线程启动控制接口；未设置 token 或监听失败时只打印警告
+pub fn spawn(config: &ApiConfig, control: RunControl) {
+    if config.token.is_empty() {
+        warn!("⚠️ [接口] 未设置 [api] token，控制接口不启动 (任何网页都能跨域调用无口令的接口)");
+        return;
+    }
+    let listener = match TcpListener::bind(&config.bind) {
+        Ok(l) => l,
+        Err(e) => {
+            warn!("⚠️ [接口] 无法监听 {}: {}", config.bind, e);
+            return;
+        }
+    };
+    info!("🌐 [接口] 控制接口: http://{}/status", config.bind);
+
+    let token = config.token.clone();
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let _ = stream.set_read_timeout(Some(Duration::from_secs(5)));
+            handle(stream, &token, &control);
+        }
+    });
+}
+
+fn handle(mut stream: TcpStream, token: &str, control: &RunControl) {
+    let Some(req) = read_request(&stream) else {
+        return;
+    };
+    let (status, body) = route(&req, token, control);
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: application/json; charset=utf-8\r\nContent-Length: {}\r\n\
+         Access-Control-Allow-Origin: *\r\nAccess-Control-Allow-Headers: Authorization\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    );
+    let _ = stream.write_all(response.as_bytes());
+}
+
+fn route(req: &Request, token: &str, control: &RunControl) -> (&'static str, String) {
+    // 浏览器带 Authorization 头时先发预检请求
+    if req.method == "OPTIONS" {
+        return ("204 No Content", String::new());
+    }
+    let bearer = format!("Bearer {}", token);
+    if req.query.get("token").map(String::as_str) != Some(token) && req.auth.as_deref() != Some(bearer.as_str()) {
+        return ("401 Unauthorized", json!({ "error": "unauthorized" }).to_string());
+    }
+
+    let command = match (req.method.as_str(), req.path.as_str()) {
+        ("GET", "/status") => "status",
+        ("POST", "/pause" | "/resume" | "/stop" | "/navigate") => &req.path[1..],
+        _ => return ("404 Not Found", json!({ "error": "not found" }).to_string()),
+    };
+    let target = req.query.get("target").cloned().unwrap_or_else(|| req.body.trim().to_string());
+    match execute(command, &target, control) {
+        Ok(v) => ("200 OK", v.to_string()),
+        Err(e) => ("400 Bad Request", json!({ "error": e }).to_string()),
+    }
+}
+
+/// 执行一条控制命令 (HTTP 与命名管道共用)：status / pause / resume / stop / navigate <目标>
+pub fn execute(command: &str, arg: &str, control: &RunControl) -> Result<serde_json::Value, String> {
+    let ok = json!({ "ok": true });
+    match command {
+        "status" => serde_json::to_value(control.status()).map_err(|e| e.to_string()),
+        "pause" => {
+            control.set_paused(true);
+            info!("⏸️ [接口] 已暂停");
+            Ok(ok)
+        }
+        "resume" => {
+            control.set_paused(false);
+            info!("▶️ [接口] 继续运行");
+            Ok(ok)
+        }
+        "stop" => {
+            warn!("🛑 [接口] 收到停止请求，当前动作完成后退出");
+            control.stop();
+            Ok(ok)
+        }
+        "navigate" => {
+            if arg.is_empty() {
+                return Err("缺少 target".to_string());
+            }
+            info!("🌐 [接口] 下一轮导航目标: {}", arg);
+            control.request_target(arg.to_string());
+            Ok(ok)
+        }
+        other => Err(format!("未知命令: {}", other)),
+    }
+}
+
+/// 读取一个请求 (指标接口共用)
+pub(crate) fn read_request(stream: &TcpStream) -> Option<Request> {
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+    reader.read_line(&mut line).ok()?;
+    let mut parts = line.split_whitespace();
+    let method = parts.next()?.to_string();
+    let uri = parts.next()?;
+    let (path, query) = match uri.split_once('?') {
+        Some((p, q)) => (p.to_string(), parse_query(q)),
+        None => (uri.to_string(), HashMap::new()),
+    };
+
+    let mut auth = None;
+    let mut content_length = 0;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header).ok()? == 0 || header.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            match name.trim().to_ascii_lowercase().as_str() {
+                "authorization" => auth = Some(value.trim().to_string()),
+                "content-length" => content_length = value.trim().parse().unwrap_or(0),
+                _ => {}
+            }
+        }
+    }
+
+    let mut body = vec![0u8; content_length.min(MAX_BODY)];
+    reader.read_exact(&mut body).ok()?;
+    Some(Request { method, path, query, auth, body: String::from_utf8_lossy(&body).to_string() })
+}
+
+fn parse_query(query: &str) -> HashMap<String, String> {
+    query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .map(|(k, v)| (percent_decode(k), percent_decode(v)))
+        .collect()
+}
+
+/// 解码 URL 中的 %XX 与 + (目标名一般是中文)
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => out.push(b' '),
+            b'%' if i + 2 < bytes.len() => {
+                let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).ok().and_then(|h| u8::from_str_radix(h, 16).ok());
+                match hex {
+                    Some(b) => {
+                        out.push(b);
+                        i += 2;
+                    }
+                    None => out.push(b'%'),
+                }
+            }
+            b => out.push(b),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).to_string()
+}
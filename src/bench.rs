@@ -0,0 +1,209 @@
+// src/bench.rs
+use crate::capture;
+use crate::hardware::InputDriver;
+use crate::ocr::OcrBackend;
+use crate::vision::{self, GrayTemplate, SearchImage};
+use image::{imageops, DynamicImage, RgbaImage};
+use std::time::{Duration, Instant};
+
+// ==========================================
+// 感知基准测试 (bench 子命令)
+// ==========================================
+// 逐项测量一次感知循环的开销：截图 (整屏 / 区域)、不同区域尺寸的 OCR、模板匹配吞吐、串口往返，
+// 每项重复 N 次后给出百分位，最后估算一次区域轮询 (截图 + OCR) 占 1 秒预算的比例。
+// 各项直接调用截图后端与 OCR 后端，不经过共享帧缓存，测到的是真实的单次开销。
+
+/// 每项开始前丢弃的次数 (DXGI 首帧要等待、OCR 首次调用要加载模型)
+const WARMUP: usize = 3;
+/// OCR 测试的区域尺寸 (宽, 高)，以屏幕中心为中心
+const OCR_SIZES: [(i32, i32); 4] = [(100, 30), (200, 60), (400, 120), (800, 240)];
+/// 轮询预算估算使用的 OCR 区域 (与波次 / 金币区域的量级相当)
+const POLL_OCR_SIZE: (i32, i32) = (200, 60);
+/// 模板匹配：模板边长与搜索区域 (宽, 高)
+const MATCH_TEMPLATE: u32 = 48;
+const MATCH_REGION: (u32, u32) = (320, 180);
+
+pub struct BenchOptions {
+    pub iterations: usize,
+    pub capture: bool,
+    pub ocr: bool,
+    pub matching: bool,
+    pub serial: bool,
+}
+
+/// 一组耗时的统计 (毫秒)
+#[derive(Debug, Clone, Copy)]
+pub struct Stats {
+    pub n: usize,
+    pub min: f64,
+    pub mean: f64,
+    pub p50: f64,
+    pub p90: f64,
+    pub p99: f64,
+    pub max: f64,
+}
+
+impl Stats {
+    pub fn from_samples(samples: &[Duration]) -> Option<Self> {
+        if samples.is_empty() {
+            return None;
+        }
+        let mut ms: Vec<f64> = samples.iter().map(|d| d.as_secs_f64() * 1000.0).collect();
+        ms.sort_by(|a, b| a.total_cmp(b));
+        // 最近秩法
+        let pct = |p: f64| ms[((p * ms.len() as f64).ceil() as usize).clamp(1, ms.len()) - 1];
+        Some(Self {
+            n: ms.len(),
+            min: ms[0],
+            mean: ms.iter().sum::<f64>() / ms.len() as f64,
+            p50: pct(0.50),
+            p90: pct(0.90),
+            p99: pct(0.99),
+            max: ms[ms.len() - 1],
+        })
+    }
+}
+
+/// 预热后重复执行 f，只统计成功 (返回 true) 的次数
+fn measure(iterations: usize, mut f: impl FnMut() -> bool) -> (Option<Stats>, usize) {
+    for _ in 0..WARMUP {
+        f();
+    }
+    let mut samples = Vec::with_capacity(iterations);
+    let mut failed = 0;
+    for _ in 0..iterations {
+        let started = Instant::now();
+        if f() {
+            samples.push(started.elapsed());
+        } else {
+            failed += 1;
+        }
+    }
+    (Stats::from_samples(&samples), failed)
+}
+
+fn print_header() {
+    println!("  {:<22} {:>5} {:>8} {:>8} {:>8} {:>8} {:>8} {:>8}", "项目", "次数", "min", "mean", "p50", "p90", "p99", "max");
+}
+
+fn print_row(name: &str, result: &(Option<Stats>, usize)) {
+    match result {
+        (Some(s), failed) => {
+            let failed = if *failed > 0 { format!("  (失败 {} 次)", failed) } else { String::new() };
+            println!(
+                "  {:<22} {:>5} {:>8.2} {:>8.2} {:>8.2} {:>8.2} {:>8.2} {:>8.2}{}",
+                name, s.n, s.min, s.mean, s.p50, s.p90, s.p99, s.max, failed
+            );
+        }
+        (None, failed) => println!("  {:<22} 全部失败 ({} 次)", name, failed),
+    }
+}
+
+/// 以 (cx, cy) 为中心、w x h 的区域
+fn centered(center: (i32, i32), w: i32, h: i32) -> [i32; 4] {
+    [center.0 - w / 2, center.1 - h / 2, center.0 + w / 2, center.1 + h / 2]
+}
+
+/// 运行基准测试并打印报告。driver 为未经输入队列包装的原始驱动 (串口往返需要直接测量)
+pub fn run(opts: &BenchOptions, driver: &mut dyn InputDriver, ocr: &dyn OcrBackend) {
+    let n = opts.iterations.max(1);
+    let backend = capture::backend();
+    let Some(screen) = backend.capture_screen() else {
+        println!("❌ 截图失败 ({})，无法测试", backend.name());
+        return;
+    };
+    let center = (screen.width() as i32 / 2, screen.height() as i32 / 2);
+    println!("⏱️ 感知基准测试：每项 {} 次 (另预热 {} 次)，单位 ms", n, WARMUP);
+    println!("   屏幕 {}x{}，截图后端 {}，OCR 后端 {}", screen.width(), screen.height(), backend.name(), ocr.name());
+
+    let mut rect_capture = None;
+    if opts.capture {
+        println!("\n📸 截图");
+        print_header();
+        print_row(&format!("整屏 ({})", backend.name()), &measure(n, || backend.capture_screen().is_some()));
+        let (w, h) = POLL_OCR_SIZE;
+        let rect = centered(center, w, h);
+        let result = measure(n, || backend.capture_rect(rect).is_some());
+        print_row(&format!("区域 {}x{}", w, h), &result);
+        rect_capture = result.0;
+    }
+
+    let mut poll_ocr = None;
+    if opts.ocr {
+        println!("\n🔤 OCR (已裁剪的图像，不含截图；导航识别开启多重曝光时每次约为此处的数倍)");
+        print_header();
+        for (w, h) in OCR_SIZES {
+            let rect = centered(center, w, h);
+            let Some(crop) = crate::frames::crop(&screen, rect) else { continue };
+            let image = DynamicImage::ImageRgba8(crop);
+            let result = measure(n, || {
+                ocr.recognize(&image, rect);
+                true
+            });
+            print_row(&format!("{}x{}", w, h), &result);
+            if (w, h) == POLL_OCR_SIZE {
+                poll_ocr = result.0;
+            }
+        }
+    }
+
+    if opts.matching {
+        println!("\n🧩 模板匹配 (灰度 NCC)");
+        print_header();
+        print_row(&format!("建积分图 {}x{}", screen.width(), screen.height()), &measure(n, || {
+            SearchImage::from_rgba(&screen).width() > 0
+        }));
+        let search = SearchImage::from_rgba(&screen);
+        let (rw, rh) = MATCH_REGION;
+        let region = [
+            (center.0 as u32).saturating_sub(rw / 2),
+            (center.1 as u32).saturating_sub(rh / 2),
+            center.0 as u32 + rw / 2,
+            center.1 as u32 + rh / 2,
+        ];
+        let template = template_from(&screen, center, MATCH_TEMPLATE);
+        let result = measure(n, || template.best_match_in(&search, region).is_some());
+        print_row(&format!("{}px 模板 / {}x{} 区域", MATCH_TEMPLATE, rw, rh), &result);
+        if let (Some(s), _) = result {
+            let positions = ((rw - MATCH_TEMPLATE + 1) * (rh - MATCH_TEMPLATE + 1)) as f64;
+            println!("  -> 吞吐 {:.1} 万个位置/秒 (p50)", positions / (s.p50 / 1000.0) / 10_000.0);
+        }
+    }
+
+    if opts.serial {
+        println!("\n🔌 串口往返 (心跳帧)");
+        match driver.firmware() {
+            None => println!("  当前驱动不是串口硬件盒子，跳过"),
+            Some(fw) => {
+                let before = driver.metrics().heartbeats_sent;
+                print_header();
+                let result = measure(n, || {
+                    driver.heartbeat();
+                    driver.metrics().missed_acks == 0
+                });
+                print_row("写入 + 确认", &result);
+                if fw.legacy {
+                    println!("  ⚠️ 旧固件不回 ACK，以上只是写入耗时 (含 4ms 帧间隔)");
+                } else {
+                    println!("  (含 4ms 帧间隔；共发送 {} 帧)", driver.metrics().heartbeats_sent - before);
+                }
+            }
+        }
+    }
+
+    if let (Some(cap), Some(ocr)) = (rect_capture, poll_ocr) {
+        let (w, h) = POLL_OCR_SIZE;
+        let total = cap.p90 + ocr.p90;
+        println!("\n📊 单次区域轮询 (区域截图 + {}x{} OCR) p90 ≈ {:.1} ms，1 秒轮询占用 {:.1}%", w, h, total, total / 10.0);
+        if total > 500.0 {
+            println!("   ⚠️ 超过预算的一半，1 秒轮询会挤占其他动作，建议放宽间隔或改用模板匹配");
+        }
+    }
+}
+
+/// 从截图中心取一块作为模板 (缺少真实模板时用画面本身，保证可匹配)
+fn template_from(screen: &RgbaImage, center: (i32, i32), size: u32) -> GrayTemplate {
+    let x = (center.0 as u32).saturating_sub(size / 2);
+    let y = (center.1 as u32).saturating_sub(size / 2);
+    GrayTemplate::new(vision::to_gray(&imageops::crop_imm(screen, x, y, size, size).to_image()))
+}
@@ -0,0 +1,166 @@
+// src/calibration.rs
+use crate::vision::GrayTemplate;
+use crate::human::HumanDriver;
+use crate::frames;
+use image::imageops::{self, FilterType};
+use image::GrayImage;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::thread;
+use std::time::Duration;
+use tracing::{error, info, warn};
+
+// ==========================================
+// 镜头灵敏度校准
+// ==========================================
+// 游戏内镜头灵敏度不同，按住方向键的时长与画面平移距离的比例也不同。
+// calibrate() 按住方向键一段已知时间，用模板匹配测量画面位移，得出 像素/毫秒 系数，
+// 保存到 calibration.json，供 HumanDriver 与 TowerDefenseApp 换算按键时长。
+
+pub const DEFAULT_PATH: &str = "calibration.json";
+
+/// 截图缩小倍数 (整屏 NCC 搜索太慢)
+const DOWNSCALE: u32 = 4;
+/// 缩小后用于跟踪的中心图块边长
+const PATCH: u32 = 48;
+/// 匹配得分低于该值的测量视为失败 (画面变化太大或纹理太少)
+const MIN_SCORE: f32 = 0.6;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct Calibration {
+    /// 按住平移键时画面每毫秒移动的像素数
+    pub pan_px_per_ms: f32,
+}
+
+impl Calibration {
+    /// 读取校准结果；文件不存在或格式错误时返回 None
+    pub fn load(path: &str) -> Option<Self> {
+        let content = fs::read_to_string(path).ok()?;
+        serde_json::from_str(&content).ok()
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        fs::write(path, json).map_err(|e| format!("无法保存 {}: {}", path, e))
+    }
+
+    /// 平移 pixels 像素需要按住的毫秒数
+    pub fn pan_ms(&self, pixels: f32) -> u64 {
+        (pixels.abs() / self.pan_px_per_ms.max(f32::EPSILON)).round() as u64
+    }
+}
+
+/// 截取主屏并缩小为灰度图
+fn grab_gray() -> Option<GrayImage> {
+    let rgba = frames::capture_screen()?;
+    let gray = image::DynamicImage::ImageRgba8(rgba).to_luma8();
+    let (w, h) = (gray.width() / DOWNSCALE, gray.height() / DOWNSCALE);
+    Some(imageops::resize(&gray, w, h, FilterType::Triangle))
+}
+
+/// 一次测量：按住 key hold_ms 毫秒，返回画面位移 (像素)
+fn measure_once(human: &mut HumanDriver, key: char, hold_ms: u64) -> Option<f32> {
+    let before = grab_gray()?;
+    if before.width() < PATCH || before.height() < PATCH {
+        return None;
+    }
+    let (px, py) = ((before.width() - PATCH) / 2, (before.height() - PATCH) / 2);
+    let patch = GrayTemplate::new(imageops::crop_imm(&before, px, py, PATCH, PATCH).to_image());
+
+    human.key_hold(key, hold_ms);
+    // 等待镜头惯性停止
+    thread::sleep(Duration::from_millis(400));
+
+    let after = grab_gray()?;
+    let (bx, by, score) = patch.best_match(&after)?;
+    if score < MIN_SCORE {
+        warn!("⚠️ [校准] 匹配得分过低 ({:.2})，本次测量作废", score);
+        return None;
+    }
+    let dx = (bx as f32 - px as f32) * DOWNSCALE as f32;
+    let dy = (by as f32 - py as f32) * DOWNSCALE as f32;
+    Some((dx * dx + dy * dy).sqrt())
+}
+
+/// 执行 trials 次测量并取中位数；每次测量后按反方向键回到原位
+pub fn calibrate(human: &mut HumanDriver, key: char, back_key: char, hold_ms: u64, trials: u32) -> Option<Calibration> {
+    info!("📐 [校准] 按住 '{}' {}ms，共 {} 次...", key, hold_ms, trials);
+    let mut samples = Vec::new();
+    for i in 1..=trials.max(1) {
+        if let Some(px) = measure_once(human, key, hold_ms) {
+            info!("   -> 第 {} 次: 位移 {:.0}px", i, px);
+            if px > 0.0 {
+                samples.push(px / hold_ms as f32);
+            }
+        }
+        human.key_hold(back_key, hold_ms);
+        thread::sleep(Duration::from_millis(400));
+    }
+    if samples.is_empty() {
+        error!("❌ [校准] 没有有效测量");
+        return None;
+    }
+    samples.sort_by(|a, b| a.total_cmp(b));
+    let cal = Calibration { pan_px_per_ms: samples[samples.len() / 2] };
+    info!("✅ [校准] 平移速度 {:.3} px/ms ({:.0} px/s)", cal.pan_px_per_ms, cal.pan_px_per_ms * 1000.0);
+    Some(cal)
+}
+
+// ==========================================
+// 缩放校准
+// ==========================================
+// 塔防地图上的网格线是等间距的。对截图逐列 (逐行) 累加灰度梯度得到一维信号，
+// 信号自相关的最强周期即屏幕上的格子边长，与 MapMeta.grid_pixel_size 比较即可判断缩放是否到位。
+
+/// 测量图中网格的屏幕间距 (像素)，只在 [min_px, max_px] 范围内搜索；纹理不足时返回 None
+pub fn measure_grid_spacing(img: &GrayImage, min_px: f32, max_px: f32) -> Option<f32> {
+    let (w, h) = img.dimensions();
+    let cols: Vec<f32> = (0..w.saturating_sub(1))
+        .map(|x| (0..h).map(|y| (img.get_pixel(x + 1, y)[0] as f32 - img.get_pixel(x, y)[0] as f32).abs()).sum())
+        .collect();
+    let rows: Vec<f32> = (0..h.saturating_sub(1))
+        .map(|y| (0..w).map(|x| (img.get_pixel(x, y + 1)[0] as f32 - img.get_pixel(x, y)[0] as f32).abs()).sum())
+        .collect();
+    let periods: Vec<f32> = [cols, rows]
+        .iter()
+        .filter_map(|s| dominant_period(s, min_px, max_px))
+        .collect();
+    if periods.is_empty() {
+        return None;
+    }
+    Some(periods.iter().sum::<f32>() / periods.len() as f32)
+}
+
+/// 一维信号自相关最强的周期 (抛物线插值到亚像素)
+fn dominant_period(signal: &[f32], min_px: f32, max_px: f32) -> Option<f32> {
+    let n = signal.len();
+    let mean = signal.iter().sum::<f32>() / n.max(1) as f32;
+    let s: Vec<f32> = signal.iter().map(|v| v - mean).collect();
+    let energy: f32 = s.iter().map(|v| v * v).sum::<f32>() / n.max(1) as f32;
+    if energy <= f32::EPSILON {
+        return None;
+    }
+
+    let lo = (min_px.floor() as usize).max(2);
+    let hi = (max_px.ceil() as usize).min(n / 2);
+    if lo + 2 > hi {
+        return None;
+    }
+    let acf = |lag: usize| s.iter().zip(&s[lag..]).map(|(a, b)| a * b).sum::<f32>() / (n - lag) as f32 / energy;
+
+    let values: Vec<f32> = (lo - 1..=hi + 1).map(acf).collect();
+    let (best_i, best) = values[1..values.len() - 1]
+        .iter()
+        .enumerate()
+        .map(|(i, v)| (i + 1, *v))
+        .filter(|&(i, v)| v >= values[i - 1] && v >= values[i + 1])
+        .max_by(|a, b| a.1.total_cmp(&b.1))?;
+    // 相关性太弱说明画面里没有规则网格
+    if best < 0.2 {
+        return None;
+    }
+    let (l, c, r) = (values[best_i - 1], best, values[best_i + 1]);
+    let denom = l - 2.0 * c + r;
+    let offset = if denom.abs() > f32::EPSILON { 0.5 * (l - r) / denom } else { 0.0 };
+    Some((lo - 1 + best_i) as f32 + offset)
+}
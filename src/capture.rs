@@ -0,0 +1,315 @@
+// src/capture.rs
+use crate::nav::game_screen;
+use image::RgbaImage;
+use std::sync::{Arc, RwLock};
+use tracing::{info, warn};
+// ==========================================
+// 截图后端
+// ==========================================
+// screenshots (GDI) 每次整屏截图 100ms 以上，是整个感知循环的瓶颈。
+// DXGI Desktop Duplication 直接从显存取桌面图像：最近一帧常驻 GPU 纹理，画面没变时直接复用；
+// 区域截图只把该区域拷到 staging 纹理再读回，不做整帧拷贝。
+// 初始化失败 (远程桌面、旋转屏等) 时使用 GDI；运行中失效 (分辨率切换、UAC 安全桌面) 时自动重建，重建失败的那一次回退到 GDI。
+// Linux (feature = "linux") 没有 DXGI，始终使用 screenshots crate (X11 / Wayland)。
+
+pub trait CaptureBackend: Send + Sync {
+    fn name(&self) -> &'static str;
+    /// 截取游戏所在显示器的整个画面
+    fn capture_screen(&self) -> Option<RgbaImage>;
+    /// 截取区域 [x1, y1, x2, y2] (显示器坐标)
+    fn capture_rect(&self, rect: [i32; 4]) -> Option<RgbaImage>;
+}
+
+static BACKEND: RwLock<Option<Arc<dyn CaptureBackend>>> = RwLock::new(None);
+
+/// 按 [general] capture 选择截图后端：auto (默认，优先 DXGI) / dxgi / gdi。需在 set_game_display 之后调用
+#[cfg(feature = "windows")]
+pub fn init(kind: &str) {
+    let backend: Arc<dyn CaptureBackend> = match kind.to_lowercase().as_str() {
+        "gdi" => Arc::new(GdiCapture),
+        other => {
+            if !matches!(other, "auto" | "dxgi" | "") {
+                warn!("⚠️ [截图] 未知后端 '{}'，按 auto 处理", kind);
+            }
+            match DxgiCapture::new() {
+                Ok(d) => Arc::new(d),
+                Err(e) => {
+                    warn!("⚠️ [截图] DXGI 初始化失败，使用 GDI: {}", e);
+                    Arc::new(GdiCapture)
+                }
+            }
+        }
+    };
+    info!("📸 [截图] 后端: {}", backend.name());
+    if let Ok(mut b) = BACKEND.write() {
+        *b = Some(backend);
+    }
+}
+
+/// Linux 只有 screenshots 一种后端
+#[cfg(not(feature = "windows"))]
+pub fn init(kind: &str) {
+    if !matches!(kind.to_lowercase().as_str(), "auto" | "gdi" | "") {
+        warn!("⚠️ [截图] 后端 '{}' 在此平台不可用，使用 {}", kind, GdiCapture.name());
+    }
+    info!("📸 [截图] 后端: {}", GdiCapture.name());
+    if let Ok(mut b) = BACKEND.write() {
+        *b = Some(Arc::new(GdiCapture));
+    }
+}
+
+/// 当前截图后端；未调用 init 时为 GDI
+pub fn backend() -> Arc<dyn CaptureBackend> {
+    match BACKEND.read().ok().and_then(|b| b.clone()) {
+        Some(b) => b,
+        None => Arc::new(GdiCapture),
+    }
+}
+
+pub fn capture_screen() -> Option<RgbaImage> {
+    backend().capture_screen()
+}
+
+pub fn capture_rect(rect: [i32; 4]) -> Option<RgbaImage> {
+    backend().capture_rect(rect)
+}
+
+// ==========================================
+// 1. GDI (screenshots crate)
+// ==========================================
+/// screenshots crate 截图：Windows 上为 GDI，Linux 上为 X11 (Wayland 下经桌面门户)
+pub struct GdiCapture;
+
+impl CaptureBackend for GdiCapture {
+    fn name(&self) -> &'static str {
+        if cfg!(feature = "windows") { "GDI" } else { "X11" }
+    }
+
+    fn capture_screen(&self) -> Option<RgbaImage> {
+        let captured = game_screen()?.capture().ok()?;
+        RgbaImage::from_raw(captured.width(), captured.height(), captured.into_raw())
+    }
+
+    fn capture_rect(&self, rect: [i32; 4]) -> Option<RgbaImage> {
+        let w = (rect[2] - rect[0]).max(1) as u32;
+        let h = (rect[3] - rect[1]).max(1) as u32;
+        let captured = game_screen()?.capture_area(rect[0], rect[1], w, h).ok()?;
+        RgbaImage::from_raw(captured.width(), captured.height(), captured.into_raw())
+    }
+}
+
+// ==========================================
+// 2. DXGI Desktop Duplication
+// ==========================================
+#[cfg(feature = "windows")]
+pub use dxgi::DxgiCapture;
+
+#[cfg(feature = "windows")]
+mod dxgi {
+    use super::{CaptureBackend, GdiCapture};
+    use crate::nav::game_screen;
+    use image::RgbaImage;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Mutex;
+    use tracing::{debug, warn};
+    use windows::core::{ComInterface, Error, Result as WinResult};
+    use windows::Win32::Foundation::{E_FAIL, HMODULE};
+    use windows::Win32::Graphics::Direct3D::D3D_DRIVER_TYPE_UNKNOWN;
+    use windows::Win32::Graphics::Direct3D11::{
+        D3D11CreateDevice, ID3D11Device, ID3D11DeviceContext, ID3D11Texture2D, D3D11_BOX, D3D11_CPU_ACCESS_READ,
+        D3D11_CREATE_DEVICE_BGRA_SUPPORT, D3D11_MAPPED_SUBRESOURCE, D3D11_MAP_READ, D3D11_SDK_VERSION, D3D11_TEXTURE2D_DESC,
+        D3D11_USAGE, D3D11_USAGE_DEFAULT, D3D11_USAGE_STAGING,
+    };
+    use windows::Win32::Graphics::Dxgi::Common::{DXGI_FORMAT_B8G8R8A8_UNORM, DXGI_SAMPLE_DESC};
+    use windows::Win32::Graphics::Dxgi::{
+        CreateDXGIFactory1, IDXGIFactory1, IDXGIOutput1, IDXGIOutputDuplication, IDXGIResource, DXGI_ERROR_NOT_FOUND,
+        DXGI_ERROR_WAIT_TIMEOUT, DXGI_OUTDUPL_DESC, DXGI_OUTDUPL_FRAME_INFO, DXGI_OUTPUT_DESC,
+    };
+
+    pub struct DxgiCapture {
+        /// 游戏显示器在虚拟桌面中的左上角，用于重建时找回同一个输出
+        origin: (i32, i32),
+        state: Mutex<Option<Duplication>>,
+        /// 已提示过回退 (避免每帧刷屏)
+        warned: AtomicBool,
+    }
+
+    impl DxgiCapture {
+        pub fn new() -> Result<Self, String> {
+            let screen = game_screen().ok_or("未检测到显示器")?;
+            let origin = (screen.display_info.x, screen.display_info.y);
+            let dup = unsafe { Duplication::open(origin) }.map_err(|e| e.message().to_string())?;
+            Ok(Self { origin, state: Mutex::new(Some(dup)), warned: AtomicBool::new(false) })
+        }
+
+        /// 取一帧并读回 rect 区域 (None 为整屏)；失效时重建一次
+        fn grab(&self, rect: Option<[i32; 4]>) -> Option<RgbaImage> {
+            let mut state = self.state.lock().ok()?;
+            let mut last_err = None;
+            for _ in 0..2 {
+                if state.is_none() {
+                    match unsafe { Duplication::open(self.origin) } {
+                        Ok(d) => *state = Some(d),
+                        Err(e) => {
+                            last_err = Some(e);
+                            break;
+                        }
+                    }
+                }
+                let dup = state.as_mut()?;
+                match unsafe { dup.capture(rect) } {
+                    Ok(img) => return img,
+                    Err(e) => {
+                        debug!("[截图] DXGI 截图失败，重建: {}", e);
+                        *state = None;
+                        last_err = Some(e);
+                    }
+                }
+            }
+            if !self.warned.swap(true, Ordering::Relaxed) {
+                warn!("⚠️ [截图] DXGI 暂不可用，回退到 GDI: {}", last_err.map(|e| e.message().to_string()).unwrap_or_default());
+            }
+            None
+        }
+    }
+
+    impl CaptureBackend for DxgiCapture {
+        fn name(&self) -> &'static str {
+            "DXGI"
+        }
+
+        fn capture_screen(&self) -> Option<RgbaImage> {
+            self.grab(None).or_else(|| GdiCapture.capture_screen())
+        }
+
+        fn capture_rect(&self, rect: [i32; 4]) -> Option<RgbaImage> {
+            self.grab(Some(rect)).or_else(|| GdiCapture.capture_rect(rect))
+        }
+    }
+
+    /// 一个输出的复制会话与复用的纹理 (COM 对象只在 DxgiCapture 的锁内使用)
+    struct Duplication {
+        context: ID3D11DeviceContext,
+        dup: IDXGIOutputDuplication,
+        width: u32,
+        height: u32,
+        /// 最近一帧桌面图像 (显存)；画面没有变化时 AcquireNextFrame 超时，直接复用
+        frame: ID3D11Texture2D,
+        has_frame: bool,
+        /// CPU 可读的中转纹理 (与桌面同尺寸)，区域截图只写入左上角
+        staging: ID3D11Texture2D,
+    }
+
+    impl Duplication {
+        /// 找到左上角为 origin 的输出并开始复制
+        unsafe fn open(origin: (i32, i32)) -> WinResult<Self> {
+            let factory: IDXGIFactory1 = CreateDXGIFactory1()?;
+            let mut a = 0;
+            while let Ok(adapter) = factory.EnumAdapters1(a) {
+                a += 1;
+                let mut o = 0;
+                while let Ok(output) = adapter.EnumOutputs(o) {
+                    o += 1;
+                    let mut desc = DXGI_OUTPUT_DESC::default();
+                    output.GetDesc(&mut desc)?;
+                    if (desc.DesktopCoordinates.left, desc.DesktopCoordinates.top) != origin {
+                        continue;
+                    }
+
+                    let (mut device, mut context): (Option<ID3D11Device>, Option<ID3D11DeviceContext>) = (None, None);
+                    D3D11CreateDevice(
+                        &adapter,
+                        D3D_DRIVER_TYPE_UNKNOWN,
+                        HMODULE::default(),
+                        D3D11_CREATE_DEVICE_BGRA_SUPPORT,
+                        None,
+                        D3D11_SDK_VERSION,
+                        Some(&mut device),
+                        None,
+                        Some(&mut context),
+                    )?;
+                    let (device, context) = (device.ok_or(Error::from(E_FAIL))?, context.ok_or(Error::from(E_FAIL))?);
+                    let dup = output.cast::<IDXGIOutput1>()?.DuplicateOutput(&device)?;
+
+                    let mut dup_desc = DXGI_OUTDUPL_DESC::default();
+                    dup.GetDesc(&mut dup_desc);
+                    let (width, height) = (dup_desc.ModeDesc.Width, dup_desc.ModeDesc.Height);
+                    let frame = create_texture(&device, width, height, D3D11_USAGE_DEFAULT, 0)?;
+                    let staging = create_texture(&device, width, height, D3D11_USAGE_STAGING, D3D11_CPU_ACCESS_READ.0 as u32)?;
+                    return Ok(Self { context, dup, width, height, frame, has_frame: false, staging });
+                }
+            }
+            Err(Error::from(DXGI_ERROR_NOT_FOUND))
+        }
+
+        /// 有新画面时把桌面图像拷进 frame (显存内拷贝)
+        unsafe fn refresh(&mut self) -> WinResult<()> {
+            let mut info = DXGI_OUTDUPL_FRAME_INFO::default();
+            let mut resource: Option<IDXGIResource> = None;
+            // 首帧多等一会儿，之后不等待：超时说明画面没变
+            let timeout = if self.has_frame { 0 } else { 500 };
+            match self.dup.AcquireNextFrame(timeout, &mut info, &mut resource) {
+                Ok(()) => {}
+                Err(e) if e.code() == DXGI_ERROR_WAIT_TIMEOUT && self.has_frame => return Ok(()),
+                Err(e) => return Err(e),
+            }
+            // 只有鼠标移动时 LastPresentTime 为 0，桌面图像没变
+            let copied = match resource {
+                Some(r) if info.LastPresentTime != 0 || !self.has_frame => r.cast::<ID3D11Texture2D>().map(|tex| {
+                    self.context.CopyResource(&self.frame, &tex);
+                    self.has_frame = true;
+                }),
+                _ => Ok(()),
+            };
+            let released = self.dup.ReleaseFrame();
+            copied.and(released)
+        }
+
+        /// 读回区域 (None 为整屏)；区域完全在屏幕外时返回 Ok(None)
+        unsafe fn capture(&mut self, rect: Option<[i32; 4]>) -> WinResult<Option<RgbaImage>> {
+            self.refresh()?;
+            let [x1, y1, x2, y2] = rect.unwrap_or([0, 0, self.width as i32, self.height as i32]);
+            let clamp = |v: i32, max: u32| v.clamp(0, max as i32) as u32;
+            let (left, top) = (clamp(x1, self.width), clamp(y1, self.height));
+            let (right, bottom) = (clamp(x2.max(x1 + 1), self.width), clamp(y2.max(y1 + 1), self.height));
+            if right <= left || bottom <= top {
+                return Ok(None);
+            }
+            let (w, h) = (right - left, bottom - top);
+
+            let region = D3D11_BOX { left, top, front: 0, right, bottom, back: 1 };
+            self.context.CopySubresourceRegion(&self.staging, 0, 0, 0, 0, &self.frame, 0, Some(&region));
+            let mut mapped = D3D11_MAPPED_SUBRESOURCE::default();
+            self.context.Map(&self.staging, 0, D3D11_MAP_READ, 0, Some(&mut mapped))?;
+
+            // BGRA -> RGBA，逐行按 RowPitch 读取
+            let mut pixels = Vec::with_capacity((w * h * 4) as usize);
+            for row in 0..h as usize {
+                let src = std::slice::from_raw_parts((mapped.pData as *const u8).add(row * mapped.RowPitch as usize), w as usize * 4);
+                for px in src.chunks_exact(4) {
+                    pixels.extend_from_slice(&[px[2], px[1], px[0], 255]);
+                }
+            }
+            self.context.Unmap(&self.staging, 0);
+            Ok(RgbaImage::from_raw(w, h, pixels))
+        }
+    }
+
+    unsafe fn create_texture(device: &ID3D11Device, width: u32, height: u32, usage: D3D11_USAGE, cpu_access: u32) -> WinResult<ID3D11Texture2D> {
+        let desc = D3D11_TEXTURE2D_DESC {
+            Width: width,
+            Height: height,
+            MipLevels: 1,
+            ArraySize: 1,
+            Format: DXGI_FORMAT_B8G8R8A8_UNORM,
+            SampleDesc: DXGI_SAMPLE_DESC { Count: 1, Quality: 0 },
+            Usage: usage,
+            BindFlags: 0,
+            CPUAccessFlags: cpu_access,
+            MiscFlags: 0,
+        };
+        let mut texture = None;
+        device.CreateTexture2D(&desc, None, Some(&mut texture))?;
+        texture.ok_or(Error::from(E_FAIL))
+    }
+}
@@ -0,0 +1,129 @@
+// src/clipboard.rs
+
+// ==========================================
+// 系统剪贴板 (文本)
+// ==========================================
+// 硬件盒子只能发送 HID 键码，无法直接输入中文等字符；
+// HumanDriver 的粘贴输入模式先写入剪贴板，再发送 Ctrl+V。
+// Windows 直接调用剪贴板 API；Linux 调用 wl-copy / wl-paste (Wayland) 或 xclip (X11)。
+
+#[cfg(feature = "windows")]
+pub use win32::{get_text, set_text};
+#[cfg(not(feature = "windows"))]
+pub use command::{get_text, set_text};
+
+#[cfg(feature = "windows")]
+mod win32 {
+    use windows::Win32::Foundation::{HANDLE, HGLOBAL, HWND};
+    use windows::Win32::System::DataExchange::{
+        CloseClipboard, EmptyClipboard, GetClipboardData, OpenClipboard, SetClipboardData,
+    };
+    use windows::Win32::System::Memory::{GlobalAlloc, GlobalLock, GlobalUnlock, GMEM_MOVEABLE};
+
+    /// CF_UNICODETEXT (定义在 Win32_System_Ole 中，这里直接写常量以免引入整个特性)
+    const CF_UNICODETEXT: u32 = 13;
+
+    /// 打开剪贴板并在离开作用域时关闭
+    struct ClipboardGuard;
+
+    impl ClipboardGuard {
+        fn open() -> Result<Self, String> {
+            // 其他进程可能短暂占用剪贴板，重试几次
+            for _ in 0..5 {
+                if unsafe { OpenClipboard(HWND(0)) }.is_ok() {
+                    return Ok(Self);
+                }
+                std::thread::sleep(std::time::Duration::from_millis(20));
+            }
+            Err("无法打开剪贴板".to_string())
+        }
+    }
+
+    impl Drop for ClipboardGuard {
+        fn drop(&mut self) {
+            let _ = unsafe { CloseClipboard() };
+        }
+    }
+
+    /// 读取剪贴板中的文本；没有文本时返回 None
+    pub fn get_text() -> Option<String> {
+        let _guard = ClipboardGuard::open().ok()?;
+        unsafe {
+            let handle = GetClipboardData(CF_UNICODETEXT).ok()?;
+            let hmem = HGLOBAL(handle.0 as *mut _);
+            let ptr = GlobalLock(hmem) as *const u16;
+            if ptr.is_null() {
+                return None;
+            }
+            let mut len = 0;
+            while *ptr.add(len) != 0 {
+                len += 1;
+            }
+            let text = String::from_utf16_lossy(std::slice::from_raw_parts(ptr, len));
+            let _ = GlobalUnlock(hmem);
+            Some(text)
+        }
+    }
+
+    /// 将文本写入剪贴板 (覆盖原内容)
+    pub fn set_text(text: &str) -> Result<(), String> {
+        let wide: Vec<u16> = text.encode_utf16().chain(std::iter::once(0)).collect();
+        let _guard = ClipboardGuard::open()?;
+        unsafe {
+            EmptyClipboard().map_err(|e| format!("清空剪贴板失败: {}", e))?;
+            let hmem = GlobalAlloc(GMEM_MOVEABLE, wide.len() * 2).map_err(|e| format!("分配剪贴板内存失败: {}", e))?;
+            let ptr = GlobalLock(hmem) as *mut u16;
+            if ptr.is_null() {
+                return Err("锁定剪贴板内存失败".to_string());
+            }
+            std::ptr::copy_nonoverlapping(wide.as_ptr(), ptr, wide.len());
+            let _ = GlobalUnlock(hmem);
+            // 成功后内存归系统所有，无需释放
+            SetClipboardData(CF_UNICODETEXT, HANDLE(hmem.0 as isize)).map_err(|e| format!("写入剪贴板失败: {}", e))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(not(feature = "windows"))]
+mod command {
+    use std::io::Write;
+    use std::process::{Command, Stdio};
+
+    /// (读取命令, 写入命令)：有 WAYLAND_DISPLAY 时用 wl-clipboard，否则用 xclip
+    fn programs() -> (Vec<&'static str>, Vec<&'static str>) {
+        if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+            (vec!["wl-paste", "--no-newline"], vec!["wl-copy"])
+        } else {
+            (vec!["xclip", "-selection", "clipboard", "-o"], vec!["xclip", "-selection", "clipboard"])
+        }
+    }
+
+    /// 读取剪贴板中的文本；没有文本时返回 None
+    pub fn get_text() -> Option<String> {
+        let (read, _) = programs();
+        let output = Command::new(read[0]).args(&read[1..]).stderr(Stdio::null()).output().ok()?;
+        output.status.success().then(|| String::from_utf8_lossy(&output.stdout).to_string())
+    }
+
+    /// 将文本写入剪贴板 (覆盖原内容)
+    pub fn set_text(text: &str) -> Result<(), String> {
+        let (_, write) = programs();
+        let mut child = Command::new(write[0])
+            .args(&write[1..])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| format!("无法运行 {} (请安装 wl-clipboard 或 xclip): {}", write[0], e))?;
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(text.as_bytes()).map_err(|e| format!("写入剪贴板失败: {}", e))?;
+        }
+        // xclip / wl-copy 会在后台常驻以持有剪贴板内容，这里只等前台进程退出
+        let status = child.wait().map_err(|e| format!("写入剪贴板失败: {}", e))?;
+        if !status.success() {
+            return Err(format!("{} 退出码 {}", write[0], status));
+        }
+        Ok(())
+    }
+}
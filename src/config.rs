@@ -0,0 +1,309 @@
+// src/config.rs
+use crate::api::ApiConfig;
+use crate::capture;
+use crate::frames;
+use crate::hardware::{KeyboardConfig, MouseConfig};
+use crate::logging::LoggingConfig;
+use crate::metrics::MetricsConfig;
+use crate::notify::NotifyConfig;
+use crate::human::{IdleConfig, MotionConfig, TimingProfile};
+use crate::nav::{self, HookAction, RetryPolicy};
+use crate::ocr::OcrConfig;
+use crate::retention::RetentionConfig;
+use crate::schedule::ScheduleConfig;
+use crate::session::SessionConfig;
+use crate::tower_defense::{data_file_path, TDConfigOverride};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use tracing::{info, warn};
+
+// ==========================================
+// 全局配置 (config.toml)
+// ==========================================
+// 所有字段均有默认值，配置文件不存在时直接使用默认配置。
+// [general] 与 [ocr] 的部分字段可用环境变量 NZM_* 覆盖，命令行参数优先级最高。
+
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct AppConfig {
+    /// 设备、屏幕与文件路径
+    #[serde(default)]
+    pub general: GeneralConfig,
+    #[serde(default)]
+    pub retention: RetentionConfig,
+    /// 按导航目标声明的任务钩子
+    #[serde(default)]
+    pub tasks: Vec<TaskConfig>,
+    #[serde(default)]
+    pub telemetry: TelemetryConfig,
+    #[serde(default)]
+    pub ocr: OcrConfig,
+    #[serde(default)]
+    pub keyboard: KeyboardConfig,
+    #[serde(default)]
+    pub mouse: MouseConfig,
+    /// 鼠标移动轨迹 (预设或自定义 MotionProfile)
+    #[serde(default)]
+    pub motion: MotionConfig,
+    /// 点击 / 按键 / 停留的随机节奏
+    #[serde(default)]
+    pub timing: TimingProfile,
+    /// 空闲时的光标小动作
+    #[serde(default)]
+    pub idle: IdleConfig,
+    /// schedule 子命令的启动时刻 / 间隔
+    #[serde(default)]
+    pub schedule: ScheduleConfig,
+    /// 本地控制接口 (HTTP)
+    #[serde(default)]
+    pub api: ApiConfig,
+    /// 运行指标的 HTTP 抓取接口 (Prometheus)
+    #[serde(default)]
+    pub metrics: MetricsConfig,
+    /// 日志级别与滚动文件
+    #[serde(default)]
+    pub logging: LoggingConfig,
+    /// 关键事件的 webhook 通知
+    #[serde(default)]
+    pub notify: NotifyConfig,
+    /// 会话录像 (--session) 的目录与截图参数
+    #[serde(default)]
+    pub session: SessionConfig,
+    /// 命名的运行配置 ([profiles.<名称>])，用 --profile 选择
+    #[serde(default)]
+    pub profiles: BTreeMap<String, ProfileConfig>,
+}
+
+/// 启动参数 ([general])：命令行 > 环境变量 > 配置文件 > 默认值
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct GeneralConfig {
+    /// 串口号 (如 COM9)；AUTO 自动探测，SOFT 软件模拟，NONE 空驱动
+    pub port: String,
+    /// serial / kmbox / ch9329 / soft / none
+    pub device_type: String,
+    /// 游戏分辨率；0 表示启动时按游戏所在显示器自动检测
+    pub screen_width: u16,
+    pub screen_height: u16,
+    /// 游戏所在显示器的序号 (从 1 开始)；0 为主屏
+    pub display: usize,
+    /// 截图后端：auto (优先 DXGI 桌面复制，不可用时 GDI) / dxgi / gdi
+    pub capture: String,
+    /// 帧服务后台截图间隔 (ms)；0 为按需截图 (区域订阅只在有人截图时推送)
+    pub frame_interval_ms: u64,
+    /// 共享帧的复用时限 (ms)：这段时间内的截图请求直接裁剪最近一帧
+    pub frame_max_age_ms: u64,
+    /// 场景图：单个 TOML 文件或片段目录
+    pub ui_map: String,
+    /// 塔防界面参数文件
+    pub td_config: String,
+    /// 地形 / 策略 / 陷阱配置所在目录 (空为运行目录)
+    pub map_dir: String,
+    /// 文件名模板，{目标名} 替换为导航目标；不写扩展名时 .toml 存在则用 .toml，否则 .json
+    pub terrain_file: String,
+    pub strategy_file: String,
+    pub traps_file: String,
+    /// 当前启用的运行配置 (--profile)，未指定时为空
+    #[serde(skip)]
+    pub profile: ProfileConfig,
+}
+
+impl Default for GeneralConfig {
+    fn default() -> Self {
+        Self {
+            port: "AUTO".to_string(),
+            device_type: "serial".to_string(),
+            screen_width: 0,
+            screen_height: 0,
+            display: 0,
+            capture: "auto".to_string(),
+            frame_interval_ms: 0,
+            frame_max_age_ms: 50,
+            ui_map: "ui_map.toml".to_string(),
+            td_config: "td_config.toml".to_string(),
+            map_dir: String::new(),
+            terrain_file: "{目标名}地图".to_string(),
+            strategy_file: "{目标名}策略".to_string(),
+            traps_file: "traps_config".to_string(),
+            profile: ProfileConfig::default(),
+        }
+    }
+}
+
+impl GeneralConfig {
+    /// 选择游戏显示器与截图后端并确定分辨率：未配置时自动检测，已配置但与实际不一致时给出警告
+    pub fn resolve_screen(&mut self) {
+        nav::set_game_display(self.display);
+        capture::init(&self.capture);
+        frames::configure(self.frame_interval_ms, self.frame_max_age_ms);
+        let detected = nav::detect_resolution();
+        match (self.screen_width, self.screen_height, detected) {
+            (0, _, Some((w, h))) | (_, 0, Some((w, h))) => {
+                (self.screen_width, self.screen_height) = (w as u16, h as u16);
+                info!("🖥️ 分辨率: {}x{} (自动检测)", w, h);
+            }
+            (0, _, None) | (_, 0, None) => {
+                (self.screen_width, self.screen_height) = (1920, 1080);
+                warn!("⚠️ 无法检测显示器分辨率，按 1920x1080 处理");
+            }
+            (w, h, Some((dw, dh))) if (w as u32, h as u32) != (dw, dh) => {
+                warn!("⚠️ 配置的分辨率 {}x{} 与显示器实际 {}x{} 不一致，鼠标坐标可能错位", w, h, dw, dh);
+            }
+            _ => {}
+        }
+    }
+
+    /// 按模板拼出 target 的数据文件路径 (位于 map_dir 下)
+    pub fn data_path(&self, template: &str, target: &str) -> String {
+        let name = template.replace("{目标名}", target);
+        let path = Path::new(&self.map_dir).join(&name).to_string_lossy().to_string();
+        let has_ext = Path::new(&name)
+            .extension()
+            .is_some_and(|e| e.eq_ignore_ascii_case("json") || e.eq_ignore_ascii_case("toml"));
+        if has_ext {
+            path
+        } else {
+            data_file_path(&path)
+        }
+    }
+
+    pub fn terrain_path(&self, target: &str) -> String {
+        self.data_path(&self.terrain_file, target)
+    }
+
+    pub fn strategy_path(&self, target: &str) -> String {
+        self.data_path(&self.strategy_file, target)
+    }
+
+    pub fn traps_path(&self) -> String {
+        self.data_path(&self.traps_file, "")
+    }
+}
+
+/// 导航遥测：每一步写一行 JSON，便于通宵运行后排查导航失败
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct TelemetryConfig {
+    pub enabled: bool,
+    pub path: String,
+}
+
+impl Default for TelemetryConfig {
+    fn default() -> Self {
+        Self { enabled: false, path: "logs/nav_telemetry.jsonl".to_string() }
+    }
+}
+
+/// 单个导航目标的任务配置
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct TaskConfig {
+    pub target: String,
+    /// 导航前执行 (如关闭启动器弹窗)
+    #[serde(default)]
+    pub pre_hooks: Vec<HookAction>,
+    /// 任务完成后执行 (如按截图快捷键)
+    #[serde(default)]
+    pub post_hooks: Vec<HookAction>,
+    /// 导航失败时的重试次数与退避间隔
+    #[serde(default)]
+    pub retry: RetryPolicy,
+}
+
+/// 一张地图的运行配置：目标、装备、数据文件、节奏与界面参数打包在一起，切换地图只需换 --profile
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct ProfileConfig {
+    /// 未写 --target 时的导航目标
+    pub target: Option<String>,
+    /// 固定的装备栏 (按顺序分配快捷键)；为空时按策略自动生成
+    pub loadout: Vec<String>,
+    /// 地形 / 策略 / 陷阱文件，写法同 [general] 的 terrain_file 等 (相对 map_dir，可含 {目标名})
+    pub terrain: Option<String>,
+    pub strategy: Option<String>,
+    pub traps: Option<String>,
+    /// 替换 [timing] 的点击 / 按键节奏
+    pub timing: Option<TimingProfile>,
+    /// 界面参数覆盖 (识别区域等)，优先于 td_config.toml 与地形文件
+    pub td: TDConfigOverride,
+}
+
+impl AppConfig {
+    pub fn task(&self, target: &str) -> Option<&TaskConfig> {
+        self.tasks.iter().find(|t| t.target == target)
+    }
+
+    /// 启用 [profiles.<name>]：覆盖数据文件模板与节奏，其余项 (目标、装备、界面参数) 记在 general.profile 中
+    pub fn apply_profile(&mut self, name: &str) -> Result<(), String> {
+        let Some(profile) = self.profiles.get(name).cloned() else {
+            let names: Vec<&str> = self.profiles.keys().map(String::as_str).collect();
+            return Err(format!(
+                "未找到运行配置 [profiles.{}] (已有: {})",
+                name,
+                if names.is_empty() { "无".to_string() } else { names.join(", ") }
+            ));
+        };
+        let g = &mut self.general;
+        if let Some(v) = &profile.terrain { g.terrain_file = v.clone(); }
+        if let Some(v) = &profile.strategy { g.strategy_file = v.clone(); }
+        if let Some(v) = &profile.traps { g.traps_file = v.clone(); }
+        if let Some(v) = &profile.timing { self.timing = v.clone(); }
+        info!("📦 已启用运行配置: {}", name);
+        self.general.profile = profile;
+        Ok(())
+    }
+
+    /// 加载全局配置；文件缺失或解析失败时回退到默认值。环境变量覆盖由 apply_env 在日志初始化后应用
+    pub fn load(path: &str) -> Self {
+        Self::load_file(path)
+    }
+
+    /// 环境变量覆盖：NZM_PORT、NZM_DEVICE_TYPE、NZM_SCREEN (如 2560x1440)、NZM_DISPLAY、NZM_CAPTURE、NZM_UI_MAP、
+    /// NZM_TD_CONFIG、NZM_MAP_DIR、NZM_OCR_BACKEND、NZM_OCR_LANGUAGE
+    pub fn apply_env(&mut self) {
+        let var = |name: &str| std::env::var(name).ok().filter(|v| !v.trim().is_empty());
+        let g = &mut self.general;
+        if let Some(v) = var("NZM_PORT") { g.port = v; }
+        if let Some(v) = var("NZM_DEVICE_TYPE") { g.device_type = v; }
+        if let Some(v) = var("NZM_CAPTURE") { g.capture = v; }
+        if let Some(v) = var("NZM_UI_MAP") { g.ui_map = v; }
+        if let Some(v) = var("NZM_TD_CONFIG") { g.td_config = v; }
+        if let Some(v) = var("NZM_MAP_DIR") { g.map_dir = v; }
+        if let Some(v) = var("NZM_SCREEN") {
+            match v.split_once(['x', 'X']).and_then(|(w, h)| Some((w.trim().parse().ok()?, h.trim().parse().ok()?))) {
+                Some((w, h)) => (g.screen_width, g.screen_height) = (w, h),
+                None => warn!("⚠️ NZM_SCREEN 格式应为 宽x高，已忽略: {}", v),
+            }
+        }
+        if let Some(v) = var("NZM_DISPLAY") {
+            match v.trim().parse() {
+                Ok(n) => g.display = n,
+                Err(_) => warn!("⚠️ NZM_DISPLAY 应为显示器序号，已忽略: {}", v),
+            }
+        }
+        if let Some(v) = var("NZM_OCR_BACKEND") { self.ocr.backend = v; }
+        if let Some(v) = var("NZM_OCR_LANGUAGE") { self.ocr.language = v; }
+    }
+
+    fn load_file(path: &str) -> Self {
+        if !Path::new(path).exists() {
+            return Self::default();
+        }
+        match fs::read_to_string(path) {
+            Ok(content) => match toml::from_str::<AppConfig>(&content) {
+                Ok(cfg) => {
+                    println!("⚙️ 已加载全局配置: {}", path);
+                    cfg
+                }
+                Err(e) => {
+                    println!("⚠️ 配置解析失败 ({})，使用默认配置: {}", path, e);
+                    Self::default()
+                }
+            },
+            Err(e) => {
+                println!("⚠️ 无法读取配置 ({})，使用默认配置: {}", path, e);
+                Self::default()
+            }
+        }
+    }
+}
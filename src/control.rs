@@ -0,0 +1,281 @@
+// src/control.rs
+use chrono::Local;
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+#[cfg(feature = "windows")]
+use windows::Win32::Foundation::BOOL;
+#[cfg(feature = "windows")]
+use windows::Win32::System::Console::SetConsoleCtrlHandler;
+#[cfg(feature = "windows")]
+use windows::Win32::UI::Input::KeyboardAndMouse::{GetAsyncKeyState, VK_F10, VK_F9};
+
+// ==========================================
+// 运行控制 (暂停 / 停止)
+// ==========================================
+// 业务模块持有 RunControl 的克隆，用 control.sleep() 代替 thread::sleep：
+// 暂停时原地等待，停止时立即返回 false，由调用方收尾 (释放按键、保存进度) 后退出。
+// 停止信号来源：第一次 Ctrl+C、F10 热键；F9 切换暂停。再次 Ctrl+C 则按默认方式结束进程。
+// 同时携带运行状态 (目标、场景、波次、已放置的建造)，供控制接口 (api.rs) 查询。
+// Linux 上 Ctrl+C 即 SIGINT / SIGTERM，热键从 /dev/input 的键盘设备读取 (需 input 组权限)。
+
+#[derive(Clone, Default)]
+pub struct RunControl {
+    stop: Arc<AtomicBool>,
+    pause: Arc<AtomicBool>,
+    status: Arc<Mutex<RunStatus>>,
+    /// 外部请求的下一个导航目标
+    target_request: Arc<Mutex<Option<String>>>,
+}
+
+/// 当前运行状态快照
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct RunStatus {
+    /// 当前导航目标
+    pub target: Option<String>,
+    /// 最近一次确认的场景
+    pub scene: Option<String>,
+    /// 塔防地图名 (不在塔防中时为空)
+    pub map: Option<String>,
+    pub wave: Option<i32>,
+    pub placed: Vec<PlacedTower>,
+    pub paused: bool,
+    pub stopped: bool,
+    /// 最近一次更新的时间
+    pub updated: String,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct PlacedTower {
+    pub uid: usize,
+    pub name: String,
+    pub grid: [usize; 2],
+}
+
+/// sleep 的检查粒度
+const SLICE: Duration = Duration::from_millis(50);
+
+impl RunControl {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn stop(&self) {
+        self.stop.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.stop.load(Ordering::SeqCst)
+    }
+
+    pub fn set_paused(&self, paused: bool) {
+        self.pause.store(paused, Ordering::SeqCst);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.pause.load(Ordering::SeqCst)
+    }
+
+    /// 暂停期间阻塞；返回 false 表示已收到停止信号
+    pub fn wait_if_paused(&self) -> bool {
+        while self.is_paused() && !self.is_stopped() {
+            thread::sleep(SLICE);
+        }
+        !self.is_stopped()
+    }
+
+    /// 可中断的 sleep：暂停时不计时，停止时立即返回 false
+    pub fn sleep(&self, dur: Duration) -> bool {
+        let mut remaining = dur;
+        while !remaining.is_zero() {
+            if !self.wait_if_paused() {
+                return false;
+            }
+            let step = remaining.min(SLICE);
+            let started = Instant::now();
+            thread::sleep(step);
+            remaining = remaining.saturating_sub(started.elapsed());
+        }
+        !self.is_stopped()
+    }
+
+    /// 修改运行状态并记录更新时间
+    pub fn update_status(&self, f: impl FnOnce(&mut RunStatus)) {
+        if let Ok(mut s) = self.status.lock() {
+            f(&mut s);
+            s.updated = Local::now().format("%H:%M:%S").to_string();
+        }
+    }
+
+    pub fn status(&self) -> RunStatus {
+        let mut s = self.status.lock().map(|s| s.clone()).unwrap_or_default();
+        s.paused = self.is_paused();
+        s.stopped = self.is_stopped();
+        s
+    }
+
+    /// 请求主循环下一轮改为导航到 target
+    pub fn request_target(&self, target: String) {
+        if let Ok(mut t) = self.target_request.lock() {
+            *t = Some(target);
+        }
+    }
+
+    pub fn take_target_request(&self) -> Option<String> {
+        self.target_request.lock().ok().and_then(|mut t| t.take())
+    }
+
+    /// 注册 Ctrl+C：第一次只发出停止信号，让业务模块自行收尾
+    pub fn install_ctrl_c(&self) {
+        let _ = CTRL_C_CONTROL.set(self.clone());
+        #[cfg(feature = "windows")]
+        unsafe {
+            let _ = SetConsoleCtrlHandler(Some(ctrl_c_handler), true);
+        }
+        #[cfg(feature = "linux")]
+        add_signal_handler(on_ctrl_c);
+    }
+
+    /// 后台轮询热键：F9 暂停 / 继续，F10 停止
+    pub fn spawn_hotkeys(&self) {
+        let control = self.clone();
+        #[cfg(feature = "windows")]
+        let pressed = |vk: u16| unsafe { GetAsyncKeyState(vk as i32) } & 0x0001 != 0;
+        #[cfg(feature = "linux")]
+        let mut pressed = {
+            let mut keyboards = linux_hotkeys::Keyboards::open();
+            if keyboards.is_empty() {
+                warn!("⚠️ [控制] 无法读取 /dev/input 键盘设备 (需加入 input 组)，热键不可用，请用 Ctrl+C 停止");
+                return;
+            }
+            move |key: u16| keyboards.pressed(key)
+        };
+        #[cfg(feature = "windows")]
+        let (f9, f10) = (VK_F9.0, VK_F10.0);
+        #[cfg(feature = "linux")]
+        let (f9, f10) = (crate::uinput::KEY_F9, crate::uinput::KEY_F10);
+        thread::spawn(move || loop {
+            if pressed(f9) {
+                let paused = !control.is_paused();
+                control.set_paused(paused);
+                info!("{}", if paused { "⏸️ [控制] 已暂停 (F9 继续)" } else { "▶️ [控制] 继续运行" });
+            }
+            if pressed(f10) && !control.is_stopped() {
+                info!("🛑 [控制] 收到停止热键，当前动作完成后退出");
+                control.stop();
+            }
+            thread::sleep(Duration::from_millis(100));
+        });
+        info!("⌨️ [控制] 热键: F9 暂停/继续，F10 停止");
+    }
+}
+
+static CTRL_C_CONTROL: OnceLock<RunControl> = OnceLock::new();
+
+/// 返回 true 表示已处理；已在停止中时返回 false，交给后续处理函数 (释放按键) 与默认行为 (退出进程)
+fn on_ctrl_c() -> bool {
+    match CTRL_C_CONTROL.get() {
+        Some(c) if !c.is_stopped() => {
+            println!("🛑 [控制] 收到 Ctrl+C，正在收尾 (再按一次强制退出)...");
+            c.stop();
+            true
+        }
+        _ => false,
+    }
+}
+
+#[cfg(feature = "windows")]
+unsafe extern "system" fn ctrl_c_handler(_ctrl_type: u32) -> BOOL {
+    BOOL(on_ctrl_c() as i32)
+}
+
+// ==========================================
+// Linux：SIGINT / SIGTERM 与热键
+// ==========================================
+// 信号处理函数里不能加锁，由 ctrlc 的后台线程依次调用登记的处理函数：
+// 后登记的先调用 (与 SetConsoleCtrlHandler 一致)，有一个返回 true 即停止；全部返回 false 时退出进程。
+
+#[cfg(feature = "linux")]
+static SIGNAL_HANDLERS: Mutex<Vec<fn() -> bool>> = Mutex::new(Vec::new());
+
+/// 登记 Ctrl+C 处理函数；返回 true 表示已处理 (不退出进程)
+#[cfg(feature = "linux")]
+pub(crate) fn add_signal_handler(handler: fn() -> bool) {
+    let first = match SIGNAL_HANDLERS.lock() {
+        Ok(mut handlers) => {
+            handlers.push(handler);
+            handlers.len() == 1
+        }
+        Err(_) => return,
+    };
+    if first {
+        let _ = ctrlc::set_handler(|| {
+            let handlers = SIGNAL_HANDLERS.lock().map(|h| h.clone()).unwrap_or_default();
+            if !handlers.iter().rev().any(|h| h()) {
+                std::process::exit(130);
+            }
+        });
+    }
+}
+
+#[cfg(feature = "linux")]
+mod linux_hotkeys {
+    use crate::uinput::EV_KEY;
+    use std::fs::{self, File, OpenOptions};
+    use std::io::Read;
+    use std::os::unix::fs::OpenOptionsExt;
+
+    /// 所有键盘类输入设备 (非阻塞读取)；记录两次轮询之间按下过的键，语义同 GetAsyncKeyState 的最低位
+    pub struct Keyboards {
+        devices: Vec<File>,
+        pressed: Vec<u16>,
+    }
+
+    impl Keyboards {
+        pub fn open() -> Self {
+            let devices = fs::read_dir("/dev/input/by-path")
+                .into_iter()
+                .flatten()
+                .flatten()
+                .map(|e| e.path())
+                .filter(|p| p.to_string_lossy().ends_with("-event-kbd"))
+                .filter_map(|p| OpenOptions::new().read(true).custom_flags(libc::O_NONBLOCK).open(p).ok())
+                .collect();
+            Self { devices, pressed: Vec::new() }
+        }
+
+        pub fn is_empty(&self) -> bool {
+            self.devices.is_empty()
+        }
+
+        /// 自上次查询以来 key 是否被按下过
+        pub fn pressed(&mut self, key: u16) -> bool {
+            self.poll();
+            let before = self.pressed.len();
+            self.pressed.retain(|&k| k != key);
+            self.pressed.len() != before
+        }
+
+        fn poll(&mut self) {
+            const EVENT_SIZE: usize = std::mem::size_of::<libc::input_event>();
+            let mut buf = [0u8; EVENT_SIZE * 64];
+            for device in &mut self.devices {
+                while let Ok(n) = device.read(&mut buf) {
+                    if n == 0 {
+                        break;
+                    }
+                    for chunk in buf[..n].chunks_exact(EVENT_SIZE) {
+                        // input_event 为纯数据结构，任意字节都是合法值
+                        let event: libc::input_event = unsafe { std::ptr::read_unaligned(chunk.as_ptr().cast()) };
+                        if event.type_ == EV_KEY && event.value == 1 && !self.pressed.contains(&event.code) {
+                            self.pressed.push(event.code);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
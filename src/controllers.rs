@@ -0,0 +1,250 @@
+// src/controllers.rs
+use crate::hardware::InputDriver;
+use serialport::SerialPort;
+use std::io::Write;
+use std::net::UdpSocket;
+use std::thread;
+use std::time::Duration;
+use tracing::info;
+
+// ==========================================
+// 第三方键鼠控制器
+// ==========================================
+// 与自研串口盒子一样实现 InputDriver，上层 (HumanDriver / NavEngine / 业务模块) 无需改动。
+// - KMBox Net : UDP，仅支持相对移动，绝对坐标由驱动内部记录的光标位置换算
+// - CH9329    : 串口转 HID 芯片，支持绝对坐标 (0 ~ 4095)
+
+// ==========================================
+// 1. KMBox Net (UDP)
+// ==========================================
+const KM_CMD_CONNECT: u32 = 0xAF3C_2828;
+const KM_CMD_MOUSE_MOVE: u32 = 0xAEDE_7345;
+const KM_CMD_MOUSE_LEFT: u32 = 0x9823_AE8D;
+const KM_CMD_MOUSE_RIGHT: u32 = 0x238D_8212;
+const KM_CMD_MOUSE_WHEEL: u32 = 0xFFEE_AD38;
+const KM_CMD_KEYBOARD_ALL: u32 = 0x123C_2C2F;
+
+pub struct KmboxNetDriver {
+    socket: UdpSocket,
+    /// 设备屏幕上显示的 UUID (8 位十六进制)
+    mac: u32,
+    index: u32,
+    buttons: i32,
+    cur_x: i32,
+    cur_y: i32,
+    pub screen_w: u16,
+    pub screen_h: u16,
+}
+
+impl KmboxNetDriver {
+    /// addr 格式: "IP:端口:UUID"，如 "192.168.2.188:8808:1A2B3C4D"
+    pub fn new(addr: &str, screen_w: u16, screen_h: u16) -> Result<Self, String> {
+        let parts: Vec<&str> = addr.split(':').collect();
+        let [ip, port, uuid] = parts.as_slice() else {
+            return Err(format!("KMBox 地址格式应为 IP:端口:UUID，实际为 '{}'", addr));
+        };
+        let mac = u32::from_str_radix(uuid, 16).map_err(|_| format!("无效的 KMBox UUID: {}", uuid))?;
+        let socket = UdpSocket::bind("0.0.0.0:0").map_err(|e| format!("无法创建 UDP 套接字: {}", e))?;
+        socket
+            .connect(format!("{}:{}", ip, port))
+            .map_err(|e| format!("无法连接 KMBox {}:{}: {}", ip, port, e))?;
+        let _ = socket.set_read_timeout(Some(Duration::from_millis(200)));
+
+        let mut drv = Self {
+            socket,
+            mac,
+            index: 0,
+            buttons: 0,
+            cur_x: screen_w as i32 / 2,
+            cur_y: screen_h as i32 / 2,
+            screen_w,
+            screen_h,
+        };
+        if !drv.send(KM_CMD_CONNECT, &[]) {
+            return Err(format!("KMBox {}:{} 无响应", ip, port));
+        }
+        info!("🔌 [KMBox] 已连接 {}:{}", ip, port);
+        Ok(drv)
+    }
+
+    /// 发送 16 字节包头 (mac, rand, index, cmd) + 负载，等待设备回包
+    fn send(&mut self, cmd: u32, payload: &[u8]) -> bool {
+        self.index = self.index.wrapping_add(1);
+        let mut packet = Vec::with_capacity(16 + payload.len());
+        packet.extend_from_slice(&self.mac.to_le_bytes());
+        packet.extend_from_slice(&rand::random::<u32>().to_le_bytes());
+        packet.extend_from_slice(&self.index.to_le_bytes());
+        packet.extend_from_slice(&cmd.to_le_bytes());
+        packet.extend_from_slice(payload);
+        if self.socket.send(&packet).is_err() {
+            return false;
+        }
+        let mut reply = [0u8; 1024];
+        self.socket.recv(&mut reply).is_ok()
+    }
+
+    /// soft_mouse_t: button, x, y, wheel, point[10] (均为 i32)
+    fn send_mouse(&mut self, cmd: u32, dx: i32, dy: i32, wheel: i32) {
+        let mut payload = Vec::with_capacity(56);
+        for v in [self.buttons, dx, dy, wheel] {
+            payload.extend_from_slice(&v.to_le_bytes());
+        }
+        payload.resize(56, 0);
+        self.send(cmd, &payload);
+    }
+}
+
+impl InputDriver for KmboxNetDriver {
+    fn heartbeat(&mut self) {}
+    fn switch_identity(&mut self, _index: u8) {}
+
+    fn mouse_abs(&mut self, x: u16, y: u16) {
+        let (x, y) = (x.min(self.screen_w) as i32, y.min(self.screen_h) as i32);
+        let (dx, dy) = (x - self.cur_x, y - self.cur_y);
+        if dx != 0 || dy != 0 {
+            self.send_mouse(KM_CMD_MOUSE_MOVE, dx, dy, 0);
+        }
+        self.cur_x = x;
+        self.cur_y = y;
+    }
+
+    fn mouse_move(&mut self, dx: i32, dy: i32, wheel: i8) {
+        if wheel != 0 {
+            self.send_mouse(KM_CMD_MOUSE_WHEEL, 0, 0, wheel as i32);
+        }
+        if dx != 0 || dy != 0 {
+            self.send_mouse(KM_CMD_MOUSE_MOVE, dx, dy, 0);
+            self.cur_x = (self.cur_x + dx).clamp(0, self.screen_w as i32);
+            self.cur_y = (self.cur_y + dy).clamp(0, self.screen_h as i32);
+        }
+    }
+
+    fn mouse_down(&mut self, left: bool, right: bool) {
+        if left {
+            self.buttons |= 0x01;
+            self.send_mouse(KM_CMD_MOUSE_LEFT, 0, 0, 0);
+        }
+        if right {
+            self.buttons |= 0x02;
+            self.send_mouse(KM_CMD_MOUSE_RIGHT, 0, 0, 0);
+        }
+    }
+
+    fn mouse_up(&mut self) {
+        let pressed = self.buttons;
+        self.buttons = 0;
+        if pressed & 0x01 != 0 {
+            self.send_mouse(KM_CMD_MOUSE_LEFT, 0, 0, 0);
+        }
+        if pressed & 0x02 != 0 {
+            self.send_mouse(KM_CMD_MOUSE_RIGHT, 0, 0, 0);
+        }
+    }
+
+    /// soft_keyboard_t: ctrl, resvel, button[10]
+    fn key_down(&mut self, keycode: u8, modifier: u8) {
+        let mut payload = [0u8; 12];
+        payload[0] = modifier;
+        payload[2] = keycode;
+        self.send(KM_CMD_KEYBOARD_ALL, &payload);
+    }
+
+    fn key_up(&mut self) {
+        self.send(KM_CMD_KEYBOARD_ALL, &[0u8; 12]);
+    }
+}
+
+// ==========================================
+// 2. CH9329 (串口转 HID)
+// ==========================================
+// 帧格式: 57 AB | 地址 00 | 命令 | 长度 | 数据 | 累加和
+const CH_CMD_KEYBOARD: u8 = 0x02;
+const CH_CMD_MOUSE_ABS: u8 = 0x04;
+const CH_CMD_MOUSE_REL: u8 = 0x05;
+/// CH9329 绝对坐标的分辨率
+const CH_ABS_RANGE: f32 = 4096.0;
+
+pub struct Ch9329Driver {
+    port: Box<dyn SerialPort>,
+    buttons: u8,
+    pub screen_w: u16,
+    pub screen_h: u16,
+}
+
+// 只通过 &mut self 访问串口
+unsafe impl Sync for Ch9329Driver {}
+
+impl Ch9329Driver {
+    /// CH9329 出厂波特率为 9600
+    pub fn new(port_name: &str, baud_rate: u32, screen_w: u16, screen_h: u16) -> Result<Self, String> {
+        let port = serialport::new(port_name, baud_rate)
+            .timeout(Duration::from_millis(100))
+            .open()
+            .map_err(|e| format!("无法打开 CH9329 串口 {}: {}", port_name, e))?;
+        info!("🔌 [CH9329] 已连接 {} @ {}", port_name, baud_rate);
+        Ok(Self { port, buttons: 0, screen_w, screen_h })
+    }
+
+    fn send(&mut self, cmd: u8, data: &[u8]) {
+        let mut frame = vec![0x57, 0xAB, 0x00, cmd, data.len() as u8];
+        frame.extend_from_slice(data);
+        let sum = frame.iter().fold(0u8, |acc, b| acc.wrapping_add(*b));
+        frame.push(sum);
+        let _ = self.port.write_all(&frame);
+        let _ = self.port.flush();
+        // 芯片会回 ACK，丢弃即可
+        let mut ack = [0u8; 16];
+        let _ = std::io::Read::read(&mut self.port, &mut ack);
+        thread::sleep(Duration::from_millis(2));
+    }
+
+    fn send_rel(&mut self, dx: i8, dy: i8, wheel: i8) {
+        self.send(CH_CMD_MOUSE_REL, &[0x01, self.buttons, dx as u8, dy as u8, wheel as u8]);
+    }
+}
+
+impl InputDriver for Ch9329Driver {
+    fn heartbeat(&mut self) {}
+    fn switch_identity(&mut self, _index: u8) {}
+
+    fn mouse_abs(&mut self, x: u16, y: u16) {
+        let tx = ((x as f32 / self.screen_w as f32) * CH_ABS_RANGE).clamp(0.0, CH_ABS_RANGE - 1.0) as u16;
+        let ty = ((y as f32 / self.screen_h as f32) * CH_ABS_RANGE).clamp(0.0, CH_ABS_RANGE - 1.0) as u16;
+        let [xl, xh] = tx.to_le_bytes();
+        let [yl, yh] = ty.to_le_bytes();
+        self.send(CH_CMD_MOUSE_ABS, &[0x02, self.buttons, xl, xh, yl, yh, 0]);
+    }
+
+    fn mouse_move(&mut self, dx: i32, dy: i32, wheel: i8) {
+        if wheel != 0 {
+            self.send_rel(0, 0, wheel);
+        }
+        let (mut cur_dx, mut cur_dy) = (dx, dy);
+        while cur_dx != 0 || cur_dy != 0 {
+            let step_x = cur_dx.clamp(-127, 127);
+            let step_y = cur_dy.clamp(-127, 127);
+            self.send_rel(step_x as i8, step_y as i8, 0);
+            cur_dx -= step_x;
+            cur_dy -= step_y;
+        }
+    }
+
+    fn mouse_down(&mut self, left: bool, right: bool) {
+        if left { self.buttons |= 0x01; }
+        if right { self.buttons |= 0x02; }
+        self.send_rel(0, 0, 0);
+    }
+
+    fn mouse_up(&mut self) {
+        self.buttons = 0;
+        self.send_rel(0, 0, 0);
+    }
+
+    fn key_down(&mut self, keycode: u8, modifier: u8) {
+        self.send(CH_CMD_KEYBOARD, &[modifier, 0, keycode, 0, 0, 0, 0, 0]);
+    }
+
+    fn key_up(&mut self) {
+        self.send(CH_CMD_KEYBOARD, &[0; 8]);
+    }
+}
@@ -0,0 +1,117 @@
+// src/digits.rs
+use crate::vision::{GrayTemplate, SearchImage};
+use image::RgbaImage;
+use std::fs;
+use std::path::Path;
+use tracing::{debug, info, warn};
+
+// ==========================================
+// 数字模板识别 (OCR 兜底)
+// ==========================================
+// 游戏的艺术字体是 Windows OCR 最容易翻车的地方，而波次数字又是最关键的读数。
+// 这里用 UI_tool 导出的 0~9 数字切图做归一化互相关 (NCC) 匹配，逐位拼出数字。
+
+struct DigitTemplate {
+    digit: u8,
+    tpl: GrayTemplate,
+}
+
+pub struct DigitTemplates {
+    templates: Vec<DigitTemplate>,
+    /// NCC 得分阈值 (0.0 ~ 1.0)
+    pub threshold: f32,
+}
+
+impl DigitTemplates {
+    /// 从目录加载数字模板。
+    /// 文件名为 `N.png`，同一数字的多个变体可命名为 `N_xxx.png`。
+    pub fn load_dir(dir: &str, threshold: f32) -> Option<Self> {
+        let entries = fs::read_dir(dir).ok()?;
+        let mut templates = Vec::new();
+
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let stem = match path.file_stem().and_then(|s| s.to_str()) {
+                Some(s) => s.to_string(),
+                None => continue,
+            };
+            let mut chars = stem.chars();
+            let digit = match chars.next().and_then(|c| c.to_digit(10)) {
+                Some(d) => d as u8,
+                None => continue,
+            };
+            if !matches!(chars.next(), None | Some('_')) {
+                continue;
+            }
+            match image::open(&path) {
+                Ok(img) => templates.push(DigitTemplate { digit, tpl: GrayTemplate::new(img.to_luma8()) }),
+                Err(e) => warn!("⚠️ [Digits] 模板加载失败 {:?}: {}", path, e),
+            }
+        }
+
+        if templates.is_empty() {
+            return None;
+        }
+        info!("🔢 [Digits] 已加载 {} 个数字模板 ({})", templates.len(), dir);
+        Some(Self { templates, threshold })
+    }
+
+    pub fn load_default(dir: &str) -> Option<Self> {
+        if !Path::new(dir).is_dir() {
+            return None;
+        }
+        Self::load_dir(dir, 0.8)
+    }
+
+    /// 在图像中识别所有数字串，按从左到右顺序返回
+    pub fn recognize_groups(&self, img: &RgbaImage) -> Vec<i32> {
+        let search = SearchImage::from_rgba(img);
+
+        // 1. 收集所有超过阈值的命中 (x, 宽度, 得分, 数字)
+        let mut hits: Vec<(u32, u32, f32, u8)> = Vec::new();
+        for dt in &self.templates {
+            for (x, _, score) in dt.tpl.find_all(&search, self.threshold) {
+                hits.push((x, dt.tpl.width, score, dt.digit));
+            }
+        }
+        if hits.is_empty() {
+            return Vec::new();
+        }
+
+        // 2. 非极大值抑制：按得分从高到低，水平重叠超过半个字宽的视为同一位
+        hits.sort_by(|a, b| b.2.partial_cmp(&a.2).unwrap_or(std::cmp::Ordering::Equal));
+        let mut accepted: Vec<(u32, u32, f32, u8)> = Vec::new();
+        for hit in hits {
+            let overlaps = accepted.iter().any(|a| {
+                let left = hit.0.max(a.0);
+                let right = (hit.0 + hit.1).min(a.0 + a.1);
+                right > left && (right - left) * 2 > hit.1.min(a.1)
+            });
+            if !overlaps {
+                accepted.push(hit);
+            }
+        }
+
+        // 3. 从左到右拼接，间距超过半个字宽的视为不同数字串 (如 "3/20")
+        accepted.sort_by_key(|h| h.0);
+        let avg_w = accepted.iter().map(|h| h.1).sum::<u32>() / accepted.len() as u32;
+        let mut groups: Vec<String> = vec![String::new()];
+        let mut prev_end: Option<u32> = None;
+        for h in &accepted {
+            if let Some(end) = prev_end {
+                if h.0 > end + avg_w / 2 {
+                    groups.push(String::new());
+                }
+            }
+            groups.last_mut().unwrap().push((b'0' + h.3) as char);
+            prev_end = Some(h.0 + h.1);
+        }
+        debug!("🔢 [Digits] 模板识别结果: {:?}", groups);
+        groups.iter().filter_map(|g| g.parse::<i32>().ok()).collect()
+    }
+
+    /// 识别区域内的第一个数字串
+    pub fn recognize(&self, img: &RgbaImage) -> Option<i32> {
+        self.recognize_groups(img).first().copied()
+    }
+}
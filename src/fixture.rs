@@ -0,0 +1,197 @@
+// src/fixture.rs
+use crate::frames::{ScreenProvider, SharedFrame};
+use crate::hardware::{InputCommand, InputLog, MockDriver};
+use crate::human::HumanDriver;
+use crate::input_queue::InputQueue;
+use crate::nav::{NavEngine, NavEventKind};
+use crate::ocr::{OcrBackend, OcrText};
+use image::RgbaImage;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Instant;
+
+// ==========================================
+// 测试夹具：预录截图 + 模拟输入
+// ==========================================
+// 不开游戏也能驱动 NavEngine / TowerDefenseApp：画面来自一组命名的截图 (FixtureScreen)，
+// 文字由截图旁的标注文件给出 (FixtureOcr，结果确定、与系统 OCR 语言包无关)，键鼠命令进 MockDriver 的日志。
+//
+// 夹具目录中每个 <名称>.png 是一幅画面，可选的 <名称>.toml 标注其中的文字 (格式同场景图的文字锚点)：
+//   text = [{ rect = [262, 16, 389, 97], val = "波次3" }]
+// 画面名称与场景 ID 一致时，follow_transitions 会在每次跳转点击后切换到目标场景的画面。
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct FixtureText {
+    pub rect: [i32; 4],
+    pub val: String,
+}
+
+#[derive(Deserialize, Default)]
+struct FixtureTexts {
+    #[serde(default)]
+    text: Vec<FixtureText>,
+}
+
+/// 一幅画面与其中的文字
+pub struct Fixture {
+    pub image: Arc<RgbaImage>,
+    pub texts: Vec<FixtureText>,
+}
+
+/// 按名称切换的预录画面
+#[derive(Default)]
+pub struct FixtureScreen {
+    fixtures: RwLock<BTreeMap<String, Fixture>>,
+    current: RwLock<Option<String>>,
+    /// 每次切换画面加一 (帧序号)
+    seq: AtomicU64,
+}
+
+impl FixtureScreen {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 加载目录中的所有 *.png 及同名的 .toml 文字标注
+    pub fn load_dir(dir: impl AsRef<Path>) -> Result<Self, String> {
+        let dir = dir.as_ref();
+        let screen = Self::new();
+        let entries = fs::read_dir(dir).map_err(|e| format!("无法读取夹具目录 {}: {}", dir.display(), e))?;
+        for path in entries.flatten().map(|e| e.path()) {
+            if path.extension().and_then(|e| e.to_str()) != Some("png") {
+                continue;
+            }
+            let name = path.file_stem().unwrap_or_default().to_string_lossy().to_string();
+            let image = image::open(&path).map_err(|e| format!("无法加载夹具 {}: {}", path.display(), e))?.to_rgba8();
+            let texts = match fs::read_to_string(path.with_extension("toml")) {
+                Ok(content) => toml::from_str::<FixtureTexts>(&content)
+                    .map_err(|e| format!("夹具标注 {} 格式错误: {}", path.with_extension("toml").display(), e))?
+                    .text,
+                Err(_) => Vec::new(),
+            };
+            screen.insert(&name, image, texts);
+        }
+        Ok(screen)
+    }
+
+    pub fn insert(&self, name: &str, image: RgbaImage, texts: Vec<FixtureText>) {
+        if let Ok(mut fixtures) = self.fixtures.write() {
+            fixtures.insert(name.to_string(), Fixture { image: Arc::new(image), texts });
+        }
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        self.fixtures.read().map(|f| f.keys().cloned().collect()).unwrap_or_default()
+    }
+
+    /// 切换到名为 name 的画面；不存在时保持当前画面并返回 false
+    pub fn show(&self, name: &str) -> bool {
+        let exists = self.fixtures.read().map(|f| f.contains_key(name)).unwrap_or(false);
+        if exists {
+            if let Ok(mut current) = self.current.write() {
+                *current = Some(name.to_string());
+                self.seq.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        exists
+    }
+
+    /// 当前画面的名称
+    pub fn current(&self) -> Option<String> {
+        self.current.read().ok()?.clone()
+    }
+
+    /// 当前画面中与 rect 相交的文字 (按标注顺序拼接)
+    pub fn text_in(&self, rect: [i32; 4]) -> String {
+        let Some(name) = self.current() else { return String::new() };
+        let Ok(fixtures) = self.fixtures.read() else { return String::new() };
+        let intersects = |r: &[i32; 4]| r[0] < rect[2] && rect[0] < r[2] && r[1] < rect[3] && rect[1] < r[3];
+        fixtures
+            .get(&name)
+            .map(|f| f.texts.iter().filter(|t| intersects(&t.rect)).map(|t| t.val.as_str()).collect())
+            .unwrap_or_default()
+    }
+
+    /// 每次跳转点击后切换到目标场景的画面 (没有该画面时不变，模拟点击无效)
+    pub fn follow_transitions(self: &Arc<Self>, engine: &NavEngine) {
+        let screen = Arc::clone(self);
+        engine.on_event(move |e| {
+            if let NavEventKind::TransitionClicked { to, .. } = &e.kind {
+                screen.show(to);
+            }
+        });
+    }
+}
+
+impl ScreenProvider for FixtureScreen {
+    fn frame(&self) -> Option<SharedFrame> {
+        let name = self.current()?;
+        let fixtures = self.fixtures.read().ok()?;
+        let image = Arc::clone(&fixtures.get(&name)?.image);
+        Some(SharedFrame { image, seq: self.seq.load(Ordering::Relaxed), captured_at: Instant::now() })
+    }
+}
+
+/// 从 FixtureScreen 的标注读取文字的 OCR 后端
+pub struct FixtureOcr {
+    screen: Arc<FixtureScreen>,
+}
+
+impl FixtureOcr {
+    pub fn new(screen: Arc<FixtureScreen>) -> Self {
+        Self { screen }
+    }
+}
+
+impl OcrBackend for FixtureOcr {
+    fn name(&self) -> &str {
+        "fixture"
+    }
+
+    fn recognize(&self, _image: &image::DynamicImage, rect: [i32; 4]) -> OcrText {
+        let text = self.screen.text_in(rect);
+        let confidence = if text.is_empty() { 0.0 } else { 1.0 };
+        OcrText { text, confidence }
+    }
+
+    /// 标注即结果，不需要预处理
+    fn multi_exposure(&self) -> bool {
+        false
+    }
+}
+
+/// 一套接好夹具的引擎：画面与 OCR 来自 screen，键鼠命令写入 input
+pub struct Harness {
+    pub engine: Arc<NavEngine>,
+    pub driver: Arc<Mutex<HumanDriver>>,
+    pub screen: Arc<FixtureScreen>,
+    pub input: InputLog,
+}
+
+impl Harness {
+    /// 用场景图 map_path 与夹具画面创建引擎；跳转点击后自动切换到目标场景的画面
+    pub fn new(map_path: &str, screen: FixtureScreen) -> Self {
+        let screen = Arc::new(screen);
+        let mock = MockDriver::new();
+        let input = mock.log();
+        let driver = Arc::new(Mutex::new(HumanDriver::new(InputQueue::spawn(Box::new(mock)), 960, 540)));
+        let engine = NavEngine::new(map_path, Arc::clone(&driver))
+            .with_screen(screen.clone())
+            .with_ocr_backend(Box::new(FixtureOcr::new(Arc::clone(&screen))));
+        let engine = Arc::new(engine);
+        screen.follow_transitions(&engine);
+        Self { engine, driver, screen, input }
+    }
+
+    /// 已下发的键鼠命令 (先等输入队列执行完毕)
+    pub fn commands(&self) -> Vec<InputCommand> {
+        if let Ok(d) = self.driver.lock() {
+            d.device.flush();
+        }
+        self.input.lock().map(|l| l.clone()).unwrap_or_default()
+    }
+}
@@ -0,0 +1,193 @@
+// src/frames.rs
+use crate::capture;
+use image::RgbaImage;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex, OnceLock, RwLock};
+use std::thread;
+use std::time::{Duration, Instant};
+use tracing::info;
+
+// ==========================================
+// 共享帧服务
+// ==========================================
+// 场景识别、波次 OCR、放置校验等各自截图，同一秒内经常重复截同一画面。
+// 这里统一持有最近一帧：足够新 (max_age 内) 的请求直接裁剪复用，否则由一个线程截图、其他线程等待后共用，
+// 截屏访问因此串行化。可选的后台线程按固定间隔截图，并把订阅的区域推送给订阅者。
+// NavEngine 通过 ScreenProvider 取帧，测试时可换成预先录好的截图 (见 fixture.rs)。
+
+/// 默认复用时限：点击后的界面变化至少要几百毫秒才稳定，50ms 内的帧可以放心复用
+const DEFAULT_MAX_AGE_MS: u64 = 50;
+
+/// 一帧整屏画面 (多个使用者共享同一份像素)
+#[derive(Clone)]
+pub struct SharedFrame {
+    pub image: Arc<RgbaImage>,
+    /// 帧序号 (从 1 开始递增)
+    pub seq: u64,
+    pub captured_at: Instant,
+}
+
+impl SharedFrame {
+    pub fn crop(&self, rect: [i32; 4]) -> Option<RgbaImage> {
+        crop(&self.image, rect)
+    }
+}
+
+/// 订阅区域的一次推送
+pub struct RegionFrame {
+    pub seq: u64,
+    pub captured_at: Instant,
+    pub image: RgbaImage,
+}
+
+/// 从整屏中裁剪 [x1, y1, x2, y2]，超出画面的部分截掉；完全在画面外时返回 None
+pub fn crop(frame: &RgbaImage, rect: [i32; 4]) -> Option<RgbaImage> {
+    let x = rect[0].max(0) as u32;
+    let y = rect[1].max(0) as u32;
+    let x2 = (rect[2].max(rect[0] + 1) as u32).min(frame.width());
+    let y2 = (rect[3].max(rect[1] + 1) as u32).min(frame.height());
+    if x >= x2 || y >= y2 {
+        return None;
+    }
+    Some(image::imageops::crop_imm(frame, x, y, x2 - x, y2 - y).to_image())
+}
+
+/// 画面来源：NavEngine 的场景识别、OCR 与区域截图都从这里取帧
+pub trait ScreenProvider: Send + Sync {
+    /// 当前画面 (整屏)
+    fn frame(&self) -> Option<SharedFrame>;
+
+    /// 当前画面中的区域 [x1, y1, x2, y2]
+    fn crop(&self, rect: [i32; 4]) -> Option<RgbaImage> {
+        self.frame()?.crop(rect)
+    }
+}
+
+pub struct FrameService {
+    /// 复用时限 (ms)
+    max_age_ms: AtomicU64,
+    latest: RwLock<Option<SharedFrame>>,
+    /// 截图互斥，内含最近的帧序号
+    capture_lock: Mutex<u64>,
+    subscribers: Mutex<Vec<([i32; 4], Sender<RegionFrame>)>>,
+    running: AtomicBool,
+}
+
+impl FrameService {
+    pub fn new(max_age: Duration) -> Self {
+        Self {
+            max_age_ms: AtomicU64::new(max_age.as_millis() as u64),
+            latest: RwLock::new(None),
+            capture_lock: Mutex::new(0),
+            subscribers: Mutex::new(Vec::new()),
+            running: AtomicBool::new(false),
+        }
+    }
+
+    pub fn set_max_age(&self, max_age: Duration) {
+        self.max_age_ms.store(max_age.as_millis() as u64, Ordering::Relaxed);
+    }
+
+    /// 最近一帧 (不截图)
+    pub fn latest(&self) -> Option<SharedFrame> {
+        self.latest.read().ok()?.clone()
+    }
+
+    /// 不超过复用时限的一帧；没有则截图
+    pub fn frame(&self) -> Option<SharedFrame> {
+        self.frame_within(Duration::from_millis(self.max_age_ms.load(Ordering::Relaxed)))
+    }
+
+    /// 不超过 max_age 的一帧；没有则截图 (Duration::ZERO 强制截新帧)
+    pub fn frame_within(&self, max_age: Duration) -> Option<SharedFrame> {
+        if let Some(f) = self.fresh(max_age) {
+            return Some(f);
+        }
+        let mut seq = self.capture_lock.lock().ok()?;
+        // 等锁期间其他线程可能已经截好
+        if let Some(f) = self.fresh(max_age) {
+            return Some(f);
+        }
+        let image = capture::capture_screen()?;
+        *seq += 1;
+        let frame = SharedFrame { image: Arc::new(image), seq: *seq, captured_at: Instant::now() };
+        if let Ok(mut latest) = self.latest.write() {
+            *latest = Some(frame.clone());
+        }
+        self.publish(&frame);
+        Some(frame)
+    }
+
+    fn fresh(&self, max_age: Duration) -> Option<SharedFrame> {
+        self.latest().filter(|f| !max_age.is_zero() && f.captured_at.elapsed() <= max_age)
+    }
+
+    /// 从共享帧中裁剪区域
+    pub fn crop(&self, rect: [i32; 4]) -> Option<RgbaImage> {
+        self.frame()?.crop(rect)
+    }
+
+    /// 订阅区域：之后每截一帧都会推送该区域的裁剪 (接收端丢弃后自动退订)
+    pub fn subscribe(&self, rect: [i32; 4]) -> Receiver<RegionFrame> {
+        let (tx, rx) = mpsc::channel();
+        if let Ok(mut subs) = self.subscribers.lock() {
+            subs.push((rect, tx));
+        }
+        rx
+    }
+
+    fn publish(&self, frame: &SharedFrame) {
+        let Ok(mut subs) = self.subscribers.lock() else { return };
+        subs.retain(|(rect, tx)| match frame.crop(*rect) {
+            Some(image) => tx.send(RegionFrame { seq: frame.seq, captured_at: frame.captured_at, image }).is_ok(),
+            None => true,
+        });
+    }
+
+    /// 后台按 interval 截图 (驱动区域订阅，也让按需请求总能命中新帧)；重复调用无效
+    pub fn spawn(self: &Arc<Self>, interval: Duration) {
+        if interval.is_zero() || self.running.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        let service = Arc::clone(self);
+        thread::spawn(move || loop {
+            // 其他线程刚截过就不重复截
+            service.frame_within(interval / 2);
+            thread::sleep(interval);
+        });
+    }
+}
+
+impl ScreenProvider for FrameService {
+    fn frame(&self) -> Option<SharedFrame> {
+        FrameService::frame(self)
+    }
+}
+
+static SERVICE: OnceLock<Arc<FrameService>> = OnceLock::new();
+
+/// 全局帧服务 (nav、塔防、通知截图共用)
+pub fn service() -> &'static Arc<FrameService> {
+    SERVICE.get_or_init(|| Arc::new(FrameService::new(Duration::from_millis(DEFAULT_MAX_AGE_MS))))
+}
+
+/// 按 [general] frame_interval_ms / frame_max_age_ms 配置全局帧服务
+pub fn configure(interval_ms: u64, max_age_ms: u64) {
+    let service = service();
+    service.set_max_age(Duration::from_millis(max_age_ms));
+    if interval_ms > 0 {
+        info!("🎞️ [帧服务] 后台截图间隔 {}ms，复用时限 {}ms", interval_ms, max_age_ms);
+        service.spawn(Duration::from_millis(interval_ms));
+    }
+}
+
+/// 整屏画面 (复用共享帧)
+pub fn capture_screen() -> Option<RgbaImage> {
+    service().frame().map(|f| (*f.image).clone())
+}
+
+/// 区域画面 (从共享帧裁剪)
+pub fn capture_rect(rect: [i32; 4]) -> Option<RgbaImage> {
+    service().crop(rect)
+}
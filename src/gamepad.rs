@@ -0,0 +1,127 @@
+// src/gamepad.rs
+use crate::hardware::GamepadState;
+use crate::input_queue::InputQueue;
+use rand::Rng;
+use std::thread;
+use std::time::Duration;
+
+// ==========================================
+// 手柄输入层 (与 HumanDriver 平级)
+// ==========================================
+// 业务模块可按需选择键鼠 (HumanDriver) 或手柄 (GamepadDriver)，两者共用同一个输入队列。
+// 需要硬件盒子固件支持 CAP_GAMEPAD，不支持时所有操作被忽略。
+
+pub const BTN_DPAD_UP: u16 = 0x0001;
+pub const BTN_DPAD_DOWN: u16 = 0x0002;
+pub const BTN_DPAD_LEFT: u16 = 0x0004;
+pub const BTN_DPAD_RIGHT: u16 = 0x0008;
+pub const BTN_START: u16 = 0x0010;
+pub const BTN_BACK: u16 = 0x0020;
+pub const BTN_LS: u16 = 0x0040;
+pub const BTN_RS: u16 = 0x0080;
+pub const BTN_LB: u16 = 0x0100;
+pub const BTN_RB: u16 = 0x0200;
+/// 扳机按数字键处理，占用 XInput 未使用的两位
+pub const BTN_LT: u16 = 0x0400;
+pub const BTN_RT: u16 = 0x0800;
+pub const BTN_A: u16 = 0x1000;
+pub const BTN_B: u16 = 0x2000;
+pub const BTN_X: u16 = 0x4000;
+pub const BTN_Y: u16 = 0x8000;
+
+/// 配置文件中的按键名 ("a", "lb", "start", "up" ...) 转为按键位
+pub fn button_from_name(name: &str) -> Option<u16> {
+    let b = match name.trim().to_lowercase().as_str() {
+        "a" => BTN_A,
+        "b" => BTN_B,
+        "x" => BTN_X,
+        "y" => BTN_Y,
+        "lb" => BTN_LB,
+        "rb" => BTN_RB,
+        "lt" => BTN_LT,
+        "rt" => BTN_RT,
+        "ls" => BTN_LS,
+        "rs" => BTN_RS,
+        "start" | "menu" => BTN_START,
+        "back" | "view" => BTN_BACK,
+        "up" => BTN_DPAD_UP,
+        "down" => BTN_DPAD_DOWN,
+        "left" => BTN_DPAD_LEFT,
+        "right" => BTN_DPAD_RIGHT,
+        _ => return None,
+    };
+    Some(b)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stick {
+    Left,
+    Right,
+}
+
+pub struct GamepadDriver {
+    device: InputQueue,
+    /// 当前保持的状态，每次变化都整帧下发
+    state: GamepadState,
+}
+
+impl GamepadDriver {
+    pub fn new(device: InputQueue) -> Self {
+        Self { device, state: GamepadState::default() }
+    }
+
+    /// 底层驱动是否支持手柄
+    pub fn available(&self) -> bool {
+        self.device.supports_gamepad()
+    }
+
+    fn send(&self) {
+        self.device.gamepad(self.state);
+    }
+
+    pub fn press(&mut self, buttons: u16) {
+        self.state.buttons |= buttons;
+        self.send();
+    }
+
+    pub fn release(&mut self, buttons: u16) {
+        self.state.buttons &= !buttons;
+        self.send();
+    }
+
+    /// 按住按键 ms 毫秒；ms 为 0 时按拟人化短按处理
+    pub fn tap(&mut self, buttons: u16, ms: u64) {
+        self.press(buttons);
+        let hold = if ms > 0 { ms } else { rand::thread_rng().gen_range(50..110) };
+        thread::sleep(Duration::from_millis(hold));
+        self.release(buttons);
+    }
+
+    /// 设置摇杆位置 (-1.0 ~ 1.0，y 向上为正)
+    pub fn stick(&mut self, stick: Stick, x: f32, y: f32) {
+        let to_axis = |v: f32| (v.clamp(-1.0, 1.0) * 127.0).round() as i8;
+        match stick {
+            Stick::Left => { self.state.lx = to_axis(x); self.state.ly = to_axis(y); }
+            Stick::Right => { self.state.rx = to_axis(x); self.state.ry = to_axis(y); }
+        }
+        self.send();
+    }
+
+    /// 推动摇杆保持 ms 毫秒后回中，起止各有一小段渐变
+    pub fn tilt(&mut self, stick: Stick, x: f32, y: f32, ms: u64) {
+        const RAMP_STEPS: u32 = 4;
+        for i in 1..=RAMP_STEPS {
+            let k = i as f32 / RAMP_STEPS as f32;
+            self.stick(stick, x * k, y * k);
+            thread::sleep(Duration::from_millis(15));
+        }
+        thread::sleep(Duration::from_millis(ms));
+        self.stick(stick, 0.0, 0.0);
+    }
+
+    /// 松开所有按键、摇杆回中
+    pub fn release_all(&mut self) {
+        self.state = GamepadState::default();
+        self.send();
+    }
+}
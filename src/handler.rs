@@ -0,0 +1,144 @@
+// src/handler.rs
+//
+// 场景处理器注册表：导航到带 handler 的场景 (NavResult::Handover) 后，按 handler 名称分发给已注册的处理器。
+// 主程序注册塔防 ("td")、日活 ("daily") 与脚本 (".rhai")，库的使用者可注册自己的处理器接管新的玩法。
+use crate::config::GeneralConfig;
+use crate::control::RunControl;
+use crate::daily_routine::DailyRoutineApp;
+use crate::human::HumanDriver;
+use crate::nav::NavEngine;
+use crate::notify::{self, NotifyEvent};
+use crate::tower_defense::{RunSummary, TowerDefenseApp};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tracing::{error, info, warn};
+
+/// 处理器执行结果
+#[derive(Debug, Clone)]
+pub enum HandlerOutcome {
+    /// 完成一局：计入局数，之后返回大厅；塔防附带本局摘要
+    Run(Option<RunSummary>),
+    /// 一次性任务 (如日活)：不计入局数
+    Done,
+    /// 收到停止信号提前结束
+    Stopped(Option<RunSummary>),
+}
+
+pub trait SceneHandler {
+    /// 接管场景 scene_id；name 为场景的 handler 字段 (脚本处理器据此找到脚本文件)
+    fn handle(&mut self, scene_id: &str, name: &str) -> HandlerOutcome;
+}
+
+/// 闭包也可直接注册为处理器
+impl<F: FnMut(&str, &str) -> HandlerOutcome> SceneHandler for F {
+    fn handle(&mut self, scene_id: &str, name: &str) -> HandlerOutcome {
+        self(scene_id, name)
+    }
+}
+
+pub struct HandlerRegistry {
+    /// 以 "." 开头的名称按后缀匹配 (如 ".rhai" 接管所有 handler = "xxx.rhai" 的场景)
+    handlers: HashMap<String, Box<dyn SceneHandler>>,
+    /// 场景未填写 handler 或名称未注册时使用的处理器
+    default: String,
+}
+
+impl HandlerRegistry {
+    pub fn new(default: &str) -> Self {
+        Self { handlers: HashMap::new(), default: default.to_string() }
+    }
+
+    /// 注册处理器；同名时替换原有处理器
+    pub fn register(&mut self, name: &str, handler: Box<dyn SceneHandler>) -> &mut Self {
+        self.handlers.insert(name.to_string(), handler);
+        self
+    }
+
+    /// 已注册的处理器名称 (按名称排序)
+    pub fn names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.handlers.keys().map(String::as_str).collect();
+        names.sort_unstable();
+        names
+    }
+
+    /// 名称对应的注册键：完全匹配优先，其次后缀匹配，最后退回默认处理器
+    fn resolve(&self, name: &str) -> Option<String> {
+        if self.handlers.contains_key(name) {
+            return Some(name.to_string());
+        }
+        let suffix = self.handlers.keys().filter(|k| k.starts_with('.') && name.ends_with(k.as_str())).max_by_key(|k| k.len());
+        if let Some(key) = suffix {
+            return Some(key.clone());
+        }
+        if self.handlers.contains_key(&self.default) {
+            warn!("⚠️ [路由] 未注册的处理器 '{}'，改用默认处理器 '{}'", name, self.default);
+            return Some(self.default.clone());
+        }
+        None
+    }
+
+    /// 把场景交给对应的处理器
+    pub fn dispatch(&mut self, scene_id: &str, handler: Option<&str>) -> HandlerOutcome {
+        let name = handler.unwrap_or(&self.default).to_string();
+        let Some(key) = self.resolve(&name) else {
+            error!("❌ [路由] 场景 [{}] 的处理器 '{}' 未注册 (已注册: {})", scene_id, name, self.names().join(", "));
+            return HandlerOutcome::Done;
+        };
+        info!("🧭 [路由] [{}] 交给处理器 '{}'", scene_id, name);
+        let handler = self.handlers.get_mut(&key).expect("resolve 返回的键必定存在");
+        handler.handle(scene_id, &name)
+    }
+}
+
+// ==========================================
+// 内置处理器
+// ==========================================
+
+/// 塔防处理器：按场景 id 加载地形 / 策略 / 界面参数，跑完一局
+pub struct TdHandler {
+    general: GeneralConfig,
+    driver: Arc<Mutex<HumanDriver>>,
+    nav: Arc<NavEngine>,
+    control: RunControl,
+}
+
+impl TdHandler {
+    pub fn new(general: GeneralConfig, driver: Arc<Mutex<HumanDriver>>, nav: Arc<NavEngine>) -> Self {
+        Self { general, driver, nav, control: RunControl::new() }
+    }
+
+    /// 共享运行控制：停止信号会中断正在进行的一局
+    pub fn with_control(mut self, control: RunControl) -> Self {
+        self.control = control;
+        self
+    }
+}
+
+impl SceneHandler for TdHandler {
+    fn handle(&mut self, scene_id: &str, _name: &str) -> HandlerOutcome {
+        let general = &self.general;
+        let mut td_app = TowerDefenseApp::new(Arc::clone(&self.driver), Arc::clone(&self.nav))
+            .with_control(self.control.clone())
+            .with_profile(&general.profile);
+
+        let map_file = general.terrain_path(scene_id);
+        let strategy_file = general.strategy_path(scene_id);
+        let traps_file = general.traps_path();
+        td_app.load_config(&general.td_config, scene_id);
+
+        info!("📂 加载配置: {} | {}", map_file, strategy_file);
+        let summary = td_app.run(&map_file, &strategy_file, &traps_file);
+        if summary.stopped {
+            return HandlerOutcome::Stopped(Some(summary));
+        }
+        notify::send(NotifyEvent::RunCompleted, format!("[{}] {}", scene_id, summary));
+        HandlerOutcome::Run(Some(summary))
+    }
+}
+
+impl SceneHandler for DailyRoutineApp {
+    fn handle(&mut self, _scene_id: &str, _name: &str) -> HandlerOutcome {
+        self.run();
+        HandlerOutcome::Done
+    }
+}
@@ -1,13 +1,19 @@
+use crate::controllers::{Ch9329Driver, KmboxNetDriver};
 use byteorder::{LittleEndian, WriteBytesExt};
 // ✨ Added Axis to imports
+#[cfg(feature = "windows")]
 use enigo::{
     Direction, Enigo, Key, Keyboard, Mouse, Settings, Coordinate,
-    Button, Axis 
+    Button, Axis
 };
+use serde::{Deserialize, Serialize};
 use serialport::SerialPort;
+use std::collections::HashMap;
 use std::io::Write;
+use std::sync::{Arc, Mutex};
 use std::thread;
 use std::time::Duration;
+use tracing::{debug, info, warn};
 
 // ==========================================
 // 1. Common Interface (Trait)
@@ -21,6 +27,115 @@ pub trait InputDriver: Send + Sync {
     fn key_down(&mut self, keycode: u8, modifier: u8);
     fn key_up(&mut self);
     fn switch_identity(&mut self, index: u8);
+
+    /// 水平滚轮；不支持的驱动忽略
+    fn mouse_hwheel(&mut self, _delta: i8) {}
+
+    /// 松开所有按键、鼠标按钮与手柄 (紧急停止)
+    fn release_all(&mut self) {
+        self.mouse_up();
+        self.key_up();
+        if self.supports_gamepad() {
+            self.gamepad(GamepadState::default());
+        }
+    }
+
+    /// 是否支持手柄报告
+    fn supports_gamepad(&self) -> bool {
+        false
+    }
+
+    /// 发送一帧手柄状态；不支持手柄的驱动忽略
+    fn gamepad(&mut self, _state: GamepadState) {}
+
+    /// 硬件盒子的固件信息；非硬件驱动返回 None
+    fn firmware(&self) -> Option<FirmwareInfo> {
+        None
+    }
+
+    /// 链路指标 (写入延迟、吞吐、丢失心跳)；软件驱动返回全 0
+    fn metrics(&self) -> InputMetrics {
+        InputMetrics::default()
+    }
+}
+
+/// 输入链路指标快照
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct InputMetrics {
+    /// 已发送的命令帧总数 (含心跳)
+    pub commands: u64,
+    /// 写入失败的帧数
+    pub write_errors: u64,
+    /// 单帧写入+刷新耗时的平均值/最大值 (毫秒)，串口无回包时以此近似往返延迟
+    pub avg_latency_ms: f32,
+    pub max_latency_ms: f32,
+    /// 最近 METRICS_WINDOW 内的每秒命令数
+    pub commands_per_sec: f32,
+    pub heartbeats_sent: u64,
+    /// 写入失败或间隔超过 2 倍周期 (被阻塞) 的心跳数
+    pub heartbeats_dropped: u64,
+    /// 连续未被确认的心跳数 (写入失败，或 v2 固件未回 ACK)；收到确认后清零
+    pub missed_acks: u32,
+}
+
+impl std::fmt::Display for InputMetrics {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} 帧 ({:.1}/s) | 延迟 avg {:.2}ms max {:.2}ms | 写入失败 {} | 心跳 {}/丢失 {}",
+            self.commands, self.commands_per_sec, self.avg_latency_ms, self.max_latency_ms,
+            self.write_errors, self.heartbeats_sent, self.heartbeats_dropped
+        )
+    }
+}
+
+/// 吞吐统计窗口
+const METRICS_WINDOW: Duration = Duration::from_secs(5);
+/// 心跳周期 (与 main.rs 心跳线程一致)
+const HEARTBEAT_PERIOD: Duration = Duration::from_secs(1);
+
+#[derive(Default)]
+struct LinkStats {
+    metrics: InputMetrics,
+    total_latency_ms: f64,
+    recent: std::collections::VecDeque<std::time::Instant>,
+    last_heartbeat: Option<std::time::Instant>,
+}
+
+impl LinkStats {
+    fn record_write(&mut self, elapsed: Duration, ok: bool) {
+        let now = std::time::Instant::now();
+        let ms = elapsed.as_secs_f32() * 1000.0;
+        let m = &mut self.metrics;
+        m.commands += 1;
+        if !ok {
+            m.write_errors += 1;
+        }
+        self.total_latency_ms += ms as f64;
+        m.avg_latency_ms = (self.total_latency_ms / m.commands as f64) as f32;
+        m.max_latency_ms = m.max_latency_ms.max(ms);
+        self.recent.push_back(now);
+        while self.recent.front().is_some_and(|t| now.duration_since(*t) > METRICS_WINDOW) {
+            self.recent.pop_front();
+        }
+    }
+
+    fn record_heartbeat(&mut self, ok: bool, acked: bool) {
+        let now = std::time::Instant::now();
+        self.metrics.heartbeats_sent += 1;
+        let late = self.last_heartbeat.is_some_and(|t| now.duration_since(t) > HEARTBEAT_PERIOD * 2);
+        if !ok || late {
+            self.metrics.heartbeats_dropped += 1;
+        }
+        self.metrics.missed_acks = if ok && acked { 0 } else { self.metrics.missed_acks + 1 };
+        self.last_heartbeat = Some(now);
+    }
+
+    fn snapshot(&self) -> InputMetrics {
+        let mut m = self.metrics;
+        m.commands_per_sec = self.recent.len() as f32 / METRICS_WINDOW.as_secs_f32();
+        m
+    }
 }
 
 // ==========================================
@@ -35,18 +150,87 @@ enum EventType {
     MouseRel = 0x02,
     MouseAbs = 0x03,
     System = 0x04,
+    /// 手柄报告：按键位 u16 + 左右摇杆 (i8 x4)
+    Gamepad = 0x05,
 }
 
 #[repr(u8)]
 enum SystemCmd {
     SetId = 0x10,
+    /// 查询固件版本，v2+ 固件回复 [HEAD, System, 0x20, major, minor, caps, 0, 0, 0, 0, TAIL]
+    GetVersion = 0x20,
     Heartbeat = 0xFF,
 }
 
+/// 能力位：帧尾前追加 1 字节 XOR 校验 (对类型字节到延迟字段)
+pub const CAP_CHECKSUM: u8 = 0x01;
+/// 能力位：支持 SetId 切换 USB 身份
+pub const CAP_IDENTITY: u8 = 0x02;
+/// 能力位：支持手柄报告 (EventType::Gamepad)
+pub const CAP_GAMEPAD: u8 = 0x04;
+
+/// 一帧完整的手柄状态 (XInput 布局)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct GamepadState {
+    /// 按键位，见 gamepad::BTN_*
+    pub buttons: u16,
+    /// 摇杆 -127 ~ 127，y 轴向上为正
+    pub lx: i8,
+    pub ly: i8,
+    pub rx: i8,
+    pub ry: i8,
+}
+
+/// 本程序支持的协议主版本范围
+pub const SUPPORTED_PROTOCOL: std::ops::RangeInclusive<u8> = 1..=2;
+
+/// 握手得到的固件信息
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FirmwareInfo {
+    pub major: u8,
+    pub minor: u8,
+    pub caps: u8,
+    /// 固件未响应版本查询 (旧固件)，按 v1 协议通信
+    pub legacy: bool,
+}
+
+impl FirmwareInfo {
+    /// 不支持版本查询的旧固件：11 字节帧、无校验、支持 SetId
+    pub const LEGACY: FirmwareInfo = FirmwareInfo { major: 1, minor: 0, caps: CAP_IDENTITY, legacy: true };
+
+    pub fn has(&self, cap: u8) -> bool {
+        self.caps & cap != 0
+    }
+
+    pub fn is_compatible(&self) -> bool {
+        SUPPORTED_PROTOCOL.contains(&self.major)
+    }
+}
+
+impl std::fmt::Display for FirmwareInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "v{}.{} (caps 0x{:02X})", self.major, self.minor, self.caps)?;
+        if self.legacy {
+            write!(f, " [旧固件，未响应版本查询]")?;
+        }
+        Ok(())
+    }
+}
+
+/// 硬件盒子常用的 USB 串口芯片 (VID, PID)，自动探测时优先尝试
+const KNOWN_USB_IDS: &[(u16, u16)] = &[
+    (0x1A86, 0x7523), // CH340
+    (0x1A86, 0x55D3), // CH343
+    (0x10C4, 0xEA60), // CP210x
+    (0x303A, 0x1001), // ESP32-S3 原生 USB
+];
+
 pub struct HardwareDriver {
     port: Box<dyn SerialPort>,
     pub screen_w: u16,
     pub screen_h: u16,
+    firmware: FirmwareInfo,
+    stats: LinkStats,
 }
 
 impl HardwareDriver {
@@ -56,20 +240,112 @@ impl HardwareDriver {
             .open()
             .map_err(|e| format!("无法打开串口 {}: {}", port_name, e))?;
 
-        Ok(Self { port, screen_w, screen_h })
+        let mut drv = Self { port, screen_w, screen_h, firmware: FirmwareInfo::LEGACY, stats: LinkStats::default() };
+        drv.firmware = drv.handshake();
+        info!("🔌 [串口] {} 固件 {}", port_name, drv.firmware);
+        Ok(drv)
     }
 
-    fn send_raw(&mut self, event_type: EventType, b: [u8; 6], delay_ms: u16) {
-        let mut frame = Vec::with_capacity(11);
+    /// 查询固件版本与能力；超时或回复不合法时视为旧固件
+    fn handshake(&mut self) -> FirmwareInfo {
+        let _ = self.port.clear(serialport::ClearBuffer::Input);
+        let query = [FRAME_HEAD, EventType::System as u8, SystemCmd::GetVersion as u8, 0, 0, 0, 0, 0, 0, 0, FRAME_TAIL];
+        if self.port.write_all(&query).and_then(|_| self.port.flush()).is_err() {
+            return FirmwareInfo::LEGACY;
+        }
+
+        let mut reply = [0u8; 11];
+        let mut got = 0;
+        let deadline = std::time::Instant::now() + Duration::from_millis(300);
+        while got < reply.len() && std::time::Instant::now() < deadline {
+            match std::io::Read::read(&mut self.port, &mut reply[got..]) {
+                Ok(n) if n > 0 => got += n,
+                _ => {}
+            }
+        }
+        let valid = got == reply.len()
+            && reply[0] == FRAME_HEAD
+            && reply[1] == EventType::System as u8
+            && reply[2] == SystemCmd::GetVersion as u8
+            && reply[10] == FRAME_TAIL;
+        if !valid {
+            return FirmwareInfo::LEGACY;
+        }
+        FirmwareInfo { major: reply[3], minor: reply[4], caps: reply[5], legacy: false }
+    }
+
+    /// 枚举串口并自动连接硬件盒子，返回驱动及端口名。
+    /// 先尝试 VID/PID 命中的端口，再尝试其余 USB 串口；能打开并写入心跳帧即视为连接成功。
+    pub fn discover(baud_rate: u32, screen_w: u16, screen_h: u16) -> Result<(Self, String), String> {
+        let ports = serialport::available_ports().map_err(|e| format!("无法枚举串口: {}", e))?;
+        let mut candidates: Vec<(bool, String)> = ports
+            .into_iter()
+            .filter_map(|p| match p.port_type {
+                serialport::SerialPortType::UsbPort(usb) => {
+                    Some((KNOWN_USB_IDS.contains(&(usb.vid, usb.pid)), p.port_name))
+                }
+                _ => None,
+            })
+            .collect();
+        if candidates.is_empty() {
+            return Err("未发现任何 USB 串口设备".to_string());
+        }
+        // 已知芯片排在前面
+        candidates.sort_by_key(|(known, _)| !*known);
+
+        for (known, name) in candidates {
+            debug!("🔍 [串口探测] 尝试 {}{}", name, if known { " (已知芯片)" } else { "" });
+            match Self::new(&name, baud_rate, screen_w, screen_h) {
+                Ok(mut drv) => {
+                    if drv.probe() {
+                        info!("🔌 [串口探测] 已连接硬件盒子: {}", name);
+                        return Ok((drv, name));
+                    }
+                }
+                Err(e) => debug!("   ↳ {}", e),
+            }
+        }
+        Err("未找到可用的硬件盒子".to_string())
+    }
+
+    /// 握手：发送心跳帧，能完整写入即认为设备在线
+    fn probe(&mut self) -> bool {
+        let frame = [FRAME_HEAD, EventType::System as u8, SystemCmd::Heartbeat as u8, 0, 0, 0, 0, 0, 0, 0, FRAME_TAIL];
+        self.port.write_all(&frame).and_then(|_| self.port.flush()).is_ok()
+    }
+
+    /// v2 固件会原样回显心跳帧
+    fn read_heartbeat_ack(&mut self) -> bool {
+        let mut reply = [0u8; 11];
+        let mut got = 0;
+        let deadline = std::time::Instant::now() + Duration::from_millis(50);
+        while got < reply.len() && std::time::Instant::now() < deadline {
+            match std::io::Read::read(&mut self.port, &mut reply[got..]) {
+                Ok(n) if n > 0 => got += n,
+                _ => {}
+            }
+        }
+        got >= 3 && reply[0] == FRAME_HEAD && reply[1] == EventType::System as u8 && reply[2] == SystemCmd::Heartbeat as u8
+    }
+
+    /// 发送一帧，返回是否写入成功
+    fn send_raw(&mut self, event_type: EventType, b: [u8; 6], delay_ms: u16) -> bool {
+        let mut frame = Vec::with_capacity(12);
         frame.push(FRAME_HEAD);
         frame.push(event_type as u8);
         frame.extend_from_slice(&b);
         frame.write_u16::<LittleEndian>(delay_ms).unwrap();
+        if self.firmware.has(CAP_CHECKSUM) {
+            let sum = frame[1..].iter().fold(0u8, |acc, v| acc ^ v);
+            frame.push(sum);
+        }
         frame.push(FRAME_TAIL);
 
-        let _ = self.port.write_all(&frame);
-        let _ = self.port.flush();
+        let started = std::time::Instant::now();
+        let ok = self.port.write_all(&frame).and_then(|_| self.port.flush()).is_ok();
+        self.stats.record_write(started.elapsed(), ok);
         thread::sleep(Duration::from_millis(4));
+        ok
     }
 }
 
@@ -79,10 +355,37 @@ impl InputDriver for HardwareDriver {
     fn heartbeat(&mut self) {
         let mut b = [0u8; 6];
         b[0] = SystemCmd::Heartbeat as u8;
-        self.send_raw(EventType::System, b, 0);
+        let ok = self.send_raw(EventType::System, b, 0);
+        // 旧固件不回 ACK，写入成功即视为确认
+        let acked = ok && (self.firmware.legacy || self.read_heartbeat_ack());
+        self.stats.record_heartbeat(ok, acked);
+    }
+
+    fn metrics(&self) -> InputMetrics {
+        self.stats.snapshot()
+    }
+
+    fn firmware(&self) -> Option<FirmwareInfo> {
+        Some(self.firmware)
+    }
+
+    fn supports_gamepad(&self) -> bool {
+        self.firmware.has(CAP_GAMEPAD)
+    }
+
+    fn gamepad(&mut self, state: GamepadState) {
+        if !self.supports_gamepad() {
+            return;
+        }
+        let [b0, b1] = state.buttons.to_le_bytes();
+        self.send_raw(EventType::Gamepad, [b0, b1, state.lx as u8, state.ly as u8, state.rx as u8, state.ry as u8], 0);
     }
 
     fn switch_identity(&mut self, index: u8) {
+        if !self.firmware.has(CAP_IDENTITY) {
+            warn!("⚠️ [串口] 固件 {} 不支持切换身份，已忽略", self.firmware);
+            return;
+        }
         let mut b = [0u8; 6];
         b[0] = SystemCmd::SetId as u8;
         b[1] = index;
@@ -148,23 +451,32 @@ impl InputDriver for HardwareDriver {
 // ==========================================
 // 3. Software Driver (Software / Enigo 0.6.1)
 // ==========================================
+// Windows 上经 SendInput 注入；Linux 的软件输入见 uinput::UinputDriver
+#[cfg(feature = "windows")]
 pub struct SoftwareDriver {
     enigo: Enigo,
     pub screen_w: u16,
     pub screen_h: u16,
     last_key: Option<Key>,
+    /// 以扫描码按下的键与修饰键 (key_up 时释放)
+    held_scan: Vec<u16>,
 }
 
+#[cfg(feature = "windows")]
 unsafe impl Sync for SoftwareDriver {}
 
+#[cfg(feature = "windows")]
 impl SoftwareDriver {
-    pub fn new(screen_w: u16, screen_h: u16) -> Self {
-        Self {
-            enigo: Enigo::new(&Settings::default()).unwrap(),
+    pub fn new(screen_w: u16, screen_h: u16) -> Result<Self, String> {
+        let enigo = Enigo::new(&Settings::default())
+            .map_err(|e| format!("无法初始化软件输入 (SendInput): {}", e))?;
+        Ok(Self {
+            enigo,
             screen_w,
             screen_h,
             last_key: None,
-        }
+            held_scan: Vec::new(),
+        })
     }
 
     fn hid_to_enigo(&self, hid: u8) -> Option<Key> {
@@ -200,6 +512,7 @@ impl SoftwareDriver {
     }
 }
 
+#[cfg(feature = "windows")]
 impl InputDriver for SoftwareDriver {
     fn heartbeat(&mut self) {}
     fn switch_identity(&mut self, _index: u8) {}
@@ -216,6 +529,10 @@ impl InputDriver for SoftwareDriver {
         }
     }
 
+    fn mouse_hwheel(&mut self, delta: i8) {
+        let _ = self.enigo.scroll(delta as i32, Axis::Horizontal);
+    }
+
     fn mouse_down(&mut self, left: bool, right: bool) {
         if left { let _ = self.enigo.button(Button::Left, Direction::Press); }
         if right { let _ = self.enigo.button(Button::Right, Direction::Press); }
@@ -227,11 +544,21 @@ impl InputDriver for SoftwareDriver {
     }
 
     fn key_down(&mut self, keycode: u8, modifier: u8) {
-        if (modifier & 0x02) != 0 || (modifier & 0x20) != 0 {
-            let _ = self.enigo.key(Key::Shift, Direction::Press);
+        // 修饰键位 bit i 对应 HID 0xE0 + i
+        for bit in 0..8u8 {
+            if modifier & (1 << bit) != 0 {
+                if let Some(sc) = hid_to_scancode(0xE0 + bit) {
+                    let _ = self.enigo.raw(sc, Direction::Press);
+                    self.held_scan.push(sc);
+                }
+            }
         }
 
-        if let Some(key) = self.hid_to_enigo(keycode) {
+        // 优先按扫描码注入物理键位 (与键盘布局无关)，无对应扫描码时回退到虚拟键
+        if let Some(sc) = hid_to_scancode(keycode) {
+            let _ = self.enigo.raw(sc, Direction::Press);
+            self.held_scan.push(sc);
+        } else if let Some(key) = self.hid_to_enigo(keycode) {
             let _ = self.enigo.key(key, Direction::Press);
             self.last_key = Some(key);
         }
@@ -242,16 +569,648 @@ impl InputDriver for SoftwareDriver {
             let _ = self.enigo.key(key, Direction::Release);
             self.last_key = None;
         }
-        let _ = self.enigo.key(Key::Shift, Direction::Release);
+        while let Some(sc) = self.held_scan.pop() {
+            let _ = self.enigo.raw(sc, Direction::Release);
+        }
+    }
+}
+
+// ==========================================
+// 4. Null Driver (Dry-Run)
+// ==========================================
+/// 不产生任何输入，只打印动作；用于无硬件盒子/无桌面会话时安全运行
+pub struct NullDriver {
+    /// 是否打印每个动作
+    pub verbose: bool,
+}
+
+impl NullDriver {
+    pub fn new(verbose: bool) -> Self {
+        Self { verbose }
+    }
+
+    fn log(&self, action: std::fmt::Arguments) {
+        if self.verbose {
+            info!("🫥 [NullDriver] {}", action);
+        }
+    }
+}
+
+impl InputDriver for NullDriver {
+    fn heartbeat(&mut self) {}
+    fn switch_identity(&mut self, index: u8) {
+        self.log(format_args!("switch_identity({})", index));
+    }
+
+    fn mouse_abs(&mut self, x: u16, y: u16) {
+        self.log(format_args!("mouse_abs({}, {})", x, y));
+    }
+
+    fn mouse_move(&mut self, dx: i32, dy: i32, wheel: i8) {
+        if wheel != 0 {
+            self.log(format_args!("mouse_move({}, {}, wheel {})", dx, dy, wheel));
+        }
+    }
+
+    fn mouse_hwheel(&mut self, delta: i8) {
+        self.log(format_args!("mouse_hwheel({})", delta));
+    }
+
+    fn mouse_down(&mut self, left: bool, right: bool) {
+        self.log(format_args!("mouse_down(left {}, right {})", left, right));
+    }
+
+    fn mouse_up(&mut self) {
+        self.log(format_args!("mouse_up"));
+    }
+
+    fn key_down(&mut self, keycode: u8, modifier: u8) {
+        self.log(format_args!("key_down(0x{:02X}, mod 0x{:02X})", keycode, modifier));
+    }
+
+    fn key_up(&mut self) {
+        self.log(format_args!("key_up"));
+    }
+
+    fn supports_gamepad(&self) -> bool {
+        true
+    }
+
+    fn gamepad(&mut self, state: GamepadState) {
+        self.log(format_args!("gamepad({:?})", state));
+    }
+}
+
+// ==========================================
+// 5. Mock Driver (Recording)
+// ==========================================
+/// 一条输入命令，与 InputDriver 的方法一一对应
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum InputCommand {
+    Heartbeat,
+    SwitchIdentity { index: u8 },
+    MouseAbs { x: u16, y: u16 },
+    MouseMove { dx: i32, dy: i32, wheel: i8 },
+    MouseHWheel { delta: i8 },
+    MouseDown { left: bool, right: bool },
+    MouseUp,
+    KeyDown { keycode: u8, modifier: u8 },
+    KeyUp,
+    Gamepad { state: GamepadState },
+}
+
+impl InputCommand {
+    /// 将命令发送到任意驱动
+    pub fn apply(&self, driver: &mut dyn InputDriver) {
+        match *self {
+            InputCommand::Heartbeat => driver.heartbeat(),
+            InputCommand::SwitchIdentity { index } => driver.switch_identity(index),
+            InputCommand::MouseAbs { x, y } => driver.mouse_abs(x, y),
+            InputCommand::MouseMove { dx, dy, wheel } => driver.mouse_move(dx, dy, wheel),
+            InputCommand::MouseHWheel { delta } => driver.mouse_hwheel(delta),
+            InputCommand::MouseDown { left, right } => driver.mouse_down(left, right),
+            InputCommand::MouseUp => driver.mouse_up(),
+            InputCommand::KeyDown { keycode, modifier } => driver.key_down(keycode, modifier),
+            InputCommand::KeyUp => driver.key_up(),
+            InputCommand::Gamepad { state } => driver.gamepad(state),
+        }
+    }
+}
+
+/// MockDriver 的命令日志，驱动被装箱交给 HumanDriver 后仍可通过克隆的句柄检查
+pub type InputLog = Arc<Mutex<Vec<InputCommand>>>;
+
+/// 记录所有命令而不产生真实输入；用于测试 TowerDefenseApp / NavEngine 逻辑和策略演练
+pub struct MockDriver {
+    log: InputLog,
+    /// 是否记录心跳 (默认不记录，避免日志被心跳线程刷屏)
+    pub record_heartbeat: bool,
+}
+
+impl MockDriver {
+    pub fn new() -> Self {
+        Self { log: Arc::new(Mutex::new(Vec::new())), record_heartbeat: false }
+    }
+
+    /// 获取命令日志句柄
+    pub fn log(&self) -> InputLog {
+        Arc::clone(&self.log)
+    }
+
+    fn push(&self, cmd: InputCommand) {
+        if let Ok(mut log) = self.log.lock() {
+            log.push(cmd);
+        }
+    }
+}
+
+impl Default for MockDriver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl InputDriver for MockDriver {
+    fn heartbeat(&mut self) {
+        if self.record_heartbeat {
+            self.push(InputCommand::Heartbeat);
+        }
+    }
+
+    fn switch_identity(&mut self, index: u8) {
+        self.push(InputCommand::SwitchIdentity { index });
+    }
+
+    fn mouse_abs(&mut self, x: u16, y: u16) {
+        self.push(InputCommand::MouseAbs { x, y });
+    }
+
+    fn mouse_move(&mut self, dx: i32, dy: i32, wheel: i8) {
+        self.push(InputCommand::MouseMove { dx, dy, wheel });
+    }
+
+    fn mouse_hwheel(&mut self, delta: i8) {
+        self.push(InputCommand::MouseHWheel { delta });
+    }
+
+    fn mouse_down(&mut self, left: bool, right: bool) {
+        self.push(InputCommand::MouseDown { left, right });
+    }
+
+    fn mouse_up(&mut self) {
+        self.push(InputCommand::MouseUp);
+    }
+
+    fn key_down(&mut self, keycode: u8, modifier: u8) {
+        self.push(InputCommand::KeyDown { keycode, modifier });
+    }
+
+    fn key_up(&mut self) {
+        self.push(InputCommand::KeyUp);
+    }
+
+    fn supports_gamepad(&self) -> bool {
+        true
+    }
+
+    fn gamepad(&mut self, state: GamepadState) {
+        self.push(InputCommand::Gamepad { state });
     }
 }
 
 // ==========================================
-// 4. Factory Function
+// 6. Keyboard Layout
+// ==========================================
+// 硬件盒子发送的是 HID 键码 (物理键位)，系统再按当前键盘布局翻译成字符。
+// KeyLayout 负责 "逻辑按键 -> 物理键位"：在 AZERTY 布局下按 'a' 需要发送 US 的 Q 键位。
+
+/// HID 修饰键位 (左侧)；右侧为左移 4 位
+pub const MOD_CTRL: u8 = 0x01;
+pub const MOD_SHIFT: u8 = 0x02;
+pub const MOD_ALT: u8 = 0x04;
+pub const MOD_GUI: u8 = 0x08;
+/// AltGr (右 Alt)
+pub const MOD_ALTGR: u8 = 0x40;
+
+/// 一次按键：HID 键码 + 修饰键位
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyStroke {
+    pub code: u8,
+    pub modifier: u8,
+}
+
+impl KeyStroke {
+    pub const fn new(code: u8, modifier: u8) -> Self {
+        Self { code, modifier }
+    }
+
+    /// 修饰键 (HID 0xE0 ~ 0xE7) 对应的修饰位；普通键返回 None
+    pub fn modifier_bit(&self) -> Option<u8> {
+        (0xE0..=0xE7).contains(&self.code).then(|| 1 << (self.code - 0xE0))
+    }
+
+    /// 合并为一帧：修饰键并入修饰位，普通键取最后一个
+    pub fn combine(keys: &[KeyStroke]) -> KeyStroke {
+        keys.iter().fold(KeyStroke::new(0, 0), |acc, k| match k.modifier_bit() {
+            Some(bit) => KeyStroke::new(acc.code, acc.modifier | bit | k.modifier),
+            None => KeyStroke::new(k.code, acc.modifier | k.modifier),
+        })
+    }
+}
+
+/// 键盘布局配置 (config.toml 的 [keyboard])
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct KeyboardConfig {
+    /// "us" / "azerty" / "qwertz"
+    pub layout: String,
+    /// 按键覆盖：逻辑按键名 -> 按键名或 HID 键码 (如 p = "0x13"、interact = "f")
+    pub keys: HashMap<String, String>,
+    /// 文本输入方式
+    pub typing: TypingMode,
+}
+
+impl Default for KeyboardConfig {
+    fn default() -> Self {
+        Self { layout: "us".to_string(), keys: HashMap::new(), typing: TypingMode::Auto }
+    }
+}
+
+/// 文本输入方式 (HumanDriver::type_humanly)
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum TypingMode {
+    /// 布局能打出全部字符时逐键输入，否则整段粘贴
+    #[default]
+    Auto,
+    /// 始终逐键输入，无法映射的字符被跳过
+    Keys,
+    /// 始终写入剪贴板后 Ctrl+V (中文昵称、聊天命令等任意 UTF-8 文本)
+    Paste,
+}
+
+/// US 布局的字符表 (字符, HID, 修饰)
+const US_CHARS: &[(char, u8, u8)] = &[
+    (' ', 0x2C, 0), ('\n', 0x28, 0), ('\t', 0x2B, 0), ('\u{1B}', 0x29, 0), ('\u{8}', 0x2A, 0),
+    ('-', 0x2D, 0), ('=', 0x2E, 0), ('[', 0x2F, 0), (']', 0x30, 0), ('\\', 0x31, 0),
+    (';', 0x33, 0), ('\'', 0x34, 0), ('`', 0x35, 0), (',', 0x36, 0), ('.', 0x37, 0), ('/', 0x38, 0),
+    ('!', 0x1E, MOD_SHIFT), ('@', 0x1F, MOD_SHIFT), ('#', 0x20, MOD_SHIFT), ('$', 0x21, MOD_SHIFT),
+    ('%', 0x22, MOD_SHIFT), ('^', 0x23, MOD_SHIFT), ('&', 0x24, MOD_SHIFT), ('*', 0x25, MOD_SHIFT),
+    ('(', 0x26, MOD_SHIFT), (')', 0x27, MOD_SHIFT), ('_', 0x2D, MOD_SHIFT), ('+', 0x2E, MOD_SHIFT),
+    ('{', 0x2F, MOD_SHIFT), ('}', 0x30, MOD_SHIFT), ('|', 0x31, MOD_SHIFT), (':', 0x33, MOD_SHIFT),
+    ('"', 0x34, MOD_SHIFT), ('~', 0x35, MOD_SHIFT), ('<', 0x36, MOD_SHIFT), ('>', 0x37, MOD_SHIFT),
+    ('?', 0x38, MOD_SHIFT),
+];
+
+/// 法语 AZERTY 相对 US 的差异
+const AZERTY_CHARS: &[(char, u8, u8)] = &[
+    ('a', 0x14, 0), ('q', 0x04, 0), ('z', 0x1A, 0), ('w', 0x1D, 0), ('m', 0x33, 0),
+    ('&', 0x1E, 0), ('é', 0x1F, 0), ('"', 0x20, 0), ('\'', 0x21, 0), ('(', 0x22, 0),
+    ('-', 0x23, 0), ('è', 0x24, 0), ('_', 0x25, 0), ('ç', 0x26, 0), ('à', 0x27, 0),
+    ('1', 0x1E, MOD_SHIFT), ('2', 0x1F, MOD_SHIFT), ('3', 0x20, MOD_SHIFT), ('4', 0x21, MOD_SHIFT),
+    ('5', 0x22, MOD_SHIFT), ('6', 0x23, MOD_SHIFT), ('7', 0x24, MOD_SHIFT), ('8', 0x25, MOD_SHIFT),
+    ('9', 0x26, MOD_SHIFT), ('0', 0x27, MOD_SHIFT),
+    (')', 0x2D, 0), ('=', 0x2E, 0), ('+', 0x2E, MOD_SHIFT), ('$', 0x30, 0), ('*', 0x31, 0),
+    ('ù', 0x34, 0), ('%', 0x34, MOD_SHIFT), (',', 0x10, 0), ('?', 0x10, MOD_SHIFT),
+    (';', 0x36, 0), ('.', 0x36, MOD_SHIFT), (':', 0x37, 0), ('/', 0x37, MOD_SHIFT),
+    ('!', 0x38, 0), ('<', 0x64, 0), ('>', 0x64, MOD_SHIFT),
+    ('~', 0x1F, MOD_ALTGR), ('#', 0x20, MOD_ALTGR), ('{', 0x21, MOD_ALTGR), ('[', 0x22, MOD_ALTGR),
+    ('|', 0x23, MOD_ALTGR), ('\\', 0x25, MOD_ALTGR), ('@', 0x27, MOD_ALTGR), (']', 0x2D, MOD_ALTGR),
+    ('}', 0x2E, MOD_ALTGR), ('€', 0x08, MOD_ALTGR),
+];
+
+/// 德语 QWERTZ 相对 US 的差异
+const QWERTZ_CHARS: &[(char, u8, u8)] = &[
+    ('y', 0x1D, 0), ('z', 0x1C, 0), ('ß', 0x2D, 0), ('ü', 0x2F, 0), ('ö', 0x33, 0), ('ä', 0x34, 0),
+    ('+', 0x30, 0), ('#', 0x32, 0), ('-', 0x38, 0), ('<', 0x64, 0),
+    ('!', 0x1E, MOD_SHIFT), ('"', 0x1F, MOD_SHIFT), ('§', 0x20, MOD_SHIFT), ('$', 0x21, MOD_SHIFT),
+    ('%', 0x22, MOD_SHIFT), ('&', 0x23, MOD_SHIFT), ('/', 0x24, MOD_SHIFT), ('(', 0x25, MOD_SHIFT),
+    (')', 0x26, MOD_SHIFT), ('=', 0x27, MOD_SHIFT), ('?', 0x2D, MOD_SHIFT), ('*', 0x30, MOD_SHIFT),
+    ('\'', 0x32, MOD_SHIFT), (';', 0x36, MOD_SHIFT), (':', 0x37, MOD_SHIFT), ('_', 0x38, MOD_SHIFT),
+    ('>', 0x64, MOD_SHIFT),
+    ('@', 0x14, MOD_ALTGR), ('{', 0x24, MOD_ALTGR), ('[', 0x25, MOD_ALTGR), (']', 0x26, MOD_ALTGR),
+    ('}', 0x27, MOD_ALTGR), ('\\', 0x2D, MOD_ALTGR), ('~', 0x30, MOD_ALTGR), ('|', 0x64, MOD_ALTGR),
+    ('€', 0x08, MOD_ALTGR),
+];
+
+/// 与布局无关的命名按键 (功能键、方向键、小键盘、修饰键)
+fn named_key(name: &str) -> Option<KeyStroke> {
+    let code = match name {
+        "esc" | "escape" => 0x29,
+        "enter" | "return" => 0x28,
+        "space" => 0x2C,
+        "tab" => 0x2B,
+        "backspace" => 0x2A,
+        "capslock" => 0x39,
+        "printscreen" => 0x46,
+        "scrolllock" => 0x47,
+        "pause" => 0x48,
+        "insert" => 0x49,
+        "home" => 0x4A,
+        "pageup" => 0x4B,
+        "delete" | "del" => 0x4C,
+        "end" => 0x4D,
+        "pagedown" => 0x4E,
+        "right" => 0x4F,
+        "left" => 0x50,
+        "down" => 0x51,
+        "up" => 0x52,
+        "numlock" => 0x53,
+        "num/" => 0x54,
+        "num*" => 0x55,
+        "num-" => 0x56,
+        "num+" => 0x57,
+        "numenter" => 0x58,
+        "num." => 0x63,
+        "ctrl" | "lctrl" => 0xE0,
+        "shift" | "lshift" => 0xE1,
+        "alt" | "lalt" => 0xE2,
+        "win" | "lwin" => 0xE3,
+        "rctrl" => 0xE4,
+        "rshift" => 0xE5,
+        "ralt" | "altgr" => 0xE6,
+        "rwin" => 0xE7,
+        _ => {
+            if let Some(n) = name.strip_prefix("num").and_then(|n| n.parse::<u8>().ok()) {
+                return match n {
+                    0 => Some(KeyStroke::new(0x62, 0)),
+                    1..=9 => Some(KeyStroke::new(0x59 + n - 1, 0)),
+                    _ => None,
+                };
+            }
+            if let Some(n) = name.strip_prefix('f').and_then(|n| n.parse::<u8>().ok()) {
+                return match n {
+                    1..=12 => Some(KeyStroke::new(0x3A + n - 1, 0)),
+                    13..=24 => Some(KeyStroke::new(0x68 + n - 13, 0)),
+                    _ => None,
+                };
+            }
+            return None;
+        }
+    };
+    Some(KeyStroke::new(code, 0))
+}
+
+/// US 布局下的单个字符
+fn us_char(ch: char) -> Option<KeyStroke> {
+    match ch {
+        'a'..='z' => Some(KeyStroke::new(ch as u8 - b'a' + 0x04, 0)),
+        'A'..='Z' => Some(KeyStroke::new(ch as u8 - b'A' + 0x04, MOD_SHIFT)),
+        '1'..='9' => Some(KeyStroke::new(ch as u8 - b'1' + 0x1E, 0)),
+        '0' => Some(KeyStroke::new(0x27, 0)),
+        _ => US_CHARS.iter().find(|(c, _, _)| *c == ch).map(|&(_, code, m)| KeyStroke::new(code, m)),
+    }
+}
+
+/// 逻辑按键到物理键位的映射
+#[derive(Debug, Clone)]
+pub struct KeyLayout {
+    pub name: String,
+    /// 相对 US 布局不同的字符
+    chars: HashMap<char, KeyStroke>,
+    /// 用户覆盖 (小写按键名)
+    overrides: HashMap<String, KeyStroke>,
+}
+
+impl Default for KeyLayout {
+    fn default() -> Self {
+        Self::us()
+    }
+}
+
+impl KeyLayout {
+    fn with_table(name: &str, table: &[(char, u8, u8)]) -> Self {
+        Self {
+            name: name.to_string(),
+            chars: table.iter().map(|&(c, code, m)| (c, KeyStroke::new(code, m))).collect(),
+            overrides: HashMap::new(),
+        }
+    }
+
+    pub fn us() -> Self {
+        Self::with_table("us", &[])
+    }
+
+    pub fn azerty() -> Self {
+        Self::with_table("azerty", AZERTY_CHARS)
+    }
+
+    pub fn qwertz() -> Self {
+        Self::with_table("qwertz", QWERTZ_CHARS)
+    }
+
+    pub fn by_name(name: &str) -> Option<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "us" | "qwerty" | "" => Some(Self::us()),
+            "azerty" | "fr" => Some(Self::azerty()),
+            "qwertz" | "de" => Some(Self::qwertz()),
+            _ => None,
+        }
+    }
+
+    /// 按配置构建布局；未知布局回退 US，无效的覆盖项会被跳过并提示
+    pub fn from_config(cfg: &KeyboardConfig) -> Self {
+        let mut layout = Self::by_name(&cfg.layout).unwrap_or_else(|| {
+            warn!("⚠️ [键盘] 未知布局 '{}'，使用 US", cfg.layout);
+            Self::us()
+        });
+        for (name, target) in &cfg.keys {
+            let stroke = parse_hex(target).map(|code| KeyStroke::new(code, 0)).or_else(|| layout.resolve(target));
+            match stroke {
+                Some(k) => { layout.overrides.insert(name.trim().to_lowercase(), k); }
+                None => warn!("⚠️ [键盘] 无效的按键覆盖 {} = {}", name, target),
+            }
+        }
+        layout
+    }
+
+    /// 单个字符对应的按键
+    pub fn char_stroke(&self, ch: char) -> Option<KeyStroke> {
+        if let Some(k) = self.chars.get(&ch) {
+            return Some(*k);
+        }
+        // 大写字母 = 当前布局的小写字母 + Shift
+        if ch.is_ascii_uppercase() {
+            if let Some(k) = self.chars.get(&ch.to_ascii_lowercase()) {
+                return Some(KeyStroke::new(k.code, k.modifier | MOD_SHIFT));
+            }
+        }
+        us_char(ch)
+    }
+
+    /// 将配置中的按键名 ("esc", "f12", "num5", "0x13", "g", "é", "ctrl+shift+s" ...) 解析为按键
+    pub fn resolve(&self, name: &str) -> Option<KeyStroke> {
+        self.resolve_combo(name).map(|keys| KeyStroke::combine(&keys))
+    }
+
+    /// 解析组合键 ("ctrl+shift+s" / "alt+tab")，按书写顺序返回每个按键
+    pub fn resolve_combo(&self, combo: &str) -> Option<Vec<KeyStroke>> {
+        let trimmed = combo.trim();
+        // "+" 本身与 "num+" 不是组合键
+        if trimmed.len() > 1 && trimmed.contains('+') && named_key(&trimmed.to_lowercase()).is_none() {
+            return trimmed.split('+').map(|part| self.resolve_single(part)).collect();
+        }
+        self.resolve_single(trimmed).map(|k| vec![k])
+    }
+
+    fn resolve_single(&self, name: &str) -> Option<KeyStroke> {
+        let trimmed = name.trim();
+        let lower = trimmed.to_lowercase();
+        if let Some(k) = self.overrides.get(&lower) {
+            return Some(*k);
+        }
+        let mut chars = trimmed.chars();
+        if let (Some(ch), None) = (chars.next(), chars.next()) {
+            return self.char_stroke(ch);
+        }
+        if trimmed.is_empty() {
+            return None;
+        }
+        named_key(&lower).or_else(|| parse_hex(&lower).map(|code| KeyStroke::new(code, 0)))
+    }
+}
+
+fn parse_hex(s: &str) -> Option<u8> {
+    let s = s.trim();
+    s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")).and_then(|h| u8::from_str_radix(h, 16).ok())
+}
+
+/// 将配置文件中的按键名 ("esc", "space", "f12", "g" ...) 按 US 布局转换为 HID 键码
+pub fn hid_from_name(name: &str) -> Option<u8> {
+    KeyLayout::us().resolve(name).map(|k| k.code)
+}
+
+/// 扩展键标志 (与 enigo::EXT 相同，SendInput 据此加 KEYEVENTF_EXTENDEDKEY)
+const EXT: u16 = 0xFF00;
+
+/// HID 键码 -> Set 1 扫描码 (软件驱动按物理键位注入，与硬件盒子行为一致)
+fn hid_to_scancode(hid: u8) -> Option<u16> {
+    const LETTERS: [u16; 26] = [
+        0x1E, 0x30, 0x2E, 0x20, 0x12, 0x21, 0x22, 0x23, 0x17, 0x24, 0x25, 0x26, 0x32,
+        0x31, 0x18, 0x19, 0x10, 0x13, 0x1F, 0x14, 0x16, 0x2F, 0x11, 0x2D, 0x15, 0x2C,
+    ];
+    let sc = match hid {
+        0x04..=0x1D => LETTERS[(hid - 0x04) as usize],
+        0x1E..=0x27 => 0x02 + (hid - 0x1E) as u16,
+        0x28 => 0x1C,
+        0x29 => 0x01,
+        0x2A => 0x0E,
+        0x2B => 0x0F,
+        0x2C => 0x39,
+        0x2D => 0x0C,
+        0x2E => 0x0D,
+        0x2F => 0x1A,
+        0x30 => 0x1B,
+        0x31 | 0x32 => 0x2B,
+        0x33 => 0x27,
+        0x34 => 0x28,
+        0x35 => 0x29,
+        0x36 => 0x33,
+        0x37 => 0x34,
+        0x38 => 0x35,
+        0x39 => 0x3A,
+        0x3A..=0x43 => 0x3B + (hid - 0x3A) as u16,
+        0x44 => 0x57,
+        0x45 => 0x58,
+        0x46 => EXT | 0x37,
+        0x47 => 0x46,
+        0x49 => EXT | 0x52,
+        0x4A => EXT | 0x47,
+        0x4B => EXT | 0x49,
+        0x4C => EXT | 0x53,
+        0x4D => EXT | 0x4F,
+        0x4E => EXT | 0x51,
+        0x4F => EXT | 0x4D,
+        0x50 => EXT | 0x4B,
+        0x51 => EXT | 0x50,
+        0x52 => EXT | 0x48,
+        0x53 => 0x45,
+        0x54 => EXT | 0x35,
+        0x55 => 0x37,
+        0x56 => 0x4A,
+        0x57 => 0x4E,
+        0x58 => EXT | 0x1C,
+        0x59..=0x61 => [0x4F, 0x50, 0x51, 0x4B, 0x4C, 0x4D, 0x47, 0x48, 0x49][(hid - 0x59) as usize],
+        0x62 => 0x52,
+        0x63 => 0x53,
+        0x64 => 0x56,
+        0xE0 => 0x1D,
+        0xE1 => 0x2A,
+        0xE2 => 0x38,
+        0xE3 => EXT | 0x5B,
+        0xE4 => EXT | 0x1D,
+        0xE5 => 0x36,
+        0xE6 => EXT | 0x38,
+        0xE7 => EXT | 0x5C,
+        _ => return None,
+    };
+    Some(sc)
+}
+
+/// HID 键码 -> Linux evdev 键码 (KEY_*)：基本键区的 evdev 键码与 Set 1 扫描码相同，扩展键查表
+#[cfg(feature = "linux")]
+pub(crate) fn hid_to_evdev(hid: u8) -> Option<u16> {
+    let sc = hid_to_scancode(hid)?;
+    if sc & EXT != EXT {
+        return Some(sc);
+    }
+    let code = match sc & 0xFF {
+        0x1C => 96,  // KEY_KPENTER
+        0x1D => 97,  // KEY_RIGHTCTRL
+        0x35 => 98,  // KEY_KPSLASH
+        0x37 => 99,  // KEY_SYSRQ (PrintScreen)
+        0x38 => 100, // KEY_RIGHTALT
+        0x47 => 102, // KEY_HOME
+        0x48 => 103, // KEY_UP
+        0x49 => 104, // KEY_PAGEUP
+        0x4B => 105, // KEY_LEFT
+        0x4D => 106, // KEY_RIGHT
+        0x4F => 107, // KEY_END
+        0x50 => 108, // KEY_DOWN
+        0x51 => 109, // KEY_PAGEDOWN
+        0x52 => 110, // KEY_INSERT
+        0x53 => 111, // KEY_DELETE
+        0x5B => 125, // KEY_LEFTMETA
+        0x5C => 126, // KEY_RIGHTMETA
+        _ => return None,
+    };
+    Some(code)
+}
+
+// ==========================================
+// 7. Mouse Reporting Mode
+// ==========================================
+/// 鼠标上报方式：部分游戏忽略绝对坐标注入，只响应相对位移
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum MouseMode {
+    #[default]
+    Absolute,
+    Relative,
+}
+
+/// 鼠标配置 (config.toml 的 [mouse])
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct MouseConfig {
+    pub mode: MouseMode,
+    /// 相对模式下每个屏幕像素对应的位移计数 (受游戏灵敏度/系统指针速度影响)
+    pub relative_scale: f32,
+}
+
+impl Default for MouseConfig {
+    fn default() -> Self {
+        Self { mode: MouseMode::Absolute, relative_scale: 1.0 }
+    }
+}
+
+// ==========================================
+// 8. Factory Function
 // ==========================================
 pub enum DriverType {
     Hardware,
     Software,
+    /// 空驱动：不产生任何输入
+    Null,
+    /// KMBox Net (UDP)，port 为 "IP:端口:UUID"
+    Kmbox,
+    /// CH9329 串口转 HID 芯片
+    Ch9329,
+}
+
+impl DriverType {
+    /// 解析 --device-type：serial / soft / none / kmbox / ch9329
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "serial" | "hardware" => Some(DriverType::Hardware),
+            "soft" | "software" => Some(DriverType::Software),
+            "none" | "null" => Some(DriverType::Null),
+            "kmbox" | "kmbox-net" => Some(DriverType::Kmbox),
+            "ch9329" => Some(DriverType::Ch9329),
+            _ => None,
+        }
+    }
 }
 
 pub fn create_driver(
@@ -261,13 +1220,23 @@ pub fn create_driver(
     screen_h: u16
 ) -> Result<Box<dyn InputDriver>, String> {
     match t {
+        DriverType::Hardware if port.eq_ignore_ascii_case("AUTO") => {
+            let (drv, _) = HardwareDriver::discover(115200, screen_w, screen_h)?;
+            Ok(Box::new(drv))
+        }
         DriverType::Hardware => {
             let drv = HardwareDriver::new(port, 115200, screen_w, screen_h)?;
             Ok(Box::new(drv))
         }
         DriverType::Software => {
-            let drv = SoftwareDriver::new(screen_w, screen_h);
+            #[cfg(feature = "windows")]
+            let drv = SoftwareDriver::new(screen_w, screen_h)?;
+            #[cfg(feature = "linux")]
+            let drv = crate::uinput::UinputDriver::new(screen_w, screen_h)?;
             Ok(Box::new(drv))
         }
+        DriverType::Null => Ok(Box::new(NullDriver::new(true))),
+        DriverType::Kmbox => Ok(Box::new(KmboxNetDriver::new(port, screen_w, screen_h)?)),
+        DriverType::Ch9329 => Ok(Box::new(Ch9329Driver::new(port, 9600, screen_w, screen_h)?)),
     }
 }
\ No newline at end of file
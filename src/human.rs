@@ -1,87 +1,466 @@
 // src/human.rs
-use crate::hardware::InputDriver;
-use std::sync::{Arc, Mutex};
+use crate::calibration::Calibration;
+use crate::clipboard;
+use crate::hardware::{InputMetrics, KeyLayout, KeyStroke, MouseConfig, MouseMode, TypingMode};
+use crate::input_queue::InputQueue;
+use crate::trace::TraceWriter;
 use std::thread;
 use std::time::Duration;
 use rand::Rng;
 use rand_distr::{Normal, Distribution};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tracing::{info, warn};
+
+// ==========================================
+// 0. 节奏参数 (TimingProfile)
+// ==========================================
+
+/// 随机延迟的分布
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum DelayDist {
+    /// [min, max] 均匀分布
+    Uniform,
+    /// 以区间中点为均值、区间宽度 1/6 为标准差的正态分布，截断到 [min, max]
+    Normal,
+}
+
+/// 一个随机延迟区间 (毫秒)
+#[derive(Deserialize, Debug, Clone, Copy)]
+#[serde(default)]
+pub struct DelayRange {
+    pub min_ms: u64,
+    pub max_ms: u64,
+    pub dist: DelayDist,
+}
+
+impl Default for DelayRange {
+    fn default() -> Self {
+        Self::uniform(0, 0)
+    }
+}
+
+impl DelayRange {
+    pub const fn uniform(min_ms: u64, max_ms: u64) -> Self {
+        Self { min_ms, max_ms, dist: DelayDist::Uniform }
+    }
+
+    /// 抽取一次延迟；max <= min 时固定为 min
+    pub fn sample_ms(&self) -> u64 {
+        if self.max_ms <= self.min_ms {
+            return self.min_ms;
+        }
+        let mut rng = rand::thread_rng();
+        match self.dist {
+            DelayDist::Uniform => rng.gen_range(self.min_ms..=self.max_ms),
+            DelayDist::Normal => {
+                let (lo, hi) = (self.min_ms as f64, self.max_ms as f64);
+                let v = Normal::new((lo + hi) / 2.0, (hi - lo) / 6.0)
+                    .map(|n| n.sample(&mut rng))
+                    .unwrap_or(lo);
+                v.clamp(lo, hi).round() as u64
+            }
+        }
+    }
+
+    /// 按抽取的时长休眠 (0 时不休眠)
+    pub fn sleep(&self) {
+        let ms = self.sample_ms();
+        if ms > 0 {
+            thread::sleep(Duration::from_millis(ms));
+        }
+    }
+}
+
+/// 各类动作的拟人化节奏；config.toml 的 [timing]，不同游戏可配置不同节奏
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct TimingProfile {
+    /// 鼠标到位后、按下前的犹豫
+    pub pre_click: DelayRange,
+    /// 鼠标按住时长 (click_humanly 未指定时)
+    pub click_hold: DelayRange,
+    /// 按键按住时长 (key_click)
+    pub key_hold: DelayRange,
+    /// 组合键逐个按下的间隔
+    pub inter_key: DelayRange,
+    /// 点击 / 按键完成后的停留
+    pub post_action: DelayRange,
+    /// 默认移动耗时 (拖拽前的定位等)
+    pub move_duration: DelayRange,
+}
+
+impl Default for TimingProfile {
+    fn default() -> Self {
+        Self {
+            pre_click: DelayRange::uniform(0, 0),
+            click_hold: DelayRange::uniform(30, 75),
+            key_hold: DelayRange::uniform(35, 70),
+            inter_key: DelayRange::uniform(25, 60),
+            post_action: DelayRange::uniform(0, 0),
+            move_duration: DelayRange::uniform(500, 500),
+        }
+    }
+}
+
+// ==========================================
+// 0. 鼠标轨迹参数 (MotionProfile)
+// ==========================================
+
+/// 轨迹曲线类型
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum MotionCurve {
+    /// 三次贝塞尔 (两个随机控制点)
+    Bezier,
+    /// WindMouse：重力 + 随机风力逐步逼近目标
+    WindMouse,
+}
+
+/// 速度曲线 (仅对贝塞尔轨迹生效，WindMouse 自带加减速)
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SpeedRamp {
+    Linear,
+    EaseInOut,
+    EaseOut,
+}
+
+/// 拟人化移动参数；可在 config.toml 的 [motion.profiles.<名称>] 中自定义
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct MotionProfile {
+    pub curve: MotionCurve,
+    /// 控制点随机偏移幅度 (像素)
+    pub control_variance: f32,
+    pub speed_ramp: SpeedRamp,
+    /// 终点随机偏移 (像素)
+    pub endpoint_jitter: f32,
+    /// 每秒下发的轨迹点数
+    pub steps_per_sec: f32,
+    /// WindMouse 参数：重力、风力、单步最大距离
+    pub gravity: f32,
+    pub wind: f32,
+    pub max_step: f32,
+    /// 越过目标再回正的概率 (0 ~ 1)，仅对长距离移动生效
+    pub overshoot_chance: f32,
+    /// 越过目标的最大距离 (像素)
+    pub overshoot_px: f32,
+    /// 触发越过的最短移动距离 (像素)
+    pub overshoot_min_distance: f32,
+}
+
+impl Default for MotionProfile {
+    fn default() -> Self {
+        Self {
+            curve: MotionCurve::Bezier,
+            control_variance: 40.0,
+            speed_ramp: SpeedRamp::EaseInOut,
+            endpoint_jitter: 2.0,
+            steps_per_sec: 80.0,
+            gravity: 9.0,
+            wind: 3.0,
+            max_step: 15.0,
+            overshoot_chance: 0.0,
+            overshoot_px: 12.0,
+            overshoot_min_distance: 200.0,
+        }
+    }
+}
+
+impl MotionProfile {
+    /// 内置预设：default / precise / sloppy / fast / windmouse
+    pub fn preset(name: &str) -> Option<Self> {
+        let base = Self::default();
+        let p = match name.to_lowercase().as_str() {
+            "default" => base,
+            "precise" => Self { control_variance: 12.0, endpoint_jitter: 0.0, steps_per_sec: 100.0, ..base },
+            "sloppy" => Self { control_variance: 90.0, endpoint_jitter: 5.0, speed_ramp: SpeedRamp::EaseOut, overshoot_chance: 0.4, overshoot_px: 20.0, ..base },
+            "fast" => Self { control_variance: 20.0, speed_ramp: SpeedRamp::EaseOut, steps_per_sec: 120.0, ..base },
+            "windmouse" => Self { curve: MotionCurve::WindMouse, ..base },
+            _ => return None,
+        };
+        Some(p)
+    }
+}
+
+/// config.toml 的 [motion]：选择默认轨迹，并可声明自定义轨迹
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct MotionConfig {
+    /// 默认使用的轨迹名 (预设或 profiles 中的名称)
+    pub profile: String,
+    pub profiles: HashMap<String, MotionProfile>,
+}
+
+impl Default for MotionConfig {
+    fn default() -> Self {
+        Self { profile: "default".to_string(), profiles: HashMap::new() }
+    }
+}
+
+impl MotionConfig {
+    /// 按名称查找：自定义轨迹优先，其次内置预设
+    pub fn get(&self, name: &str) -> Option<MotionProfile> {
+        self.profiles.get(name).cloned().or_else(|| MotionProfile::preset(name))
+    }
+}
 
 pub struct HumanDriver {
-    // ✨ 核心修改：使用 Box<dyn InputDriver> 来存储多态驱动
-    pub device: Arc<Mutex<Box<dyn InputDriver>>>,
+    /// 输入命令队列 (底层驱动由队列的工作线程独占)
+    pub device: InputQueue,
     pub cur_x: f32,
     pub cur_y: f32,
+    /// 键盘布局：逻辑按键 -> 物理键位
+    pub layout: KeyLayout,
+    /// 鼠标上报方式；相对模式下拟人轨迹被转换为位移流
+    pub mouse: MouseConfig,
+    /// 默认移动轨迹参数
+    pub motion: MotionProfile,
+    /// 可按名称选择的轨迹 (自定义 + 预设)
+    pub motions: MotionConfig,
+    /// 文本输入方式 (逐键 / 粘贴)
+    pub typing: TypingMode,
+    /// 点击、按键与停顿的随机节奏
+    pub timing: TimingProfile,
+    /// 轨迹调试导出 (None 为关闭)
+    trace: Option<TraceWriter>,
+    /// 镜头平移校准结果 (见 calibration::calibrate)
+    pub calibration: Option<Calibration>,
 }
 
 impl HumanDriver {
     /// 初始化拟人化驱动器
-    // ✨ 核心修改：参数类型同步更新
-    pub fn new(device: Arc<Mutex<Box<dyn InputDriver>>>, start_x: u16, start_y: u16) -> Self {
+    pub fn new(device: InputQueue, start_x: u16, start_y: u16) -> Self {
         Self {
             device,
             cur_x: start_x as f32,
             cur_y: start_y as f32,
+            layout: KeyLayout::us(),
+            mouse: MouseConfig::default(),
+            motion: MotionProfile::default(),
+            motions: MotionConfig::default(),
+            typing: TypingMode::Auto,
+            timing: TimingProfile::default(),
+            trace: None,
+            calibration: None,
+        }
+    }
+
+    pub fn with_calibration(mut self, calibration: Option<Calibration>) -> Self {
+        self.calibration = calibration;
+        self
+    }
+
+    /// 按住平移键使画面移动约 pixels 像素；未校准时返回 false
+    pub fn pan_by(&mut self, key: char, pixels: f32) -> bool {
+        let Some(cal) = self.calibration else { return false };
+        let ms = cal.pan_ms(pixels);
+        if ms > 0 {
+            self.key_hold(key, ms);
+        }
+        true
+    }
+
+    /// 将之后的每条移动轨迹写入 JSONL 文件 (见 trace::render)
+    pub fn with_trace(mut self, path: &str) -> Self {
+        match TraceWriter::new(path) {
+            Ok(w) => self.trace = Some(w),
+            Err(e) => warn!("⚠️ [轨迹] 无法创建 {}: {}", path, e),
+        }
+        self
+    }
+
+    pub fn with_timing(mut self, timing: TimingProfile) -> Self {
+        self.timing = timing;
+        self
+    }
+
+    pub fn with_typing(mut self, typing: TypingMode) -> Self {
+        self.typing = typing;
+        self
+    }
+
+    /// 加载轨迹配置并选中其默认轨迹
+    pub fn with_motion(mut self, motions: MotionConfig) -> Self {
+        self.motion = motions.get(&motions.profile).unwrap_or_else(|| {
+            warn!("⚠️ [轨迹] 未知的轨迹 '{}'，使用 default", motions.profile);
+            MotionProfile::default()
+        });
+        self.motions = motions;
+        self
+    }
+
+    /// 按名称切换默认轨迹 (如某个业务模块整体使用 "precise")；未知名称返回 false
+    pub fn set_motion(&mut self, name: &str) -> bool {
+        match self.motions.get(name) {
+            Some(p) => { self.motion = p; true }
+            None => false,
         }
     }
 
+    /// 使用指定的鼠标上报方式
+    pub fn with_mouse(mut self, mouse: MouseConfig) -> Self {
+        self.mouse = mouse;
+        self
+    }
+
+    /// 运行时切换绝对/相对模式
+    pub fn set_mouse_mode(&mut self, mode: MouseMode) {
+        self.mouse.mode = mode;
+    }
+
+    /// 使用指定的键盘布局
+    pub fn with_layout(mut self, layout: KeyLayout) -> Self {
+        self.layout = layout;
+        self
+    }
+
+    /// 底层驱动的链路指标快照 (供仪表盘监控链路是否劣化)
+    pub fn metrics(&self) -> InputMetrics {
+        self.device.metrics()
+    }
+
     // ==========================================
     // 1. 基础输入原子操作 (原子层)
     // ==========================================
 
-    /// 内部辅助：字符转 HID 键码
-    fn char_to_keycode(&self, ch: char) -> u8 {
-        match ch.to_ascii_lowercase() {
-            'a'..='z' => ch.to_ascii_lowercase() as u8 - b'a' + 0x04,
-            '1'..='9' => ch as u8 - b'1' + 0x1E,
-            '0' => 0x27,
-            ' ' => 0x2C,
-            _ => 0,
-        }
+    /// 内部辅助：按当前布局把字符转为按键
+    fn char_to_stroke(&self, ch: char) -> Option<KeyStroke> {
+        // 游戏按键不区分大小写，'P' 与 'p' 都按同一个键位
+        self.layout.char_stroke(ch.to_ascii_lowercase())
     }
 
     /// 🔥 【键盘长按】
     /// 允许指定按下的毫秒数。如果是 0，则执行一次极短的点击。
     pub fn key_hold(&mut self, ch: char, ms: u64) {
-        let keycode = self.char_to_keycode(ch);
-        if keycode != 0 {
-            if let Ok(mut dev) = self.device.lock() {
-                dev.key_down(keycode, 0);
-            }
-            
-            // 如果 ms 为 0，模拟一个非常短的物理接触
-            let hold_time = if ms > 0 { ms } else { rand::thread_rng().gen_range(20..45) };
-            thread::sleep(Duration::from_millis(hold_time));
+        if let Some(stroke) = self.char_to_stroke(ch) {
+            self.hold_stroke(stroke, ms);
+        }
+    }
+
+    /// 按配置中的按键名 ("esc" / "f5" / "num0" / "g") 长按；无法解析时返回 false
+    pub fn key_hold_name(&mut self, name: &str, ms: u64) -> bool {
+        match self.layout.resolve_combo(name) {
+            Some(keys) if keys.len() > 1 => self.key_combo(name, ms),
+            Some(keys) if keys.len() == 1 => { self.hold_stroke(keys[0], ms); true }
+            _ => false,
+        }
+    }
 
-            if let Ok(mut dev) = self.device.lock() {
-                dev.key_up();
+    /// 【拟人化组合键】如 "ctrl+shift+s" / "alt+tab"
+    /// 按书写顺序逐个按下 (间隔见 timing.inter_key)，按住 hold_ms 后一次性松开；无法解析时返回 false
+    pub fn key_combo(&mut self, combo: &str, hold_ms: u64) -> bool {
+        let keys = match self.layout.resolve_combo(combo) {
+            Some(k) if !k.is_empty() => k,
+            _ => return false,
+        };
+        for i in 1..=keys.len() {
+            // 已按下的前 i 个键合并为一帧 (HID 报告是完整状态)
+            let k = KeyStroke::combine(&keys[..i]);
+            self.device.key_down(k.code, k.modifier);
+            if i < keys.len() {
+                self.timing.inter_key.sleep();
             }
         }
+        let hold = if hold_ms > 0 { hold_ms } else { rand::thread_rng().gen_range(40..90) };
+        thread::sleep(Duration::from_millis(hold));
+        self.device.key_up();
+        true
+    }
+
+    fn hold_stroke(&mut self, stroke: KeyStroke, ms: u64) {
+        self.device.key_down(stroke.code, stroke.modifier);
+
+        // 如果 ms 为 0，模拟一个非常短的物理接触
+        let hold_time = if ms > 0 { ms } else { rand::thread_rng().gen_range(20..45) };
+        thread::sleep(Duration::from_millis(hold_time));
+
+        self.device.key_up();
+    }
+
+    /// 按下任意 HID 键码 (用于 ESC / F 键等无法用字符表示的按键)
+    pub fn key_hold_hid(&mut self, keycode: u8, ms: u64) {
+        self.device.key_down(keycode, 0);
+        let hold_time = if ms > 0 { ms } else { rand::thread_rng().gen_range(35..70) };
+        thread::sleep(Duration::from_millis(hold_time));
+        self.device.key_up();
     }
 
     /// 【拟人化按键点击】 (短按)
     pub fn key_click(&mut self, ch: char) {
-        // 模拟真实按键点击通常在 30-70ms 之间
-        let jitter = rand::thread_rng().gen_range(35..70);
-        self.key_hold(ch, jitter);
+        // 模拟真实按键点击通常在 30-70ms 之间 (timing.key_hold)
+        let hold = self.timing.key_hold.sample_ms().max(1);
+        self.key_hold(ch, hold);
+        self.timing.post_action.sleep();
     }
 
     /// 🔥 【模拟鼠标滚轮】
     /// delta: 120 的倍数，正数为向上滚，负数为向下滚
     pub fn mouse_scroll(&mut self, delta: i32) {
-        if let Ok(mut dev) = self.device.lock() {
-            // 在 lib.rs 中 mouse_move 的第三个参数通常对应滚轮字节
-            dev.mouse_move(0, 0, delta as i8);
-        }
+        // 在 lib.rs 中 mouse_move 的第三个参数通常对应滚轮字节
+        self.device.mouse_move(0, 0, delta as i8);
         // 滚轮后稍微停顿符合人体工程学
         thread::sleep(Duration::from_millis(100));
     }
 
+    /// 🔥 【拟人滚动】在 duration_sec 内滚动 total 格 (正数向上)
+    /// 每次 1~3 格，中段快、首尾慢 (模拟滚轮惯性)，偶尔短暂停顿
+    pub fn scroll_humanly(&mut self, total: i32, duration_sec: f32) {
+        self.scroll_eased(total, duration_sec, false);
+    }
+
+    /// 🔥 【拟人横向滚动】正数向右；驱动不支持水平滚轮时被忽略
+    pub fn hscroll_humanly(&mut self, total: i32, duration_sec: f32) {
+        self.scroll_eased(total, duration_sec, true);
+    }
+
+    fn scroll_eased(&mut self, total: i32, duration_sec: f32, horizontal: bool) {
+        if total == 0 {
+            return;
+        }
+        let mut rng = rand::thread_rng();
+        let sign = total.signum();
+        let mut remaining = total.unsigned_abs();
+        let mut ticks = Vec::new();
+        while remaining > 0 {
+            let n = rng.gen_range(1..=3).min(remaining);
+            ticks.push(n);
+            remaining -= n;
+        }
+
+        // 每格的等待时间与当前进度的速度成反比：速度 ~ sin(πp)，首尾保底 0.3
+        let count = ticks.len() as f32;
+        let weights: Vec<f32> = (0..ticks.len())
+            .map(|i| {
+                let p = (i as f32 + 0.5) / count;
+                1.0 / ((std::f32::consts::PI * p).sin() + 0.3)
+            })
+            .collect();
+        let weight_sum: f32 = weights.iter().sum();
+
+        for (n, w) in ticks.iter().zip(&weights) {
+            let delta = (sign * *n as i32) as i8;
+            if horizontal {
+                self.device.mouse_hwheel(delta);
+            } else {
+                self.device.mouse_move(0, 0, delta);
+            }
+            let mut wait = duration_sec * w / weight_sum * rng.gen_range(0.8..1.2);
+            if rng.gen_bool(0.1) {
+                wait += rng.gen_range(0.04..0.12);
+            }
+            thread::sleep(Duration::from_secs_f32(wait));
+        }
+    }
+
     /// 🔥 【相对移动】
     /// 用于在当前位置基础上进行微调或防掉线微动
     pub fn move_relative(&mut self, dx: i32, dy: i32) {
-        if let Ok(mut dev) = self.device.lock() {
-            dev.mouse_move(dx, dy, 0);
-        }
+        self.device.mouse_move(dx, dy, 0);
         self.cur_x += dx as f32;
         self.cur_y += dy as f32;
     }
@@ -92,37 +471,85 @@ impl HumanDriver {
 
     /// 【高级拟人移动】
     pub fn move_to_humanly(&mut self, target_x: u16, target_y: u16, duration_sec: f32) {
+        let profile = self.motion.clone();
+        self.move_to_with(target_x, target_y, duration_sec, &profile);
+    }
+
+    /// 【指定轨迹移动】按给定的 MotionProfile 移动 (单次调用覆盖默认轨迹)
+    pub fn move_to_with(&mut self, target_x: u16, target_y: u16, duration_sec: f32, profile: &MotionProfile) {
         let mut rng = rand::thread_rng();
         let start = (self.cur_x, self.cur_y);
+
+        // 越过目标再回正：主移动落在目标后方，随后用一段短而精确的移动修正
+        let (dx, dy) = (target_x as f32 - start.0, target_y as f32 - start.1);
+        let dist = (dx * dx + dy * dy).sqrt();
+        if profile.overshoot_chance > 0.0
+            && dist >= profile.overshoot_min_distance.max(1.0)
+            && rng.gen::<f32>() < profile.overshoot_chance
+        {
+            let over = rng.gen_range(0.3..1.0) * profile.overshoot_px;
+            let side = rng.gen_range(-0.3..0.3) * over;
+            let (ux, uy) = (dx / dist, dy / dist);
+            let ox = (target_x as f32 + ux * over - uy * side).max(0.0) as u16;
+            let oy = (target_y as f32 + uy * over + ux * side).max(0.0) as u16;
+            let first = MotionProfile { overshoot_chance: 0.0, ..profile.clone() };
+            let settle = MotionProfile {
+                overshoot_chance: 0.0,
+                control_variance: (over * 0.5).max(1.0),
+                speed_ramp: SpeedRamp::EaseOut,
+                ..profile.clone()
+            };
+            self.move_to_with(ox, oy, duration_sec * 0.85, &first);
+            thread::sleep(Duration::from_millis(rng.gen_range(30..90)));
+            self.move_to_with(target_x, target_y, (duration_sec * 0.15).max(0.06), &settle);
+            return;
+        }
         
-        let end = (
-            target_x as f32 + rng.gen_range(-2.0..2.0),
-            target_y as f32 + rng.gen_range(-2.0..2.0)
-        );
+        let jitter = profile.endpoint_jitter;
+        let end = if jitter > 0.0 {
+            (
+                target_x as f32 + rng.gen_range(-jitter..jitter),
+                target_y as f32 + rng.gen_range(-jitter..jitter)
+            )
+        } else {
+            (target_x as f32, target_y as f32)
+        };
 
-        let ctrl1 = (
-            start.0 + (end.0 - start.0) * 0.2 + rng.gen_range(-40.0..40.0),
-            start.1 + (end.1 - start.1) * 0.2 + rng.gen_range(-40.0..40.0)
-        );
-        let ctrl2 = (
-            start.0 + (end.0 - start.0) * 0.8 + rng.gen_range(-20.0..60.0),
-            start.1 + (end.1 - start.1) * 0.8 + rng.gen_range(-20.0..60.0)
-        );
+        let steps = ((duration_sec * profile.steps_per_sec) as u32).max(1);
+        let path = match profile.curve {
+            MotionCurve::Bezier => Self::bezier_path(start, end, steps, profile),
+            MotionCurve::WindMouse => Self::wind_mouse_path(start, end, profile),
+        };
+        let interval = Duration::from_secs_f32(duration_sec / path.len().max(1) as f32);
+        let started = std::time::Instant::now();
+        let mut traced = Vec::new();
 
-        let steps = (duration_sec * 80.0) as u32; 
-        let interval = Duration::from_secs_f32(duration_sec / steps as f32);
+        // 相对模式：记录已发送的累计位移 (以计数为单位)，按整数差值下发，避免舍入误差累积
+        let scale = self.mouse.relative_scale;
+        let mut sent = ((start.0 * scale).round() as i32, (start.1 * scale).round() as i32);
 
-        for i in 0..=steps {
-            let t_linear = i as f32 / steps as f32;
-            let t_eased = Self::ease_in_out_cubic(t_linear);
-            let (px, py) = Self::bezier_cubic(t_eased, start, ctrl1, ctrl2, end);
-            
-            if let Ok(mut dev) = self.device.lock() {
-                dev.mouse_abs(px as u16, py as u16);
+        for (px, py) in path {
+            match self.mouse.mode {
+                MouseMode::Absolute => self.device.mouse_abs(px as u16, py as u16),
+                MouseMode::Relative => {
+                    let target = ((px * scale).round() as i32, (py * scale).round() as i32);
+                    let (dx, dy) = (target.0 - sent.0, target.1 - sent.1);
+                    if dx != 0 || dy != 0 {
+                        self.device.mouse_move(dx, dy, 0);
+                    }
+                    sent = target;
+                }
+            }
+            if self.trace.is_some() {
+                traced.push([px, py, started.elapsed().as_secs_f32() * 1000.0]);
             }
             thread::sleep(interval);
         }
 
+        if let Some(trace) = self.trace.as_mut() {
+            let curve = format!("{:?}", profile.curve);
+            trace.write(&curve, (target_x, target_y), end, traced);
+        }
         self.cur_x = end.0;
         self.cur_y = end.1;
     }
@@ -130,15 +557,40 @@ impl HumanDriver {
     /// 【拟人化鼠标点击】
     /// 增加 hold_ms 参数以支持长按点击（如蓄力）
     pub fn click_humanly(&mut self, left: bool, right: bool, hold_ms: u64) {
-        let mut rng = rand::thread_rng();
-        if let Ok(mut dev) = self.device.lock() {
-            dev.mouse_down(left, right);
-            
-            let sleep_time = if hold_ms > 0 { hold_ms } else { rng.gen_range(30..75) };
-            thread::sleep(Duration::from_millis(sleep_time));
-            
-            dev.mouse_up();
-        }
+        self.timing.pre_click.sleep();
+        self.device.mouse_down(left, right);
+        
+        let sleep_time = if hold_ms > 0 { hold_ms } else { self.timing.click_hold.sample_ms() };
+        thread::sleep(Duration::from_millis(sleep_time));
+        
+        self.device.mouse_up();
+        self.timing.post_action.sleep();
+    }
+
+    /// 【拟人化长按】按住鼠标约 duration_ms 毫秒 (±8% 正态抖动)，用于需要长按的 UI 元素
+    pub fn click_hold_humanly(&mut self, left: bool, right: bool, duration_ms: u64) {
+        let base = duration_ms as f32;
+        let hold = Normal::new(base, base * 0.08)
+            .map(|n| n.sample(&mut rand::thread_rng()))
+            .unwrap_or(base)
+            .clamp(base * 0.8, base * 1.2) as u64;
+        self.click_humanly(left, right, hold.max(1));
+    }
+
+    /// 【拟人化拖拽】按住左键从 from 拖到 to (用于滑动菜单)
+    pub fn drag_humanly(&mut self, from: (u16, u16), to: (u16, u16), duration_sec: f32) {
+        self.drag_button_humanly(from, to, duration_sec, false);
+    }
+
+    /// 同 drag_humanly，right 为 true 时按住右键拖动 (塔防镜头拖拽)
+    pub fn drag_button_humanly(&mut self, from: (u16, u16), to: (u16, u16), duration_sec: f32, right: bool) {
+        let approach = self.timing.move_duration.sample_ms() as f32 / 1000.0;
+        self.move_to_humanly(from.0, from.1, approach.max(0.05));
+        self.device.mouse_down(!right, right);
+        thread::sleep(Duration::from_millis(rand::thread_rng().gen_range(60..120)));
+        self.move_to_humanly(to.0, to.1, duration_sec);
+        thread::sleep(Duration::from_millis(rand::thread_rng().gen_range(40..90)));
+        self.device.mouse_up();
     }
 
     pub fn double_click_humanly(&mut self, left: bool, right: bool, interval_ms: u64) {
@@ -155,14 +607,31 @@ impl HumanDriver {
     }
 
     /// 【拟人化打字】
+    /// 按 self.typing 选择逐键输入或剪贴板粘贴；Auto 模式下遇到布局打不出的字符 (中文等) 整段粘贴
     pub fn type_humanly(&mut self, text: &str, base_wpm: f32) {
+        let typeable = text.chars().all(|ch| self.layout.char_stroke(ch).is_some());
+        let paste = match self.typing {
+            TypingMode::Keys => false,
+            TypingMode::Paste => true,
+            TypingMode::Auto => !typeable,
+        };
+        if paste {
+            match self.paste_text(text) {
+                Ok(()) => return,
+                Err(e) => warn!("⚠️ [输入] 粘贴失败 ({})，改为逐键输入", e),
+            }
+        }
+
         let base_delay_ms = 60.0 / (base_wpm * 5.0) * 1000.0;
         let normal_dist = Normal::new(base_delay_ms, base_delay_ms * 0.3).unwrap();
         let mut rng = rand::thread_rng();
 
         for ch in text.chars() {
-            // 直接复用我们新写的 key_click
-            self.key_click(ch);
+            // 文本输入保留大小写 (key_click 面向游戏按键，会统一转小写)
+            match self.layout.char_stroke(ch) {
+                Some(stroke) => self.hold_stroke(stroke, rng.gen_range(35..70)),
+                None => warn!("⚠️ [输入] 当前布局无法输入字符 '{}'，已跳过", ch),
+            }
 
             // 字符间的随机停顿
             let delay = normal_dist.sample(&mut rng).max(10.0) as u64;
@@ -170,10 +639,97 @@ impl HumanDriver {
         }
     }
 
+    /// 【粘贴输入】写入剪贴板后发送 Ctrl+V，完成后恢复原剪贴板文本
+    pub fn paste_text(&mut self, text: &str) -> Result<(), String> {
+        let previous = clipboard::get_text();
+        clipboard::set_text(text)?;
+        thread::sleep(Duration::from_millis(rand::thread_rng().gen_range(40..90)));
+        if !self.key_combo("ctrl+v", rand::thread_rng().gen_range(50..90)) {
+            return Err("当前布局无法解析 ctrl+v".to_string());
+        }
+        // 等游戏读取剪贴板后再恢复
+        thread::sleep(Duration::from_millis(150));
+        if let Some(prev) = previous {
+            let _ = clipboard::set_text(&prev);
+        }
+        Ok(())
+    }
+
     // ==========================================
     // 3. 数学辅助函数 (数学层)
     // ==========================================
 
+    /// 贝塞尔轨迹：控制点在 20% / 80% 处随机偏移，按速度曲线取 steps + 1 个点
+    fn bezier_path(start: (f32, f32), end: (f32, f32), steps: u32, profile: &MotionProfile) -> Vec<(f32, f32)> {
+        let mut rng = rand::thread_rng();
+        let v = profile.control_variance.max(0.001);
+        let ctrl1 = (
+            start.0 + (end.0 - start.0) * 0.2 + rng.gen_range(-v..v),
+            start.1 + (end.1 - start.1) * 0.2 + rng.gen_range(-v..v)
+        );
+        // 第二个控制点略偏向一侧，模拟手腕惯性
+        let ctrl2 = (
+            start.0 + (end.0 - start.0) * 0.8 + rng.gen_range(-v * 0.5..v * 1.5),
+            start.1 + (end.1 - start.1) * 0.8 + rng.gen_range(-v * 0.5..v * 1.5)
+        );
+        (0..=steps)
+            .map(|i| {
+                let t = i as f32 / steps as f32;
+                let t = match profile.speed_ramp {
+                    SpeedRamp::Linear => t,
+                    SpeedRamp::EaseInOut => Self::ease_in_out_cubic(t),
+                    SpeedRamp::EaseOut => 1.0 - (1.0 - t).powi(3),
+                };
+                Self::bezier_cubic(t, start, ctrl1, ctrl2, end)
+            })
+            .collect()
+    }
+
+    /// WindMouse (Benjamin J. Land)：重力拉向终点，风力随机扰动，接近终点时减速
+    fn wind_mouse_path(start: (f32, f32), end: (f32, f32), profile: &MotionProfile) -> Vec<(f32, f32)> {
+        let mut rng = rand::thread_rng();
+        let (gravity, wind_max) = (profile.gravity, profile.wind);
+        let mut max_step = profile.max_step.max(1.0);
+        let target_area = max_step * 0.8;
+        let (mut x, mut y) = start;
+        let (mut vx, mut vy, mut wx, mut wy) = (0.0f32, 0.0f32, 0.0f32, 0.0f32);
+        let mut path = vec![start];
+
+        // 保险：防止参数异常时死循环
+        for _ in 0..10_000 {
+            let dist = ((end.0 - x).powi(2) + (end.1 - y).powi(2)).sqrt();
+            if dist < 1.0 {
+                break;
+            }
+            let w = wind_max.min(dist);
+            if dist >= target_area {
+                wx = wx / 3f32.sqrt() + (rng.gen::<f32>() * 2.0 - 1.0) * w / 5f32.sqrt();
+                wy = wy / 3f32.sqrt() + (rng.gen::<f32>() * 2.0 - 1.0) * w / 5f32.sqrt();
+            } else {
+                wx /= 3f32.sqrt();
+                wy /= 3f32.sqrt();
+                if max_step < 3.0 {
+                    max_step = rng.gen::<f32>() * 3.0 + 3.0;
+                } else {
+                    max_step /= 5f32.sqrt();
+                }
+            }
+            vx += wx + gravity * (end.0 - x) / dist;
+            vy += wy + gravity * (end.1 - y) / dist;
+            let v = (vx * vx + vy * vy).sqrt();
+            if v > max_step {
+                let clip = max_step / 2.0 + rng.gen::<f32>() * max_step / 2.0;
+                vx = vx / v * clip;
+                vy = vy / v * clip;
+            }
+            x += vx;
+            y += vy;
+            path.push((x, y));
+        }
+        path.push(end);
+        path
+    }
+
     fn ease_in_out_cubic(t: f32) -> f32 {
         if t < 0.5 { 4.0 * t * t * t } else { 1.0 - (-2.0 * t + 2.0).powi(3) / 2.0 }
     }
@@ -186,4 +742,98 @@ impl HumanDriver {
         let y = uu * u * p0.1 + 3.0 * uu * t * p1.1 + 3.0 * u * tt * p2.1 + tt * t * p3.1;
         (x, y)
     }
-}
\ No newline at end of file
+}
+// ==========================================
+// 4. 空闲行为 (IdleBehavior)
+// ==========================================
+// 长时间 OCR 轮询时鼠标纹丝不动是明显的脚本特征。
+// 后台线程在输入队列空闲一段时间后，偶尔让光标做几像素的漂移再回到原位；
+// 一旦有真实命令投递 (或有按键处于按下状态) 立即收尾让路。
+
+/// config.toml 的 [idle]
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct IdleConfig {
+    pub enabled: bool,
+    /// 队列空闲多久后开始小动作 (毫秒)
+    pub after_ms: u64,
+    /// 两次小动作之间的停顿
+    pub interval: DelayRange,
+    /// 漂移的最大距离 (像素)
+    pub drift_px: i32,
+}
+
+impl Default for IdleConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            after_ms: 5000,
+            interval: DelayRange { min_ms: 3000, max_ms: 12000, dist: DelayDist::Normal },
+            drift_px: 3,
+        }
+    }
+}
+
+/// 空闲行为线程句柄；drop 或 stop() 后线程在下一次检查时退出
+pub struct IdleBehavior {
+    running: Arc<AtomicBool>,
+}
+
+impl IdleBehavior {
+    pub fn spawn(device: InputQueue, cfg: IdleConfig) -> Self {
+        let running = Arc::new(AtomicBool::new(true));
+        let flag = Arc::clone(&running);
+        thread::spawn(move || {
+            while flag.load(Ordering::Relaxed) {
+                cfg.interval.sleep();
+                if device.idle_for() >= Duration::from_millis(cfg.after_ms) {
+                    Self::drift(&device, cfg.drift_px.max(1), cfg.after_ms);
+                }
+            }
+        });
+        info!("🫧 [空闲] 已启用空闲小动作 (空闲 {}ms 后触发)", cfg.after_ms);
+        Self { running }
+    }
+
+    pub fn stop(&self) {
+        self.running.store(false, Ordering::Relaxed);
+    }
+
+    /// 逐像素漂移出去再回来；途中出现真实命令则一次性回到原位后退出
+    fn drift(device: &InputQueue, max_px: i32, after_ms: u64) {
+        let mut rng = rand::thread_rng();
+        let (tx, ty) = (rng.gen_range(-max_px..=max_px), rng.gen_range(-max_px..=max_px));
+        let steps = tx.abs().max(ty.abs()).max(1);
+        let (mut ox, mut oy) = (0, 0);
+        let busy = || device.idle_for() < Duration::from_millis(after_ms);
+
+        for i in 1..=steps {
+            if busy() {
+                break;
+            }
+            let (nx, ny) = (tx * i / steps, ty * i / steps);
+            device.idle_nudge(nx - ox, ny - oy);
+            (ox, oy) = (nx, ny);
+            thread::sleep(Duration::from_millis(rng.gen_range(25..70)));
+        }
+        if !busy() {
+            // 停顿片刻再慢慢回去
+            thread::sleep(Duration::from_millis(rng.gen_range(200..800)));
+            while (ox != 0 || oy != 0) && !busy() {
+                let (sx, sy) = (-ox.signum(), -oy.signum());
+                device.idle_nudge(sx, sy);
+                (ox, oy) = (ox + sx, oy + sy);
+                thread::sleep(Duration::from_millis(rng.gen_range(25..70)));
+            }
+        }
+        if ox != 0 || oy != 0 {
+            device.idle_nudge(-ox, -oy);
+        }
+    }
+}
+
+impl Drop for IdleBehavior {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
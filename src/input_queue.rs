@@ -0,0 +1,450 @@
+// src/input_queue.rs
+use crate::hardware::{FirmwareInfo, GamepadState, InputCommand, InputDriver, InputMetrics, KeyStroke};
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicU8, Ordering as AtomicOrdering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex, OnceLock};
+#[cfg(feature = "windows")]
+use windows::Win32::Foundation::BOOL;
+#[cfg(feature = "windows")]
+use windows::Win32::System::Console::SetConsoleCtrlHandler;
+use std::thread;
+use std::time::{Duration, Instant};
+use tracing::{error, info, warn};
+
+// ==========================================
+// 1. 输入命令队列
+// ==========================================
+// 底层驱动只由一个工作线程持有，调用方通过 InputQueue 投递命令，无需加锁。
+// 工作线程按优先级执行：心跳 > 紧急停止 > 普通输入；同优先级保持投递顺序。
+// 调用方的 sleep (如 key_hold 的按住时间) 不再占用驱动，心跳不会被长按阻塞。
+
+/// 命令优先级 (数值越大越先执行)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Priority {
+    Normal = 0,
+    Stop = 1,
+    Heartbeat = 2,
+}
+
+enum Job {
+    Command(InputCommand),
+    /// 丢弃之前排队的普通命令并释放所有按键/按钮，完成后可选地通知调用方
+    EmergencyStop(Option<Sender<()>>),
+    /// 之前的命令执行完毕后通知调用方
+    Flush(Sender<()>),
+}
+
+struct Queued {
+    priority: Priority,
+    seq: u64,
+    job: Job,
+}
+
+impl PartialEq for Queued {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Queued {}
+
+impl PartialOrd for Queued {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Queued {
+    /// BinaryHeap 为大顶堆：优先级高者在前，同优先级序号小者在前
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.priority.cmp(&other.priority).then_with(|| other.seq.cmp(&self.seq))
+    }
+}
+
+/// 连续多少次心跳未确认判定为 Unhealthy
+const UNHEALTHY_AFTER_MISSED: u32 = 3;
+
+/// 输入链路健康状态 (由心跳确认情况推断)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkHealth {
+    Healthy = 0,
+    /// 有心跳未确认，但尚未达到阈值
+    Degraded = 1,
+    /// 连续 UNHEALTHY_AFTER_MISSED 次未确认，应暂停自动化
+    Unhealthy = 2,
+}
+
+impl LinkHealth {
+    fn from_missed(missed: u32) -> Self {
+        match missed {
+            0 => LinkHealth::Healthy,
+            n if n < UNHEALTHY_AFTER_MISSED => LinkHealth::Degraded,
+            _ => LinkHealth::Unhealthy,
+        }
+    }
+
+    fn from_u8(v: u8) -> Self {
+        match v {
+            0 => LinkHealth::Healthy,
+            1 => LinkHealth::Degraded,
+            _ => LinkHealth::Unhealthy,
+        }
+    }
+}
+
+pub type HealthListener = Box<dyn Fn(LinkHealth) + Send + Sync>;
+
+/// 工作线程发布的驱动状态 (固件信息、链路指标)
+#[derive(Default)]
+struct Status {
+    firmware: Option<FirmwareInfo>,
+    gamepad: bool,
+    metrics: InputMetrics,
+}
+
+/// 输入命令队列句柄，可随意克隆并跨线程共享
+#[derive(Clone)]
+pub struct InputQueue {
+    tx: Sender<Queued>,
+    seq: Arc<AtomicU64>,
+    status: Arc<Mutex<Status>>,
+    heartbeat_running: Arc<AtomicBool>,
+    health: Arc<AtomicU8>,
+    unhealthy_listeners: Arc<Mutex<Vec<HealthListener>>>,
+    /// 最近一次普通命令的投递时间 (相对 started 的毫秒数)，供空闲行为判断
+    started: Instant,
+    last_command_ms: Arc<AtomicU64>,
+    /// 当前按住的输入：HELD_KEY / HELD_MOUSE
+    held: Arc<AtomicU8>,
+}
+
+const HELD_KEY: u8 = 0x01;
+const HELD_MOUSE: u8 = 0x02;
+
+impl InputQueue {
+    /// 启动工作线程，驱动的所有权转移给它
+    pub fn spawn(driver: Box<dyn InputDriver>) -> Self {
+        let (tx, rx) = mpsc::channel();
+        let gamepad = driver.supports_gamepad();
+        let status = Arc::new(Mutex::new(Status { firmware: driver.firmware(), gamepad, metrics: driver.metrics() }));
+        let worker_status = Arc::clone(&status);
+        thread::Builder::new()
+            .name("input-worker".to_string())
+            .spawn(move || Self::run(driver, rx, worker_status))
+            .expect("无法启动输入工作线程");
+        Self {
+            tx,
+            seq: Arc::new(AtomicU64::new(0)),
+            status,
+            heartbeat_running: Arc::new(AtomicBool::new(false)),
+            health: Arc::new(AtomicU8::new(LinkHealth::Healthy as u8)),
+            unhealthy_listeners: Arc::new(Mutex::new(Vec::new())),
+            started: Instant::now(),
+            last_command_ms: Arc::new(AtomicU64::new(0)),
+            held: Arc::new(AtomicU8::new(0)),
+        }
+    }
+
+    fn run(mut driver: Box<dyn InputDriver>, rx: Receiver<Queued>, status: Arc<Mutex<Status>>) {
+        let mut heap: BinaryHeap<Queued> = BinaryHeap::new();
+        loop {
+            if heap.is_empty() {
+                match rx.recv() {
+                    Ok(q) => heap.push(q),
+                    // 所有句柄都已释放
+                    Err(_) => break,
+                }
+            }
+            while let Ok(q) = rx.try_recv() {
+                heap.push(q);
+            }
+            let Some(next) = heap.pop() else { continue };
+            match next.job {
+                Job::Command(cmd) => cmd.apply(driver.as_mut()),
+                Job::EmergencyStop(done) => {
+                    heap.retain(|q| q.priority != Priority::Normal || q.seq > next.seq);
+                    driver.release_all();
+                    warn!("🛑 [输入] 紧急停止：已清空待执行命令并释放所有按键");
+                    if let Some(done) = done {
+                        let _ = done.send(());
+                    }
+                }
+                Job::Flush(done) => {
+                    let _ = done.send(());
+                }
+            }
+            if let Ok(mut s) = status.lock() {
+                s.metrics = driver.metrics();
+            }
+        }
+    }
+
+    fn push(&self, priority: Priority, job: Job) {
+        let seq = self.seq.fetch_add(1, AtomicOrdering::Relaxed);
+        let _ = self.tx.send(Queued { priority, seq, job });
+    }
+
+    fn command(&self, cmd: InputCommand) {
+        let now = self.started.elapsed().as_millis() as u64;
+        self.last_command_ms.store(now, AtomicOrdering::Relaxed);
+        match cmd {
+            InputCommand::KeyDown { .. } => { self.held.fetch_or(HELD_KEY, AtomicOrdering::Relaxed); }
+            InputCommand::KeyUp => { self.held.fetch_and(!HELD_KEY, AtomicOrdering::Relaxed); }
+            InputCommand::MouseDown { .. } => { self.held.fetch_or(HELD_MOUSE, AtomicOrdering::Relaxed); }
+            InputCommand::MouseUp => { self.held.fetch_and(!HELD_MOUSE, AtomicOrdering::Relaxed); }
+            _ => {}
+        }
+        self.push(Priority::Normal, Job::Command(cmd));
+    }
+
+    /// 距最近一次普通命令的时长；有按键或鼠标按钮处于按下状态时返回 0
+    pub fn idle_for(&self) -> Duration {
+        if self.held.load(AtomicOrdering::Relaxed) != 0 {
+            return Duration::ZERO;
+        }
+        let last = self.last_command_ms.load(AtomicOrdering::Relaxed);
+        Duration::from_millis((self.started.elapsed().as_millis() as u64).saturating_sub(last))
+    }
+
+    /// 空闲行为专用的相对移动：不刷新 idle_for，避免空闲行为把自己当作真实活动
+    pub fn idle_nudge(&self, dx: i32, dy: i32) {
+        self.push(Priority::Normal, Job::Command(InputCommand::MouseMove { dx, dy, wheel: 0 }));
+    }
+
+    pub fn heartbeat(&self) {
+        self.push(Priority::Heartbeat, Job::Command(InputCommand::Heartbeat));
+    }
+
+    pub fn switch_identity(&self, index: u8) {
+        self.command(InputCommand::SwitchIdentity { index });
+    }
+
+    pub fn mouse_abs(&self, x: u16, y: u16) {
+        self.command(InputCommand::MouseAbs { x, y });
+    }
+
+    pub fn mouse_move(&self, dx: i32, dy: i32, wheel: i8) {
+        self.command(InputCommand::MouseMove { dx, dy, wheel });
+    }
+
+    pub fn mouse_hwheel(&self, delta: i8) {
+        self.command(InputCommand::MouseHWheel { delta });
+    }
+
+    pub fn mouse_down(&self, left: bool, right: bool) {
+        self.command(InputCommand::MouseDown { left, right });
+    }
+
+    pub fn mouse_up(&self) {
+        self.command(InputCommand::MouseUp);
+    }
+
+    pub fn key_down(&self, keycode: u8, modifier: u8) {
+        self.command(InputCommand::KeyDown { keycode, modifier });
+    }
+
+    pub fn key_up(&self) {
+        self.command(InputCommand::KeyUp);
+    }
+
+    /// 同一帧按下组合键 (如 Ctrl+Shift+S)，用 key_up 释放
+    pub fn key_combo(&self, keys: &[KeyStroke]) {
+        let k = KeyStroke::combine(keys);
+        self.key_down(k.code, k.modifier);
+    }
+
+    /// 投递手柄状态；驱动是否支持见 supports_gamepad
+    pub fn gamepad(&self, state: GamepadState) {
+        self.command(InputCommand::Gamepad { state });
+    }
+
+    /// 底层驱动是否支持手柄报告
+    pub fn supports_gamepad(&self) -> bool {
+        self.status.lock().map(|s| s.gamepad).unwrap_or(false)
+    }
+
+    /// 紧急停止：跳过所有排队中的普通命令，立即松开鼠标与按键
+    pub fn emergency_stop(&self) {
+        self.held.store(0, AtomicOrdering::Relaxed);
+        self.push(Priority::Stop, Job::EmergencyStop(None));
+    }
+
+    /// 同 emergency_stop，但阻塞到释放完成 (最多 500ms)。
+    /// 可在信号处理函数、热键线程、panic hook 中调用。
+    pub fn panic_release(&self) {
+        let (done_tx, done_rx) = mpsc::channel();
+        self.held.store(0, AtomicOrdering::Relaxed);
+        self.push(Priority::Stop, Job::EmergencyStop(Some(done_tx)));
+        let _ = done_rx.recv_timeout(Duration::from_millis(500));
+    }
+
+    /// 安装兜底：任意线程 panic 或控制台收到 Ctrl+C / 关闭时执行 panic_release，
+    /// 保证崩溃的策略不会让按键一直处于按下状态
+    pub fn install_panic_guards(&self) {
+        let _ = PANIC_QUEUE.set(self.clone());
+
+        let default_hook = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            if let Some(q) = PANIC_QUEUE.get() {
+                q.panic_release();
+            }
+            default_hook(info);
+        }));
+
+        #[cfg(feature = "windows")]
+        unsafe {
+            let _ = SetConsoleCtrlHandler(Some(console_ctrl_handler), true);
+        }
+        #[cfg(feature = "linux")]
+        crate::control::add_signal_handler(release_on_signal);
+    }
+
+    /// 阻塞直到此前投递的普通命令全部执行完毕
+    pub fn flush(&self) {
+        let (done_tx, done_rx) = mpsc::channel();
+        self.push(Priority::Normal, Job::Flush(done_tx));
+        let _ = done_rx.recv();
+    }
+
+    pub fn firmware(&self) -> Option<FirmwareInfo> {
+        self.status.lock().ok().and_then(|s| s.firmware)
+    }
+
+    // ==========================================
+    // 2. 心跳与链路健康
+    // ==========================================
+
+    /// 启动心跳线程 (重复调用无效)。每个周期投递一次高优先级心跳，
+    /// 并根据连续未确认次数更新健康状态；变为 Unhealthy 时通知 on_unhealthy 回调。
+    pub fn start_heartbeat(&self, period: Duration) {
+        if self.heartbeat_running.swap(true, AtomicOrdering::SeqCst) {
+            return;
+        }
+        let queue = self.clone();
+        thread::Builder::new()
+            .name("input-heartbeat".to_string())
+            .spawn(move || {
+                while queue.heartbeat_running.load(AtomicOrdering::SeqCst) {
+                    queue.heartbeat();
+                    thread::sleep(period);
+                    queue.update_health();
+                }
+            })
+            .expect("无法启动心跳线程");
+    }
+
+    pub fn stop_heartbeat(&self) {
+        self.heartbeat_running.store(false, AtomicOrdering::SeqCst);
+    }
+
+    pub fn health(&self) -> LinkHealth {
+        LinkHealth::from_u8(self.health.load(AtomicOrdering::SeqCst))
+    }
+
+    /// 注册链路变为 Unhealthy 时的回调 (在心跳线程中调用)
+    pub fn on_unhealthy<F>(&self, f: F)
+    where
+        F: Fn(LinkHealth) + Send + Sync + 'static,
+    {
+        if let Ok(mut l) = self.unhealthy_listeners.lock() {
+            l.push(Box::new(f));
+        }
+    }
+
+    fn update_health(&self) {
+        let missed = self.metrics().missed_acks;
+        let now = LinkHealth::from_missed(missed);
+        let before = LinkHealth::from_u8(self.health.swap(now as u8, AtomicOrdering::SeqCst));
+        if now == before {
+            return;
+        }
+        match now {
+            LinkHealth::Healthy => info!("💚 [输入] 链路已恢复"),
+            LinkHealth::Degraded => warn!("💛 [输入] 心跳未确认 ({} 次)", missed),
+            LinkHealth::Unhealthy => {
+                error!("💔 [输入] 连续 {} 次心跳未确认，链路异常", missed);
+                if let Ok(listeners) = self.unhealthy_listeners.lock() {
+                    for f in listeners.iter() {
+                        f(now);
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn metrics(&self) -> InputMetrics {
+        self.status.lock().map(|s| s.metrics).unwrap_or_default()
+    }
+}
+
+static PANIC_QUEUE: OnceLock<InputQueue> = OnceLock::new();
+
+/// Ctrl+C / Ctrl+Break / 关闭控制台 (Linux 上为 SIGINT / SIGTERM)：先释放按键，再交给默认处理 (退出进程)
+fn release_on_signal() -> bool {
+    if let Some(q) = PANIC_QUEUE.get() {
+        q.panic_release();
+    }
+    false
+}
+
+#[cfg(feature = "windows")]
+unsafe extern "system" fn console_ctrl_handler(_ctrl_type: u32) -> BOOL {
+    BOOL(release_on_signal() as i32)
+}
+
+/// 让队列句柄也能当作 InputDriver 使用 (如录像回放)
+impl InputDriver for InputQueue {
+    fn heartbeat(&mut self) {
+        InputQueue::heartbeat(self);
+    }
+
+    fn switch_identity(&mut self, index: u8) {
+        InputQueue::switch_identity(self, index);
+    }
+
+    fn mouse_abs(&mut self, x: u16, y: u16) {
+        InputQueue::mouse_abs(self, x, y);
+    }
+
+    fn mouse_move(&mut self, dx: i32, dy: i32, wheel: i8) {
+        InputQueue::mouse_move(self, dx, dy, wheel);
+    }
+
+    fn mouse_hwheel(&mut self, delta: i8) {
+        InputQueue::mouse_hwheel(self, delta);
+    }
+
+    fn mouse_down(&mut self, left: bool, right: bool) {
+        InputQueue::mouse_down(self, left, right);
+    }
+
+    fn mouse_up(&mut self) {
+        InputQueue::mouse_up(self);
+    }
+
+    fn key_down(&mut self, keycode: u8, modifier: u8) {
+        InputQueue::key_down(self, keycode, modifier);
+    }
+
+    fn key_up(&mut self) {
+        InputQueue::key_up(self);
+    }
+
+    fn supports_gamepad(&self) -> bool {
+        InputQueue::supports_gamepad(self)
+    }
+
+    fn gamepad(&mut self, state: GamepadState) {
+        InputQueue::gamepad(self, state);
+    }
+
+    fn firmware(&self) -> Option<FirmwareInfo> {
+        InputQueue::firmware(self)
+    }
+
+    fn metrics(&self) -> InputMetrics {
+        InputQueue::metrics(self)
+    }
+}
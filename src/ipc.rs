@@ -0,0 +1,152 @@
+// src/ipc.rs
+use crate::api;
+use crate::control::RunControl;
+use serde_json::json;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::thread;
+#[cfg(not(feature = "windows"))]
+use std::time::Duration;
+use tracing::{info, warn};
+#[cfg(feature = "windows")]
+use std::fs::{File, OpenOptions};
+#[cfg(feature = "windows")]
+use std::os::windows::io::{AsRawHandle, FromRawHandle};
+#[cfg(feature = "windows")]
+use windows::core::PCWSTR;
+#[cfg(feature = "windows")]
+use windows::Win32::Foundation::{ERROR_PIPE_CONNECTED, HANDLE, INVALID_HANDLE_VALUE};
+#[cfg(feature = "windows")]
+use windows::Win32::Storage::FileSystem::PIPE_ACCESS_DUPLEX;
+#[cfg(feature = "windows")]
+use windows::Win32::System::Pipes::{
+    ConnectNamedPipe, CreateNamedPipeW, DisconnectNamedPipe, PIPE_READMODE_BYTE, PIPE_TYPE_BYTE, PIPE_UNLIMITED_INSTANCES,
+    PIPE_WAIT,
+};
+#[cfg(not(feature = "windows"))]
+use std::os::unix::net::{UnixListener, UnixStream};
+
+// ==========================================
+// 后台模式的状态管道
+// ==========================================
+// --daemon 时在命名管道 \\.\pipe\nzm_cmd (Linux 上为 Unix socket /tmp/nzm_cmd.sock) 上提供与 HTTP 接口相同的命令：
+// 客户端写一行 "status" / "pause" / "resume" / "stop" / "navigate <目标>"，服务端回复一行 JSON 后断开。
+// ctl 子命令即为客户端，也可用任何能读写命名管道 / socket 的工具连接 (如 socat)。
+
+#[cfg(feature = "windows")]
+pub const PIPE_NAME: &str = r"\\.\pipe\nzm_cmd";
+#[cfg(not(feature = "windows"))]
+pub const PIPE_NAME: &str = "/tmp/nzm_cmd.sock";
+
+/// 管道缓冲区大小 (状态 JSON 一般不超过几 KB)
+#[cfg(feature = "windows")]
+const BUFFER_SIZE: u32 = 64 * 1024;
+
+/// 在后台线程中逐个处理管道连接
+#[cfg(feature = "windows")]
+pub fn serve(control: RunControl) {
+    thread::spawn(move || loop {
+        let pipe = match create_instance() {
+            Ok(p) => p,
+            Err(e) => {
+                warn!("⚠️ [管道] 无法创建 {}: {}", PIPE_NAME, e);
+                return;
+            }
+        };
+        handle(pipe, &control);
+    });
+    info!("📡 [管道] 状态管道: {}", PIPE_NAME);
+}
+
+/// 在后台线程中逐个处理 socket 连接
+#[cfg(not(feature = "windows"))]
+pub fn serve(control: RunControl) {
+    // 上次异常退出时留下的 socket 文件会让 bind 失败
+    let _ = std::fs::remove_file(PIPE_NAME);
+    let listener = match UnixListener::bind(PIPE_NAME) {
+        Ok(l) => l,
+        Err(e) => {
+            warn!("⚠️ [管道] 无法创建 {}: {}", PIPE_NAME, e);
+            return;
+        }
+    };
+    thread::spawn(move || {
+        for stream in listener.incoming().map_while(Result::ok) {
+            // 连接逐个处理：连上后不发命令的客户端不能一直占住管道
+            let _ = stream.set_read_timeout(Some(Duration::from_secs(5)));
+            let _ = stream.set_write_timeout(Some(Duration::from_secs(5)));
+            respond(&stream, &control);
+        }
+    });
+    info!("📡 [管道] 状态管道: {}", PIPE_NAME);
+}
+
+/// 创建一个管道实例并等待客户端连接
+#[cfg(feature = "windows")]
+fn create_instance() -> windows::core::Result<File> {
+    let name: Vec<u16> = PIPE_NAME.encode_utf16().chain(std::iter::once(0)).collect();
+    let handle = unsafe {
+        CreateNamedPipeW(
+            PCWSTR(name.as_ptr()),
+            PIPE_ACCESS_DUPLEX,
+            PIPE_TYPE_BYTE | PIPE_READMODE_BYTE | PIPE_WAIT,
+            PIPE_UNLIMITED_INSTANCES,
+            BUFFER_SIZE,
+            BUFFER_SIZE,
+            0,
+            None,
+        )
+    };
+    if handle == INVALID_HANDLE_VALUE {
+        return Err(windows::core::Error::from_win32());
+    }
+    // 由 File 负责关闭句柄
+    let file = unsafe { File::from_raw_handle(handle.0 as _) };
+    match unsafe { ConnectNamedPipe(handle, None) } {
+        // 客户端在 Create 与 Connect 之间已连上
+        Err(e) if e.code() != ERROR_PIPE_CONNECTED.to_hresult() => Err(e),
+        _ => Ok(file),
+    }
+}
+
+#[cfg(feature = "windows")]
+fn handle(pipe: File, control: &RunControl) {
+    respond(&pipe, control);
+    // 等客户端读完再断开
+    let _ = pipe.sync_all();
+    unsafe {
+        let _ = DisconnectNamedPipe(HANDLE(pipe.as_raw_handle() as isize));
+    }
+}
+
+/// 读一行命令，回复一行 JSON
+fn respond<S>(stream: &S, control: &RunControl)
+where
+    for<'a> &'a S: Read + Write,
+{
+    let mut line = String::new();
+    if BufReader::new(stream).read_line(&mut line).is_err() {
+        return;
+    }
+    let line = line.trim();
+    let (command, arg) = line.split_once(' ').unwrap_or((line, ""));
+    let reply = match api::execute(command, arg.trim(), control) {
+        Ok(v) => v,
+        Err(e) => json!({ "error": e }),
+    };
+    let mut writer = stream;
+    let _ = writeln!(writer, "{}", reply);
+}
+
+/// 客户端：发送一条命令并返回服务端的回复
+pub fn request(command: &str) -> Result<String, String> {
+    #[cfg(feature = "windows")]
+    let pipe = OpenOptions::new().read(true).write(true).open(PIPE_NAME);
+    #[cfg(not(feature = "windows"))]
+    let pipe = UnixStream::connect(PIPE_NAME);
+    let pipe = pipe.map_err(|e| format!("无法连接 {} (后台进程是否以 --daemon 运行?): {}", PIPE_NAME, e))?;
+    let mut writer = &pipe;
+    writeln!(writer, "{}", command).map_err(|e| e.to_string())?;
+    let mut reply = String::new();
+    BufReader::new(&pipe).read_line(&mut reply).map_err(|e| e.to_string())?;
+    Ok(reply.trim().to_string())
+}
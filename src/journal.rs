@@ -0,0 +1,128 @@
+// src/journal.rs
+use chrono::Local;
+use image::RgbaImage;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+// ==========================================
+// 塔防动作日志
+// ==========================================
+// 每局一个 JSONL 文件 (如 logs/journal/td_20240101_203000.jsonl)，每执行一次放置 / 升级 / 拆除 / 技能写一行：
+// 波次、阶段、uid、地图坐标、屏幕坐标、镜头偏移、最近一次识别的金币，以及可选的格子截图。
+// 用于排查 "墙为什么放偏了一格" 这类问题；journal 子命令按波次打印。
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct JournalEntry {
+    pub ts: String,
+    pub wave: i32,
+    pub is_late: bool,
+    /// place / upgrade / demolish / ability
+    pub action: String,
+    #[serde(default)]
+    pub uid: Option<usize>,
+    pub name: String,
+    /// 地图像素坐标 (升级与技能没有)
+    #[serde(default)]
+    pub map: Option<[f32; 2]>,
+    #[serde(default)]
+    pub screen: Option<[f32; 2]>,
+    pub camera: [f32; 2],
+    /// 按下的按键
+    #[serde(default)]
+    pub key: Option<char>,
+    #[serde(default)]
+    pub gold: Option<u32>,
+    pub ok: bool,
+    /// 动作后的格子截图 (相对日志文件所在目录)
+    #[serde(default)]
+    pub snapshot: Option<String>,
+}
+
+pub struct Journal {
+    file: File,
+    /// 截图目录：与日志同名的文件夹
+    snapshot_dir: PathBuf,
+    seq: AtomicUsize,
+}
+
+impl Journal {
+    /// 在 dir 下新建本局日志
+    pub fn create(dir: &str) -> std::io::Result<Self> {
+        fs::create_dir_all(dir)?;
+        let stem = format!("td_{}", Local::now().format("%Y%m%d_%H%M%S"));
+        let path = Path::new(dir).join(format!("{}.jsonl", stem));
+        println!("📒 [日志] 本局动作记录: {}", path.display());
+        Ok(Self {
+            file: File::create(&path)?,
+            snapshot_dir: Path::new(dir).join(stem),
+            seq: AtomicUsize::new(0),
+        })
+    }
+
+    /// 写入一行；提供截图时保存为 PNG 并记录相对路径
+    pub fn write(&self, mut entry: JournalEntry, snapshot: Option<&RgbaImage>) {
+        let seq = self.seq.fetch_add(1, Ordering::Relaxed);
+        if let Some(img) = snapshot {
+            let name = format!("{:04}_{}_{}.png", seq, entry.action, entry.uid.unwrap_or(0));
+            let path = self.snapshot_dir.join(&name);
+            if fs::create_dir_all(&self.snapshot_dir).is_ok() && img.save(&path).is_ok() {
+                let dir_name = self.snapshot_dir.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+                entry.snapshot = Some(format!("{}/{}", dir_name, name));
+            }
+        }
+        entry.ts = Local::now().format("%H:%M:%S%.3f").to_string();
+        if let Ok(line) = serde_json::to_string(&entry) {
+            let _ = writeln!(&self.file, "{}", line);
+        }
+    }
+}
+
+/// 读取动作日志 (跳过无法解析的行)
+pub fn load(path: &str) -> Result<Vec<JournalEntry>, String> {
+    let file = File::open(path).map_err(|e| format!("无法打开动作日志 {}: {}", path, e))?;
+    Ok(BufReader::new(file)
+        .lines()
+        .map_while(Result::ok)
+        .filter_map(|l| serde_json::from_str(&l).ok())
+        .collect())
+}
+
+/// 按波次打印动作日志；uid 不为空时只显示该建造的记录
+pub fn print(path: &str, uid: Option<usize>) -> Result<(), String> {
+    let entries = load(path)?;
+    let shown: Vec<&JournalEntry> = entries.iter().filter(|e| uid.is_none() || e.uid == uid).collect();
+    println!("📒 {} ({} 条{})", path, shown.len(), uid.map(|u| format!("，uid {}", u)).unwrap_or_default());
+
+    let mut phase = None;
+    for e in &shown {
+        if phase != Some((e.wave, e.is_late)) {
+            phase = Some((e.wave, e.is_late));
+            println!("\n── 第 {} 波 [{}] ──", e.wave, if e.is_late { "后期" } else { "前期" });
+        }
+        let fmt_pt = |p: Option<[f32; 2]>| p.map(|[x, y]| format!("({:.0}, {:.0})", x, y)).unwrap_or_else(|| "-".to_string());
+        println!(
+            "  {} {} {:<8} {}{} 地图 {} 屏幕 {} 镜头 ({:.0}, {:.0}){}{}{}",
+            e.ts,
+            if e.ok { "✅" } else { "❌" },
+            e.action,
+            e.name,
+            e.uid.map(|u| format!(" #{}", u)).unwrap_or_default(),
+            fmt_pt(e.map),
+            fmt_pt(e.screen),
+            e.camera[0],
+            e.camera[1],
+            e.key.map(|k| format!(" 键 '{}'", k)).unwrap_or_default(),
+            e.gold.map(|g| format!(" 金币 {}", g)).unwrap_or_default(),
+            e.snapshot.as_deref().map(|s| format!(" 📷 {}", s)).unwrap_or_default(),
+        );
+    }
+
+    let failed = shown.iter().filter(|e| !e.ok).count();
+    if failed > 0 {
+        println!("\n⚠️ 失败动作 {} 条", failed);
+    }
+    Ok(())
+}
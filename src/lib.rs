@@ -1,7 +1,53 @@
 // src/lib.rs
 
+// 平台实现二选一：windows (默认) 或 linux
+#[cfg(all(feature = "windows", feature = "linux"))]
+compile_error!("windows 与 linux feature 只能启用一个 (Linux 上请使用 --no-default-features --features linux)");
+#[cfg(not(any(feature = "windows", feature = "linux")))]
+compile_error!("需要启用 windows (默认) 或 linux feature");
+#[cfg(all(feature = "windows", not(windows)))]
+compile_error!("windows feature 只能在 Windows 上编译 (Linux 上请使用 --no-default-features --features linux)");
+#[cfg(all(feature = "linux", not(target_os = "linux")))]
+compile_error!("linux feature 只能在 Linux 上编译");
+
 pub mod hardware;      // 新增：底层驱动
+pub mod controllers;   // 第三方控制器 (KMBox Net / CH9329)
+#[cfg(feature = "linux")]
+pub mod uinput;        // Linux 软件输入 (/dev/uinput 虚拟键鼠)
+pub mod input_queue;   // 输入命令队列 (工作线程 + 优先级)
 pub mod human;         // 拟人化层
+pub mod gamepad;       // 手柄输入层
+pub mod capture;       // 截图后端 (DXGI 桌面复制 / GDI / X11)
+pub mod frames;        // 共享帧服务 (截图复用、区域订阅)
 pub mod nav;           // 视觉导航层
 pub mod tower_defense; // 业务逻辑层
-pub mod daily_routine; // 日常任务层
\ No newline at end of file
+pub mod daily_routine; // 日常任务层
+pub mod handler;       // 场景处理器注册表 (Handover 分发)
+pub mod digits;        // 数字模板识别 (OCR 兜底)
+pub mod vision;        // 模板匹配 (灰度 NCC，积分图加速)
+pub mod config;        // 全局配置 (config.toml)
+pub mod retention;     // 日志/截图保留策略
+pub mod ocr;           // OCR 后端 (WinRT / PaddleOCR / Tesseract)
+pub mod recorder;      // 输入录制与回放
+pub mod session;       // 会话录像 (截图 + 输入 + 识别事件)
+pub mod bench;         // 感知基准测试 (截图 / OCR / 模板匹配 / 串口往返)
+pub mod fixture;       // 测试夹具 (预录截图 + 模拟输入)
+pub mod clipboard;     // 系统剪贴板 (粘贴输入)
+pub mod trace;         // 鼠标轨迹调试导出与渲染
+pub mod calibration;   // 镜头灵敏度校准
+pub mod macros;        // 动作序列 (宏) 构建与执行
+pub mod control;       // 运行控制 (暂停 / 停止、Ctrl+C 与热键)
+pub mod journal;       // 塔防动作日志与回看
+pub mod validate;      // 塔防策略 / 地形校验
+pub mod schedule;      // 定时运行 (每日时刻 / 固定间隔)
+pub mod logging;       // 日志 (tracing，控制台 + 滚动文件)
+pub mod api;           // 本地控制接口 (HTTP：状态查询、暂停 / 停止、切换目标)
+pub mod metrics;       // 运行指标 (Prometheus /metrics)
+pub mod ipc;           // 后台模式的命名管道 / Unix socket (ctl 子命令)
+pub mod report;        // 机器可读的运行结果 (--result-file)
+pub mod notify;        // 远程通知 (webhook / Discord)
+pub mod watchdog;      // 卡住检测 (长时间无场景 / 波次变化)
+#[cfg(feature = "tui")]
+pub mod tui;           // 终端面板 (--tui)
+#[cfg(feature = "script")]
+pub mod script;        // 脚本场景处理器 (rhai)
\ No newline at end of file
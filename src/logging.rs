@@ -0,0 +1,129 @@
+// src/logging.rs
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fmt;
+use tracing::{Event, Subscriber};
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_appender::rolling::{Builder, Rotation};
+use tracing_subscriber::fmt::format::Writer;
+use tracing_subscriber::fmt::time::ChronoLocal;
+use tracing_subscriber::fmt::{FmtContext, FormatEvent, FormatFields};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::EnvFilter;
+
+// ==========================================
+// 日志 (tracing)
+// ==========================================
+// 控制台只输出消息本身 (与原来的 println 一致)；日志文件额外带时间、级别、模块与所在 span
+// (如 navigate{target=...}、td{map=...})，按天 / 小时滚动，只保留最近 max_files 个。
+// 级别可按模块单独设置；环境变量 NZM_LOG (EnvFilter 语法) 优先于配置文件。
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct LoggingConfig {
+    /// 默认级别：error / warn / info / debug / trace
+    pub level: String,
+    /// 按模块覆盖级别，如 { "nzm_cmd::nav" = "debug" }
+    pub modules: BTreeMap<String, String>,
+    /// 日志文件目录；为空时不写文件
+    pub dir: String,
+    /// daily / hourly / never
+    pub rotation: String,
+    /// 保留的日志文件数 (0 = 不清理)
+    pub max_files: usize,
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        Self {
+            level: "info".to_string(),
+            modules: BTreeMap::new(),
+            dir: "logs/app".to_string(),
+            rotation: "daily".to_string(),
+            max_files: 14,
+        }
+    }
+}
+
+impl LoggingConfig {
+    /// EnvFilter 语法的过滤规则
+    pub fn filter_directives(&self) -> String {
+        std::iter::once(self.level.clone())
+            .chain(self.modules.iter().map(|(m, l)| format!("{}={}", m, l)))
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+}
+
+/// 控制台格式：只输出消息与字段，保持原有的 emoji 风格
+struct ConsoleFormat;
+
+impl<S, N> FormatEvent<S, N> for ConsoleFormat
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'a> FormatFields<'a> + 'static,
+{
+    fn format_event(&self, ctx: &FmtContext<'_, S, N>, mut writer: Writer<'_>, event: &Event<'_>) -> fmt::Result {
+        ctx.field_format().format_fields(writer.by_ref(), event)?;
+        writeln!(writer)
+    }
+}
+
+/// 初始化全局日志；返回的 guard 需存活到进程结束，否则文件日志可能丢失末尾几行
+pub fn init(config: &LoggingConfig) -> Option<WorkerGuard> {
+    let directives = std::env::var("NZM_LOG").unwrap_or_else(|_| config.filter_directives());
+    let filter = EnvFilter::try_new(&directives).unwrap_or_else(|e| {
+        println!("⚠️ [日志] 无效的级别设置 \"{}\" ({})，使用 info", directives, e);
+        EnvFilter::new("info")
+    });
+    // 控制台与文件共用 span 字段的格式化结果，两边都关闭 ANSI 才不会把颜色码写进文件
+    let console = tracing_subscriber::fmt::layer().with_ansi(false).event_format(ConsoleFormat);
+
+    let (file, guard) = match open_file(config) {
+        Some((writer, guard)) => {
+            let layer = tracing_subscriber::fmt::layer()
+                .with_ansi(false)
+                .with_timer(ChronoLocal::new("%Y-%m-%d %H:%M:%S%.3f".to_string()))
+                .with_writer(writer);
+            (Some(layer), Some(guard))
+        }
+        None => (None, None),
+    };
+
+    if tracing_subscriber::registry().with(filter).with(console).with(file).try_init().is_err() {
+        println!("⚠️ [日志] 全局日志已被初始化，忽略本次设置");
+    }
+    guard
+}
+
+fn open_file(config: &LoggingConfig) -> Option<(tracing_appender::non_blocking::NonBlocking, WorkerGuard)> {
+    if config.dir.is_empty() {
+        return None;
+    }
+    if let Err(e) = std::fs::create_dir_all(&config.dir) {
+        println!("⚠️ [日志] 无法创建日志目录 {}: {}", config.dir, e);
+        return None;
+    }
+    let rotation = match config.rotation.as_str() {
+        "hourly" => Rotation::HOURLY,
+        "never" => Rotation::NEVER,
+        "daily" => Rotation::DAILY,
+        other => {
+            println!("⚠️ [日志] 未知的滚动方式 \"{}\"，按 daily 处理", other);
+            Rotation::DAILY
+        }
+    };
+    let mut builder = Builder::new().rotation(rotation).filename_prefix("nzm").filename_suffix("log");
+    if config.max_files > 0 {
+        builder = builder.max_log_files(config.max_files);
+    }
+    match builder.build(&config.dir) {
+        Ok(appender) => Some(tracing_appender::non_blocking(appender)),
+        Err(e) => {
+            println!("⚠️ [日志] 无法创建日志文件 ({}): {}", config.dir, e);
+            None
+        }
+    }
+}
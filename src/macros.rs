@@ -0,0 +1,157 @@
+// src/macros.rs
+use crate::human::{DelayRange, HumanDriver};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::thread;
+use std::time::Duration;
+use tracing::warn;
+
+// ==========================================
+// 动作序列 (宏)
+// ==========================================
+// 把常见的 "移动 -> 点击 -> 等待 -> 按键" 串成一个可复用的序列：
+//   Macro::new().move_to(960, 540).click().wait_range(200, 400).key('g').run(&mut human);
+// 所有步骤经 HumanDriver 执行，沿用其轨迹与节奏配置；序列可与 TOML 互转:
+//   [[steps]]
+//   type = "move_to"
+//   x = 960
+//   y = 540
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum MacroStep {
+    /// 拟人移动；未指定耗时则取 timing.move_duration
+    MoveTo {
+        x: u16,
+        y: u16,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        duration: Option<f32>,
+    },
+    Click {
+        #[serde(default)]
+        right: bool,
+    },
+    DoubleClick,
+    /// 长按鼠标左键
+    HoldClick { ms: u64 },
+    /// 按键名或组合键 ("g" / "esc" / "ctrl+s")
+    Key { key: String },
+    KeyHold { key: String, ms: u64 },
+    Type { text: String },
+    Scroll { delta: i32 },
+    Wait { ms: u64 },
+    WaitRange { min_ms: u64, max_ms: u64 },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct Macro {
+    #[serde(default)]
+    pub steps: Vec<MacroStep>,
+}
+
+impl Macro {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn push(mut self, step: MacroStep) -> Self {
+        self.steps.push(step);
+        self
+    }
+
+    pub fn move_to(self, x: u16, y: u16) -> Self {
+        self.push(MacroStep::MoveTo { x, y, duration: None })
+    }
+
+    pub fn move_to_in(self, x: u16, y: u16, duration: f32) -> Self {
+        self.push(MacroStep::MoveTo { x, y, duration: Some(duration) })
+    }
+
+    pub fn click(self) -> Self {
+        self.push(MacroStep::Click { right: false })
+    }
+
+    pub fn right_click(self) -> Self {
+        self.push(MacroStep::Click { right: true })
+    }
+
+    pub fn double_click(self) -> Self {
+        self.push(MacroStep::DoubleClick)
+    }
+
+    pub fn hold_click(self, ms: u64) -> Self {
+        self.push(MacroStep::HoldClick { ms })
+    }
+
+    /// 短按一个键；接受字符 ('g') 或按键名 ("esc" / "ctrl+s")
+    pub fn key(self, key: impl Into<String>) -> Self {
+        self.push(MacroStep::Key { key: key.into() })
+    }
+
+    pub fn key_hold(self, key: impl Into<String>, ms: u64) -> Self {
+        self.push(MacroStep::KeyHold { key: key.into(), ms })
+    }
+
+    pub fn type_text(self, text: impl Into<String>) -> Self {
+        self.push(MacroStep::Type { text: text.into() })
+    }
+
+    pub fn scroll(self, delta: i32) -> Self {
+        self.push(MacroStep::Scroll { delta })
+    }
+
+    pub fn wait(self, ms: u64) -> Self {
+        self.push(MacroStep::Wait { ms })
+    }
+
+    pub fn wait_range(self, min_ms: u64, max_ms: u64) -> Self {
+        self.push(MacroStep::WaitRange { min_ms, max_ms })
+    }
+
+    pub fn from_toml(content: &str) -> Result<Self, String> {
+        toml::from_str(content).map_err(|e| format!("宏格式错误: {}", e))
+    }
+
+    pub fn to_toml(&self) -> Result<String, String> {
+        toml::to_string_pretty(self).map_err(|e| e.to_string())
+    }
+
+    pub fn load(path: &str) -> Result<Self, String> {
+        let content = fs::read_to_string(path).map_err(|e| format!("无法读取宏 {}: {}", path, e))?;
+        Self::from_toml(&content)
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        fs::write(path, self.to_toml()?).map_err(|e| format!("无法保存宏 {}: {}", path, e))
+    }
+
+    /// 依次执行所有步骤
+    pub fn run(&self, human: &mut HumanDriver) {
+        for step in &self.steps {
+            match step {
+                MacroStep::MoveTo { x, y, duration } => {
+                    let secs = duration.unwrap_or_else(|| human.timing.move_duration.sample_ms() as f32 / 1000.0);
+                    human.move_to_humanly(*x, *y, secs.max(0.05));
+                }
+                MacroStep::Click { right } => human.click_humanly(!right, *right, 0),
+                MacroStep::DoubleClick => human.double_click_humanly(true, false, 80),
+                MacroStep::HoldClick { ms } => human.click_hold_humanly(true, false, *ms),
+                MacroStep::Key { key } => {
+                    let hold = human.timing.key_hold.sample_ms().max(1);
+                    if !human.key_hold_name(key, hold) {
+                        warn!("⚠️ [宏] 无法解析按键 '{}'", key);
+                    }
+                }
+                MacroStep::KeyHold { key, ms } => {
+                    if !human.key_hold_name(key, *ms) {
+                        warn!("⚠️ [宏] 无法解析按键 '{}'", key);
+                    }
+                }
+                MacroStep::Type { text } => human.type_humanly(text, 60.0),
+                MacroStep::Scroll { delta } => human.scroll_humanly(*delta, 0.12 * delta.unsigned_abs() as f32),
+                MacroStep::Wait { ms } => thread::sleep(Duration::from_millis(*ms)),
+                MacroStep::WaitRange { min_ms, max_ms } => DelayRange::uniform(*min_ms, *max_ms).sleep(),
+            }
+        }
+    }
+}
@@ -1,169 +1,1110 @@
 // src/main.rs
-use clap::Parser;
+use chrono::Local;
+use clap::{Parser, Subcommand, ValueEnum};
+use nzm_cmd::config::{AppConfig, GeneralConfig};
 use nzm_cmd::daily_routine::DailyRoutineApp;
-use nzm_cmd::hardware::{create_driver, DriverType, InputDriver};
-use nzm_cmd::human::HumanDriver;
-use nzm_cmd::nav::{NavEngine, NavResult};
-use nzm_cmd::tower_defense::TowerDefenseApp;
+use nzm_cmd::handler::{HandlerOutcome, HandlerRegistry, TdHandler};
+use nzm_cmd::hardware::{create_driver, DriverType, InputDriver, KeyLayout, SUPPORTED_PROTOCOL};
+use nzm_cmd::human::{HumanDriver, IdleBehavior};
+use nzm_cmd::input_queue::{InputQueue, LinkHealth};
+use nzm_cmd::nav::{game_screen, NavEngine, NavEventKind, NavResult};
+use nzm_cmd::api;
+use nzm_cmd::bench::{self, BenchOptions};
+use nzm_cmd::ipc;
+use nzm_cmd::metrics;
+use nzm_cmd::ocr;
+use nzm_cmd::recorder::{self, RecordingDriver};
+use nzm_cmd::report::ResultRecorder;
+use nzm_cmd::trace;
+use nzm_cmd::journal;
+use nzm_cmd::logging;
+use nzm_cmd::notify::{self, NotifyEvent};
+use nzm_cmd::validate::{self, Severity};
+use nzm_cmd::control::RunControl;
+use nzm_cmd::calibration::{self, Calibration};
+use nzm_cmd::capture;
+use nzm_cmd::retention;
+use nzm_cmd::session::{self, SessionRecorder};
+use nzm_cmd::tower_defense::{RunOutcome, RunSummary, TdProgress, TowerDefenseApp};
 use screenshots::Screen;
 use std::sync::{Arc, Mutex};
 use std::thread;
 use std::time::{Duration, Instant};
+use tracing::{error, info, warn};
+#[cfg(feature = "windows")]
+use windows::Win32::System::Console::FreeConsole;
+
+/// 每局塔防结束后返回的大厅场景
+const LOBBY_SCENE: &str = "游戏大厅主界面";
+/// 未指定 --target 时的目标
+const DEFAULT_TARGET: &str = "空间站普通";
+/// 多目标队列中，同一目标连续导航失败达到此次数后跳到下一个目标
+const QUEUE_NAV_FAILURE_LIMIT: u32 = 3;
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
-struct Args {
-    #[arg(short, long, default_value = "COM3")]
-    port: String,
+struct Cli {
+    #[command(flatten)]
+    device: DeviceArgs,
 
-    #[arg(short, long, default_value = "空间站普通")]
-    target: String,
+    /// 不写子命令时等同于 run
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+/// 所有子命令通用的设备 / 场景图参数 (可写在子命令前或后)；未写的项取 config.toml 的 [general]
+#[derive(clap::Args, Debug)]
+struct DeviceArgs {
+    /// 全局配置文件
+    #[arg(long, default_value = "config.toml", global = true)]
+    config: String,
+
+    /// 串口号 (如 COM9)；AUTO 自动探测硬件盒子，SOFT 软件模拟，NONE 空驱动 (默认 AUTO)
+    #[arg(short, long, global = true)]
+    port: Option<String>,
+
+    /// 输入设备类型：serial (自研串口盒子) / kmbox / ch9329 / soft / none (默认 serial)
+    #[arg(long, global = true)]
+    device_type: Option<String>,
+
+    /// 场景图：单个 TOML 文件，或存放多个 TOML 片段的目录 (如 maps/，默认 ui_map.toml)
+    #[arg(long, global = true)]
+    map: Option<String>,
+
+    /// 严格模式：ui_map.toml 校验出错误时拒绝启动
+    #[arg(long, global = true)]
+    strict: bool,
 
+    /// 录制本次运行下发的所有键鼠命令到文件 (JSON Lines)
+    #[arg(long, global = true)]
+    record: Option<String>,
+
+    /// 会话录像：定时保存缩小截图，并记录键鼠命令、场景识别与 OCR 结果 (sessions/<时间>/，可在 UI_tool 回放)
+    #[arg(long, global = true)]
+    session: bool,
+
+    /// 将每条拟人鼠标轨迹写入文件 (JSON Lines)
+    #[arg(long, global = true)]
+    trace: Option<String>,
+
+    /// 无设备模式：强制使用空驱动，只打印键鼠动作，导航与塔防流程照常执行
+    #[arg(long, alias = "simulate", global = true)]
+    no_device: bool,
+
+    /// 终端面板：链路状态、场景、波次、最近动作与可滚动的日志 (需 --features tui 编译)
+    #[arg(long, global = true)]
+    tui: bool,
+
+    /// 运行配置：使用 config.toml 中 [profiles.<名称>] 的目标、装备栏、数据文件、节奏与界面参数
+    #[arg(long, global = true)]
+    profile: Option<String>,
+
+    /// 后台模式：启动后脱离控制台，只写日志文件，状态与停止请求走命名管道 (见 ctl 子命令)
+    #[arg(long, global = true)]
+    daemon: bool,
+
+    /// 结束时写出 JSON 运行结果 (最终状态、导航结果、经过的场景、每局波次、错误)；"-" 输出到标准输出
+    #[arg(long, global = true)]
+    result_file: Option<String>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// 导航到目标并循环执行 (默认)
+    Run(RunArgs),
+    /// 刷图模式：只循环 导航 -> 塔防 -> 回大厅，结束时打印每局结果汇总
+    Farm(FarmArgs),
+    /// 定时运行：按 [schedule] 或 --at / --every-hours 启动刷图，两轮之间待机 (心跳照常)
+    Schedule(ScheduleArgs),
+    /// 硬件 / 识别测试
+    Test {
+        #[arg(value_enum)]
+        mode: TestMode,
+    },
+    /// 检查地形与策略文件 (uid 重复、越界、重叠、无效的升级/拆除)，有错误时返回 1
+    Validate(ValidateArgs),
+    /// 离线模拟塔防策略，打印完整动作时间线后退出 (不发送任何输入)
+    Simulate {
+        /// 不填时取运行配置的 target，再不填为空间站普通
+        #[arg(short, long)]
+        target: Option<String>,
+    },
+    /// 按原始时间回放录像文件
+    Playback { path: String },
+    /// 将轨迹文件叠加到截图上，输出同名 .png
+    RenderTrace {
+        path: String,
+        /// 背景截图
+        #[arg(long, default_value = "screenshot.png")]
+        bg: String,
+    },
+    /// 连接 --daemon 运行的后台进程：status / pause / resume / stop / navigate <目标>
+    Ctl {
+        command: String,
+        /// navigate 的目标
+        target: Option<String>,
+    },
+    /// 按波次打印塔防动作日志 (logs/journal/*.jsonl)
+    Journal {
+        path: String,
+        /// 只显示该 uid 的记录
+        #[arg(long)]
+        uid: Option<usize>,
+    },
+    /// 感知基准测试：截图、OCR、模板匹配与串口往返的耗时百分位 (不发送键鼠输入)
+    Bench(BenchArgs),
+    /// 按时间打印会话录像的事件 (sessions/<时间>/)
+    Session {
+        path: String,
+        /// 只显示该类事件：输入 / 场景 / 跳转 / OCR / 弹窗 / 导航 / 截图
+        #[arg(long)]
+        only: Option<String>,
+    },
+}
+
+/// run / farm 的目标：可重复 --target，或从队列文件读取
+#[derive(clap::Args, Debug, Clone, Default)]
+struct TargetArgs {
+    /// 目标场景，可重复指定以按顺序执行多个目标 (如 -t 空间站普通 -t 地铁炼狱)；
+    /// 不填时取运行配置的 target，再不填为空间站普通
+    #[arg(short, long = "target")]
+    targets: Vec<String>,
+
+    /// 目标队列文件：每行一个目标，空行与 # 开头的行忽略；指定后忽略 --target
     #[arg(long)]
-    test: Option<String>,
+    queue: Option<String>,
+}
+
+impl TargetArgs {
+    /// 按顺序返回要执行的目标；未指定任何目标时为 [fallback]
+    fn resolve(&self, fallback: &str) -> Result<Vec<String>, String> {
+        let Some(path) = &self.queue else {
+            if self.targets.is_empty() {
+                return Ok(vec![fallback.to_string()]);
+            }
+            return Ok(self.targets.clone());
+        };
+        let text = std::fs::read_to_string(path).map_err(|e| format!("无法读取目标队列 {}: {}", path, e))?;
+        let targets: Vec<String> = text
+            .lines()
+            .map(str::trim)
+            .filter(|l| !l.is_empty() && !l.starts_with('#'))
+            .map(String::from)
+            .collect();
+        if targets.is_empty() {
+            return Err(format!("目标队列 {} 为空", path));
+        }
+        Ok(targets)
+    }
+}
+
+#[derive(clap::Args, Debug, Default)]
+struct RunArgs {
+    #[command(flatten)]
+    targets: TargetArgs,
+
+    /// 塔防连续刷的局数，达到后退出 (0 = 不限；多个目标时为每个目标的局数，0 视为 1)
+    #[arg(long, default_value_t = 0)]
+    loops: u32,
+
+    /// 从塔防进度文件继续上一局 (跳过导航与入场)，如 logs/td_progress.json
+    #[arg(long)]
+    resume: Option<String>,
+
+    /// 只打印导航路线，不执行任何点击
+    #[arg(long)]
+    dry_run: bool,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+struct FarmArgs {
+    #[command(flatten)]
+    targets: TargetArgs,
+
+    /// 局数，达到后退出 (0 = 不限；多个目标时为每个目标的局数，0 视为 1)
+    #[arg(long, default_value_t = 0)]
+    loops: u32,
+
+    /// 总时长 (分钟)，到时后打完当前局退出，多个目标时为整个队列的时长 (0 = 不限)
+    #[arg(long, default_value_t = 0)]
+    minutes: u64,
+}
+
+#[derive(clap::Args, Debug)]
+struct ScheduleArgs {
+    /// 每轮的目标、局数与时长 (局数 0 视为每个目标 1 局)
+    #[command(flatten)]
+    farm: FarmArgs,
+
+    /// 每天的启动时刻 (HH:MM)，可重复；指定后覆盖 [schedule] at
+    #[arg(long)]
+    at: Vec<String>,
+
+    /// 每隔多少小时启动一轮；指定后覆盖 [schedule] every_hours
+    #[arg(long)]
+    every_hours: Option<f64>,
+}
+
+#[derive(clap::Args, Debug)]
+struct ValidateArgs {
+    /// 地形文件；不填时按 --target 推断
+    terrain: Option<String>,
+
+    /// 策略文件；不填时把地形文件名中的 "地图" 换成 "策略"
+    strategy: Option<String>,
+
+    /// 不填时取运行配置的 target，再不填为空间站普通
+    #[arg(short, long)]
+    target: Option<String>,
+}
+
+#[derive(clap::Args, Debug)]
+struct BenchArgs {
+    /// 每项的测量次数
+    #[arg(short = 'n', long, default_value_t = 50)]
+    iterations: usize,
+
+    /// 只测这些项 (可重复)，不填时全部测试
+    #[arg(long, value_enum)]
+    only: Vec<BenchPart>,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+enum BenchPart {
+    /// 整屏 / 区域截图
+    Capture,
+    /// 不同区域尺寸的 OCR
+    Ocr,
+    /// 模板匹配
+    Match,
+    /// 串口心跳往返
+    Serial,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum TestMode {
+    /// 键鼠
+    Input,
+    /// 截图
+    Screen,
+    /// 识别
+    Ocr,
+    /// 滚轮
+    Scroll,
+    /// 组合键
+    Combo,
+    /// 在塔防地图内测量镜头平移速度，写入 calibration.json
+    Calibrate,
 }
 
 fn main() {
-    let args = Args::parse();
+    let cli = Cli::parse();
+    let args = cli.device;
+    let command = cli.command.unwrap_or_else(|| Command::Run(RunArgs::default()));
 
-    println!("========================================");
-    println!("🚀 NZM_CMD 智能控制中心");
-    println!("📍 端口: {}", args.port);
-    if let Some(t) = &args.test {
-        println!("🔧 模式: 测试 ({})", t);
-    } else {
-        println!("🎯 目标: {}", args.target);
+    let mut app_config = AppConfig::load(&args.config);
+    if args.daemon && app_config.logging.dir.is_empty() {
+        // 后台模式没有控制台，日志只能写文件
+        app_config.logging.dir = "logs/app".to_string();
+    }
+    // guard 存活到 main 结束，保证文件日志写完
+    let _log_guard = logging::init(&app_config.logging);
+    // 环境变量覆盖在日志就绪后应用，被忽略的取值才能写进日志文件
+    app_config.apply_env();
+    if let Some(name) = &args.profile {
+        if let Err(e) = app_config.apply_profile(name) {
+            println!("❌ {}", e);
+            std::process::exit(1);
+        }
+    }
+    let mut general = app_config.general.clone();
+    if let Some(v) = &args.port { general.port = v.clone(); }
+    if let Some(v) = &args.device_type { general.device_type = v.clone(); }
+    if let Some(v) = &args.map { general.ui_map = v.clone(); }
+
+    // 不需要设备的子命令
+    match &command {
+        Command::RenderTrace { path, bg } => {
+            let out = std::path::Path::new(path).with_extension("png");
+            match trace::render(path, bg, &out.to_string_lossy()) {
+                Ok(n) => println!("🖼️ 已将 {} 条轨迹渲染到 {}", n, out.display()),
+                Err(e) => println!("❌ {}", e),
+            }
+            return;
+        }
+        Command::Ctl { command, target } => {
+            let line = format!("{} {}", command, target.as_deref().unwrap_or("")).trim().to_string();
+            match ipc::request(&line) {
+                Ok(reply) => match serde_json::from_str::<serde_json::Value>(&reply) {
+                    Ok(v) => println!("{}", serde_json::to_string_pretty(&v).unwrap_or(reply)),
+                    Err(_) => println!("{}", reply),
+                },
+                Err(e) => {
+                    println!("❌ {}", e);
+                    std::process::exit(1);
+                }
+            }
+            return;
+        }
+        Command::Journal { path, uid } => {
+            if let Err(e) = journal::print(path, *uid) {
+                println!("❌ {}", e);
+            }
+            return;
+        }
+        Command::Session { path, only } => {
+            if let Err(e) = session::print(path, only.as_deref()) {
+                println!("❌ {}", e);
+            }
+            return;
+        }
+        Command::Validate(v) => {
+            if !run_validate(v, &general) {
+                std::process::exit(1);
+            }
+            return;
+        }
+        _ => {}
     }
-    println!("========================================");
 
-    let (sw, sh) = (1920, 1080);
+    // 定时运行：命令行的 --at / --every-hours 覆盖 [schedule]
+    if let Command::Schedule(s) = &command {
+        if !s.at.is_empty() { app_config.schedule.at = s.at.clone(); }
+        if let Some(h) = s.every_hours { app_config.schedule.every_hours = h; }
+        if let Err(e) = app_config.schedule.validate() {
+            println!("❌ [定时] {}", e);
+            return;
+        }
+    }
 
-    let driver_type = if args.port.to_uppercase() == "SOFT" {
-        DriverType::Software
-    } else {
-        DriverType::Hardware
+    let targets = match &command {
+        Command::Run(RunArgs { targets, .. })
+        | Command::Farm(FarmArgs { targets, .. })
+        | Command::Schedule(ScheduleArgs { farm: FarmArgs { targets, .. }, .. }) => match targets.resolve(&default_target(&general)) {
+            Ok(t) => t,
+            Err(e) => {
+                println!("❌ {}", e);
+                return;
+            }
+        },
+        Command::Simulate { target } => vec![target.clone().unwrap_or_else(|| default_target(&general))],
+        _ => Vec::new(),
     };
 
-    let driver_box: Box<dyn InputDriver> = match create_driver(driver_type, &args.port, sw, sh) {
+    info!("========================================");
+    info!("🚀 NZM_CMD 智能控制中心");
+    info!("📍 端口: {}", general.port);
+    match &command {
+        Command::Test { mode } => info!("🔧 模式: 测试 ({:?})", mode),
+        Command::Bench(_) => info!("⏱️ 模式: 基准测试"),
+        _ if !targets.is_empty() => info!("🎯 目标: {}", targets.join(" -> ")),
+        _ => {}
+    }
+    info!("========================================");
+
+    retention::start(app_config.retention.clone());
+
+    general.resolve_screen();
+    let (sw, sh) = (general.screen_width, general.screen_height);
+
+    let offline = matches!(&command, Command::Simulate { .. } | Command::Run(RunArgs { dry_run: true, .. }));
+    if args.no_device {
+        info!("🫥 [无设备模式] 所有键鼠动作只打印不执行");
+    }
+    let offline = offline || args.no_device;
+    let driver_type = match general.port.to_uppercase().as_str() {
+        _ if offline => DriverType::Null,
+        "SOFT" => DriverType::Software,
+        "NONE" => DriverType::Null,
+        _ => DriverType::from_name(&general.device_type).unwrap_or_else(|| {
+            warn!("⚠️ 未知设备类型 '{}'，按 serial 处理", general.device_type);
+            DriverType::Hardware
+        }),
+    };
+
+    // 回退链：硬件 -> 软件模拟 -> 空驱动 (只打印，不输入)
+    let driver_box: Box<dyn InputDriver> = match create_driver(driver_type, &general.port, sw, sh) {
         Ok(d) => d,
         Err(e) => {
-            println!("⚠️ 警告: 无法初始化驱动 ({})", e);
-            println!("⚠️ 尝试回退到 [软件模拟模式]...");
-            create_driver(DriverType::Software, "", sw, sh).unwrap()
+            warn!("⚠️ 警告: 无法初始化驱动 ({})", e);
+            warn!("⚠️ 尝试回退到 [软件模拟模式]...");
+            match create_driver(DriverType::Software, "", sw, sh) {
+                Ok(d) => d,
+                Err(e) => {
+                    warn!("⚠️ 软件模拟也不可用 ({})，使用 [空驱动]，不会产生任何输入", e);
+                    create_driver(DriverType::Null, "", sw, sh).expect("空驱动不会失败")
+                }
+            }
         }
     };
 
-    let driver_arc: Arc<Mutex<Box<dyn InputDriver>>> = Arc::new(Mutex::new(driver_box));
-
-    let hb = Arc::clone(&driver_arc);
-    thread::spawn(move || loop {
-        if let Ok(mut d) = hb.lock() {
-            d.heartbeat();
+    if let Some(fw) = driver_box.firmware() {
+        if !fw.is_compatible() {
+            warn!(
+                "⚠️ 警告: 硬件固件 {} 的协议版本不受支持 (支持 v{}~v{})，键鼠命令可能无法正确执行，请升级固件或程序",
+                fw, SUPPORTED_PROTOCOL.start(), SUPPORTED_PROTOCOL.end()
+            );
         }
-        thread::sleep(Duration::from_secs(1));
-    });
+    }
 
-    let human_driver = Arc::new(Mutex::new(HumanDriver::new(
-        Arc::clone(&driver_arc),
-        sw / 2,
-        sh / 2,
-    )));
+    if let Command::Bench(b) = &command {
+        let part = |p| b.only.is_empty() || b.only.contains(&p);
+        let opts = BenchOptions {
+            iterations: b.iterations,
+            capture: part(BenchPart::Capture),
+            ocr: part(BenchPart::Ocr),
+            matching: part(BenchPart::Match),
+            serial: part(BenchPart::Serial),
+        };
+        let mut driver_box = driver_box;
+        bench::run(&opts, driver_box.as_mut(), ocr::create_backend(&app_config.ocr).as_ref());
+        return;
+    }
 
-    let engine = Arc::new(NavEngine::new("ui_map.toml", Arc::clone(&human_driver)));
+    let driver_box: Box<dyn InputDriver> = match &args.record {
+        Some(path) => match RecordingDriver::new(driver_box, path) {
+            Ok(rec) => Box::new(rec),
+            Err(e) => {
+                error!("❌ 无法创建录像文件 {}: {}", path, e);
+                return;
+            }
+        },
+        None => driver_box,
+    };
 
-    if let Some(mode) = args.test.as_deref() {
-        println!("⏳ 5秒后开始执行 [{}] 测试...", mode);
+    // 离线模拟 / Dry-Run 不操作游戏，没有可录的画面
+    let session = match args.session {
+        true if matches!(&command, Command::Simulate { .. } | Command::Run(RunArgs { dry_run: true, .. })) => {
+            warn!("⚠️ 离线命令不录制会话，忽略 --session");
+            None
+        }
+        true => match SessionRecorder::create(&app_config.session, &std::env::args().collect::<Vec<_>>().join(" ")) {
+            Ok(s) => Some(s),
+            Err(e) => {
+                error!("❌ 无法创建会话目录 {}: {}", app_config.session.dir, e);
+                return;
+            }
+        },
+        false => None,
+    };
+    let driver_box: Box<dyn InputDriver> = match &session {
+        Some(s) => Box::new(RecordingDriver::for_session(driver_box, Arc::clone(s))),
+        None => driver_box,
+    };
+
+    let input = InputQueue::spawn(driver_box);
+
+    if let Command::Playback { path } = &command {
+        info!("⏳ 5秒后开始回放 {}...", path);
         thread::sleep(Duration::from_secs(5));
-        match mode {
-            "input" => run_input_test(human_driver),
-            "screen" => run_screen_test(),
-            "ocr" => run_ocr_test(engine),
-            "scroll" => run_scroll_test(human_driver),
-            "combo" => run_combo_test(human_driver), // ✨ 新增这一行
-            _ => println!("❌ 未知测试模式"),
+        if let Some(s) = &session {
+            s.spawn_frames(&app_config.session);
+        }
+        if let Err(e) = recorder::playback(path, &mut input.clone()) {
+            error!("❌ {}", e);
+        }
+        input.flush();
+        if let Some(s) = &session {
+            s.finish(false);
         }
         return;
     }
 
-    println!("✅ 引擎就绪，5秒后开始自动化循环...");
-    thread::sleep(Duration::from_secs(5));
+    input.install_panic_guards();
 
-    loop {
-        println!("\n🔄 [主控] 正在导航至: {}...", args.target);
+    // 心跳走高优先级通道，不会被长按等普通输入阻塞；链路异常时主循环暂停
+    input.start_heartbeat(Duration::from_secs(1));
+    input.on_unhealthy(|_| {
+        info!("⏸️ [主控] 输入设备无响应，自动化将在下一轮开始前暂停");
+        notify::send(NotifyEvent::HardwareDisconnected, "连续心跳未确认，自动化将在下一轮开始前暂停");
+    });
 
-        let nav_result = engine.navigate(&args.target);
+    let mut human = HumanDriver::new(input.clone(), sw / 2, sh / 2)
+        .with_layout(KeyLayout::from_config(&app_config.keyboard))
+        .with_mouse(app_config.mouse.clone())
+        .with_motion(app_config.motion.clone())
+        .with_typing(app_config.keyboard.typing)
+        .with_timing(app_config.timing.clone())
+        .with_calibration(Calibration::load(calibration::DEFAULT_PATH));
+    if let Some(path) = &args.trace {
+        human = human.with_trace(path);
+    }
+    let human_driver = Arc::new(Mutex::new(human));
+    let _idle = app_config.idle.enabled.then(|| IdleBehavior::spawn(input.clone(), app_config.idle.clone()));
 
-        match nav_result {
-            NavResult::Handover(scene_id, handler_opt) => {
-                println!("⚔️ [主控] 导航成功: [{}]", scene_id);
+    let engine = if args.strict {
+        match NavEngine::new_strict(&general.ui_map, Arc::clone(&human_driver)) {
+            Ok(e) => Arc::new(e.with_ocr_backend(ocr::create_backend(&app_config.ocr))),
+            Err(report) => {
+                error!("❌ [严格模式] {} 存在 {} 个问题，拒绝启动", general.ui_map, report.issues.len());
+                return;
+            }
+        }
+    } else {
+        Arc::new(NavEngine::new(&general.ui_map, Arc::clone(&human_driver)).with_ocr_backend(ocr::create_backend(&app_config.ocr)))
+    };
 
-                let handler_key = handler_opt.as_deref().unwrap_or("td");
+    if app_config.telemetry.enabled {
+        if let Err(e) = engine.enable_telemetry(&app_config.telemetry.path) {
+            warn!("⚠️ 无法开启导航遥测 ({}): {}", app_config.telemetry.path, e);
+        }
+    }
+    notify::init(&app_config.notify);
+    notify::attach(&engine);
+    metrics::attach(&engine);
+    if let Some(s) = &session {
+        s.attach(&engine);
+        s.spawn_frames(&app_config.session);
+    }
 
-                match handler_key {
-                    "daily" => {
-                        println!("📅 [路由] 检测到 'daily' 标记，启动日活模块...");
-                        let app =
-                            DailyRoutineApp::new(Arc::clone(&human_driver), Arc::clone(&engine));
-                        app.run();
-                    }
-                    "td" | _ => {
-                        println!("🏰 [路由] 启动塔防模块 (Handler: {})...", handler_key);
-                        let mut td_app =
-                            TowerDefenseApp::new(Arc::clone(&human_driver), Arc::clone(&engine));
+    match &command {
+        Command::Run(RunArgs { dry_run: true, .. }) => {
+            for target in &targets {
+                info!("🧪 [Dry-Run] 规划路线: -> [{}]", target);
+                let steps = engine.plan(target);
+                if steps.is_empty() {
+                    warn!("⚠️ [Dry-Run] 无可执行路线 (已在目标、无法定位起点或无路可走)");
+                }
+                for (i, s) in steps.iter().enumerate() {
+                    info!(
+                        "  {}. [{}] -> [{}] 点击 ({}, {}) | 延迟 {}ms | 超时 {}ms | 代价 {}",
+                        i + 1, s.from, s.to, s.coords[0], s.coords[1], s.post_delay, s.timeout_ms, s.cost
+                    );
+                }
+            }
+            return;
+        }
+        Command::Simulate { .. } => {
+            let target = &targets[0];
+            let mut td_app = TowerDefenseApp::new(Arc::clone(&human_driver), Arc::clone(&engine))
+                .with_profile(&general.profile);
+            td_app.load_config(&general.td_config, target);
+            td_app.simulate(&general.terrain_path(target), &general.strategy_path(target), &general.traps_path());
+            return;
+        }
+        Command::Test { mode } => {
+            info!("⏳ 5秒后开始执行 [{:?}] 测试...", mode);
+            thread::sleep(Duration::from_secs(5));
+            match mode {
+                TestMode::Input => run_input_test(human_driver),
+                TestMode::Screen => run_screen_test(),
+                TestMode::Ocr => run_ocr_test(engine),
+                TestMode::Scroll => run_scroll_test(human_driver),
+                TestMode::Combo => run_combo_test(human_driver),
+                TestMode::Calibrate => {
+                    // 需在塔防地图内执行：按住 s / w 测量镜头平移速度
+                    TowerDefenseApp::new(Arc::clone(&human_driver), Arc::clone(&engine)).calibrate();
+                }
+            }
+            input.flush();
+            if let Some(s) = &session {
+                s.finish(false);
+            }
+            return;
+        }
+        _ => {}
+    }
 
-                        let map_file = format!("{}地图.json", scene_id);
-                        let strategy_file = format!("{}策略.json", scene_id);
-                        let traps_file = "traps_config.json";
+    info!("✅ 引擎就绪，5秒后开始自动化循环...");
+    thread::sleep(Duration::from_secs(5));
 
-                        println!("📂 加载配置: {} | {}", map_file, strategy_file);
-                        td_app.run(&map_file, &strategy_file, traps_file);
+    // 第一次 Ctrl+C / F10 让当前模块收尾后退出，F9 暂停
+    let control = RunControl::new();
+    control.install_ctrl_c();
+    control.spawn_hotkeys();
+    {
+        let control = control.clone();
+        engine.on_event(move |e| {
+            if let NavEventKind::SceneEntered { scene, .. } = &e.kind {
+                control.update_status(|s| s.scene = Some(scene.clone()));
+            }
+        });
+    }
+    if app_config.api.enabled {
+        api::spawn(&app_config.api, control.clone());
+    }
+    if app_config.metrics.enabled {
+        metrics::spawn(&app_config.metrics, input.clone());
+    }
+    if args.daemon {
+        ipc::serve(control.clone());
+        info!("🌙 [后台] 即将脱离控制台，日志见 {}，用 ctl status / ctl stop 查询或停止", app_config.logging.dir);
+        // Linux 上没有控制台可脱离，由 systemd / nohup 负责后台运行
+        #[cfg(feature = "windows")]
+        unsafe {
+            let _ = FreeConsole();
+        }
+    }
+    #[cfg(feature = "tui")]
+    let _dashboard = if args.tui && !args.daemon {
+        match nzm_cmd::tui::Dashboard::start(control.clone(), &engine, input.clone()) {
+            Ok(d) => Some(d),
+            Err(e) => {
+                warn!("⚠️ 终端面板启动失败，继续使用普通输出: {}", e);
+                None
+            }
+        }
+    } else {
+        None
+    };
+    #[cfg(not(feature = "tui"))]
+    if args.tui {
+        warn!("⚠️ 未启用 tui feature，忽略 --tui (需 cargo build --features tui)");
+    }
+
+    let command_name = match &command {
+        Command::Farm(_) => "farm",
+        Command::Schedule(_) => "schedule",
+        _ => "run",
+    };
+    let recorder = ResultRecorder::new(command_name, &targets);
+    recorder.attach(&engine);
+
+    let mut handlers = build_handlers(&general, &engine, &human_driver, &control);
+    let results = match command {
+        Command::Farm(farm) => run_farm(&farm, &targets, &mut handlers, &engine, &human_driver, &control, &app_config),
+        Command::Schedule(schedule) => {
+            run_schedule(&schedule.farm, &targets, &mut handlers, &engine, &human_driver, &control, &app_config)
+        }
+        Command::Run(run) => {
+            let mut results = Vec::new();
+            if let Some(state_path) = &run.resume {
+                let mut td_app = TowerDefenseApp::new(Arc::clone(&human_driver), Arc::clone(&engine))
+                    .with_control(control.clone())
+                    .with_profile(&general.profile);
+                let map_name = TdProgress::load(state_path).map(|s| s.map_name).unwrap_or_default();
+                if !map_name.is_empty() {
+                    td_app.load_config(&general.td_config, &map_name);
+                }
+                match td_app.run_resume(state_path) {
+                    Ok(summary) => {
+                        let mut resumed = TargetResult::new(&map_name);
+                        resumed.runs.push((map_name, summary));
+                        results.push(resumed);
+                    }
+                    Err(e) => {
+                        error!("❌ {}", e);
+                        recorder.error(e);
                     }
                 }
+            }
+            if !control.is_stopped() {
+                results.extend(run_loop(&run, &targets, &mut handlers, &engine, &human_driver, &control, &app_config));
+            }
+            results
+        }
+        // 其余子命令已在前面处理
+        _ => return,
+    };
+    input.flush();
+    if let Some(s) = &session {
+        s.finish(control.is_stopped());
+    }
 
-                println!("🎉 本局任务结束，5秒后重新开始循环...");
-                thread::sleep(Duration::from_secs(5));
+    if let Some(path) = &args.result_file {
+        for r in &results {
+            for (map, summary) in &r.runs {
+                recorder.record_run(&r.target, map, summary);
             }
+            if r.skipped {
+                recorder.error(format!("目标 [{}] 连续导航失败 {} 次，已跳过", r.target, r.nav_failures));
+            }
+        }
+        let result = recorder.finish(control.is_stopped(), input.metrics().commands);
+        match result.write(path) {
+            Ok(()) if path != "-" => info!("📝 运行结果已写入 {} ({})", path, result.status),
+            Ok(()) => {}
+            Err(e) => error!("❌ 无法输出运行结果: {}", e),
+        }
+    }
+}
 
-            NavResult::Failed => {
-                println!("❌ [主控] 导航失败，执行重置操作 (ESC)...");
+/// run 子命令的主循环：导航 -> 按处理器执行 (塔防 / 日活) -> 回大厅，直到达到局数或收到停止信号
+fn run_loop(
+    run: &RunArgs,
+    targets: &[String],
+    handlers: &mut HandlerRegistry,
+    engine: &Arc<NavEngine>,
+    human_driver: &Arc<Mutex<HumanDriver>>,
+    control: &RunControl,
+    app_config: &AppConfig,
+) -> Vec<TargetResult> {
+    // 多个目标：按队列依次刷，每个目标结束后回大厅
+    if targets.len() > 1 {
+        let queue = FarmArgs { targets: run.targets.clone(), loops: run.loops, minutes: 0 };
+        return run_farm(&queue, targets, handlers, engine, human_driver, control, app_config);
+    }
 
-                if let Ok(mut human) = human_driver.lock() {
-                    human.key_hold('\u{1B}', 100);
+    let mut target = targets[0].clone();
+    let mut task = app_config.task(&target).cloned().unwrap_or_default();
+    let mut results = vec![TargetResult::new(&target)];
+    let link_health = || human_driver.lock().map(|h| h.device.health()).unwrap_or(LinkHealth::Healthy);
 
-                    if let Ok(mut dev) = human.device.lock() {
-                        dev.key_down(0x29, 0);
-                    }
-                    thread::sleep(Duration::from_millis(100));
-                    if let Ok(mut dev) = human.device.lock() {
-                        dev.key_up();
-                    }
+    let mut runs_done: u32 = 0;
+    loop {
+        if control.is_stopped() {
+            info!("👋 [主控] 已停止");
+            break;
+        }
+        if run.loops > 0 && runs_done >= run.loops {
+            info!("🏁 [主控] 已完成 {} 局，退出", runs_done);
+            break;
+        }
+
+        if link_health() == LinkHealth::Unhealthy {
+            info!("⏸️ [主控] 输入链路异常，等待恢复...");
+            // 等待期间仍响应暂停 / 停止；收到停止时回到循环开头退出
+            while link_health() == LinkHealth::Unhealthy && control.sleep(Duration::from_secs(2)) {}
+            if control.is_stopped() {
+                continue;
+            }
+            info!("▶️ [主控] 输入链路已恢复，继续自动化");
+        }
+
+        // 控制接口请求切换目标
+        if let Some(t) = control.take_target_request() {
+            info!("🌐 [主控] 目标切换: {} -> {}", target, t);
+            task = app_config.task(&t).cloned().unwrap_or_default();
+            target = t;
+            results.push(TargetResult::new(&target));
+        }
+        let result = results.last_mut().expect("至少有一个目标");
+
+        info!("🔄 [主控] 正在导航至: {}...", target);
+        control.update_status(|s| s.target = Some(target.clone()));
 
-                    thread::sleep(Duration::from_millis(100));
-                    if let Ok(mut dev) = human.device.lock() {
-                        dev.key_down(0x2C, 0); // 空格键扫描码
+        let report = engine.navigate_with_hooks(&target, &task.pre_hooks, &task.post_hooks, &task.retry);
+
+        match report.result {
+            NavResult::Handover(scene_id, handler_opt) => {
+                info!("⚔️ [主控] 导航成功: [{}]", scene_id);
+
+                match handlers.dispatch(&scene_id, handler_opt.as_deref()) {
+                    HandlerOutcome::Run(summary) => {
+                        runs_done += 1;
+                        match summary {
+                            Some(summary) => {
+                                info!("📈 [主控] 第 {} 局: {:?}", runs_done, summary.outcome);
+                                result.runs.push((scene_id.clone(), summary));
+                            }
+                            None => info!("📈 [主控] 第 {} 局结束", runs_done),
+                        }
+                        return_to_lobby(engine);
                     }
-                    thread::sleep(Duration::from_millis(100));
-                    if let Ok(mut dev) = human.device.lock() {
-                        dev.key_up(); 
+                    HandlerOutcome::Stopped(summary) => {
+                        result.runs.extend(summary.map(|s| (scene_id.clone(), s)));
+                        break;
                     }
+                    HandlerOutcome::Done => {}
                 }
 
-                println!("⏳ 等待界面重置 (3秒)...");
-                thread::sleep(Duration::from_secs(3));
+                engine.run_hooks("post", &task.post_hooks);
+
+                info!("🎉 本局任务结束，5秒后重新开始循环...");
+                thread::sleep(Duration::from_secs(5));
+            }
+
+            NavResult::Failed(_) => {
+                result.nav_failures += 1;
+                error!("❌ [主控] 导航失败: {}", report.summary());
+                reset_after_failure(human_driver);
+            }
+
+            NavResult::Aborted(ref scene_id) => {
+                warn!("🛑 [主控] 检测到危险场景 [{}]，停止自动化，请人工处理！", scene_id);
+                if let Ok(d) = human_driver.lock() {
+                    d.device.panic_release();
+                }
+                info!("📋 {}", report.summary());
+                break;
             }
 
             NavResult::Success => {
-                println!("✅ [主控] 导航到达终点，等待重置...");
+                info!("✅ [主控] 导航到达终点，等待重置...");
                 thread::sleep(Duration::from_secs(5));
             }
         }
     }
+    results
+}
+
+/// 校验地形与策略文件并打印结果；无错误时返回 true
+fn run_validate(v: &ValidateArgs, general: &GeneralConfig) -> bool {
+    let target = v.target.clone().unwrap_or_else(|| default_target(general));
+    let terrain_file = v.terrain.clone().unwrap_or_else(|| general.terrain_path(&target));
+    let strategy_file = match (&v.strategy, &v.terrain) {
+        (Some(s), _) => s.clone(),
+        (None, Some(t)) => t.replace("地图", "策略"),
+        (None, None) => general.strategy_path(&target),
+    };
+    println!("🔍 校验 {} + {}", terrain_file, strategy_file);
+    match validate::validate_files(&terrain_file, &strategy_file) {
+        Ok(issues) => {
+            for issue in &issues {
+                println!("  {}", issue);
+            }
+            let errors = issues.iter().filter(|i| i.severity == Severity::Error).count();
+            println!("📋 错误 {} 条，警告 {} 条", errors, issues.len() - errors);
+            errors == 0
+        }
+        Err(e) => {
+            println!("❌ {}", e);
+            false
+        }
+    }
+}
+
+/// 未指定目标时使用的目标：运行配置的 target，否则为 DEFAULT_TARGET
+fn default_target(general: &GeneralConfig) -> String {
+    general.profile.target.clone().unwrap_or_else(|| DEFAULT_TARGET.to_string())
+}
+
+/// 注册场景处理器：未填写 handler 的场景默认交给塔防
+fn build_handlers(
+    general: &GeneralConfig,
+    engine: &Arc<NavEngine>,
+    human_driver: &Arc<Mutex<HumanDriver>>,
+    control: &RunControl,
+) -> HandlerRegistry {
+    let mut handlers = HandlerRegistry::new("td");
+    handlers.register(
+        "td",
+        Box::new(TdHandler::new(general.clone(), Arc::clone(human_driver), Arc::clone(engine)).with_control(control.clone())),
+    );
+    handlers.register("daily", Box::new(DailyRoutineApp::new(Arc::clone(human_driver), Arc::clone(engine))));
+    #[cfg(feature = "script")]
+    handlers.register(".rhai", Box::new(nzm_cmd::script::ScriptHandler::new(Arc::clone(engine)).with_control(control.clone())));
+    #[cfg(not(feature = "script"))]
+    handlers.register(
+        ".rhai",
+        Box::new(|_: &str, path: &str| {
+            error!("❌ [脚本] 未启用 script feature，无法执行 {} (需 cargo build --features script)", path);
+            HandlerOutcome::Run(None)
+        }),
+    );
+    handlers
+}
+
+/// 从结算界面回到大厅，下一轮再从大厅导航进图
+fn return_to_lobby(engine: &NavEngine) {
+    let back = engine.navigate(LOBBY_SCENE);
+    if back.result != NavResult::Success {
+        warn!("⚠️ [主控] 返回大厅失败: {}", back.summary());
+    }
+}
+
+/// 导航失败后按 ESC + 空格尝试回到可识别的界面
+fn reset_after_failure(human_driver: &Arc<Mutex<HumanDriver>>) {
+    error!("❌ [主控] 执行重置操作 (ESC)...");
+
+    if let Ok(mut human) = human_driver.lock() {
+        human.key_hold('\u{1B}', 100);
+
+        human.device.key_down(0x29, 0);
+        thread::sleep(Duration::from_millis(100));
+        human.device.key_up();
+
+        thread::sleep(Duration::from_millis(100));
+        human.device.key_down(0x2C, 0); // 空格键扫描码
+        thread::sleep(Duration::from_millis(100));
+        human.device.key_up(); 
+    }
+
+    info!("⏳ 等待界面重置 (3秒)...");
+    thread::sleep(Duration::from_secs(3));
+}
+
+/// 单个目标在刷图 / 队列中的结果
+struct TargetResult {
+    target: String,
+    runs: Vec<(String, RunSummary)>,
+    nav_failures: u32,
+    /// 连续导航失败过多被跳过
+    skipped: bool,
+}
+
+impl TargetResult {
+    fn new(target: &str) -> Self {
+        Self { target: target.to_string(), runs: Vec::new(), nav_failures: 0, skipped: false }
+    }
+}
+
+/// 刷图模式：按顺序对每个目标循环 导航 -> 塔防 -> 回大厅，直到达到局数 / 时长或收到停止信号
+fn run_farm(
+    args: &FarmArgs,
+    targets: &[String],
+    handlers: &mut HandlerRegistry,
+    engine: &Arc<NavEngine>,
+    human_driver: &Arc<Mutex<HumanDriver>>,
+    control: &RunControl,
+    app_config: &AppConfig,
+) -> Vec<TargetResult> {
+    let started = Instant::now();
+    let deadline = (args.minutes > 0).then(|| Duration::from_secs(args.minutes * 60));
+    // 多个目标时每个目标至少一局，否则永远轮不到下一个
+    let loops = if targets.len() > 1 { args.loops.max(1) } else { args.loops };
+    let mut results: Vec<TargetResult> = Vec::new();
+
+    info!("🌾 [刷图] 目标 {} | 局数 {}{} | 时长 {}",
+        targets.join(" -> "),
+        if loops > 0 { loops.to_string() } else { "不限".to_string() },
+        if targets.len() > 1 { " / 目标" } else { "" },
+        deadline.map(|d| format!("{} 分钟", d.as_secs() / 60)).unwrap_or_else(|| "不限".to_string()),
+    );
+
+    'queue: for (idx, target) in targets.iter().enumerate() {
+        let task = app_config.task(target).cloned().unwrap_or_default();
+        results.push(TargetResult::new(target));
+        let result = results.last_mut().expect("刚刚加入");
+        let mut consecutive_failures = 0u32;
+        let mut completed = 0u32;
+        if targets.len() > 1 {
+            info!("📋 [队列] 目标 {}/{}: {}", idx + 1, targets.len(), target);
+        }
+
+        loop {
+            if control.is_stopped() {
+                info!("👋 [刷图] 已停止");
+                break 'queue;
+            }
+            if deadline.is_some_and(|d| started.elapsed() >= d) {
+                info!("⏰ [刷图] 已达到设定时长");
+                break 'queue;
+            }
+            if loops > 0 && completed >= loops {
+                info!("🏁 [刷图] {} 已完成 {} 局", target, completed);
+                break;
+            }
+
+            info!("🔄 [刷图] {} 第 {} 局，正在导航至: {}...", target, completed + 1, target);
+            control.update_status(|s| s.target = Some(target.clone()));
+            let report = engine.navigate_with_hooks(target, &task.pre_hooks, &task.post_hooks, &task.retry);
+            match report.result {
+                NavResult::Handover(scene_id, handler) => {
+                    consecutive_failures = 0;
+                    // 刷图时一次性任务同样计为一局，否则达不到局数
+                    match handlers.dispatch(&scene_id, handler.as_deref()) {
+                        HandlerOutcome::Run(summary) => result.runs.extend(summary.map(|s| (scene_id, s))),
+                        HandlerOutcome::Done => {}
+                        HandlerOutcome::Stopped(summary) => {
+                            result.runs.extend(summary.map(|s| (scene_id, s)));
+                            break 'queue;
+                        }
+                    }
+                    completed += 1;
+                    engine.run_hooks("post", &task.post_hooks);
+                    return_to_lobby(engine);
+                }
+                NavResult::Failed(_) => {
+                    consecutive_failures += 1;
+                    result.nav_failures += 1;
+                    error!("❌ [刷图] 导航失败 ({} 次): {}", consecutive_failures, report.summary());
+                    reset_after_failure(human_driver);
+                    if targets.len() > 1 && consecutive_failures >= QUEUE_NAV_FAILURE_LIMIT {
+                        info!("⏭️ [队列] {} 连续导航失败 {} 次，跳到下一个目标", target, consecutive_failures);
+                        result.skipped = true;
+                        break;
+                    }
+                }
+                NavResult::Aborted(ref scene_id) => {
+                    warn!("🛑 [刷图] 检测到危险场景 [{}]，停止刷图，请人工处理！", scene_id);
+                    if let Ok(d) = human_driver.lock() {
+                        d.device.panic_release();
+                    }
+                    break 'queue;
+                }
+                NavResult::Success => {
+                    info!("✅ [刷图] 导航到达终点，等待重置...");
+                    thread::sleep(Duration::from_secs(5));
+                }
+            }
+        }
+    }
+
+    print_farm_summary(&results, started.elapsed());
+    results
+}
+
+/// 定时运行：等到下一个启动时间后跑一轮刷图，结束后回到待机，直到收到停止信号
+fn run_schedule(
+    args: &FarmArgs,
+    targets: &[String],
+    handlers: &mut HandlerRegistry,
+    engine: &Arc<NavEngine>,
+    human_driver: &Arc<Mutex<HumanDriver>>,
+    control: &RunControl,
+    app_config: &AppConfig,
+) -> Vec<TargetResult> {
+    let schedule = &app_config.schedule;
+    // 每轮必须有尽头，否则永远等不到下一轮
+    let round = FarmArgs { loops: args.loops.max(1), ..args.clone() };
+    let mut last_start = None;
+    let mut rounds = 0u32;
+    let mut results = Vec::new();
+
+    loop {
+        let Some(next) = schedule.next_run(Local::now(), last_start) else {
+            error!("❌ [定时] 无法计算下一轮的启动时间");
+            return results;
+        };
+        info!("⏰ [定时] 下一轮: {} (待机中，心跳照常)", next.format("%Y-%m-%d %H:%M"));
+        let wait = (next - Local::now()).to_std().unwrap_or_default();
+        if !control.sleep(wait) {
+            info!("👋 [定时] 已停止，共运行 {} 轮", rounds);
+            return results;
+        }
+
+        rounds += 1;
+        last_start = Some(Local::now());
+        info!("📅 [定时] 第 {} 轮开始", rounds);
+        results.extend(run_farm(&round, targets, handlers, engine, human_driver, control, app_config));
+        if control.is_stopped() {
+            info!("👋 [定时] 已停止，共运行 {} 轮", rounds);
+            return results;
+        }
+    }
+}
+
+fn print_farm_summary(results: &[TargetResult], elapsed: Duration) {
+    let runs: Vec<&(String, RunSummary)> = results.iter().flat_map(|r| &r.runs).collect();
+    let count = |runs: &[&(String, RunSummary)], o: RunOutcome| runs.iter().filter(|(_, s)| s.outcome == o).count();
+    info!("========================================");
+    info!("🌾 刷图汇总: {} 局 | 总用时 {} 分钟", runs.len(), elapsed.as_secs() / 60);
+    for (i, (map, summary)) in runs.iter().enumerate() {
+        info!("  #{:<3} [{}] {}", i + 1, map, summary);
+    }
+    if results.len() > 1 {
+        info!("  ---- 按目标 ----");
+        for r in results {
+            let target_runs: Vec<&(String, RunSummary)> = r.runs.iter().collect();
+            info!(
+                "  [{}] {} 局 | 胜利 {} | 失败 {} | 导航失败 {}{}",
+                r.target,
+                r.runs.len(),
+                count(&target_runs, RunOutcome::Victory),
+                count(&target_runs, RunOutcome::Defeat),
+                r.nav_failures,
+                if r.skipped { " (已跳过)" } else { "" },
+            );
+        }
+    }
+    info!(
+        "  胜利 {} | 失败 {} | 未知 {}",
+        count(&runs, RunOutcome::Victory),
+        count(&runs, RunOutcome::Defeat),
+        count(&runs, RunOutcome::Unknown)
+    );
+    if !runs.is_empty() {
+        let avg = runs.iter().map(|(_, s)| s.elapsed.as_secs()).sum::<u64>() / runs.len() as u64;
+        info!("  平均每局 {}s", avg);
+    }
+    info!("========================================");
 }
 
 fn run_input_test(driver: Arc<Mutex<HumanDriver>>) {
-    println!("Testing Mouse & Keyboard...");
+    info!("Testing Mouse & Keyboard...");
     if let Ok(mut d) = driver.lock() {
-        println!("-> 移动鼠标 (矩形轨迹)");
+        info!("-> 移动鼠标 (矩形轨迹)");
         let start_x = 500;
         let start_y = 500;
         d.move_to_humanly(start_x, start_y, 0.5);
@@ -172,77 +1113,85 @@ fn run_input_test(driver: Arc<Mutex<HumanDriver>>) {
         d.move_to_humanly(start_x, start_y + 300, 0.5);
         d.move_to_humanly(start_x, start_y, 0.5);
 
-        println!("-> 执行点击 (Click)");
+        info!("-> 执行点击 (Click)");
         d.click_humanly(true, false, 0);
         thread::sleep(Duration::from_millis(500));
 
-        println!("-> 模拟键盘输入 'hello 123'");
+        info!("-> 模拟键盘输入 'hello 123'");
         d.type_humanly("hello 123", 60.0);
     }
-    println!("Done.");
+    info!("Done.");
 }
 
 fn run_screen_test() {
-    println!("Testing Screen Capture...");
+    info!("Testing Screen Capture...");
     let start = Instant::now();
-    let screens = Screen::all().unwrap_or_default();
+    for (i, s) in Screen::all().unwrap_or_default().iter().enumerate() {
+        let info = &s.display_info;
+        info!("-> 显示器 {}: {}x{}{}", i + 1, info.width, info.height, if info.is_primary { " (主屏)" } else { "" });
+    }
 
-    if let Some(screen) = screens.first() {
-        println!(
-            "-> 检测到屏幕: {}x{}",
+    if let Some(screen) = game_screen() {
+        info!(
+            "-> 游戏显示器: {}x{}",
             screen.display_info.width, screen.display_info.height
         );
-        match screen.capture() {
-            Ok(image) => {
+        let backend = capture::backend();
+        match backend.capture_screen() {
+            Some(image) => {
                 let path = "debug_screenshot.png";
                 image.save(path).unwrap();
-                println!(
+                info!(
                     "✅ 截图成功! 已保存至: {} (耗时 {}ms)",
                     path,
                     start.elapsed().as_millis()
                 );
+                // 连续截图的平均耗时 (DXGI 首帧需要等待，之后才是稳定速度)
+                let timed = Instant::now();
+                let frames = (0..10).filter(|_| backend.capture_screen().is_some()).count().max(1);
+                info!("-> {} 连续截图: 平均 {}ms/帧", backend.name(), timed.elapsed().as_millis() / frames as u128);
             }
-            Err(e) => println!("❌ 截图失败: {}", e),
+            None => error!("❌ 截图失败 ({})", backend.name()),
         }
     } else {
-        println!("❌ 未检测到显示器");
+        error!("❌ 未检测到显示器");
     }
 }
 
 fn run_ocr_test(engine: Arc<NavEngine>) {
-    println!("Testing OCR Function...");
+    info!("Testing OCR Function...");
     let rect = [100, 100, 500, 200];
-    println!("-> 正在识别区域: {:?}", rect);
+    info!("-> 正在识别区域: {:?}", rect);
     let start = Instant::now();
     let text = engine.ocr_area(rect);
 
-    println!("----------------------------------------");
-    println!("⏱️ 耗时: {} ms", start.elapsed().as_millis());
-    println!("📝 识别结果: [{}]", text);
-    println!("----------------------------------------");
+    info!("----------------------------------------");
+    info!("⏱️ 耗时: {} ms", start.elapsed().as_millis());
+    info!("📝 识别结果: [{}]", text);
+    info!("----------------------------------------");
 
     if text.is_empty() {
-        println!("⚠️ 警告: 识别结果为空，请确认该区域有文字。");
+        warn!("⚠️ 警告: 识别结果为空，请确认该区域有文字。");
     }
 }
 
 fn run_scroll_test(driver: Arc<Mutex<HumanDriver>>) {
-    println!("Testing Mouse Scroll...");
+    info!("Testing Mouse Scroll...");
     if let Ok(mut d) = driver.lock() {
-        println!("-> 向下滚动 5 格 (Scroll Down)");
+        info!("-> 向下滚动 5 格 (Scroll Down)");
         d.mouse_scroll(-5);
 
         thread::sleep(Duration::from_secs(2));
 
-        println!("-> 向上滚动 5 格 (Scroll Up)");
+        info!("-> 向上滚动 5 格 (Scroll Up)");
         d.mouse_scroll(5);
     }
-    println!("Done.");
+    info!("Done.");
 }
 
 // ✨ 新增 Combo 测试函数
 fn run_combo_test(driver: Arc<Mutex<HumanDriver>>) {
-    println!("Testing Combo Sequence (Loop)... Press Ctrl+C to stop.");
+    info!("Testing Combo Sequence (Loop)... Press Ctrl+C to stop.");
     // 默认间隔 50ms
     let delay = Duration::from_millis(40);
 
@@ -262,23 +1211,15 @@ fn run_combo_test(driver: Arc<Mutex<HumanDriver>>) {
             thread::sleep(delay);
 
             // 2. 按 b, 按 5
-            if let Ok(mut dev) = human.device.lock() {
-                dev.key_down(key_b, 0);
-            }
+            human.device.key_down(key_b, 0);
             thread::sleep(delay);
-            if let Ok(mut dev) = human.device.lock() {
-                dev.key_down(key_5, 0);
-            }
+            human.device.key_down(key_5, 0);
             thread::sleep(delay);
 
             // 3. 松 b, 松 5
-            if let Ok(mut dev) = human.device.lock() {
-                dev.key_up(); // 释放 (通常是释放所有或最后一个)
-            }
+            human.device.key_up(); // 释放 (通常是释放所有或最后一个)
             thread::sleep(delay);
-            if let Ok(mut dev) = human.device.lock() {
-                dev.key_up(); // 再次释放以防万一
-            }
+            human.device.key_up(); // 再次释放以防万一
             thread::sleep(delay);
             thread::sleep(delay);
             thread::sleep(delay);
@@ -308,23 +1249,15 @@ fn run_combo_test(driver: Arc<Mutex<HumanDriver>>) {
             thread::sleep(delay);
 
             // 5. 按 b, 按 4
-            if let Ok(mut dev) = human.device.lock() {
-                dev.key_down(key_b, 0);
-            }
+            human.device.key_down(key_b, 0);
             thread::sleep(delay);
-            if let Ok(mut dev) = human.device.lock() {
-                dev.key_down(key_4, 0);
-            }
+            human.device.key_down(key_4, 0);
             thread::sleep(delay);
 
             // 6. 松 b, 松 4
-            if let Ok(mut dev) = human.device.lock() {
-                dev.key_up();
-            }
+            human.device.key_up();
             thread::sleep(delay);
-            if let Ok(mut dev) = human.device.lock() {
-                dev.key_up();
-            }
+            human.device.key_up();
             thread::sleep(delay);
             thread::sleep(delay);
             thread::sleep(delay);
@@ -0,0 +1,252 @@
+// src/metrics.rs
+use crate::api;
+use crate::input_queue::InputQueue;
+use crate::nav::{NavEngine, NavEventKind, NavResult};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::io::Write;
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::thread;
+use std::time::Duration;
+use tracing::{info, warn};
+
+// ==========================================
+// 运行指标 (Prometheus 文本格式)
+// ==========================================
+// 计数与耗时分布始终在内存中累加 (只是几次原子加法)，开启 [metrics] 后由后台线程提供 GET /metrics，
+// 供 Prometheus 抓取、Grafana 画出通宵运行的趋势 (如 OCR 失败率上升、识别变慢)。
+// 计数器只增不减，每分钟的速率用 rate(nzm_scenes_detected_total[5m]) * 60 之类的查询得到。
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct MetricsConfig {
+    pub enabled: bool,
+    /// 监听地址；Prometheus 在另一台机器上时改为 0.0.0.0:9898
+    pub bind: String,
+}
+
+impl Default for MetricsConfig {
+    fn default() -> Self {
+        Self { enabled: false, bind: "127.0.0.1:9898".to_string() }
+    }
+}
+
+/// 耗时分布的桶上限 (秒)，覆盖单次 OCR 的几毫秒到整屏识别的数秒
+const BUCKETS: [f64; 11] = [0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+#[derive(Default)]
+pub struct Counter(AtomicU64);
+
+impl Counter {
+    pub fn inc(&self) {
+        self.0.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn get(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// 按一个标签值分组的计数器 (场景 ID、放置结果等，取值有限)
+#[derive(Default)]
+pub struct LabeledCounter(Mutex<BTreeMap<String, u64>>);
+
+impl LabeledCounter {
+    pub fn inc(&self, label: &str) {
+        if let Ok(mut m) = self.0.lock() {
+            *m.entry(label.to_string()).or_default() += 1;
+        }
+    }
+
+    pub fn get(&self, label: &str) -> u64 {
+        self.0.lock().ok().and_then(|m| m.get(label).copied()).unwrap_or(0)
+    }
+
+    fn snapshot(&self) -> BTreeMap<String, u64> {
+        self.0.lock().map(|m| m.clone()).unwrap_or_default()
+    }
+}
+
+/// 累积直方图 (各桶计数不含更小的桶，输出时再累加)
+#[derive(Default)]
+pub struct Histogram {
+    buckets: [AtomicU64; BUCKETS.len()],
+    count: AtomicU64,
+    /// 总耗时 (微秒)
+    sum_us: AtomicU64,
+}
+
+impl Histogram {
+    pub fn observe(&self, elapsed: Duration) {
+        let secs = elapsed.as_secs_f64();
+        if let Some(i) = BUCKETS.iter().position(|&le| secs <= le) {
+            self.buckets[i].fetch_add(1, Ordering::Relaxed);
+        }
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.sum_us.fetch_add(elapsed.as_micros() as u64, Ordering::Relaxed);
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count.load(Ordering::Relaxed)
+    }
+}
+
+/// 全部运行指标
+#[derive(Default)]
+pub struct Metrics {
+    /// 确认进入的场景 (按场景 ID)
+    pub scenes_detected: LabeledCounter,
+    pub transitions: Counter,
+    pub ocr_calls: Counter,
+    /// 识别结果为空的 OCR
+    pub ocr_failures: Counter,
+    /// 导航结束 (success / handover / failed / aborted)
+    pub navigations: LabeledCounter,
+    /// 塔防确认的新波次
+    pub wave_transitions: Counter,
+    /// 塔防放置 (ok / failed)
+    pub placements: LabeledCounter,
+    /// 一次场景识别 (取帧 + 全部锚点)
+    pub scene_identify: Histogram,
+    /// 一次区域 OCR (含多重曝光)
+    pub ocr_latency: Histogram,
+    /// 塔防监控线程的一次波次轮询
+    pub wave_poll: Histogram,
+}
+
+static METRICS: OnceLock<Metrics> = OnceLock::new();
+
+/// 全局指标 (nav、塔防与接口线程共用)
+pub fn global() -> &'static Metrics {
+    METRICS.get_or_init(Metrics::default)
+}
+
+/// 统计引擎的场景、跳转、OCR 与导航事件
+pub fn attach(engine: &NavEngine) {
+    engine.on_event(|e| {
+        let m = global();
+        match &e.kind {
+            NavEventKind::SceneEntered { scene, .. } => m.scenes_detected.inc(scene),
+            NavEventKind::TransitionClicked { .. } => m.transitions.inc(),
+            NavEventKind::OcrCompleted { text, elapsed_ms, .. } => {
+                m.ocr_calls.inc();
+                if text.trim().is_empty() {
+                    m.ocr_failures.inc();
+                }
+                m.ocr_latency.observe(Duration::from_millis(*elapsed_ms));
+            }
+            NavEventKind::NavigationFinished { result, .. } => {
+                let label = match result {
+                    NavResult::Success => "success",
+                    NavResult::Handover(..) => "handover",
+                    NavResult::Failed(_) => "failed",
+                    NavResult::Aborted(_) => "aborted",
+                };
+                m.navigations.inc(label);
+            }
+            NavEventKind::PopupDismissed { .. } => {}
+        }
+    });
+}
+
+/// 在后台线程提供 GET /metrics；input 用于输出串口 / 输入队列的统计。监听失败时只打印警告
+pub fn spawn(config: &MetricsConfig, input: InputQueue) {
+    let listener = match TcpListener::bind(&config.bind) {
+        Ok(l) => l,
+        Err(e) => {
+            warn!("⚠️ [指标] 无法监听 {}: {}", config.bind, e);
+            return;
+        }
+    };
+    info!("📈 [指标] Prometheus 抓取地址: http://{}/metrics", config.bind);
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let _ = stream.set_read_timeout(Some(Duration::from_secs(5)));
+            handle(stream, &input);
+        }
+    });
+}
+
+fn handle(mut stream: TcpStream, input: &InputQueue) {
+    let Some(req) = api::read_request(&stream) else {
+        return;
+    };
+    let (status, body) = match (req.method.as_str(), req.path.as_str()) {
+        ("GET", "/metrics") => ("200 OK", render(global(), input)),
+        _ => ("404 Not Found", "not found\n".to_string()),
+    };
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: text/plain; version=0.0.4; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    );
+    let _ = stream.write_all(response.as_bytes());
+}
+
+/// 按 Prometheus 文本格式输出全部指标
+pub fn render(m: &Metrics, input: &InputQueue) -> String {
+    let mut out = String::new();
+    labeled(&mut out, "nzm_scenes_detected_total", "确认进入的场景数", "scene", &m.scenes_detected);
+    counter(&mut out, "nzm_transitions_total", "执行的跳转点击数", m.transitions.get());
+    counter(&mut out, "nzm_ocr_calls_total", "区域 OCR 次数", m.ocr_calls.get());
+    counter(&mut out, "nzm_ocr_failures_total", "识别结果为空的 OCR 次数", m.ocr_failures.get());
+    labeled(&mut out, "nzm_navigations_total", "导航结束次数", "result", &m.navigations);
+    counter(&mut out, "nzm_wave_transitions_total", "塔防确认的新波次数", m.wave_transitions.get());
+    labeled(&mut out, "nzm_placements_total", "塔防放置次数", "result", &m.placements);
+
+    let metrics = input.metrics();
+    counter(&mut out, "nzm_input_commands_total", "已发送的命令帧数 (含心跳)", metrics.commands);
+    counter(&mut out, "nzm_serial_write_errors_total", "写入失败的命令帧数", metrics.write_errors);
+    counter(&mut out, "nzm_serial_heartbeats_dropped_total", "写入失败或被阻塞的心跳数", metrics.heartbeats_dropped);
+    gauge(&mut out, "nzm_serial_missed_acks", "连续未被确认的心跳数", metrics.missed_acks as f64);
+    gauge(&mut out, "nzm_serial_latency_avg_seconds", "单帧写入平均耗时", metrics.avg_latency_ms as f64 / 1000.0);
+
+    histogram(&mut out, "nzm_scene_identify_seconds", "一次场景识别的耗时", &m.scene_identify);
+    histogram(&mut out, "nzm_ocr_seconds", "一次区域 OCR 的耗时 (含多重曝光)", &m.ocr_latency);
+    histogram(&mut out, "nzm_wave_poll_seconds", "塔防一次波次轮询的耗时", &m.wave_poll);
+    out
+}
+
+fn header(out: &mut String, name: &str, help: &str, kind: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+fn counter(out: &mut String, name: &str, help: &str, value: u64) {
+    header(out, name, help, "counter");
+    let _ = writeln!(out, "{} {}", name, value);
+}
+
+fn gauge(out: &mut String, name: &str, help: &str, value: f64) {
+    header(out, name, help, "gauge");
+    let _ = writeln!(out, "{} {}", name, value);
+}
+
+fn labeled(out: &mut String, name: &str, help: &str, label: &str, c: &LabeledCounter) {
+    header(out, name, help, "counter");
+    for (value, n) in c.snapshot() {
+        let _ = writeln!(out, "{}{{{}=\"{}\"}} {}", name, label, escape(&value), n);
+    }
+}
+
+fn histogram(out: &mut String, name: &str, help: &str, h: &Histogram) {
+    header(out, name, help, "histogram");
+    let mut cumulative = 0;
+    for (le, bucket) in BUCKETS.iter().zip(&h.buckets) {
+        cumulative += bucket.load(Ordering::Relaxed);
+        let _ = writeln!(out, "{}_bucket{{le=\"{}\"}} {}", name, le, cumulative);
+    }
+    let count = h.count();
+    let _ = writeln!(out, "{}_bucket{{le=\"+Inf\"}} {}", name, count);
+    let _ = writeln!(out, "{}_sum {}", name, h.sum_us.load(Ordering::Relaxed) as f64 / 1_000_000.0);
+    let _ = writeln!(out, "{}_count {}", name, count);
+}
+
+/// 标签值中的反斜杠、引号与换行需要转义
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
@@ -1,166 +1,435 @@
 // src/nav.rs
+use crate::frames::{self, ScreenProvider};
+use crate::vision::{GrayTemplate, SearchImage};
 use crate::human::HumanDriver;
+use crate::metrics;
+use regex::Regex;
 use serde::Deserialize;
-use std::collections::{HashMap, VecDeque};
-use std::sync::{Arc, Mutex};
-use std::sync::atomic::AtomicUsize;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, VecDeque};
+use std::sync::{mpsc, Arc, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::thread;
 use std::time::{Duration, Instant};
 use std::fs;
 use std::path::Path;
-use std::io::Cursor;
+use std::io::Write;
 
 use screenshots::Screen;
-use windows::Media::Ocr::OcrEngine;
-use windows::Globalization::Language;
-use windows::Graphics::Imaging::BitmapDecoder;
-use windows::Storage::Streams::{DataWriter, InMemoryRandomAccessStream};
+
+use nzm_schema::map::{
+    HsvAnchor, ImageAnchor, MapFile, RecoveryAction, Scene, TextAnchor, Transition, TransitionAction, TransitionCondition,
+    MAX_SCROLL_DELTA,
+};
+
+pub use crate::ocr::{OcrBackend, OcrText};
+use crate::ocr::{self, OcrConfig};
+use tracing::{debug, error, info, info_span, warn};
 
 // ==========================================
 // 0. 结果枚举
 // ==========================================
-#[derive(Debug, PartialEq)]
+/// 场景 ID (对应 ui_map.toml 中的 `id`)
+pub type SceneId = String;
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum NavResult {
     Success,
     // ✨ 修改：Handover 携带 (场景ID, 处理器代号)
     Handover(String, Option<String>),
-    Failed,
+    Failed(NavError),
+    /// 识别到危险场景 (如账号异地登录、连接断开)，已立即停止一切输入
+    Aborted(SceneId),
 }
 
-// ==========================================
-// 1. TOML 配置数据结构
-// ==========================================
-#[derive(Deserialize, Debug, Clone)]
-struct TomlRoot { scenes: Vec<Scene> }
+/// 导航失败的具体原因
+#[derive(Debug, Clone, PartialEq)]
+pub enum NavError {
+    /// 所有场景锚点均未命中，无法确定起点
+    UnknownStart { ocr_attempts: usize },
+    /// 场景图中不存在从起点到目标的路径
+    NoPath { from: String, to: String },
+    /// 点击跳转后等待目标场景超时
+    TransitionTimeout {
+        from: String,
+        to: String,
+        /// 超时后重新识别到的实际场景
+        last_scene: Option<String>,
+        waited_ms: u64,
+        ocr_attempts: usize,
+    },
+}
+
+impl std::fmt::Display for NavError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NavError::UnknownStart { ocr_attempts } =>
+                write!(f, "无法定位起点 (OCR {} 次)", ocr_attempts),
+            NavError::NoPath { from, to } =>
+                write!(f, "无路可走 [{}] -> [{}]", from, to),
+            NavError::TransitionTimeout { from, to, last_scene, waited_ms, ocr_attempts } =>
+                write!(f, "跳转超时 [{}] -> [{}]，等待 {}ms (OCR {} 次)，当前识别为 {:?}",
+                    from, to, waited_ms, ocr_attempts, last_scene),
+        }
+    }
+}
 
+/// 单次导航的重试策略：失败后等待 backoff_ms，每次重试等待时间乘以 backoff_factor
 #[derive(Deserialize, Debug, Clone)]
-struct Scene {
-    id: String,
-    #[serde(default)] logic: String,
-    #[serde(default)] anchors: Option<Anchors>,
-    #[serde(default)] transitions: Option<Vec<Transition>>,
-    // ✨ 新增：处理该界面的函数代号 (例如 "daily", "td")
-    #[serde(default)]
-    handler: Option<String>,
+#[serde(default)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    pub backoff_ms: u64,
+    pub backoff_factor: f32,
 }
 
-#[derive(Deserialize, Debug, Clone, Default)]
-struct Anchors {
-    text: Option<Vec<TextAnchor>>,
-    color: Option<Vec<ColorAnchor>>,
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self { max_retries: 0, backoff_ms: 1000, backoff_factor: 2.0 }
+    }
 }
 
-#[derive(Deserialize, Debug, Clone)]
-struct TextAnchor {
-    rect: [i32; 4],
-    val: String,
+impl RetryPolicy {
+    /// 第 n 次重试 (从 1 开始) 前的等待时间
+    pub fn delay_for(&self, retry: u32) -> Duration {
+        let factor = self.backoff_factor.max(1.0).powi(retry.saturating_sub(1) as i32);
+        Duration::from_millis((self.backoff_ms as f32 * factor) as u64)
+    }
 }
 
-#[derive(Deserialize, Debug, Clone)]
-struct ColorAnchor {
-    pos: [i32; 2],
-    val: String,
-    tol: u8,
+/// 单步跳转的结果
+#[derive(Debug, Clone, PartialEq)]
+pub enum TransitionOutcome {
+    /// 已确认进入目标场景
+    Arrived,
+    /// 目标为托管节点，控制权已移交
+    HandedOver,
+    /// 超时后经 on_fail 恢复动作进入目标场景
+    Recovered,
+    /// 等待目标场景超时
+    Timeout,
 }
 
-#[derive(Deserialize, Debug, Clone)]
-struct Transition {
-    target: String,
-    coords: [i32; 2],
-    #[serde(default = "default_delay")]
-    post_delay: u64,
+#[derive(Debug, Clone)]
+pub struct StepReport {
+    pub from: String,
+    pub to: String,
+    pub coords: [i32; 2],
+    pub outcome: TransitionOutcome,
+    pub elapsed_ms: u64,
 }
 
-fn default_delay() -> u64 { 500 }
+/// 一次导航的完整报告：无论成功失败都会返回，供调用方解释发生了什么
+#[derive(Debug, Clone)]
+pub struct NavReport {
+    pub target: String,
+    pub result: NavResult,
+    /// 识别到的起点场景
+    pub start_scene: Option<String>,
+    /// 规划的场景序列 (含起点)
+    pub planned_path: Vec<String>,
+    pub steps: Vec<StepReport>,
+    /// 最后确认所在的场景
+    pub stopped_at: Option<String>,
+    /// 最后一次场景识别的置信度 (0.0 ~ 1.0)
+    pub last_confidence: f32,
+    /// 失败现场截图路径
+    pub evidence: Option<String>,
+    pub elapsed_ms: u64,
+    /// 本次导航消耗的 OCR 次数
+    pub ocr_attempts: usize,
+    /// 总尝试次数 (含重试)
+    pub attempts: u32,
+    /// 途中关闭的全局弹窗数
+    pub popups_dismissed: usize,
+}
+
+impl NavReport {
+    fn new(target: &str) -> Self {
+        Self {
+            target: target.to_string(),
+            result: NavResult::Failed(NavError::UnknownStart { ocr_attempts: 0 }),
+            start_scene: None,
+            planned_path: Vec::new(),
+            steps: Vec::new(),
+            stopped_at: None,
+            last_confidence: 0.0,
+            evidence: None,
+            elapsed_ms: 0,
+            ocr_attempts: 0,
+            attempts: 1,
+            popups_dismissed: 0,
+        }
+    }
+
+    /// 成功完成的跳转数
+    pub fn transitions_succeeded(&self) -> usize {
+        self.steps.iter().filter(|s| s.outcome != TransitionOutcome::Timeout).count()
+    }
+
+    fn result_text(&self) -> String {
+        match &self.result {
+            NavResult::Failed(e) => format!("失败: {}", e),
+            NavResult::Aborted(id) => format!("中止: 危险场景 [{}]", id),
+            other => format!("{:?}", other),
+        }
+    }
+
+    pub fn summary(&self) -> String {
+        format!(
+            "目标 [{}] | 结果 {} | 起点 {:?} | 跳转 {}/{} | 停在 {:?} | 置信度 {:.0}% | OCR {} 次 | 弹窗 {} | 尝试 {} 次 | 耗时 {}ms{}",
+            self.target, self.result_text(), self.start_scene,
+            self.transitions_succeeded(), self.planned_path.len().saturating_sub(1),
+            self.stopped_at, self.last_confidence * 100.0, self.ocr_attempts, self.popups_dismissed, self.attempts, self.elapsed_ms,
+            self.evidence.as_ref().map(|p| format!(" | 现场: {}", p)).unwrap_or_default()
+        )
+    }
+}
+
+/// 规划出的单步跳转 (dry-run 预览用)
+#[derive(Debug, Clone)]
+pub struct TransitionStep {
+    pub from: SceneId,
+    pub to: SceneId,
+    pub coords: [i32; 2],
+    pub post_delay: u64,
+    pub timeout_ms: u64,
+    pub cost: u64,
+}
+
+/// 导航事件 (通过 `NavEngine::on_event` / `NavEngine::subscribe` 订阅)
+#[derive(Debug, Clone)]
+pub struct NavEvent {
+    pub timestamp: chrono::DateTime<chrono::Local>,
+    pub kind: NavEventKind,
+}
+
+#[derive(Debug, Clone)]
+pub enum NavEventKind {
+    /// 确认进入某场景
+    SceneEntered { scene: String, confidence: f32 },
+    /// 执行了一次跳转点击
+    TransitionClicked { from: String, to: String, coords: [i32; 2] },
+    /// 完成一次区域 OCR
+    OcrCompleted { rect: [i32; 4], text: String, elapsed_ms: u64 },
+    /// 关闭了一个全局弹窗
+    PopupDismissed { popup: String },
+    /// 一次导航结束 (成功、移交或失败)
+    NavigationFinished { target: String, result: NavResult, elapsed_ms: u64 },
+}
+
+type NavListener = Box<dyn Fn(&NavEvent) + Send + Sync>;
+
+/// ui_map.toml 加载时的校验问题
+#[derive(Debug, Clone, PartialEq)]
+pub enum ValidationIssue {
+    /// 场景 ID 重复 (后者会覆盖前者)
+    DuplicateId(String),
+    /// 跳转指向不存在的场景
+    UnknownTarget { scene: String, target: String },
+    /// 场景没有任何锚点 (无法被识别，只能作为托管节点)
+    NoAnchors(String),
+    /// 没有任何场景跳转到该场景 (只能作为起点)
+    Unreachable(String),
+    /// 文本锚点 val_regex 或跳转 condition.regex 无法编译
+    InvalidRegex { scene: String, pattern: String },
+    /// 跳转 condition.op 不是支持的比较运算符
+    InvalidConditionOp { scene: String, op: String },
+    /// scroll 跳转的 delta 超过 MAX_SCROLL_DELTA 格 (多半误写成了 Windows 的 120 单位)
+    ScrollDeltaTooLarge { scene: String, delta: i32 },
+}
+
+impl ValidationIssue {
+    /// 错误级问题会导致导航必然失败；其余为警告
+    pub fn is_error(&self) -> bool {
+        matches!(self, ValidationIssue::DuplicateId(_) | ValidationIssue::UnknownTarget { .. } | ValidationIssue::InvalidRegex { .. }
+            | ValidationIssue::InvalidConditionOp { .. } | ValidationIssue::ScrollDeltaTooLarge { .. })
+    }
+}
+
+impl std::fmt::Display for ValidationIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ValidationIssue::DuplicateId(id) => write!(f, "场景 ID 重复: [{}]", id),
+            ValidationIssue::UnknownTarget { scene, target } =>
+                write!(f, "[{}] 的跳转指向不存在的场景 [{}]", scene, target),
+            ValidationIssue::NoAnchors(id) => write!(f, "[{}] 没有锚点", id),
+            ValidationIssue::Unreachable(id) => write!(f, "[{}] 不可达 (没有场景跳转到它)", id),
+            ValidationIssue::InvalidRegex { scene, pattern } =>
+                write!(f, "[{}] 的正则无效: {}", scene, pattern),
+            ValidationIssue::InvalidConditionOp { scene, op } =>
+                write!(f, "[{}] 的跳转条件运算符无效: {} (支持 {})", scene, op, TransitionCondition::OPS.join(" ")),
+            ValidationIssue::ScrollDeltaTooLarge { scene, delta } =>
+                write!(f, "[{}] 的滚动跳转 delta = {} 超过 {} 格 (delta 为滚轮格数，不是 120 的倍数)", scene, delta, MAX_SCROLL_DELTA),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ValidationReport {
+    pub issues: Vec<ValidationIssue>,
+}
+
+impl ValidationReport {
+    pub fn has_errors(&self) -> bool {
+        self.issues.iter().any(|i| i.is_error())
+    }
+
+    pub fn print(&self) {
+        if self.issues.is_empty() {
+            info!("✅ [校验] ui_map 场景图校验通过");
+            return;
+        }
+        for issue in &self.issues {
+            let tag = if issue.is_error() { "❌" } else { "⚠️" };
+            info!("{} [校验] {}", tag, issue);
+        }
+    }
+}
+
+// ==========================================
+// 1. TOML 配置数据结构
+// ==========================================
+// 地图文件格式 (MapFile / Scene / Transition ...) 在 nzm_schema::map 中定义，与 tools/UI_tool 共用
+
+/// 场景锚点评估结果
+#[derive(Debug, Clone, Default)]
+struct SceneMatch {
+    /// 完全命中的锚点数
+    hits: usize,
+    total: usize,
+    /// 场景置信度：AND 取各锚点均值，OR 取最大值
+    confidence: f32,
+    passed: bool,
+    /// 各文本锚点的 OCR 原文 (遥测用)
+    ocr_texts: Vec<String>,
+}
+
+/// 坐标换算：按比例缩放 (实际分辨率 / 基准分辨率) 后再平移 offset (窗口客户区偏移)
+#[derive(Debug, Clone, Copy)]
+struct Scale { x: f32, y: f32, offset: [i32; 2] }
+
+impl Scale {
+    fn is_identity(&self) -> bool { self.x == 1.0 && self.y == 1.0 && self.offset == [0, 0] }
+    fn pos(&self, p: [i32; 2]) -> [i32; 2] {
+        [(p[0] as f32 * self.x).round() as i32 + self.offset[0], (p[1] as f32 * self.y).round() as i32 + self.offset[1]]
+    }
+}
+
+/// 导航前/完成后的钩子动作 (在 config.toml 的 [[tasks]] 中声明)
+#[derive(Deserialize, Debug, Clone)]
+#[serde(tag = "type")]
+pub enum HookAction {
+    /// 按键名: "esc" / "space" / "f12" / 单个字符
+    Key { key: String },
+    Click { x: i32, y: i32 },
+    Wait { ms: u64 },
+    Log { msg: String },
+}
 
 // ==========================================
 // 2. 接口层 (OCR 与 多重图像预处理)
 // ==========================================
+
+/// 游戏所在显示器的序号 (从 1 开始)；0 表示主屏
+static GAME_DISPLAY: AtomicUsize = AtomicUsize::new(0);
+
+/// 选择游戏所在的显示器 ([general] display)，之后所有截图都取自该显示器
+pub fn set_game_display(index: usize) {
+    GAME_DISPLAY.store(index, Ordering::Relaxed);
+}
+
+/// 游戏所在的显示器：指定序号不存在时回退到主屏
+pub fn game_screen() -> Option<Screen> {
+    let screens = Screen::all().unwrap_or_default();
+    let index = GAME_DISPLAY.load(Ordering::Relaxed);
+    let selected = index.checked_sub(1).and_then(|i| screens.get(i));
+    selected
+        .or_else(|| screens.iter().find(|s| s.display_info.is_primary))
+        .or_else(|| screens.first())
+        .copied()
+}
+
+/// 游戏所在显示器的分辨率
+pub fn detect_resolution() -> Option<(u32, u32)> {
+    game_screen().map(|s| (s.display_info.width, s.display_info.height))
+}
+
 struct GameInterface {
     driver: Arc<Mutex<HumanDriver>>,
-    ocr_engine: Option<OcrEngine>,
+    ocr: Box<dyn OcrBackend>,
+    /// 画面来源 (默认为全局帧服务)
+    screen: Arc<dyn ScreenProvider>,
     screenshot_count: AtomicUsize, 
+    /// 累计 OCR 区域识别次数 (用于失败诊断)
+    ocr_count: AtomicUsize,
+    /// 导航事件监听者
+    listeners: Mutex<Vec<NavListener>>,
 }
 
-unsafe impl Send for GameInterface {}
-unsafe impl Sync for GameInterface {}
-
 impl GameInterface {
     fn new(driver: Arc<Mutex<HumanDriver>>) -> Self {
-        println!("🚀 初始化 Windows OCR...");
-        let engine = match Language::CreateLanguage(&windows::core::HSTRING::from("zh-Hans")) {
-            Ok(lang) => match OcrEngine::TryCreateFromLanguage(&lang) {
-                Ok(e) => Some(e),
-                Err(_) => OcrEngine::TryCreateFromUserProfileLanguages().ok()
-            },
-            Err(_) => OcrEngine::TryCreateFromUserProfileLanguages().ok(),
-        };
         Self { 
             driver, 
-            ocr_engine: engine,
+            ocr: ocr::create_backend(&OcrConfig::default()),
+            screen: frames::service().clone(),
             screenshot_count: AtomicUsize::new(0), 
+            ocr_count: AtomicUsize::new(0),
+            listeners: Mutex::new(Vec::new()),
         }
     }
 
-    /// 调用底层 Windows OCR 识别单张图像
-    fn run_windows_ocr(&self, dynamic_img: image::DynamicImage) -> String {
-        if self.ocr_engine.is_none() { return String::new(); }
-        let engine = self.ocr_engine.as_ref().unwrap();
+    /// 截取屏幕指定区域 [x1, y1, x2, y2]，转换为本 crate 使用的 image 版本
+    pub fn capture_rect(&self, rect: [i32; 4]) -> Option<image::RgbaImage> {
+         self.screen.crop(rect)
+    }
 
-        let mut png_buffer = Cursor::new(Vec::new());
-        if dynamic_img.write_to(&mut png_buffer, image::ImageFormat::Png).is_err() { return String::new(); }
-        let png_bytes = png_buffer.into_inner();
+    /// 取一帧 (帧服务的共享帧)，供本轮检测的所有锚点共用
+    fn grab_frame(&self) -> Frame {
+        let image = self.screen.frame().map(|f| f.image).unwrap_or_default();
+        Frame { image, ocr: Mutex::new(HashMap::new()) }
+    }
 
-        let stream = InMemoryRandomAccessStream::new().unwrap();
-        let writer = DataWriter::CreateDataWriter(&stream).unwrap();
-        if writer.WriteBytes(&png_bytes).is_err() { return String::new(); }
-        if writer.StoreAsync().unwrap().get().is_err() { return String::new(); }
-        if writer.FlushAsync().unwrap().get().is_err() { return String::new(); }
-        if writer.DetachStream().is_err() { return String::new(); }
-        if stream.Seek(0).is_err() { return String::new(); }
+    pub fn get_text_from_area(&self, rect: [i32; 4]) -> String {
+        self.get_ocr_text_from_area(rect).text
+    }
 
-        let decoder = match BitmapDecoder::CreateAsync(&stream) {
-             Ok(op) => match op.get() { Ok(d) => d, Err(_) => return String::new() },
-             Err(_) => return String::new(),
-        };
-        let software_bitmap = match decoder.GetSoftwareBitmapAsync() {
-             Ok(op) => match op.get() { Ok(b) => b, Err(_) => return String::new() },
-             Err(_) => return String::new(),
-        };
-        let result = match engine.RecognizeAsync(&software_bitmap) {
-             Ok(op) => match op.get() { Ok(res) => res, Err(_) => return String::new() },
-             Err(_) => return String::new(),
-        };
-        
-        let mut full_text = String::new();
-        if let Ok(lines) = result.Lines() {
-            for line in lines {
-                if let Ok(text) = line.Text() { full_text.push_str(&text.to_string()); }
-            }
-        }
-        full_text.replace(|c: char| c.is_whitespace(), "")
+    /// 同 get_text_from_area，同时带回 OCR 置信度
+    pub fn get_ocr_text_from_area(&self, rect: [i32; 4]) -> OcrText {
+         match self.capture_rect(rect) {
+             Some(img) => self.recognize_image(img, rect),
+             None => OcrText::default(),
+         }
     }
 
-    pub fn get_text_from_area(&self, rect: [i32; 4]) -> String {
-         let x = rect[0]; 
-         let y = rect[1];
-         let w = (rect[2] - rect[0]).max(1);
-         let h = (rect[3] - rect[1]).max(1);
-         
-         let screens = Screen::all().unwrap_or_default();
-         let screen = match screens.first() { Some(s) => s, None => return String::new() };
-         
-         let captured_data = match screen.capture_area(x, y, w as u32, h as u32) {
-             Ok(img) => img,
-             Err(_) => return String::new(),
-         };
+    /// 批量 OCR：只截一帧，裁剪出各区域后并行识别，结果与 rects 顺序一致
+    pub fn get_texts_from_areas(&self, rects: &[[i32; 4]]) -> Vec<String> {
+         if rects.is_empty() { return Vec::new(); }
+         let frame = self.grab_frame();
+         frame.prefetch_texts(self, rects);
+         rects.iter().map(|r| frame.text(self, *r)).collect()
+    }
+
+    /// 对已截取的区域图像执行预处理 + 多重曝光 OCR
+    /// 多重曝光时置信度取各次识别中的最高值
+    fn recognize_image(&self, rgba_img: image::RgbaImage, rect: [i32; 4]) -> OcrText {
+         let (w, h) = rgba_img.dimensions();
+         self.ocr_count.fetch_add(1, Ordering::Relaxed);
+         let ocr_start = Instant::now();
 
          // 1. 基础转换
-         let rgba_img = image::RgbaImage::from_raw(captured_data.width(), captured_data.height(), captured_data.into_raw()).unwrap();
          let dynamic_img = image::DynamicImage::ImageRgba8(rgba_img);
 
+         if !self.ocr.multi_exposure() {
+             let result = self.ocr.recognize(&dynamic_img, rect);
+             self.emit(NavEventKind::OcrCompleted {
+                 rect, text: result.text.clone(), elapsed_ms: ocr_start.elapsed().as_millis() as u64,
+             });
+             return result;
+         }
+
          // 2. 🔥 2倍放大：Lanczos3 采样能有效平滑艺术字边缘
-         let scaled_img = dynamic_img.resize(w as u32 * 2, h as u32 * 2, image::imageops::FilterType::Lanczos3);
+         let scaled_img = dynamic_img.resize(w * 2, h * 2, image::imageops::FilterType::Lanczos3);
          
          // 3. 🔥 多重曝光 OCR 策略
          let mut results = Vec::new();
@@ -168,45 +437,100 @@ impl GameInterface {
          // 策略 A: 强二值化 (阈值 200)
          let mut luma_high = scaled_img.grayscale().into_luma8();
          for pixel in luma_high.pixels_mut() { pixel[0] = if pixel[0] > 200 { 255 } else { 0 }; }
-         results.push(self.run_windows_ocr(image::DynamicImage::ImageLuma8(luma_high)));
+         results.push(self.ocr.recognize(&image::DynamicImage::ImageLuma8(luma_high), rect));
 
          // 策略 B: 中等二值化 (阈值 140)
          let mut luma_mid = scaled_img.grayscale().into_luma8();
          for pixel in luma_mid.pixels_mut() { pixel[0] = if pixel[0] > 140 { 255 } else { 0 }; }
-         results.push(self.run_windows_ocr(image::DynamicImage::ImageLuma8(luma_mid)));
+         results.push(self.ocr.recognize(&image::DynamicImage::ImageLuma8(luma_mid), rect));
 
          // 策略 C: 原色缩放图
-         results.push(self.run_windows_ocr(scaled_img.clone()));
+         results.push(self.ocr.recognize(&scaled_img, rect));
 
          // 4. 合并所有识别到的文本块
-         let final_text = results.join(" ");
-         final_text
+         let final_text = results.iter().map(|r| r.text.as_str()).collect::<Vec<_>>().join(" ");
+         let confidence = results.iter().map(|r| r.confidence).fold(0.0, f32::max);
+         self.emit(NavEventKind::OcrCompleted {
+             rect, text: final_text.clone(), elapsed_ms: ocr_start.elapsed().as_millis() as u64,
+         });
+         OcrText { text: final_text, confidence }
     }
 
-    fn check_text_anchor(&self, rect: [i32; 4], expected: &str) -> bool {
-        let output = self.get_text_from_area(rect);
-        output.contains(expected)
+    fn emit(&self, kind: NavEventKind) {
+        if let Ok(listeners) = self.listeners.lock() {
+            if listeners.is_empty() { return; }
+            let event = NavEvent { timestamp: chrono::Local::now(), kind };
+            for l in listeners.iter() { l(&event); }
+        }
     }
 
     pub fn debug_ocr_file(&self, file_path: &str, expected_contain: &str) {
-        println!("📂 [本地测试] 加载: {}", file_path);
+        info!("📂 [本地测试] 加载: {}", file_path);
         if !Path::new(file_path).exists() { return; }
         let dynamic_img = image::open(file_path).expect("加载失败");
-        let output = self.run_windows_ocr(dynamic_img);
-        println!("📝 结果: [{}] | 期望: [{}] -> {}", output, expected_contain, output.contains(expected_contain));
-    }
-
-    fn check_color_anchor(&self, pos: [i32; 2], expected_hex: &str, tolerance: u8) -> bool {
-        let x = pos[0]; let y = pos[1];
-        let screens = Screen::all().unwrap_or_default();
-        let screen = match screens.first() { Some(s) => s, None => return false };
-        let image = match screen.capture_area(x, y, 1, 1) { Ok(img) => img, Err(_) => return false };
-        let data = image.as_raw();
-        if data.len() < 3 { return false; }
-        let (r, g, b) = (data[0], data[1], data[2]);
-        let expected_rgb = hex::decode(expected_hex.trim_start_matches('#')).unwrap_or(vec![0,0,0]);
+        let output = self.ocr.recognize(&dynamic_img, [0, 0, 0, 0]).text;
+        info!("📝 结果: [{}] | 期望: [{}] -> {}", output, expected_contain, output.contains(expected_contain));
+    }
+
+    /// 颜色锚点置信度：容差内为 1.0，超出容差后按色差线性衰减
+    fn color_anchor_confidence(frame: &Frame, pos: [i32; 2], expected_hex: &str, tolerance: u8) -> f32 {
+        let (x, y) = (pos[0], pos[1]);
+        if x < 0 || y < 0 || x as u32 >= frame.image.width() || y as u32 >= frame.image.height() { return 0.0; }
+        let px = frame.image.get_pixel(x as u32, y as u32);
+        let (r, g, b) = (px[0], px[1], px[2]);
+        let expected_rgb = hex::decode(expected_hex.trim_start_matches('#')).ok().filter(|v| v.len() >= 3).unwrap_or(vec![0,0,0]);
         let diff = (r as i16 - expected_rgb[0] as i16).abs() + (g as i16 - expected_rgb[1] as i16).abs() + (b as i16 - expected_rgb[2] as i16).abs();
-        diff <= (tolerance as i16 * 3)
+        let allowed = tolerance as i16 * 3;
+        if diff <= allowed { return 1.0; }
+        (1.0 - (diff - allowed) as f32 / 255.0).max(0.0)
+    }
+
+    /// 区域颜色锚点置信度：采样区域平均色在 HSV 容差内为 1.0，超出后按最大超出比例线性衰减 (超出一倍容差为 0)
+    fn hsv_anchor_confidence(frame: &Frame, anchor: &HsvAnchor) -> f32 {
+        let Some(region) = frame.crop(anchor.sample_rect()) else { return 0.0 };
+        let mut sum = [0u64; 3];
+        for p in region.pixels() {
+            for (s, c) in sum.iter_mut().zip(p.0) { *s += c as u64; }
+        }
+        let n = (region.width() * region.height()) as u64;
+        let avg = sum.map(|s| (s / n) as u8);
+        let deviation = anchor.deviation(avg);
+        if deviation <= 1.0 { return 1.0; }
+        (2.0 - deviation).max(0.0)
+    }
+
+    /// 截取整屏保存到 failures/ 目录，返回文件路径
+    fn save_failure_screenshot(&self, tag: &str) -> Option<String> {
+        let image = self.screen.frame()?.image;
+        fs::create_dir_all("failures").ok()?;
+        let path = format!("failures/{}_{}.png", tag, chrono::Local::now().format("%Y%m%d_%H%M%S"));
+        match image.save(&path) {
+            Ok(_) => Some(path),
+            Err(e) => { warn!("⚠️ 现场截图保存失败: {}", e); None }
+        }
+    }
+
+    /// 按当前键盘布局解析按键名并按下；无法解析时返回 false
+    fn perform_key(&self, key: &str) -> bool {
+        match self.driver.lock() {
+            Ok(mut bot) => bot.key_hold_name(key, 0),
+            Err(_) => false,
+        }
+    }
+
+    fn perform_drag(&self, from: [i32; 2], to: [i32; 2]) {
+        if let Ok(mut bot) = self.driver.lock() {
+            bot.drag_humanly((from[0] as u16, from[1] as u16), (to[0] as u16, to[1] as u16), 0.4);
+        }
+    }
+
+    fn perform_scroll(&self, x: i32, y: i32, delta: i32) {
+        // 超限的 delta 已在加载校验中报告，这里截断以免长时间滚动
+        let delta = delta.clamp(-MAX_SCROLL_DELTA, MAX_SCROLL_DELTA);
+        if let Ok(mut bot) = self.driver.lock() {
+            bot.move_to_humanly(x as u16, y as u16, 0.6);
+            bot.scroll_humanly(delta, 0.12 * delta.unsigned_abs() as f32);
+        }
     }
 
     fn perform_click(&self, x: i32, y: i32) {
@@ -217,21 +541,308 @@ impl GameInterface {
     }
 }
 
+const OCR_PARALLELISM: usize = 8;
+
+/// 一个检测周期的画面：只截一次屏，所有候选场景的锚点都在这一帧上评估，
+/// 相同区域的 OCR 结果在本帧内复用。
+struct Frame {
+    image: Arc<image::RgbaImage>,
+    ocr: Mutex<HashMap<[i32; 4], String>>,
+}
+
+impl Frame {
+    fn crop(&self, rect: [i32; 4]) -> Option<image::RgbaImage> {
+        frames::crop(&self.image, rect)
+    }
+
+    /// 并行识别本帧中尚未识别过的区域
+    fn prefetch_texts(&self, interface: &GameInterface, rects: &[[i32; 4]]) {
+        let mut todo: Vec<[i32; 4]> = match self.ocr.lock() {
+            Ok(cache) => rects.iter().filter(|r| !cache.contains_key(*r)).cloned().collect(),
+            Err(_) => return,
+        };
+        todo.sort();
+        todo.dedup();
+        if todo.is_empty() { return; }
+        // 每批最多 OCR_PARALLELISM 个区域并行，避免场景很多时一次起过多线程
+        let mut results: Vec<String> = Vec::with_capacity(todo.len());
+        for chunk in todo.chunks(OCR_PARALLELISM) {
+            thread::scope(|scope| {
+                let handles: Vec<_> = chunk.iter()
+                    .map(|rect| scope.spawn(move || match self.crop(*rect) {
+                        Some(img) => interface.recognize_image(img, *rect).text,
+                        None => String::new(),
+                    }))
+                    .collect();
+                results.extend(handles.into_iter().map(|h| h.join().unwrap_or_default()));
+            });
+        }
+        if let Ok(mut cache) = self.ocr.lock() {
+            cache.extend(todo.into_iter().zip(results));
+        }
+    }
+
+    fn text(&self, interface: &GameInterface, rect: [i32; 4]) -> String {
+        if let Some(t) = self.ocr.lock().ok().and_then(|c| c.get(&rect).cloned()) { return t; }
+        let text = self.crop(rect).map(|img| interface.recognize_image(img, rect).text).unwrap_or_default();
+        if let Ok(mut cache) = self.ocr.lock() { cache.insert(rect, text.clone()); }
+        text
+    }
+}
+
+/// 文本锚点置信度：包含期望文本为 1.0，否则取 OCR 文本中与期望最接近的子串，
+/// 按 1 - 编辑距离 / 期望长度 计算 ("空间站普逍" 对 "空间站普通" 为 0.8)
+fn text_similarity(output: &str, expected: &str) -> f32 {
+    if output.contains(expected) { return 1.0; }
+    let a: Vec<char> = expected.chars().collect();
+    let b: Vec<char> = output.chars().collect();
+    if a.is_empty() || b.is_empty() { return 0.0; }
+    // 近似子串匹配 (Sellers)：子串可以从 OCR 文本的任意位置开始/结束
+    let mut prev: Vec<usize> = vec![0; b.len() + 1];
+    for (i, ca) in a.iter().enumerate() {
+        let mut cur = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let sub = prev[j] + if ca == cb { 0 } else { 1 };
+            cur[j + 1] = sub.min(prev[j + 1] + 1).min(cur[j] + 1);
+        }
+        prev = cur;
+    }
+    let dist = prev.into_iter().min().unwrap_or(a.len());
+    (1.0 - dist as f32 / a.len() as f32).max(0.0)
+}
+
 // ==========================================
 // 3. 导航引擎
 // ==========================================
 pub struct NavEngine {
     scenes: HashMap<String, Scene>,
+    global_scenes: Vec<Scene>,
+    /// 图像锚点模板缓存 (file -> 模板)
+    image_templates: HashMap<String, GrayTemplate>,
+    /// 文本锚点 val_regex 与跳转 condition.regex 的正则缓存 (pattern -> 编译结果)
+    regexes: HashMap<String, Regex>,
+    min_confidence: Option<f32>,
+    fuzzy_threshold: Option<f32>,
+    validation: ValidationReport,
+    /// 导航遥测输出 (JSON Lines)，未开启时为 None
+    telemetry: Mutex<Option<fs::File>>,
     interface: GameInterface,
 }
 
 impl NavEngine {
+    /// 加载场景图：file_path 可以是单个 TOML 文件，也可以是存放多个 TOML 片段的目录
     pub fn new(file_path: &str, driver: Arc<Mutex<HumanDriver>>) -> Self {
-        let content = fs::read_to_string(file_path).expect("无法读取 TOML");
-        let root: TomlRoot = toml::from_str(&content).expect("TOML 解析错误");
+        let mut root = if Path::new(file_path).is_dir() {
+            Self::load_fragments(file_path)
+        } else {
+            let content = fs::read_to_string(file_path).expect("无法读取 TOML");
+            Self::parse_map(file_path, &content)
+        };
+        let mut validation = Self::validate_root(&root);
+        validation.print();
+        let scale = Self::resolve_scale(root.base_resolution, root.window_offset);
+        Self::check_dpi_scale(root.dpi_scale);
+        if !scale.is_identity() {
+            for s in root.scenes.iter_mut().chain(root.global_scenes.iter_mut()) { s.map_points(|p| scale.pos(p)); }
+        }
         let mut map = HashMap::new();
         for s in root.scenes { map.insert(s.id.clone(), s); }
-        Self { scenes: map, interface: GameInterface::new(driver) }
+        if !root.global_scenes.is_empty() {
+            info!("🪟 已加载 {} 个全局弹窗场景", root.global_scenes.len());
+        }
+        let mut image_templates = HashMap::new();
+        for scene in map.values().chain(root.global_scenes.iter()) {
+            let images = scene.anchors.as_ref().and_then(|a| a.image.as_ref());
+            for img in images.into_iter().flatten() {
+                if image_templates.contains_key(&img.file) { continue; }
+                if let Some(tpl) = GrayTemplate::load_scaled(&img.file, scale.x, scale.y) {
+                    image_templates.insert(img.file.clone(), tpl);
+                }
+            }
+        }
+        let mut regexes = HashMap::new();
+        for scene in map.values().chain(root.global_scenes.iter()) {
+            let texts = scene.anchors.as_ref().and_then(|a| a.text.as_ref());
+            let conditions = scene.transitions.iter().flatten().filter_map(|t| t.condition.as_ref());
+            let patterns = texts.into_iter().flatten().filter_map(|t| t.val_regex.as_ref())
+                .chain(conditions.filter_map(|c| c.regex.as_ref()));
+            for pattern in patterns {
+                if regexes.contains_key(pattern) { continue; }
+                match Regex::new(pattern) {
+                    Ok(re) => { regexes.insert(pattern.clone(), re); }
+                    Err(e) => {
+                        error!("❌ [校验] 正则编译失败 [{}] {}: {}", scene.id, pattern, e);
+                        validation.issues.push(ValidationIssue::InvalidRegex { scene: scene.id.clone(), pattern: pattern.clone() });
+                    }
+                }
+            }
+        }
+        Self {
+            scenes: map,
+            global_scenes: root.global_scenes,
+            min_confidence: root.min_confidence,
+            fuzzy_threshold: root.fuzzy_threshold,
+            image_templates,
+            regexes,
+            validation,
+            telemetry: Mutex::new(None),
+            interface: GameInterface::new(driver),
+        }
+    }
+
+    /// 解析单个地图文本；旧版格式按当前格式读取并提示迁移
+    fn parse_map(name: &str, content: &str) -> MapFile {
+        let (map, migration) = MapFile::from_toml_str(content).unwrap_or_else(|e| panic!("TOML 解析错误 ({}): {}", name, e));
+        if let Some(m) = migration {
+            warn!("🔁 {} 为旧版地图格式 (v{})，已按当前格式读取；用 UI_tool 打开并保存即可完成迁移", name, m.from);
+            for note in &m.notes {
+                warn!("   ⚠️ {}", note);
+            }
+        }
+        map
+    }
+
+    /// 按文件名顺序合并目录下所有 *.toml 片段。
+    /// 顶层设置 (min_confidence / fuzzy_threshold / base_resolution / window_offset / dpi_scale) 取第一个声明的片段；重复场景 ID 在校验阶段报告。
+    fn load_fragments(dir: &str) -> MapFile {
+        let mut files: Vec<_> = fs::read_dir(dir).expect("无法读取地图目录")
+            .flatten()
+            .map(|e| e.path())
+            .filter(|p| p.extension().is_some_and(|ext| ext == "toml"))
+            .collect();
+        files.sort();
+
+        let mut merged = MapFile::default();
+        let mut origin: HashMap<String, String> = HashMap::new();
+        for path in &files {
+            let name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+            let content = fs::read_to_string(path).unwrap_or_else(|e| panic!("无法读取 {}: {}", name, e));
+            let part = Self::parse_map(&name, &content);
+            for s in &part.scenes {
+                if let Some(prev) = origin.insert(s.id.clone(), name.clone()) {
+                    warn!("⚠️ 场景 [{}] 在 {} 与 {} 中重复定义", s.id, prev, name);
+                }
+            }
+            merged.scenes.extend(part.scenes);
+            merged.global_scenes.extend(part.global_scenes);
+            merged.min_confidence = merged.min_confidence.or(part.min_confidence);
+            merged.fuzzy_threshold = merged.fuzzy_threshold.or(part.fuzzy_threshold);
+            merged.base_resolution = merged.base_resolution.or(part.base_resolution);
+            merged.window_offset = merged.window_offset.or(part.window_offset);
+            merged.dpi_scale = merged.dpi_scale.or(part.dpi_scale);
+        }
+        info!("🗂️ 已合并 {} 个地图片段 ({} 个场景)", files.len(), merged.scenes.len());
+        merged
+    }
+
+    /// 严格模式：场景图存在错误级问题时拒绝启动
+    pub fn new_strict(file_path: &str, driver: Arc<Mutex<HumanDriver>>) -> Result<Self, ValidationReport> {
+        let engine = Self::new(file_path, driver);
+        if engine.validation.has_errors() {
+            return Err(engine.validation);
+        }
+        Ok(engine)
+    }
+
+    /// 加载时的场景图校验结果
+    pub fn validation(&self) -> &ValidationReport {
+        &self.validation
+    }
+
+    fn validate_root(root: &MapFile) -> ValidationReport {
+        let mut report = ValidationReport::default();
+        let mut ids: Vec<&str> = Vec::new();
+        for s in &root.scenes {
+            if ids.contains(&s.id.as_str()) {
+                report.issues.push(ValidationIssue::DuplicateId(s.id.clone()));
+            } else {
+                ids.push(&s.id);
+            }
+        }
+        let mut targeted: Vec<&str> = Vec::new();
+        for s in &root.scenes {
+            let anchor_count = s.anchors.as_ref().map(|a| {
+                a.text.as_ref().map_or(0, |v| v.len())
+                    + a.color.as_ref().map_or(0, |v| v.len())
+                    + a.hsv.as_ref().map_or(0, |v| v.len())
+                    + a.image.as_ref().map_or(0, |v| v.len())
+            }).unwrap_or(0);
+            if anchor_count == 0 {
+                report.issues.push(ValidationIssue::NoAnchors(s.id.clone()));
+            }
+            for t in s.transitions.iter().flatten() {
+                if let Some(cond) = t.condition.as_ref().filter(|c| c.compare(0).is_none()) {
+                    report.issues.push(ValidationIssue::InvalidConditionOp { scene: s.id.clone(), op: cond.op.clone() });
+                }
+                if t.action == TransitionAction::Scroll && t.delta.abs() > MAX_SCROLL_DELTA {
+                    report.issues.push(ValidationIssue::ScrollDeltaTooLarge { scene: s.id.clone(), delta: t.delta });
+                }
+                if ids.contains(&t.target.as_str()) {
+                    targeted.push(&t.target);
+                } else {
+                    report.issues.push(ValidationIssue::UnknownTarget { scene: s.id.clone(), target: t.target.clone() });
+                }
+            }
+        }
+        for id in &ids {
+            if !targeted.contains(id) {
+                report.issues.push(ValidationIssue::Unreachable(id.to_string()));
+            }
+        }
+        report
+    }
+
+    /// 根据 base_resolution 与游戏显示器的实际分辨率计算缩放比例，并带上 window_offset 平移
+    fn resolve_scale(base: Option<[u32; 2]>, window_offset: Option<[i32; 2]>) -> Scale {
+        let offset = window_offset.unwrap_or_default();
+        if offset != [0, 0] {
+            info!("🪟 ui_map 坐标相对游戏窗口，平移 ({}, {})", offset[0], offset[1]);
+        }
+        let identity = Scale { x: 1.0, y: 1.0, offset };
+        let [bw, bh] = match base { Some(b) if b[0] > 0 && b[1] > 0 => b, _ => return identity };
+        let (w, h) = match detect_resolution() { Some(r) => r, None => return identity };
+        if (w, h) != (bw, bh) {
+            warn!("⚠️ ui_map 基准分辨率 {}x{} 与实际 {}x{} 不一致，坐标将按比例缩放，锚点识别可能受影响", bw, bh, w, h);
+        }
+        Scale { x: w as f32 / bw as f32, y: h as f32 / bh as f32, offset }
+    }
+
+    /// DPI 缩放不同会改变窗口化游戏的界面布局，坐标缩放无法补偿，只能提示
+    fn check_dpi_scale(expected: Option<f32>) {
+        let (Some(expected), Some(actual)) = (expected, game_screen().map(|s| s.display_info.scale_factor)) else { return };
+        if (expected - actual).abs() > 0.01 {
+            warn!("⚠️ ui_map 采集时 DPI 缩放为 {:.0}%，当前显示器为 {:.0}%，窗口化游戏的界面布局可能不同，建议统一缩放或重新采集", expected * 100.0, actual * 100.0);
+        }
+    }
+
+    /// 替换 OCR 后端 (默认为平台默认后端：WinRT / Tesseract)，见 `ocr::create_backend`
+    pub fn with_ocr_backend(mut self, backend: Box<dyn OcrBackend>) -> Self {
+        info!("🔤 OCR 后端: {}", backend.name());
+        self.interface.ocr = backend;
+        self
+    }
+
+    /// 替换画面来源 (如回放预先录好的截图，见 fixture::FixtureScreen)
+    pub fn with_screen(mut self, screen: Arc<dyn ScreenProvider>) -> Self {
+        self.interface.screen = screen;
+        self
+    }
+
+    /// 注册导航事件回调 (在导航线程中同步调用，回调内请勿阻塞)
+    pub fn on_event<F: Fn(&NavEvent) + Send + Sync + 'static>(&self, callback: F) {
+        if let Ok(mut listeners) = self.interface.listeners.lock() {
+            listeners.push(Box::new(callback));
+        }
+    }
+
+    /// 以通道形式订阅导航事件
+    pub fn subscribe(&self) -> mpsc::Receiver<NavEvent> {
+        let (tx, rx) = mpsc::channel();
+        let tx = Mutex::new(tx);
+        self.on_event(move |e| {
+            if let Ok(tx) = tx.lock() { let _ = tx.send(e.clone()); }
+        });
+        rx
     }
 
     pub fn test_ocr_on_file(&self, filename: &str, expected: &str) {
@@ -242,86 +853,297 @@ impl NavEngine {
         self.interface.get_text_from_area(rect)
     }
 
-    fn get_match_score(&self, target_id: &str) -> usize {
-        if let Some(scene) = self.scenes.get(target_id) {
-            if scene.anchors.is_none() { return 0; }
-            let anchors = scene.anchors.as_ref().unwrap();
-            let mut score = 0;
-            let mut total_checks = 0;
-            if let Some(texts) = &anchors.text {
-                for t in texts {
-                    total_checks += 1;
-                    if self.interface.check_text_anchor(t.rect, &t.val) { score += 1; }
-                }
+    /// OCR 区域并带回置信度 (供需要判断识别可靠性的调用方，如波次读取)
+    pub fn ocr_area_detailed(&self, rect: [i32; 4]) -> OcrText {
+        self.interface.get_ocr_text_from_area(rect)
+    }
+
+    /// 批量 OCR：整帧只截一次，各区域并行识别
+    pub fn ocr_areas(&self, rects: &[[i32; 4]]) -> Vec<String> {
+        self.interface.get_texts_from_areas(rects)
+    }
+
+    /// 截取区域原图 (供模板匹配等非 OCR 识别使用)
+    pub fn capture_area(&self, rect: [i32; 4]) -> Option<image::RgbaImage> {
+        self.interface.capture_rect(rect)
+    }
+
+    /// 拟人化点击屏幕坐标 (供脚本处理器等外部模块使用)
+    pub fn click(&self, x: i32, y: i32) {
+        self.interface.perform_click(x, y);
+    }
+
+    /// 按键名按下一次 (同钩子的 key)；无法解析时返回 false
+    pub fn press_key(&self, key: &str) -> bool {
+        self.interface.perform_key(key)
+    }
+
+    /// 整屏截图保存到 failures/ 目录 (现场诊断用)，返回文件路径
+    pub fn save_screenshot(&self, tag: &str) -> Option<String> {
+        self.interface.save_failure_screenshot(tag)
+    }
+
+    /// 评估场景锚点
+    fn evaluate_scene(&self, target_id: &str) -> SceneMatch {
+        match self.scenes.get(target_id) {
+            Some(scene) => self.evaluate_anchors(scene, &self.interface.grab_frame()),
+            None => SceneMatch::default(),
+        }
+    }
+
+    /// 收集若干场景的全部文本锚点区域 (用于整帧并行 OCR 预取)
+    fn text_rects<'a>(scenes: impl Iterator<Item = &'a Scene>) -> Vec<[i32; 4]> {
+        scenes
+            .filter_map(|s| s.anchors.as_ref())
+            .flat_map(|a| a.text.iter().flatten().map(|t| t.rect))
+            .collect()
+    }
+
+    fn evaluate_anchors(&self, scene: &Scene, frame: &Frame) -> SceneMatch {
+        let anchors = match &scene.anchors { Some(a) => a, None => return SceneMatch::default() };
+        let mut confs: Vec<f32> = Vec::new();
+        // 模糊命中的文本锚点数 (相似度未满但达到 fuzzy 阈值)
+        let mut fuzzy_hits = 0;
+        let texts: Vec<&TextAnchor> = anchors.text.iter().flatten().collect();
+        let rects: Vec<[i32; 4]> = texts.iter().map(|t| t.rect).collect();
+        frame.prefetch_texts(&self.interface, &rects);
+        let outputs: Vec<String> = rects.iter().map(|r| frame.text(&self.interface, *r)).collect();
+        for (t, output) in texts.iter().zip(outputs.iter()) {
+            let conf = match &t.val_regex {
+                // 正则锚点为二值判定；编译失败的正则永不命中
+                Some(p) => if self.regexes.get(p).is_some_and(|re| re.is_match(output)) { 1.0 } else { 0.0 },
+                None => text_similarity(output, &t.val),
+            };
+            if conf < 1.0 && t.fuzzy.or(self.fuzzy_threshold).is_some_and(|th| conf >= th) {
+                debug!("🔤 [{}] 模糊命中 '{}' ≈ '{}' ({:.2})", scene.id, output, t.val, conf);
+                fuzzy_hits += 1;
             }
-            if let Some(colors) = &anchors.color {
-                for c in colors {
-                    total_checks += 1;
-                    if self.interface.check_color_anchor(c.pos, &c.val, c.tol) { score += 1; }
+            confs.push(conf);
+        }
+        for c in anchors.color.iter().flatten() {
+            confs.push(GameInterface::color_anchor_confidence(frame, c.pos, &c.val, c.tol));
+        }
+        for h in anchors.hsv.iter().flatten() {
+            confs.push(GameInterface::hsv_anchor_confidence(frame, h));
+        }
+        for i in anchors.image.iter().flatten() {
+            let conf = self.image_anchor_confidence(i, frame);
+            // 达到模板阈值即视为完全命中
+            confs.push(if conf >= i.threshold { 1.0 } else { conf });
+        }
+        if confs.is_empty() { return SceneMatch::default(); }
+
+        let total = confs.len();
+        let hits = confs.iter().filter(|c| **c >= 1.0).count() + fuzzy_hits;
+        let is_or = scene.logic.to_lowercase() == "or";
+        let confidence = if is_or {
+            confs.iter().cloned().fold(0.0, f32::max)
+        } else {
+            confs.iter().sum::<f32>() / total as f32
+        };
+        let passed = match scene.min_confidence.or(self.min_confidence) {
+            Some(min) => confidence >= min,
+            None if is_or => hits > 0,
+            None => hits == total,
+        };
+        SceneMatch { hits, total, confidence, passed, ocr_texts: outputs }
+    }
+
+    /// 图像锚点置信度：区域内最佳 NCC 得分
+    fn image_anchor_confidence(&self, anchor: &ImageAnchor, frame: &Frame) -> f32 {
+        let tpl = match self.image_templates.get(&anchor.file) { Some(t) => t, None => return 0.0 };
+        let img = match frame.crop(anchor.rect) { Some(i) => i, None => return 0.0 };
+        let search = SearchImage::from_rgba(&img);
+        tpl.best_match_in(&search, [0, 0, search.width(), search.height()]).map(|(_, _, score)| score.max(0.0)).unwrap_or(0.0)
+    }
+
+    /// 执行一次跳转动作 (click / key / drag / scroll)
+    fn perform_transition(&self, t: &Transition) {
+        if t.pre_delay > 0 {
+            thread::sleep(Duration::from_millis(t.pre_delay));
+        }
+        match t.action {
+            TransitionAction::Click => self.interface.perform_click(t.coords[0], t.coords[1]),
+            TransitionAction::Key => {
+                if !t.key.as_deref().is_some_and(|k| self.interface.perform_key(k)) {
+                    warn!("    ⚠️ 跳转 [{}] 的按键无效: {:?}", t.target, t.key);
                 }
             }
-            let passed = match scene.logic.to_lowercase().as_str() {
-                "or" => score > 0,              
-                _ => score == total_checks && total_checks > 0, 
-            };
-            if passed { return score; }
+            TransitionAction::Drag => match (t.from, t.to) {
+                (Some(from), Some(to)) => self.interface.perform_drag(from, to),
+                _ => warn!("    ⚠️ 跳转 [{}] 缺少 drag 的 from/to 坐标", t.target),
+            },
+            TransitionAction::Scroll => self.interface.perform_scroll(t.coords[0], t.coords[1], t.delta),
         }
-        0
+    }
+
+    /// 检查是否处于危险场景 (普通场景与全局场景中标记 danger = true 的)
+    fn detect_danger(&self) -> Option<SceneId> {
+        let dangers: Vec<&Scene> = self.scenes.values().chain(self.global_scenes.iter()).filter(|s| s.danger).collect();
+        if dangers.is_empty() { return None; }
+        let frame = self.interface.grab_frame();
+        frame.prefetch_texts(&self.interface, &Self::text_rects(dangers.iter().copied()));
+        let hit = dangers.into_iter().find(|s| self.evaluate_anchors(s, &frame).passed)?;
+        warn!("🛑 检测到危险场景 [{}]，立即中止导航！", hit.id);
+        Some(hit.id.clone())
+    }
+
+    /// 检查并关闭全局弹窗，直到没有弹窗命中 (最多 5 轮)，返回关闭的弹窗数
+    fn dismiss_popups(&self) -> usize {
+        let mut dismissed = 0;
+        for _ in 0..5 {
+            if self.global_scenes.iter().all(|g| g.danger) { break; }
+            let frame = self.interface.grab_frame();
+            frame.prefetch_texts(&self.interface, &Self::text_rects(self.global_scenes.iter().filter(|g| !g.danger)));
+            let popup = self.global_scenes.iter().find(|g| !g.danger && self.evaluate_anchors(g, &frame).passed);
+            let popup = match popup { Some(p) => p, None => break };
+            info!("🪟 检测到弹窗 [{}]，执行关闭...", popup.id);
+            self.interface.emit(NavEventKind::PopupDismissed { popup: popup.id.clone() });
+            for t in popup.transitions.iter().flatten() {
+                self.perform_transition(t);
+                thread::sleep(Duration::from_millis(t.post_delay));
+            }
+            dismissed += 1;
+        }
+        dismissed
     }
 
     pub fn identify_current_scene(&self, hint: Option<&str>) -> Option<String> {
-        println!("👀 扫描当前界面...");
+        self.identify_with_confidence(hint).map(|(id, _)| id)
+    }
+
+    /// 识别当前场景，同时返回置信度
+    fn identify_with_confidence(&self, hint: Option<&str>) -> Option<(String, f32)> {
+        let started = Instant::now();
+        let result = self.match_scene(hint);
+        metrics::global().scene_identify.observe(started.elapsed());
+        result
+    }
+
+    fn match_scene(&self, hint: Option<&str>) -> Option<(String, f32)> {
+        debug!("👀 扫描当前界面...");
+        let frame = self.interface.grab_frame();
         if let Some(target_id) = hint {
-            if self.get_match_score(target_id) > 0 {
-                println!("✅ 命中预期目标: [{}]", target_id);
-                return Some(target_id.to_string());
+            let m = match self.scenes.get(target_id) {
+                Some(scene) => self.evaluate_anchors(scene, &frame),
+                None => SceneMatch::default(),
+            };
+            if m.passed {
+                info!("✅ 命中预期目标: [{}]", target_id);
+                return Some((target_id.to_string(), m.confidence));
             }
         }
-        let mut best_match: Option<String> = None;
-        let mut max_score = 0;
-        for (id, _) in &self.scenes {
+        // 多个场景同时通过时，取置信度最高者；置信度相同再比命中锚点数
+        let mut best_match: Option<(String, SceneMatch)> = None;
+        frame.prefetch_texts(&self.interface, &Self::text_rects(self.scenes.values()));
+        for (id, scene) in &self.scenes {
             if let Some(h) = hint { if h == id { continue; } }
-            let score = self.get_match_score(id);
-            if score > 0 && score > max_score {
-                max_score = score;
-                best_match = Some(id.clone());
-            }
+            let m = self.evaluate_anchors(scene, &frame);
+            if !m.passed { continue; }
+            let better = match &best_match {
+                Some((_, b)) => (m.confidence, m.hits) > (b.confidence, b.hits),
+                None => true,
+            };
+            if better { best_match = Some((id.clone(), m)); }
         }
-        if let Some(id) = &best_match { println!("✅ 定位: [{}] (得分: {})", id, max_score); }
-        best_match
+        if let Some((id, m)) = &best_match {
+            info!("✅ 定位: [{}] (置信度: {:.0}%, 命中 {}/{})", id, m.confidence * 100.0, m.hits, m.total);
+        }
+        best_match.map(|(id, m)| (id, m.confidence))
+    }
+
+    /// 识别当前所在场景 (供外部模块同步界面状态)
+    pub fn detect_current_scene(&self) -> Option<SceneId> {
+        self.identify_current_scene(None)
+    }
+
+    /// 阻塞等待进入指定场景，超时返回 false
+    pub fn wait_for_scene(&self, target_id: &str, timeout: Duration) -> bool {
+        self.wait_for_scene_confidence(target_id, timeout.as_millis() as u64).0
     }
 
-    fn wait_for_scene(&self, target_id: &str, timeout_ms: u64) -> bool {
+    /// 等待进入目标场景，返回 (是否到达, 最后一次识别结果)
+    fn wait_for_scene_confidence(&self, target_id: &str, timeout_ms: u64) -> (bool, SceneMatch) {
         let start = Instant::now();
-        println!("    👀 确认进入 [{}]...", target_id);
+        let mut last = SceneMatch::default();
+        debug!("    👀 确认进入 [{}]...", target_id);
         while start.elapsed().as_millis() < timeout_ms as u128 {
-            if self.get_match_score(target_id) > 0 {
-                println!("    ✅ 确认到达 (耗时 {}ms)", start.elapsed().as_millis());
-                return true;
+            last = self.evaluate_scene(target_id);
+            if last.passed {
+                debug!("    ✅ 确认到达 (耗时 {}ms)", start.elapsed().as_millis());
+                self.interface.emit(NavEventKind::SceneEntered { scene: target_id.to_string(), confidence: last.confidence });
+                return (true, last);
             }
             thread::sleep(Duration::from_millis(200));
         }
-        println!("    ⚠️ 等待超时 [{}]", target_id);
-        false
+        warn!("    ⚠️ 等待超时 [{}]", target_id);
+        (false, last)
     }
 
-    pub fn navigate(&self, target_id: &str) -> NavResult {
-        let start_id = match self.identify_current_scene(None) {
-            Some(id) => id,
-            None => { println!("❌ 无法定位起点"); return NavResult::Failed; }
+    pub fn navigate(&self, target_id: &str) -> NavReport {
+        let _span = info_span!("navigate", target = target_id).entered();
+        self.navigate_inner(target_id, true)
+    }
+
+    fn navigate_inner(&self, target_id: &str, allow_renavigate: bool) -> NavReport {
+        let started = Instant::now();
+        let ocr_base = self.interface.ocr_count.load(Ordering::Relaxed);
+        let ocr_used = || self.interface.ocr_count.load(Ordering::Relaxed) - ocr_base;
+        let mut report = NavReport::new(target_id);
+
+        if let Some(id) = self.detect_danger() {
+            report.result = NavResult::Aborted(id);
+            return self.finish_report(report, started, ocr_used());
+        }
+        report.popups_dismissed += self.dismiss_popups();
+        let start_id = match self.identify_with_confidence(None) {
+            Some((id, conf)) => {
+                report.last_confidence = conf;
+                self.interface.emit(NavEventKind::SceneEntered { scene: id.clone(), confidence: conf });
+                id
+            }
+            None => {
+                error!("❌ 无法定位起点");
+                report.result = NavResult::Failed(NavError::UnknownStart { ocr_attempts: ocr_used() });
+                return self.finish_report(report, started, ocr_used());
+            }
         };
+        report.start_scene = Some(start_id.clone());
+        report.stopped_at = Some(start_id.clone());
+        report.planned_path.push(start_id.clone());
         if start_id == target_id {
-            println!("✅ 已在目标位置");
-            return NavResult::Success;
+            info!("✅ 已在目标位置");
+            report.result = NavResult::Success;
+            return self.finish_report(report, started, ocr_used());
         }
-        println!("🤖 规划路径: [{}] -> [{}]", start_id, target_id);
+        info!("🤖 规划路径: [{}] -> [{}]", start_id, target_id);
         let path = match self.find_path(&start_id, target_id) {
             Some(p) => p,
-            None => { println!("❌ 无路可走"); return NavResult::Failed; }
+            None => {
+                error!("❌ 无路可走");
+                report.result = NavResult::Failed(NavError::NoPath { from: start_id, to: target_id.to_string() });
+                return self.finish_report(report, started, ocr_used());
+            }
         };
-        for (i, step) in path.iter().enumerate() {
-            println!("\n➡️  [步骤 {}/{}] 点击 -> [{}]", i+1, path.len(), step.target);
-            self.interface.perform_click(step.coords[0], step.coords[1]);
+        report.planned_path.extend(path.iter().map(|t| t.target.clone()));
+
+        let mut current = start_id;
+        let mut path: VecDeque<Transition> = path.into();
+        let mut i = 0;
+        while let Some(step) = path.pop_front() {
+            let step = &step;
+            i += 1;
+            let step_start = Instant::now();
+            info!("➡️  [步骤 {}/{}] {:?} -> [{}]", i, i + path.len(), step.action, step.target);
+            if let Some(id) = self.detect_danger() {
+                report.result = NavResult::Aborted(id);
+                return self.finish_report(report, started, ocr_used());
+            }
+            report.popups_dismissed += self.dismiss_popups();
+            self.perform_transition(step);
+            self.interface.emit(NavEventKind::TransitionClicked {
+                from: current.clone(), to: step.target.clone(), coords: step.coords,
+            });
             
             // ✨ 核心修改：检查是否需要移交控制权
             // 如果 TOML 里写了 handler = "xxx"，或者它是无锚点的虚拟节点，则移交
@@ -333,47 +1155,434 @@ impl NavEngine {
             };
 
             if should_handover {
-                println!("🚀 到达托管节点 [{}]，触发处理器: {:?}", step.target, handler_name);
+                info!("🚀 到达托管节点 [{}]，触发处理器: {:?}", step.target, handler_name);
                 thread::sleep(Duration::from_millis(step.post_delay));
+                report.steps.push(StepReport {
+                    from: current.clone(), to: step.target.clone(), coords: step.coords,
+                    outcome: TransitionOutcome::HandedOver, elapsed_ms: step_start.elapsed().as_millis() as u64,
+                });
+                self.log_step(target_id, step, report.steps.last().unwrap(), None);
+                report.stopped_at = Some(step.target.clone());
+                self.interface.emit(NavEventKind::SceneEntered { scene: step.target.clone(), confidence: 0.0 });
                 // 将 handler 名称一并返回给 main
-                return NavResult::Handover(step.target.clone(), handler_name);
+                report.result = NavResult::Handover(step.target.clone(), handler_name);
+                return self.finish_report(report, started, ocr_used());
             }
 
-            let timeout = if step.post_delay < 2000 { 2000 } else { step.post_delay };
-            if !self.wait_for_scene(&step.target, timeout) {
-                println!("❌ 导航中断: 未能进入 [{}]", step.target);
-                return NavResult::Failed;
+            let timeout = step.timeout();
+            let (mut arrived, mut last_match) = self.wait_for_scene_confidence(&step.target, timeout);
+            if !arrived && !self.global_scenes.is_empty() {
+                // 跳转后弹出的弹窗会挡住目标界面：关闭后重点当前步骤，继续原路线
+                let n = self.dismiss_popups();
+                if n > 0 {
+                    report.popups_dismissed += n;
+                    if self.evaluate_scene(&current).passed {
+                        self.perform_transition(step);
+                    }
+                    (arrived, last_match) = self.wait_for_scene_confidence(&step.target, timeout);
+                }
+            }
+            let mut attempt = 0;
+            while !arrived && attempt < step.retries {
+                attempt += 1;
+                info!("🔁 [重试 {}/{}] 未进入 [{}]，再次执行跳转", attempt, step.retries, step.target);
+                if self.evaluate_scene(&current).passed {
+                    self.perform_transition(step);
+                }
+                (arrived, last_match) = self.wait_for_scene_confidence(&step.target, timeout);
             }
+            if !arrived {
+                // 超时可能是跳出了危险界面，恢复动作之前先确认
+                if let Some(id) = self.detect_danger() {
+                    report.result = NavResult::Aborted(id);
+                    return self.finish_report(report, started, ocr_used());
+                }
+            }
+            let mut outcome = if arrived { TransitionOutcome::Arrived } else { TransitionOutcome::Timeout };
+            match (&step.on_fail, arrived) {
+                (Some(RecoveryAction::Renavigate), false) if allow_renavigate => {
+                    report.steps.push(StepReport {
+                        from: current.clone(), to: step.target.clone(), coords: step.coords,
+                        outcome, elapsed_ms: step_start.elapsed().as_millis() as u64,
+                    });
+                    self.log_step(target_id, step, report.steps.last().unwrap(), Some(&last_match));
+                    info!("🔁 [恢复] 跳转超时，从当前界面重新导航...");
+                    let mut retry = self.navigate_inner(target_id, false);
+                    report.steps.append(&mut retry.steps);
+                    retry.steps = report.steps;
+                    retry.popups_dismissed += report.popups_dismissed;
+                    retry.start_scene = report.start_scene;
+                    retry.elapsed_ms = started.elapsed().as_millis() as u64;
+                    retry.ocr_attempts = ocr_used();
+                    return retry;
+                }
+                (Some(action), false) if !matches!(action, RecoveryAction::Renavigate) => {
+                    info!("🩹 [恢复] 跳转超时，执行 {:?}", action);
+                    match action {
+                        RecoveryAction::Key { key } => {
+                            if !self.interface.perform_key(key) {
+                                warn!("    ⚠️ 未知按键: {}", key);
+                            }
+                        }
+                        RecoveryAction::Click { x, y } => self.interface.perform_click(*x, *y),
+                        RecoveryAction::Renavigate => {}
+                    }
+                    (arrived, last_match) = self.wait_for_scene_confidence(&step.target, timeout);
+                    if arrived { outcome = TransitionOutcome::Recovered; }
+                }
+                _ => {}
+            }
+            report.last_confidence = last_match.confidence;
+            report.steps.push(StepReport {
+                from: current.clone(), to: step.target.clone(), coords: step.coords,
+                outcome, elapsed_ms: step_start.elapsed().as_millis() as u64,
+            });
+            self.log_step(target_id, step, report.steps.last().unwrap(), Some(&last_match));
+            if !arrived {
+                error!("❌ 导航中断: 未能进入 [{}]", step.target);
+                // 重新识别一次，告诉调用方实际停在了哪里
+                let last_scene = self.identify_current_scene(None);
+                report.stopped_at = last_scene.clone().or(report.stopped_at);
+                report.result = NavResult::Failed(NavError::TransitionTimeout {
+                    from: current, to: step.target.clone(), last_scene,
+                    waited_ms: timeout, ocr_attempts: ocr_used(),
+                });
+                return self.finish_report(report, started, ocr_used());
+            }
+            current = step.target.clone();
+            report.stopped_at = Some(current.clone());
             thread::sleep(Duration::from_millis(300));
+
+            // 到达带条件分支的场景后，按当前画面重新规划剩余路线
+            if !path.is_empty() && self.has_conditional_transitions(&current) {
+                match self.find_path(&current, target_id) {
+                    Some(p) => path = p.into(),
+                    None => {
+                        error!("❌ 条件分支后无路可走");
+                        report.result = NavResult::Failed(NavError::NoPath { from: current, to: target_id.to_string() });
+                        return self.finish_report(report, started, ocr_used());
+                    }
+                }
+            }
         }
-        println!("✅ 导航完成");
-        NavResult::Success
+        info!("✅ 导航完成");
+        report.result = NavResult::Success;
+        self.finish_report(report, started, ocr_used())
     }
 
-    fn find_path(&self, start: &str, target: &str) -> Option<Vec<Transition>> {
-        if start == target { return Some(vec![]); }
-        let mut queue = VecDeque::from([start.to_string()]);
-        let mut came_from: HashMap<String, (String, Transition)> = HashMap::new();
-        let mut visited = vec![start.to_string()];
-        while let Some(curr) = queue.pop_front() {
-            if curr == target {
-                let mut path = vec![];
-                let mut p = target.to_string();
-                while p != start {
-                    if let Some((prev, trans)) = came_from.get(&p) { path.push(trans.clone()); p = prev.clone(); }
+    /// 开启导航遥测：每一步追加一行 JSON 到 path (JSON Lines)
+    pub fn enable_telemetry(&self, path: &str) -> std::io::Result<()> {
+        if let Some(dir) = Path::new(path).parent() {
+            if !dir.as_os_str().is_empty() { fs::create_dir_all(dir)?; }
+        }
+        let file = fs::OpenOptions::new().create(true).append(true).open(path)?;
+        if let Ok(mut t) = self.telemetry.lock() { *t = Some(file); }
+        info!("📝 导航遥测已开启: {}", path);
+        Ok(())
+    }
+
+    fn log_step(&self, target_id: &str, t: &Transition, step: &StepReport, m: Option<&SceneMatch>) {
+        let mut guard = match self.telemetry.lock() { Ok(g) => g, Err(_) => return };
+        let file = match guard.as_mut() { Some(f) => f, None => return };
+        let record = serde_json::json!({
+            "ts": chrono::Local::now().to_rfc3339(),
+            "target": target_id,
+            "from": step.from,
+            "to": step.to,
+            "action": format!("{:?}", t.action).to_lowercase(),
+            "click": step.coords,
+            "outcome": format!("{:?}", step.outcome),
+            "duration_ms": step.elapsed_ms,
+            "confidence": m.map(|m| m.confidence),
+            "anchors_hit": m.map(|m| m.hits),
+            "anchors_total": m.map(|m| m.total),
+            "ocr_text": m.map(|m| m.ocr_texts.clone()).unwrap_or_default(),
+        });
+        if let Err(e) = writeln!(file, "{}", record) {
+            warn!("⚠️ 遥测写入失败: {}", e);
+        }
+    }
+
+    /// 收尾：记录耗时，失败时保存现场截图到 failures/
+    fn finish_report(&self, mut report: NavReport, started: Instant, ocr_attempts: usize) -> NavReport {
+        report.elapsed_ms = started.elapsed().as_millis() as u64;
+        report.ocr_attempts = ocr_attempts;
+        if matches!(report.result, NavResult::Failed(_) | NavResult::Aborted(_)) {
+            report.evidence = self.interface.save_failure_screenshot("nav_fail");
+            info!("📋 {}", report.summary());
+        }
+        self.interface.emit(NavEventKind::NavigationFinished {
+            target: report.target.clone(), result: report.result.clone(), elapsed_ms: report.elapsed_ms,
+        });
+        report
+    }
+
+    /// 依次执行钩子动作
+    pub fn run_hooks(&self, stage: &str, hooks: &[HookAction]) {
+        if hooks.is_empty() { return; }
+        info!("🪝 执行 {} 钩子 ({} 步)", stage, hooks.len());
+        for hook in hooks {
+            match hook {
+                HookAction::Key { key } => {
+                    if !self.interface.perform_key(key) {
+                        warn!("    ⚠️ 未知按键: {}", key);
+                    }
                 }
-                path.reverse(); return Some(path);
+                HookAction::Click { x, y } => self.interface.perform_click(*x, *y),
+                HookAction::Wait { ms } => thread::sleep(Duration::from_millis(*ms)),
+                HookAction::Log { msg } => info!("    [Hook] {}", msg),
             }
-            if let Some(scene) = self.scenes.get(&curr) {
-                if let Some(trans) = &scene.transitions {
-                    for t in trans {
-                        if !visited.contains(&t.target) {
-                            visited.push(t.target.clone()); queue.push_back(t.target.clone()); came_from.insert(t.target.clone(), (curr.clone(), t.clone()));
-                        }
-                    }
+        }
+    }
+
+    /// 按重试策略导航，仅在 Failed 时重试；返回最后一次尝试的报告
+    pub fn navigate_with_retry(&self, target_id: &str, policy: &RetryPolicy) -> NavReport {
+        let mut report = self.navigate(target_id);
+        let mut total_elapsed = report.elapsed_ms;
+        let mut total_ocr = report.ocr_attempts;
+        for retry in 1..=policy.max_retries {
+            if !matches!(report.result, NavResult::Failed(_)) { break; }
+            let delay = policy.delay_for(retry);
+            info!("🔁 导航重试 {}/{}，{}ms 后开始...", retry, policy.max_retries, delay.as_millis());
+            thread::sleep(delay);
+            report = self.navigate(target_id);
+            report.attempts = retry + 1;
+            total_elapsed += delay.as_millis() as u64 + report.elapsed_ms;
+            total_ocr += report.ocr_attempts;
+        }
+        report.elapsed_ms = total_elapsed;
+        report.ocr_attempts = total_ocr;
+        report
+    }
+
+    /// 带钩子的导航：先执行 pre 钩子再导航；直接到达终点时立即执行 post 钩子。
+    /// 若结果为 Handover，post 钩子应由调用方在处理器结束后通过 `run_hooks` 执行。
+    pub fn navigate_with_hooks(&self, target_id: &str, pre: &[HookAction], post: &[HookAction], retry: &RetryPolicy) -> NavReport {
+        self.run_hooks("pre", pre);
+        let report = self.navigate_with_retry(target_id, retry);
+        if report.result == NavResult::Success {
+            self.run_hooks("post", post);
+        }
+        report
+    }
+
+    /// 预览路线：截图识别当前场景后规划到目标的跳转序列，不产生任何键鼠输入 (跳转条件不求值，见 plan_from)。
+    /// 无法定位起点或无路可走时返回空列表。
+    pub fn plan(&self, target_id: &str) -> Vec<TransitionStep> {
+        match self.detect_current_scene() {
+            Some(start) => self.plan_from(&start, target_id).unwrap_or_default(),
+            None => Vec::new(),
+        }
+    }
+
+    /// 从指定起点规划路线 (不截图、不输入)：带 condition 的跳转不做 OCR 求值，一律视为可走
+    pub fn plan_from(&self, start: &str, target_id: &str) -> Option<Vec<TransitionStep>> {
+        let (cost, path) = shortest_path(&self.scenes, start, target_id, |_, _| true)?;
+        debug!("🧭 预览路径代价: {} ({} 步)", cost, path.len());
+        let mut from = start.to_string();
+        let mut steps = Vec::new();
+        for t in path {
+            steps.push(TransitionStep {
+                from: from.clone(),
+                to: t.target.clone(),
+                coords: t.coords,
+                post_delay: t.post_delay,
+                timeout_ms: t.timeout(),
+                cost: t.weight(),
+            });
+            from = t.target;
+        }
+        Some(steps)
+    }
+
+    fn has_conditional_transitions(&self, scene_id: &str) -> bool {
+        self.scenes.get(scene_id)
+            .and_then(|s| s.transitions.as_ref())
+            .is_some_and(|ts| ts.iter().any(|t| t.condition.is_some()))
+    }
+
+    /// 对 condition.rect 做 OCR，提取数值后与 value 比较
+    fn check_condition(&self, cond: &TransitionCondition) -> bool {
+        let text = self.interface.get_text_from_area(cond.rect);
+        let number = match &cond.regex {
+            // 无法编译的正则在加载时已报告，不在缓存中
+            Some(pattern) => self.regexes.get(pattern).and_then(|re| {
+                re.captures(&text).and_then(|c| c.get(1).or_else(|| c.get(0))).map(|m| m.as_str().to_string())
+            }),
+            None => Some(text.clone()),
+        }
+        .and_then(|s| {
+            let digits: String = s.chars().skip_while(|c| !c.is_ascii_digit()).take_while(|c| c.is_ascii_digit()).collect();
+            digits.parse::<i64>().ok()
+        });
+        let n = match number {
+            Some(n) => n,
+            None => { warn!("    ⚠️ 条件 OCR 未读到数值: [{}]", text); return false; }
+        };
+        // 未知运算符在加载时已报告为错误，这里按不满足处理
+        let ok = cond.compare(n).unwrap_or(false);
+        debug!("    🔢 条件: {} {} {} -> {}", n, cond.op, cond.value, ok);
+        ok
+    }
+
+    /// 导航用的路径规划：起点 (当前画面) 出发的带 condition 跳转会实时 OCR 求值，不满足的边被排除；
+    /// 其余场景的条件在到达该场景后重新规划时再求值。
+    fn find_path(&self, start: &str, target: &str) -> Option<Vec<Transition>> {
+        let (cost, path) = shortest_path(&self.scenes, start, target, |from, t| {
+            let Some(cond) = t.condition.as_ref().filter(|_| from == start) else { return true };
+            let ok = self.check_condition(cond);
+            if !ok {
+                debug!("🔀 条件不满足，跳过分支 [{}] -> [{}]", from, t.target);
+            }
+            ok
+        })?;
+        info!("🧭 最优路径代价: {} ({} 步)", cost, path.len());
+        Some(path)
+    }
+}
+
+/// 最短路径规划 (Dijkstra)：在 scenes.transitions 构成的有向图上按代价求最优跳转链，返回 (总代价, 跳转链)。
+/// allow(所在场景, 跳转) 为 false 的边不参与规划
+fn shortest_path(
+    scenes: &HashMap<String, Scene>,
+    start: &str,
+    target: &str,
+    mut allow: impl FnMut(&str, &Transition) -> bool,
+) -> Option<(u64, Vec<Transition>)> {
+    if start == target { return Some((0, vec![])); }
+    let mut dist: HashMap<String, u64> = HashMap::from([(start.to_string(), 0)]);
+    let mut came_from: HashMap<String, (String, Transition)> = HashMap::new();
+    let mut heap = BinaryHeap::from([Reverse((0u64, start.to_string()))]);
+    while let Some(Reverse((cost, curr))) = heap.pop() {
+        if curr == target {
+            let mut path = vec![];
+            let mut p = target.to_string();
+            while p != start {
+                let (prev, trans) = came_from.get(&p)?;
+                path.push(trans.clone());
+                p = prev.clone();
+            }
+            path.reverse();
+            return Some((cost, path));
+        }
+        if cost > dist.get(&curr).copied().unwrap_or(u64::MAX) { continue; }
+        if let Some(trans) = scenes.get(&curr).and_then(|s| s.transitions.as_ref()) {
+            for t in trans {
+                if !allow(&curr, t) { continue; }
+                let next = cost + t.weight();
+                if next < dist.get(&t.target).copied().unwrap_or(u64::MAX) {
+                    dist.insert(t.target.clone(), next);
+                    came_from.insert(t.target.clone(), (curr.clone(), t.clone()));
+                    heap.push(Reverse((next, t.target.clone())));
                 }
             }
         }
-        None
     }
-}
\ No newline at end of file
+    None
+}
+
+// ==========================================
+// 4. 异步接口 (feature = "async")
+// ==========================================
+// WinRT OCR 与拟人化输入都是阻塞调用，这里放到 tokio 的阻塞线程池中执行，
+// 调用方的 async 运行时 (如监督进程) 在导航期间不会被卡住。
+#[cfg(feature = "async")]
+impl NavEngine {
+    pub async fn navigate_async(self: Arc<Self>, target_id: String) -> NavReport {
+        tokio::task::spawn_blocking(move || self.navigate(&target_id))
+            .await
+            .expect("导航任务异常退出")
+    }
+
+    pub async fn navigate_with_retry_async(self: Arc<Self>, target_id: String, policy: RetryPolicy) -> NavReport {
+        tokio::task::spawn_blocking(move || self.navigate_with_retry(&target_id, &policy))
+            .await
+            .expect("导航任务异常退出")
+    }
+
+    pub async fn ocr_area_async(self: Arc<Self>, rect: [i32; 4]) -> String {
+        tokio::task::spawn_blocking(move || self.ocr_area(rect)).await.unwrap_or_default()
+    }
+
+    pub async fn ocr_areas_async(self: Arc<Self>, rects: Vec<[i32; 4]>) -> Vec<String> {
+        tokio::task::spawn_blocking(move || self.ocr_areas(&rects)).await.unwrap_or_default()
+    }
+
+    pub async fn detect_current_scene_async(self: Arc<Self>) -> Option<SceneId> {
+        tokio::task::spawn_blocking(move || self.detect_current_scene()).await.ok().flatten()
+    }
+
+    pub async fn wait_for_scene_async(self: Arc<Self>, target_id: String, timeout: Duration) -> bool {
+        tokio::task::spawn_blocking(move || self.wait_for_scene(&target_id, timeout)).await.unwrap_or(false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn edge(target: &str, cost: u64) -> Transition {
+        Transition { cost: Some(cost), ..Transition::click(target.to_string(), [0, 0]) }
+    }
+
+    fn graph(edges: &[(&str, Transition)]) -> HashMap<String, Scene> {
+        let mut scenes: HashMap<String, Scene> = HashMap::new();
+        for (from, t) in edges {
+            for id in [*from, t.target.as_str()] {
+                scenes.entry(id.to_string()).or_insert_with(|| Scene { id: id.to_string(), ..Default::default() });
+            }
+            scenes.get_mut(*from).unwrap().transitions.get_or_insert_with(Vec::new).push(t.clone());
+        }
+        scenes
+    }
+
+    fn hops(path: &[Transition]) -> Vec<&str> {
+        path.iter().map(|t| t.target.as_str()).collect()
+    }
+
+    #[test]
+    fn prefers_longer_cheaper_route() {
+        let scenes = graph(&[
+            ("大厅", edge("副本", 5000)),
+            ("大厅", edge("活动", 100)),
+            ("活动", edge("选关", 100)),
+            ("选关", edge("副本", 100)),
+        ]);
+        let (cost, path) = shortest_path(&scenes, "大厅", "副本", |_, _| true).unwrap();
+        assert_eq!(cost, 300);
+        assert_eq!(hops(&path), ["活动", "选关", "副本"]);
+    }
+
+    #[test]
+    fn unreachable_target_has_no_path() {
+        let scenes = graph(&[("大厅", edge("活动", 100)), ("副本", edge("大厅", 100))]);
+        assert!(shortest_path(&scenes, "大厅", "副本", |_, _| true).is_none());
+        assert!(shortest_path(&scenes, "大厅", "不存在", |_, _| true).is_none());
+    }
+
+    #[test]
+    fn start_equals_target_is_empty_path() {
+        let scenes = graph(&[("大厅", edge("活动", 100))]);
+        let (cost, path) = shortest_path(&scenes, "大厅", "大厅", |_, _| true).unwrap();
+        assert_eq!(cost, 0);
+        assert!(path.is_empty());
+    }
+
+    #[test]
+    fn excluded_conditional_edge_is_not_taken() {
+        let gated = Transition {
+            condition: Some(TransitionCondition { rect: [0, 0, 10, 10], regex: None, op: ">=".into(), value: 1 }),
+            ..edge("副本", 100)
+        };
+        let scenes = graph(&[
+            ("大厅", gated),
+            ("大厅", edge("购买门票", 200)),
+            ("购买门票", edge("副本", 200)),
+        ]);
+        let (_, path) = shortest_path(&scenes, "大厅", "副本", |_, _| true).unwrap();
+        assert_eq!(hops(&path), ["副本"]);
+        let (cost, path) = shortest_path(&scenes, "大厅", "副本", |_, t| t.condition.is_none()).unwrap();
+        assert_eq!(cost, 400);
+        assert_eq!(hops(&path), ["购买门票", "副本"]);
+    }
+}
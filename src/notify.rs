@@ -0,0 +1,232 @@
+// src/notify.rs
+use crate::frames;
+use crate::nav::{NavEngine, NavEventKind, NavResult};
+use chrono::Local;
+use serde::Deserialize;
+use serde_json::json;
+use std::collections::HashMap;
+use std::io::Cursor;
+use std::sync::{mpsc, Arc, Mutex, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant};
+use tracing::{debug, info, warn};
+
+// ==========================================
+// 远程通知 (webhook / Discord)
+// ==========================================
+// 关键事件发生时向配置的 webhook 发送一条消息，人不在电脑前也能及时知道：
+//   nav_failed            导航失败
+//   run_completed         一局塔防结束 (附本局摘要)
+//   danger_scene          识别到危险场景，自动化已停止
+//   hardware_disconnected 输入设备心跳无响应
+//   stalled               长时间没有进展且多次恢复无效，自动化已停止
+// 发送在后台线程进行，不会拖慢自动化；同一事件在 cooldown_secs 内只发一次。
+// 开启 screenshot 时附带事件发生时的游戏画面 (JPEG)，便于远程判断现场。
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct NotifyConfig {
+    pub enabled: bool,
+    /// webhook 地址
+    pub url: String,
+    /// discord / generic；为空时按地址判断 (discord.com/api/webhooks 为 discord)
+    pub format: String,
+    /// 需要通知的事件；为空时全部通知
+    pub events: Vec<String>,
+    /// 附带最新截图
+    pub screenshot: bool,
+    /// 同一事件两次通知的最短间隔 (秒)，避免导航反复失败时刷屏
+    pub cooldown_secs: u64,
+}
+
+impl Default for NotifyConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            url: String::new(),
+            format: String::new(),
+            events: Vec::new(),
+            screenshot: false,
+            cooldown_secs: 60,
+        }
+    }
+}
+
+impl NotifyConfig {
+    fn is_discord(&self) -> bool {
+        match self.format.as_str() {
+            "" => self.url.contains("discord.com/api/webhooks") || self.url.contains("discordapp.com/api/webhooks"),
+            f => f.eq_ignore_ascii_case("discord"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NotifyEvent {
+    NavFailed,
+    RunCompleted,
+    DangerScene,
+    HardwareDisconnected,
+    Stalled,
+}
+
+impl NotifyEvent {
+    /// 配置文件 events 中使用的名称
+    pub fn key(self) -> &'static str {
+        match self {
+            NotifyEvent::NavFailed => "nav_failed",
+            NotifyEvent::RunCompleted => "run_completed",
+            NotifyEvent::DangerScene => "danger_scene",
+            NotifyEvent::HardwareDisconnected => "hardware_disconnected",
+            NotifyEvent::Stalled => "stalled",
+        }
+    }
+
+    fn title(self) -> &'static str {
+        match self {
+            NotifyEvent::NavFailed => "❌ 导航失败",
+            NotifyEvent::RunCompleted => "🏁 本局结束",
+            NotifyEvent::DangerScene => "🛑 危险场景，已停止自动化",
+            NotifyEvent::HardwareDisconnected => "💔 输入设备无响应",
+            NotifyEvent::Stalled => "⏰ 长时间卡住，已停止自动化",
+        }
+    }
+}
+
+struct Message {
+    event: NotifyEvent,
+    text: String,
+    time: String,
+}
+
+struct Notifier {
+    config: NotifyConfig,
+    last_sent: Mutex<HashMap<NotifyEvent, Instant>>,
+    tx: mpsc::Sender<Message>,
+}
+
+static NOTIFIER: OnceLock<Notifier> = OnceLock::new();
+
+/// 启动通知线程；未开启或 url 为空时什么也不做
+pub fn init(config: &NotifyConfig) {
+    if !config.enabled {
+        return;
+    }
+    if config.url.is_empty() {
+        warn!("⚠️ [通知] 已开启但未配置 url，忽略");
+        return;
+    }
+    let (tx, rx) = mpsc::channel::<Message>();
+    let worker_config = config.clone();
+    thread::spawn(move || {
+        let agent = match native_tls::TlsConnector::new() {
+            Ok(tls) => ureq::AgentBuilder::new().tls_connector(Arc::new(tls)),
+            Err(e) => {
+                warn!("⚠️ [通知] TLS 初始化失败，只能发送 http 地址: {}", e);
+                ureq::AgentBuilder::new()
+            }
+        }
+        .timeout(Duration::from_secs(15))
+        .build();
+        for msg in rx {
+            if let Err(e) = post(&agent, &worker_config, &msg) {
+                warn!("⚠️ [通知] 发送失败 ({}): {}", msg.event.key(), e);
+            }
+        }
+    });
+    let notifier = Notifier { config: config.clone(), last_sent: Mutex::new(HashMap::new()), tx };
+    if NOTIFIER.set(notifier).is_ok() {
+        info!("🔔 [通知] 已开启 ({})", if config.is_discord() { "Discord" } else { "webhook" });
+    }
+}
+
+/// 发送一条通知；未开启、事件未订阅或处于冷却期时忽略
+pub fn send(event: NotifyEvent, text: impl Into<String>) {
+    let Some(n) = NOTIFIER.get() else {
+        return;
+    };
+    if !n.config.events.is_empty() && !n.config.events.iter().any(|e| e == event.key()) {
+        return;
+    }
+    if let Ok(mut last) = n.last_sent.lock() {
+        let cooldown = Duration::from_secs(n.config.cooldown_secs);
+        if last.get(&event).is_some_and(|t| t.elapsed() < cooldown) {
+            debug!("[通知] {} 处于冷却期，跳过", event.key());
+            return;
+        }
+        last.insert(event, Instant::now());
+    }
+    let msg = Message { event, text: text.into(), time: Local::now().format("%Y-%m-%d %H:%M:%S").to_string() };
+    let _ = n.tx.send(msg);
+}
+
+/// 订阅导航事件：导航失败与危险场景
+pub fn attach(engine: &NavEngine) {
+    if NOTIFIER.get().is_none() {
+        return;
+    }
+    engine.on_event(|e| {
+        if let NavEventKind::NavigationFinished { target, result, .. } = &e.kind {
+            match result {
+                NavResult::Failed(err) => send(NotifyEvent::NavFailed, format!("目标 [{}]: {}", target, err)),
+                NavResult::Aborted(scene) => {
+                    send(NotifyEvent::DangerScene, format!("导航 [{}] 时识别到 [{}]，请人工处理", target, scene))
+                }
+                _ => {}
+            }
+        }
+    });
+}
+
+fn post(agent: &ureq::Agent, config: &NotifyConfig, msg: &Message) -> Result<(), String> {
+    let content = format!("**{}**\n{}\n{}", msg.event.title(), msg.text, msg.time);
+    let payload = if config.is_discord() {
+        json!({ "content": content })
+    } else {
+        json!({ "event": msg.event.key(), "title": msg.event.title(), "message": msg.text, "time": msg.time })
+    };
+    let screenshot = if config.screenshot { capture_jpeg() } else { None };
+
+    let request = agent.post(&config.url);
+    let result = match screenshot {
+        Some(jpeg) => {
+            // Discord 读取 payload_json + files[0]；通用 webhook 为 payload + screenshot
+            let (payload_field, file_field) =
+                if config.is_discord() { ("payload_json", "files[0]") } else { ("payload", "screenshot") };
+            let boundary = format!("nzm{}", Local::now().timestamp_millis());
+            let body = multipart(&boundary, payload_field, &payload.to_string(), file_field, &jpeg);
+            request
+                .set("Content-Type", &format!("multipart/form-data; boundary={}", boundary))
+                .send_bytes(&body)
+        }
+        None => request.send_json(payload),
+    };
+    result.map(|_| ()).map_err(|e| e.to_string())
+}
+
+/// 截取游戏画面并编码为 JPEG (比 PNG 小很多，适合上传)
+fn capture_jpeg() -> Option<Vec<u8>> {
+    let rgba = frames::capture_screen()?;
+    let rgb = image::DynamicImage::ImageRgba8(rgba).to_rgb8();
+    let mut buf = Cursor::new(Vec::new());
+    rgb.write_to(&mut buf, image::ImageFormat::Jpeg).ok()?;
+    Some(buf.into_inner())
+}
+
+fn multipart(boundary: &str, payload_field: &str, payload: &str, file_field: &str, jpeg: &[u8]) -> Vec<u8> {
+    let mut body = Vec::with_capacity(jpeg.len() + payload.len() + 512);
+    body.extend_from_slice(
+        format!(
+            "--{b}\r\nContent-Disposition: form-data; name=\"{pf}\"\r\nContent-Type: application/json\r\n\r\n{p}\r\n\
+             --{b}\r\nContent-Disposition: form-data; name=\"{ff}\"; filename=\"screenshot.jpg\"\r\nContent-Type: image/jpeg\r\n\r\n",
+            b = boundary,
+            pf = payload_field,
+            p = payload,
+            ff = file_field
+        )
+        .as_bytes(),
+    );
+    body.extend_from_slice(jpeg);
+    body.extend_from_slice(format!("\r\n--{}--\r\n", boundary).as_bytes());
+    body
+}
@@ -0,0 +1,425 @@
+// src/ocr.rs
+use serde::Deserialize;
+use tracing::warn;
+
+// ==========================================
+// 1. OCR 后端抽象
+// ==========================================
+// NavEngine 只依赖 OcrBackend，具体实现可在 config.toml 的 [ocr] 中切换：
+// - "winrt"     : Windows.Media.Ocr (Windows 默认，无需额外文件，需 feature = "windows")
+// - "paddle"    : PaddleOCR 识别模型 (ONNX Runtime，需 feature = "paddle")
+// - "tesseract" : 调用 tesseract 命令行 (Linux 默认，需安装 tesseract 与对应语言包)
+
+/// 一次识别的结果
+#[derive(Debug, Clone, Default)]
+pub struct OcrText {
+    /// 已去除空白的文本
+    pub text: String,
+    /// 0.0 ~ 1.0；后端无法给出置信度时为 1.0 (有文本) 或 0.0 (空)
+    pub confidence: f32,
+}
+
+pub trait OcrBackend: Send + Sync {
+    fn name(&self) -> &str;
+
+    /// 识别一张已裁剪的区域图像，rect 为该图像在屏幕上的位置 (仅用于日志/诊断)
+    fn recognize(&self, image: &image::DynamicImage, rect: [i32; 4]) -> OcrText;
+
+    /// 是否需要 NavEngine 做多重曝光 (多阈值二值化) 预处理后合并结果
+    fn multi_exposure(&self) -> bool {
+        true
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct OcrConfig {
+    /// "winrt" / "paddle" / "tesseract"
+    pub backend: String,
+    /// PaddleOCR 识别模型 (如 ch_PP-OCRv4_rec.onnx)
+    pub model: String,
+    /// PaddleOCR 字典文件 (每行一个字符)
+    pub dict: String,
+    /// onnxruntime 动态库路径 (load-dynamic 模式)
+    pub runtime: String,
+    /// 识别语言 (BCP-47，如 zh-Hans / zh-Hant / en-US)；WinRT 在系统未安装时使用用户语言，
+    /// tesseract 换算为语言包名 (zh-Hans -> chi_sim)，也可直接写语言包名 (如 chi_sim+eng)
+    pub language: String,
+    /// tesseract 可执行文件 (不在 PATH 中时写完整路径)
+    pub tesseract: String,
+    /// tesseract 页面分割模式：7 = 单行文字 (HUD 区域)，多行区域可改为 6
+    pub psm: u8,
+}
+
+impl Default for OcrConfig {
+    fn default() -> Self {
+        Self {
+            backend: DEFAULT_BACKEND.to_string(),
+            model: "models/ch_PP-OCRv4_rec.onnx".to_string(),
+            dict: "models/ppocr_keys_v1.txt".to_string(),
+            runtime: if cfg!(feature = "windows") { "onnxruntime.dll" } else { "libonnxruntime.so" }.to_string(),
+            language: "zh-Hans".to_string(),
+            tesseract: "tesseract".to_string(),
+            psm: 7,
+        }
+    }
+}
+
+/// 平台默认的 OCR 后端
+#[cfg(feature = "windows")]
+pub const DEFAULT_BACKEND: &str = "winrt";
+#[cfg(not(feature = "windows"))]
+pub const DEFAULT_BACKEND: &str = "tesseract";
+
+/// 按配置创建 OCR 后端；初始化失败时回退到平台默认后端
+pub fn create_backend(cfg: &OcrConfig) -> Box<dyn OcrBackend> {
+    match cfg.backend.to_lowercase().as_str() {
+        "paddle" => {
+            #[cfg(feature = "paddle")]
+            match paddle::PaddleOcr::new(cfg) {
+                Ok(p) => return Box::new(p),
+                Err(e) => warn!("⚠️ [OCR] PaddleOCR 初始化失败，回退到 {}: {}", DEFAULT_BACKEND, e),
+            }
+            #[cfg(not(feature = "paddle"))]
+            warn!("⚠️ [OCR] 未启用 paddle feature，回退到 {}", DEFAULT_BACKEND);
+        }
+        "tesseract" => return Box::new(TesseractOcr::new(cfg)),
+        #[cfg(feature = "windows")]
+        "winrt" => {}
+        #[cfg(not(feature = "windows"))]
+        "winrt" => warn!("⚠️ [OCR] WinRT 只在 Windows 上可用，使用 {}", DEFAULT_BACKEND),
+        "" => {}
+        other => warn!("⚠️ [OCR] 未知后端 '{}'，使用 {}", other, DEFAULT_BACKEND),
+    }
+    default_backend(cfg)
+}
+
+#[cfg(feature = "windows")]
+fn default_backend(cfg: &OcrConfig) -> Box<dyn OcrBackend> {
+    Box::new(WinRtOcr::with_language(&cfg.language))
+}
+
+#[cfg(not(feature = "windows"))]
+fn default_backend(cfg: &OcrConfig) -> Box<dyn OcrBackend> {
+    Box::new(TesseractOcr::new(cfg))
+}
+
+// ==========================================
+// 2. Windows.Media.Ocr 实现
+// ==========================================
+#[cfg(feature = "windows")]
+pub use winrt::WinRtOcr;
+
+#[cfg(feature = "windows")]
+mod winrt {
+    use super::{OcrBackend, OcrText};
+    use std::io::Cursor;
+    use tracing::info;
+    use windows::Globalization::Language;
+    use windows::Graphics::Imaging::BitmapDecoder;
+    use windows::Media::Ocr::OcrEngine;
+    use windows::Storage::Streams::{DataWriter, InMemoryRandomAccessStream};
+
+    pub struct WinRtOcr {
+        engine: Option<OcrEngine>,
+    }
+
+    // OcrEngine 是 agile 对象，可跨线程调用
+    unsafe impl Send for WinRtOcr {}
+    unsafe impl Sync for WinRtOcr {}
+
+    impl WinRtOcr {
+        pub fn new() -> Self {
+            Self::with_language("zh-Hans")
+        }
+
+        pub fn with_language(language: &str) -> Self {
+            info!("🚀 初始化 Windows OCR ({})...", language);
+            let engine = match Language::CreateLanguage(&windows::core::HSTRING::from(language)) {
+                Ok(lang) => match OcrEngine::TryCreateFromLanguage(&lang) {
+                    Ok(e) => Some(e),
+                    Err(_) => OcrEngine::TryCreateFromUserProfileLanguages().ok()
+                },
+                Err(_) => OcrEngine::TryCreateFromUserProfileLanguages().ok(),
+            };
+            Self { engine }
+        }
+
+        fn run(&self, dynamic_img: &image::DynamicImage) -> String {
+            if self.engine.is_none() { return String::new(); }
+            let engine = self.engine.as_ref().unwrap();
+
+            let mut png_buffer = Cursor::new(Vec::new());
+            if dynamic_img.write_to(&mut png_buffer, image::ImageFormat::Png).is_err() { return String::new(); }
+            let png_bytes = png_buffer.into_inner();
+
+            let stream = InMemoryRandomAccessStream::new().unwrap();
+            let writer = DataWriter::CreateDataWriter(&stream).unwrap();
+            if writer.WriteBytes(&png_bytes).is_err() { return String::new(); }
+            if writer.StoreAsync().unwrap().get().is_err() { return String::new(); }
+            if writer.FlushAsync().unwrap().get().is_err() { return String::new(); }
+            if writer.DetachStream().is_err() { return String::new(); }
+            if stream.Seek(0).is_err() { return String::new(); }
+
+            let decoder = match BitmapDecoder::CreateAsync(&stream) {
+                 Ok(op) => match op.get() { Ok(d) => d, Err(_) => return String::new() },
+                 Err(_) => return String::new(),
+            };
+            let software_bitmap = match decoder.GetSoftwareBitmapAsync() {
+                 Ok(op) => match op.get() { Ok(b) => b, Err(_) => return String::new() },
+                 Err(_) => return String::new(),
+            };
+            let result = match engine.RecognizeAsync(&software_bitmap) {
+                 Ok(op) => match op.get() { Ok(res) => res, Err(_) => return String::new() },
+                 Err(_) => return String::new(),
+            };
+
+            let mut full_text = String::new();
+            if let Ok(lines) = result.Lines() {
+                for line in lines {
+                    if let Ok(text) = line.Text() { full_text.push_str(&text.to_string()); }
+                }
+            }
+            full_text.replace(|c: char| c.is_whitespace(), "")
+        }
+    }
+
+    impl Default for WinRtOcr {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    impl OcrBackend for WinRtOcr {
+        fn name(&self) -> &str {
+            "winrt"
+        }
+
+        /// WinRT 不提供逐字置信度，有文本即视为 1.0
+        fn recognize(&self, image: &image::DynamicImage, _rect: [i32; 4]) -> OcrText {
+            let text = self.run(image);
+            let confidence = if text.is_empty() { 0.0 } else { 1.0 };
+            OcrText { text, confidence }
+        }
+    }
+}
+
+// ==========================================
+// 3. PaddleOCR (ONNX Runtime) 实现
+// ==========================================
+// 只使用识别模型 (rec)：区域已由 ui_map 给出，不需要检测模型。
+// 输入 [1, 3, 48, W]，(x / 255 - 0.5) / 0.5；输出 [1, T, C]，CTC 贪心解码，0 号为 blank。
+#[cfg(feature = "paddle")]
+mod paddle {
+    use super::{OcrBackend, OcrConfig, OcrText};
+    use ort::session::Session;
+    use ort::value::Tensor;
+    use std::fs;
+    use std::sync::Mutex;
+    use tracing::{info, warn};
+
+    const REC_HEIGHT: u32 = 48;
+
+    pub struct PaddleOcr {
+        session: Mutex<Session>,
+        /// 下标 i 对应输出类别 i + 1 (0 为 blank)
+        dict: Vec<String>,
+    }
+
+    impl PaddleOcr {
+        pub fn new(cfg: &OcrConfig) -> Result<Self, String> {
+            ort::init_from(&cfg.runtime).commit().map_err(|e| e.to_string())?;
+            let session = Session::builder()
+                .and_then(|b| b.commit_from_file(&cfg.model))
+                .map_err(|e| format!("加载模型 {} 失败: {}", cfg.model, e))?;
+            let mut dict: Vec<String> = fs::read_to_string(&cfg.dict)
+                .map_err(|e| format!("读取字典 {} 失败: {}", cfg.dict, e))?
+                .lines()
+                .map(|l| l.to_string())
+                .collect();
+            // PaddleOCR 默认在字典末尾追加空格类别
+            dict.push(" ".to_string());
+            info!("🚀 [OCR] PaddleOCR 已加载 ({} 个字符)", dict.len());
+            Ok(Self { session: Mutex::new(session), dict })
+        }
+
+        fn preprocess(image: &image::DynamicImage) -> (Vec<f32>, usize) {
+            let rgb = image.to_rgb8();
+            let ratio = rgb.width() as f32 / rgb.height().max(1) as f32;
+            let width = ((REC_HEIGHT as f32 * ratio).ceil() as u32).clamp(16, 1280);
+            let resized = image::imageops::resize(&rgb, width, REC_HEIGHT, image::imageops::FilterType::Triangle);
+            let (w, h) = (width as usize, REC_HEIGHT as usize);
+            let mut data = vec![0f32; 3 * h * w];
+            for (x, y, px) in resized.enumerate_pixels() {
+                for c in 0..3 {
+                    data[c * h * w + y as usize * w + x as usize] = (px[c] as f32 / 255.0 - 0.5) / 0.5;
+                }
+            }
+            (data, w)
+        }
+
+        fn decode(&self, probs: &[f32], steps: usize, classes: usize) -> OcrText {
+            let mut text = String::new();
+            let mut scores = Vec::new();
+            let mut prev = 0usize;
+            for t in 0..steps {
+                let row = &probs[t * classes..(t + 1) * classes];
+                let (idx, score) = row.iter().enumerate()
+                    .fold((0, f32::MIN), |best, (i, v)| if *v > best.1 { (i, *v) } else { best });
+                if idx != 0 && idx != prev {
+                    if let Some(ch) = self.dict.get(idx - 1) {
+                        text.push_str(ch);
+                        scores.push(score);
+                    }
+                }
+                prev = idx;
+            }
+            let confidence = if scores.is_empty() { 0.0 } else { scores.iter().sum::<f32>() / scores.len() as f32 };
+            OcrText { text: text.replace(|c: char| c.is_whitespace(), ""), confidence }
+        }
+    }
+
+    impl OcrBackend for PaddleOcr {
+        fn name(&self) -> &str {
+            "paddle"
+        }
+
+        fn recognize(&self, image: &image::DynamicImage, rect: [i32; 4]) -> OcrText {
+            let (data, w) = Self::preprocess(image);
+            let input = match Tensor::from_array(([1usize, 3, REC_HEIGHT as usize, w], data.into_boxed_slice())) {
+                Ok(t) => t,
+                Err(e) => { warn!("⚠️ [OCR] 构造输入失败 {:?}: {}", rect, e); return OcrText::default(); }
+            };
+            let mut session = match self.session.lock() { Ok(s) => s, Err(_) => return OcrText::default() };
+            let outputs = match session.run(ort::inputs![input]) {
+                Ok(o) => o,
+                Err(e) => { warn!("⚠️ [OCR] 推理失败 {:?}: {}", rect, e); return OcrText::default(); }
+            };
+            match outputs[0].try_extract_tensor::<f32>() {
+                Ok((shape, probs)) if shape.len() == 3 => self.decode(probs, shape[1] as usize, shape[2] as usize),
+                _ => OcrText::default(),
+            }
+        }
+
+        /// 识别模型对原图效果最好，不做二值化多重曝光
+        fn multi_exposure(&self) -> bool {
+            false
+        }
+    }
+}
+
+// ==========================================
+// 4. Tesseract (命令行) 实现
+// ==========================================
+// 每次识别启动一次 tesseract 进程：PNG 从 stdin 传入，以 TSV 输出逐词结果与置信度。
+// 不链接 libtesseract，Linux 上装好 tesseract-ocr 与 chi_sim 语言包即可；Windows 上同样可用。
+pub use tesseract::TesseractOcr;
+
+mod tesseract {
+    use super::{OcrBackend, OcrConfig, OcrText};
+    use image::imageops::FilterType;
+    use std::io::{Cursor, Write};
+    use std::process::{Command, Stdio};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use tracing::{info, warn};
+
+    /// 低于此高度的区域先放大 2 倍 (tesseract 对 20px 以下的字识别很差)
+    const UPSCALE_BELOW: u32 = 40;
+
+    pub struct TesseractOcr {
+        program: String,
+        language: String,
+        psm: u8,
+        /// 已提示过调用失败 (避免每次轮询刷屏)
+        warned: AtomicBool,
+    }
+
+    impl TesseractOcr {
+        pub fn new(cfg: &OcrConfig) -> Self {
+            let language = language_pack(&cfg.language);
+            info!("🚀 初始化 Tesseract OCR ({})...", language);
+            let found = Command::new(&cfg.tesseract).arg("--version").stdout(Stdio::null()).stderr(Stdio::null()).status();
+            if !found.is_ok_and(|s| s.success()) {
+                warn!("⚠️ [OCR] 无法运行 {}，请安装 tesseract (如 apt install tesseract-ocr tesseract-ocr-chi-sim) 或在 [ocr] tesseract 中写完整路径", cfg.tesseract);
+            }
+            Self { program: cfg.tesseract.clone(), language, psm: cfg.psm, warned: AtomicBool::new(false) }
+        }
+
+        fn run(&self, image: &image::DynamicImage) -> Result<OcrText, String> {
+            let image = if image.height() < UPSCALE_BELOW {
+                image.resize(image.width() * 2, image.height() * 2, FilterType::Lanczos3)
+            } else {
+                image.clone()
+            };
+            let mut png = Cursor::new(Vec::new());
+            image.write_to(&mut png, image::ImageFormat::Png).map_err(|e| e.to_string())?;
+
+            let mut child = Command::new(&self.program)
+                .args(["stdin", "stdout", "-l", &self.language, "--psm", &self.psm.to_string(), "tsv"])
+                .stdin(Stdio::piped())
+                .stdout(Stdio::piped())
+                .stderr(Stdio::piped())
+                .spawn()
+                .map_err(|e| format!("无法启动 {}: {}", self.program, e))?;
+            // tesseract 读完整张图后才输出，先写完再读不会互相等待；drop 后 stdin 关闭
+            if let Some(mut stdin) = child.stdin.take() {
+                stdin.write_all(png.get_ref()).map_err(|e| e.to_string())?;
+            }
+            let output = child.wait_with_output().map_err(|e| e.to_string())?;
+            if !output.status.success() {
+                return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+            }
+            Ok(parse_tsv(&String::from_utf8_lossy(&output.stdout)))
+        }
+    }
+
+    impl OcrBackend for TesseractOcr {
+        fn name(&self) -> &str {
+            "tesseract"
+        }
+
+        fn recognize(&self, image: &image::DynamicImage, rect: [i32; 4]) -> OcrText {
+            match self.run(image) {
+                Ok(text) => text,
+                Err(e) => {
+                    if !self.warned.swap(true, Ordering::Relaxed) {
+                        warn!("⚠️ [OCR] tesseract 识别 {:?} 失败 (之后不再提示): {}", rect, e);
+                    }
+                    OcrText::default()
+                }
+            }
+        }
+    }
+
+    /// BCP-47 语言标签 -> tesseract 语言包名；已是语言包名 (如 chi_sim+eng) 时原样使用
+    fn language_pack(language: &str) -> String {
+        match language.to_lowercase().as_str() {
+            "zh-hans" | "zh-cn" | "zh" => "chi_sim".to_string(),
+            "zh-hant" | "zh-tw" | "zh-hk" => "chi_tra".to_string(),
+            l if l.starts_with("en") => "eng".to_string(),
+            l if l.starts_with("ja") => "jpn".to_string(),
+            _ => language.to_string(),
+        }
+    }
+
+    /// TSV 列：level page block par line word left top width height conf text；
+    /// 取词级 (level 5) 的文字拼接，置信度为各词平均值 (0 ~ 100 换算为 0 ~ 1)
+    fn parse_tsv(tsv: &str) -> OcrText {
+        let (mut text, mut conf_sum, mut words) = (String::new(), 0.0, 0);
+        for cols in tsv.lines().skip(1).map(|l| l.split('\t').collect::<Vec<_>>()) {
+            if cols.len() < 12 || cols[0] != "5" {
+                continue;
+            }
+            let word: String = cols[11].chars().filter(|c| !c.is_whitespace()).collect();
+            let Ok(conf) = cols[10].parse::<f32>() else { continue };
+            if word.is_empty() || conf < 0.0 {
+                continue;
+            }
+            text.push_str(&word);
+            conf_sum += conf;
+            words += 1;
+        }
+        let confidence = if words > 0 { (conf_sum / words as f32 / 100.0).clamp(0.0, 1.0) } else { 0.0 };
+        OcrText { text, confidence }
+    }
+}
@@ -0,0 +1,156 @@
+// src/recorder.rs
+use crate::hardware::{FirmwareInfo, GamepadState, InputCommand, InputDriver, InputMetrics};
+use crate::session::SessionRecorder;
+use nzm_schema::session::SessionEventKind;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+// ==========================================
+// 1. 录制
+// ==========================================
+// RecordingDriver 包装任意 InputDriver：命令照常下发，同时以 JSON Lines 写入文件，
+// 每行 {"t_ms": 相对录制开始的毫秒数, "cmd": {...}}。心跳不录制。
+// 开启会话录像 (--session) 时同时作为输入事件写入会话。
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RecordedCommand {
+    pub t_ms: u64,
+    pub cmd: InputCommand,
+}
+
+pub struct RecordingDriver {
+    inner: Box<dyn InputDriver>,
+    writer: Option<BufWriter<File>>,
+    session: Option<Arc<SessionRecorder>>,
+    started: Instant,
+}
+
+impl RecordingDriver {
+    pub fn new(inner: Box<dyn InputDriver>, path: &str) -> std::io::Result<Self> {
+        if let Some(dir) = Path::new(path).parent() {
+            if !dir.as_os_str().is_empty() {
+                fs::create_dir_all(dir)?;
+            }
+        }
+        let writer = BufWriter::new(File::create(path)?);
+        println!("⏺️ [录制] 输入命令将写入 {}", path);
+        Ok(Self { inner, writer: Some(writer), session: None, started: Instant::now() })
+    }
+
+    /// 只写入会话录像的输入事件 (不单独生成录像文件)
+    pub fn for_session(inner: Box<dyn InputDriver>, session: Arc<SessionRecorder>) -> Self {
+        Self { inner, writer: None, session: Some(session), started: Instant::now() }
+    }
+
+    fn record(&mut self, cmd: InputCommand) {
+        if let Some(writer) = self.writer.as_mut() {
+            let entry = RecordedCommand { t_ms: self.started.elapsed().as_millis() as u64, cmd };
+            if let Ok(line) = serde_json::to_string(&entry) {
+                let _ = writeln!(writer, "{}", line);
+                // 每条命令都落盘，进程被强制结束时录像依然完整
+                let _ = writer.flush();
+            }
+        }
+        if let Some(session) = &self.session {
+            session.record(SessionEventKind::Input { cmd: format!("{:?}", cmd) });
+        }
+        cmd.apply(self.inner.as_mut());
+    }
+}
+
+impl InputDriver for RecordingDriver {
+    fn heartbeat(&mut self) {
+        self.inner.heartbeat();
+    }
+
+    fn firmware(&self) -> Option<FirmwareInfo> {
+        self.inner.firmware()
+    }
+
+    fn metrics(&self) -> InputMetrics {
+        self.inner.metrics()
+    }
+
+    fn switch_identity(&mut self, index: u8) {
+        self.record(InputCommand::SwitchIdentity { index });
+    }
+
+    fn mouse_abs(&mut self, x: u16, y: u16) {
+        self.record(InputCommand::MouseAbs { x, y });
+    }
+
+    fn mouse_move(&mut self, dx: i32, dy: i32, wheel: i8) {
+        self.record(InputCommand::MouseMove { dx, dy, wheel });
+    }
+
+    fn mouse_hwheel(&mut self, delta: i8) {
+        self.record(InputCommand::MouseHWheel { delta });
+    }
+
+    fn mouse_down(&mut self, left: bool, right: bool) {
+        self.record(InputCommand::MouseDown { left, right });
+    }
+
+    fn mouse_up(&mut self) {
+        self.record(InputCommand::MouseUp);
+    }
+
+    fn key_down(&mut self, keycode: u8, modifier: u8) {
+        self.record(InputCommand::KeyDown { keycode, modifier });
+    }
+
+    fn key_up(&mut self) {
+        self.record(InputCommand::KeyUp);
+    }
+
+    fn supports_gamepad(&self) -> bool {
+        self.inner.supports_gamepad()
+    }
+
+    fn gamepad(&mut self, state: GamepadState) {
+        self.record(InputCommand::Gamepad { state });
+    }
+}
+
+// ==========================================
+// 2. 回放
+// ==========================================
+
+/// 读取录像文件；无法解析的行会被跳过并提示
+pub fn load(path: &str) -> Result<Vec<RecordedCommand>, String> {
+    let file = File::open(path).map_err(|e| format!("无法打开录像 {}: {}", path, e))?;
+    let mut out = Vec::new();
+    for (i, line) in BufReader::new(file).lines().enumerate() {
+        let line = line.map_err(|e| format!("读取录像 {} 失败: {}", path, e))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str::<RecordedCommand>(&line) {
+            Ok(c) => out.push(c),
+            Err(e) => println!("⚠️ [回放] 跳过第 {} 行: {}", i + 1, e),
+        }
+    }
+    Ok(out)
+}
+
+/// 按原始时间间隔重放录像，返回执行的命令数
+pub fn playback(path: &str, driver: &mut dyn InputDriver) -> Result<usize, String> {
+    let commands = load(path)?;
+    println!("▶️ [回放] {} ({} 条命令)", path, commands.len());
+    let started = Instant::now();
+    for entry in &commands {
+        let due = Duration::from_millis(entry.t_ms);
+        let now = started.elapsed();
+        if due > now {
+            thread::sleep(due - now);
+        }
+        entry.cmd.apply(driver);
+    }
+    println!("⏹️ [回放] 完成，用时 {:.1}s", started.elapsed().as_secs_f32());
+    Ok(commands.len())
+}
@@ -0,0 +1,178 @@
+// src/report.rs
+use crate::nav::{NavEngine, NavEventKind, NavResult};
+use crate::tower_defense::{RunOutcome, RunSummary};
+use chrono::Local;
+use serde::Serialize;
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+// ==========================================
+// 机器可读的运行结果 (--result-file)
+// ==========================================
+// run / farm / schedule 结束时写出一份 JSON：最终状态、最后一次导航结果、经过的场景、
+// 每局塔防的结果与波次、执行的点击 / 键鼠命令数、过程中的错误。
+// 供外部脚本判断成败，不必再解析控制台输出。路径为 "-" 时输出到标准输出 (单行，位于最后)。
+
+/// 整次运行的结果
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct RunResult {
+    /// run / farm / schedule
+    pub command: String,
+    /// completed (正常结束) / stopped (收到停止信号) / aborted (危险场景) / failed (最后一次导航失败)
+    pub status: String,
+    pub started: String,
+    pub finished: String,
+    pub elapsed_secs: u64,
+    pub targets: Vec<String>,
+    /// 最后一次导航的结果
+    pub last_navigation: Option<NavOutcome>,
+    /// 按顺序确认进入的场景 (连续重复的只记一次)
+    pub scenes: Vec<String>,
+    /// 导航中执行的跳转点击数
+    pub clicks: u64,
+    /// 下发到输入设备的命令帧总数 (含心跳)
+    pub input_commands: u64,
+    pub runs: Vec<RunRecord>,
+    pub errors: Vec<String>,
+}
+
+/// 一次导航的结果
+#[derive(Serialize, Debug, Clone)]
+pub struct NavOutcome {
+    pub target: String,
+    /// success / handover / failed / aborted
+    pub result: String,
+    /// 移交或中止时所在的场景
+    pub scene: Option<String>,
+    pub error: Option<String>,
+    pub elapsed_ms: u64,
+}
+
+/// 一局塔防的结果
+#[derive(Serialize, Debug, Clone)]
+pub struct RunRecord {
+    pub target: String,
+    pub map: String,
+    pub outcome: RunOutcome,
+    pub last_wave: i32,
+    pub placed: usize,
+    pub skipped: usize,
+    pub upgraded: usize,
+    pub demolished: usize,
+    pub elapsed_secs: u64,
+    /// 是否因停止信号提前结束
+    pub stopped: bool,
+}
+
+/// 运行过程中收集结果；克隆后共享同一份数据
+#[derive(Clone)]
+pub struct ResultRecorder {
+    result: Arc<Mutex<RunResult>>,
+    started: Instant,
+}
+
+impl ResultRecorder {
+    pub fn new(command: &str, targets: &[String]) -> Self {
+        let result = RunResult {
+            command: command.to_string(),
+            started: Local::now().to_rfc3339(),
+            targets: targets.to_vec(),
+            ..Default::default()
+        };
+        Self { result: Arc::new(Mutex::new(result)), started: Instant::now() }
+    }
+
+    /// 订阅导航事件：记录场景、点击与每次导航的结果
+    pub fn attach(&self, engine: &NavEngine) {
+        let result = Arc::clone(&self.result);
+        engine.on_event(move |e| {
+            let Ok(mut r) = result.lock() else {
+                return;
+            };
+            match &e.kind {
+                NavEventKind::SceneEntered { scene, .. } if r.scenes.last() != Some(scene) => {
+                    r.scenes.push(scene.clone());
+                }
+                NavEventKind::TransitionClicked { .. } => r.clicks += 1,
+                NavEventKind::NavigationFinished { target, result, elapsed_ms } => {
+                    let outcome = NavOutcome::new(target, result, *elapsed_ms);
+                    if let Some(err) = &outcome.error {
+                        r.errors.push(format!("导航 [{}] {}: {}", target, outcome.result, err));
+                    }
+                    r.last_navigation = Some(outcome);
+                }
+                _ => {}
+            }
+        });
+    }
+
+    /// 记录一局塔防
+    pub fn record_run(&self, target: &str, map: &str, summary: &RunSummary) {
+        if let Ok(mut r) = self.result.lock() {
+            r.runs.push(RunRecord {
+                target: target.to_string(),
+                map: map.to_string(),
+                outcome: summary.outcome,
+                last_wave: summary.last_wave,
+                placed: summary.placed,
+                skipped: summary.skipped,
+                upgraded: summary.upgraded,
+                demolished: summary.demolished,
+                elapsed_secs: summary.elapsed.as_secs(),
+                stopped: summary.stopped,
+            });
+        }
+    }
+
+    pub fn error(&self, message: impl Into<String>) {
+        if let Ok(mut r) = self.result.lock() {
+            r.errors.push(message.into());
+        }
+    }
+
+    /// 结束记录并给出最终状态
+    pub fn finish(&self, stopped: bool, input_commands: u64) -> RunResult {
+        let mut r = self.result.lock().map(|r| r.clone()).unwrap_or_default();
+        r.finished = Local::now().to_rfc3339();
+        r.elapsed_secs = self.started.elapsed().as_secs();
+        r.input_commands = input_commands;
+        let last = r.last_navigation.as_ref().map(|n| n.result.as_str());
+        r.status = match last {
+            Some("aborted") => "aborted",
+            _ if stopped => "stopped",
+            Some("failed") => "failed",
+            _ => "completed",
+        }
+        .to_string();
+        r
+    }
+}
+
+impl NavOutcome {
+    fn new(target: &str, result: &NavResult, elapsed_ms: u64) -> Self {
+        let (kind, scene, error) = match result {
+            NavResult::Success => ("success", None, None),
+            NavResult::Handover(scene, _) => ("handover", Some(scene.clone()), None),
+            NavResult::Failed(e) => ("failed", None, Some(e.to_string())),
+            NavResult::Aborted(scene) => ("aborted", Some(scene.clone()), Some(format!("危险场景 [{}]", scene))),
+        };
+        Self { target: target.to_string(), result: kind.to_string(), scene, error, elapsed_ms }
+    }
+}
+
+impl RunResult {
+    /// 写入文件；path 为 "-" 时以单行 JSON 输出到标准输出
+    pub fn write(&self, path: &str) -> Result<(), String> {
+        if path == "-" {
+            let json = serde_json::to_string(self).map_err(|e| e.to_string())?;
+            let mut out = std::io::stdout();
+            return writeln!(out, "{}", json).and_then(|_| out.flush()).map_err(|e| e.to_string());
+        }
+        if let Some(dir) = std::path::Path::new(path).parent().filter(|d| !d.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir).map_err(|e| format!("无法创建目录 {}: {}", dir.display(), e))?;
+        }
+        let json = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        std::fs::write(path, json).map_err(|e| format!("无法写入 {}: {}", path, e))
+    }
+}
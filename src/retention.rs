@@ -0,0 +1,231 @@
+// src/retention.rs
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+// ==========================================
+// 1. 保留策略配置
+// ==========================================
+// 会话录像、失败截图与调试帧一晚上就能写满磁盘，
+// 这里统一按 "最大总量 / 最长保留时间 / 磁盘剩余下限" 清理受管目录。
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct RetentionConfig {
+    pub enabled: bool,
+    /// 受管理的目录 (只清理其中的一级条目，子目录整体视为一个条目)
+    pub dirs: Vec<String>,
+    /// 受管目录总大小上限 (MB)
+    pub max_total_mb: u64,
+    /// 普通条目的最长保留天数
+    pub max_age_days: u64,
+    /// 磁盘剩余空间低于此值 (MB) 时，继续删除最旧的条目
+    pub min_free_mb: u64,
+    /// 保留失败现场：名称包含 failure_marker 的条目不参与容量清理
+    pub keep_on_failure: bool,
+    pub failure_marker: String,
+    /// 失败现场的最长保留天数
+    pub failure_max_age_days: u64,
+    /// 后台周期检查间隔 (分钟)，0 表示只在启动时执行一次
+    pub interval_minutes: u64,
+}
+
+impl Default for RetentionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            dirs: vec![
+                "logs".to_string(),
+                "debug_frames".to_string(),
+                "sessions".to_string(),
+                "failures".to_string(),
+            ],
+            max_total_mb: 2048,
+            max_age_days: 7,
+            min_free_mb: 1024,
+            keep_on_failure: true,
+            failure_marker: "fail".to_string(),
+            failure_max_age_days: 30,
+            interval_minutes: 30,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct RetentionReport {
+    pub removed: usize,
+    pub freed_bytes: u64,
+    pub kept_failures: usize,
+}
+
+struct Entry {
+    path: PathBuf,
+    size: u64,
+    modified: SystemTime,
+    is_failure: bool,
+}
+
+// ==========================================
+// 2. 清理逻辑
+// ==========================================
+
+/// 按策略执行一次清理
+pub fn apply(cfg: &RetentionConfig) -> RetentionReport {
+    let mut report = RetentionReport::default();
+    if !cfg.enabled {
+        return report;
+    }
+
+    let mut entries = collect_entries(cfg);
+    // 最旧的排在前面，优先删除
+    entries.sort_by_key(|e| e.modified);
+
+    let now = SystemTime::now();
+    let day = Duration::from_secs(24 * 3600);
+    let max_age = day * cfg.max_age_days as u32;
+    let failure_max_age = day * cfg.failure_max_age_days as u32;
+
+    // 1. 过期清理
+    entries.retain(|e| {
+        let age = now.duration_since(e.modified).unwrap_or_default();
+        let limit = if e.is_failure { failure_max_age } else { max_age };
+        if age > limit && remove_entry(&e.path) {
+            report.removed += 1;
+            report.freed_bytes += e.size;
+            return false;
+        }
+        true
+    });
+
+    // 2. 总量清理 + 3. 磁盘剩余空间清理 (失败现场受保护)
+    let max_total = cfg.max_total_mb * 1024 * 1024;
+    let min_free = cfg.min_free_mb * 1024 * 1024;
+    let mut total: u64 = entries.iter().map(|e| e.size).sum();
+
+    for e in &entries {
+        if e.is_failure {
+            report.kept_failures += 1;
+            continue;
+        }
+        let over_quota = total > max_total;
+        let low_disk = cfg.min_free_mb > 0
+            && free_disk_bytes(&e.path).map(|free| free < min_free).unwrap_or(false);
+        if !over_quota && !low_disk {
+            break;
+        }
+        if remove_entry(&e.path) {
+            report.removed += 1;
+            report.freed_bytes += e.size;
+            total = total.saturating_sub(e.size);
+        }
+    }
+
+    if report.removed > 0 {
+        println!(
+            "🧹 [Retention] 已清理 {} 项，释放 {:.1} MB (保留失败现场 {} 项)",
+            report.removed,
+            report.freed_bytes as f64 / 1024.0 / 1024.0,
+            report.kept_failures
+        );
+    }
+    report
+}
+
+/// 启动时执行一次，并按间隔在后台周期执行
+pub fn start(cfg: RetentionConfig) {
+    if !cfg.enabled {
+        return;
+    }
+    apply(&cfg);
+    if cfg.interval_minutes == 0 {
+        return;
+    }
+    thread::spawn(move || loop {
+        thread::sleep(Duration::from_secs(cfg.interval_minutes * 60));
+        apply(&cfg);
+    });
+}
+
+fn collect_entries(cfg: &RetentionConfig) -> Vec<Entry> {
+    let mut entries = Vec::new();
+    for dir in &cfg.dirs {
+        let rd = match fs::read_dir(dir) {
+            Ok(rd) => rd,
+            Err(_) => continue,
+        };
+        for item in rd.flatten() {
+            let path = item.path();
+            let meta = match item.metadata() {
+                Ok(m) => m,
+                Err(_) => continue,
+            };
+            let name = item.file_name().to_string_lossy().to_lowercase();
+            entries.push(Entry {
+                size: path_size(&path),
+                modified: meta.modified().unwrap_or(SystemTime::UNIX_EPOCH),
+                is_failure: cfg.keep_on_failure
+                    && !cfg.failure_marker.is_empty()
+                    && name.contains(&cfg.failure_marker.to_lowercase()),
+                path,
+            });
+        }
+    }
+    entries
+}
+
+fn path_size(path: &Path) -> u64 {
+    let meta = match fs::metadata(path) {
+        Ok(m) => m,
+        Err(_) => return 0,
+    };
+    if meta.is_file() {
+        return meta.len();
+    }
+    fs::read_dir(path)
+        .map(|rd| rd.flatten().map(|e| path_size(&e.path())).sum())
+        .unwrap_or(0)
+}
+
+fn remove_entry(path: &Path) -> bool {
+    let res = if path.is_dir() {
+        fs::remove_dir_all(path)
+    } else {
+        fs::remove_file(path)
+    };
+    if let Err(e) = &res {
+        println!("⚠️ [Retention] 删除失败 {:?}: {}", path, e);
+    }
+    res.is_ok()
+}
+
+/// 查询路径所在卷的剩余可用空间
+#[cfg(feature = "windows")]
+fn free_disk_bytes(path: &Path) -> Option<u64> {
+    use windows::core::HSTRING;
+    use windows::Win32::Storage::FileSystem::GetDiskFreeSpaceExW;
+
+    let dir = if path.is_dir() { path } else { path.parent()? };
+    let abs = fs::canonicalize(dir).ok()?;
+    let mut free: u64 = 0;
+    unsafe {
+        GetDiskFreeSpaceExW(&HSTRING::from(abs.as_os_str()), Some(&mut free), None, None).ok()?;
+    }
+    Some(free)
+}
+
+#[cfg(feature = "linux")]
+fn free_disk_bytes(path: &Path) -> Option<u64> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let dir = if path.is_dir() { path } else { path.parent()? };
+    let abs = fs::canonicalize(dir).ok()?;
+    let c_path = std::ffi::CString::new(abs.as_os_str().as_bytes()).ok()?;
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(c_path.as_ptr(), &mut stat) } != 0 {
+        return None;
+    }
+    // 非 root 用户可用的块数
+    Some(stat.f_bavail as u64 * stat.f_frsize as u64)
+}
@@ -0,0 +1,53 @@
+// src/schedule.rs
+use chrono::{DateTime, Duration as ChronoDuration, Local, NaiveTime};
+use serde::Deserialize;
+
+// ==========================================
+// 定时运行
+// ==========================================
+// schedule 子命令按配置的时刻 (如每日 05:00 重置后) 或每隔 N 小时启动一轮刷图。
+// 两轮之间程序保持运行：硬件心跳照常，输入设备空闲，不再需要 Windows 计划任务反复拉起进程。
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct ScheduleConfig {
+    /// 每天的启动时刻 (本地时间 HH:MM)，如 ["05:00", "20:30"]
+    pub at: Vec<String>,
+    /// 每隔多少小时启动一轮 (0 = 不按间隔)；第一轮立即开始，之后按上一轮的开始时间计算
+    pub every_hours: f64,
+}
+
+impl ScheduleConfig {
+    /// 解析启动时刻；格式错误时返回 Err
+    pub fn times(&self) -> Result<Vec<NaiveTime>, String> {
+        self.at
+            .iter()
+            .map(|s| NaiveTime::parse_from_str(s.trim(), "%H:%M").map_err(|_| format!("无效的启动时刻 \"{}\" (应为 HH:MM)", s)))
+            .collect()
+    }
+
+    /// 检查配置是否可用
+    pub fn validate(&self) -> Result<(), String> {
+        self.times()?;
+        if self.every_hours < 0.0 {
+            return Err(format!("every_hours 不能为负数: {}", self.every_hours));
+        }
+        if self.at.is_empty() && self.every_hours == 0.0 {
+            return Err("未配置任何启动时刻 (at) 或间隔 (every_hours)".to_string());
+        }
+        Ok(())
+    }
+
+    /// 下一次启动时间：取 "下一个整点时刻" 与 "上一轮开始 + 间隔" 中较早的一个
+    pub fn next_run(&self, now: DateTime<Local>, last_start: Option<DateTime<Local>>) -> Option<DateTime<Local>> {
+        let by_time = self.times().ok()?.into_iter().filter_map(|t| {
+            let today = now.date_naive().and_time(t).and_local_timezone(Local).earliest()?;
+            Some(if today > now { today } else { today + ChronoDuration::days(1) })
+        });
+        let by_interval = (self.every_hours > 0.0).then(|| match last_start {
+            Some(s) => s + ChronoDuration::seconds((self.every_hours * 3600.0) as i64),
+            None => now,
+        });
+        by_time.chain(by_interval).min()
+    }
+}
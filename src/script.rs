@@ -0,0 +1,157 @@
+// src/script.rs
+//
+// 脚本场景处理器：场景的 handler 指向 .rhai 文件时，到达该场景后由内嵌的 rhai 引擎执行脚本，
+// 无需重新编译即可自动化新的玩法。脚本只能调用下面注册的接口，不能访问文件系统或进程
+// (import 被禁用，运算次数有上限，死循环不会一直占着处理器线程)。
+//
+// 脚本接口 (坐标为屏幕像素)：
+//   ocr(x1, y1, x2, y2) -> String        识别区域文字
+//   click(x, y)                          拟人化点击
+//   key(name) -> bool                    按键 (按键名同钩子)，未知按键返回 false
+//   wait(ms) -> bool                     可中断的等待，收到停止信号返回 false
+//   wait_for_scene(id, timeout_ms) -> bool
+//   current_scene() -> String            当前场景 id，无法识别时为 ""
+//   navigate(id) -> bool                 导航到指定场景 (到达或交接均算成功)
+//   print(...) / debug(...)              写入日志
+// 预置常量 SCENE 为触发脚本的场景 id。
+use crate::control::RunControl;
+use crate::handler::{HandlerOutcome, SceneHandler};
+use crate::nav::{NavEngine, NavResult};
+use rhai::{Dynamic, Engine, Scope};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{debug, error, info, warn};
+
+/// 单次脚本执行的运算次数上限：wait() 等接口调用各算一次，正常的轮询脚本跑一整晚也远达不到
+const MAX_OPERATIONS: u64 = 50_000_000;
+
+/// 脚本执行结果
+#[derive(Debug, Clone, PartialEq)]
+pub enum ScriptOutcome {
+    Completed,
+    /// 收到停止信号，脚本被中断
+    Stopped,
+    Failed(String),
+}
+
+pub struct ScriptHandler {
+    nav: Arc<NavEngine>,
+    control: RunControl,
+}
+
+impl ScriptHandler {
+    pub fn new(nav: Arc<NavEngine>) -> Self {
+        Self { nav, control: RunControl::new() }
+    }
+
+    /// 共享运行控制：停止信号会中断正在执行的脚本
+    pub fn with_control(mut self, control: RunControl) -> Self {
+        self.control = control;
+        self
+    }
+
+    /// 在场景 scene_id 上执行脚本文件
+    pub fn run(&self, path: &str, scene_id: &str) -> ScriptOutcome {
+        info!("📜 [脚本] 执行 {} (场景: {})", path, scene_id);
+        if !Path::new(path).exists() {
+            return ScriptOutcome::Failed(format!("脚本文件不存在: {}", path));
+        }
+        let engine = self.build_engine();
+        let ast = match engine.compile_file(path.into()) {
+            Ok(ast) => ast,
+            Err(e) => return ScriptOutcome::Failed(format!("脚本编译失败: {}", e)),
+        };
+        let mut scope = Scope::new();
+        scope.push_constant("SCENE", scene_id.to_string());
+        match engine.run_ast_with_scope(&mut scope, &ast) {
+            Ok(()) => ScriptOutcome::Completed,
+            Err(_) if self.control.is_stopped() => ScriptOutcome::Stopped,
+            Err(e) => ScriptOutcome::Failed(format!("脚本运行出错: {}", e)),
+        }
+    }
+
+    /// 构建只包含安全接口的引擎
+    fn build_engine(&self) -> Engine {
+        let mut engine = Engine::new();
+        // 默认的文件模块解析器会让 import "..." 读取任意 .rhai 文件
+        engine.set_module_resolver(rhai::module_resolvers::DummyModuleResolver::new());
+        engine.set_max_operations(MAX_OPERATIONS);
+
+        let control = self.control.clone();
+        engine.on_progress(move |_| {
+            if control.is_stopped() { Some(Dynamic::UNIT) } else { None }
+        });
+        engine.on_print(|s| info!("📜 [脚本] {}", s));
+        engine.on_debug(|s, _, pos| debug!("📜 [脚本] {} ({})", s, pos));
+
+        let nav = Arc::clone(&self.nav);
+        engine.register_fn("ocr", move |x1: i64, y1: i64, x2: i64, y2: i64| {
+            nav.ocr_area([x1 as i32, y1 as i32, x2 as i32, y2 as i32])
+        });
+
+        let nav = Arc::clone(&self.nav);
+        let control = self.control.clone();
+        engine.register_fn("click", move |x: i64, y: i64| {
+            if control.wait_if_paused() {
+                nav.click(x as i32, y as i32);
+            }
+        });
+
+        let nav = Arc::clone(&self.nav);
+        let control = self.control.clone();
+        engine.register_fn("key", move |name: &str| {
+            if !control.wait_if_paused() {
+                return false;
+            }
+            let ok = nav.press_key(name);
+            if !ok {
+                warn!("⚠️ [脚本] 未知按键: {}", name);
+            }
+            ok
+        });
+
+        let control = self.control.clone();
+        engine.register_fn("wait", move |ms: i64| control.sleep(Duration::from_millis(ms.max(0) as u64)));
+
+        let nav = Arc::clone(&self.nav);
+        engine.register_fn("wait_for_scene", move |id: &str, timeout_ms: i64| {
+            nav.wait_for_scene(id, Duration::from_millis(timeout_ms.max(0) as u64))
+        });
+
+        let nav = Arc::clone(&self.nav);
+        engine.register_fn("current_scene", move || nav.detect_current_scene().unwrap_or_default());
+
+        let nav = Arc::clone(&self.nav);
+        engine.register_fn("navigate", move |id: &str| {
+            let report = nav.navigate(id);
+            if !matches!(report.result, NavResult::Success | NavResult::Handover(..)) {
+                warn!("⚠️ [脚本] 导航到 [{}] 失败: {}", id, report.summary());
+                return false;
+            }
+            true
+        });
+
+        engine
+    }
+}
+
+/// 注册键为 ".rhai"：场景的 handler 即脚本路径；脚本出错同样计为一局，避免卡在同一场景
+impl SceneHandler for ScriptHandler {
+    fn handle(&mut self, scene_id: &str, name: &str) -> HandlerOutcome {
+        match self.run(name, scene_id) {
+            ScriptOutcome::Completed => {
+                info!("✅ [脚本] {} 执行完毕", name);
+                HandlerOutcome::Run(None)
+            }
+            ScriptOutcome::Stopped => {
+                info!("🛑 [脚本] {} 已中断", name);
+                HandlerOutcome::Stopped(None)
+            }
+            ScriptOutcome::Failed(e) => {
+                error!("❌ [脚本] {}", e);
+                HandlerOutcome::Run(None)
+            }
+        }
+    }
+}
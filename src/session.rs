@@ -0,0 +1,169 @@
+// src/session.rs
+use crate::frames;
+use crate::nav::{NavEngine, NavEventKind};
+use chrono::Local;
+use image::codecs::jpeg::JpegEncoder;
+use image::imageops::{self, FilterType};
+use nzm_schema::session::{self, SessionEvent, SessionEventKind};
+use serde::Deserialize;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+// ==========================================
+// 会话录像 (--session)
+// ==========================================
+// 通宵运行出问题时留下现场：定时保存缩小截图，同时记录每条键鼠命令、场景识别、跳转与 OCR 结果，
+// 全部按相对时间写进 sessions/<开始时间>/ (格式见 nzm_schema::session)。
+// UI_tool 的「🎞️ 会话回放」可拖动时间轴逐帧查看，session 子命令在终端按时间打印。
+
+/// 会话录像配置 ([session])
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct SessionConfig {
+    /// 会话目录的父目录 (已在 [retention] 的默认受管目录中)
+    pub dir: String,
+    /// 截图间隔 (ms)
+    pub frame_interval_ms: u64,
+    /// 截图缩放比例 (0.5 = 长宽各一半)
+    pub frame_scale: f32,
+    /// JPEG 质量 (1 ~ 100)
+    pub jpeg_quality: u8,
+}
+
+impl Default for SessionConfig {
+    fn default() -> Self {
+        Self { dir: "sessions".to_string(), frame_interval_ms: 2000, frame_scale: 0.5, jpeg_quality: 70 }
+    }
+}
+
+pub struct SessionRecorder {
+    dir: PathBuf,
+    started: Instant,
+    writer: Mutex<BufWriter<File>>,
+    stopped: AtomicBool,
+}
+
+impl SessionRecorder {
+    /// 在 config.dir 下新建本次会话目录
+    pub fn create(config: &SessionConfig, command: &str) -> std::io::Result<Arc<Self>> {
+        let dir = Path::new(&config.dir).join(Local::now().format("%Y%m%d_%H%M%S").to_string());
+        fs::create_dir_all(dir.join(session::FRAMES_DIR))?;
+        let writer = BufWriter::new(File::create(dir.join(session::EVENTS_FILE))?);
+        info!("🎞️ [会话] 录像目录: {}", dir.display());
+        let recorder = Arc::new(Self { dir, started: Instant::now(), writer: Mutex::new(writer), stopped: AtomicBool::new(false) });
+        recorder.record(SessionEventKind::Start {
+            started_at: Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+            command: command.to_string(),
+        });
+        Ok(recorder)
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn elapsed_ms(&self) -> u64 {
+        self.started.elapsed().as_millis() as u64
+    }
+
+    /// 追加一个事件 (每行立即落盘，进程被强制结束时前面的记录依然完整)
+    pub fn record(&self, kind: SessionEventKind) {
+        let event = SessionEvent { t_ms: self.elapsed_ms(), kind };
+        let Ok(line) = serde_json::to_string(&event) else { return };
+        if let Ok(mut w) = self.writer.lock() {
+            let _ = writeln!(w, "{}", line);
+            let _ = w.flush();
+        }
+    }
+
+    /// 记录导航事件 (场景、跳转、OCR、弹窗、导航结果)
+    pub fn attach(self: &Arc<Self>, engine: &NavEngine) {
+        let recorder = Arc::clone(self);
+        engine.on_event(move |e| {
+            let kind = match &e.kind {
+                NavEventKind::SceneEntered { scene, confidence } => SessionEventKind::Scene { scene: scene.clone(), confidence: *confidence },
+                NavEventKind::TransitionClicked { from, to, coords } => {
+                    SessionEventKind::Transition { from: from.clone(), to: to.clone(), coords: *coords }
+                }
+                NavEventKind::OcrCompleted { rect, text, elapsed_ms } => {
+                    SessionEventKind::Ocr { rect: *rect, text: text.clone(), elapsed_ms: *elapsed_ms }
+                }
+                NavEventKind::PopupDismissed { popup } => SessionEventKind::Popup { popup: popup.clone() },
+                NavEventKind::NavigationFinished { target, result, elapsed_ms } => {
+                    SessionEventKind::Navigation { target: target.clone(), result: format!("{:?}", result), elapsed_ms: *elapsed_ms }
+                }
+            };
+            recorder.record(kind);
+        });
+    }
+
+    /// 后台定时保存缩小截图 (取帧服务的共享帧，不额外截屏)，finish 后停止
+    pub fn spawn_frames(self: &Arc<Self>, config: &SessionConfig) {
+        let recorder = Arc::clone(self);
+        let interval = Duration::from_millis(config.frame_interval_ms.max(100));
+        let scale = config.frame_scale.clamp(0.05, 1.0);
+        let quality = config.jpeg_quality.clamp(1, 100);
+        thread::spawn(move || {
+            while !recorder.stopped.load(Ordering::Relaxed) {
+                if let Err(e) = recorder.save_frame(scale, quality) {
+                    warn!("⚠️ [会话] 截图保存失败: {}", e);
+                }
+                thread::sleep(interval);
+            }
+        });
+    }
+
+    fn save_frame(&self, scale: f32, quality: u8) -> Result<(), String> {
+        let frame = frames::service().frame().ok_or("截图失败")?;
+        let (w, h) = (
+            ((frame.image.width() as f32 * scale) as u32).max(1),
+            ((frame.image.height() as f32 * scale) as u32).max(1),
+        );
+        let small = image::DynamicImage::ImageRgba8(imageops::resize(frame.image.as_ref(), w, h, FilterType::Triangle)).to_rgb8();
+        let file = session::frame_file(self.elapsed_ms());
+        let out = File::create(self.dir.join(&file)).map_err(|e| e.to_string())?;
+        small.write_with_encoder(JpegEncoder::new_with_quality(BufWriter::new(out), quality)).map_err(|e| e.to_string())?;
+        self.record(SessionEventKind::Frame { file, scale });
+        Ok(())
+    }
+
+    /// 结束会话：停止截图并写入结束事件
+    pub fn finish(&self, stopped: bool) {
+        if self.stopped.swap(true, Ordering::Relaxed) {
+            return;
+        }
+        self.record(SessionEventKind::End { stopped });
+        info!("🎞️ [会话] 录像已保存: {}", self.dir.display());
+    }
+}
+
+/// session 子命令：按时间打印事件，可只看某一类 (如 "OCR")
+pub fn print(dir: &str, only: Option<&str>) -> Result<(), String> {
+    let (events, skipped) = session::load_events(Path::new(dir))?;
+    let frames = events.iter().filter(|e| matches!(e.kind, SessionEventKind::Frame { .. })).count();
+    println!("🎞️ {} ({} 条事件，{} 帧截图)", dir, events.len(), frames);
+    for e in &events {
+        let label = e.kind.label();
+        if matches!(e.kind, SessionEventKind::Frame { .. }) && only != Some(label) {
+            continue;
+        }
+        if only.is_some_and(|o| o != label) {
+            continue;
+        }
+        let t = e.t_ms / 1000;
+        println!("  {:02}:{:02}:{:02}.{:03} {:<4} {}", t / 3600, t / 60 % 60, t % 60, e.t_ms % 1000, label, e.kind);
+    }
+    if !events.iter().any(|e| matches!(e.kind, SessionEventKind::End { .. })) {
+        println!("\n⚠️ 没有结束记录：进程可能被强制结束或崩溃，最后一条事件即事发时刻");
+    }
+    if skipped > 0 {
+        println!("⚠️ 跳过 {} 行无法解析的记录", skipped);
+    }
+    Ok(())
+}
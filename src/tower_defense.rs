@@ -1,26 +1,34 @@
+use crate::calibration::{self, Calibration};
+use crate::config::ProfileConfig;
+use crate::control::{PlacedTower, RunControl};
+use crate::digits::DigitTemplates;
 use crate::human::HumanDriver;
+use crate::journal::{Journal, JournalEntry};
+use crate::metrics;
 use crate::nav::NavEngine;
+use crate::notify::{self, NotifyEvent};
+use crate::vision;
+use crate::watchdog::Watchdog;
+use nzm_schema::migrate::check_strategy_version;
 use regex::Regex;
-use serde::Deserialize;
-use std::collections::{HashMap, HashSet};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs;
-use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
 use std::thread;
 use std::time::{Duration, Instant};
+use tracing::{error, info, info_span, warn};
 
 // ==========================================
 // 1. 数据结构协议
 // ==========================================
-
-// ✨ 新增：预备阶段动作定义 (用于 MapMeta)
-#[derive(Deserialize, Debug, Clone)]
-#[serde(tag = "type")]
-pub enum PrepAction {
-    KeyDown { key: char },
-    KeyUpAll,
-    Wait { ms: u64 },
-    Log { msg: String },
-}
+// 地形 / 策略 / 陷阱配置的文件格式在 nzm_schema 中定义 (与 tools/ 下的工具共用)，这里重新导出
+pub use nzm_schema::strategy::{
+    AbilityEvent, BuildingExport, CameraMode, DemolishEvent, EmergencyPlan, MapBuildingsExport, MapMeta,
+    MapTerrainExport, PrepAction, TDConfigOverride, TrapConfigItem, UpgradeEvent, WaveDetector,
+};
 
 #[derive(Deserialize, Debug, Clone)]
 #[serde(tag = "type")]
@@ -48,13 +56,92 @@ pub enum InitAction {
     },
 }
 
-#[derive(Debug, Clone)]
+/// 塔防界面参数 (td_config.toml)；顶层为默认值，[maps."地图名"] 中的字段覆盖对应地图
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
 pub struct TDConfig {
     pub hud_check_rect: [i32; 4],
     pub hud_wave_loop_rect: [i32; 4],
     pub safe_zone: [i32; 4],
     pub screen_width: f32,
     pub screen_height: f32,
+    /// 波次数字模板目录 (UI_tool 导出的 0~9 切图)，OCR 读不出波次时兜底
+    pub digit_template_dir: String,
+    /// 波次 OCR 置信度低于该值时也用数字模板复核 (艺术字体常被读错而非读不出)；0 表示只在 OCR 读不出时兜底
+    pub wave_ocr_min_confidence: f32,
+    /// 战斗中的波次识别方式
+    pub wave_detector: WaveDetector,
+    /// template 模式下匹配的 HUD 区域 (只含波次数字)；未设置时使用 hud_check_rect
+    pub hud_wave_digits_rect: Option<[i32; 4]>,
+    /// 金币显示区域；设置后建造前先确认余额足够 (需在 traps_config.json 中填写 cost)
+    pub gold_rect: Option<[i32; 4]>,
+    /// 从 OCR 文本中提取金币数的正则 (第一个捕获组)
+    pub gold_regex: String,
+    /// 余额不足时的重新识别间隔与最长等待 (毫秒)；超时的建造顺延到之后的阶段
+    pub afford_retry_ms: u64,
+    pub afford_max_wait_ms: u64,
+    /// 等待超时后卖掉已放置的 sellable 建造凑钱，再执行计划中的建造
+    pub sell_to_afford: bool,
+    /// 陷阱未填写 refund 时的返还比例
+    pub sell_refund_ratio: f32,
+    /// 放置后截图比对格子是否发生变化，未变化则重试
+    pub verify_placement: bool,
+    /// 放置失败后的重试次数；仍失败则不标记为已放置，顺延到之后的阶段
+    pub place_retries: u32,
+    /// 放置前后格子区域的平均灰度差阈值 (0 ~ 255)
+    pub verify_diff_threshold: f32,
+    /// 波次倒计时区域；设置后开战后等到剩余 late_phase_remaining_s 秒才执行后期 (is_late) 操作
+    pub wave_timer_rect: Option<[i32; 4]>,
+    pub late_phase_remaining_s: u32,
+    /// 倒计时识别间隔与最长等待 (毫秒)
+    pub wave_timer_poll_ms: u64,
+    pub late_phase_max_wait_ms: u64,
+    /// 开局按网格间距校准缩放 (代替固定滚轮次数)；测量区域默认 safe_zone
+    pub zoom_calibration: bool,
+    pub zoom_rect: Option<[i32; 4]>,
+    /// 屏幕格子边长与 grid_pixel_size 的允许相对误差
+    pub zoom_tolerance: f32,
+    pub zoom_max_steps: u32,
+    /// 每格滚轮后等待画面稳定的时间 (毫秒)
+    pub zoom_settle_ms: u64,
+    /// 基地血量区域 ("3500/5000" 或 "70%")；设置后每轮监控检查，低于 base_hp_threshold (%) 时执行策略中的 emergency 预案
+    pub base_hp_rect: Option<[i32; 4]>,
+    pub base_hp_threshold: u32,
+    /// 两次应急之间的最短间隔 (毫秒)
+    pub emergency_cooldown_ms: u64,
+    /// 镜头平移速度 (像素/秒)；不填时使用校准结果，未校准为 300
+    pub move_speed: Option<f32>,
+    /// 镜头平移方式：key_hold / drag
+    pub camera_mode: CameraMode,
+    /// drag 模式按住右键拖动；false 时用左键
+    pub drag_right_button: bool,
+    /// drag 模式下鼠标每拖动 1 像素镜头移动的地图像素
+    pub drag_ratio: f32,
+    /// 单次拖动的最大距离 (像素)，更远的移动拆成多次
+    pub drag_stroke_px: f32,
+    /// 装备栏每页的快捷键 (按装备顺序分配)
+    pub hotbar_keys: Vec<char>,
+    /// 切换到第 n 页时按的键 (下标即页号)；为空时只有一页，装备超过 hotbar_keys 的部分无法使用
+    pub page_keys: Vec<char>,
+    /// 监控线程识别波次的间隔 (毫秒)
+    pub monitor_poll_ms: u64,
+    /// 同一建造 (含备选位置) 累计失败的阶段数上限，达到后跳过并记录，不再重试
+    pub place_max_failures: u32,
+    /// 每局动作日志目录 (放置 / 升级 / 拆除 / 技能)，为空时不记录
+    pub journal_dir: String,
+    /// 日志附带动作后的格子截图
+    pub journal_snapshots: bool,
+    /// 进度文件：每个波次阶段后及结束时写入，崩溃后可用 run_resume 继续
+    pub progress_path: String,
+    /// 结算界面的场景 id (需在 ui_map.toml 中定义锚点)；识别到即判定本局结束
+    pub victory_scene: String,
+    pub defeat_scene: String,
+    /// 每局结果追加写入的 JSONL 文件
+    pub outcome_log_path: String,
+    /// 超过该秒数没有波次推进视为卡住：截图、按 ESC 并重新识别 (0 = 不检测)
+    pub stall_timeout_s: u64,
+    /// 连续恢复无效的次数上限，超过后停止自动化
+    pub stall_max_recoveries: u32,
 }
 
 impl Default for TDConfig {
@@ -65,78 +152,277 @@ impl Default for TDConfig {
             safe_zone: [200, 200, 1720, 880],
             screen_width: 1920.0,
             screen_height: 1080.0,
+            digit_template_dir: "digits".to_string(),
+            wave_ocr_min_confidence: 0.6,
+            wave_detector: WaveDetector::TabOcr,
+            hud_wave_digits_rect: None,
+            gold_rect: None,
+            gold_regex: r"(\d[\d,\s]*)".to_string(),
+            afford_retry_ms: 2000,
+            afford_max_wait_ms: 15000,
+            sell_to_afford: false,
+            sell_refund_ratio: 0.5,
+            verify_placement: false,
+            place_retries: 2,
+            verify_diff_threshold: 8.0,
+            place_max_failures: 3,
+            monitor_poll_ms: 2000,
+            wave_timer_rect: None,
+            late_phase_remaining_s: 20,
+            wave_timer_poll_ms: 1000,
+            late_phase_max_wait_ms: 180_000,
+            zoom_calibration: false,
+            zoom_rect: None,
+            zoom_tolerance: 0.03,
+            zoom_max_steps: 60,
+            zoom_settle_ms: 250,
+            base_hp_rect: None,
+            base_hp_threshold: 40,
+            emergency_cooldown_ms: 60_000,
+            move_speed: None,
+            camera_mode: CameraMode::KeyHold,
+            drag_right_button: true,
+            drag_ratio: 1.0,
+            drag_stroke_px: 400.0,
+            hotbar_keys: vec!['4', '5', '6', '7'],
+            page_keys: Vec::new(),
+            journal_dir: "logs/journal".to_string(),
+            journal_snapshots: false,
+            progress_path: "logs/td_progress.json".to_string(),
+            victory_scene: "胜利结算".to_string(),
+            defeat_scene: "失败结算".to_string(),
+            outcome_log_path: "logs/td_outcomes.jsonl".to_string(),
+            stall_timeout_s: 600,
+            stall_max_recoveries: 2,
         }
     }
 }
 
-// ✨ 修改：TrapConfigItem 增加 b_type 和 grid_index
-#[derive(Deserialize, Debug, Clone)]
-pub struct TrapConfigItem {
-    pub name: String,
-    #[serde(default)]
-    pub b_type: String, // "Floor", "Wall", "Ceiling"
-    #[serde(default)]
-    pub grid_index: [i32; 2], // [col, row]
+impl TDConfig {
+    /// 应用单张地图的覆盖项，未写的字段保持不变
+    pub fn apply_override(&mut self, o: &TDConfigOverride) {
+        if let Some(v) = o.hud_check_rect { self.hud_check_rect = v; }
+        if let Some(v) = o.hud_wave_loop_rect { self.hud_wave_loop_rect = v; }
+        if let Some(v) = o.safe_zone { self.safe_zone = v; }
+        if let Some(v) = o.screen_width { self.screen_width = v; }
+        if let Some(v) = o.screen_height { self.screen_height = v; }
+        if let Some(v) = &o.digit_template_dir { self.digit_template_dir = v.clone(); }
+        if let Some(v) = o.wave_detector { self.wave_detector = v; }
+        if let Some(v) = o.hud_wave_digits_rect { self.hud_wave_digits_rect = Some(v); }
+        if let Some(v) = o.gold_rect { self.gold_rect = Some(v); }
+        if let Some(v) = o.wave_timer_rect { self.wave_timer_rect = Some(v); }
+        if let Some(v) = o.base_hp_rect { self.base_hp_rect = Some(v); }
+        if let Some(v) = o.move_speed { self.move_speed = Some(v); }
+        if let Some(v) = o.camera_mode { self.camera_mode = v; }
+        if let Some(v) = o.drag_ratio { self.drag_ratio = v; }
+    }
 }
 
-// ✨ 修改：MapMeta 增加 prep_actions
-#[derive(Deserialize, Debug, Clone)]
-pub struct MapMeta {
-    pub grid_pixel_size: f32,
-    pub offset_x: f32,
-    pub offset_y: f32,
-    pub bottom: f32,
+#[derive(Deserialize, Debug, Default)]
+struct TDConfigFile {
+    #[serde(flatten)]
+    base: TDConfig,
     #[serde(default)]
-    pub prep_actions: Vec<PrepAction>,
+    maps: HashMap<String, TDConfigOverride>,
 }
 
-#[derive(Deserialize, Debug, Clone)]
-pub struct BuildingExport {
-    pub uid: usize,
-    pub name: String,
-    pub grid_x: usize,
-    pub grid_y: usize,
-    pub width: usize,
-    pub height: usize,
-    #[serde(default)]
-    pub wave_num: i32,
-    #[serde(default)]
-    pub is_late: bool,
+impl TDConfig {
+    /// 读取 td_config.toml 并应用 map_name 的覆盖项；文件不存在时使用内置默认值
+    pub fn load(path: &str, map_name: &str) -> Self {
+        let file: TDConfigFile = match fs::read_to_string(path) {
+            Ok(content) => match toml::from_str(&content) {
+                Ok(f) => f,
+                Err(e) => {
+                    warn!("⚠️ [塔防] {} 解析失败，使用默认界面参数: {}", path, e);
+                    return Self::default();
+                }
+            },
+            Err(_) => return Self::default(),
+        };
+        let mut cfg = file.base;
+        if let Some(o) = file.maps.get(map_name) {
+            info!("🗺️ [塔防] 应用地图 '{}' 的界面参数覆盖", map_name);
+            cfg.apply_override(o);
+        }
+        cfg
+    }
 }
 
-#[derive(Deserialize, Debug, Clone)]
-pub struct UpgradeEvent {
-    pub building_name: String,
-    pub wave_num: i32,
-    pub is_late: bool,
+/// 解析血量文本为百分比："3500/5000" 按比例换算，单个数字 (如 "70%") 视为百分比
+pub fn parse_hp_percent(text: &str) -> Option<u32> {
+    let re = Regex::new(r"(\d+)\s*/\s*(\d+)").unwrap();
+    if let Some(caps) = re.captures(text) {
+        let cur: u64 = caps[1].parse().ok()?;
+        let max: u64 = caps[2].parse().ok()?;
+        if max == 0 {
+            return None;
+        }
+        return Some((cur * 100 / max).min(100) as u32);
+    }
+    let digits: String = text.chars().filter(|c| c.is_ascii_digit()).collect();
+    digits.parse::<u32>().ok().filter(|v| *v <= 100)
 }
 
-#[derive(Deserialize, Debug, Clone)]
-pub struct DemolishEvent {
-    pub uid: usize,
-    pub name: String,
-    pub grid_x: usize,
-    pub grid_y: usize,
-    pub width: usize,
-    pub height: usize,
-    pub wave_num: i32,
-    pub is_late: bool,
+/// 解析倒计时文本为剩余秒数，支持 "01:23" 与 "83" 两种写法
+pub fn parse_countdown(text: &str) -> Option<u32> {
+    let re = Regex::new(r"(\d{1,2})\s*[:：]\s*(\d{2})").unwrap();
+    if let Some(caps) = re.captures(text) {
+        let m: u32 = caps[1].parse().ok()?;
+        let s: u32 = caps[2].parse().ok()?;
+        return Some(m * 60 + s);
+    }
+    let digits: String = text.chars().filter(|c| c.is_ascii_digit()).collect();
+    digits.parse().ok()
 }
 
-#[derive(Deserialize, Debug, Clone)]
-pub struct MapTerrainExport {
-    pub map_name: String,
-    pub meta: MapMeta,
+fn is_toml(path: &str) -> bool {
+    std::path::Path::new(path)
+        .extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case("toml"))
 }
 
-#[derive(Deserialize, Debug, Clone)]
-pub struct MapBuildingsExport {
+/// 读取地形 / 策略等数据文件：.toml 按 TOML 解析 (可写注释)，其余按 JSON
+pub fn parse_data_file<T: DeserializeOwned>(path: &str) -> Result<T, String> {
+    let content = fs::read_to_string(path).map_err(|e| format!("无法读取 {}: {}", path, e))?;
+    if is_toml(path) {
+        toml::from_str(&content).map_err(|e| format!("{} 解析失败: {}", path, e))
+    } else {
+        serde_json::from_str(&content).map_err(|e| format!("{} 解析失败: {}", path, e))
+    }
+}
+
+/// 陷阱配置的 TOML 形式：顶层不能是数组，写成 [[traps]]
+#[derive(Deserialize)]
+struct TrapConfigFile {
+    traps: Vec<TrapConfigItem>,
+}
+
+pub fn parse_trap_file(path: &str) -> Result<Vec<TrapConfigItem>, String> {
+    if is_toml(path) {
+        parse_data_file::<TrapConfigFile>(path).map(|f| f.traps)
+    } else {
+        parse_data_file(path)
+    }
+}
+
+/// 数据文件路径：stem.toml 存在时优先使用 (手写版)，否则 stem.json
+pub fn data_file_path(stem: &str) -> String {
+    let toml_path = format!("{}.toml", stem);
+    if std::path::Path::new(&toml_path).exists() {
+        toml_path
+    } else {
+        format!("{}.json", stem)
+    }
+}
+
+/// 计算 only_if 条件：用 && 连接的若干 "变量 比较符 整数"
+/// 变量: wave (当前波次)、resource / gold (金币余额，无法识别时条件不成立)
+pub fn check_only_if(expr: &str, wave: i32, gold: Option<u32>) -> Result<bool, String> {
+    let re = Regex::new(r"^\s*(\w+)\s*(>=|<=|==|!=|>|<)\s*(-?\d+)\s*$").unwrap();
+    let mut result = true;
+    for clause in expr.split("&&") {
+        let caps = re.captures(clause).ok_or_else(|| format!("无法解析条件: {}", clause.trim()))?;
+        let rhs: i64 = caps[3].parse().map_err(|_| format!("数值错误: {}", &caps[3]))?;
+        let lhs: Option<i64> = match &caps[1] {
+            "wave" => Some(wave as i64),
+            "resource" | "gold" => gold.map(|g| g as i64),
+            other => return Err(format!("未知变量: {}", other)),
+        };
+        let ok = lhs.is_some_and(|l| match &caps[2] {
+            ">=" => l >= rhs,
+            "<=" => l <= rhs,
+            "==" => l == rhs,
+            "!=" => l != rhs,
+            ">" => l > rhs,
+            _ => l < rhs,
+        });
+        result &= ok;
+    }
+    Ok(result)
+}
+
+/// 升级完成记录的键 (同名塔按 uid 区分)
+fn upgrade_done_key(u: &UpgradeEvent) -> String {
+    match u.uid {
+        Some(uid) => format!("{}-{}-{}-{}", u.building_name, u.wave_num, u.is_late, uid),
+        None => format!("{}-{}-{}", u.building_name, u.wave_num, u.is_late),
+    }
+}
+
+/// 本局进度快照：每个波次阶段后写入 progress_path，崩溃后可用 run_resume 继续
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct TdProgress {
+    /// 地图名 (td_config.toml 的覆盖键)
     pub map_name: String,
-    pub buildings: Vec<BuildingExport>,
-    #[serde(default)]
-    pub upgrades: Vec<UpgradeEvent>,
-    #[serde(default)]
-    pub demolishes: Vec<DemolishEvent>,
+    pub terrain_path: String,
+    pub strategy_path: String,
+    pub trap_path: String,
+    pub last_wave: i32,
+    pub placed_uids: Vec<usize>,
+    pub completed_upgrades: Vec<String>,
+    /// 已释放的技能 (策略 abilities 中的下标)
+    pub completed_abilities: Vec<usize>,
+    pub completed_demolishes: Vec<usize>,
+    /// 改放到备选格子的建造 uid -> [grid_x, grid_y]
+    pub relocated: HashMap<usize, [usize; 2]>,
+    pub skipped_uids: Vec<usize>,
+    pub camera_offset_x: f32,
+    pub camera_offset_y: f32,
+}
+
+impl TdProgress {
+    pub fn load(path: &str) -> Result<Self, String> {
+        let content = fs::read_to_string(path).map_err(|e| format!("无法读取进度文件 {}: {}", path, e))?;
+        serde_json::from_str(&content).map_err(|e| format!("进度文件格式错误 {}: {}", path, e))
+    }
+}
+
+/// 一局的结果 (由结算界面识别)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RunOutcome {
+    Victory,
+    Defeat,
+    /// 未识别到结算界面 (如连续丢失波次后退出、被停止)
+    #[default]
+    Unknown,
+}
+
+/// run() 的返回值
+#[derive(Debug, Clone, Default)]
+pub struct RunSummary {
+    /// 是否因停止信号提前结束
+    pub stopped: bool,
+    pub outcome: RunOutcome,
+    pub last_wave: i32,
+    pub placed: usize,
+    /// 多次放置失败后放弃的建造数
+    pub skipped: usize,
+    pub upgraded: usize,
+    pub demolished: usize,
+    pub elapsed: Duration,
+}
+
+impl std::fmt::Display for RunSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} | {} | 最后波次 {} | 放置 {} (跳过 {}) | 升级 {} | 拆除 {} | 用时 {}s",
+            if self.stopped { "已停止" } else { "已完成" },
+            match self.outcome {
+                RunOutcome::Victory => "胜利",
+                RunOutcome::Defeat => "失败",
+                RunOutcome::Unknown => "结果未知",
+            },
+            self.last_wave,
+            self.placed,
+            self.skipped,
+            self.upgraded,
+            self.demolished,
+            self.elapsed.as_secs()
+        )
+    }
 }
 
 #[derive(Debug, Default)]
@@ -159,6 +445,96 @@ struct ScheduledTask {
     priority: u8,
 }
 
+/// 一次镜头移动，执行后镜头偏移为 offset
+#[derive(Debug, Clone)]
+struct CameraPlan {
+    motion: CameraMotion,
+    offset: f32,
+}
+
+/// 一笔拖动：屏幕坐标起点 -> 终点
+type DragStroke = ((f32, f32), (f32, f32));
+
+#[derive(Debug, Clone)]
+enum CameraMotion {
+    /// 先按住 edge_key 贴边，再按住 pan_key 平移 pan_ms
+    Keys { edge_key: char, pan_key: char, pan_ms: u64 },
+    /// 依次按住鼠标键从起点拖到终点 (屏幕坐标)
+    Drag { strokes: Vec<DragStroke> },
+}
+
+impl CameraPlan {
+    fn push_sim_actions(&self, actions: &mut Vec<SimAction>) {
+        match &self.motion {
+            CameraMotion::Keys { edge_key, pan_key, pan_ms } => {
+                actions.push(SimAction::AlignEdge { key: *edge_key });
+                if *pan_ms > 0 {
+                    actions.push(SimAction::Pan { key: *pan_key, ms: *pan_ms });
+                }
+            }
+            CameraMotion::Drag { strokes } => {
+                for &(from, to) in strokes {
+                    actions.push(SimAction::Drag { from, to });
+                }
+            }
+        }
+    }
+}
+
+/// simulate() 输出的单个动作
+#[derive(Debug, Clone)]
+pub enum SimAction {
+    /// 按住 key 把镜头推到边界
+    AlignEdge { key: char },
+    /// 按住 key 平移 ms 毫秒
+    Pan { key: char, ms: u64 },
+    /// 按住鼠标键拖动镜头
+    Drag { from: (f32, f32), to: (f32, f32) },
+    SwitchPage { page: usize, key: char },
+    Demolish { uid: usize, name: String, screen: (f32, f32) },
+    Place { uid: usize, name: String, key: char, screen: (f32, f32) },
+    Upgrade { name: String, key: char, screen: Option<(f32, f32)> },
+    Ability { key: char, target: Option<[i32; 2]> },
+    /// 前期结束，按 G 开战
+    StartWave,
+}
+
+#[derive(Debug, Clone)]
+pub struct SimStep {
+    pub wave: i32,
+    pub is_late: bool,
+    pub action: SimAction,
+}
+
+impl std::fmt::Display for SimStep {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[W{} {}] ", self.wave, if self.is_late { "后期" } else { "前期" })?;
+        match &self.action {
+            SimAction::AlignEdge { key } => write!(f, "镜头贴边: 按住 '{}' 2500ms", key),
+            SimAction::Pan { key, ms } => write!(f, "镜头平移: 按住 '{}' {}ms", key, ms),
+            SimAction::Drag { from, to } => {
+                write!(f, "镜头拖动: ({:.0}, {:.0}) -> ({:.0}, {:.0})", from.0, from.1, to.0, to.1)
+            }
+            SimAction::SwitchPage { page, key } => write!(f, "装备栏换到第 {} 页: '{}'", page, key),
+            SimAction::Demolish { uid, name, screen } => {
+                write!(f, "拆除 {} (uid {}) @ ({:.0}, {:.0}) + E", name, uid, screen.0, screen.1)
+            }
+            SimAction::Place { uid, name, key, screen } => {
+                write!(f, "放置 {} (uid {}) 键 '{}' @ ({:.0}, {:.0})", name, uid, key, screen.0, screen.1)
+            }
+            SimAction::Upgrade { name, key, screen } => match screen {
+                Some((x, y)) => write!(f, "升级 {}: 点击 ({:.0}, {:.0}) 选中后长按 '{}'", name, x, y, key),
+                None => write!(f, "升级 {}: 长按 '{}'", name, key),
+            },
+            SimAction::Ability { key, target } => match target {
+                Some([x, y]) => write!(f, "技能 '{}' -> 点击 ({}, {})", key, x, y),
+                None => write!(f, "技能 '{}'", key),
+            },
+            SimAction::StartWave => write!(f, "按 G 开战"),
+        }
+    }
+}
+
 // 辅助函数：将字符转换为 HID 键码
 fn get_hid_code(c: char) -> u8 {
     match c.to_ascii_lowercase() {
@@ -181,23 +557,67 @@ pub struct TowerDefenseApp {
     strategy_buildings: Vec<BuildingExport>,
     strategy_upgrades: Vec<UpgradeEvent>,
     strategy_demolishes: Vec<DemolishEvent>,
+    strategy_abilities: Vec<AbilityEvent>,
+    strategy_emergency: EmergencyPlan,
+    /// 上次触发应急预案的时间
+    last_emergency: Option<Instant>,
 
     placed_uids: HashSet<usize>,
     completed_upgrade_keys: HashSet<String>,
+    completed_abilities: HashSet<usize>,
     completed_demolish_uids: HashSet<usize>,
 
+    /// 放置失败的阶段计数；达到 place_max_failures 后移入 skipped_uids
+    place_failures: HashMap<usize, u32>,
+    skipped_uids: HashSet<usize>,
+    /// 实际放在备选格子的建造 (拆除时按新位置定位)
+    relocated: HashMap<usize, [usize; 2]>,
+
     last_confirmed_wave: i32,
     last_wave_change_time: Instant,
+    /// 卡住检测：波次推进时喂狗
+    watchdog: Watchdog,
 
     trap_lookup: HashMap<String, TrapConfigItem>,
     active_loadout: Vec<String>,
+    /// 运行配置 (--profile) 指定的固定装备栏与界面参数覆盖
+    profile: ProfileConfig,
+    /// 装备栏当前所在页
+    current_page: usize,
 
     camera_offset_y: f32,
+    camera_offset_x: f32,
     move_speed: f32,
+
+    digit_templates: Option<Arc<DigitTemplates>>,
+
+    /// 最近一次识别的金币余额 (建造后扣减，不足时重新识别)
+    gold: Option<u32>,
+
+    /// 暂停 / 停止信号；所有等待都经由它，可被随时打断
+    control: RunControl,
+
+    /// 当前地图名与 (地形, 策略, 陷阱) 文件路径，写入进度文件供恢复
+    map_name: String,
+    paths: (String, String, String),
+
+    /// 本局结果，monitor_waves 识别到结算界面时写入
+    outcome: RunOutcome,
+
+    /// 本局动作日志 (journal_dir 为空时不记录) 与当前所处阶段
+    journal: Option<Journal>,
+    current_late: bool,
 }
 
 impl TowerDefenseApp {
     pub fn new(driver: Arc<Mutex<HumanDriver>>, nav: Arc<NavEngine>) -> Self {
+        // 有校准结果时用实测的镜头平移速度
+        let move_speed = driver
+            .lock()
+            .ok()
+            .and_then(|d| d.calibration)
+            .map(|c| c.pan_px_per_ms * 1000.0)
+            .unwrap_or(300.0);
         Self {
             driver,
             nav,
@@ -206,95 +626,179 @@ impl TowerDefenseApp {
             strategy_buildings: Vec::new(),
             strategy_upgrades: Vec::new(),
             strategy_demolishes: Vec::new(),
+            strategy_abilities: Vec::new(),
+            strategy_emergency: EmergencyPlan::default(),
+            last_emergency: None,
             placed_uids: HashSet::new(),
             completed_upgrade_keys: HashSet::new(),
+            completed_abilities: HashSet::new(),
             completed_demolish_uids: HashSet::new(),
+            place_failures: HashMap::new(),
+            skipped_uids: HashSet::new(),
+            relocated: HashMap::new(),
             last_confirmed_wave: 0,
             last_wave_change_time: Instant::now(),
+            watchdog: Watchdog::default(),
             trap_lookup: HashMap::new(),
             active_loadout: Vec::new(),
+            profile: ProfileConfig::default(),
+            current_page: 0,
             camera_offset_y: 0.0,
-            move_speed: 300.0,
+            camera_offset_x: 0.0,
+            move_speed,
+            digit_templates: None,
+            gold: None,
+            control: RunControl::new(),
+            map_name: String::new(),
+            paths: Default::default(),
+            outcome: RunOutcome::Unknown,
+            journal: None,
+            current_late: false,
+        }
+    }
+
+    pub fn with_control(mut self, control: RunControl) -> Self {
+        self.control = control;
+        self
+    }
+
+    pub fn with_profile(mut self, profile: &ProfileConfig) -> Self {
+        self.profile = profile.clone();
+        self
+    }
+
+    /// 加载界面参数 (见 TDConfig::load)
+    pub fn load_config(&mut self, path: &str, map_name: &str) {
+        self.config = TDConfig::load(path, map_name);
+        self.map_name = map_name.to_string();
+        if let Some((w, h)) = crate::nav::detect_resolution() {
+            if (self.config.screen_width, self.config.screen_height) != (w as f32, h as f32) {
+                warn!(
+                    "⚠️ [塔防] {} 的界面参数按 {}x{} 编写，显示器实际为 {}x{}，请按实际分辨率调整 td_config.toml",
+                    map_name, self.config.screen_width, self.config.screen_height, w, h
+                );
+            }
+        }
+        if let Some(speed) = self.config.move_speed {
+            self.move_speed = speed;
         }
     }
 
     pub fn load_strategy(&mut self, path: &str) {
-        if let Ok(c) = fs::read_to_string(path) {
-            if let Ok(data) = serde_json::from_str::<MapBuildingsExport>(&c) {
+        let data = parse_data_file::<MapBuildingsExport>(path)
+            .and_then(|d| check_strategy_version(d.version).map(|_| d));
+        match data {
+            Ok(data) => {
                 self.strategy_buildings = data.buildings;
                 self.strategy_upgrades = data.upgrades;
                 self.strategy_demolishes = data.demolishes;
-                println!(
-                    "🏗️ 策略加载成功: 建{} | 升{} | 拆{}",
+                self.strategy_abilities = data.abilities;
+                self.strategy_emergency = data.emergency;
+                info!(
+                    "🏗️ 策略加载成功 (v{}): 建{} | 升{} | 拆{} | 技能{}",
+                    data.version,
                     self.strategy_buildings.len(),
                     self.strategy_upgrades.len(),
-                    self.strategy_demolishes.len()
+                    self.strategy_demolishes.len(),
+                    self.strategy_abilities.len()
                 );
-            } else {
-                println!("❌ 策略 JSON 解析失败");
+                if let Some(notes) = &data.notes {
+                    info!("   📝 {}", notes);
+                }
+                for b in &self.strategy_buildings {
+                    if let Some(Err(e)) = b.only_if.as_deref().map(|c| check_only_if(c, 0, Some(0))) {
+                        warn!("⚠️ [策略] {} (uid {}) 的 only_if 无效，将永不建造: {}", b.name, b.uid, e);
+                    }
+                }
             }
+            Err(e) => error!("❌ 策略加载失败: {}", e),
         }
     }
 
     pub fn recognize_wave_status(&self, rect: [i32; 4], use_tab: bool) -> Option<WaveStatus> {
-        const KEY_TAB: u8 = 0x2B;
-        if use_tab {
-            if let Ok(driver) = self.driver.lock() {
-                if let Ok(mut dev) = driver.device.lock() {
-                    dev.key_down(KEY_TAB, 0);
-                }
-            }
-            thread::sleep(Duration::from_millis(500));
+        self.wave_probe().recognize_wave_status(rect, use_tab)
+    }
+
+    /// 复制识别波次所需的状态 (供监控线程使用)
+    fn wave_probe(&self) -> WaveProbe {
+        WaveProbe {
+            driver: Arc::clone(&self.driver),
+            nav: Arc::clone(&self.nav),
+            control: self.control.clone(),
+            config: self.config.clone(),
+            digit_templates: self.digit_templates.clone(),
         }
+    }
 
-        let text: String = self.nav.ocr_area(rect);
+    /// OCR 识别当前金币；未配置 gold_rect 或识别失败时返回 None
+    fn recognize_gold(&self) -> Option<u32> {
+        let rect = self.config.gold_rect?;
+        let text = self.nav.ocr_area(rect);
+        let re = Regex::new(&self.config.gold_regex).ok()?;
+        let raw = re.captures(&text)?.get(1)?.as_str().to_string();
+        let digits: String = raw.chars().filter(|c| c.is_ascii_digit()).collect();
+        digits.parse().ok()
+    }
 
-        if use_tab {
-            if let Ok(driver) = self.driver.lock() {
-                if let Ok(mut dev) = driver.device.lock() {
-                    dev.key_up();
+    /// 开战后等待进入后期：配置了 wave_timer_rect 时读倒计时，剩余不超过 late_phase_remaining_s 秒再返回；
+    /// 未配置时沿用固定 1 秒
+    fn wait_for_late_phase(&self) {
+        let Some(rect) = self.config.wave_timer_rect else {
+            self.control.sleep(Duration::from_secs(1));
+            return;
+        };
+        let started = Instant::now();
+        let max_wait = Duration::from_millis(self.config.late_phase_max_wait_ms);
+        let mut misses = 0;
+        loop {
+            match parse_countdown(&self.nav.ocr_area(rect)) {
+                Some(left) if left <= self.config.late_phase_remaining_s => {
+                    info!("⏱️ [倒计时] 剩余 {}s，进入后期", left);
+                    return;
                 }
-            }
-            thread::sleep(Duration::from_millis(500));
-            if let Ok(driver) = self.driver.lock() {
-                if let Ok(mut dev) = driver.device.lock() {
-                    dev.key_down(KEY_TAB, 0);
+                Some(_) => misses = 0,
+                None => {
+                    misses += 1;
+                    // 连续读不到倒计时 (区域配置错误或该波无倒计时) 时不再空等
+                    if misses >= 5 {
+                        warn!("⚠️ [倒计时] 连续 {} 次无法识别，直接进入后期", misses);
+                        return;
+                    }
                 }
             }
-            thread::sleep(Duration::from_millis(100));
-            if let Ok(driver) = self.driver.lock() {
-                if let Ok(mut dev) = driver.device.lock() {
-                    dev.key_up();
-                }
+            if started.elapsed() >= max_wait {
+                info!("⏭️ [倒计时] 等待超时，直接进入后期");
+                return;
+            }
+            if !self.control.sleep(Duration::from_millis(self.config.wave_timer_poll_ms)) {
+                return;
             }
         }
+    }
 
-        if text.is_empty() {
-            return None;
+    /// 等到余额足够建造 name；未启用金币检查或价格为 0 时直接通过
+    fn wait_until_affordable(&mut self, name: &str) -> bool {
+        let cost = self.trap_lookup.get(name).map(|t| t.cost).unwrap_or(0);
+        if cost == 0 || self.config.gold_rect.is_none() {
+            return true;
+        }
+        if self.gold.is_some_and(|g| g >= cost) {
+            return true;
+        }
+        let started = Instant::now();
+        loop {
+            self.gold = self.recognize_gold();
+            match self.gold {
+                Some(g) if g >= cost => return true,
+                Some(g) => info!("💰 [金币] 余额 {} 不足以建造 {} ({})，等待中...", g, name, cost),
+                None => warn!("⚠️ [金币] 无法识别余额，稍后重试"),
+            }
+            if self.control.is_stopped() || started.elapsed() >= Duration::from_millis(self.config.afford_max_wait_ms) {
+                info!("⏭️ [金币] 等待超时，{} 顺延到下一阶段", name);
+                return false;
+            }
+            self.control.sleep(Duration::from_millis(self.config.afford_retry_ms));
         }
-
-        println!(
-            "🔍 [OCR Debug] 原始文本: 「{}」 (Mode: {})",
-            text.trim(),
-            if use_tab { "TAB" } else { "HUD" }
-        );
-
-        let val = if use_tab {
-            let re = Regex::new(r"(\d+)[/\dSI日]+.*波次").ok()?;
-            re.captures(&text).and_then(|caps| {
-                let num = caps.get(1)?.as_str().parse::<i32>().ok()?;
-                println!("✅ [OCR Match] TAB 模式匹配成功: 第 {} 波", num);
-                Some(num)
-            })?
-        } else {
-            let re = Regex::new(r"波次\s*(\d+)").ok()?;
-            re.captures(&text).and_then(|caps| {
-                let num = caps.get(1)?.as_str().parse::<i32>().ok()?;
-                println!("✅ [OCR Match] HUD 模式匹配成功: 第 {} 波", num);
-                Some(num)
-            })?
-        };
-        Some(WaveStatus { current_wave: val })
     }
 
     fn validate_wave_transition(&mut self, detected_wave: i32) -> bool {
@@ -303,12 +807,14 @@ impl TowerDefenseApp {
         let is_next_wave = detected_wave == self.last_confirmed_wave + 1;
         let is_long_enough = elapsed >= 60 || self.last_confirmed_wave == 0;
         if is_next_wave && is_long_enough {
-            println!(
+            info!(
                 "✅ [Monitor] 新波次: {} -> {}",
                 self.last_confirmed_wave, detected_wave
             );
             self.last_confirmed_wave = detected_wave;
             self.last_wave_change_time = now;
+            self.watchdog.feed();
+            metrics::global().wave_transitions.inc();
             true
         } else {
             false
@@ -316,37 +822,168 @@ impl TowerDefenseApp {
     }
 
     fn are_tasks_in_current_view(&self, tasks: &[ScheduledTask]) -> bool {
-        let [_, sz_y1, _, sz_y2] = self.config.safe_zone;
+        let [sz_x1, sz_y1, sz_x2, sz_y2] = self.config.safe_zone;
         let view_top = self.camera_offset_y;
         let safe_map_top = view_top + sz_y1 as f32;
         let safe_map_bottom = view_top + sz_y2 as f32;
+        let safe_map_left = self.camera_offset_x + sz_x1 as f32;
+        let safe_map_right = self.camera_offset_x + sz_x2 as f32;
 
         for task in tasks {
             if task.map_y < safe_map_top || task.map_y > safe_map_bottom {
                 return false;
             }
+            // 未指定 uid 的升级任务没有坐标 (map_x 为 0)，不参与横向判断
+            if matches!(&task.action, TaskAction::Upgrade(u) if u.uid.is_none()) {
+                continue;
+            }
+            if task.map_x < safe_map_left || task.map_x > safe_map_right {
+                return false;
+            }
         }
         true
     }
 
     pub fn execute_wave_phase(&mut self, wave: i32, is_late: bool) {
         let phase_name = if is_late { "后期" } else { "前期" };
-        println!(
+        info!(
             "🚀 优化执行第 {} 波 [{}] (拆除优先模式)...",
             wave, phase_name
         );
 
+        self.current_late = is_late;
+        let (demolish_tasks, groups) = self.plan_wave_phase(wave, is_late);
+
+        if !demolish_tasks.is_empty() {
+            info!(
+                "🧹 [Step 1] 正在执行全图拆除任务 ({}个)...",
+                demolish_tasks.len()
+            );
+            self.dispatch_tasks_by_region(demolish_tasks);
+        }
+
+        let multi = groups.len() > 1;
+        for (prio, tasks) in groups {
+            if self.control.is_stopped() {
+                return;
+            }
+            self.check_base_health();
+            if multi {
+                info!("🏗️ [Step 2] 优先级 {} 的建造与升级任务 ({}个)...", prio, tasks.len());
+            } else {
+                info!("🏗️ [Step 2] 正在执行建造与升级任务 ({}个)...", tasks.len());
+            }
+            self.dispatch_tasks_by_region(tasks);
+        }
+
+        self.execute_abilities(wave, is_late);
+    }
+
+    /// 本阶段待释放的技能 (策略下标, 技能)
+    fn due_abilities(&self, wave: i32, is_late: bool) -> Vec<(usize, AbilityEvent)> {
+        self.strategy_abilities
+            .iter()
+            .enumerate()
+            .filter(|(i, a)| a.wave_num == wave && a.is_late == is_late && !self.completed_abilities.contains(i))
+            .map(|(i, a)| (i, a.clone()))
+            .collect()
+    }
+
+    fn execute_abilities(&mut self, wave: i32, is_late: bool) {
+        let due = self.due_abilities(wave, is_late);
+        if due.is_empty() {
+            return;
+        }
+        info!("✨ [Step 3] 释放技能 ({}个)...", due.len());
+        for (idx, a) in due {
+            if !self.control.wait_if_paused() {
+                return;
+            }
+            self.use_ability(&a);
+            self.completed_abilities.insert(idx);
+        }
+    }
+
+    fn use_ability(&mut self, a: &AbilityEvent) {
+        if let Ok(mut d) = self.driver.lock() {
+            info!("   -> 按 '{}'{}", a.key, a.target.map(|[x, y]| format!(" 并点击 ({}, {})", x, y)).unwrap_or_default());
+            d.key_click(a.key);
+            if let Some([x, y]) = a.target {
+                self.control.sleep(Duration::from_millis(200));
+                d.move_to_humanly(x as u16, y as u16, 0.3);
+                d.click_humanly(true, false, 0);
+            }
+        }
+        let mut entry = self.journal_entry("ability", a.notes.as_deref().unwrap_or(""));
+        entry.screen = a.target.map(|[x, y]| [x as f32, y as f32]);
+        entry.key = Some(a.key);
+        self.record(entry, None);
+        self.control.sleep(Duration::from_millis(300));
+    }
+
+    /// 识别基地血量百分比；未配置 base_hp_rect 或识别失败时返回 None
+    fn recognize_base_hp(&self) -> Option<u32> {
+        let rect = self.config.base_hp_rect?;
+        parse_hp_percent(&self.nav.ocr_area(rect))
+    }
+
+    /// 基地血量低于阈值时执行应急预案：先放应急技能，再补建 emergency 中尚未放置的建造。
+    /// 两次触发之间至少间隔 emergency_cooldown_ms；触发时返回 true
+    fn check_base_health(&mut self) -> bool {
+        if self.strategy_emergency.is_empty() {
+            return false;
+        }
+        let cooling = self
+            .last_emergency
+            .is_some_and(|t| t.elapsed() < Duration::from_millis(self.config.emergency_cooldown_ms));
+        if cooling {
+            return false;
+        }
+        let Some(hp) = self.recognize_base_hp() else { return false };
+        if hp >= self.config.base_hp_threshold {
+            return false;
+        }
+
+        info!("🚨 [应急] 基地血量 {}% 低于 {}%，执行应急预案", hp, self.config.base_hp_threshold);
+        self.last_emergency = Some(Instant::now());
+        for a in self.strategy_emergency.abilities.clone() {
+            if self.control.is_stopped() {
+                return true;
+            }
+            self.use_ability(&a);
+        }
+
+        let tasks: Vec<ScheduledTask> = self
+            .strategy_emergency
+            .buildings
+            .iter()
+            .filter(|b| !self.placed_uids.contains(&b.uid) && !self.skipped_uids.contains(&b.uid))
+            .filter_map(|b| {
+                let (px, py) = self.get_absolute_map_pixel(b.grid_x, b.grid_y, b.width, b.height)?;
+                Some(ScheduledTask { action: TaskAction::Place(b.clone()), map_y: py, map_x: px, priority: 1 })
+            })
+            .collect();
+        if !tasks.is_empty() {
+            info!("🚨 [应急] 补建后备建造 ({}个)...", tasks.len());
+            self.dispatch_tasks_by_region(tasks);
+        }
+        true
+    }
+
+    /// 生成本阶段的 (拆除任务, 按优先级从高到低分组的建造与升级任务)
+    fn plan_wave_phase(&mut self, wave: i32, is_late: bool) -> (Vec<ScheduledTask>, Vec<(i32, Vec<ScheduledTask>)>) {
         let mut demolish_tasks = Vec::new();
-        let mut build_upgrade_tasks = Vec::new();
+        // 按策略 priority 分组，高优先级的组先整体执行
+        let mut build_groups: BTreeMap<i32, Vec<ScheduledTask>> = BTreeMap::new();
+        let mut upgrade_tasks = Vec::new();
 
         for d in self.strategy_demolishes.iter().filter(|d| {
             d.wave_num == wave
                 && d.is_late == is_late
                 && !self.completed_demolish_uids.contains(&d.uid)
         }) {
-            if let Some((px, py)) =
-                self.get_absolute_map_pixel(d.grid_x, d.grid_y, d.width, d.height)
-            {
+            let [gx, gy] = self.relocated.get(&d.uid).copied().unwrap_or([d.grid_x, d.grid_y]);
+            if let Some((px, py)) = self.get_absolute_map_pixel(gx, gy, d.width, d.height) {
                 demolish_tasks.push(ScheduledTask {
                     action: TaskAction::Demolish(d.clone()),
                     map_y: py,
@@ -356,13 +993,33 @@ impl TowerDefenseApp {
             }
         }
 
-        for b in self.strategy_buildings.iter().filter(|b| {
-            b.wave_num == wave && b.is_late == is_late && !self.placed_uids.contains(&b.uid)
-        }) {
+        // 之前阶段因金币不足 / 条件未满足而顺延的建造一并纳入
+        let due: Vec<&BuildingExport> = self
+            .strategy_buildings
+            .iter()
+            .filter(|b| {
+                let due = b.wave_num < wave || (b.wave_num == wave && (is_late || !b.is_late));
+                due && !self.placed_uids.contains(&b.uid) && !self.skipped_uids.contains(&b.uid)
+            })
+            .collect();
+        // 有条件引用金币时先刷新一次余额
+        let needs_gold = due
+            .iter()
+            .any(|b| b.only_if.as_deref().is_some_and(|c| c.contains("resource") || c.contains("gold")));
+        if needs_gold && self.config.gold_rect.is_some() {
+            self.gold = self.recognize_gold();
+        }
+        for b in due {
+            if let Some(cond) = &b.only_if {
+                if !check_only_if(cond, wave, self.gold).unwrap_or(false) {
+                    info!("⏸️ [策略] {} (uid {}) 条件 \"{}\" 未满足，顺延", b.name, b.uid, cond);
+                    continue;
+                }
+            }
             if let Some((px, py)) =
                 self.get_absolute_map_pixel(b.grid_x, b.grid_y, b.width, b.height)
             {
-                build_upgrade_tasks.push(ScheduledTask {
+                build_groups.entry(b.priority).or_default().push(ScheduledTask {
                     action: TaskAction::Place(b.clone()),
                     map_y: py,
                     map_x: px,
@@ -376,40 +1033,32 @@ impl TowerDefenseApp {
             .iter()
             .filter(|u| u.wave_num == wave && u.is_late == is_late)
         {
-            let key = format!("{}-{}-{}", u.building_name, u.wave_num, u.is_late);
-            if !self.completed_upgrade_keys.contains(&key) {
-                build_upgrade_tasks.push(ScheduledTask {
+            if !self.completed_upgrade_keys.contains(&upgrade_done_key(u)) {
+                let (map_x, map_y) = self.upgrade_target(u).unwrap_or((0.0, 0.0));
+                upgrade_tasks.push(ScheduledTask {
                     action: TaskAction::Upgrade(u.clone()),
-                    map_y: 0.0,
-                    map_x: 0.0,
+                    map_y,
+                    map_x,
                     priority: 2,
                 });
             }
         }
 
-        if demolish_tasks.is_empty() && build_upgrade_tasks.is_empty() {
-            return;
-        }
-
-        if !demolish_tasks.is_empty() {
-            println!(
-                "🧹 [Step 1] 正在执行全图拆除任务 ({}个)...",
-                demolish_tasks.len()
-            );
-            self.dispatch_tasks_by_region(demolish_tasks);
+        // 升级跟随最低优先级的一组 (未使用 priority 时只有一组，与旧版行为一致)
+        let mut groups: Vec<(i32, Vec<ScheduledTask>)> = build_groups.into_iter().rev().collect();
+        match groups.last_mut() {
+            Some((_, last)) => last.append(&mut upgrade_tasks),
+            None if !upgrade_tasks.is_empty() => groups.push((0, upgrade_tasks)),
+            None => {}
         }
-
-        if !build_upgrade_tasks.is_empty() {
-            println!(
-                "🏗️ [Step 2] 正在执行建造与升级任务 ({}个)...",
-                build_upgrade_tasks.len()
-            );
-            build_upgrade_tasks.sort_by(|a, b| a.priority.cmp(&b.priority));
-            self.dispatch_tasks_by_region(build_upgrade_tasks);
+        for (_, tasks) in groups.iter_mut() {
+            tasks.sort_by_key(|t| t.priority);
         }
+        (demolish_tasks, groups)
     }
 
-    fn dispatch_tasks_by_region(&mut self, tasks: Vec<ScheduledTask>) {
+    /// 按地图上下半区拆分任务：上半区从上往下、下半区从下往上，返回 (是否上半区, 任务) 且不含空组
+    fn split_by_region(&self, tasks: Vec<ScheduledTask>) -> Vec<(bool, Vec<ScheduledTask>)> {
         let meta = self.map_meta.as_ref().unwrap();
         let map_h = meta.bottom;
         let screen_h = self.config.screen_height;
@@ -419,69 +1068,325 @@ impl TowerDefenseApp {
             .into_iter()
             .partition(|t| t.map_y <= mid_point + screen_h / 2.0);
 
-        if !upper.is_empty() {
-            upper.sort_by(|a, b| {
-                a.map_y
-                    .partial_cmp(&b.map_y)
-                    .unwrap()
-                    .then(a.priority.cmp(&b.priority))
-            });
-            if self.are_tasks_in_current_view(&upper) {
-                println!("✨ 上半区任务在视野内，直接执行");
-                self.process_task_batch(upper, false);
-            } else {
-                self.align_camera_to_edge(true);
-                self.process_task_batch(upper, true);
-            }
-        }
+        upper.sort_by(|a, b| {
+            a.map_y
+                .partial_cmp(&b.map_y)
+                .unwrap()
+                .then(a.priority.cmp(&b.priority))
+        });
+        lower.sort_by(|a, b| {
+            b.map_y
+                .partial_cmp(&a.map_y)
+                .unwrap()
+                .then(a.priority.cmp(&b.priority))
+        });
+        [(true, upper), (false, lower)]
+            .into_iter()
+            .filter(|(_, t)| !t.is_empty())
+            .collect()
+    }
 
-        if !lower.is_empty() {
-            lower.sort_by(|a, b| {
-                b.map_y
-                    .partial_cmp(&a.map_y)
-                    .unwrap()
-                    .then(a.priority.cmp(&b.priority))
-            });
-            if self.are_tasks_in_current_view(&lower) {
-                println!("✨ 下半区任务在视野内，直接执行");
-                self.process_task_batch(lower, false);
+    fn dispatch_tasks_by_region(&mut self, tasks: Vec<ScheduledTask>) {
+        for (top, batch) in self.split_by_region(tasks) {
+            if self.control.is_stopped() {
+                return;
+            }
+            if self.are_tasks_in_current_view(&batch) {
+                info!("✨ {}半区任务在视野内，直接执行", if top { "上" } else { "下" });
+                self.process_task_batch(batch, false);
             } else {
-                self.align_camera_to_edge(false);
-                self.process_task_batch(lower, true);
+                self.align_camera_to_edge(top);
+                self.process_task_batch(batch, true);
             }
         }
     }
 
-    fn process_task_batch(&mut self, tasks: Vec<ScheduledTask>, force_initial_refresh: bool) {
-        let mut last_build_key: Option<char> = None;
-        let mut is_first_task = true;
-
-        for task in tasks {
-            if let TaskAction::Upgrade(u) = &task.action {
-                self.execute_single_upgrade(u);
-                continue;
-            }
+    /// 离线模拟整份策略：逐波计算镜头移动、换页、按键与屏幕坐标，不发送任何输入。
+    /// 会改写本实例的进度状态，请在单独创建的实例上调用；模拟时假定金币充足、每次放置都成功
+    pub fn simulate(&mut self, terrain_p: &str, strategy_p: &str, trap_p: &str) -> Vec<SimStep> {
+        self.config.journal_dir.clear();
+        self.prepare(terrain_p, strategy_p, trap_p);
+        if self.map_meta.is_none() {
+            error!("❌ [模拟] 地形文件加载失败: {}", terrain_p);
+            return Vec::new();
+        }
+        self.config.gold_rect = None;
+        self.gold = Some(u32::MAX);
+        self.camera_offset_x = 0.0;
+        self.camera_offset_y = 0.0;
+        self.current_page = 0;
 
-            let mut screen_moved = self.smart_move_camera(task.map_y);
-            if is_first_task && force_initial_refresh {
-                screen_moved = true;
-                is_first_task = false;
-            }
+        let max_wave = self
+            .strategy_buildings
+            .iter()
+            .map(|b| b.wave_num)
+            .chain(self.strategy_upgrades.iter().map(|u| u.wave_num))
+            .chain(self.strategy_demolishes.iter().map(|d| d.wave_num))
+            .chain(self.strategy_abilities.iter().map(|a| a.wave_num))
+            .max()
+            .unwrap_or(0);
 
-            match &task.action {
-                TaskAction::Demolish(d) => {
-                    self.perform_demolish_action(task.map_x, task.map_y, d.uid)
+        let mut steps = Vec::new();
+        for wave in 1..=max_wave {
+            for is_late in [false, true] {
+                let push = |steps: &mut Vec<SimStep>, action| steps.push(SimStep { wave, is_late, action });
+                if is_late {
+                    push(&mut steps, SimAction::StartWave);
                 }
-                TaskAction::Place(b) => self.perform_build_action(
-                    &mut last_build_key,
-                    screen_moved,
-                    task.map_x,
-                    task.map_y,
-                    &b.name,
-                    b.uid,
-                ),
-                _ => {}
-            }
+                let (demolish_tasks, groups) = self.plan_wave_phase(wave, is_late);
+                let batches = std::iter::once(demolish_tasks).chain(groups.into_iter().map(|(_, t)| t));
+                for tasks in batches {
+                    for (top, batch) in self.split_by_region(tasks) {
+                        if !self.are_tasks_in_current_view(&batch) {
+                            push(&mut steps, SimAction::AlignEdge { key: if top { 'w' } else { 's' } });
+                            let max_scroll_y = (self.map_meta.as_ref().unwrap().bottom - self.config.screen_height).max(0.0);
+                            self.camera_offset_y = if top { 0.0 } else { max_scroll_y };
+                        }
+                        for task in batch {
+                            for action in self.simulate_task(&task) {
+                                push(&mut steps, action);
+                            }
+                        }
+                    }
+                }
+                for (idx, a) in self.due_abilities(wave, is_late) {
+                    push(&mut steps, SimAction::Ability { key: a.key, target: a.target });
+                    self.completed_abilities.insert(idx);
+                }
+            }
+        }
+
+        info!("🧪 [模拟] {} 共 {} 波，{} 个动作:", strategy_p, max_wave, steps.len());
+        for s in &steps {
+            info!("  {}", s);
+        }
+        let unplaced: Vec<_> = self
+            .strategy_buildings
+            .iter()
+            .filter(|b| !self.placed_uids.contains(&b.uid))
+            .map(|b| format!("{} (uid {})", b.name, b.uid))
+            .collect();
+        if !unplaced.is_empty() {
+            warn!("⚠️ [模拟] 以下建造始终未执行 (条件不满足或坐标无效): {}", unplaced.join(", "));
+        }
+        steps
+    }
+
+    /// 模拟单个任务，按与 process_task_batch 相同的顺序更新镜头与装备栏状态
+    fn simulate_task(&mut self, task: &ScheduledTask) -> Vec<SimAction> {
+        let mut actions = Vec::new();
+        let switch_page = |app: &mut Self, page: usize, actions: &mut Vec<SimAction>| {
+            if page != app.current_page {
+                if let Some(&key) = app.config.page_keys.get(page) {
+                    actions.push(SimAction::SwitchPage { page, key });
+                    app.current_page = page;
+                }
+            }
+        };
+
+        if let TaskAction::Upgrade(u) = &task.action {
+            if self.upgrade_target(u).is_none() {
+                let (key, page) = self.trap_binding(&u.building_name);
+                switch_page(self, page, &mut actions);
+                actions.push(SimAction::Upgrade { name: u.building_name.clone(), key, screen: None });
+                self.completed_upgrade_keys.insert(upgrade_done_key(u));
+                return actions;
+            }
+        }
+
+        if let Some(plan) = self.plan_camera_y(task.map_y) {
+            plan.push_sim_actions(&mut actions);
+            self.camera_offset_y = plan.offset;
+        }
+        if let Some(plan) = self.plan_camera_x(task.map_x) {
+            plan.push_sim_actions(&mut actions);
+            self.camera_offset_x = plan.offset;
+        }
+
+        let [sz_x1, sz_y1, sz_x2, sz_y2] = self.config.safe_zone;
+        let screen = (
+            (task.map_x - self.camera_offset_x).clamp(sz_x1 as f32, sz_x2 as f32),
+            (task.map_y - self.camera_offset_y).clamp(sz_y1 as f32, sz_y2 as f32),
+        );
+        match &task.action {
+            TaskAction::Demolish(d) => {
+                actions.push(SimAction::Demolish { uid: d.uid, name: d.name.clone(), screen });
+                self.completed_demolish_uids.insert(d.uid);
+            }
+            TaskAction::Place(b) => {
+                let (key, page) = self.trap_binding(&b.name);
+                switch_page(self, page, &mut actions);
+                actions.push(SimAction::Place { uid: b.uid, name: b.name.clone(), key, screen });
+                self.placed_uids.insert(b.uid);
+            }
+            TaskAction::Upgrade(u) => {
+                let (key, page) = self.trap_binding(&u.building_name);
+                switch_page(self, page, &mut actions);
+                actions.push(SimAction::Upgrade { name: u.building_name.clone(), key, screen: Some(screen) });
+                self.completed_upgrade_keys.insert(upgrade_done_key(u));
+            }
+        }
+        actions
+    }
+
+    fn process_task_batch(&mut self, tasks: Vec<ScheduledTask>, force_initial_refresh: bool) {
+        let mut last_build_key: Option<char> = None;
+        let mut is_first_task = true;
+
+        for task in tasks {
+            if !self.control.wait_if_paused() {
+                return;
+            }
+            if let TaskAction::Upgrade(u) = &task.action {
+                if self.upgrade_target(u).is_none() {
+                    self.execute_single_upgrade(u, None);
+                    continue;
+                }
+            }
+
+            let mut screen_moved = self.smart_move_camera(task.map_y);
+            screen_moved |= self.smart_move_camera_x(task.map_x);
+            if is_first_task && force_initial_refresh {
+                screen_moved = true;
+                is_first_task = false;
+            }
+
+            match &task.action {
+                TaskAction::Demolish(d) => {
+                    self.perform_demolish_action(task.map_x, task.map_y, d.uid)
+                }
+                TaskAction::Place(b) => {
+                    let mut affordable = self.wait_until_affordable(&b.name);
+                    if !affordable && self.sell_to_afford(b) {
+                        affordable = true;
+                        // 卖塔时镜头可能已移走，回到目标并重新切换陷阱
+                        screen_moved |= self.smart_move_camera(task.map_y);
+                        screen_moved |= self.smart_move_camera_x(task.map_x);
+                        last_build_key = None;
+                    }
+                    if !affordable {
+                        continue;
+                    }
+                    let placed = self.perform_build_action(
+                        &mut last_build_key,
+                        screen_moved,
+                        task.map_x,
+                        task.map_y,
+                        &b.name,
+                        b.uid,
+                    );
+                    if !placed && !self.place_at_alternates(b, &mut last_build_key) {
+                        // 状态不确定，下一座塔重新切换陷阱
+                        last_build_key = None;
+                        self.record_place_failure(b);
+                        continue;
+                    }
+                    let cost = self.trap_lookup.get(&b.name).map(|t| t.cost).unwrap_or(0);
+                    self.gold = self.gold.map(|g| g.saturating_sub(cost));
+                }
+                TaskAction::Upgrade(u) => {
+                    self.execute_single_upgrade(u, Some((task.map_x, task.map_y)));
+                    // 点选塔会打断放置状态，下一座塔重新切换陷阱
+                    last_build_key = None;
+                }
+            }
+        }
+    }
+
+    /// 出售一座建造预计返还的金币
+    fn refund_of(&self, name: &str) -> u32 {
+        match self.trap_lookup.get(name) {
+            Some(t) if t.refund > 0 => t.refund,
+            Some(t) => (t.cost as f32 * self.config.sell_refund_ratio) as u32,
+            None => 0,
+        }
+    }
+
+    /// 余额不足时卖掉已放置的 sellable 建造 (返还多的优先)，凑够后确认余额
+    fn sell_to_afford(&mut self, target: &BuildingExport) -> bool {
+        if !self.config.sell_to_afford {
+            return false;
+        }
+        let cost = self.trap_lookup.get(&target.name).map(|t| t.cost).unwrap_or(0);
+        let Some(gold) = self.gold else { return false };
+        let deficit = cost.saturating_sub(gold);
+
+        let mut candidates: Vec<(BuildingExport, u32)> = self
+            .strategy_buildings
+            .iter()
+            .filter(|b| {
+                // 不卖比目标优先级更高的建造
+                b.sellable
+                    && b.priority <= target.priority
+                    && b.uid != target.uid
+                    && self.placed_uids.contains(&b.uid)
+                    && !self.completed_demolish_uids.contains(&b.uid)
+            })
+            .map(|b| (b.clone(), self.refund_of(&b.name)))
+            .filter(|(_, refund)| *refund > 0)
+            .collect();
+        candidates.sort_by_key(|c| std::cmp::Reverse(c.1));
+
+        let mut chosen = Vec::new();
+        let mut reclaimed = 0;
+        for (b, refund) in candidates {
+            if reclaimed >= deficit {
+                break;
+            }
+            reclaimed += refund;
+            chosen.push(b);
+        }
+        if reclaimed < deficit {
+            info!("💸 [卖塔] 可出售建造共返还 {}，不足以补齐 {} 的差额 {}", reclaimed, target.name, deficit);
+            return false;
+        }
+
+        for b in &chosen {
+            if self.control.is_stopped() {
+                return false;
+            }
+            let [gx, gy] = self.relocated.get(&b.uid).copied().unwrap_or([b.grid_x, b.grid_y]);
+            let Some((px, py)) = self.get_absolute_map_pixel(gx, gy, b.width, b.height) else { continue };
+            info!("💸 [卖塔] 出售 {} (uid {}) 为 {} 凑钱", b.name, b.uid, target.name);
+            self.smart_move_camera(py);
+            self.smart_move_camera_x(px);
+            self.perform_demolish_action(px, py, b.uid);
+        }
+
+        // 以实际识别的余额为准
+        self.gold = None;
+        self.wait_until_affordable(&target.name)
+    }
+
+    /// 原位置放置失败后依次尝试备选格子，成功则记录新位置
+    fn place_at_alternates(&mut self, b: &BuildingExport, last_key: &mut Option<char>) -> bool {
+        for &[gx, gy] in &b.alternates {
+            if self.control.is_stopped() {
+                return false;
+            }
+            let Some((px, py)) = self.get_absolute_map_pixel(gx, gy, b.width, b.height) else { continue };
+            info!("↪️ [重规划] {} (uid {}) 改用备选格子 ({}, {})", b.name, b.uid, gx, gy);
+            let mut moved = self.smart_move_camera(py);
+            moved |= self.smart_move_camera_x(px);
+            *last_key = None;
+            if self.perform_build_action(last_key, moved, px, py, &b.name, b.uid) {
+                self.relocated.insert(b.uid, [gx, gy]);
+                return true;
+            }
+        }
+        false
+    }
+
+    /// 累计失败次数，达到上限后跳过该建造，避免每个阶段都在同一 uid 上空转
+    fn record_place_failure(&mut self, b: &BuildingExport) {
+        let count = self.place_failures.entry(b.uid).or_insert(0);
+        *count += 1;
+        if *count >= self.config.place_max_failures {
+            info!(
+                "⏭️ [重规划] {} (uid {}) 连续 {} 个阶段无法放置 (含 {} 个备选格子)，跳过",
+                b.name, b.uid, count, b.alternates.len()
+            );
+            self.skipped_uids.insert(b.uid);
         }
     }
 
@@ -489,35 +1394,47 @@ impl TowerDefenseApp {
 
     fn perform_demolish_action(&mut self, map_x: f32, map_y: f32, uid: usize) {
         let [sz_x1, sz_y1, sz_x2, sz_y2] = self.config.safe_zone;
-        let screen_x = (map_x - 0.0).clamp(sz_x1 as f32, sz_x2 as f32);
+        let screen_x = (map_x - self.camera_offset_x).clamp(sz_x1 as f32, sz_x2 as f32);
         let screen_y = (map_y - self.camera_offset_y).clamp(sz_y1 as f32, sz_y2 as f32);
 
         if let Ok(mut driver) = self.driver.lock() {
             // 1. 移动到位后强制停顿，确保准星彻底对齐格子
             driver.move_to_humanly(screen_x as u16, screen_y as u16, 0.4);
-            thread::sleep(Duration::from_millis(50));
+            self.control.sleep(Duration::from_millis(50));
 
             // 2. 点击选中 (增加 hold 时间到 60ms，防止点击过快游戏未响应)
             driver.click_humanly(true, false, 60); 
             
             // 3. 等待选中框出现的延迟 (从 150ms 增加到 250ms)
-            thread::sleep(Duration::from_millis(150));
+            self.control.sleep(Duration::from_millis(150));
 
             // 4. 🔥 双击 'E' 拆除 (Double Tap)
             // 第一下 E：执行拆除
             driver.key_click('e');
             
             // 间隔 100ms
-            thread::sleep(Duration::from_millis(100));
+            self.control.sleep(Duration::from_millis(100));
             
             // 第二下 E：保险措施 (防止第一下被吞，或者部分陷阱需要二次确认)
             driver.key_click('e');
         }
         
         self.completed_demolish_uids.insert(uid);
+        let name = self
+            .strategy_buildings
+            .iter()
+            .find(|b| b.uid == uid)
+            .map(|b| b.name.clone())
+            .unwrap_or_default();
+        let mut entry = self.journal_entry("demolish", &name);
+        entry.uid = Some(uid);
+        entry.map = Some([map_x, map_y]);
+        entry.screen = Some([screen_x, screen_y]);
+        entry.key = Some('e');
+        self.record(entry, Some(self.cell_region(screen_x, screen_y)));
         
         // 动作后摇 (稍微缩短一点，因为我们已经多按了一次E)
-        thread::sleep(Duration::from_millis(200));
+        self.control.sleep(Duration::from_millis(200));
     }
 
 // src/tower_defense.rs
@@ -530,220 +1447,554 @@ impl TowerDefenseApp {
         map_y: f32,
         name: &str,
         uid: usize,
-    ) {
+    ) -> bool {
         let [sz_x1, sz_y1, sz_x2, sz_y2] = self.config.safe_zone;
-        let screen_x = (map_x - 0.0).clamp(sz_x1 as f32, sz_x2 as f32);
+        let screen_x = (map_x - self.camera_offset_x).clamp(sz_x1 as f32, sz_x2 as f32);
         let screen_y = (map_y - self.camera_offset_y).clamp(sz_y1 as f32, sz_y2 as f32);
-        let key = self.get_trap_key(name);
+        let (key, page) = self.trap_binding(name);
+        // 换页后装备栏状态已变，按首次放置处理
+        let screen_moved = self.switch_page(page) || screen_moved;
+
+        // 放置前先截取目标格子，供放置后比对
+        let region = self.cell_region(screen_x, screen_y);
+        let before = if self.config.verify_placement { self.nav.capture_area(region) } else { None };
 
         if let Ok(mut d) = self.driver.lock() {
             // 1. 移动鼠标
             d.move_to_humanly(screen_x as u16, screen_y as u16, 0.35);
 
             // [稳定性] 移动到位后强制停顿，等待鼠标“落稳”
-            thread::sleep(Duration::from_millis(50));
+            self.control.sleep(Duration::from_millis(50));
 
             // 🔥 [核心修复] 判定条件增加 `last_key.is_none()`
             // 含义：如果是本批次的第一座塔（无论是否移动了视野），或者刚刚移动过视野，
             // 都强制执行“三连击”切枪逻辑，确保陷阱切出率 100%。
             if screen_moved || last_key.is_none() {
-                let swap_key = if key == '4' { '5' } else { '4' };
+                let swap_key = self.config.hotbar_keys.iter().copied().find(|k| *k != key).unwrap_or('4');
                 
                 // 执行：目标键 -> 干扰键 -> 目标键 (强刷状态)
                 d.key_click(key);
-                thread::sleep(Duration::from_millis(120));
+                self.control.sleep(Duration::from_millis(120));
                 d.key_click(swap_key);
-                thread::sleep(Duration::from_millis(120));
+                self.control.sleep(Duration::from_millis(120));
                 d.key_click(key);
 
                 // 等待陷阱虚影完全浮现
-                thread::sleep(Duration::from_millis(250));
+                self.control.sleep(Duration::from_millis(250));
                 *last_key = Some(key);
             } else if Some(key) != *last_key {
                 // 如果不是第一座，且类型变了（原地换塔），则单次按键切换
                 d.key_click(key);
                 *last_key = Some(key);
-                thread::sleep(Duration::from_millis(250));
+                self.control.sleep(Duration::from_millis(250));
             } else {
                 // 同种塔连续放置，仅需微小延迟
-                thread::sleep(Duration::from_millis(50));
+                self.control.sleep(Duration::from_millis(50));
             }
 
             // 执行双击放置
             d.double_click_humanly(true, false, 150);
         }
+
+        if let Some(before) = before {
+            let mut attempt = 0;
+            while !self.verify_placed(region, &before, screen_x, screen_y) {
+                if attempt >= self.config.place_retries {
+                    error!("❌ [放置校验] {} (uid {}) 未能放置，稍后重试", name, uid);
+                    self.control.sleep(Duration::from_millis(250));
+                    let mut entry = self.journal_entry("place", name);
+                    entry.uid = Some(uid);
+                    entry.map = Some([map_x, map_y]);
+                    entry.screen = Some([screen_x, screen_y]);
+                    entry.key = Some(key);
+                    entry.ok = false;
+                    self.record(entry, Some(region));
+                    metrics::global().placements.inc("failed");
+                    return false;
+                }
+                attempt += 1;
+                info!("🔁 [放置校验] {} (uid {}) 未出现，第 {} 次重试", name, uid, attempt);
+                if let Ok(mut d) = self.driver.lock() {
+                    d.key_click(key);
+                    self.control.sleep(Duration::from_millis(250));
+                    d.move_to_humanly(screen_x as u16, screen_y as u16, 0.3);
+                    self.control.sleep(Duration::from_millis(50));
+                    d.double_click_humanly(true, false, 150);
+                }
+            }
+        }
         self.placed_uids.insert(uid);
+        metrics::global().placements.inc("ok");
 
         // 动作后摇
-        thread::sleep(Duration::from_millis(250));
+        self.control.sleep(Duration::from_millis(250));
+        let mut entry = self.journal_entry("place", name);
+        entry.uid = Some(uid);
+        entry.map = Some([map_x, map_y]);
+        entry.screen = Some([screen_x, screen_y]);
+        entry.key = Some(key);
+        self.record(entry, Some(region));
+        true
+    }
+
+    /// 预填当前波次、阶段、镜头与金币的日志条目
+    fn journal_entry(&self, action: &str, name: &str) -> JournalEntry {
+        JournalEntry {
+            ts: String::new(),
+            wave: self.last_confirmed_wave,
+            is_late: self.current_late,
+            action: action.to_string(),
+            uid: None,
+            name: name.to_string(),
+            map: None,
+            screen: None,
+            camera: [self.camera_offset_x, self.camera_offset_y],
+            key: None,
+            gold: self.gold,
+            ok: true,
+            snapshot: None,
+        }
+    }
+
+    /// 写入动作日志；开启 journal_snapshots 时附带 region 截图
+    fn record(&self, entry: JournalEntry, region: Option<[i32; 4]>) {
+        let Some(journal) = &self.journal else { return };
+        let snapshot = region
+            .filter(|_| self.config.journal_snapshots)
+            .and_then(|r| self.nav.capture_area(r));
+        journal.write(entry, snapshot.as_ref());
+    }
+
+    /// 以格子中心为圆心、边长两格的截图区域
+    fn cell_region(&self, screen_x: f32, screen_y: f32) -> [i32; 4] {
+        let half = self.map_meta.as_ref().map(|m| m.grid_pixel_size).unwrap_or(20.0);
+        [
+            (screen_x - half) as i32,
+            (screen_y - half) as i32,
+            (screen_x + half) as i32,
+            (screen_y + half) as i32,
+        ]
+    }
+
+    /// 把光标 (及跟随的陷阱虚影) 移出格子后截图，与放置前比较平均灰度差
+    fn verify_placed(&self, region: [i32; 4], before: &image::RgbaImage, screen_x: f32, screen_y: f32) -> bool {
+        let away = (region[2] - region[0]) as f32 * 2.5;
+        let center_x = self.config.screen_width / 2.0;
+        let away_x = if screen_x < center_x { screen_x + away } else { screen_x - away };
+        if let Ok(mut d) = self.driver.lock() {
+            d.move_to_humanly(away_x as u16, screen_y as u16, 0.15);
+        }
+        self.control.sleep(Duration::from_millis(300));
+
+        let Some(after) = self.nav.capture_area(region) else { return true };
+        match vision::mean_abs_diff(before, &after) {
+            Some(diff) => diff >= self.config.verify_diff_threshold,
+            None => true,
+        }
+    }
+
+    /// 指定 uid 的升级目标在地图上的像素坐标 (优先使用放置时的备选位置)
+    fn upgrade_target(&self, u: &UpgradeEvent) -> Option<(f32, f32)> {
+        let uid = u.uid?;
+        let b = self
+            .strategy_buildings
+            .iter()
+            .chain(self.strategy_emergency.buildings.iter())
+            .find(|b| b.uid == uid)?;
+        let [gx, gy] = self.relocated.get(&uid).copied().unwrap_or([b.grid_x, b.grid_y]);
+        self.get_absolute_map_pixel(gx, gy, b.width, b.height)
     }
 
-    fn execute_single_upgrade(&mut self, u: &UpgradeEvent) {
-        let key = self.get_trap_key(&u.building_name);
+    /// 升级：target 为地图坐标时先点击选中那座塔 (镜头需已移到位)，再长按陷阱键
+    fn execute_single_upgrade(&mut self, u: &UpgradeEvent, target: Option<(f32, f32)>) {
+        let (key, page) = self.trap_binding(&u.building_name);
+        self.switch_page(page);
+        let screen = target.map(|(map_x, map_y)| {
+            let [sz_x1, sz_y1, sz_x2, sz_y2] = self.config.safe_zone;
+            (
+                (map_x - self.camera_offset_x).clamp(sz_x1 as f32, sz_x2 as f32),
+                (map_y - self.camera_offset_y).clamp(sz_y1 as f32, sz_y2 as f32),
+            )
+        });
         if let Ok(mut d) = self.driver.lock() {
-            println!("   -> 长按 '{}' (800ms) 以升级: {}", key, u.building_name);
+            if let Some((sx, sy)) = screen {
+                info!("   -> 点击选中 {} (uid {}) @ ({:.0}, {:.0})", u.building_name, u.uid.unwrap_or(0), sx, sy);
+                d.move_to_humanly(sx as u16, sy as u16, 0.4);
+                self.control.sleep(Duration::from_millis(50));
+                d.click_humanly(true, false, 60);
+                self.control.sleep(Duration::from_millis(150));
+            }
+            info!("   -> 长按 '{}' (1500ms) 以升级: {}", key, u.building_name);
             d.key_hold(key, 1500);
         }
-        let key_str = format!("{}-{}-{}", u.building_name, u.wave_num, u.is_late);
-        self.completed_upgrade_keys.insert(key_str);
-        thread::sleep(Duration::from_millis(400));
+        self.completed_upgrade_keys.insert(upgrade_done_key(u));
+        let mut entry = self.journal_entry("upgrade", &u.building_name);
+        entry.uid = u.uid;
+        entry.map = target.map(|(x, y)| [x, y]);
+        entry.screen = screen.map(|(x, y)| [x, y]);
+        entry.key = Some(key);
+        let region = screen.map(|(x, y)| self.cell_region(x, y));
+        self.record(entry, region);
+        self.control.sleep(Duration::from_millis(400));
     }
 
     fn align_camera_to_edge(&mut self, top: bool) {
         let meta = self.map_meta.as_ref().unwrap();
         let max_scroll_y = (meta.bottom - self.config.screen_height).max(0.0);
 
-        if let Ok(mut human) = self.driver.lock() {
-            let key = if top { 'w' } else { 's' };
-            println!("🔄 强制归零: {}", if top { "顶部" } else { "底部" });
-            human.key_hold(key, 2500);
+        let target = if top { 0.0 } else { max_scroll_y };
+        info!("🔄 强制归零: {}", if top { "顶部" } else { "底部" });
+        match self.config.camera_mode {
+            CameraMode::KeyHold => {
+                if let Ok(mut human) = self.driver.lock() {
+                    human.key_hold(if top { 'w' } else { 's' }, 2500);
+                }
+            }
+            CameraMode::Drag => {
+                // 镜头位置可能已漂移，按整张地图的距离拖过头确保贴边
+                let delta = if top { -max_scroll_y } else { max_scroll_y };
+                let strokes = self.drag_strokes(delta, true);
+                self.drag_camera(&strokes);
+            }
         }
-        self.camera_offset_y = if top { 0.0 } else { max_scroll_y };
-        thread::sleep(Duration::from_millis(500));
+        self.camera_offset_y = target;
+        self.control.sleep(Duration::from_millis(500));
     }
 
-    fn scroll_camera_by_pixels(
-        &self,
-        direction: char,
-        pixels: f32,
-        time_resolution_ms: u64,
-    ) -> f32 {
+    /// 重新测量镜头平移速度，更新本实例与 HumanDriver 并写入 calibration.json
+    pub fn calibrate(&mut self) -> Option<Calibration> {
+        let cal = {
+            let mut human = self.driver.lock().ok()?;
+            let cal = calibration::calibrate(&mut human, 's', 'w', 300, 3)?;
+            human.calibration = Some(cal);
+            cal
+        };
+        self.move_speed = cal.pan_px_per_ms * 1000.0;
+        if let Err(e) = cal.save(calibration::DEFAULT_PATH) {
+            warn!("⚠️ [校准] {}", e);
+        }
+        Some(cal)
+    }
+
+    /// 平移 pixels 像素需要按住的时长 (按 time_resolution_ms 取整)；不足 10 像素时为 0
+    fn pan_ms_for(&self, pixels: f32, time_resolution_ms: u64) -> u64 {
         if pixels < 10.0 {
-            return 0.0;
+            return 0;
         }
         let raw_ms = (pixels / self.move_speed * 1000.0) as u64;
         let units = (raw_ms + time_resolution_ms / 2) / time_resolution_ms;
-        let final_ms = units.max(1) * time_resolution_ms;
-
-        if let Ok(mut human) = self.driver.lock() {
-            human.key_hold(direction, final_ms);
-        }
-        (final_ms as f32 / 1000.0) * self.move_speed
+        units.max(1) * time_resolution_ms
     }
 
-    fn smart_move_camera(&mut self, target_map_y: f32) -> bool {
+    /// 纵向镜头计划；目标已在安全区附近 (偏差 < 90 像素) 时返回 None
+    fn plan_camera_y(&self, target_map_y: f32) -> Option<CameraPlan> {
         let [_, z_y1, _, z_y2] = self.config.safe_zone;
         let screen_h = self.config.screen_height;
         let safe_center_screen_y = (z_y1 + z_y2) as f32 / 2.0;
-        let max_scroll_y = (self.map_meta.as_ref().unwrap().bottom - screen_h).max(0.0);
+        let max_scroll_y = (self.map_meta.as_ref()?.bottom - screen_h).max(0.0);
 
         let ideal_cam_y = (target_map_y - safe_center_screen_y).clamp(0.0, max_scroll_y);
-        let delta = ideal_cam_y - self.camera_offset_y;
+        if (ideal_cam_y - self.camera_offset_y).abs() < 90.0 {
+            return None;
+        }
+        Some(self.plan_move(ideal_cam_y, max_scroll_y, self.camera_offset_y, true))
+    }
 
-        if delta.abs() < 90.0 {
-            return false;
+    /// 横向镜头计划；地图不超过屏幕宽度或偏差 < 90 像素时返回 None
+    fn plan_camera_x(&self, target_map_x: f32) -> Option<CameraPlan> {
+        let [z_x1, _, z_x2, _] = self.config.safe_zone;
+        let screen_w = self.config.screen_width;
+        let max_scroll_x = (self.map_meta.as_ref()?.right - screen_w).max(0.0);
+        if max_scroll_x <= 0.0 {
+            return None;
         }
 
-        let mid_scroll = max_scroll_y / 2.0;
-        const SCROLL_RES: u64 = 100;
+        let safe_center_screen_x = (z_x1 + z_x2) as f32 / 2.0;
+        let ideal_cam_x = (target_map_x - safe_center_screen_x).clamp(0.0, max_scroll_x);
+        if (ideal_cam_x - self.camera_offset_x).abs() < 90.0 {
+            return None;
+        }
+        Some(self.plan_move(ideal_cam_x, max_scroll_x, self.camera_offset_x, false))
+    }
 
-        if ideal_cam_y <= mid_scroll {
-            self.align_camera_to_edge(true);
-            self.camera_offset_y = 0.0;
-            if ideal_cam_y > 10.0 {
-                let moved = self.scroll_camera_by_pixels('s', ideal_cam_y, SCROLL_RES);
-                self.camera_offset_y += moved;
+    /// 按 camera_mode 规划把镜头从 current 移到 ideal 的动作
+    fn plan_move(&self, ideal: f32, max_scroll: f32, current: f32, vertical: bool) -> CameraPlan {
+        let keys = if vertical { ('w', 's') } else { ('a', 'd') };
+        match self.config.camera_mode {
+            CameraMode::KeyHold => self.plan_pan(ideal, max_scroll, keys),
+            CameraMode::Drag => {
+                // 目标在边界上时多拖一段，顺便消除累计误差
+                const EDGE_OVERSHOOT: f32 = 100.0;
+                let mut delta = ideal - current;
+                if ideal <= 0.0 {
+                    delta -= EDGE_OVERSHOOT;
+                } else if ideal >= max_scroll {
+                    delta += EDGE_OVERSHOOT;
+                }
+                CameraPlan {
+                    motion: CameraMotion::Drag { strokes: self.drag_strokes(delta, vertical) },
+                    offset: ideal,
+                }
+            }
+        }
+    }
+
+    /// 先贴到离目标较近的边界 (keys.0 为起点侧)，再朝目标平移
+    fn plan_pan(&self, ideal: f32, max_scroll: f32, keys: (char, char)) -> CameraPlan {
+        const SCROLL_RES: u64 = 100;
+        if ideal <= max_scroll / 2.0 {
+            let pan_ms = if ideal > 10.0 { self.pan_ms_for(ideal, SCROLL_RES) } else { 0 };
+            CameraPlan {
+                motion: CameraMotion::Keys { edge_key: keys.0, pan_key: keys.1, pan_ms },
+                offset: (pan_ms as f32 / 1000.0) * self.move_speed,
             }
         } else {
-            self.align_camera_to_edge(false);
-            self.camera_offset_y = max_scroll_y;
-            let dist_up = max_scroll_y - ideal_cam_y;
-            if dist_up > 10.0 {
-                let moved = self.scroll_camera_by_pixels('w', dist_up, SCROLL_RES);
-                self.camera_offset_y -= moved;
+            let dist = max_scroll - ideal;
+            let pan_ms = if dist > 10.0 { self.pan_ms_for(dist, SCROLL_RES) } else { 0 };
+            CameraPlan {
+                motion: CameraMotion::Keys { edge_key: keys.1, pan_key: keys.0, pan_ms },
+                offset: max_scroll - (pan_ms as f32 / 1000.0) * self.move_speed,
+            }
+        }
+    }
+
+    /// 把镜头移动 delta 地图像素所需的拖动笔画：以安全区中心为轴，鼠标朝反方向拖，
+    /// 每笔不超过 drag_stroke_px
+    fn drag_strokes(&self, delta: f32, vertical: bool) -> Vec<DragStroke> {
+        let [z_x1, z_y1, z_x2, z_y2] = self.config.safe_zone;
+        let center = ((z_x1 + z_x2) as f32 / 2.0, (z_y1 + z_y2) as f32 / 2.0);
+        let stroke_max = self.config.drag_stroke_px.max(20.0);
+        let mut remaining = -delta / self.config.drag_ratio.max(0.01);
+
+        let mut strokes = Vec::new();
+        while remaining.abs() >= 1.0 {
+            let len = remaining.clamp(-stroke_max, stroke_max);
+            remaining -= len;
+            let stroke = if vertical {
+                ((center.0, center.1 - len / 2.0), (center.0, center.1 + len / 2.0))
+            } else {
+                ((center.0 - len / 2.0, center.1), (center.0 + len / 2.0, center.1))
+            };
+            strokes.push(stroke);
+        }
+        strokes
+    }
+
+    fn drag_camera(&mut self, strokes: &[DragStroke]) {
+        let right = self.config.drag_right_button;
+        for &(from, to) in strokes {
+            if let Ok(mut human) = self.driver.lock() {
+                human.drag_button_humanly(
+                    (from.0 as u16, from.1 as u16),
+                    (to.0 as u16, to.1 as u16),
+                    0.25,
+                    right,
+                );
             }
+            self.control.sleep(Duration::from_millis(80));
         }
-        thread::sleep(Duration::from_millis(200));
+    }
+
+    fn smart_move_camera(&mut self, target_map_y: f32) -> bool {
+        let Some(plan) = self.plan_camera_y(target_map_y) else { return false };
+        match &plan.motion {
+            CameraMotion::Keys { edge_key, pan_key, pan_ms } => {
+                self.align_camera_to_edge(*edge_key == 'w');
+                if *pan_ms > 0 {
+                    if let Ok(mut human) = self.driver.lock() {
+                        human.key_hold(*pan_key, *pan_ms);
+                    }
+                }
+            }
+            CameraMotion::Drag { strokes } => self.drag_camera(strokes),
+        }
+        self.camera_offset_y = plan.offset;
+        self.control.sleep(Duration::from_millis(200));
+        true
+    }
+
+    /// 横向版 smart_move_camera：先用 'a' / 'd' 贴到左 / 右边界，再按像素平移 (drag 模式直接拖动)
+    fn smart_move_camera_x(&mut self, target_map_x: f32) -> bool {
+        let Some(plan) = self.plan_camera_x(target_map_x) else { return false };
+        match &plan.motion {
+            CameraMotion::Keys { edge_key, pan_key, pan_ms } => {
+                if let Ok(mut human) = self.driver.lock() {
+                    info!("🔄 横向归零: {}", if *edge_key == 'a' { "左侧" } else { "右侧" });
+                    human.key_hold(*edge_key, 2500);
+                }
+                self.control.sleep(Duration::from_millis(500));
+
+                if *pan_ms > 0 {
+                    if let Ok(mut human) = self.driver.lock() {
+                        human.key_hold(*pan_key, *pan_ms);
+                    }
+                }
+            }
+            CameraMotion::Drag { strokes } => self.drag_camera(strokes),
+        }
+        self.camera_offset_x = plan.offset;
+        self.control.sleep(Duration::from_millis(200));
         true
     }
 
     pub fn load_map_terrain(&mut self, path: &str) {
-        if let Ok(c) = fs::read_to_string(path) {
-            if let Ok(data) = serde_json::from_str::<MapTerrainExport>(&c) {
+        match parse_data_file::<MapTerrainExport>(path) {
+            Ok(data) => {
                 self.map_meta = Some(data.meta);
+                if let Some(o) = &data.overrides {
+                    info!("🗺️ [塔防] 应用地形文件中的地图参数覆盖");
+                    self.config.apply_override(o);
+                    if let Some(speed) = self.config.move_speed {
+                        self.move_speed = speed;
+                    }
+                }
             }
+            Err(e) => error!("❌ 地形加载失败: {}", e),
         }
     }
 
-    pub fn load_trap_config(&mut self, json_path: &str) {
-        if let Ok(c) = fs::read_to_string(json_path) {
-            if let Ok(items) = serde_json::from_str::<Vec<TrapConfigItem>>(&c) {
+    pub fn load_trap_config(&mut self, path: &str) {
+        match parse_trap_file(path) {
+            Ok(items) => {
                 for item in items {
                     self.trap_lookup.insert(item.name.clone(), item);
                 }
             }
+            Err(e) => error!("❌ 陷阱配置加载失败: {}", e),
         }
     }
 
     pub fn setup_view(&mut self) {
-        println!("🔭 对齐左上角边界...");
+        info!("🔭 对齐左上角边界...");
         if let Ok(mut human) = self.driver.lock() {
             human.key_click('o');
-            thread::sleep(Duration::from_secs(2));
-            for _ in 1..=4 {
-                for _ in 0..10 {
-                    human.mouse_scroll(-120);
-                    thread::sleep(Duration::from_millis(30));
+        }
+        self.control.sleep(Duration::from_secs(2));
+        if !(self.config.zoom_calibration && self.calibrate_zoom()) {
+            if let Ok(mut human) = self.driver.lock() {
+                for _ in 1..=4 {
+                    for _ in 0..10 {
+                        human.mouse_scroll(-120);
+                        self.control.sleep(Duration::from_millis(30));
+                    }
+                    self.control.sleep(Duration::from_millis(100));
+                }
+            }
+        }
+        match self.config.camera_mode {
+            CameraMode::KeyHold => {
+                if let Ok(mut human) = self.driver.lock() {
+                    for _ in 1..=2 {
+                        human.key_hold('w', 200);
+                        self.control.sleep(Duration::from_millis(50));
+                        human.key_hold('a', 200);
+                        self.control.sleep(Duration::from_millis(50));
+                    }
+                    human.key_hold('w', 200);
+                    human.key_hold('a', 200);
                 }
-                thread::sleep(Duration::from_millis(100));
             }
-            for _ in 1..=2 {
-                human.key_hold('w', 200);
-                thread::sleep(Duration::from_millis(50));
-                human.key_hold('a', 200);
-                thread::sleep(Duration::from_millis(50));
+            CameraMode::Drag => {
+                // 开局位置未知，按整张地图的尺寸往左上拖
+                let (map_w, map_h) = self.map_meta.as_ref().map(|m| (m.right, m.bottom)).unwrap_or((0.0, 0.0));
+                let strokes_y = self.drag_strokes(-map_h, true);
+                let strokes_x = self.drag_strokes(-map_w, false);
+                self.drag_camera(&strokes_y);
+                self.drag_camera(&strokes_x);
             }
-            human.key_hold('w', 200);
-            human.key_hold('a', 200);
         }
         self.camera_offset_y = 0.0;
+        self.camera_offset_x = 0.0;
+    }
+
+    /// 逐格滚轮调整缩放，直到屏幕上的格子边长与 MapMeta.grid_pixel_size 相符；无法测量时返回 false
+    pub fn calibrate_zoom(&mut self) -> bool {
+        let Some(target) = self.map_meta.as_ref().map(|m| m.grid_pixel_size) else { return false };
+        let rect = self.config.zoom_rect.unwrap_or(self.config.safe_zone);
+        let tolerance = self.config.zoom_tolerance;
+        let mut last_dir = 0;
+        let mut reversals = 0;
+
+        for step in 0..self.config.zoom_max_steps {
+            if self.control.is_stopped() {
+                return false;
+            }
+            let measured = self
+                .nav
+                .capture_area(rect)
+                .map(|img| image::DynamicImage::ImageRgba8(img).to_luma8())
+                .and_then(|gray| calibration::measure_grid_spacing(&gray, target * 0.4, target * 2.5));
+            let Some(measured) = measured else {
+                warn!("⚠️ [缩放] 无法从画面测量网格间距，改用固定滚轮缩放");
+                return false;
+            };
+            let ratio = measured / target;
+            if (ratio - 1.0).abs() <= tolerance {
+                info!("✅ [缩放] 格子 {:.1}px (目标 {:.1}px)，{} 步完成", measured, target, step);
+                return true;
+            }
+            // 格子偏大说明放大过头，向下滚缩小
+            let dir = if ratio > 1.0 { -1 } else { 1 };
+            if last_dir != 0 && dir != last_dir {
+                reversals += 1;
+                // 在目标两侧来回跳，说明一格滚轮的步长已超过容差，取当前位置
+                if reversals >= 2 {
+                    info!("✅ [缩放] 格子 {:.1}px (目标 {:.1}px)，已到滚轮精度极限", measured, target);
+                    return true;
+                }
+            }
+            last_dir = dir;
+            if let Ok(mut human) = self.driver.lock() {
+                human.mouse_scroll(120 * dir);
+            }
+            self.control.sleep(Duration::from_millis(self.config.zoom_settle_ms));
+        }
+        warn!("⚠️ [缩放] {} 步内未对齐网格，改用固定滚轮缩放", self.config.zoom_max_steps);
+        false
     }
 
     pub fn execute_prep_logic(&self) {
-        println!("🔧 执行赛前准备...");
+        info!("🔧 执行赛前准备...");
 
         if let Some(meta) = &self.map_meta {
             if !meta.prep_actions.is_empty() {
-                println!("   -> 加载自定义战术动作 ({} 步)", meta.prep_actions.len());
+                info!("   -> 加载自定义战术动作 ({} 步)", meta.prep_actions.len());
                 if let Ok(human) = self.driver.lock() {
-                    if let Ok(mut dev) = human.device.lock() {
-                        for action in &meta.prep_actions {
-                            match action {
-                                PrepAction::KeyDown { key } => {
-                                    let code = get_hid_code(*key);
-                                    if code != 0 {
-                                        dev.key_down(code, 0);
-                                    }
-                                }
-                                PrepAction::KeyUpAll => {
-                                    dev.key_up();
-                                }
-                                PrepAction::Wait { ms } => {
-                                    drop(dev);
-                                    thread::sleep(Duration::from_millis(*ms));
-                                    dev = human.device.lock().unwrap();
-                                }
-                                PrepAction::Log { msg } => {
-                                    println!("   [Prep] {}", msg);
+                    let dev = &human.device;
+                    for action in &meta.prep_actions {
+                        match action {
+                            PrepAction::KeyDown { key } => {
+                                let code = get_hid_code(*key);
+                                if code != 0 {
+                                    dev.key_down(code, 0);
                                 }
                             }
+                            PrepAction::KeyUpAll => {
+                                dev.key_up();
+                            }
+                            PrepAction::Wait { ms } => {
+                                self.control.sleep(Duration::from_millis(*ms));
+                            }
+                            PrepAction::Log { msg } => {
+                                info!("   [Prep] {}", msg);
+                            }
                         }
-                        dev.key_up();
                     }
+                    dev.key_up();
                 }
             }
         }
 
         if let Ok(mut human) = self.driver.lock() {
             human.key_click('n');
-            thread::sleep(Duration::from_millis(500));
+            self.control.sleep(Duration::from_millis(500));
         }
 
         self.select_loadout();
 
         if let Ok(mut human) = self.driver.lock() {
             human.key_click('n');
-            thread::sleep(Duration::from_millis(500));
+            self.control.sleep(Duration::from_millis(500));
         }
     }
 
@@ -753,7 +2004,16 @@ impl TowerDefenseApp {
         const GRID_STEP_X: i32 = 170;
         const GRID_STEP_Y: i32 = 205;
 
-        for name in self.active_loadout.iter().take(4) {
+        let pages = self.config.page_keys.len().max(1);
+        let capacity = self.config.hotbar_keys.len() * pages;
+        if self.active_loadout.len() > capacity {
+            warn!(
+                "⚠️ [装备] 策略需要 {} 种陷阱，装备栏只有 {} 格 (在 td_config.toml 中配置 page_keys 以启用翻页)",
+                self.active_loadout.len(),
+                capacity
+            );
+        }
+        for name in self.active_loadout.iter().take(capacity) {
             if let Some(config) = self.trap_lookup.get(name) {
                 let (tab_x, tab_y) = match config.b_type.as_str() {
                     "Wall" => (172, 375),
@@ -764,7 +2024,7 @@ impl TowerDefenseApp {
                 if let Ok(mut d) = self.driver.lock() {
                     d.move_to_humanly(tab_x, tab_y, 0.4);
                     d.click_humanly(true, false, 0);
-                    thread::sleep(Duration::from_millis(350));
+                    self.control.sleep(Duration::from_millis(350));
 
                     let col = config.grid_index[0];
                     let row = config.grid_index[1];
@@ -774,9 +2034,9 @@ impl TowerDefenseApp {
                     d.move_to_humanly(target_x as u16, target_y as u16, 0.4);
                     d.click_humanly(true, false, 0);
                 }
-                thread::sleep(Duration::from_millis(400));
+                self.control.sleep(Duration::from_millis(400));
             } else {
-                println!("⚠️ [Config Error] 未找到陷阱配置: {}", name);
+                warn!("⚠️ [Config Error] 未找到陷阱配置: {}", name);
             }
         }
     }
@@ -794,30 +2054,209 @@ impl TowerDefenseApp {
         Some((sx, sy))
     }
 
-    fn get_trap_key(&self, name: &str) -> char {
+    /// 陷阱对应的 (快捷键, 装备栏页号)
+    fn trap_binding(&self, name: &str) -> (char, usize) {
         let index = self
             .active_loadout
             .iter()
             .position(|t| t == name)
             .unwrap_or(0);
-        match index {
-            0 => '4',
-            1 => '5',
-            2 => '6',
-            3 => '7',
-            _ => '1',
+        let per_page = self.config.hotbar_keys.len().max(1);
+        let item = self.trap_lookup.get(name);
+        let page = item.and_then(|t| t.page).unwrap_or(index / per_page);
+        let key = item
+            .and_then(|t| t.key)
+            .or_else(|| self.config.hotbar_keys.get(index % per_page).copied())
+            .unwrap_or('1');
+        (key, page)
+    }
+
+    /// 切到指定装备栏页，实际按键换页时返回 true
+    fn switch_page(&mut self, page: usize) -> bool {
+        if page == self.current_page {
+            return false;
+        }
+        let Some(&page_key) = self.config.page_keys.get(page) else {
+            warn!("⚠️ [装备] 未配置第 {} 页的切换键 (page_keys)，留在当前页", page);
+            return false;
+        };
+        info!("📑 [装备] 切换到第 {} 页 ('{}')", page, page_key);
+        if let Ok(mut d) = self.driver.lock() {
+            d.key_click(page_key);
+        }
+        self.control.sleep(Duration::from_millis(250));
+        self.current_page = page;
+        true
+    }
+
+    /// 运行一局；收到停止信号时释放按键、保存进度并提前返回
+    pub fn run(&mut self, terrain_p: &str, strategy_p: &str, trap_p: &str) -> RunSummary {
+        let _span = info_span!("td", map = %self.map_name).entered();
+        let started = Instant::now();
+        self.prepare(terrain_p, strategy_p, trap_p);
+        if self.start_battle() {
+            self.monitor_waves();
+        }
+        self.finish(started)
+    }
+
+    /// 从进度文件继续 (程序崩溃后重启时使用)：跳过入场与赛前准备，直接进入波次监控
+    pub fn run_resume(&mut self, state_path: &str) -> Result<RunSummary, String> {
+        let state = TdProgress::load(state_path)?;
+        let _span = info_span!("td", map = %state.map_name).entered();
+        let started = Instant::now();
+        self.config.progress_path = state_path.to_string();
+        self.prepare(&state.terrain_path, &state.strategy_path, &state.trap_path);
+
+        self.map_name = state.map_name.clone();
+        self.last_confirmed_wave = state.last_wave;
+        self.last_wave_change_time = Instant::now();
+        self.placed_uids = state.placed_uids.into_iter().collect();
+        self.completed_upgrade_keys = state.completed_upgrades.into_iter().collect();
+        self.completed_abilities = state.completed_abilities.into_iter().collect();
+        self.completed_demolish_uids = state.completed_demolishes.into_iter().collect();
+        self.relocated = state.relocated;
+        self.skipped_uids = state.skipped_uids.into_iter().collect();
+        self.camera_offset_x = state.camera_offset_x;
+        self.camera_offset_y = state.camera_offset_y;
+        info!(
+            "♻️ [塔防] 从第 {} 波继续 (已放置 {}，已升级 {}，已拆除 {})",
+            self.last_confirmed_wave,
+            self.placed_uids.len(),
+            self.completed_upgrade_keys.len(),
+            self.completed_demolish_uids.len()
+        );
+
+        self.monitor_waves();
+        Ok(self.finish(started))
+    }
+
+    fn finish(&mut self, started: Instant) -> RunSummary {
+        let stopped = self.control.is_stopped();
+        if stopped {
+            if let Ok(d) = self.driver.lock() {
+                d.device.panic_release();
+            }
         }
+        self.save_progress();
+        let summary = RunSummary {
+            stopped,
+            outcome: self.outcome,
+            last_wave: self.last_confirmed_wave,
+            placed: self.placed_uids.len(),
+            skipped: self.skipped_uids.len(),
+            upgraded: self.completed_upgrade_keys.len(),
+            demolished: self.completed_demolish_uids.len(),
+            elapsed: started.elapsed(),
+        };
+        info!("📊 [塔防] {}", summary);
+        if !stopped {
+            self.log_outcome(&summary);
+        }
+        summary
     }
 
-    pub fn run(&mut self, terrain_p: &str, strategy_p: &str, trap_p: &str) {
+    /// 追加一行本局结果到 outcome_log_path
+    fn log_outcome(&self, summary: &RunSummary) {
+        let path = std::path::Path::new(&self.config.outcome_log_path);
+        if let Some(dir) = path.parent() {
+            let _ = fs::create_dir_all(dir);
+        }
+        let record = serde_json::json!({
+            "ts": chrono::Local::now().to_rfc3339(),
+            "map": self.map_name,
+            "outcome": summary.outcome,
+            "last_wave": summary.last_wave,
+            "placed": summary.placed,
+            "elapsed_s": summary.elapsed.as_secs(),
+        });
+        let written = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .and_then(|mut f| {
+                use std::io::Write;
+                writeln!(f, "{}", record)
+            });
+        if let Err(e) = written {
+            warn!("⚠️ [塔防] 结果记录失败 {}: {}", path.display(), e);
+        }
+    }
+
+    fn save_progress(&self) {
+        let progress = TdProgress {
+            map_name: self.map_name.clone(),
+            terrain_path: self.paths.0.clone(),
+            strategy_path: self.paths.1.clone(),
+            trap_path: self.paths.2.clone(),
+            last_wave: self.last_confirmed_wave,
+            placed_uids: self.placed_uids.iter().copied().collect(),
+            completed_upgrades: self.completed_upgrade_keys.iter().cloned().collect(),
+            completed_abilities: self.completed_abilities.iter().copied().collect(),
+            completed_demolishes: self.completed_demolish_uids.iter().copied().collect(),
+            relocated: self.relocated.clone(),
+            skipped_uids: self.skipped_uids.iter().copied().collect(),
+            camera_offset_x: self.camera_offset_x,
+            camera_offset_y: self.camera_offset_y,
+        };
+        let path = std::path::Path::new(&self.config.progress_path);
+        if let Some(dir) = path.parent() {
+            let _ = fs::create_dir_all(dir);
+        }
+        match serde_json::to_string_pretty(&progress) {
+            Ok(json) => {
+                if let Err(e) = fs::write(path, json) {
+                    warn!("⚠️ [塔防] 进度保存失败 {}: {}", path.display(), e);
+                }
+            }
+            Err(e) => warn!("⚠️ [塔防] 进度序列化失败: {}", e),
+        }
+        self.publish_status();
+    }
+
+    /// 将当前波次与已放置的建造写入运行状态 (控制接口查询)
+    fn publish_status(&self) {
+        let placed: Vec<PlacedTower> = self
+            .strategy_buildings
+            .iter()
+            .chain(self.strategy_emergency.buildings.iter())
+            .filter(|b| self.placed_uids.contains(&b.uid))
+            .map(|b| PlacedTower {
+                uid: b.uid,
+                name: b.name.clone(),
+                grid: self.relocated.get(&b.uid).copied().unwrap_or([b.grid_x, b.grid_y]),
+            })
+            .collect();
+        self.control.update_status(|s| {
+            s.map = Some(self.map_name.clone());
+            s.wave = Some(self.last_confirmed_wave);
+            s.placed = placed;
+        });
+    }
+
+    /// 加载地图、陷阱与策略，并按策略生成装备栏
+    fn prepare(&mut self, terrain_p: &str, strategy_p: &str, trap_p: &str) {
+        self.paths = (terrain_p.to_string(), strategy_p.to_string(), trap_p.to_string());
         self.load_map_terrain(terrain_p);
+        self.apply_profile_overrides();
         self.load_trap_config(trap_p);
         self.load_strategy(strategy_p);
+        self.watchdog = Watchdog::new(Duration::from_secs(self.config.stall_timeout_s), self.config.stall_max_recoveries);
+        self.digit_templates = DigitTemplates::load_default(&self.config.digit_template_dir).map(Arc::new);
+        if !self.config.journal_dir.is_empty() {
+            self.journal = Journal::create(&self.config.journal_dir)
+                .map_err(|e| warn!("⚠️ [日志] 无法创建动作日志: {}", e))
+                .ok();
+        }
+        if self.config.wave_detector == WaveDetector::Template && self.digit_templates.is_none() {
+            warn!("⚠️ [塔防] wave_detector = \"template\" 但未加载到数字模板，将只使用 HUD OCR");
+        }
 
         let mut seen = HashSet::new();
         let mut derived_loadout = Vec::new();
 
-        for b in &self.strategy_buildings {
+        // 应急后备建造同样需要带进装备栏
+        for b in self.strategy_buildings.iter().chain(&self.strategy_emergency.buildings) {
             if !seen.contains(&b.name) && self.trap_lookup.contains_key(&b.name) {
                 seen.insert(b.name.clone());
                 derived_loadout.push(b.name.clone());
@@ -830,98 +2269,393 @@ impl TowerDefenseApp {
             }
         }
 
+        if !self.profile.loadout.is_empty() {
+            for name in self.profile.loadout.iter().filter(|n| !self.trap_lookup.contains_key(*n)) {
+                warn!("⚠️ [装备] 运行配置中的 '{}' 不在陷阱配置中", name);
+            }
+            let missing: Vec<&String> = derived_loadout.iter().filter(|n| !self.profile.loadout.contains(n)).collect();
+            if !missing.is_empty() {
+                warn!("⚠️ [装备] 策略用到的 {:?} 不在运行配置的装备栏中，这些建造将无法放置", missing);
+            }
+            info!("📋 使用运行配置的装备列表: {:?}", self.profile.loadout);
+            self.active_loadout = self.profile.loadout.clone();
+            return;
+        }
+
         if derived_loadout.is_empty() {
-            println!("⚠️ 警告: 策略中未发现已知陷阱，装备栏将为空！");
+            warn!("⚠️ 警告: 策略中未发现已知陷阱，装备栏将为空！");
         } else {
-            println!("📋 自动分析策略，生成装备列表: {:?}", derived_loadout);
+            info!("📋 自动分析策略，生成装备列表: {:?}", derived_loadout);
         }
         self.active_loadout = derived_loadout;
+    }
 
+    /// 运行配置的界面参数最后应用，优先于 td_config.toml 与地形文件
+    fn apply_profile_overrides(&mut self) {
+        self.config.apply_override(&self.profile.td);
+        if let Some(speed) = self.profile.td.move_speed {
+            self.move_speed = speed;
+        }
+    }
+
+    /// 点击入口并等待战斗开始，随后执行赛前准备；被停止或卡住后放弃时返回 false
+    fn start_battle(&mut self) -> bool {
         if let Ok(mut human) = self.driver.lock() {
-            println!("👆 点击游戏入口...");
+            info!("👆 点击游戏入口...");
             human.move_to_humanly(1700, 950, 0.5);
             human.click_humanly(true, false, 0);
             human.move_to_humanly(1110, 670, 0.5);
             human.click_humanly(true, false, 0);
         }
 
-        println!("⏳ 等待战斗开始...");
+        info!("⏳ 等待战斗开始...");
         loop {
             if let Some(status) = self.recognize_wave_status(self.config.hud_check_rect, false) {
                 if status.current_wave > 0 {
-                    println!("🎮 战斗开始! 初始波次: {}", status.current_wave);
+                    info!("🎮 战斗开始! 初始波次: {}", status.current_wave);
                     self.last_wave_change_time = Instant::now();
+                    self.watchdog.feed();
                     break;
                 }
             }
-            thread::sleep(Duration::from_millis(1000));
+            if !self.control.sleep(Duration::from_millis(1000)) || !self.check_stall() {
+                return false;
+            }
         }
 
         self.execute_prep_logic();
         self.setup_view();
+        true
+    }
+
+    /// 暂停期间阻塞 (暂停时间不计入卡住检测)；返回 false 表示已收到停止信号
+    fn wait_if_paused(&mut self) -> bool {
+        if !self.control.is_paused() {
+            return !self.control.is_stopped();
+        }
+        let alive = self.control.wait_if_paused();
+        self.watchdog.extend();
+        alive
+    }
+
+    /// 卡住检测：超过 stall_timeout_s 没有波次推进时截图、按 ESC 关闭可能遮挡的对话框并重新识别。
+    /// 返回 false 表示应结束本局：已离开战斗界面 (由主循环重新导航到目标)，或连续恢复无效后已停止自动化
+    fn check_stall(&mut self) -> bool {
+        if !self.watchdog.is_stalled() {
+            return true;
+        }
+        let idle = self.watchdog.idle_for().as_secs();
+        let attempt = self.watchdog.begin_recovery();
+        warn!(
+            "⏰ [看门狗] {} 秒没有波次变化 (当前第 {} 波)，尝试恢复 ({}/{})",
+            idle, self.last_confirmed_wave, attempt, self.watchdog.max_recoveries()
+        );
+        if let Some(path) = self.nav.save_screenshot("stall") {
+            info!("📸 [看门狗] 现场截图: {}", path);
+        }
+        if self.watchdog.exhausted() {
+            error!("❌ [看门狗] 连续 {} 次恢复无效，停止自动化，请人工处理", attempt - 1);
+            notify::send(
+                NotifyEvent::Stalled,
+                format!("[{}] 第 {} 波起 {} 秒没有进展，恢复 {} 次无效", self.map_name, self.last_confirmed_wave, idle, attempt - 1),
+            );
+            self.control.stop();
+            return false;
+        }
+
+        if let Ok(d) = self.driver.lock() {
+            d.device.key_down(0x29, 0); // ESC
+            self.control.sleep(Duration::from_millis(100));
+            d.device.key_up();
+        }
+        if !self.control.sleep(Duration::from_secs(2)) {
+            return false;
+        }
+        if self.recognize_wave_status(self.config.hud_check_rect, false).is_some() {
+            info!("✅ [看门狗] 仍在战斗中，继续监控");
+            return true;
+        }
+        match self.nav.detect_current_scene() {
+            Some(scene) if scene != self.map_name => {
+                warn!("↩️ [看门狗] 当前在 [{}]，结束本局，由主循环重新导航到目标", scene);
+                false
+            }
+            _ => true,
+        }
+    }
+
+    /// 波次监控主循环：识别波次 -> 执行前期 -> 按 G 开战 -> 执行后期
+    fn monitor_waves(&mut self) {
+
+        info!("🤖 自动化监控中...");
+        let state = Arc::new(MonitorState::default());
+        let (tx, rx) = mpsc::channel();
+        let monitor = {
+            let probe = self.wave_probe();
+            let state = Arc::clone(&state);
+            thread::spawn(move || probe.run(state, tx))
+        };
+        let poll = Duration::from_millis(self.config.monitor_poll_ms.max(200));
 
-        println!("🤖 自动化监控中...");
         let mut no_wave_count = 0;
-        loop {
-            // 尝试检测波次 (带 Tab 切换)
-            // 我们把结果存下来，以便处理 "未检测到" 的情况
-            let wave_status_opt = self.recognize_wave_status(self.config.hud_wave_loop_rect, true);
+        while self.wait_if_paused() {
+            if !self.check_stall() {
+                break;
+            }
+            {
+                let _busy = state.gate.lock();
+                self.check_base_health();
+            }
+            let mut event = match rx.recv_timeout(poll) {
+                Ok(e) => e,
+                Err(mpsc::RecvTimeoutError::Timeout) => continue,
+                Err(mpsc::RecvTimeoutError::Disconnected) => break,
+            };
+            // 执行期间积压的事件只看最新一条
+            while let Ok(e) = rx.try_recv() {
+                event = e;
+            }
+            // 执行动作期间持有 gate，监控线程不会按 TAB
+            let _busy = state.gate.lock();
 
-            if let Some(status) = wave_status_opt {
+            if let MonitorEvent::Wave(current_wave) = event {
                 // === 情况 A: 正常检测到波次 ===
                 no_wave_count = 0; // 重置计数器
-                if self.validate_wave_transition(status.current_wave) {
-                    let current_wave = status.current_wave;
+                if self.validate_wave_transition(current_wave) {
                     self.execute_wave_phase(current_wave, false);
-                    println!("🔔 波次 {} 前期完成，按 G 开战", current_wave);
+                    self.save_progress();
+                    info!("🔔 波次 {} 前期完成，按 G 开战", current_wave);
                     if let Ok(mut d) = self.driver.lock() {
                         d.key_click('g');
                     }
-                    thread::sleep(Duration::from_secs(1));
+                    self.wait_for_late_phase();
                     self.execute_wave_phase(current_wave, true);
+                    self.save_progress();
                 }
+            } else if let MonitorEvent::Outcome(outcome) = event {
+                info!("🏁 [Monitor] 识别到结算界面: {:?}", outcome);
+                self.outcome = outcome;
+                break;
             } else {
                 // === 情况 B: 未检测到波次 (可能是结算界面) ===
                 no_wave_count += 1;
-                println!(
+                warn!(
                     "⚠️ [Monitor] 未检测到波次信息 ({}/2)，尝试跳过结算...",
                     no_wave_count
                 );
 
                 if let Ok(mut d) = self.driver.lock() {
-                    println!("   -> 点击空格 (Space) + 双击 ESC");
+                    info!("   -> 点击空格 (Space) + 双击 ESC");
 
                     // 直接操作底层设备发送 HID 码 0x29 (ESC)
-                    if let Ok(mut dev) = d.device.lock() {
-                        // 第一次 ESC
-                        dev.key_down(0x29, 0);
-                        thread::sleep(Duration::from_millis(100)); // 按下持续时间
-                        dev.key_up();
+                    // 第一次 ESC
+                    d.device.key_down(0x29, 0);
+                    self.control.sleep(Duration::from_millis(100)); // 按下持续时间
+                    d.device.key_up();
 
-                        thread::sleep(Duration::from_millis(300)); // 两次按键间隔
-                    }
+                    self.control.sleep(Duration::from_millis(300)); // 两次按键间隔
 
                     // 点击空格 (跳过结算动画)
                     d.key_click(' ');
-                    thread::sleep(Duration::from_millis(500));
+                    self.control.sleep(Duration::from_millis(500));
 
-                    if let Ok(mut dev) = d.device.lock() {
-                        // 第二次 ESC
-                        dev.key_down(0x29, 0);
-                        thread::sleep(Duration::from_millis(100));
-                        dev.key_up();
-                    }
+                    // 第二次 ESC
+                    d.device.key_down(0x29, 0);
+                    self.control.sleep(Duration::from_millis(100));
+                    d.device.key_up();
                 }
 
                 // 2. 检查退出条件
                 if no_wave_count >= 3 {
-                    println!("🏁 连续 2 次未检测到波次，判定为游戏结束。");
-                    println!("🔄 退出当前循环，返回主程序...");
+                    info!("🏁 连续 2 次未检测到波次，判定为游戏结束。");
+                    info!("🔄 退出当前循环，返回主程序...");
                     break; // 跳出 loop，函数结束，控制权交还给 main 的 loop
                 }
             }
+        }
+
+        state.finished.store(true, Ordering::Relaxed);
+        let _ = monitor.join();
+    }
+}
+
+// ==========================================
+// 3. 波次监控线程
+// ==========================================
+// 监控线程按 monitor_poll_ms 识别波次 / 结算界面，通过 channel 通知执行循环；
+// 执行循环在操作期间持有 gate，此时监控线程不按 TAB，只读 HUD。
+
+/// 监控线程发给执行循环的事件
+#[derive(Debug)]
+enum MonitorEvent {
+    Wave(i32),
+    /// 未识别到波次 (可能在结算界面)
+    NoWave,
+    Outcome(RunOutcome),
+}
+
+#[derive(Default)]
+struct MonitorState {
+    /// 执行循环发送输入时持有
+    gate: Mutex<()>,
+    /// 执行循环结束，监控线程退出
+    finished: AtomicBool,
+}
+
+/// 识别波次与结算界面所需的状态，可移入监控线程
+struct WaveProbe {
+    driver: Arc<Mutex<HumanDriver>>,
+    nav: Arc<NavEngine>,
+    control: RunControl,
+    config: TDConfig,
+    digit_templates: Option<Arc<DigitTemplates>>,
+}
+
+impl WaveProbe {
+    fn recognize_wave_status(&self, rect: [i32; 4], use_tab: bool) -> Option<WaveStatus> {
+        const KEY_TAB: u8 = 0x2B;
+        if use_tab {
+            if let Ok(driver) = self.driver.lock() {
+                driver.device.key_down(KEY_TAB, 0);
+            }
+            self.control.sleep(Duration::from_millis(500));
+        }
+
+        let ocr = self.nav.ocr_area_detailed(rect);
+        // 加载了数字模板时顺便截一张原图 (TAB 仍按住)，OCR 失败或置信度低时用于兜底
+        let snapshot = self
+            .digit_templates
+            .as_ref()
+            .and_then(|_| self.nav.capture_area(rect));
+
+        if use_tab {
+            if let Ok(driver) = self.driver.lock() {
+                driver.device.key_up();
+            }
+            self.control.sleep(Duration::from_millis(500));
+            if let Ok(driver) = self.driver.lock() {
+                driver.device.key_down(KEY_TAB, 0);
+            }
+            self.control.sleep(Duration::from_millis(100));
+            if let Ok(driver) = self.driver.lock() {
+                driver.device.key_up();
+            }
+        }
 
-            thread::sleep(Duration::from_millis(10000));
+        let parsed = Self::parse_wave_text(&ocr.text, use_tab);
+        let val = match parsed {
+            Some(v) if ocr.confidence >= self.config.wave_ocr_min_confidence => v,
+            _ => {
+                let matched = self.digit_templates.as_ref().zip(snapshot.as_ref()).and_then(|(t, img)| t.recognize(img));
+                match (matched, parsed) {
+                    (Some(num), None) => {
+                        info!("✅ [Template Match] OCR 未命中，数字模板兜底: 第 {} 波", num);
+                        num
+                    }
+                    (Some(num), Some(v)) => {
+                        if num != v {
+                            info!("✅ [Template Match] OCR 置信度低 ({:.2}) 读到 {}，以数字模板为准: 第 {} 波", ocr.confidence, v, num);
+                        }
+                        num
+                    }
+                    // 模板也认不出时沿用低置信度的 OCR 结果
+                    (None, Some(v)) => v,
+                    (None, None) => return None,
+                }
+            }
+        };
+        Some(WaveStatus { current_wave: val })
+    }
+
+    /// 按配置的方式识别当前波次
+    /// allow_tab 为 false (执行循环正在操作) 时 tab_ocr 改读 HUD，不按 TAB
+    fn detect_wave(&self, allow_tab: bool) -> Option<WaveStatus> {
+        match self.config.wave_detector {
+            WaveDetector::TabOcr if allow_tab => self.recognize_wave_status(self.config.hud_wave_loop_rect, true),
+            WaveDetector::TabOcr => self.recognize_wave_status(self.config.hud_check_rect, false),
+            WaveDetector::Template => {
+                let rect = self.config.hud_wave_digits_rect.unwrap_or(self.config.hud_check_rect);
+                let matched = self
+                    .digit_templates
+                    .as_ref()
+                    .and_then(|t| t.recognize(&self.nav.capture_area(rect)?));
+                match matched {
+                    Some(num) if num > 0 => Some(WaveStatus { current_wave: num }),
+                    _ => self.recognize_wave_status(self.config.hud_check_rect, false),
+                }
+            }
+        }
+    }
+
+    fn parse_wave_text(text: &str, use_tab: bool) -> Option<i32> {
+        if text.is_empty() {
+            return None;
+        }
+
+        info!(
+            "🔍 [OCR Debug] 原始文本: 「{}」 (Mode: {})",
+            text.trim(),
+            if use_tab { "TAB" } else { "HUD" }
+        );
+
+        if use_tab {
+            let re = Regex::new(r"(\d+)[/\dSI日]+.*波次").ok()?;
+            re.captures(text).and_then(|caps| {
+                let num = caps.get(1)?.as_str().parse::<i32>().ok()?;
+                info!("✅ [OCR Match] TAB 模式匹配成功: 第 {} 波", num);
+                Some(num)
+            })
+        } else {
+            let re = Regex::new(r"波次\s*(\d+)").ok()?;
+            re.captures(text).and_then(|caps| {
+                let num = caps.get(1)?.as_str().parse::<i32>().ok()?;
+                info!("✅ [OCR Match] HUD 模式匹配成功: 第 {} 波", num);
+                Some(num)
+            })
+        }
+    }
+
+    /// 识别是否处于结算界面
+    fn detect_outcome(&self) -> Option<RunOutcome> {
+        let scene = self.nav.detect_current_scene()?;
+        if scene == self.config.victory_scene {
+            Some(RunOutcome::Victory)
+        } else if scene == self.config.defeat_scene {
+            Some(RunOutcome::Defeat)
+        } else {
+            None
+        }
+    }
+
+    /// 监控线程主循环；执行循环结束或停止时退出
+    fn run(self, state: Arc<MonitorState>, tx: mpsc::Sender<MonitorEvent>) {
+        let poll_ms = self.config.monitor_poll_ms.max(200);
+        info!("👁️ [Monitor] 波次监控线程启动 (间隔 {}ms)", poll_ms);
+        while !state.finished.load(Ordering::Relaxed) && self.control.wait_if_paused() {
+            let started = Instant::now();
+            let event = {
+                // 拿不到 gate 说明执行循环正在操作：只读 HUD，且漏识别不作数
+                let idle = state.gate.try_lock().ok();
+                match self.detect_wave(idle.is_some()) {
+                    Some(status) => Some(MonitorEvent::Wave(status.current_wave)),
+                    None if idle.is_none() => None,
+                    None => Some(self.detect_outcome().map(MonitorEvent::Outcome).unwrap_or(MonitorEvent::NoWave)),
+                }
+            };
+            metrics::global().wave_poll.observe(started.elapsed());
+            // 未识别到波次时执行循环会按 ESC 跳过结算，放慢节奏 (与旧版一致的 10 秒)
+            let wait_ms = if matches!(event, Some(MonitorEvent::NoWave)) { 10_000 } else { poll_ms };
+            if let Some(e) = event {
+                if tx.send(e).is_err() {
+                    break;
+                }
+            }
+            let deadline = Instant::now() + Duration::from_millis(wait_ms);
+            while Instant::now() < deadline && !state.finished.load(Ordering::Relaxed) {
+                if !self.control.sleep(Duration::from_millis(200)) {
+                    return;
+                }
+            }
         }
     }
 }
@@ -0,0 +1,101 @@
+// src/trace.rs
+use chrono::Local;
+use image::{Rgba, RgbaImage};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+// ==========================================
+// 鼠标轨迹调试导出
+// ==========================================
+// HumanDriver 开启 trace 后，每次拟人移动写一行 JSON：
+// {"ts": 时间, "curve": 轨迹类型, "target": [目标], "end": [实际终点], "points": [[x, y, t_ms], ...]}
+// render() 将轨迹叠加到截图上，用于检查轨迹是否自然、点击是否偏移。
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TraceEntry {
+    pub ts: String,
+    pub curve: String,
+    pub target: [u16; 2],
+    /// 加入终点抖动后的实际落点
+    pub end: [f32; 2],
+    /// 轨迹点：x, y, 相对移动开始的毫秒数
+    pub points: Vec<[f32; 3]>,
+}
+
+pub struct TraceWriter {
+    writer: BufWriter<File>,
+}
+
+impl TraceWriter {
+    pub fn new(path: &str) -> std::io::Result<Self> {
+        if let Some(dir) = Path::new(path).parent() {
+            if !dir.as_os_str().is_empty() {
+                fs::create_dir_all(dir)?;
+            }
+        }
+        println!("🖊️ [轨迹] 鼠标轨迹将写入 {}", path);
+        Ok(Self { writer: BufWriter::new(File::create(path)?) })
+    }
+
+    pub fn write(&mut self, curve: &str, target: (u16, u16), end: (f32, f32), points: Vec<[f32; 3]>) {
+        let entry = TraceEntry {
+            ts: Local::now().format("%H:%M:%S%.3f").to_string(),
+            curve: curve.to_string(),
+            target: [target.0, target.1],
+            end: [end.0, end.1],
+            points,
+        };
+        if let Ok(line) = serde_json::to_string(&entry) {
+            let _ = writeln!(self.writer, "{}", line);
+            let _ = self.writer.flush();
+        }
+    }
+}
+
+/// 读取轨迹文件 (跳过无法解析的行)
+pub fn load(path: &str) -> Result<Vec<TraceEntry>, String> {
+    let file = File::open(path).map_err(|e| format!("无法打开轨迹文件 {}: {}", path, e))?;
+    Ok(BufReader::new(file)
+        .lines()
+        .map_while(Result::ok)
+        .filter_map(|l| serde_json::from_str(&l).ok())
+        .collect())
+}
+
+/// 将轨迹叠加到截图上：线条由绿 (起点) 渐变到红 (终点)，蓝色十字为目标点
+pub fn render(trace_path: &str, background: &str, out_path: &str) -> Result<usize, String> {
+    let entries = load(trace_path)?;
+    let mut img = image::open(background)
+        .map_err(|e| format!("无法打开截图 {}: {}", background, e))?
+        .to_rgba8();
+
+    for entry in &entries {
+        let n = entry.points.len().max(2) as f32 - 1.0;
+        for (i, seg) in entry.points.windows(2).enumerate() {
+            let k = i as f32 / n;
+            let color = Rgba([(255.0 * k) as u8, (255.0 * (1.0 - k)) as u8, 0, 255]);
+            draw_line(&mut img, (seg[0][0], seg[0][1]), (seg[1][0], seg[1][1]), color);
+        }
+        let (tx, ty) = (entry.target[0] as f32, entry.target[1] as f32);
+        let blue = Rgba([0, 128, 255, 255]);
+        draw_line(&mut img, (tx - 6.0, ty), (tx + 6.0, ty), blue);
+        draw_line(&mut img, (tx, ty - 6.0), (tx, ty + 6.0), blue);
+    }
+
+    img.save(out_path).map_err(|e| format!("无法保存 {}: {}", out_path, e))?;
+    Ok(entries.len())
+}
+
+fn draw_line(img: &mut RgbaImage, from: (f32, f32), to: (f32, f32), color: Rgba<u8>) {
+    let steps = (to.0 - from.0).abs().max((to.1 - from.1).abs()).ceil().max(1.0) as u32;
+    for s in 0..=steps {
+        let t = s as f32 / steps as f32;
+        let x = (from.0 + (to.0 - from.0) * t).round();
+        let y = (from.1 + (to.1 - from.1) * t).round();
+        if x >= 0.0 && y >= 0.0 && (x as u32) < img.width() && (y as u32) < img.height() {
+            img.put_pixel(x as u32, y as u32, color);
+        }
+    }
+}
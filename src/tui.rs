@@ -0,0 +1,332 @@
+// src/tui.rs
+use crate::control::RunControl;
+use crate::input_queue::{InputQueue, LinkHealth};
+use crate::nav::{NavEngine, NavEvent, NavEventKind};
+use ratatui::backend::CrosstermBackend;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::crossterm::execute;
+use ratatui::crossterm::terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, List, ListItem, Paragraph};
+use ratatui::{Frame, Terminal};
+use std::collections::VecDeque;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+#[cfg(feature = "windows")]
+use std::os::windows::io::AsRawHandle;
+#[cfg(feature = "windows")]
+use windows::Win32::Foundation::HANDLE;
+#[cfg(feature = "windows")]
+use windows::Win32::System::Console::{GetStdHandle, SetStdHandle, STD_OUTPUT_HANDLE};
+#[cfg(feature = "linux")]
+use std::os::fd::{AsRawFd, RawFd};
+
+// ==========================================
+// 终端面板 (--tui，需 feature = "tui")
+// ==========================================
+// 把进程的标准输出重定向到管道，所有 println 进入可滚动的日志区；面板直接画在 CONOUT$ (Linux 为 /dev/tty) 上：
+// 链路状态、目标 / 场景 / 波次、最近的导航动作与 OCR 结果。
+// 面板存活期间终端处于 raw 模式，Ctrl+C 由面板转为停止信号 (再按一次强制退出)。
+// 退出时恢复终端与标准输出，并把最后几行日志 (通常是本局摘要) 打印到控制台。
+
+/// 日志区保留的行数
+const LOG_CAPACITY: usize = 2000;
+/// 动作 / OCR 区保留的条数
+const FEED_CAPACITY: usize = 50;
+/// 退出面板后重新打印的日志行数
+const TAIL_ON_EXIT: usize = 20;
+
+type Log = Arc<Mutex<VecDeque<String>>>;
+
+/// 控制台设备 (标准输出被重定向后仍可直接写终端)
+#[cfg(feature = "windows")]
+const CONSOLE: &str = "CONOUT$";
+#[cfg(feature = "linux")]
+const CONSOLE: &str = "/dev/tty";
+
+#[cfg(feature = "windows")]
+type StdoutHandle = HANDLE;
+#[cfg(feature = "linux")]
+type StdoutHandle = RawFd;
+
+/// 把标准输出换成 writer，返回原来的标准输出
+#[cfg(feature = "windows")]
+fn redirect_stdout(writer: &io::PipeWriter) -> io::Result<StdoutHandle> {
+    let original = unsafe { GetStdHandle(STD_OUTPUT_HANDLE) }.map_err(io::Error::other)?;
+    unsafe { SetStdHandle(STD_OUTPUT_HANDLE, HANDLE(writer.as_raw_handle() as isize)) }.map_err(io::Error::other)?;
+    Ok(original)
+}
+
+#[cfg(feature = "windows")]
+fn restore_stdout(original: StdoutHandle) {
+    unsafe {
+        let _ = SetStdHandle(STD_OUTPUT_HANDLE, original);
+    }
+}
+
+#[cfg(feature = "linux")]
+fn redirect_stdout(writer: &io::PipeWriter) -> io::Result<StdoutHandle> {
+    let original = unsafe { libc::dup(libc::STDOUT_FILENO) };
+    if original < 0 || unsafe { libc::dup2(writer.as_raw_fd(), libc::STDOUT_FILENO) } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(original)
+}
+
+#[cfg(feature = "linux")]
+fn restore_stdout(original: StdoutHandle) {
+    unsafe {
+        libc::dup2(original, libc::STDOUT_FILENO);
+        libc::close(original);
+    }
+}
+
+fn push_capped(buf: &mut VecDeque<String>, line: String, cap: usize) {
+    if buf.len() >= cap {
+        buf.pop_front();
+    }
+    buf.push_back(line);
+}
+
+/// 面板句柄；drop 时恢复终端与标准输出
+pub struct Dashboard {
+    done: Arc<AtomicBool>,
+    ui: Option<JoinHandle<()>>,
+    log: Log,
+    original_stdout: StdoutHandle,
+}
+
+struct UiState {
+    control: RunControl,
+    input: InputQueue,
+    events: mpsc::Receiver<NavEvent>,
+    log: Log,
+    actions: VecDeque<String>,
+    ocr: VecDeque<String>,
+    /// 日志区离底部的行数；0 表示跟随最新输出
+    scroll: usize,
+}
+
+impl Dashboard {
+    /// 接管标准输出并启动面板线程
+    pub fn start(control: RunControl, engine: &NavEngine, input: InputQueue) -> io::Result<Self> {
+        let console = OpenOptions::new().write(true).open(CONSOLE)?;
+        let (reader, writer) = io::pipe()?;
+        let _ = io::stdout().flush();
+        let original_stdout = redirect_stdout(&writer)?;
+        // 写端在恢复标准输出之前一直有效
+        std::mem::forget(writer);
+
+        let log: Log = Arc::new(Mutex::new(VecDeque::new()));
+        {
+            let log = Arc::clone(&log);
+            thread::spawn(move || {
+                for line in BufReader::new(reader).lines().map_while(Result::ok) {
+                    if let Ok(mut l) = log.lock() {
+                        push_capped(&mut l, line, LOG_CAPACITY);
+                    }
+                }
+            });
+        }
+
+        let done = Arc::new(AtomicBool::new(false));
+        let state = UiState {
+            control,
+            input,
+            events: engine.subscribe(),
+            log: Arc::clone(&log),
+            actions: VecDeque::new(),
+            ocr: VecDeque::new(),
+            scroll: 0,
+        };
+        let ui = {
+            let done = Arc::clone(&done);
+            thread::spawn(move || {
+                if let Err(e) = run_ui(console, state, &done) {
+                    println!("⚠️ [面板] 已退出: {}", e);
+                }
+            })
+        };
+        Ok(Self { done, ui: Some(ui), log, original_stdout })
+    }
+}
+
+impl Drop for Dashboard {
+    fn drop(&mut self) {
+        self.done.store(true, Ordering::SeqCst);
+        if let Some(ui) = self.ui.take() {
+            let _ = ui.join();
+        }
+        let _ = io::stdout().flush();
+        restore_stdout(self.original_stdout);
+        if let Ok(log) = self.log.lock() {
+            for line in log.iter().skip(log.len().saturating_sub(TAIL_ON_EXIT)) {
+                println!("{}", line);
+            }
+        }
+    }
+}
+
+fn run_ui(console: File, mut state: UiState, done: &AtomicBool) -> io::Result<()> {
+    enable_raw_mode()?;
+    let mut terminal = Terminal::new(CrosstermBackend::new(console))?;
+    execute!(terminal.backend_mut(), EnterAlternateScreen)?;
+
+    let mut ctrl_c_pressed = false;
+    let result = (|| -> io::Result<()> {
+        while !done.load(Ordering::SeqCst) {
+            state.drain_events();
+            terminal.draw(|f| state.render(f))?;
+
+            if !event::poll(Duration::from_millis(200))? {
+                continue;
+            }
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            match key.code {
+                KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    if ctrl_c_pressed {
+                        return Err(io::Error::new(io::ErrorKind::Interrupted, "再次按下 Ctrl+C"));
+                    }
+                    ctrl_c_pressed = true;
+                    println!("🛑 [控制] 收到 Ctrl+C，正在收尾 (再按一次强制退出)...");
+                    state.control.stop();
+                }
+                KeyCode::Char('q') => {
+                    println!("🛑 [控制] 收到停止按键，当前动作完成后退出");
+                    state.control.stop();
+                }
+                KeyCode::Char('p') => {
+                    let paused = !state.control.is_paused();
+                    state.control.set_paused(paused);
+                    println!("{}", if paused { "⏸️ [控制] 已暂停 (p 继续)" } else { "▶️ [控制] 继续运行" });
+                }
+                KeyCode::Up => state.scroll += 1,
+                KeyCode::Down => state.scroll = state.scroll.saturating_sub(1),
+                KeyCode::PageUp => state.scroll += 10,
+                KeyCode::PageDown => state.scroll = state.scroll.saturating_sub(10),
+                KeyCode::End => state.scroll = 0,
+                _ => {}
+            }
+        }
+        Ok(())
+    })();
+
+    let _ = execute!(terminal.backend_mut(), LeaveAlternateScreen);
+    let _ = disable_raw_mode();
+    let _ = terminal.show_cursor();
+    if let Err(e) = &result {
+        if e.kind() == io::ErrorKind::Interrupted {
+            std::process::exit(130);
+        }
+    }
+    result
+}
+
+impl UiState {
+    fn drain_events(&mut self) {
+        while let Ok(e) = self.events.try_recv() {
+            let ts = e.timestamp.format("%H:%M:%S");
+            let line = match e.kind {
+                NavEventKind::SceneEntered { scene, confidence } => format!("{} 进入 [{}] ({:.2})", ts, scene, confidence),
+                NavEventKind::TransitionClicked { from, to, coords } => {
+                    format!("{} 点击 ({}, {}) [{}] -> [{}]", ts, coords[0], coords[1], from, to)
+                }
+                NavEventKind::PopupDismissed { popup } => format!("{} 关闭弹窗 [{}]", ts, popup),
+                NavEventKind::NavigationFinished { target, result, elapsed_ms } => {
+                    format!("{} 导航结束 [{}] {:?} ({}ms)", ts, target, result, elapsed_ms)
+                }
+                NavEventKind::OcrCompleted { rect, text, elapsed_ms } => {
+                    let line = format!("{} {:?} \"{}\" ({}ms)", ts, rect, text, elapsed_ms);
+                    push_capped(&mut self.ocr, line, FEED_CAPACITY);
+                    continue;
+                }
+            };
+            push_capped(&mut self.actions, line, FEED_CAPACITY);
+        }
+    }
+
+    fn render(&self, f: &mut Frame) {
+        let [status_area, feeds_area, log_area] =
+            Layout::vertical([Constraint::Length(6), Constraint::Length(12), Constraint::Min(5)]).areas(f.area());
+        let [actions_area, ocr_area] =
+            Layout::horizontal([Constraint::Percentage(55), Constraint::Percentage(45)]).areas(feeds_area);
+
+        self.render_status(f, status_area);
+        render_feed(f, actions_area, "最近动作", &self.actions);
+        render_feed(f, ocr_area, "OCR", &self.ocr);
+        self.render_log(f, log_area);
+    }
+
+    fn render_status(&self, f: &mut Frame, area: Rect) {
+        let status = self.control.status();
+        let (health, color) = match self.input.health() {
+            LinkHealth::Healthy => ("正常", Color::Green),
+            LinkHealth::Degraded => ("心跳延迟", Color::Yellow),
+            LinkHealth::Unhealthy => ("无响应", Color::Red),
+        };
+        let run = if status.stopped {
+            Span::styled("停止中", Style::default().fg(Color::Red))
+        } else if status.paused {
+            Span::styled("已暂停", Style::default().fg(Color::Yellow))
+        } else {
+            Span::styled("运行中", Style::default().fg(Color::Green))
+        };
+        let none = || "-".to_string();
+        let lines = vec![
+            Line::from(vec![
+                Span::raw("链路: "),
+                Span::styled(health, Style::default().fg(color)),
+                Span::raw("   状态: "),
+                run,
+                Span::raw(format!("   更新: {}", status.updated)),
+            ]),
+            Line::from(format!(
+                "目标: {}   场景: {}",
+                status.target.clone().unwrap_or_else(none),
+                status.scene.clone().unwrap_or_else(none)
+            )),
+            Line::from(format!(
+                "塔防: {}   波次: {}   已放置: {}",
+                status.map.clone().unwrap_or_else(none),
+                status.wave.map(|w| w.to_string()).unwrap_or_else(none),
+                status.placed.len()
+            )),
+            Line::from(Span::styled(
+                "p 暂停/继续  q 停止  ↑↓/PgUp/PgDn 滚动日志  End 跟随最新",
+                Style::default().fg(Color::DarkGray),
+            )),
+        ];
+        f.render_widget(Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title("NZM_CMD")), area);
+    }
+
+    fn render_log(&self, f: &mut Frame, area: Rect) {
+        let height = area.height.saturating_sub(2) as usize;
+        let lines: Vec<Line> = match self.log.lock() {
+            Ok(log) => {
+                let end = log.len().saturating_sub(self.scroll.min(log.len().saturating_sub(height)));
+                let start = end.saturating_sub(height);
+                log.range(start..end).map(|l| Line::from(l.clone())).collect()
+            }
+            Err(_) => Vec::new(),
+        };
+        let title = if self.scroll == 0 { "日志".to_string() } else { format!("日志 (向上 {} 行)", self.scroll) };
+        f.render_widget(Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title(title)), area);
+    }
+}
+
+fn render_feed(f: &mut Frame, area: Rect, title: &str, feed: &VecDeque<String>) {
+    let height = area.height.saturating_sub(2) as usize;
+    let items: Vec<ListItem> = feed.iter().rev().take(height).map(|l| ListItem::new(l.as_str())).collect();
+    f.render_widget(List::new(items).block(Block::default().borders(Borders::ALL).title(title)), area);
+}
@@ -0,0 +1,197 @@
+// src/uinput.rs
+use crate::hardware::{hid_to_evdev, InputDriver};
+use std::ffi::c_ulong;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::os::fd::AsRawFd;
+use std::os::unix::fs::OpenOptionsExt;
+use std::thread;
+use std::time::Duration;
+
+// ==========================================
+// Linux 软件输入 (/dev/uinput)
+// ==========================================
+// 在内核中创建一个虚拟键鼠设备，X11 / Wayland / 游戏看到的是一块真实的输入设备，
+// 不依赖桌面环境的注入接口。键码按 HID -> evdev 换算，与硬件盒子一样按物理键位输入。
+// 需要 /dev/uinput 的写权限 (root，或 udev 规则把它交给 input 组)。
+// 进程退出时内核销毁设备并抬起所有仍按下的键。
+
+const UINPUT_PATH: &str = "/dev/uinput";
+
+// linux/input-event-codes.h
+pub(crate) const EV_SYN: u16 = 0x00;
+pub(crate) const EV_KEY: u16 = 0x01;
+pub(crate) const EV_REL: u16 = 0x02;
+pub(crate) const EV_ABS: u16 = 0x03;
+const SYN_REPORT: u16 = 0;
+const REL_X: u16 = 0x00;
+const REL_Y: u16 = 0x01;
+const REL_HWHEEL: u16 = 0x06;
+const REL_WHEEL: u16 = 0x08;
+const ABS_X: u16 = 0x00;
+const ABS_Y: u16 = 0x01;
+const BTN_LEFT: u16 = 0x110;
+const BTN_RIGHT: u16 = 0x111;
+pub(crate) const KEY_F9: u16 = 67;
+pub(crate) const KEY_F10: u16 = 68;
+/// 注册的键盘键码范围 (KEY_ESC ~ KEY_MICMUTE，覆盖 HID 能换算到的全部键)
+const KEY_MAX_USED: u16 = 248;
+const BUS_VIRTUAL: u16 = 0x06;
+
+// linux/uinput.h：_IOW('U', nr, size) / _IO('U', nr)
+const fn iow(nr: c_ulong, size: usize) -> c_ulong {
+    (1 << 30) | ((size as c_ulong) << 16) | ((b'U' as c_ulong) << 8) | nr
+}
+const UI_DEV_CREATE: c_ulong = (b'U' as c_ulong) << 8 | 1;
+const UI_DEV_DESTROY: c_ulong = (b'U' as c_ulong) << 8 | 2;
+const UI_DEV_SETUP: c_ulong = iow(3, std::mem::size_of::<libc::uinput_setup>());
+const UI_ABS_SETUP: c_ulong = iow(4, std::mem::size_of::<libc::uinput_abs_setup>());
+const UI_SET_EVBIT: c_ulong = iow(100, std::mem::size_of::<libc::c_int>());
+const UI_SET_KEYBIT: c_ulong = iow(101, std::mem::size_of::<libc::c_int>());
+const UI_SET_RELBIT: c_ulong = iow(102, std::mem::size_of::<libc::c_int>());
+const UI_SET_ABSBIT: c_ulong = iow(103, std::mem::size_of::<libc::c_int>());
+
+/// 设备创建后等待桌面环境识别的时间 (过早输入会丢失)
+const SETTLE: Duration = Duration::from_millis(300);
+
+pub struct UinputDriver {
+    device: File,
+    pub screen_w: u16,
+    pub screen_h: u16,
+    /// 已按下的键 (含修饰键)，key_up 时按相反顺序抬起
+    held: Vec<u16>,
+}
+
+impl UinputDriver {
+    pub fn new(screen_w: u16, screen_h: u16) -> Result<Self, String> {
+        let device = OpenOptions::new()
+            .write(true)
+            .custom_flags(libc::O_NONBLOCK)
+            .open(UINPUT_PATH)
+            .map_err(|e| format!("无法打开 {} (需 root 或 input 组的 udev 规则): {}", UINPUT_PATH, e))?;
+        let fd = device.as_raw_fd();
+        let ioctl = |request: c_ulong, value: libc::c_int| -> Result<(), String> {
+            if unsafe { libc::ioctl(fd, request as _, value) } < 0 {
+                return Err(format!("uinput 设置失败 ({:#x}): {}", request, std::io::Error::last_os_error()));
+            }
+            Ok(())
+        };
+        for ev in [EV_SYN, EV_KEY, EV_REL, EV_ABS] {
+            ioctl(UI_SET_EVBIT, ev as _)?;
+        }
+        for key in (1..=KEY_MAX_USED).chain([BTN_LEFT, BTN_RIGHT]) {
+            ioctl(UI_SET_KEYBIT, key as _)?;
+        }
+        for rel in [REL_X, REL_Y, REL_WHEEL, REL_HWHEEL] {
+            ioctl(UI_SET_RELBIT, rel as _)?;
+        }
+        for (abs, max) in [(ABS_X, screen_w), (ABS_Y, screen_h)] {
+            ioctl(UI_SET_ABSBIT, abs as _)?;
+            let mut setup: libc::uinput_abs_setup = unsafe { std::mem::zeroed() };
+            setup.code = abs;
+            setup.absinfo.maximum = max.saturating_sub(1) as i32;
+            if unsafe { libc::ioctl(fd, UI_ABS_SETUP as _, &setup) } < 0 {
+                return Err(format!("uinput 坐标轴设置失败: {}", std::io::Error::last_os_error()));
+            }
+        }
+
+        let mut setup: libc::uinput_setup = unsafe { std::mem::zeroed() };
+        setup.id.bustype = BUS_VIRTUAL;
+        setup.id.vendor = 0x1209;
+        setup.id.product = 0x4e5a;
+        for (dst, src) in setup.name.iter_mut().zip(b"nzm_cmd virtual input") {
+            *dst = *src as libc::c_char;
+        }
+        if unsafe { libc::ioctl(fd, UI_DEV_SETUP as _, &setup) } < 0 || unsafe { libc::ioctl(fd, UI_DEV_CREATE as _) } < 0 {
+            return Err(format!("创建 uinput 设备失败: {}", std::io::Error::last_os_error()));
+        }
+        thread::sleep(SETTLE);
+        Ok(Self { device, screen_w, screen_h, held: Vec::new() })
+    }
+
+    /// 写入一组事件并以 SYN_REPORT 结束
+    fn emit(&mut self, events: &[(u16, u16, i32)]) {
+        let mut buf = Vec::with_capacity((events.len() + 1) * std::mem::size_of::<libc::input_event>());
+        for &(type_, code, value) in events.iter().chain([(EV_SYN, SYN_REPORT, 0)].iter()) {
+            let mut event: libc::input_event = unsafe { std::mem::zeroed() };
+            event.type_ = type_;
+            event.code = code;
+            event.value = value;
+            // input_event 为纯数据结构，按内存布局写入
+            let bytes = unsafe {
+                std::slice::from_raw_parts((&event as *const libc::input_event).cast::<u8>(), std::mem::size_of::<libc::input_event>())
+            };
+            buf.extend_from_slice(bytes);
+        }
+        let _ = self.device.write_all(&buf);
+    }
+
+    fn press(&mut self, code: u16) {
+        if !self.held.contains(&code) {
+            self.emit(&[(EV_KEY, code, 1)]);
+            self.held.push(code);
+        }
+    }
+}
+
+impl Drop for UinputDriver {
+    fn drop(&mut self) {
+        unsafe {
+            libc::ioctl(self.device.as_raw_fd(), UI_DEV_DESTROY as _);
+        }
+    }
+}
+
+impl InputDriver for UinputDriver {
+    fn heartbeat(&mut self) {}
+    fn switch_identity(&mut self, _index: u8) {}
+
+    fn mouse_abs(&mut self, x: u16, y: u16) {
+        self.emit(&[(EV_ABS, ABS_X, x as i32), (EV_ABS, ABS_Y, y as i32)]);
+    }
+
+    fn mouse_move(&mut self, dx: i32, dy: i32, wheel: i8) {
+        self.emit(&[(EV_REL, REL_X, dx), (EV_REL, REL_Y, dy)]);
+        if wheel != 0 {
+            // HID 与 evdev 一致：正值向上
+            self.emit(&[(EV_REL, REL_WHEEL, wheel as i32)]);
+        }
+    }
+
+    fn mouse_hwheel(&mut self, delta: i8) {
+        self.emit(&[(EV_REL, REL_HWHEEL, delta as i32)]);
+    }
+
+    fn mouse_down(&mut self, left: bool, right: bool) {
+        if left {
+            self.emit(&[(EV_KEY, BTN_LEFT, 1)]);
+        }
+        if right {
+            self.emit(&[(EV_KEY, BTN_RIGHT, 1)]);
+        }
+    }
+
+    fn mouse_up(&mut self) {
+        self.emit(&[(EV_KEY, BTN_LEFT, 0), (EV_KEY, BTN_RIGHT, 0)]);
+    }
+
+    fn key_down(&mut self, keycode: u8, modifier: u8) {
+        // 修饰键位 bit i 对应 HID 0xE0 + i
+        for bit in 0..8u8 {
+            if modifier & (1 << bit) != 0 {
+                if let Some(code) = hid_to_evdev(0xE0 + bit) {
+                    self.press(code);
+                }
+            }
+        }
+        if let Some(code) = hid_to_evdev(keycode) {
+            self.press(code);
+        }
+    }
+
+    fn key_up(&mut self) {
+        while let Some(code) = self.held.pop() {
+            self.emit(&[(EV_KEY, code, 0)]);
+        }
+    }
+}
@@ -0,0 +1,163 @@
+// src/validate.rs
+use crate::tower_defense::{check_only_if, parse_data_file, BuildingExport, MapBuildingsExport, MapMeta, MapTerrainExport};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+// ==========================================
+// 策略 / 地形校验
+// ==========================================
+// 上线前检查 <地图>地图.json + <地图>策略.json：uid 重复、超出地图、同时存在的建造互相重叠、
+// 升级了策略里没有的陷阱、拆除从未建造的 uid 等。validate 子命令调用，也可在代码中直接使用。
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    /// 执行时必然出错
+    Error,
+    /// 可能是笔误，但不影响执行
+    Warning,
+}
+
+#[derive(Debug, Clone)]
+pub struct Issue {
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let icon = match self.severity {
+            Severity::Error => "❌",
+            Severity::Warning => "⚠️",
+        };
+        write!(f, "{} {}", icon, self.message)
+    }
+}
+
+/// 波次阶段的先后顺序 (同一波前期在后期之前)
+type Phase = (i32, bool);
+
+/// 建造占用的格子矩形 [x1, y1, x2, y2) (网格坐标)
+fn footprint(b: &BuildingExport) -> [usize; 4] {
+    [b.grid_x, b.grid_y, b.grid_x + b.width, b.grid_y + b.height]
+}
+
+fn overlaps(a: [usize; 4], b: [usize; 4]) -> bool {
+    a[0] < b[2] && b[0] < a[2] && a[1] < b[3] && b[1] < a[3]
+}
+
+/// 格子矩形超出地图时返回说明；meta.right 为 0 (未填写) 时不检查横向
+fn out_of_bounds(meta: &MapMeta, gx: usize, gy: usize, w: usize, h: usize) -> Option<String> {
+    let x2 = meta.offset_x + (gx + w) as f32 * meta.grid_pixel_size;
+    let y2 = meta.offset_y + (gy + h) as f32 * meta.grid_pixel_size;
+    if y2 > meta.bottom {
+        return Some(format!("下边缘 {:.0} 超出地图底部 {:.0}", y2, meta.bottom));
+    }
+    if meta.right > 0.0 && x2 > meta.right {
+        return Some(format!("右边缘 {:.0} 超出地图右侧 {:.0}", x2, meta.right));
+    }
+    None
+}
+
+/// 校验已解析的地形与策略
+pub fn validate(terrain: &MapTerrainExport, strategy: &MapBuildingsExport) -> Vec<Issue> {
+    let mut issues = Vec::new();
+    let mut error = |msg: String| issues.push(Issue { severity: Severity::Error, message: msg });
+    let mut warnings = Vec::new();
+    let mut warn = |msg: String| warnings.push(Issue { severity: Severity::Warning, message: msg });
+
+    if terrain.map_name != strategy.map_name {
+        warn(format!("地形地图名 \"{}\" 与策略地图名 \"{}\" 不一致", terrain.map_name, strategy.map_name));
+    }
+
+    // 1. uid 重复 (含应急建造)
+    let all: Vec<&BuildingExport> = strategy.buildings.iter().chain(strategy.emergency.buildings.iter()).collect();
+    let mut seen = HashSet::new();
+    for b in &all {
+        if !seen.insert(b.uid) {
+            error(format!("uid {} 重复 ({})", b.uid, b.name));
+        }
+    }
+    let by_uid: HashMap<usize, &BuildingExport> = all.iter().map(|b| (b.uid, *b)).collect();
+
+    // 2. 超出地图 (含备选格子)
+    let meta = &terrain.meta;
+    for b in &all {
+        if let Some(why) = out_of_bounds(meta, b.grid_x, b.grid_y, b.width, b.height) {
+            error(format!("{} (uid {}) @ ({}, {}) {}", b.name, b.uid, b.grid_x, b.grid_y, why));
+        }
+        for &[gx, gy] in &b.alternates {
+            if let Some(why) = out_of_bounds(meta, gx, gy, b.width, b.height) {
+                error(format!("{} (uid {}) 的备选格子 ({}, {}) {}", b.name, b.uid, gx, gy, why));
+            }
+        }
+        if let Some(Err(e)) = b.only_if.as_deref().map(|c| check_only_if(c, 0, Some(0))) {
+            error(format!("{} (uid {}) 的 only_if 无效: {}", b.name, b.uid, e));
+        }
+    }
+
+    // 3. 拆除：必须拆已建造过的 uid，且不早于建造
+    let mut demolished_at: HashMap<usize, Phase> = HashMap::new();
+    for d in &strategy.demolishes {
+        let phase = (d.wave_num, d.is_late);
+        match by_uid.get(&d.uid) {
+            None => error(format!("第 {} 波拆除的 uid {} ({}) 从未建造", d.wave_num, d.uid, d.name)),
+            Some(b) if phase < (b.wave_num, b.is_late) => error(format!(
+                "uid {} ({}) 在第 {} 波建造，却在更早的第 {} 波拆除",
+                d.uid, b.name, b.wave_num, d.wave_num
+            )),
+            Some(_) => {
+                let entry = demolished_at.entry(d.uid).or_insert(phase);
+                *entry = (*entry).min(phase);
+            }
+        }
+    }
+
+    // 4. 同时存在的建造互相重叠 (同一阶段先拆后建，拆除当阶段可在原位新建)
+    let alive = |b: &BuildingExport| ((b.wave_num, b.is_late), demolished_at.get(&b.uid).copied());
+    for (i, a) in strategy.buildings.iter().enumerate() {
+        for b in &strategy.buildings[i + 1..] {
+            if !overlaps(footprint(a), footprint(b)) {
+                continue;
+            }
+            let (a_from, a_until) = alive(a);
+            let (b_from, b_until) = alive(b);
+            let a_before_b_gone = b_until.is_none_or(|t| a_from < t);
+            let b_before_a_gone = a_until.is_none_or(|t| b_from < t);
+            if a_before_b_gone && b_before_a_gone {
+                error(format!(
+                    "{} (uid {}) 与 {} (uid {}) 的占地重叠 @ ({}, {})",
+                    a.name, a.uid, b.name, b.uid, b.grid_x, b.grid_y
+                ));
+            }
+        }
+    }
+
+    // 5. 升级：陷阱必须在策略的装备中，指定的 uid 必须存在且同名
+    let names: HashSet<&str> = all.iter().map(|b| b.name.as_str()).collect();
+    for u in &strategy.upgrades {
+        if !names.contains(u.building_name.as_str()) {
+            error(format!("第 {} 波升级的 {} 不在策略的任何建造中 (不会被装备)", u.wave_num, u.building_name));
+        }
+        if let Some(uid) = u.uid {
+            match by_uid.get(&uid) {
+                None => error(format!("第 {} 波升级的 uid {} 不存在", u.wave_num, uid)),
+                Some(b) if b.name != u.building_name => warn(format!(
+                    "第 {} 波升级 uid {} 写的是 {}，但该建造是 {}",
+                    u.wave_num, uid, u.building_name, b.name
+                )),
+                Some(_) => {}
+            }
+        }
+    }
+
+    issues.extend(warnings);
+    issues
+}
+
+/// 读取并校验地形与策略文件 (JSON 或 TOML)；文件无法读取或解析时返回 Err
+pub fn validate_files(terrain_path: &str, strategy_path: &str) -> Result<Vec<Issue>, String> {
+    let terrain: MapTerrainExport = parse_data_file(terrain_path)?;
+    let strategy: MapBuildingsExport = parse_data_file(strategy_path)?;
+    nzm_schema::migrate::check_strategy_version(strategy.version)?;
+    Ok(validate(&terrain, &strategy))
+}
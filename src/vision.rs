@@ -0,0 +1,206 @@
+// src/vision.rs
+use image::{GrayImage, RgbaImage};
+use tracing::warn;
+
+// ==========================================
+// 模板匹配 (导航图像锚点、波次数字识别、镜头校准、放置校验共用)
+// ==========================================
+// 零均值归一化互相关 (ZNCC)：搜索图先建积分图，任意窗口的均值与能量 O(1) 取得，
+// 每个位置只需累加一次互相关；逐行取连续切片做 8 路点积，编译器可自动向量化为 SIMD 指令。
+// 比起 OCR，图标、艺术字与画面变化这类视觉判断用它又快又稳。
+
+/// 转灰度
+pub fn to_gray(img: &RgbaImage) -> GrayImage {
+    image::DynamicImage::ImageRgba8(img.clone()).to_luma8()
+}
+
+/// 两张同尺寸图片的平均逐像素差 (RGB 三通道平均，0 ~ 255)；尺寸不同时返回 None
+pub fn mean_abs_diff(a: &RgbaImage, b: &RgbaImage) -> Option<f32> {
+    if a.dimensions() != b.dimensions() {
+        return None;
+    }
+    let total: f32 = a
+        .pixels()
+        .zip(b.pixels())
+        .map(|(a, b)| (0..3).map(|c| (a[c] as f32 - b[c] as f32).abs()).sum::<f32>() / 3.0)
+        .sum();
+    Some(total / (a.width() * a.height()).max(1) as f32)
+}
+
+/// 预处理过的搜索图：灰度像素与积分图。同一张图匹配多个模板时 (如 0~9 数字) 只需建一次
+pub struct SearchImage {
+    width: u32,
+    height: u32,
+    pixels: Vec<f32>,
+    /// (width + 1) x (height + 1) 的像素和 / 平方和积分图
+    sum: Vec<f64>,
+    sq_sum: Vec<f64>,
+}
+
+impl SearchImage {
+    pub fn new(img: &GrayImage) -> Self {
+        let (width, height) = img.dimensions();
+        let pixels: Vec<f32> = img.pixels().map(|p| p[0] as f32).collect();
+        let stride = width as usize + 1;
+        let mut sum = vec![0.0f64; stride * (height as usize + 1)];
+        let mut sq_sum = sum.clone();
+        for y in 0..height as usize {
+            let (mut row, mut row_sq) = (0.0f64, 0.0f64);
+            for x in 0..width as usize {
+                let v = pixels[y * width as usize + x] as f64;
+                row += v;
+                row_sq += v * v;
+                sum[(y + 1) * stride + x + 1] = sum[y * stride + x + 1] + row;
+                sq_sum[(y + 1) * stride + x + 1] = sq_sum[y * stride + x + 1] + row_sq;
+            }
+        }
+        Self { width, height, pixels, sum, sq_sum }
+    }
+
+    pub fn from_rgba(img: &RgbaImage) -> Self {
+        Self::new(&to_gray(img))
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// 窗口内的 (像素和, 平方和)
+    fn window(&self, x: u32, y: u32, w: u32, h: u32) -> (f64, f64) {
+        let stride = self.width as usize + 1;
+        let (x1, y1, x2, y2) = (x as usize, y as usize, (x + w) as usize, (y + h) as usize);
+        let rect = |t: &[f64]| t[y2 * stride + x2] - t[y1 * stride + x2] - t[y2 * stride + x1] + t[y1 * stride + x1];
+        (rect(&self.sum), rect(&self.sq_sum))
+    }
+
+    fn row(&self, x: u32, y: u32, len: u32) -> &[f32] {
+        let start = (y * self.width + x) as usize;
+        &self.pixels[start..start + len as usize]
+    }
+}
+
+/// 8 路累加的点积，便于自动向量化
+fn dot(a: &[f32], b: &[f32]) -> f32 {
+    let mut acc = [0.0f32; 8];
+    let (ca, cb) = (a.chunks_exact(8), b.chunks_exact(8));
+    let tail: f32 = ca.remainder().iter().zip(cb.remainder()).map(|(x, y)| x * y).sum();
+    for (xa, xb) in ca.zip(cb) {
+        for ((s, x), y) in acc.iter_mut().zip(xa).zip(xb) {
+            *s += x * y;
+        }
+    }
+    acc.iter().sum::<f32>() + tail
+}
+
+/// 灰度模板
+pub struct GrayTemplate {
+    pub width: u32,
+    pub height: u32,
+    /// 去均值后的像素 (行优先)
+    pixels: Vec<f32>,
+    /// 去均值后的平方和开方
+    norm: f32,
+}
+
+impl GrayTemplate {
+    pub fn new(img: GrayImage) -> Self {
+        let (width, height) = img.dimensions();
+        let raw: Vec<f32> = img.pixels().map(|p| p[0] as f32).collect();
+        let mean = raw.iter().sum::<f32>() / raw.len().max(1) as f32;
+        let pixels: Vec<f32> = raw.iter().map(|v| v - mean).collect();
+        let norm = pixels.iter().map(|v| v * v).sum::<f32>().sqrt();
+        Self { width, height, pixels, norm }
+    }
+
+    pub fn load(path: &str) -> Option<Self> {
+        Self::load_scaled(path, 1.0, 1.0)
+    }
+
+    /// 加载模板并按比例缩放 (用于分辨率适配)
+    pub fn load_scaled(path: &str, sx: f32, sy: f32) -> Option<Self> {
+        match image::open(path) {
+            Ok(img) => {
+                let gray = img.to_luma8();
+                if sx == 1.0 && sy == 1.0 {
+                    return Some(Self::new(gray));
+                }
+                let w = ((gray.width() as f32 * sx).round() as u32).max(1);
+                let h = ((gray.height() as f32 * sy).round() as u32).max(1);
+                Some(Self::new(image::imageops::resize(&gray, w, h, image::imageops::FilterType::Triangle)))
+            }
+            Err(e) => {
+                warn!("⚠️ [Template] 模板加载失败 {}: {}", path, e);
+                None
+            }
+        }
+    }
+
+    /// 模板左上角放在 (ox, oy) 时的零均值归一化互相关 (-1.0 ~ 1.0)
+    pub fn ncc_at(&self, img: &SearchImage, ox: u32, oy: u32) -> f32 {
+        let n = (self.width * self.height) as f64;
+        let (sum, sq_sum) = img.window(ox, oy, self.width, self.height);
+        let energy = (sq_sum - sum * sum / n).max(0.0) as f32;
+        let denom = energy.sqrt() * self.norm;
+        if denom < f32::EPSILON {
+            return 0.0;
+        }
+        // 模板已去均值，窗口均值项与之相乘求和为 0，只需累加原始像素
+        let cross: f32 = (0..self.height)
+            .map(|y| {
+                let start = (y * self.width) as usize;
+                dot(img.row(ox, oy + y, self.width), &self.pixels[start..start + self.width as usize])
+            })
+            .sum();
+        cross / denom
+    }
+
+    /// 模板左上角可放置的范围 (x 起止, y 起止，均含)；region 为 [x1, y1, x2, y2]，模板必须完全落在其中
+    fn positions(&self, img: &SearchImage, region: [u32; 4]) -> Option<(u32, u32, u32, u32)> {
+        let x2 = region[2].min(img.width);
+        let y2 = region[3].min(img.height);
+        if region[0] + self.width > x2 || region[1] + self.height > y2 {
+            return None;
+        }
+        Some((region[0], x2 - self.width, region[1], y2 - self.height))
+    }
+
+    /// 在整张图上滑动搜索，返回得分最高的位置 (x, y, score)
+    pub fn best_match(&self, img: &GrayImage) -> Option<(u32, u32, f32)> {
+        let search = SearchImage::new(img);
+        self.best_match_in(&search, [0, 0, search.width, search.height])
+    }
+
+    /// 只在 region [x1, y1, x2, y2] 内搜索，返回得分最高的位置 (x, y, score)
+    pub fn best_match_in(&self, img: &SearchImage, region: [u32; 4]) -> Option<(u32, u32, f32)> {
+        let (x1, x2, y1, y2) = self.positions(img, region)?;
+        let mut best = (x1, y1, f32::MIN);
+        for y in y1..=y2 {
+            for x in x1..=x2 {
+                let score = self.ncc_at(img, x, y);
+                if score > best.2 {
+                    best = (x, y, score);
+                }
+            }
+        }
+        Some(best)
+    }
+
+    /// 整张图上所有得分不低于 threshold 的位置 (x, y, score)，未做非极大值抑制
+    pub fn find_all(&self, img: &SearchImage, threshold: f32) -> Vec<(u32, u32, f32)> {
+        let Some((x1, x2, y1, y2)) = self.positions(img, [0, 0, img.width, img.height]) else { return Vec::new() };
+        let mut hits = Vec::new();
+        for y in y1..=y2 {
+            for x in x1..=x2 {
+                let score = self.ncc_at(img, x, y);
+                if score >= threshold {
+                    hits.push((x, y, score));
+                }
+            }
+        }
+        hits
+    }
+}
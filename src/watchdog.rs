@@ -0,0 +1,66 @@
+// src/watchdog.rs
+use std::time::{Duration, Instant};
+
+// ==========================================
+// 卡住检测 (看门狗)
+// ==========================================
+// 记录最近一次 "有进展" (确认场景变化、波次推进) 的时间；超过 timeout 没有进展即视为卡住
+// (如漏关的对话框挡住画面，识别一直原地轮询)。调用方负责截图与恢复动作，
+// 每次恢复尝试重新计时，连续恢复超过 max_recoveries 次仍无进展时应放弃。
+
+#[derive(Debug)]
+pub struct Watchdog {
+    /// 为 0 时不检测
+    timeout: Duration,
+    max_recoveries: u32,
+    last_progress: Instant,
+    recoveries: u32,
+}
+
+impl Default for Watchdog {
+    fn default() -> Self {
+        Self::new(Duration::ZERO, 0)
+    }
+}
+
+impl Watchdog {
+    pub fn new(timeout: Duration, max_recoveries: u32) -> Self {
+        Self { timeout, max_recoveries, last_progress: Instant::now(), recoveries: 0 }
+    }
+
+    /// 有进展：重新计时并清零恢复次数
+    pub fn feed(&mut self) {
+        self.last_progress = Instant::now();
+        self.recoveries = 0;
+    }
+
+    /// 只重新计时 (如暂停结束后)，保留恢复次数
+    pub fn extend(&mut self) {
+        self.last_progress = Instant::now();
+    }
+
+    pub fn is_stalled(&self) -> bool {
+        !self.timeout.is_zero() && self.last_progress.elapsed() >= self.timeout
+    }
+
+    /// 距最近一次进展的时间
+    pub fn idle_for(&self) -> Duration {
+        self.last_progress.elapsed()
+    }
+
+    /// 记录一次恢复尝试并重新计时；返回这是第几次
+    pub fn begin_recovery(&mut self) -> u32 {
+        self.recoveries += 1;
+        self.last_progress = Instant::now();
+        self.recoveries
+    }
+
+    /// 恢复次数已超过上限，应放弃
+    pub fn exhausted(&self) -> bool {
+        self.recoveries > self.max_recoveries
+    }
+
+    pub fn max_recoveries(&self) -> u32 {
+        self.max_recoveries
+    }
+}
@@ -0,0 +1,97 @@
+// tests/navigation.rs
+// 用 tests/fixtures 中的预录画面驱动 NavEngine：场景识别、多步导航与托管移交
+use nzm_cmd::fixture::{FixtureScreen, Harness};
+use nzm_cmd::hardware::InputCommand;
+use nzm_cmd::nav::NavResult;
+use image::{Rgba, RgbaImage};
+
+const FIXTURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures");
+
+fn harness() -> Harness {
+    let screen = FixtureScreen::load_dir(FIXTURES).expect("加载夹具失败");
+    Harness::new(&format!("{}/ui_map.toml", FIXTURES), screen)
+}
+
+fn clicks(commands: &[InputCommand]) -> usize {
+    commands.iter().filter(|c| matches!(c, InputCommand::MouseDown { left: true, .. })).count()
+}
+
+#[test]
+fn detects_each_scene_from_its_screenshot() {
+    let h = harness();
+    for scene in ["游戏大厅主界面", "选择玩法", "地图选择", "结算胜利"] {
+        assert!(h.screen.show(scene));
+        assert_eq!(h.engine.detect_current_scene().as_deref(), Some(scene));
+    }
+}
+
+#[test]
+fn unknown_screen_is_not_a_scene() {
+    let h = harness();
+    assert!(h.screen.show("wave_1"));
+    assert_eq!(h.engine.detect_current_scene(), None);
+}
+
+#[test]
+fn already_at_target_sends_no_input() {
+    let h = harness();
+    h.screen.show("选择玩法");
+    let report = h.engine.navigate("选择玩法");
+    assert_eq!(report.result, NavResult::Success);
+    assert!(report.steps.is_empty());
+    assert_eq!(clicks(&h.commands()), 0);
+}
+
+#[test]
+fn navigates_through_fixture_screens() {
+    let h = harness();
+    h.screen.show("游戏大厅主界面");
+    let report = h.engine.navigate("地图选择");
+    assert_eq!(report.result, NavResult::Success);
+    assert_eq!(report.planned_path, ["游戏大厅主界面", "选择玩法", "地图选择"]);
+    assert_eq!(report.steps.len(), 2);
+    assert_eq!(report.stopped_at.as_deref(), Some("地图选择"));
+    assert_eq!(h.screen.current().as_deref(), Some("地图选择"));
+    assert_eq!(clicks(&h.commands()), 2);
+}
+
+#[test]
+fn hands_over_to_scene_handler() {
+    let h = harness();
+    h.screen.show("结算胜利");
+    let report = h.engine.navigate("塔防战斗");
+    assert_eq!(report.result, NavResult::Handover("塔防战斗".to_string(), Some("td".to_string())));
+    assert_eq!(report.planned_path.first().map(String::as_str), Some("结算胜利"));
+    assert_eq!(report.steps.len(), 4);
+    assert_eq!(clicks(&h.commands()), 4);
+}
+
+/// 深色背景上以 (960, 540) 为中心的 9x9 色块；每个像素按棋盘格交替加减 jitter，模拟发光动画的明暗起伏
+fn loading_screen(rgb: [u8; 3], jitter: i16) -> RgbaImage {
+    let mut img = RgbaImage::from_pixel(1920, 1080, Rgba([20, 20, 24, 255]));
+    for y in 536..545 {
+        for x in 956..965 {
+            let d = if (x + y) % 2 == 0 { jitter } else { -jitter };
+            let [r, g, b] = rgb.map(|c| (c as i16 + d).clamp(0, 255) as u8);
+            img.put_pixel(x, y, Rgba([r, g, b, 255]));
+        }
+    }
+    img
+}
+
+#[test]
+fn hsv_anchor_tolerates_glow_and_gamma() {
+    let h = harness();
+    // 比期望色 #E6B43C 整体亮一些，且逐像素起伏远超单像素 RGB 锚点的容差
+    h.screen.insert("加载中", loading_screen([240, 190, 66], 40), Vec::new());
+    assert!(h.screen.show("加载中"));
+    assert_eq!(h.engine.detect_current_scene().as_deref(), Some("加载中"));
+}
+
+#[test]
+fn hsv_anchor_rejects_other_hue() {
+    let h = harness();
+    h.screen.insert("加载中", loading_screen([60, 200, 80], 0), Vec::new());
+    assert!(h.screen.show("加载中"));
+    assert_eq!(h.engine.detect_current_scene(), None);
+}
@@ -0,0 +1,52 @@
+// tests/tower_defense.rs
+// 用预录的 HUD 画面模拟一局的波次推进：HUD / TAB 两种识别方式与结算界面
+use nzm_cmd::fixture::{FixtureScreen, Harness};
+use nzm_cmd::hardware::InputCommand;
+use nzm_cmd::tower_defense::{TDConfig, TowerDefenseApp};
+use std::sync::Arc;
+
+const FIXTURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures");
+const KEY_TAB: u8 = 0x2B;
+
+fn setup() -> (Harness, TowerDefenseApp) {
+    let screen = FixtureScreen::load_dir(FIXTURES).expect("加载夹具失败");
+    let h = Harness::new(&format!("{}/ui_map.toml", FIXTURES), screen);
+    let td = TowerDefenseApp::new(Arc::clone(&h.driver), Arc::clone(&h.engine));
+    (h, td)
+}
+
+#[test]
+fn reads_wave_sequence_from_hud() {
+    let (h, td) = setup();
+    let rect = TDConfig::default().hud_check_rect;
+    let waves: Vec<Option<i32>> = ["wave_1", "wave_2", "wave_3"]
+        .iter()
+        .map(|w| {
+            assert!(h.screen.show(w));
+            td.recognize_wave_status(rect, false).map(|s| s.current_wave)
+        })
+        .collect();
+    assert_eq!(waves, [Some(1), Some(2), Some(3)]);
+    // HUD 模式只读画面，不按任何键
+    assert!(h.commands().is_empty());
+}
+
+#[test]
+fn reads_wave_from_tab_panel_holding_tab() {
+    let (h, td) = setup();
+    h.screen.show("tab_wave_2");
+    let status = td.recognize_wave_status(TDConfig::default().hud_wave_loop_rect, true);
+    assert_eq!(status.map(|s| s.current_wave), Some(2));
+    let tab_presses = h.commands().iter().filter(|c| matches!(c, InputCommand::KeyDown { keycode: KEY_TAB, .. })).count();
+    assert_eq!(tab_presses, 2);
+}
+
+#[test]
+fn no_wave_on_settlement_screen() {
+    let (h, td) = setup();
+    h.screen.show("wave_3");
+    assert!(td.recognize_wave_status(TDConfig::default().hud_check_rect, false).is_some());
+    h.screen.show("结算胜利");
+    assert!(td.recognize_wave_status(TDConfig::default().hud_check_rect, false).is_none());
+    assert_eq!(h.engine.detect_current_scene().as_deref(), Some("结算胜利"));
+}
@@ -1,16 +1,29 @@
 #![windows_subsystem = "windows"]
 
+#[cfg(all(feature = "windows", not(windows)))]
+compile_error!("windows feature 只能在 Windows 上编译 (Linux 上请使用 --no-default-features --features linux)");
+
 use eframe::egui::{self, Color32, Pos2, Rect, RichText, Sense, Stroke, Vec2};
+use nzm_schema::map::{self as schema, TransitionAction};
+use nzm_schema::session::{self as session, SessionEvent, SessionEventKind};
 use screenshots::Screen;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use std::fs;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 use std::collections::VecDeque;
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
 
-// OCR 所需的引用
+// OCR 所需的引用 (Linux 上调用 tesseract 命令行)
+#[cfg(feature = "windows")]
 use std::io::Cursor;
+#[cfg(feature = "windows")]
+use windows::Globalization::Language;
+#[cfg(feature = "windows")]
 use windows::Media::Ocr::{OcrEngine, OcrResult}; 
+#[cfg(feature = "windows")]
 use windows::Graphics::Imaging::BitmapDecoder;
+#[cfg(feature = "windows")]
 use windows::Storage::Streams::{DataWriter, InMemoryRandomAccessStream};
 
 // ==========================================
@@ -23,32 +36,151 @@ enum RecognitionLogic { AND, OR }
 enum ElementKind {
     TextAnchor { text: String },
     ColorAnchor { color_hex: String, tolerance: u8 },
-    Button { target: String, post_delay: u32 },
+    /// 区域颜色锚点：pos_or_rect 为采样区域 (正方形)，区域平均色按 HSV 分量容差比较
+    HsvAnchor { color_hex: String, hue_tol: f32, sat_tol: f32, val_tol: f32 },
+    Button { target: String, post_delay: u32, extra: TransitionExtra },
+    /// 图像锚点：rect 内做模板匹配，file 为模板 PNG (相对地图文件所在目录)
+    ImageAnchor { file: String, threshold: f32 },
 }
 
-#[derive(Clone)]
+/// 跳转除目标与等待外的参数：点击 / 滚动位置与拖拽起点取元素中心
+#[derive(Clone, PartialEq, Default)]
+struct TransitionExtra {
+    action: TransitionAction,
+    /// action = key 时的按键名 (如 "esc")
+    key: String,
+    /// action = drag 时的终点
+    drag_to: [i32; 2],
+    /// action = scroll 时的滚轮格数 (正数向上)
+    delta: i32,
+    pre_delay: u32,
+    retries: u32,
+}
+
+impl TransitionExtra {
+    /// 连线标签等处的简短描述
+    fn describe(&self, center: Pos2) -> String {
+        match self.action {
+            TransitionAction::Click => format!("({}, {})", center.x as i32, center.y as i32),
+            TransitionAction::Key => format!("key {}", self.key),
+            TransitionAction::Drag => format!("drag ({}, {}) → ({}, {})", center.x as i32, center.y as i32, self.drag_to[0], self.drag_to[1]),
+            TransitionAction::Scroll => format!("scroll {} @ ({}, {})", self.delta, center.x as i32, center.y as i32),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq)]
 struct UIElementDraft {
     pos_or_rect: Rect,
     kind: ElementKind,
 }
 
-#[derive(Deserialize)]
-struct TomlRoot { scenes: Vec<TomlScene> }
-#[derive(Deserialize)]
-struct TomlScene { id: String, name: String, logic: Option<String>, anchors: Option<TomlAnchors>, transitions: Option<Vec<TomlTransition>>, handler: Option<String> }
-#[derive(Deserialize)]
-struct TomlAnchors { text: Option<Vec<TomlTextAnchor>>, color: Option<Vec<TomlColorAnchor>> }
-#[derive(Deserialize)]
-struct TomlTextAnchor { rect: [i32; 4], val: String }
-#[derive(Deserialize)]
-struct TomlColorAnchor { pos: [i32; 2], val: String, tol: u8 }
-#[derive(Deserialize)]
-struct TomlTransition { target: String, coords: [i32; 2], post_delay: u32 }
+/// 左侧面板与中央区域的视图
+#[derive(Clone, Copy, PartialEq)]
+enum ViewMode {
+    /// 场景编辑 (截图上画锚点与跳转)
+    Edit,
+    /// 场景可视化 (节点图)
+    Visualize,
+    /// 快速截取：旧版简易模式，框选后直接记录锚点 / 点击，输出地图片段，不改动场景
+    QuickCapture,
+}
+
+/// 快速截取记录的一条，直接使用主程序的地图类型 (nzm_schema)
+enum QuickEntry {
+    Text(schema::TextAnchor),
+    Color(schema::ColorAnchor),
+    Click(schema::Transition),
+}
+
+impl QuickEntry {
+    /// 截图上的位置 (颜色锚点与点击为 1 像素)
+    fn rect(&self) -> Rect {
+        let point = |p: [i32; 2]| Rect::from_min_size(Pos2::new(p[0] as f32, p[1] as f32), Vec2::splat(1.0));
+        match self {
+            QuickEntry::Text(t) => Rect::from_min_max(Pos2::new(t.rect[0] as f32, t.rect[1] as f32), Pos2::new(t.rect[2] as f32, t.rect[3] as f32)),
+            QuickEntry::Color(c) => point(c.pos),
+            QuickEntry::Click(t) => point(t.coords),
+        }
+    }
+
+    fn describe(&self) -> String {
+        match self {
+            QuickEntry::Text(t) => format!("⚓ {:?} {:?}", t.val, t.rect),
+            QuickEntry::Color(c) => format!("🧪 {} {:?}", c.val, c.pos),
+            QuickEntry::Click(t) => format!("🖱️ {:?}", t.coords),
+        }
+    }
+}
+
+/// 快速截取的记录整理成一个 [[scenes]] 片段，可直接粘贴进 ui_map.toml
+fn quick_capture_toml(entries: &[QuickEntry]) -> Result<String, String> {
+    let mut scene = schema::Scene { id: "quick_capture".into(), ..Default::default() };
+    let mut anchors = schema::Anchors::default();
+    for entry in entries {
+        match entry {
+            QuickEntry::Text(t) => anchors.text.get_or_insert_with(Vec::new).push(t.clone()),
+            QuickEntry::Color(c) => anchors.color.get_or_insert_with(Vec::new).push(c.clone()),
+            QuickEntry::Click(t) => scene.transitions.get_or_insert_with(Vec::new).push(t.clone()),
+        }
+    }
+    if anchors.text.is_some() || anchors.color.is_some() {
+        scene.anchors = Some(anchors);
+    }
+    let map = schema::MapFile { scenes: vec![scene], ..Default::default() };
+    toml::to_string(&map).map_err(|e| e.to_string())
+}
+
+/// 在截图上拖动已有元素：整体移动，或拖动某个角 (0 左上, 1 右上, 2 右下, 3 左下) 调整大小
+#[derive(Clone, Copy)]
+enum ElementDrag { Move, Corner(usize) }
+
+/// 角点手柄的拾取半径 (屏幕像素)
+const HANDLE_RADIUS: f32 = 6.0;
+
+/// 选中颜色锚点时，预览其周围多少像素范围内的容差命中情况
+const TOLERANCE_PREVIEW_RADIUS: i32 = 20;
+
+/// 容差预览中判断单个像素是否命中
+type PixelMatcher = Box<dyn Fn([u8; 3]) -> bool>;
+
+fn hex_to_color32(hex: &str) -> Color32 {
+    let [r, g, b] = parse_hex_color(hex);
+    Color32::from_rgb(r, g, b)
+}
+
+/// 选区复制到剪贴板的写法：TOML 中的 `[x1, y1, x2, y2]`，以及跳转用的中心点 `[x, y]`
+fn rect_text(r: Rect) -> String {
+    format!("[{}, {}, {}, {}]", r.min.x as i32, r.min.y as i32, r.max.x as i32, r.max.y as i32)
+}
+
+fn center_text(r: Rect) -> String {
+    format!("[{}, {}]", r.center().x as i32, r.center().y as i32)
+}
+
+fn rect_corners(r: Rect) -> [Pos2; 4] {
+    [r.left_top(), r.right_top(), r.right_bottom(), r.left_bottom()]
+}
+
+/// 按拖动方式更新元素区域：移动时平移 delta，调整大小时把对应角移到 target (保持至少 1 像素)
+fn drag_rect(r: Rect, drag: ElementDrag, delta: Vec2, target: Pos2) -> Rect {
+    match drag {
+        ElementDrag::Move => r.translate(delta),
+        ElementDrag::Corner(c) => {
+            let mut r = r;
+            if c == 0 || c == 3 { r.min.x = target.x.min(r.max.x - 1.0); } else { r.max.x = target.x.max(r.min.x + 1.0); }
+            if c == 0 || c == 1 { r.min.y = target.y.min(r.max.y - 1.0); } else { r.max.y = target.y.max(r.min.y + 1.0); }
+            r
+        }
+    }
+}
+
+// 地图文件的读取结构见 nzm_schema::map (与主程序共用)
 
 // ==========================================
 // 1.5 场景结构
 // ==========================================
-#[derive(Clone)]
+#[derive(Clone, PartialEq)]
 struct Scene {
     id: String,
     name: String,
@@ -57,6 +189,9 @@ struct Scene {
     handler: Option<String>,
     viz_pos: Pos2,
     viz_size: Vec2,
+    /// 参考截图在 MapBuilderTool::shot_store 中的键 (打开的图片为其路径，实时截图为 capture-N)；
+    /// 切换场景时显示各自的截图，避免把锚点画到别的画面上
+    shot: Option<String>,
 }
 
 impl Default for Scene {
@@ -69,38 +204,456 @@ impl Default for Scene {
             handler: None,
             viz_pos: Pos2::ZERO,
             viz_size: Vec2::new(150.0, 80.0),
+            shot: None,
+        }
+    }
+}
+
+// ==========================================
+// 1.6 撤销 / 重做
+// ==========================================
+/// 撤销栈的最大步数
+const HISTORY_LIMIT: usize = 100;
+
+#[derive(Clone)]
+struct Snapshot {
+    scenes: Vec<Scene>,
+    current: usize,
+}
+
+/// 编辑历史：每帧把场景列表与上次提交的快照比较，有变化且操作已结束 (鼠标松开、文本框失去焦点) 时记为一步。
+/// 锚点增删改、场景新建 / 删除 / 复制、可视化中拖动场景都会被记录；一次拖动或一段连续输入合并为一步。
+struct EditHistory {
+    undo: Vec<Snapshot>,
+    redo: Vec<Snapshot>,
+    committed: Snapshot,
+}
+
+impl EditHistory {
+    fn new(scenes: &[Scene], current: usize) -> Self {
+        Self { undo: Vec::new(), redo: Vec::new(), committed: Snapshot { scenes: scenes.to_vec(), current } }
+    }
+
+    /// 每帧调用；interacting 为 true (鼠标按住或正在输入) 时暂不提交
+    fn track(&mut self, scenes: &[Scene], current: usize, interacting: bool) {
+        if scenes == self.committed.scenes.as_slice() {
+            self.committed.current = current;
+        } else if !interacting {
+            self.commit(scenes, current);
+        }
+    }
+
+    /// 把当前状态记为新的一步 (与上次提交相同时不记)
+    fn commit(&mut self, scenes: &[Scene], current: usize) {
+        if scenes == self.committed.scenes.as_slice() {
+            return;
+        }
+        let previous = std::mem::replace(&mut self.committed, Snapshot { scenes: scenes.to_vec(), current });
+        self.undo.push(previous);
+        if self.undo.len() > HISTORY_LIMIT {
+            self.undo.remove(0);
+        }
+        self.redo.clear();
+    }
+
+    fn undo(&mut self, scenes: &mut Vec<Scene>, current: &mut usize) -> bool {
+        self.commit(scenes, *current);
+        let Some(previous) = self.undo.pop() else { return false };
+        self.redo.push(std::mem::replace(&mut self.committed, previous));
+        self.restore(scenes, current);
+        true
+    }
+
+    fn redo(&mut self, scenes: &mut Vec<Scene>, current: &mut usize) -> bool {
+        self.commit(scenes, *current);
+        let Some(next) = self.redo.pop() else { return false };
+        self.undo.push(std::mem::replace(&mut self.committed, next));
+        self.restore(scenes, current);
+        true
+    }
+
+    fn restore(&self, scenes: &mut Vec<Scene>, current: &mut usize) {
+        *scenes = self.committed.scenes.clone();
+        *current = self.committed.current.min(scenes.len().saturating_sub(1));
+    }
+}
+
+// ==========================================
+// 1.7 工具设置 (截图来源)
+// ==========================================
+/// 工具自身的设置，保存在工作目录下，下次启动沿用
+const SETTINGS_FILE: &str = "ui_tool_settings.toml";
+
+#[derive(Serialize, Deserialize, Default, Clone)]
+#[serde(default)]
+struct ToolSettings {
+    /// 截图使用的显示器 ID；为空或该显示器已不存在时使用第一个显示器
+    monitor_id: Option<u32>,
+    /// 设置后只截取该标题窗口的客户区，锚点坐标相对于窗口 (优先于 monitor_id)
+    window_title: Option<String>,
+    /// 最近打开 / 保存的地图文件，文件对话框从其所在目录开始
+    last_map_file: Option<String>,
+    /// 区域识别使用的 OCR：WinRT 识别语言 (BCP-47，如 zh-Hans)，"paddle" 为 PaddleOCR，空为系统用户语言
+    ocr: String,
+    /// F9 热键截图时加入队列 (事后逐张查看)，而不是直接替换当前截图
+    queue_captures: bool,
+}
+
+impl ToolSettings {
+    fn load() -> Self {
+        fs::read_to_string(SETTINGS_FILE).ok().and_then(|s| toml::from_str(&s).ok()).unwrap_or_default()
+    }
+
+    fn save(&self) -> Result<(), String> {
+        let content = toml::to_string(self).map_err(|e| e.to_string())?;
+        fs::write(SETTINGS_FILE, content).map_err(|e| e.to_string())
+    }
+}
+
+// ==========================================
+// 1.8 项目文件 (目录)
+// ==========================================
+/// 项目目录结构：
+/// project.toml   布局与编辑器状态 (本结构)
+/// map.toml       地图
+/// shots/*.png    各场景的参考截图
+/// anchors/*.png  图像锚点模板
+const PROJECT_FILE: &str = "project.toml";
+const PROJECT_MAP_FILE: &str = "map.toml";
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+struct ProjectFile {
+    scenes: Vec<ProjectScene>,
+    editor: ProjectEditor,
+}
+
+/// 按场景 ID 记录的可视化节点位置与参考截图 (相对项目目录)
+#[derive(Serialize, Deserialize)]
+struct ProjectScene {
+    id: String,
+    viz_pos: [f32; 2],
+    viz_size: [f32; 2],
+    shot: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+struct ProjectEditor {
+    current_scene: usize,
+    show_visualization: bool,
+    viz_pan: [f32; 2],
+    viz_zoom: f32,
+    capture_offset: Option<[i32; 2]>,
+    capture_display: Option<([u32; 2], f32)>,
+    settings: ToolSettings,
+}
+
+/// 场景 ID 转为文件名：去掉文件名中不允许的字符
+fn file_stem(id: &str) -> String {
+    id.chars().map(|c| if r#"\/:*?"<>|"#.contains(c) { '_' } else { c }).collect()
+}
+
+/// 截取一帧：返回图像，以及窗口模式下客户区左上角相对所在显示器的偏移
+fn capture_frame(settings: &ToolSettings) -> Option<(image::RgbaImage, Option<[i32; 2]>)> {
+    let Some(title) = &settings.window_title else {
+        return capture_screen(settings.monitor_id).map(|img| (img, None));
+    };
+    let (x, y, w, h) = window_client_rect(title)?;
+    let screen = Screen::from_point(x, y).ok()?;
+    let offset = [x - screen.display_info.x, y - screen.display_info.y];
+    let full = screen.capture().ok()?;
+    // 窗口超出显示器的部分裁掉
+    let (cx, cy) = (offset[0].max(0) as u32, offset[1].max(0) as u32);
+    let cw = w.min(full.width().saturating_sub(cx));
+    let ch = h.min(full.height().saturating_sub(cy));
+    if cw == 0 || ch == 0 {
+        return None;
+    }
+    Some((image::imageops::crop_imm(&full, cx, cy, cw, ch).to_image(), Some(offset)))
+}
+
+/// 截图来源所在显示器的分辨率与 DPI 缩放，生成 TOML 时写入 base_resolution / dpi_scale
+fn capture_display(settings: &ToolSettings) -> Option<([u32; 2], f32)> {
+    let screen = match &settings.window_title {
+        Some(title) => {
+            let (x, y, _, _) = window_client_rect(title)?;
+            Screen::from_point(x, y).ok()?
+        }
+        None => pick_screen(settings.monitor_id)?,
+    };
+    let d = screen.display_info;
+    Some(([d.width, d.height], d.scale_factor))
+}
+
+/// 列出所有可见且有标题的顶层窗口
+#[cfg(feature = "windows")]
+fn list_windows() -> Vec<String> {
+    use winapi::shared::minwindef::{BOOL, LPARAM, TRUE};
+    use winapi::shared::windef::HWND;
+    use winapi::um::winuser::{EnumWindows, GetWindowTextW, IsWindowVisible};
+
+    unsafe extern "system" fn collect(hwnd: HWND, lparam: LPARAM) -> BOOL {
+        unsafe {
+            let titles = &mut *(lparam as *mut Vec<String>);
+            if IsWindowVisible(hwnd) != 0 {
+                let mut buf = [0u16; 256];
+                let len = GetWindowTextW(hwnd, buf.as_mut_ptr(), buf.len() as i32);
+                if len > 0 {
+                    titles.push(String::from_utf16_lossy(&buf[..len as usize]));
+                }
+            }
+        }
+        TRUE
+    }
+
+    let mut titles: Vec<String> = Vec::new();
+    unsafe { EnumWindows(Some(collect), &mut titles as *mut Vec<String> as LPARAM); }
+    titles.sort();
+    titles.dedup();
+    titles
+}
+
+/// 按标题查找窗口，返回客户区在虚拟桌面上的 (x, y, 宽, 高)
+#[cfg(feature = "windows")]
+fn window_client_rect(title: &str) -> Option<(i32, i32, u32, u32)> {
+    use winapi::shared::windef::{POINT, RECT};
+    use winapi::um::winuser::{ClientToScreen, FindWindowW, GetClientRect, IsIconic};
+
+    let wide: Vec<u16> = title.encode_utf16().chain(std::iter::once(0)).collect();
+    unsafe {
+        let hwnd = FindWindowW(std::ptr::null(), wide.as_ptr());
+        // 最小化的窗口客户区为空
+        if hwnd.is_null() || IsIconic(hwnd) != 0 {
+            return None;
+        }
+        let mut rect = RECT { left: 0, top: 0, right: 0, bottom: 0 };
+        let mut origin = POINT { x: 0, y: 0 };
+        if GetClientRect(hwnd, &mut rect) == 0 || ClientToScreen(hwnd, &mut origin) == 0 {
+            return None;
+        }
+        Some((origin.x, origin.y, (rect.right - rect.left) as u32, (rect.bottom - rect.top) as u32))
+    }
+}
+
+/// Linux (X11)：wmctrl -lG 列出的窗口，(标题, x, y, 宽, 高)；Wayland 下不可用
+#[cfg(not(feature = "windows"))]
+fn wmctrl_windows() -> Vec<(String, i32, i32, u32, u32)> {
+    let Ok(output) = std::process::Command::new("wmctrl").arg("-lG").output() else { return Vec::new() };
+    String::from_utf8_lossy(&output.stdout).lines().filter_map(|line| {
+        // 窗口 ID、桌面、x、y、宽、高、主机名，其余为标题 (标题内可能有空格)
+        let mut rest = line;
+        let mut fields = Vec::new();
+        for _ in 0..7 {
+            let t = rest.trim_start();
+            let end = t.find(char::is_whitespace).unwrap_or(t.len());
+            fields.push(&t[..end]);
+            rest = &t[end..];
+        }
+        let title = rest.trim();
+        if title.is_empty() {
+            return None;
+        }
+        Some((title.to_string(), fields[2].parse().ok()?, fields[3].parse().ok()?, fields[4].parse().ok()?, fields[5].parse().ok()?))
+    }).collect()
+}
+
+#[cfg(not(feature = "windows"))]
+fn list_windows() -> Vec<String> {
+    let mut titles: Vec<String> = wmctrl_windows().into_iter().map(|w| w.0).collect();
+    titles.sort();
+    titles.dedup();
+    titles
+}
+
+/// wmctrl 给出的是窗口几何 (可能含标题栏)，需要时在截图上再框选
+#[cfg(not(feature = "windows"))]
+fn window_client_rect(title: &str) -> Option<(i32, i32, u32, u32)> {
+    wmctrl_windows().into_iter().find(|w| w.0 == title).map(|(_, x, y, w, h)| (x, y, w, h))
+}
+
+/// 列出所有显示器：(ID, 下拉框中显示的名称)
+/// 热键截图的结果：截图失败时为 None
+type HotkeyCapture = Option<(image::RgbaImage, Option<[i32; 2]>)>;
+
+/// 后台轮询全局截图热键 (F9)：工具窗口最小化 / 在后台时也能截图，深层菜单不必赶 3 秒倒计时。
+/// 截图来源取 settings 的最新值 (界面每帧同步)，成功时响一声提示
+#[cfg(feature = "windows")]
+fn spawn_capture_hotkey(ctx: egui::Context, settings: Arc<Mutex<ToolSettings>>, tx: mpsc::Sender<HotkeyCapture>) {
+    use winapi::um::winuser::{GetAsyncKeyState, MessageBeep, MB_OK, VK_F9};
+    thread::spawn(move || loop {
+        if unsafe { GetAsyncKeyState(VK_F9) } & 0x0001 != 0 {
+            let current = settings.lock().map(|s| s.clone()).unwrap_or_default();
+            let frame = capture_frame(&current);
+            if frame.is_some() {
+                unsafe { MessageBeep(MB_OK); }
+            }
+            if tx.send(frame).is_err() {
+                return;
+            }
+            ctx.request_repaint();
         }
+        thread::sleep(Duration::from_millis(50));
+    });
+}
+
+/// Linux：从 /dev/input 的键盘设备读取 F9 (需 input 组权限，无权限时热键不可用)
+#[cfg(not(feature = "windows"))]
+fn spawn_capture_hotkey(ctx: egui::Context, settings: Arc<Mutex<ToolSettings>>, tx: mpsc::Sender<HotkeyCapture>) {
+    use std::io::Read;
+    // struct input_event (64 位)：timeval 16 字节，type u16，code u16，value i32
+    const EVENT_SIZE: usize = 24;
+    const EV_KEY: u16 = 1;
+    const KEY_F9: u16 = 67;
+    let keyboards = fs::read_dir("/dev/input/by-path").into_iter().flatten().flatten()
+        .map(|e| e.path())
+        .filter(|p| p.to_string_lossy().ends_with("-event-kbd"));
+    for path in keyboards {
+        let Ok(mut device) = fs::File::open(&path) else { continue };
+        let (ctx, settings, tx) = (ctx.clone(), settings.clone(), tx.clone());
+        thread::spawn(move || {
+            let mut event = [0u8; EVENT_SIZE];
+            while device.read_exact(&mut event).is_ok() {
+                let kind = u16::from_ne_bytes([event[16], event[17]]);
+                let code = u16::from_ne_bytes([event[18], event[19]]);
+                let value = i32::from_ne_bytes([event[20], event[21], event[22], event[23]]);
+                if kind != EV_KEY || code != KEY_F9 || value != 1 {
+                    continue;
+                }
+                let current = settings.lock().map(|s| s.clone()).unwrap_or_default();
+                if tx.send(capture_frame(&current)).is_err() {
+                    return;
+                }
+                ctx.request_repaint();
+            }
+        });
     }
 }
 
+fn list_monitors() -> Vec<(u32, String)> {
+    Screen::all().unwrap_or_default().iter().enumerate()
+        .map(|(i, s)| {
+            let d = &s.display_info;
+            (d.id, format!("显示器 {}: {}x{} @ ({}, {})", i + 1, d.width, d.height, d.x, d.y))
+        })
+        .collect()
+}
+
 // ==========================================
 // 2. 编辑器状态
 // ==========================================
+/// 可视化中新建 / 编辑跳转的弹窗状态
+struct TransitionEditor {
+    scene: usize,
+    /// 编辑已有跳转时为其元素序号，新建时为 None
+    draft: Option<usize>,
+    target: String,
+    /// 点击 / 滚动位置，拖拽起点
+    coords: [i32; 2],
+    post_delay: u32,
+    extra: TransitionExtra,
+}
+
+/// 批量重新 OCR 的一条差异：对新截图重新识别某个文字锚点，由用户逐条决定是否采用
+struct ReocrEntry {
+    scene: usize,
+    draft: usize,
+    old: String,
+    new: String,
+    accept: bool,
+}
+
 struct MapBuilderTool {
     texture: Option<egui::TextureHandle>,
     raw_image: Option<image::RgbaImage>, 
     img_size: Vec2,
+    /// 参考截图 (键见 Scene::shot)，随项目保存
+    shot_store: std::collections::HashMap<String, image::RgbaImage>,
+    /// 当前显示的截图的键，以及生成下一个实时截图键的序号
+    shown_shot: Option<String>,
+    shot_seq: usize,
     
-    ocr_engine: Option<OcrEngine>,
+    ocr_engine: Option<RegionOcr>,
+    /// 系统已安装的 WinRT 识别语言 (标签, 显示名)
+    ocr_languages: Vec<(String, String)>,
     ocr_test_result: String, 
+    /// 导出数字模板时对应的数字 (0~9)
+    digit_export_value: u8,
+
+    // 锚点稳定性采样
+    stability_duration_secs: u32,
+    stability_interval_ms: u32,
+    stability_run: Option<StabilityRun>,
+    /// (场景序号, 采样时的元素列表, 结果)：结果按元素序号对应，场景元素有改动时作废
+    stability_results: Option<(usize, Vec<UIElementDraft>, Vec<AnchorStability>)>,
+    // 实时锚点测试 (场景序号, 结果)
+    live_test_rx: Option<(usize, mpsc::Receiver<Option<Vec<AnchorCheck>>>)>,
+    live_test_results: Option<(usize, Vec<AnchorCheck>)>,
+    // 批量重新 OCR：是否覆盖全部场景，以及待确认的差异
+    reocr_all_scenes: bool,
+    reocr_review: Option<Vec<ReocrEntry>>,
+    // 离线回归：后台任务、结果与是否只显示有误的截图
+    regression_run: Option<RegressionRun>,
+    regression_results: Option<Vec<RegressionRow>>,
+    regression_failures_only: bool,
+    session_replay: Option<SessionReplay>,
+    /// 整图 OCR 得到的候选锚点 (对应当前截图)，点击采用为文字锚点
+    anchor_suggestions: Vec<AnchorSuggestion>,
 
     scenes: Vec<Scene>,
     current_scene_index: usize,
+    /// 场景编辑的撤销 / 重做 (Ctrl+Z / Ctrl+Y)
+    history: EditHistory,
     
     start_pos: Option<Pos2>,
     current_rect: Option<Rect>,
     is_color_picker_mode: bool,
+    /// 截图上选中的元素 (场景序号, 元素序号) 与正在进行的拖动
+    selected_draft: Option<(usize, usize)>,
+    element_drag: Option<(usize, ElementDrag)>,
     capture_timer: Option<Instant>, 
+    /// 全局热键截图：共享给热键线程的截图来源、结果通道，以及排队模式下的截图队列与当前查看位置
+    hotkey_settings: Arc<Mutex<ToolSettings>>,
+    hotkey_rx: mpsc::Receiver<HotkeyCapture>,
+    /// (参考截图键, 图像, 客户区偏移)
+    capture_queue: Vec<(String, image::RgbaImage, Option<[i32; 2]>)>,
+    capture_queue_index: usize,
+    /// 截图使用的显示器 (见 ToolSettings)
+    settings: ToolSettings,
+    monitors: Vec<(u32, String)>,
+    windows: Vec<String>,
+    /// 最近一次窗口截图时客户区相对显示器的偏移，生成 TOML 时写入 window_offset
+    capture_offset: Option<[i32; 2]>,
+    /// 最近一次实时截图所在显示器的分辨率与 DPI 缩放，生成 TOML 时写入 base_resolution / dpi_scale
+    capture_display: Option<([u32; 2], f32)>,
 
     toml_content: String,
+    /// 等待确认的保存 (与磁盘文件的差异)
+    save_review: Option<SaveReview>,
+    /// 当前项目目录 (见 1.8)
+    project_dir: Option<std::path::PathBuf>,
+    /// 当前地图文件及其载入 / 保存时的修改时间 (用于发现外部修改)
+    map_file: Option<(std::path::PathBuf, Option<std::time::SystemTime>)>,
+    /// 最近一次导入/生成的文档树 (保留注释、空行与键顺序)
+    source_doc: Option<toml_edit::DocumentMut>,
     status_msg: String,
     
+    view: ViewMode,
+    /// 快速截取的记录
+    quick_entries: Vec<QuickEntry>,
+
     // 可视化相关
-    show_visualization: bool,
     viz_dragging_scene: Option<usize>,
     viz_drag_offset: Vec2,
+    /// Shift+拖拽连线的起点场景
+    viz_link_from: Option<usize>,
+    transition_editor: Option<TransitionEditor>,
     viz_pan: Vec2,
     viz_zoom: f32,
+
+    // 截图编辑的缩放 (相对适应面板大小的倍数) 与平移
+    shot_zoom: f32,
+    shot_pan: Vec2,
 }
 
 impl MapBuilderTool {
@@ -112,6 +665,135 @@ impl MapBuilderTool {
         &mut self.scenes[self.current_scene_index]
     }
     
+    /// 当前场景中选中的元素序号 (切换场景或元素被删除后失效)
+    fn selected_draft(&self) -> Option<usize> {
+        self.selected_draft
+            .filter(|(scene, draft)| *scene == self.current_scene_index && *draft < self.current_scene().drafts.len())
+            .map(|(_, draft)| draft)
+    }
+
+    /// 截图上某个屏幕位置下的元素：优先选中元素的角点手柄，其次是最上层的元素本体
+    fn hit_test_element(&self, p: Pos2, to_screen: &dyn Fn(Pos2) -> Pos2) -> Option<(usize, ElementDrag)> {
+        let drafts = &self.current_scene().drafts;
+        let screen_rect = |r: Rect| Rect::from_min_max(to_screen(r.min), to_screen(r.max)).expand(3.0);
+        if let Some(i) = self.selected_draft() {
+            // 颜色锚点是单个像素，只能移动
+            if !matches!(drafts[i].kind, ElementKind::ColorAnchor { .. }) {
+                let corner = rect_corners(drafts[i].pos_or_rect).iter().position(|c| to_screen(*c).distance(p) <= HANDLE_RADIUS);
+                if let Some(c) = corner {
+                    return Some((i, ElementDrag::Corner(c)));
+                }
+            }
+            if screen_rect(drafts[i].pos_or_rect).contains(p) {
+                return Some((i, ElementDrag::Move));
+            }
+        }
+        drafts.iter().enumerate().rev()
+            .find(|(_, d)| screen_rect(d.pos_or_rect).contains(p))
+            .map(|(i, _)| (i, ElementDrag::Move))
+    }
+
+    /// 方向键微调选中元素：移动 1 像素，按住 Shift 时调整右下角
+    fn nudge_selected(&mut self, ctx: &egui::Context) {
+        let Some(i) = self.selected_draft() else { return };
+        if ctx.wants_keyboard_input() { return; }
+        let (delta, resize, delete) = ctx.input_mut(|input| {
+            let mut delta = Vec2::ZERO;
+            let mut resize = false;
+            for (key, d) in [(egui::Key::ArrowLeft, Vec2::new(-1.0, 0.0)), (egui::Key::ArrowRight, Vec2::new(1.0, 0.0)),
+                             (egui::Key::ArrowUp, Vec2::new(0.0, -1.0)), (egui::Key::ArrowDown, Vec2::new(0.0, 1.0))] {
+                if input.consume_key(egui::Modifiers::NONE, key) { delta += d; }
+                if input.consume_key(egui::Modifiers::SHIFT, key) { delta += d; resize = true; }
+            }
+            (delta, resize, input.consume_key(egui::Modifiers::NONE, egui::Key::Delete))
+        });
+        if delete {
+            self.current_scene_mut().drafts.remove(i);
+            self.selected_draft = None;
+            self.status_msg = "已删除选中元素".into();
+        } else if delta != Vec2::ZERO {
+            let draft = &mut self.current_scene_mut().drafts[i];
+            let r = draft.pos_or_rect;
+            draft.pos_or_rect = if resize && !matches!(draft.kind, ElementKind::ColorAnchor { .. }) {
+                drag_rect(r, ElementDrag::Corner(2), delta, r.max + delta)
+            } else {
+                r.translate(delta)
+            };
+        }
+    }
+
+    /// 用选区添加文字锚点，文字取最近一次区域 OCR 的结果
+    fn add_text_anchor(&mut self, rect: Rect) {
+        let val = if self.ocr_test_result.is_empty() || self.ocr_test_result.contains("...") { "Text".to_string() } else { self.ocr_test_result.clone() };
+        self.current_scene_mut().drafts.push(UIElementDraft { pos_or_rect: rect, kind: ElementKind::TextAnchor { text: val } });
+        self.current_rect = None;
+    }
+
+    /// 用选区左上角的像素添加颜色锚点
+    fn add_color_anchor(&mut self, rect: Rect) {
+        let color = self.pick_color(rect.min);
+        let rect = Rect::from_min_size(rect.min, Vec2::splat(1.0));
+        self.current_scene_mut().drafts.push(UIElementDraft { pos_or_rect: rect, kind: ElementKind::ColorAnchor { color_hex: color, tolerance: 15 } });
+        self.current_rect = None;
+    }
+
+    /// 用选区添加区域颜色锚点：采样区域取以选区左上角为起点、较长边为边长的正方形 (选区过小时用默认边长)，颜色取区域平均色
+    fn add_hsv_anchor(&mut self, rect: Rect) {
+        let side = rect.width().max(rect.height()).round();
+        let side = if side < 2.0 { schema::default_hsv_size() as f32 } else { side };
+        let rect = Rect::from_min_size(rect.min.round(), Vec2::splat(side));
+        let color = self.average_color(rect);
+        let kind = ElementKind::HsvAnchor {
+            color_hex: color,
+            hue_tol: schema::default_hue_tol(),
+            sat_tol: schema::default_sat_tol(),
+            val_tol: schema::default_val_tol(),
+        };
+        self.current_scene_mut().drafts.push(UIElementDraft { pos_or_rect: rect, kind });
+        self.current_rect = None;
+    }
+
+    fn add_button(&mut self, rect: Rect) {
+        self.current_scene_mut().drafts.push(UIElementDraft { pos_or_rect: rect, kind: ElementKind::Button { target: "next".into(), post_delay: 500, extra: TransitionExtra::default() } });
+        self.current_rect = None;
+    }
+
+    /// 常用操作的快捷键 (文本框有焦点时不响应)：
+    /// S 延时截图，A / C / H / B 用当前选区添加文字锚点 / 颜色锚点 / 区域颜色锚点 / 跳转，Ctrl+S 保存，Tab / Shift+Tab 切换场景
+    fn handle_shortcuts(&mut self, ctx: &egui::Context) {
+        if ctx.wants_keyboard_input() { return; }
+        let [capture, text, color, hsv, button, save, next, prev] = ctx.input_mut(|i| {
+            [
+                i.consume_key(egui::Modifiers::NONE, egui::Key::S),
+                i.consume_key(egui::Modifiers::NONE, egui::Key::A),
+                i.consume_key(egui::Modifiers::NONE, egui::Key::C),
+                i.consume_key(egui::Modifiers::NONE, egui::Key::H),
+                i.consume_key(egui::Modifiers::NONE, egui::Key::B),
+                i.consume_key(egui::Modifiers::COMMAND, egui::Key::S),
+                i.consume_key(egui::Modifiers::NONE, egui::Key::Tab),
+                i.consume_key(egui::Modifiers::SHIFT, egui::Key::Tab),
+            ]
+        });
+        if capture && self.capture_timer.is_none() {
+            self.capture_timer = Some(Instant::now());
+        }
+        if let Some(rect) = self.current_rect.filter(|_| self.view == ViewMode::Edit) {
+            if text { self.add_text_anchor(rect); }
+            else if color { self.add_color_anchor(rect); }
+            else if hsv { self.add_hsv_anchor(rect); }
+            else if button { self.add_button(rect); }
+        }
+        if save {
+            // 打开了项目时保存整个项目，否则只保存地图文件
+            if self.project_dir.is_some() { self.save_project(false); } else { self.save_map_file(false); }
+        }
+        if (next || prev) && !self.scenes.is_empty() {
+            let n = self.scenes.len();
+            self.current_scene_index = if next { (self.current_scene_index + 1) % n } else { (self.current_scene_index + n - 1) % n };
+            self.status_msg = format!("已切换到场景：{}", self.current_scene().name);
+        }
+    }
+
     fn add_new_scene(&mut self) {
         let new_id = format!("scene_{}", self.scenes.len() + 1);
         let new_name = format!("新场景 {}", self.scenes.len() + 1);
@@ -127,11 +809,28 @@ impl MapBuilderTool {
             handler: None,
             viz_pos,
             viz_size: Vec2::new(150.0, 80.0),
+            shot: None,
         });
         self.current_scene_index = self.scenes.len() - 1;
         self.status_msg = "已添加新场景".into();
     }
     
+    fn undo(&mut self) {
+        self.status_msg = if self.history.undo(&mut self.scenes, &mut self.current_scene_index) {
+            format!("已撤销 (还可撤销 {} 步)", self.history.undo.len())
+        } else {
+            "没有可撤销的操作".into()
+        };
+    }
+
+    fn redo(&mut self) {
+        self.status_msg = if self.history.redo(&mut self.scenes, &mut self.current_scene_index) {
+            format!("已重做 (还可重做 {} 步)", self.history.redo.len())
+        } else {
+            "没有可重做的操作".into()
+        };
+    }
+
     fn delete_current_scene(&mut self) {
         if self.scenes.len() > 1 {
             self.scenes.remove(self.current_scene_index);
@@ -157,6 +856,7 @@ impl MapBuilderTool {
             handler: scene.handler.clone(),
             viz_pos: new_viz_pos,
             viz_size: scene.viz_size,
+            shot: scene.shot.clone(),
         });
         self.current_scene_index = self.scenes.len() - 1;
         self.status_msg = "已复制场景".into();
@@ -169,8 +869,12 @@ impl MapBuilderTool {
     fn new(cc: &eframe::CreationContext<'_>) -> Self {
         setup_custom_fonts(&cc.egui_ctx);
         
-        let engine = OcrEngine::TryCreateFromUserProfileLanguages().ok();
-        let status = if engine.is_some() { "OCR 引擎就绪" } else { "⚠️ OCR 初始化失败" };
+        let settings = ToolSettings::load();
+        let engine = RegionOcr::create(&settings.ocr);
+        let status = match &engine {
+            Ok(e) => format!("OCR 引擎就绪 ({})", e.name()),
+            Err(e) => format!("⚠️ OCR 初始化失败: {}", e),
+        };
 
         let initial_scene = Scene {
             id: "lobby_01".into(),
@@ -180,152 +884,782 @@ impl MapBuilderTool {
             handler: None,
             viz_pos: Pos2::new(100.0, 100.0),
             viz_size: Vec2::new(150.0, 80.0),
+            shot: None,
         };
+        let scenes = vec![initial_scene];
+        let history = EditHistory::new(&scenes, 0);
+
+        let hotkey_settings = Arc::new(Mutex::new(settings.clone()));
+        let (hotkey_tx, hotkey_rx) = mpsc::channel();
+        spawn_capture_hotkey(cc.egui_ctx.clone(), hotkey_settings.clone(), hotkey_tx);
 
         Self {
             texture: None,
             raw_image: None,
             img_size: Vec2::ZERO,
-            ocr_engine: engine,          
+            shot_store: std::collections::HashMap::new(),
+            shown_shot: None,
+            shot_seq: 0,
+            ocr_engine: engine.ok(),
+            ocr_languages: RegionOcr::languages(),
             ocr_test_result: String::new(), 
-            scenes: vec![initial_scene],
+            digit_export_value: 0,
+            stability_duration_secs: 30,
+            stability_interval_ms: 1000,
+            stability_run: None,
+            stability_results: None,
+            live_test_rx: None,
+            live_test_results: None,
+            reocr_all_scenes: false,
+            reocr_review: None,
+            regression_run: None,
+            regression_results: None,
+            regression_failures_only: false,
+            session_replay: None,
+            anchor_suggestions: Vec::new(),
+            scenes,
             current_scene_index: 0,
+            history,
             start_pos: None,
             current_rect: None,
             is_color_picker_mode: false,
+            selected_draft: None,
+            element_drag: None,
             capture_timer: None,
+            hotkey_settings,
+            hotkey_rx,
+            capture_queue: Vec::new(),
+            capture_queue_index: 0,
+            settings,
+            monitors: list_monitors(),
+            windows: Vec::new(),
+            capture_offset: None,
+            capture_display: None,
             toml_content: String::new(),
-            status_msg: status.into(),
-            
-            show_visualization: false,
+            save_review: None,
+            project_dir: None,
+            map_file: None,
+            source_doc: None,
+            status_msg: status,
+            view: ViewMode::Edit,
+            quick_entries: Vec::new(),
+
             viz_dragging_scene: None,
             viz_drag_offset: Vec2::ZERO,
+            viz_link_from: None,
+            transition_editor: None,
             viz_pan: Vec2::ZERO,
             viz_zoom: 1.0,
+            shot_zoom: 1.0,
+            shot_pan: Vec2::ZERO,
         }
     }
 
     fn capture_immediate(&mut self, ctx: &egui::Context) {
-        let screens = Screen::all().unwrap();
-        if let Some(screen) = screens.first() {
-            if let Ok(image) = screen.capture() {
-                self.img_size = Vec2::new(image.width() as f32, image.height() as f32);
-                self.raw_image = Some(image.clone()); 
-                let color_img = egui::ColorImage::from_rgba_unmultiplied(
-                    [image.width() as usize, image.height() as usize], 
-                    image.as_flat_samples().as_slice()
-                );
-                self.texture = Some(ctx.load_texture("shot", color_img, Default::default()));
-                self.status_msg = "截图成功".into();
-            }
+        if let Some((image, offset)) = capture_frame(&self.settings) {
+            self.capture_offset = offset;
+            self.capture_display = capture_display(&self.settings);
+            self.set_screenshot(ctx, image);
+            self.store_scene_shot(None);
+            self.status_msg = match offset {
+                Some([x, y]) => format!("窗口截图成功 (客户区偏移 {}, {})", x, y),
+                None => "截图成功".into(),
+            };
+        } else if let Some(title) = &self.settings.window_title {
+            self.status_msg = format!("⚠️ 截图失败：找不到窗口 [{}] 或窗口已最小化", title);
+        } else {
+            self.status_msg = "⚠️ 截图失败，请检查所选显示器".into();
         }
     }
 
-    fn pick_color(&self, p: Pos2) -> String {
-        if let Some(img) = &self.raw_image {
-            let x = p.x as u32;
-            let y = p.y as u32;
-            if x < img.width() && y < img.height() {
-                let pixel = img.get_pixel(x, y);
-                return format!("#{:02X}{:02X}{:02X}", pixel[0], pixel[1], pixel[2]);
+    /// 接收热键截图：排队模式下加入队列，否则直接作为当前截图
+    fn poll_hotkey_captures(&mut self, ctx: &egui::Context) {
+        if let Ok(mut shared) = self.hotkey_settings.lock() {
+            *shared = self.settings.clone();
+        }
+        while let Ok(frame) = self.hotkey_rx.try_recv() {
+            let Some((image, offset)) = frame else {
+                self.status_msg = "⚠️ 热键截图失败，请检查截图来源".into();
+                continue;
+            };
+            if self.settings.queue_captures {
+                // 入队时就分配键，来回翻看同一张不会重复存储
+                self.shot_seq += 1;
+                self.capture_queue.push((format!("capture-{}", self.shot_seq), image, offset));
+                self.capture_display = capture_display(&self.settings);
+                self.status_msg = format!("F9 截图已加入队列 (共 {} 张)", self.capture_queue.len());
+            } else {
+                self.capture_offset = offset;
+                self.capture_display = capture_display(&self.settings);
+                self.set_screenshot(ctx, image);
+                self.store_scene_shot(None);
+                self.status_msg = "F9 截图成功".into();
             }
         }
-        "#FFFFFF".into()
     }
 
-    fn build_toml(&mut self) {
-        let mut toml = String::new();
-        
-        for scene in &self.scenes {
-            let logic_str = if scene.logic == RecognitionLogic::AND { "and" } else { "or" };
-            toml.push_str(&format!("[[scenes]]\nid = \"{}\"\nname = \"{}\"\nlogic = \"{}\"\n", scene.id, scene.name, logic_str));
-            
-            if let Some(handler) = &scene.handler {
-                toml.push_str(&format!("handler = \"{}\"\n", handler));
+    /// 查看队列中的第 index 张截图，并作为当前场景的截图
+    fn show_queued_capture(&mut self, ctx: &egui::Context, index: usize) {
+        let Some((key, image, offset)) = self.capture_queue.get(index).cloned() else { return };
+        self.capture_queue_index = index;
+        self.capture_offset = offset;
+        self.set_screenshot(ctx, image);
+        self.store_scene_shot(Some(key));
+    }
+
+    fn draw_capture_queue(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            if ui.checkbox(&mut self.settings.queue_captures, "F9 截图排队").on_hover_text("F9 全局热键截图 (工具最小化时也有效)；勾选后加入队列，事后逐张查看").changed()
+                && let Err(e) = self.settings.save() {
+                self.status_msg = format!("⚠️ 保存设置失败: {}", e);
             }
-            
-            toml.push_str("\n[scenes.anchors]\n");
-            toml.push_str("text = [\n");
-            
-            for d in scene.drafts.iter() {
-                if let ElementKind::TextAnchor { text } = &d.kind {
-                    toml.push_str(&format!("  {{ rect = [{}, {}, {}, {}], val = \"{}\" }},\n",
-                        d.pos_or_rect.min.x as i32, d.pos_or_rect.min.y as i32, d.pos_or_rect.max.x as i32, d.pos_or_rect.max.y as i32, text));
-                }
+            if self.capture_queue.is_empty() {
+                return;
             }
-            
-            toml.push_str("]\ncolor = [\n");
-            
-            for d in scene.drafts.iter() {
-                if let ElementKind::ColorAnchor { color_hex, tolerance } = &d.kind {
-                    toml.push_str(&format!("  {{ pos = [{}, {}], val = \"{}\" , tol = {} }},\n",
-                        d.pos_or_rect.min.x as i32, d.pos_or_rect.min.y as i32, color_hex, tolerance));
-                }
+            let (n, i) = (self.capture_queue.len(), self.capture_queue_index.min(self.capture_queue.len() - 1));
+            if ui.add_enabled(i > 0, egui::Button::new("◀")).clicked() { self.show_queued_capture(ui.ctx(), i - 1); }
+            if ui.button(format!("{}/{}", i + 1, n)).on_hover_text("显示这张截图").clicked() { self.show_queued_capture(ui.ctx(), i); }
+            if ui.add_enabled(i + 1 < n, egui::Button::new("▶")).clicked() { self.show_queued_capture(ui.ctx(), i + 1); }
+            if ui.button("🗑").on_hover_text("清空队列").clicked() {
+                self.capture_queue.clear();
+                self.capture_queue_index = 0;
             }
-            
-            toml.push_str("]\n\n# --- 动作步骤 ---\n");
-            
-            for d in scene.drafts.iter() {
-                if let ElementKind::Button { target, post_delay } = &d.kind {
-                    toml.push_str("[[scenes.transitions]]\n");
-                    toml.push_str(&format!("target = \"{}\"\n", target));
-                    toml.push_str(&format!("coords = [{}, {}]\n", d.pos_or_rect.center().x as i32, d.pos_or_rect.center().y as i32));
-                    toml.push_str(&format!("post_delay = {}\n\n", post_delay));
-                }
+        });
+    }
+
+    /// 从图片文件载入截图 (其他机器采集的截图、历史运行的失败截图等)
+    fn open_image(&mut self, ctx: &egui::Context) {
+        let Some(path) = rfd::FileDialog::new()
+            .set_title("打开截图")
+            .add_filter("图片", &["png", "jpg", "jpeg", "bmp"])
+            .pick_file() else { return };
+        match image::open(&path) {
+            Ok(img) => {
+                // 文件截图没有窗口与显示器信息，不改写 window_offset / base_resolution
+                self.capture_offset = None;
+                self.capture_display = None;
+                self.set_screenshot(ctx, img.to_rgba8());
+                self.store_scene_shot(Some(path.display().to_string()));
+                self.status_msg = format!("已载入 {} ({}x{})", path.display(), self.img_size.x, self.img_size.y);
             }
-            
-            toml.push_str("\n");
+            Err(e) => self.status_msg = format!("⚠️ 无法打开图片: {}", e),
         }
-        
-        self.toml_content = toml;
-        self.status_msg = "TOML 已生成".into();
     }
 
-    fn import_toml(&mut self) {
-        if self.toml_content.trim().is_empty() { self.status_msg = "导入失败：内容为空".into(); return; }
-        match toml::from_str::<TomlRoot>(&self.toml_content) {
-            Ok(root) => {
-                self.scenes.clear();
-                
-                let mut temp_scenes: Vec<(usize, String, String, Option<String>, Vec<UIElementDraft>, Option<String>)> = Vec::new();
-                
-                for (idx, scene) in root.scenes.iter().enumerate() {
-                    let mut drafts = Vec::new();
-                    
-                    if let Some(anchors) = &scene.anchors {
-                        if let Some(texts) = &anchors.text {
-                            for t in texts {
-                                let rect = Rect::from_min_max(Pos2::new(t.rect[0] as f32, t.rect[1] as f32), Pos2::new(t.rect[2] as f32, t.rect[3] as f32));
-                                drafts.push(UIElementDraft { pos_or_rect: rect, kind: ElementKind::TextAnchor { text: t.val.clone() } });
-                            }
-                        }
-                        if let Some(colors) = &anchors.color {
-                            for c in colors {
-                                let pos = Pos2::new(c.pos[0] as f32, c.pos[1] as f32);
-                                let rect = Rect::from_min_max(pos, pos + Vec2::splat(1.0));
-                                drafts.push(UIElementDraft { pos_or_rect: rect, kind: ElementKind::ColorAnchor { color_hex: c.val.clone(), tolerance: c.tol } });
-                            }
-                        }
-                    }
-                    if let Some(transitions) = &scene.transitions {
-                        for t in transitions {
-                            let rect = Rect::from_center_size(Pos2::new(t.coords[0] as f32, t.coords[1] as f32), Vec2::splat(20.0));
-                            drafts.push(UIElementDraft { pos_or_rect: rect, kind: ElementKind::Button { target: t.target.clone(), post_delay: t.post_delay } });
-                        }
-                    }
-                    
-                    let handler = scene.handler.clone();
+    /// 把当前截图记为当前场景的参考截图；key 为空时 (实时截图) 生成 capture-N
+    fn store_scene_shot(&mut self, key: Option<String>) {
+        let Some(img) = &self.raw_image else { return };
+        let key = key.unwrap_or_else(|| {
+            self.shot_seq += 1;
+            format!("capture-{}", self.shot_seq)
+        });
+        self.shot_store.insert(key.clone(), img.clone());
+        self.current_scene_mut().shot = Some(key.clone());
+        self.shown_shot = Some(key);
+    }
+
+    /// 把正在显示的截图关联到当前场景 (截图属于别的场景或没有来源时)
+    fn associate_shown_shot(&mut self) {
+        match self.shown_shot.clone().filter(|k| self.shot_store.contains_key(k)) {
+            Some(key) => self.current_scene_mut().shot = Some(key),
+            None => self.store_scene_shot(None),
+        }
+        self.status_msg = format!("已将当前截图设为场景 [{}] 的参考截图", self.current_scene().id);
+    }
+
+    /// 切换到有参考截图的场景时换上它的截图；没有参考截图的场景保留当前截图 (截图区会提示)
+    fn show_scene_shot(&mut self, ctx: &egui::Context) {
+        let Some(key) = self.current_scene().shot.clone() else { return };
+        if self.shown_shot.as_ref() == Some(&key) {
+            return;
+        }
+        if let Some(img) = self.shot_store.get(&key) {
+            let img = img.clone();
+            self.set_screenshot(ctx, img);
+            self.shown_shot = Some(key);
+        }
+    }
+
+    /// 保存项目：地图、布局、编辑器设置、各场景参考截图，以及图像锚点模板 (从原地图目录复制过来)
+    fn save_project(&mut self, save_as: bool) {
+        let dir = match &self.project_dir {
+            Some(dir) if !save_as => dir.clone(),
+            _ => match rfd::FileDialog::new().set_title("选择项目目录").pick_folder() {
+                Some(dir) => dir,
+                None => return,
+            },
+        };
+        self.build_toml();
+        if let Err(e) = self.write_project(&dir) {
+            self.status_msg = format!("⚠️ 保存项目失败: {}", e);
+            return;
+        }
+        let shots = self.scenes.iter().filter(|s| s.shot.is_some()).count();
+        self.status_msg = format!("项目已保存到 {} ({} 个场景有参考截图)", dir.display(), shots);
+        self.remember_map_file(dir.join(PROJECT_MAP_FILE));
+        self.project_dir = Some(dir);
+    }
+
+    fn write_project(&self, dir: &std::path::Path) -> Result<(), String> {
+        let old_map_dir = self.map_dir();
+        for scene in &self.scenes {
+            for d in &scene.drafts {
+                let ElementKind::ImageAnchor { file, .. } = &d.kind else { continue };
+                let (src, dst) = (old_map_dir.join(file), dir.join(file));
+                if src.exists() && !dst.exists() {
+                    if let Some(parent) = dst.parent() {
+                        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+                    }
+                    fs::copy(&src, &dst).map_err(|e| format!("复制模板 {} 失败: {}", file, e))?;
+                }
+            }
+        }
+
+        let mut project = ProjectFile {
+            scenes: Vec::new(),
+            editor: ProjectEditor {
+                current_scene: self.current_scene_index,
+                show_visualization: self.view == ViewMode::Visualize,
+                viz_pan: [self.viz_pan.x, self.viz_pan.y],
+                viz_zoom: self.viz_zoom,
+                capture_offset: self.capture_offset,
+                capture_display: self.capture_display,
+                settings: self.settings.clone(),
+            },
+        };
+        // 多个场景共用同一张截图时只保存一份
+        let mut saved: std::collections::HashMap<&str, String> = std::collections::HashMap::new();
+        for (i, scene) in self.scenes.iter().enumerate() {
+            let shot = match scene.shot.as_deref().and_then(|k| Some((k, self.shot_store.get(k)?))) {
+                Some((key, _)) if saved.contains_key(key) => saved.get(key).cloned(),
+                Some((key, img)) => {
+                    let name = format!("shots/{:02}_{}.png", i + 1, file_stem(&scene.id));
+                    fs::create_dir_all(dir.join("shots")).map_err(|e| e.to_string())?;
+                    img.save(dir.join(&name)).map_err(|e| format!("保存截图 {} 失败: {}", name, e))?;
+                    saved.insert(key, name.clone());
+                    Some(name)
+                }
+                None => None,
+            };
+            project.scenes.push(ProjectScene {
+                id: scene.id.clone(),
+                viz_pos: [scene.viz_pos.x, scene.viz_pos.y],
+                viz_size: [scene.viz_size.x, scene.viz_size.y],
+                shot,
+            });
+        }
+
+        fs::write(dir.join(PROJECT_MAP_FILE), &self.toml_content).map_err(|e| e.to_string())?;
+        let content = toml::to_string(&project).map_err(|e| e.to_string())?;
+        fs::write(dir.join(PROJECT_FILE), content).map_err(|e| e.to_string())
+    }
+
+    /// 打开项目目录：载入地图后按场景 ID 恢复布局与参考截图，再恢复编辑器设置
+    fn open_project(&mut self, ctx: &egui::Context) {
+        let Some(dir) = rfd::FileDialog::new().set_title("打开项目目录").pick_folder() else { return };
+        let project: ProjectFile = match fs::read_to_string(dir.join(PROJECT_FILE)).map_err(|e| e.to_string())
+            .and_then(|s| toml::from_str(&s).map_err(|e| e.to_string())) {
+            Ok(p) => p,
+            Err(e) => { self.status_msg = format!("⚠️ 不是有效的项目目录 ({}): {}", PROJECT_FILE, e); return; }
+        };
+        match fs::read_to_string(dir.join(PROJECT_MAP_FILE)) {
+            Ok(content) => self.toml_content = content,
+            Err(e) => { self.status_msg = format!("⚠️ 读取 {} 失败: {}", PROJECT_MAP_FILE, e); return; }
+        }
+        self.import_toml();
+
+        self.shot_store.clear();
+        self.shown_shot = None;
+        self.scenes.iter_mut().for_each(|s| s.shot = None);
+        let mut missing = 0;
+        for ps in &project.scenes {
+            if let Some(scene) = self.scenes.iter_mut().find(|s| s.id == ps.id) {
+                scene.viz_pos = Pos2::new(ps.viz_pos[0], ps.viz_pos[1]);
+                scene.viz_size = Vec2::new(ps.viz_size[0], ps.viz_size[1]);
+            }
+            // 以项目内的相对路径为键，共用的截图只载入一次
+            if let Some(shot) = &ps.shot {
+                if !self.shot_store.contains_key(shot) {
+                    match image::open(dir.join(shot)) {
+                        Ok(img) => { self.shot_store.insert(shot.clone(), img.to_rgba8()); }
+                        Err(_) => { missing += 1; continue; }
+                    }
+                }
+                if let Some(scene) = self.scenes.iter_mut().find(|s| s.id == ps.id) {
+                    scene.shot = Some(shot.clone());
+                }
+            }
+        }
+
+        let editor = project.editor;
+        self.current_scene_index = editor.current_scene.min(self.scenes.len().saturating_sub(1));
+        self.view = if editor.show_visualization { ViewMode::Visualize } else { ViewMode::Edit };
+        self.viz_pan = Vec2::new(editor.viz_pan[0], editor.viz_pan[1]);
+        if editor.viz_zoom > 0.0 {
+            self.viz_zoom = editor.viz_zoom;
+        }
+        self.capture_offset = editor.capture_offset;
+        self.capture_display = editor.capture_display;
+        let last_map_file = self.settings.last_map_file.take();
+        self.settings = ToolSettings { last_map_file, ..editor.settings };
+        self.ocr_engine = RegionOcr::create(&self.settings.ocr).ok();
+        self.show_scene_shot(ctx);
+
+        self.status_msg = format!("已打开项目 {}：{} 个场景，{} 张参考截图", dir.display(), self.scenes.len(), self.shot_store.len());
+        self.history = EditHistory::new(&self.scenes, self.current_scene_index);
+        if missing > 0 {
+            self.status_msg.push_str(&format!("，⚠️ {} 张截图缺失", missing));
+        }
+        self.remember_map_file(dir.join(PROJECT_MAP_FILE));
+        self.project_dir = Some(dir);
+    }
+
+    /// 当前地图文件所在目录 (未关联文件时为工作目录)；图像锚点模板相对该目录保存
+    fn map_dir(&self) -> std::path::PathBuf {
+        self.map_file.as_ref()
+            .and_then(|(p, _)| p.parent())
+            .filter(|d| !d.as_os_str().is_empty())
+            .map(|d| d.to_path_buf())
+            .unwrap_or_else(|| std::path::PathBuf::from("."))
+    }
+
+    /// 裁剪选区保存为模板 PNG (地图目录下 anchors/)，并添加图像锚点
+    fn add_image_anchor(&mut self, rect: Rect) {
+        let img = match &self.raw_image { Some(img) => img, None => { self.status_msg = "⚠️ 请先截图".into(); return; } };
+        let (x, y) = (rect.min.x.max(0.0) as u32, rect.min.y.max(0.0) as u32);
+        let (w, h) = (rect.width().max(1.0) as u32, rect.height().max(1.0) as u32);
+        if x + w > img.width() || y + h > img.height() {
+            self.status_msg = "区域超出图片范围".into();
+            return;
+        }
+        let template = image::imageops::crop_imm(img, x, y, w, h).to_image();
+
+        let dir = self.map_dir().join("anchors");
+        if let Err(e) = fs::create_dir_all(&dir) {
+            self.status_msg = format!("创建 anchors 目录失败: {}", e);
+            return;
+        }
+        // 文件名取场景 ID；已存在时加序号
+        let stem = file_stem(&self.current_scene().id);
+        let mut k = 1;
+        let mut name = format!("{}_{}.png", stem, k);
+        while dir.join(&name).exists() {
+            k += 1;
+            name = format!("{}_{}.png", stem, k);
+        }
+        if let Err(e) = template.save(dir.join(&name)) {
+            self.status_msg = format!("保存模板失败: {}", e);
+            return;
+        }
+        let file = format!("anchors/{}", name);
+        self.status_msg = format!("已保存模板 {}", dir.join(&name).display());
+        self.current_scene_mut().drafts.push(UIElementDraft { pos_or_rect: rect, kind: ElementKind::ImageAnchor { file, threshold: 0.9 } });
+        self.current_rect = None;
+    }
+
+    /// 地图文件对话框：从上次使用的目录开始
+    fn map_file_dialog(&self, title: &str) -> rfd::FileDialog {
+        let mut dialog = rfd::FileDialog::new().set_title(title).add_filter("TOML", &["toml"]);
+        if let Some(last) = self.settings.last_map_file.as_deref().map(std::path::Path::new) {
+            if let Some(dir) = last.parent().filter(|d| d.is_dir()) {
+                dialog = dialog.set_directory(dir);
+            }
+            if let Some(name) = last.file_name() {
+                dialog = dialog.set_file_name(name.to_string_lossy());
+            }
+        }
+        dialog
+    }
+
+    fn open_map_file(&mut self) {
+        let Some(path) = self.map_file_dialog("打开地图文件").pick_file() else { return };
+        match fs::read_to_string(&path) {
+            Ok(content) => {
+                self.toml_content = content;
+                self.import_toml();
+                self.status_msg = format!("{} ({})", self.status_msg, path.display());
+                self.remember_map_file(path);
+            }
+            Err(e) => self.status_msg = format!("⚠️ 加载文件失败: {}", e),
+        }
+    }
+
+    /// 保存地图：save_as 或尚未关联文件时弹出另存为对话框 (由系统确认是否覆盖已有文件)
+    fn save_map_file(&mut self, save_as: bool) {
+        if self.toml_content.trim().is_empty() {
+            self.build_toml();
+        }
+        let path = match &self.map_file {
+            Some((path, _)) if !save_as => path.clone(),
+            _ => match self.map_file_dialog("保存地图文件").save_file() {
+                Some(path) => path,
+                None => return,
+            },
+        };
+        // 磁盘上已有不同内容时先展示差异，确认后才覆盖，避免盲目覆盖别人的修改
+        if let Ok(disk) = fs::read_to_string(&path) && disk != self.toml_content {
+            let disk_mtime = fs::metadata(&path).and_then(|m| m.modified()).ok();
+            let externally_modified = match &self.map_file {
+                Some((p, loaded_mtime)) if *p == path => disk_mtime.is_some() && disk_mtime != *loaded_mtime,
+                _ => false,
+            };
+            self.save_review = Some(SaveReview::new(path, &disk, &self.toml_content, externally_modified));
+            self.status_msg = "请确认与磁盘文件的差异后再保存".into();
+            return;
+        }
+        self.write_map_file(path);
+    }
+
+    fn write_map_file(&mut self, path: std::path::PathBuf) {
+        match fs::write(&path, &self.toml_content) {
+            Ok(_) => {
+                self.status_msg = format!("已保存到 {}", path.display());
+                self.remember_map_file(path);
+            }
+            Err(e) => self.status_msg = format!("⚠️ 保存文件失败: {}", e),
+        }
+    }
+
+    /// 保存前的差异确认弹窗：场景级变化摘要 + 逐行差异
+    fn draw_save_review(&mut self, ctx: &egui::Context) {
+        let Some(review) = &self.save_review else { return };
+        let (mut confirm, mut cancel) = (false, false);
+        egui::Window::new("💾 保存前差异").collapsible(false).default_size([700.0, 500.0]).show(ctx, |ui| {
+            ui.label(format!("{} (红色为磁盘上的内容，绿色为将要写入的内容)", review.path.display()));
+            if review.externally_modified {
+                ui.label(RichText::new("⚠️ 该文件在载入后被其他程序修改过，覆盖会丢失这些修改").color(Color32::from_rgb(255, 80, 80)).strong());
+            }
+            let list = |ids: &[String]| if ids.is_empty() { "无".to_string() } else { ids.join(", ") };
+            ui.label(RichText::new(format!("➕ 新增场景: {}", list(&review.added))).color(Color32::from_rgb(0, 200, 100)));
+            ui.label(RichText::new(format!("➖ 删除场景: {}", list(&review.removed))).color(Color32::from_rgb(255, 80, 80)));
+            ui.label(RichText::new(format!("✏️ 修改场景: {}", list(&review.changed))).color(Color32::from_rgb(255, 180, 0)));
+            ui.separator();
+            egui::ScrollArea::both().max_height(380.0).show(ui, |ui| {
+                for line in &review.lines {
+                    let (text, color) = match line {
+                        DiffLine::Same(s) => (format!("  {}", s), Color32::GRAY),
+                        DiffLine::Removed(s) => (format!("- {}", s), Color32::from_rgb(255, 100, 100)),
+                        DiffLine::Added(s) => (format!("+ {}", s), Color32::from_rgb(80, 220, 120)),
+                        DiffLine::Skipped(n) => (format!("  ⋯ {} 行未改动", n), Color32::DARK_GRAY),
+                    };
+                    ui.label(RichText::new(text).monospace().color(color));
+                }
+            });
+            ui.horizontal(|ui| {
+                confirm = ui.button("💾 确认覆盖").clicked();
+                cancel = ui.button("取消").clicked();
+            });
+        });
+        if confirm && let Some(review) = self.save_review.take() {
+            self.write_map_file(review.path);
+        } else if cancel {
+            self.save_review = None;
+            self.status_msg = "已取消保存".into();
+        }
+    }
+
+    /// 关联当前地图文件并记入设置
+    fn remember_map_file(&mut self, path: std::path::PathBuf) {
+        let mtime = fs::metadata(&path).and_then(|m| m.modified()).ok();
+        self.settings.last_map_file = Some(path.display().to_string());
+        if let Err(e) = self.settings.save() {
+            self.status_msg = format!("⚠️ 保存设置失败: {}", e);
+        }
+        self.map_file = Some((path, mtime));
+    }
+
+    /// 替换当前截图并重置缩放
+    fn set_screenshot(&mut self, ctx: &egui::Context, image: image::RgbaImage) {
+        self.img_size = Vec2::new(image.width() as f32, image.height() as f32);
+        let color_img = egui::ColorImage::from_rgba_unmultiplied(
+            [image.width() as usize, image.height() as usize], 
+            image.as_flat_samples().as_slice()
+        );
+        // 放大后按像素显示，不做插值模糊，方便取色
+        self.texture = Some(ctx.load_texture("shot", color_img, egui::TextureOptions::NEAREST));
+        self.raw_image = Some(image);
+        self.current_rect = None;
+        self.anchor_suggestions.clear();
+        self.shot_zoom = 1.0;
+        self.shot_pan = Vec2::ZERO;
+    }
+
+    /// 截图来源：整个显示器，或按标题指定的窗口 (只截客户区)
+    fn draw_window_selector(&mut self, ui: &mut egui::Ui) {
+        let mut by_window = self.settings.window_title.is_some();
+        ui.horizontal(|ui| {
+            ui.label("截图来源:");
+            ui.radio_value(&mut by_window, false, "整个显示器");
+            ui.radio_value(&mut by_window, true, "指定窗口");
+        });
+        let mut changed = by_window != self.settings.window_title.is_some();
+        if changed {
+            if by_window {
+                self.windows = list_windows();
+                self.settings.window_title = Some(self.windows.first().cloned().unwrap_or_default());
+            } else {
+                self.settings.window_title = None;
+                self.capture_offset = None;
+            }
+        }
+        if let Some(current) = self.settings.window_title.clone() {
+            ui.horizontal(|ui| {
+                ui.label("窗口:");
+                egui::ComboBox::from_id_source("window").selected_text(&current).width(240.0).show_ui(ui, |ui| {
+                    for title in &self.windows {
+                        if ui.selectable_label(*title == current, title).clicked() {
+                            self.settings.window_title = Some(title.clone());
+                            changed = true;
+                        }
+                    }
+                });
+                if ui.button("🔄").on_hover_text("重新枚举窗口").clicked() {
+                    self.windows = list_windows();
+                }
+            });
+        }
+        if changed {
+            self.status_msg = match self.settings.save() {
+                Ok(_) => "已切换截图来源".into(),
+                Err(e) => format!("⚠️ 保存设置失败: {}", e),
+            };
+        }
+    }
+
+    /// OCR 引擎 / 识别语言下拉框；区域测试、稳定性采样、实时测试都使用所选引擎
+    fn draw_ocr_selector(&mut self, ui: &mut egui::Ui) {
+        let label = |choice: &str, languages: &[(String, String)]| match choice {
+            #[cfg(feature = "windows")]
+            "" => "用户语言 (系统默认)".to_string(),
+            #[cfg(not(feature = "windows"))]
+            "" => format!("tesseract {} (默认)", TESSERACT_DEFAULT_LANG),
+            c if c.eq_ignore_ascii_case("paddle") => "PaddleOCR".to_string(),
+            c => languages.iter().find(|(tag, _)| tag == c).map(|(tag, name)| format!("{} ({})", name, tag)).unwrap_or_else(|| c.to_string()),
+        };
+        let mut choices: Vec<String> = std::iter::once(String::new())
+            .chain(self.ocr_languages.iter().map(|(tag, _)| tag.clone()))
+            .collect();
+        if cfg!(feature = "paddle") {
+            choices.push("paddle".into());
+        }
+        let mut chosen = None;
+        ui.horizontal(|ui| {
+            ui.label("OCR:");
+            egui::ComboBox::from_id_source("ocr_engine").selected_text(label(&self.settings.ocr, &self.ocr_languages)).width(240.0).show_ui(ui, |ui| {
+                for c in &choices {
+                    if ui.selectable_label(*c == self.settings.ocr, label(c, &self.ocr_languages)).clicked() {
+                        chosen = Some(c.clone());
+                    }
+                }
+            });
+        });
+        if let Some(choice) = chosen {
+            self.settings.ocr = choice;
+            match RegionOcr::create(&self.settings.ocr) {
+                Ok(engine) => {
+                    self.status_msg = format!("OCR 引擎已切换: {}", engine.name());
+                    self.ocr_engine = Some(engine);
+                }
+                Err(e) => {
+                    self.status_msg = format!("⚠️ {}", e);
+                    self.ocr_engine = None;
+                }
+            }
+            if let Err(e) = self.settings.save() {
+                self.status_msg = format!("⚠️ 保存设置失败: {}", e);
+            }
+        }
+    }
+
+    /// 显示器下拉框；选择后写入设置文件
+    fn draw_monitor_selector(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("显示器:");
+            let selected = self.settings.monitor_id
+                .and_then(|id| self.monitors.iter().find(|(m, _)| *m == id))
+                .or(self.monitors.first())
+                .map(|(_, name)| name.clone())
+                .unwrap_or_else(|| "未检测到显示器".into());
+            let mut chosen = None;
+            egui::ComboBox::from_id_source("monitor").selected_text(selected).width(240.0).show_ui(ui, |ui| {
+                for (id, name) in &self.monitors {
+                    if ui.selectable_label(self.settings.monitor_id == Some(*id), name).clicked() {
+                        chosen = Some(*id);
+                    }
+                }
+            });
+            if ui.button("🔄").on_hover_text("重新检测显示器").clicked() {
+                self.monitors = list_monitors();
+            }
+            if let Some(id) = chosen {
+                self.settings.monitor_id = Some(id);
+                self.status_msg = match self.settings.save() {
+                    Ok(_) => "已切换截图显示器".into(),
+                    Err(e) => format!("⚠️ 保存设置失败: {}", e),
+                };
+            }
+        });
+    }
+
+    /// 将选区裁剪为数字模板，保存到 ./digits/N.png (供主程序波次识别兜底使用)
+    fn export_digit_template(&mut self, rect: Rect) {
+        let img = match &self.raw_image { Some(img) => img, None => { self.status_msg = "⚠️ 请先截图".into(); return; } };
+        let x = rect.min.x.max(0.0) as u32;
+        let y = rect.min.y.max(0.0) as u32;
+        let w = rect.width().max(1.0) as u32;
+        let h = rect.height().max(1.0) as u32;
+        if x + w > img.width() || y + h > img.height() {
+            self.status_msg = "区域超出图片范围".into();
+            return;
+        }
+
+        let dir = std::path::Path::new("./digits");
+        if fs::create_dir_all(dir).is_err() {
+            self.status_msg = "创建 digits 目录失败".into();
+            return;
+        }
+        // 同一数字已存在时另存为变体 N_k.png
+        let digit = self.digit_export_value.min(9);
+        let mut path = dir.join(format!("{}.png", digit));
+        let mut k = 1;
+        while path.exists() {
+            path = dir.join(format!("{}_{}.png", digit, k));
+            k += 1;
+        }
+
+        let sub_img = image::imageops::crop_imm(img, x, y, w, h).to_image();
+        match sub_img.save(&path) {
+            Ok(_) => self.status_msg = format!("已导出数字模板: {}", path.display()),
+            Err(e) => self.status_msg = format!("导出失败: {}", e),
+        }
+    }
+
+    fn pick_color(&self, p: Pos2) -> String {
+        if let Some(img) = &self.raw_image {
+            let x = p.x as u32;
+            let y = p.y as u32;
+            if x < img.width() && y < img.height() {
+                let pixel = img.get_pixel(x, y);
+                return format!("#{:02X}{:02X}{:02X}", pixel[0], pixel[1], pixel[2]);
+            }
+        }
+        "#FFFFFF".into()
+    }
+
+    /// 截图上 rect 区域的平均色
+    fn average_color(&self, rect: Rect) -> String {
+        match self.raw_image.as_ref().and_then(|img| average_rgb(img, rect)) {
+            Some([r, g, b]) => format!("#{:02X}{:02X}{:02X}", r, g, b),
+            None => "#FFFFFF".into(),
+        }
+    }
+
+    /// 生成 TOML：以导入时的原始文档为底稿增量写回，保留手写注释与键顺序
+    fn build_toml(&mut self) {
+        let mut doc = self.source_doc.clone().unwrap_or_default();
+        sync_scenes_into_doc(&mut doc, &self.scenes);
+        // 窗口截图时坐标相对窗口客户区，记录客户区偏移供主程序换算回屏幕坐标
+        if let Some([x, y]) = self.capture_offset {
+            set_value_keep_decor(doc.as_table_mut(), "window_offset", int_array(&[x as i64, y as i64]));
+        }
+        // 记录采集时的分辨率与 DPI 缩放，主程序据此缩放坐标。
+        // 文件已有不同的基准时保留原值：已有坐标是按原分辨率采集的，不能整体改写
+        let mut base_conflict = None;
+        if let Some(([w, h], dpi)) = self.capture_display {
+            let old = doc.get("base_resolution").and_then(|v| v.as_array())
+                .map(|a| a.iter().filter_map(|v| v.as_integer()).collect::<Vec<_>>());
+            match old {
+                Some(old) if old != [w as i64, h as i64] => base_conflict = Some(format!("{:?}，当前截图为 [{}, {}]", old, w, h)),
+                _ => {
+                    set_value_keep_decor(doc.as_table_mut(), "base_resolution", int_array(&[w as i64, h as i64]));
+                    set_value_keep_decor(doc.as_table_mut(), "dpi_scale", toml_edit::Value::from((dpi as f64 * 100.0).round() / 100.0));
+                }
+            }
+        }
+
+        self.toml_content = doc.to_string();
+        self.source_doc = Some(doc);
+        let issues = validate_scenes(&self.scenes, self.texture.as_ref().map(|_| self.img_size)).len();
+        self.status_msg = if issues == 0 { "TOML 已生成".into() } else { format!("TOML 已生成，⚠️ 校验发现 {} 个问题", issues) };
+        if let Some(conflict) = base_conflict {
+            self.status_msg.push_str(&format!("\n⚠️ 地图的 base_resolution 为 {}，新采集的坐标会错位，请在原分辨率下截图", conflict));
+        }
+    }
+
+    fn import_toml(&mut self) {
+        if self.toml_content.trim().is_empty() { self.status_msg = "导入失败：内容为空".into(); return; }
+        // 旧版格式 (如 tool/ 生成的地图) 由 nzm_schema 迁移到当前格式后再读取
+        match schema::MapFile::from_toml_str(&self.toml_content) {
+            Ok((root, migration)) => {
+                // 重新导入时按场景 ID 保留已关联的参考截图
+                let old_shots: std::collections::HashMap<String, String> = self.scenes.iter()
+                    .filter_map(|s| Some((s.id.clone(), s.shot.clone()?)))
+                    .collect();
+                self.scenes.clear();
+                // 记住原始文档，后续生成时在其基础上增量修改
+                self.source_doc = self.toml_content.parse::<toml_edit::DocumentMut>().ok();
+                
+                let mut temp_scenes: Vec<(usize, String, String, Option<String>, Vec<UIElementDraft>, Option<String>)> = Vec::new();
+                
+                for (idx, scene) in root.scenes.iter().enumerate() {
+                    let mut drafts = Vec::new();
                     
-                    let logic = match scene.logic {
-                        Some(ref logic_str) => match logic_str.to_lowercase().as_str() {
-                            "or" => RecognitionLogic::OR,
-                            "and" => RecognitionLogic::AND,
-                            _ => {
-                                eprintln!("Warning: Unknown logic value '{}', defaulting to AND", logic_str);
-                                RecognitionLogic::AND
+                    if let Some(anchors) = &scene.anchors {
+                        if let Some(texts) = &anchors.text {
+                            for t in texts {
+                                let rect = Rect::from_min_max(Pos2::new(t.rect[0] as f32, t.rect[1] as f32), Pos2::new(t.rect[2] as f32, t.rect[3] as f32));
+                                drafts.push(UIElementDraft { pos_or_rect: rect, kind: ElementKind::TextAnchor { text: t.val.clone() } });
+                            }
+                        }
+                        if let Some(colors) = &anchors.color {
+                            for c in colors {
+                                let pos = Pos2::new(c.pos[0] as f32, c.pos[1] as f32);
+                                let rect = Rect::from_min_max(pos, pos + Vec2::splat(1.0));
+                                drafts.push(UIElementDraft { pos_or_rect: rect, kind: ElementKind::ColorAnchor { color_hex: c.val.clone(), tolerance: c.tol } });
+                            }
+                        }
+                        if let Some(hsvs) = &anchors.hsv {
+                            for h in hsvs {
+                                let [x1, y1, x2, y2] = h.sample_rect();
+                                let rect = Rect::from_min_max(Pos2::new(x1 as f32, y1 as f32), Pos2::new(x2 as f32, y2 as f32));
+                                let kind = ElementKind::HsvAnchor { color_hex: h.val.clone(), hue_tol: h.hue_tol, sat_tol: h.sat_tol, val_tol: h.val_tol };
+                                drafts.push(UIElementDraft { pos_or_rect: rect, kind });
                             }
-                        },
-                        None => RecognitionLogic::AND,
+                        }
+                        if let Some(images) = &anchors.image {
+                            for i in images {
+                                let rect = Rect::from_min_max(Pos2::new(i.rect[0] as f32, i.rect[1] as f32), Pos2::new(i.rect[2] as f32, i.rect[3] as f32));
+                                drafts.push(UIElementDraft { pos_or_rect: rect, kind: ElementKind::ImageAnchor { file: i.file.clone(), threshold: i.threshold } });
+                            }
+                        }
+                    }
+                    if let Some(transitions) = &scene.transitions {
+                        for t in transitions {
+                            let action = t.action;
+                            // 拖拽以起点为元素位置
+                            let [x, y] = if action == TransitionAction::Drag { t.from.unwrap_or(t.coords) } else { t.coords };
+                            let rect = Rect::from_center_size(Pos2::new(x as f32, y as f32), Vec2::splat(20.0));
+                            let extra = TransitionExtra {
+                                action,
+                                key: t.key.clone().unwrap_or_default(),
+                                drag_to: t.to.unwrap_or_default(),
+                                delta: t.delta,
+                                pre_delay: t.pre_delay as u32,
+                                retries: t.retries,
+                            };
+                            drafts.push(UIElementDraft { pos_or_rect: rect, kind: ElementKind::Button { target: t.target.clone(), post_delay: t.post_delay as u32, extra } });
+                        }
+                    }
+                    
+                    let handler = scene.handler.clone();
+                    
+                    let logic = match scene.logic.to_lowercase().as_str() {
+                        "or" => RecognitionLogic::OR,
+                        "and" | "" => RecognitionLogic::AND,
+                        other => {
+                            eprintln!("Warning: Unknown logic value '{}', defaulting to AND", other);
+                            RecognitionLogic::AND
+                        }
                     };
                     
                     temp_scenes.push((idx, scene.id.clone(), scene.name.clone(), Some(if logic == RecognitionLogic::AND { "and" } else { "or" }.to_string()), drafts, handler));
@@ -341,6 +1675,7 @@ impl MapBuilderTool {
                     };
                     
                     self.scenes.push(Scene {
+                        shot: old_shots.get(&id).cloned(),
                         id,
                         name,
                         logic: logic_val,
@@ -357,12 +1692,33 @@ impl MapBuilderTool {
                 } else {
                     self.status_msg = "导入失败：未找到场景".into();
                 }
+                // 地图采集时的屏幕与当前截图来源不同时提醒，继续编辑会混入不同基准的坐标
+                if let Some(([w, h], dpi)) = capture_display(&self.settings) {
+                    if let Some([bw, bh]) = root.base_resolution && [bw, bh] != [w, h] {
+                        self.status_msg.push_str(&format!("\n⚠️ 地图采集于 {}x{}，当前截图来源为 {}x{}", bw, bh, w, h));
+                    }
+                    if let Some(base_dpi) = root.dpi_scale && (base_dpi - dpi).abs() > 0.01 {
+                        self.status_msg.push_str(&format!("\n⚠️ 地图采集时 DPI 缩放为 {:.0}%，当前为 {:.0}%", base_dpi * 100.0, dpi * 100.0));
+                    }
+                }
+                // 旧版格式：按当前格式重新生成文本，保存即完成迁移
+                if let Some(m) = migration {
+                    let mut doc = toml_edit::DocumentMut::new();
+                    set_value_keep_decor(doc.as_table_mut(), "schema_version", (schema::MAP_SCHEMA_VERSION as i64).into());
+                    sync_scenes_into_doc(&mut doc, &self.scenes);
+                    self.toml_content = doc.to_string();
+                    self.source_doc = Some(doc);
+                    self.status_msg = format!("已从旧版格式 (v{}) 迁移，{}", m.from, self.status_msg);
+                    if !m.notes.is_empty() {
+                        self.status_msg.push_str(&format!("\n⚠️ {}", m.notes.join("\n⚠️ ")));
+                    }
+                }
             },
             Err(e) => { self.status_msg = format!("解析失败: {}", e); }
         }
     }
     
-    fn calculate_layout(&self, scenes: &[TomlScene]) -> std::collections::HashMap<usize, Pos2> {
+    fn calculate_layout(&self, scenes: &[schema::Scene]) -> std::collections::HashMap<usize, Pos2> {
         use std::collections::{HashMap, HashSet};
         
         let mut positions = HashMap::new();
@@ -452,100 +1808,462 @@ impl MapBuilderTool {
     }
 
     fn perform_ocr(&mut self, rect: Rect) {
-        if self.ocr_engine.is_none() {
-            self.ocr_test_result = "OCR 引擎未初始化".into();
-            return;
-        }
+        let engine = match &self.ocr_engine {
+            Some(e) => e,
+            None => { self.ocr_test_result = "OCR 引擎未初始化".into(); return; }
+        };
         if let Some(img) = &self.raw_image {
-            let x = rect.min.x.max(0.0) as u32;
-            let y = rect.min.y.max(0.0) as u32;
-            let w = rect.width().max(1.0) as u32;
-            let h = rect.height().max(1.0) as u32;
-
-            if x + w > img.width() || y + h > img.height() {
-                self.ocr_test_result = "区域超出图片范围".into();
-                return;
-            }
-
-            let sub_img = image::imageops::crop_imm(img, x, y, w, h).to_image();
-            let scaled_img = image::imageops::resize(&sub_img, w * 2, h * 2, image::imageops::FilterType::Lanczos3);
-            let dynamic_img = image::DynamicImage::ImageRgba8(scaled_img);
-
-            let mut png_buffer = Cursor::new(Vec::new());
-            if dynamic_img.write_to(&mut png_buffer, image::ImageFormat::Png).is_err() {
-                self.ocr_test_result = "图像编码失败".into();
-                return;
-            }
-            
             self.ocr_test_result = "识别中...".into();
-            let engine = self.ocr_engine.as_ref().unwrap();
-            let png_bytes = png_buffer.into_inner();
-
-            let run_recognition = || -> windows::core::Result<String> {
-                let stream = InMemoryRandomAccessStream::new()?;
-                let writer = DataWriter::CreateDataWriter(&stream)?;
-                writer.WriteBytes(&png_bytes)?;
-                writer.StoreAsync()?.get()?;
-                writer.FlushAsync()?.get()?;
-                stream.Seek(0)?;
-
-                let decoder = BitmapDecoder::CreateAsync(&stream)?.get()?;
-                let bmp = decoder.GetSoftwareBitmapAsync()?.get()?;
-                let result: OcrResult = engine.RecognizeAsync(&bmp)?.get()?;
-                
-                let mut text = String::new();
-                if let Ok(lines) = result.Lines() {
-                    for line in lines {
-                        if let Ok(h_str) = line.Text() {
-                            text.push_str(&h_str.to_string());
-                        }
-                    }
-                }
-                Ok(text.replace(char::is_whitespace, ""))
-            };
-
-            match run_recognition() {
+            match engine.recognize(img, rect) {
                 Ok(txt) => {
                     self.ocr_test_result = if txt.is_empty() { "无文字".to_string() } else { txt };
                     self.status_msg = format!("OCR 完成: {}", self.ocr_test_result);
                 },
                 Err(e) => {
-                    self.ocr_test_result = format!("API 错误: {:?}", e);
+                    self.ocr_test_result = e;
                 }
             }
         }
     }
-    
-    fn draw_visualization_panel(&mut self, ui: &mut egui::Ui) {
-        let (resp, painter) = ui.allocate_painter(ui.available_size(), Sense::drag());
-        let rect = resp.rect;
-        
-        // 绘制背景网格
-        self.draw_grid(&painter, rect);
-        
-        // 应用平移和缩放
-        let transform = |p: Pos2| Pos2::new(
-            p.x * self.viz_zoom + self.viz_pan.x + rect.min.x,
-            p.y * self.viz_zoom + self.viz_pan.y + rect.min.y
-        );
-        let inverse_transform = |p: Pos2| Pos2::new(
-            (p.x - rect.min.x - self.viz_pan.x) / self.viz_zoom,
-            (p.y - rect.min.y - self.viz_pan.y) / self.viz_zoom
-        );
-        
-        // 绘制场景连接线
-        self.draw_scene_connections(&painter, &transform);
-        
-        // 绘制场景矩形
-        let mut clicked_scene = None;
-        for (i, scene) in self.scenes.iter().enumerate() {
-            let scene_rect = Rect::from_min_size(transform(scene.viz_pos), scene.viz_size * self.viz_zoom);
-            let is_selected = i == self.current_scene_index;
-            let has_handler = scene.handler.is_some();
-            
-            // 场景背景色
-            let bg_color = if is_selected {
-                Color32::from_rgb(100, 150, 255)
+
+    /// 用当前截图重新识别文字锚点 (当前场景或全部场景)，把识别结果有变化的列入待确认差异
+    fn batch_reocr(&mut self) {
+        let (Some(engine), Some(img)) = (&self.ocr_engine, &self.raw_image) else {
+            self.status_msg = "⚠️ 需要先截图并初始化 OCR 引擎".into();
+            return;
+        };
+        let scene_range = if self.reocr_all_scenes { 0..self.scenes.len() } else { self.current_scene_index..self.current_scene_index + 1 };
+        let (mut total, mut failed) = (0, 0);
+        let mut entries = Vec::new();
+        for si in scene_range {
+            for (di, d) in self.scenes[si].drafts.iter().enumerate() {
+                let ElementKind::TextAnchor { text } = &d.kind else { continue };
+                total += 1;
+                match engine.recognize(img, d.pos_or_rect) {
+                    Ok(new) if new != *text => entries.push(ReocrEntry { scene: si, draft: di, old: text.clone(), accept: !new.is_empty(), new }),
+                    Ok(_) => {}
+                    Err(_) => failed += 1,
+                }
+            }
+        }
+        self.status_msg = format!("重新 OCR：{} 个文字锚点，{} 个结果有变化，{} 个识别失败", total, entries.len(), failed);
+        self.reocr_review = (!entries.is_empty()).then_some(entries);
+    }
+
+    /// 对当前截图做整图 OCR，列出与当前场景已有文字锚点不重叠的候选区域
+    fn auto_suggest_anchors(&mut self) {
+        let Some(img) = &self.raw_image else { return };
+        // 需要词级包围框，只有 WinRT 提供；选了 PaddleOCR 时改用用户语言的 WinRT 引擎
+        #[cfg(feature = "windows")]
+        let lines = {
+            let fallback;
+            let engine = match &self.ocr_engine {
+                Some(RegionOcr::WinRt(engine, _)) => engine,
+                _ => match OcrEngine::TryCreateFromUserProfileLanguages() {
+                    Ok(e) => { fallback = e; &fallback }
+                    Err(e) => { self.status_msg = format!("⚠️ 无法创建 WinRT 识别引擎: {}", e); return; }
+                },
+            };
+            ocr_lines(engine, img)
+        };
+        // Linux：tesseract 的 TSV 带词级包围框；选了 PaddleOCR 时使用默认语言包
+        #[cfg(not(feature = "windows"))]
+        let lines = match &self.ocr_engine {
+            Some(RegionOcr::Tesseract(lang)) => ocr_lines(lang, img),
+            _ => ocr_lines(TESSERACT_DEFAULT_LANG, img),
+        };
+        match lines.map(|lines| suggest_anchors(lines, img)) {
+            Ok(mut suggestions) => {
+                let existing: Vec<Rect> = self.current_scene().drafts.iter()
+                    .filter(|d| matches!(d.kind, ElementKind::TextAnchor { .. }))
+                    .map(|d| d.pos_or_rect)
+                    .collect();
+                suggestions.retain(|s| !existing.iter().any(|r| r.intersects(s.rect)));
+                self.status_msg = format!("找到 {} 个候选锚点，点击截图上的青色框或列表中的「采用」添加", suggestions.len());
+                self.anchor_suggestions = suggestions;
+            }
+            Err(e) => self.status_msg = format!("⚠️ 整图 OCR 失败: {}", e),
+        }
+    }
+
+    fn accept_suggestion(&mut self, i: usize) {
+        if i >= self.anchor_suggestions.len() {
+            return;
+        }
+        let s = self.anchor_suggestions.remove(i);
+        self.status_msg = format!("已添加文字锚点: {}", s.text);
+        self.current_scene_mut().drafts.push(UIElementDraft { pos_or_rect: s.rect, kind: ElementKind::TextAnchor { text: s.text } });
+    }
+
+    /// 批量重新 OCR 的差异确认弹窗：逐条勾选后统一写回
+    fn draw_reocr_review(&mut self, ctx: &egui::Context) {
+        let Some(entries) = &mut self.reocr_review else { return };
+        let (mut apply, mut cancel) = (false, false);
+        egui::Window::new("🔁 重新 OCR 结果").collapsible(false).show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                if ui.button("全选").clicked() { entries.iter_mut().for_each(|e| e.accept = true); }
+                if ui.button("全不选").clicked() { entries.iter_mut().for_each(|e| e.accept = false); }
+            });
+            egui::ScrollArea::vertical().max_height(400.0).show(ui, |ui| {
+                egui::Grid::new("reocr_grid").striped(true).show(ui, |ui| {
+                    for e in entries.iter_mut() {
+                        let id = self.scenes.get(e.scene).map(|s| s.id.as_str()).unwrap_or("?");
+                        ui.checkbox(&mut e.accept, format!("[{}] #{}", id, e.draft + 1));
+                        ui.label(RichText::new(&e.old).color(Color32::from_rgb(255, 120, 120)));
+                        ui.label("→");
+                        // 识别结果可先手动修正再采用
+                        ui.text_edit_singleline(&mut e.new);
+                        ui.end_row();
+                    }
+                });
+            });
+            ui.horizontal(|ui| {
+                apply = ui.button("✔ 采用选中").clicked();
+                cancel = ui.button("取消").clicked();
+            });
+        });
+
+        if apply {
+            let mut applied = 0;
+            for e in entries.iter().filter(|e| e.accept) {
+                // 弹窗期间元素可能被编辑或删除，只在原文仍一致时写回
+                if let Some(ElementKind::TextAnchor { text }) = self.scenes.get_mut(e.scene).and_then(|s| s.drafts.get_mut(e.draft)).map(|d| &mut d.kind)
+                    && *text == e.old {
+                    *text = e.new.clone();
+                    applied += 1;
+                }
+            }
+            self.status_msg = format!("已更新 {} 个文字锚点", applied);
+        }
+        if apply || cancel {
+            self.reocr_review = None;
+        }
+    }
+
+    /// 开始锚点稳定性采样：后台线程在指定时长内反复截图，统计每个锚点的识别一致性
+    fn start_stability_sampling(&mut self) {
+        let anchors: Vec<(usize, ElementKind, Rect)> = self.current_scene().drafts.iter().enumerate()
+            .filter(|(_, d)| matches!(d.kind, ElementKind::TextAnchor { .. } | ElementKind::ColorAnchor { .. } | ElementKind::HsvAnchor { .. }))
+            .map(|(i, d)| (i, d.kind.clone(), d.pos_or_rect))
+            .collect();
+        if anchors.is_empty() {
+            self.status_msg = "⚠️ 当前场景没有锚点".into();
+            return;
+        }
+
+        let (tx, rx) = mpsc::channel();
+        let duration = Duration::from_secs(self.stability_duration_secs.max(1) as u64);
+        let interval = Duration::from_millis(self.stability_interval_ms.max(100) as u64);
+        let settings = self.settings.clone();
+        thread::spawn(move || run_stability_sampling(anchors, settings, duration, interval, tx));
+
+        let drafts = self.current_scene().drafts.clone();
+        self.stability_run = Some(StabilityRun { scene_index: self.current_scene_index, drafts, rx, progress: 0.0 });
+        self.stability_results = None;
+        self.status_msg = "稳定性采样将在 3 秒后开始，请切换到游戏画面".into();
+    }
+
+    /// 实时测试当前场景：3 秒后截一帧，评估每个锚点并按 AND/OR 给出结论
+    fn start_live_test(&mut self) {
+        let anchors = self.test_anchors(self.current_scene());
+        if anchors.is_empty() {
+            self.status_msg = "⚠️ 当前场景没有锚点".into();
+            return;
+        }
+        let (tx, rx) = mpsc::channel();
+        let settings = self.settings.clone();
+        thread::spawn(move || run_live_test(anchors, settings, tx));
+        self.live_test_rx = Some((self.current_scene_index, rx));
+        self.live_test_results = None;
+        self.status_msg = "将在 3 秒后截图测试，请切换到游戏画面".into();
+    }
+
+    /// 场景中需要评估的锚点 (元素序号, 类型, 区域)，交给后台线程使用
+    fn test_anchors(&self, scene: &Scene) -> Vec<(usize, ElementKind, Rect)> {
+        let map_dir = self.map_dir();
+        scene.drafts.iter().enumerate()
+            .filter(|(_, d)| !matches!(d.kind, ElementKind::Button { .. }))
+            .map(|(i, d)| {
+                // 模板路径相对地图文件，交给后台线程前换成完整路径
+                let kind = match &d.kind {
+                    ElementKind::ImageAnchor { file, threshold } => ElementKind::ImageAnchor { file: map_dir.join(file).display().to_string(), threshold: *threshold },
+                    other => other.clone(),
+                };
+                (i, kind, d.pos_or_rect)
+            })
+            .collect()
+    }
+
+    /// 离线回归：选一个截图目录 (文件名即期望的场景 ID)，用所有场景的锚点逐张评估
+    fn start_regression(&mut self) {
+        let Some(dir) = rfd::FileDialog::new().set_title("选择已标注的截图目录").pick_folder() else { return };
+        let mut files: Vec<std::path::PathBuf> = match fs::read_dir(&dir) {
+            Ok(entries) => entries.filter_map(|e| e.ok().map(|e| e.path()))
+                .filter(|p| p.extension().and_then(|e| e.to_str()).is_some_and(|e| matches!(e.to_ascii_lowercase().as_str(), "png" | "jpg" | "jpeg" | "bmp")))
+                .collect(),
+            Err(e) => {
+                self.status_msg = format!("❌ 无法读取目录: {}", e);
+                return;
+            }
+        };
+        if files.is_empty() {
+            self.status_msg = "⚠️ 目录中没有截图 (png / jpg / bmp)".into();
+            return;
+        }
+        files.sort();
+        let scenes: Vec<SceneAnchors> = self.scenes.iter()
+            .map(|s| (s.id.clone(), s.logic.clone(), self.test_anchors(s)))
+            .filter(|(_, _, anchors)| !anchors.is_empty())
+            .collect();
+        let (tx, rx) = mpsc::channel();
+        let ocr = self.settings.ocr.clone();
+        thread::spawn(move || run_regression(scenes, files, ocr, tx));
+        self.regression_run = Some(RegressionRun { rx, progress: 0.0 });
+        self.regression_results = None;
+        self.status_msg = format!("正在离线回归 {} ...", dir.display());
+    }
+
+    fn poll_regression(&mut self) {
+        let mut finished = None;
+        if let Some(run) = &mut self.regression_run {
+            while let Ok(msg) = run.rx.try_recv() {
+                match msg {
+                    RegressionMsg::Progress(p) => run.progress = p,
+                    RegressionMsg::Done(rows) => finished = Some(rows),
+                }
+            }
+        }
+        if let Some(rows) = finished {
+            let failed = rows.iter().filter(|r| !r.passed()).count();
+            self.status_msg = format!("离线回归完成：{} 张截图，{} 张有误", rows.len(), failed);
+            self.regression_results = Some(rows);
+            self.regression_run = None;
+        }
+    }
+
+    /// 离线回归结果表：每张截图的期望场景、实际命中的场景与漏识别 / 误识别
+    fn draw_regression_results(&mut self, ctx: &egui::Context) {
+        let Some(rows) = &self.regression_results else { return };
+        let mut open = true;
+        egui::Window::new("🧪 离线回归结果").open(&mut open).default_size([640.0, 420.0]).show(ctx, |ui| {
+            let missed = rows.iter().filter(|r| r.false_negative()).count();
+            let extra = rows.iter().filter(|r| !r.false_positives().is_empty()).count();
+            ui.label(format!("{} 张截图：漏识别 {} 张，误识别 {} 张", rows.len(), missed, extra));
+            ui.checkbox(&mut self.regression_failures_only, "只看有误的截图");
+            ui.separator();
+            egui::ScrollArea::vertical().max_height(340.0).show(ui, |ui| {
+                egui::Grid::new("regression_grid").striped(true).show(ui, |ui| {
+                    ui.strong("截图");
+                    ui.strong("期望场景");
+                    ui.strong("命中场景");
+                    ui.strong("结果");
+                    ui.end_row();
+                    for row in rows.iter().filter(|r| !self.regression_failures_only || !r.passed()) {
+                        ui.label(&row.file);
+                        ui.label(&row.expected);
+                        ui.label(if row.matched.is_empty() { "-".to_string() } else { row.matched.join(", ") });
+                        let red = Color32::from_rgb(255, 80, 80);
+                        if let Some(err) = &row.error {
+                            ui.label(RichText::new(format!("⚠️ {}", err)).color(red));
+                        } else if row.passed() {
+                            ui.label(RichText::new("✔").color(Color32::from_rgb(0, 200, 100)));
+                        } else {
+                            let mut problems = Vec::new();
+                            if row.false_negative() {
+                                problems.push("漏识别".to_string());
+                            }
+                            let fp = row.false_positives();
+                            if !fp.is_empty() {
+                                problems.push(format!("误识别为 {}", fp.join(", ")));
+                            }
+                            ui.label(RichText::new(problems.join("；")).color(red));
+                        }
+                        ui.end_row();
+                    }
+                });
+            });
+        });
+        if !open {
+            self.regression_results = None;
+        }
+    }
+
+    fn open_session_replay(&mut self) {
+        let Some(dir) = rfd::FileDialog::new().set_title("选择会话目录 (sessions/<时间>)").pick_folder() else { return };
+        match session::load_events(&dir) {
+            Ok((events, _)) if events.is_empty() => self.status_msg = format!("⚠️ {} 没有任何事件", dir.display()),
+            Ok((events, skipped)) => {
+                let frames = events.iter().filter(|e| matches!(e.kind, SessionEventKind::Frame { .. })).count();
+                self.status_msg = format!("会话 {}：{} 条事件，{} 帧截图", dir.display(), events.len(), frames);
+                if skipped > 0 {
+                    self.status_msg += &format!("，跳过 {} 行无法解析的记录", skipped);
+                }
+                self.session_replay = Some(SessionReplay { dir, events, t_ms: 0, filter: None, frame: None });
+            }
+            Err(e) => self.status_msg = format!("⚠️ {}", e),
+        }
+    }
+
+    /// 会话回放：拖动时间轴查看当时的截图，列表高亮当前时间之前的最后一个事件，点击事件跳转
+    fn draw_session_replay(&mut self, ctx: &egui::Context) {
+        let Some(replay) = &mut self.session_replay else { return };
+        replay.sync_frame(ctx);
+        let mut open = true;
+        let title = format!("🎞️ 会话回放 - {}", replay.dir.file_name().map(|n| n.to_string_lossy()).unwrap_or_default());
+        egui::Window::new(title).id(egui::Id::new("session_replay")).open(&mut open).default_size([900.0, 700.0]).show(ctx, |ui| {
+            let visible = replay.visible();
+            // 当前时间之前的最后一个可见事件
+            let times: Vec<u64> = visible.iter().map(|&i| replay.events[i].t_ms).collect();
+            let current_at = |t: u64| times.iter().rposition(|&et| et <= t);
+            let (before, current) = (replay.t_ms, current_at(replay.t_ms));
+            ui.horizontal(|ui| {
+                if ui.add_enabled(current.is_some_and(|c| c > 0), egui::Button::new("⏮ 上一个")).clicked() {
+                    replay.t_ms = times[current.unwrap() - 1];
+                }
+                let next = current.map_or(0, |c| c + 1);
+                if ui.add_enabled(next < visible.len(), egui::Button::new("下一个 ⏭")).clicked() {
+                    replay.t_ms = times[next];
+                }
+                ui.label(format!("{} / {}", format_ms(replay.t_ms), format_ms(replay.end_ms())));
+                ui.separator();
+                let selected = replay.filter.unwrap_or("全部");
+                egui::ComboBox::from_id_source("session_filter").selected_text(selected).show_ui(ui, |ui| {
+                    ui.selectable_value(&mut replay.filter, None, "全部");
+                    for label in ["输入", "场景", "跳转", "OCR", "弹窗", "导航"] {
+                        ui.selectable_value(&mut replay.filter, Some(label), label);
+                    }
+                });
+            });
+            let end = replay.end_ms();
+            ui.spacing_mut().slider_width = ui.available_width() - 20.0;
+            ui.add(egui::Slider::new(&mut replay.t_ms, 0..=end).show_value(false));
+            // 时间轴或按钮移动时列表跟随当前事件
+            let follow = replay.t_ms != before;
+            let current = if follow { current_at(replay.t_ms) } else { current };
+
+            match &replay.frame {
+                Some((file, tex)) => {
+                    let size = tex.size_vec2();
+                    let scale = (ui.available_width() / size.x).min(380.0 / size.y).min(1.0);
+                    ui.image((tex.id(), size * scale)).on_hover_text(file);
+                }
+                None => {
+                    ui.label("(此时间之前没有截图)");
+                }
+            }
+            if !replay.events.iter().any(|e| matches!(e.kind, SessionEventKind::End { .. })) {
+                ui.label(RichText::new("⚠️ 没有结束记录：进程可能被强制结束或崩溃，最后一条事件即事发时刻").color(Color32::from_rgb(255, 80, 80)));
+            }
+            ui.separator();
+
+            let mut jump = None;
+            egui::ScrollArea::vertical().auto_shrink([false, false]).show(ui, |ui| {
+                for (n, &i) in visible.iter().enumerate() {
+                    let e = &replay.events[i];
+                    let text = format!("{} {:<4} {}", format_ms(e.t_ms), e.kind.label(), e.kind);
+                    let is_current = current == Some(n);
+                    let mut text = RichText::new(text).monospace();
+                    if e.t_ms > replay.t_ms {
+                        text = text.weak();
+                    }
+                    let resp = ui.selectable_label(is_current, text);
+                    if is_current && follow {
+                        resp.scroll_to_me(Some(egui::Align::Center));
+                    }
+                    if resp.clicked() {
+                        jump = Some(e.t_ms);
+                    }
+                }
+            });
+            if let Some(t) = jump {
+                replay.t_ms = t;
+            }
+        });
+        if !open {
+            self.session_replay = None;
+        }
+    }
+
+    fn poll_live_test(&mut self) {
+        let Some((scene_index, rx)) = &self.live_test_rx else { return };
+        let scene_index = *scene_index;
+        let results = match rx.try_recv() {
+            Ok(r) => r,
+            Err(mpsc::TryRecvError::Empty) => return,
+            Err(mpsc::TryRecvError::Disconnected) => None,
+        };
+        self.live_test_rx = None;
+        let Some(results) = results else {
+            self.status_msg = "⚠️ 实时测试截图失败".into();
+            return;
+        };
+        let passed = results.iter().filter(|r| r.passed).count();
+        let verdict = self.scenes.get(scene_index).is_some_and(|s| live_verdict(&s.logic, &results));
+        self.status_msg = format!(
+            "实时测试：{}/{} 个锚点命中，场景{}",
+            passed, results.len(), if verdict { "识别成功 ✔" } else { "未识别 ✘" }
+        );
+        self.live_test_results = Some((scene_index, results));
+    }
+
+    /// 每帧拉取采样线程的进度与结果
+    fn poll_stability_sampling(&mut self) {
+        let mut finished = None;
+        if let Some(run) = &mut self.stability_run {
+            while let Ok(msg) = run.rx.try_recv() {
+                match msg {
+                    StabilityMsg::Progress(p) => run.progress = p,
+                    StabilityMsg::Done(results) => finished = Some((run.scene_index, std::mem::take(&mut run.drafts), results)),
+                }
+            }
+        }
+        if let Some((scene_index, drafts, results)) = finished {
+            let flaky = results.iter().filter(|r| r.is_flaky()).count();
+            self.status_msg = format!("稳定性采样完成：{} 个锚点，其中 {} 个可能闪烁", results.len(), flaky);
+            self.stability_results = Some((scene_index, drafts, results));
+            self.stability_run = None;
+        }
+    }
+
+    /// 采样后场景元素被删除、重排或修改时丢弃结果，避免得分显示在别的锚点上
+    fn expire_stability_results(&mut self) {
+        if let Some((idx, drafts, _)) = &self.stability_results
+            && self.scenes.get(*idx).is_none_or(|s| s.drafts != *drafts) {
+            self.stability_results = None;
+        }
+    }
+
+    fn draw_visualization_panel(&mut self, ui: &mut egui::Ui) {
+        // 需要同时感知点击 (选场景 / 编辑连线) 与拖拽
+        let (resp, painter) = ui.allocate_painter(ui.available_size(), Sense::click_and_drag());
+        let rect = resp.rect;
+        
+        // 绘制背景网格
+        self.draw_grid(&painter, rect);
+        
+        // 应用平移和缩放
+        let transform = |p: Pos2| Pos2::new(
+            p.x * self.viz_zoom + self.viz_pan.x + rect.min.x,
+            p.y * self.viz_zoom + self.viz_pan.y + rect.min.y
+        );
+        let inverse_transform = |p: Pos2| Pos2::new(
+            (p.x - rect.min.x - self.viz_pan.x) / self.viz_zoom,
+            (p.y - rect.min.y - self.viz_pan.y) / self.viz_zoom
+        );
+        
+        // 绘制场景连接线
+        self.draw_scene_connections(&painter, &transform);
+        
+        // 绘制场景矩形
+        let mut clicked_scene = None;
+        for (i, scene) in self.scenes.iter().enumerate() {
+            let scene_rect = Rect::from_min_size(transform(scene.viz_pos), scene.viz_size * self.viz_zoom);
+            let is_selected = i == self.current_scene_index;
+            let has_handler = scene.handler.is_some();
+            
+            // 场景背景色
+            let bg_color = if is_selected {
+                Color32::from_rgb(100, 150, 255)
             } else if has_handler {
                 Color32::from_rgb(150, 200, 150)
             } else {
@@ -593,14 +2311,45 @@ impl MapBuilderTool {
             }
         }
         
+        // 点击连线编辑跳转 (连线优先于场景)
+        let mut clicked_edge = None;
+        if resp.clicked() && let Some(p) = resp.hover_pos() && let Some(edge) = self.hit_test_edge(p, &transform) {
+            clicked_edge = Some(edge);
+            clicked_scene = None;
+        }
+
         // 处理场景选择
         if let Some(scene_idx) = clicked_scene {
             self.current_scene_index = scene_idx;
             self.status_msg = format!("已选择场景：{}", self.scenes[scene_idx].name);
         }
         
+        // Shift+拖拽：从一个场景连线到另一个场景，新建跳转
+        let shift = ui.input(|i| i.modifiers.shift);
+        if resp.drag_started() && shift {
+            let p = resp.interact_pointer_pos().unwrap_or(Pos2::ZERO);
+            self.viz_link_from = self.scenes.iter().position(|s| Rect::from_min_size(transform(s.viz_pos), s.viz_size * self.viz_zoom).contains(p));
+        }
+        if let Some(from) = self.viz_link_from {
+            if let Some(p) = resp.interact_pointer_pos() {
+                let start = Rect::from_min_size(transform(self.scenes[from].viz_pos), self.scenes[from].viz_size * self.viz_zoom).center();
+                painter.add(egui::Shape::dashed_line(&[start, p], Stroke::new(2.0, Color32::from_rgb(255, 120, 0)), 8.0, 4.0));
+            }
+            if resp.drag_released() {
+                self.viz_link_from = None;
+                let to = resp.interact_pointer_pos().or(resp.hover_pos()).and_then(|p| {
+                    self.scenes.iter().position(|s| Rect::from_min_size(transform(s.viz_pos), s.viz_size * self.viz_zoom).contains(p))
+                });
+                if let Some(to) = to.filter(|to| *to != from) {
+                    self.transition_editor = Some(TransitionEditor {
+                        scene: from, draft: None, target: self.scenes[to].id.clone(), coords: [0, 0], post_delay: 500, extra: TransitionExtra::default(),
+                    });
+                }
+            }
+        }
+
         // 处理拖拽
-        if resp.drag_started() {
+        if resp.drag_started() && !shift {
             for (i, scene) in self.scenes.iter().enumerate() {
                 let scene_rect = Rect::from_min_size(transform(scene.viz_pos), scene.viz_size * self.viz_zoom);
                 if let Some(mouse_pos) = resp.hover_pos() {
@@ -616,148 +2365,1804 @@ impl MapBuilderTool {
                 }
             }
         }
-        
-        if let Some(dragging_idx) = self.viz_dragging_scene {
-            if let Some(mouse_pos) = resp.interact_pointer_pos() {
-                let inv_pos = inverse_transform(mouse_pos);
-                self.scenes[dragging_idx].viz_pos = Pos2::new(
-                    inv_pos.x + self.viz_drag_offset.x,
-                    inv_pos.y + self.viz_drag_offset.y
-                );
-            }
-            if resp.drag_released() {
-                self.viz_dragging_scene = None;
+        
+        if let Some(dragging_idx) = self.viz_dragging_scene {
+            if let Some(mouse_pos) = resp.interact_pointer_pos() {
+                let inv_pos = inverse_transform(mouse_pos);
+                self.scenes[dragging_idx].viz_pos = Pos2::new(
+                    inv_pos.x + self.viz_drag_offset.x,
+                    inv_pos.y + self.viz_drag_offset.y
+                );
+            }
+            if resp.drag_released() {
+                self.viz_dragging_scene = None;
+            }
+        }
+        
+        // 处理平移（右键拖拽）
+        if resp.secondary_clicked() {
+            if let Some(_mouse_pos) = resp.interact_pointer_pos() {
+                self.viz_pan += resp.drag_delta();
+            }
+        }
+        
+        // 处理缩放（滚轮）
+        let scroll_delta = ui.input(|i| i.scroll_delta);
+        let zoom_factor = 1.0 + scroll_delta.y * 0.001;
+        self.viz_zoom = (self.viz_zoom * zoom_factor).clamp(0.1, 5.0);
+        
+        // 显示控制提示
+        ui.label("🖱️ 左键拖拽场景 | Shift+左键从场景拖到另一场景新建跳转 | 点击连线编辑跳转 | 右键拖拽平移 | 滚轮缩放");
+
+        if let Some((scene, draft)) = clicked_edge {
+            self.open_transition_editor(scene, draft);
+        }
+
+        self.draw_transition_editor(ui.ctx());
+    }
+
+    /// 导出场景图：按所选文件扩展名输出 DOT 或 SVG
+    fn export_graph(&mut self) {
+        let Some(path) = rfd::FileDialog::new()
+            .set_title("导出场景图")
+            .add_filter("SVG", &["svg"])
+            .add_filter("Graphviz DOT", &["dot", "gv"])
+            .set_file_name("scenes.svg")
+            .save_file() else { return };
+        let is_dot = path.extension().is_some_and(|e| e.eq_ignore_ascii_case("dot") || e.eq_ignore_ascii_case("gv"));
+        let content = if is_dot { scenes_to_dot(&self.scenes) } else { scenes_to_svg(&self.scenes) };
+        self.status_msg = match fs::write(&path, content) {
+            Ok(_) => format!("场景图已导出到 {}", path.display()),
+            Err(e) => format!("⚠️ 导出场景图失败: {}", e),
+        };
+    }
+
+    /// 校验结果列表，点击跳转到对应场景与元素
+    fn draw_validation(&mut self, ui: &mut egui::Ui) {
+        let issues = validate_scenes(&self.scenes, self.texture.as_ref().map(|_| self.img_size));
+        let title = if issues.is_empty() { "🩺 地图校验 ✔".to_string() } else { format!("🩺 地图校验 ⚠️ {} 个问题", issues.len()) };
+        let mut jump = None;
+        ui.collapsing(title, |ui| {
+            if issues.is_empty() {
+                ui.label("未发现问题");
+                return;
+            }
+            if self.texture.is_none() {
+                ui.label(RichText::new("截图后才会检查坐标是否超出范围").weak());
+            }
+            egui::ScrollArea::vertical().id_source("validation").max_height(150.0).show(ui, |ui| {
+                for issue in &issues {
+                    let scene = &self.scenes[issue.scene];
+                    let location = match issue.draft {
+                        Some(d) => format!("[{}] #{}", scene.id, d + 1),
+                        None => format!("[{}]", scene.id),
+                    };
+                    if ui.link(format!("{} {}", location, issue.message)).clicked() {
+                        jump = Some((issue.scene, issue.draft));
+                    }
+                }
+            });
+        });
+        if let Some((scene, draft)) = jump {
+            self.current_scene_index = scene;
+            self.selected_draft = draft.map(|d| (scene, d));
+            self.view = ViewMode::Edit;
+            self.status_msg = format!("已跳转到场景：{}", self.scenes[scene].name);
+        }
+    }
+
+    /// 快速截取：框选后记录为文字锚点 / 颜色锚点 / 点击，输出 [[scenes]] 片段，也可以一次性加入当前场景
+    fn draw_quick_capture(&mut self, ui: &mut egui::Ui) {
+        ui.separator();
+        ui.heading("⚡ 快速截取");
+        ui.label(RichText::new("框选后一键记录，不改动场景；生成的片段可直接粘贴进 ui_map.toml").weak());
+        ui.checkbox(&mut self.is_color_picker_mode, "🧪 吸管取色模式");
+        if let Some(rect) = self.current_rect {
+            let r = [rect.min.x as i32, rect.min.y as i32, rect.max.x as i32, rect.max.y as i32];
+            let center = [rect.center().x as i32, rect.center().y as i32];
+            ui.horizontal(|ui| {
+                ui.monospace(rect_text(rect));
+                if !self.is_color_picker_mode && ui.button("⚓ 文字").on_hover_text("区域 OCR 后记录为文字锚点").clicked() {
+                    self.perform_ocr(rect);
+                    let val = if self.ocr_test_result.is_empty() || self.ocr_test_result.contains("...") { "Text".to_string() } else { self.ocr_test_result.clone() };
+                    self.quick_entries.push(QuickEntry::Text(schema::TextAnchor { rect: r, val, val_regex: None, fuzzy: None }));
+                    self.current_rect = None;
+                }
+                if ui.button("🧪 颜色").on_hover_text("记录选区左上角像素的颜色").clicked() {
+                    let val = self.pick_color(rect.min);
+                    self.quick_entries.push(QuickEntry::Color(schema::ColorAnchor { pos: [r[0], r[1]], val, tol: 15 }));
+                    self.current_rect = None;
+                }
+                if ui.button("🖱️ 点击").on_hover_text("记录选区中心为点击跳转 (target 待填)").clicked() {
+                    let coords = if self.is_color_picker_mode { [r[0], r[1]] } else { center };
+                    self.quick_entries.push(QuickEntry::Click(schema::Transition::click(String::new(), coords)));
+                    self.current_rect = None;
+                }
+            });
+        } else {
+            ui.label("在截图上框选区域 (吸管模式下点选像素)");
+        }
+
+        let mut del = None;
+        egui::ScrollArea::vertical().id_source("quick_entries").max_height(200.0).show(ui, |ui| {
+            for (i, entry) in self.quick_entries.iter().enumerate() {
+                ui.horizontal(|ui| {
+                    if ui.small_button("❌").clicked() { del = Some(i); }
+                    ui.monospace(entry.describe());
+                });
+            }
+        });
+        if let Some(i) = del { self.quick_entries.remove(i); }
+
+        ui.horizontal(|ui| {
+            let enabled = !self.quick_entries.is_empty();
+            if ui.add_enabled(enabled, egui::Button::new("📋 复制片段")).clicked() {
+                match quick_capture_toml(&self.quick_entries) {
+                    Ok(text) => {
+                        ui.output_mut(|o| o.copied_text = text);
+                        self.status_msg = format!("已复制 {} 条记录", self.quick_entries.len());
+                    }
+                    Err(e) => self.status_msg = format!("❌ 生成片段失败: {}", e),
+                }
+            }
+            if ui.add_enabled(enabled, egui::Button::new("📌 加入当前场景")).on_hover_text("转为当前场景的锚点与跳转，之后在「截图编辑」里继续调整").clicked() {
+                let count = self.quick_entries.len();
+                for entry in std::mem::take(&mut self.quick_entries) {
+                    let pos_or_rect = entry.rect();
+                    let kind = match entry {
+                        QuickEntry::Text(t) => ElementKind::TextAnchor { text: t.val },
+                        QuickEntry::Color(c) => ElementKind::ColorAnchor { color_hex: c.val, tolerance: c.tol },
+                        QuickEntry::Click(t) => ElementKind::Button { target: t.target, post_delay: t.post_delay as u32, extra: TransitionExtra::default() },
+                    };
+                    self.current_scene_mut().drafts.push(UIElementDraft { pos_or_rect, kind });
+                }
+                self.status_msg = format!("已将 {} 条记录加入场景：{}", count, self.current_scene().name);
+            }
+            if ui.add_enabled(enabled, egui::Button::new("🗑 清空")).clicked() { self.quick_entries.clear(); }
+        });
+        if !self.quick_entries.is_empty() {
+            let mut preview = quick_capture_toml(&self.quick_entries).unwrap_or_else(|e| e);
+            ui.add(egui::TextEdit::multiline(&mut preview).font(egui::TextStyle::Monospace).desired_width(f32::INFINITY).interactive(false));
+        }
+    }
+
+    /// 连线命中测试：返回 (起点场景, 跳转元素序号)
+    fn hit_test_edge(&self, p: Pos2, transform: &dyn Fn(Pos2) -> Pos2) -> Option<(usize, usize)> {
+        let center = |s: &Scene| Rect::from_min_size(transform(s.viz_pos), s.viz_size * self.viz_zoom).center();
+        for (si, scene) in self.scenes.iter().enumerate() {
+            for (di, draft) in scene.drafts.iter().enumerate() {
+                let ElementKind::Button { target, .. } = &draft.kind else { continue };
+                let Some(to) = self.scenes.iter().find(|s| s.id == *target) else { continue };
+                let (a, b) = (center(scene), center(to));
+                // 点到线段的距离
+                let ab = b - a;
+                let t = if ab.length_sq() > 0.0 { ((p - a).dot(ab) / ab.length_sq()).clamp(0.0, 1.0) } else { 0.0 };
+                if (a + ab * t).distance(p) <= 5.0 {
+                    return Some((si, di));
+                }
+            }
+        }
+        None
+    }
+
+    /// 打开已有跳转的编辑弹窗
+    fn open_transition_editor(&mut self, scene: usize, draft: usize) {
+        let Some(d) = self.scenes.get(scene).and_then(|s| s.drafts.get(draft)) else { return };
+        if let ElementKind::Button { target, post_delay, extra } = &d.kind {
+            let c = d.pos_or_rect.center();
+            self.transition_editor = Some(TransitionEditor {
+                scene, draft: Some(draft), target: target.clone(), coords: [c.x as i32, c.y as i32], post_delay: *post_delay, extra: extra.clone(),
+            });
+        }
+    }
+
+    /// 新建 / 编辑跳转的弹窗：目标、动作 (点击 / 按键 / 拖拽 / 滚动) 及其参数、前后等待与重试
+    fn draw_transition_editor(&mut self, ctx: &egui::Context) {
+        let Some(editor) = &mut self.transition_editor else { return };
+        if editor.scene >= self.scenes.len() {
+            self.transition_editor = None;
+            return;
+        }
+        let (mut apply, mut delete, mut cancel) = (false, false, false);
+        let title = if editor.draft.is_some() { "编辑跳转" } else { "新建跳转" };
+        let scene_ids: Vec<String> = self.scenes.iter().map(|s| s.id.clone()).collect();
+        egui::Window::new(title).collapsible(false).resizable(false).show(ctx, |ui| {
+            ui.label(format!("从场景: {}", self.scenes[editor.scene].id));
+            ui.horizontal(|ui| {
+                ui.label("目标:");
+                egui::ComboBox::from_id_source("transition_target").selected_text(&editor.target).show_ui(ui, |ui| {
+                    for id in &scene_ids {
+                        ui.selectable_value(&mut editor.target, id.clone(), id);
+                    }
+                });
+            });
+            ui.horizontal(|ui| {
+                ui.label("动作:");
+                for action in TransitionAction::ALL {
+                    ui.radio_value(&mut editor.extra.action, action, action.as_str());
+                }
+            });
+            let extra = &mut editor.extra;
+            match extra.action {
+                TransitionAction::Key => {
+                    ui.horizontal(|ui| {
+                        ui.label("按键:");
+                        ui.add(egui::TextEdit::singleline(&mut extra.key).hint_text("esc / enter / f1 ...").desired_width(120.0));
+                    });
+                }
+                action => {
+                    ui.horizontal(|ui| {
+                        ui.label(match action { TransitionAction::Drag => "起点:", TransitionAction::Scroll => "滚动位置:", _ => "点击坐标:" });
+                        ui.add(egui::DragValue::new(&mut editor.coords[0]).prefix("x "));
+                        ui.add(egui::DragValue::new(&mut editor.coords[1]).prefix("y "));
+                    });
+                    if action == TransitionAction::Drag {
+                        ui.horizontal(|ui| {
+                            ui.label("终点:");
+                            ui.add(egui::DragValue::new(&mut extra.drag_to[0]).prefix("x "));
+                            ui.add(egui::DragValue::new(&mut extra.drag_to[1]).prefix("y "));
+                        });
+                    }
+                    if action == TransitionAction::Scroll {
+                        ui.add(egui::DragValue::new(&mut extra.delta).clamp_range(-schema::MAX_SCROLL_DELTA..=schema::MAX_SCROLL_DELTA).prefix("滚轮格数 (正数向上) "));
+                    }
+                }
+            }
+            ui.add(egui::DragValue::new(&mut extra.pre_delay).clamp_range(0..=60000).prefix("动作前等待 ").suffix(" ms"));
+            ui.add(egui::DragValue::new(&mut editor.post_delay).clamp_range(0..=60000).prefix("跳转后等待 ").suffix(" ms"));
+            ui.add(egui::DragValue::new(&mut extra.retries).clamp_range(0..=10).prefix("超时重试 ").suffix(" 次"))
+                .on_hover_text("等待目标场景超时且仍停在本场景时，重新执行动作的次数");
+            ui.horizontal(|ui| {
+                apply = ui.button("✔ 确定").clicked();
+                if editor.draft.is_some() {
+                    delete = ui.button("🗑 删除").clicked();
+                }
+                cancel = ui.button("取消").clicked();
+            });
+        });
+
+        if apply {
+            let rect = Rect::from_center_size(Pos2::new(editor.coords[0] as f32, editor.coords[1] as f32), Vec2::splat(20.0));
+            let draft = UIElementDraft { pos_or_rect: rect, kind: ElementKind::Button { target: editor.target.clone(), post_delay: editor.post_delay, extra: editor.extra.clone() } };
+            let drafts = &mut self.scenes[editor.scene].drafts;
+            match editor.draft.filter(|i| *i < drafts.len()) {
+                Some(i) => drafts[i] = draft,
+                None => drafts.push(draft),
+            }
+            self.status_msg = format!("已保存跳转 {} → {}", self.scenes[editor.scene].id, editor.target);
+        } else if delete && let Some(i) = editor.draft.filter(|i| *i < self.scenes[editor.scene].drafts.len()) {
+            self.scenes[editor.scene].drafts.remove(i);
+            self.status_msg = "已删除跳转".into();
+        }
+        if apply || delete || cancel {
+            self.transition_editor = None;
+        }
+    }
+    
+    fn draw_screenshot_panel(&mut self, ui: &mut egui::Ui) {
+        let (resp, painter) = ui.allocate_painter(ui.available_size(), Sense::click_and_drag());
+        if let Some(tex) = &self.texture {
+            let painter_size = resp.rect.size();
+            let fit = (painter_size.x / self.img_size.x).min(painter_size.y / self.img_size.y);
+
+            // 中键拖拽平移，中键双击复位
+            if resp.dragged_by(egui::PointerButton::Middle) {
+                self.shot_pan += resp.drag_delta();
+            }
+            if resp.double_clicked_by(egui::PointerButton::Middle) {
+                self.shot_zoom = 1.0;
+                self.shot_pan = Vec2::ZERO;
+            }
+            // 滚轮缩放，保持鼠标下的像素不动
+            if let Some(hover) = resp.hover_pos() {
+                let scroll = ui.input(|i| i.scroll_delta.y);
+                if scroll != 0.0 {
+                    let old_scale = fit * self.shot_zoom;
+                    self.shot_zoom = (self.shot_zoom * (1.0 + scroll * 0.002)).clamp(0.5, 32.0);
+                    let img_point = (hover - resp.rect.min - self.shot_pan) / old_scale;
+                    self.shot_pan = hover - resp.rect.min - img_point * (fit * self.shot_zoom);
+                }
+            }
+
+            let scale = fit * self.shot_zoom;
+            let draw_size = self.img_size * scale;
+            let draw_rect = Rect::from_min_size(resp.rect.min + self.shot_pan, draw_size);
+            painter.image(tex.id(), draw_rect, Rect::from_min_max(Pos2::ZERO, Pos2::new(1.0, 1.0)), Color32::WHITE);
+
+            let to_screen = |p: Pos2| Pos2::new(
+                draw_rect.min.x + p.x * scale,
+                draw_rect.min.y + p.y * scale
+            );
+            let from_screen = |p: Pos2| Pos2::new(
+                (p.x - draw_rect.min.x) / scale,
+                (p.y - draw_rect.min.y) / scale
+            );
+
+            // 左键按在已有元素上时拖动 / 调整它，否则框选新区域 (快速截取只框选)
+            let editing = self.view == ViewMode::Edit;
+            if resp.drag_started_by(egui::PointerButton::Primary) && let Some(p) = resp.interact_pointer_pos() {
+                self.element_drag = if editing { self.hit_test_element(p, &to_screen) } else { None };
+                match self.element_drag {
+                    Some((i, _)) => self.selected_draft = Some((self.current_scene_index, i)),
+                    None => self.start_pos = Some(from_screen(p)),
+                }
+            }
+            if editing && resp.clicked_by(egui::PointerButton::Primary) && let Some(p) = resp.interact_pointer_pos() {
+                self.selected_draft = self.hit_test_element(p, &to_screen).map(|(i, _)| (self.current_scene_index, i));
+                // 未点中已有元素时，点中候选框即采用
+                if self.selected_draft.is_none() && let Some(i) = self.anchor_suggestions.iter().position(|s| s.rect.contains(from_screen(p))) {
+                    self.accept_suggestion(i);
+                }
+            }
+            if let Some((i, drag)) = self.element_drag {
+                let delta = resp.drag_delta() / scale;
+                let target = resp.interact_pointer_pos().map(from_screen);
+                if let Some(d) = self.current_scene_mut().drafts.get_mut(i) {
+                    if let Some(target) = target {
+                        d.pos_or_rect = drag_rect(d.pos_or_rect, drag, delta, target);
+                    }
+                    // 松开后对齐到整像素
+                    if resp.drag_released_by(egui::PointerButton::Primary) {
+                        d.pos_or_rect = Rect::from_min_max(d.pos_or_rect.min.round(), d.pos_or_rect.max.round());
+                    }
+                }
+                if resp.drag_released_by(egui::PointerButton::Primary) {
+                    self.element_drag = None;
+                }
+            }
+
+            let selected = self.selected_draft().filter(|_| editing);
+            let drafts: &[UIElementDraft] = if editing { &self.current_scene().drafts } else { &[] };
+            for (i, d) in drafts.iter().enumerate() {
+                let color = match d.kind {
+                    ElementKind::TextAnchor{..} => Color32::GREEN,
+                    ElementKind::ColorAnchor{..} => Color32::from_rgb(255, 165, 0),
+                    ElementKind::HsvAnchor{..} => Color32::from_rgb(255, 215, 0),
+                    ElementKind::Button{..} => Color32::BLUE,
+                    ElementKind::ImageAnchor{..} => Color32::from_rgb(255, 0, 255),
+                };
+                let screen_rect = Rect::from_min_max(to_screen(d.pos_or_rect.min), to_screen(d.pos_or_rect.max));
+                if selected == Some(i) {
+                    painter.rect_stroke(screen_rect, 2.0, Stroke::new(3.0, Color32::YELLOW));
+                    if !matches!(d.kind, ElementKind::ColorAnchor { .. }) {
+                        for corner in rect_corners(screen_rect) {
+                            painter.rect(Rect::from_center_size(corner, Vec2::splat(HANDLE_RADIUS * 1.5)), 1.0, Color32::WHITE, Stroke::new(1.0, Color32::BLACK));
+                        }
+                    }
+                } else {
+                    painter.rect_stroke(screen_rect, 2.0, Stroke::new(2.0, color));
+                }
+                // 颜色锚点旁画出期望颜色的色块
+                if let ElementKind::ColorAnchor { color_hex, .. } | ElementKind::HsvAnchor { color_hex, .. } = &d.kind {
+                    let swatch = Rect::from_min_size(screen_rect.right_top() + Vec2::new(4.0, -14.0), Vec2::splat(12.0));
+                    painter.rect(swatch, 2.0, hex_to_color32(color_hex), Stroke::new(1.0, Color32::BLACK));
+                }
+            }
+
+            // 选中颜色锚点时：周围仍在容差内的像素以半透明青色标出 (区域颜色锚点按单个像素的 HSV 判断)
+            if let Some(img) = &self.raw_image
+                && let Some(d) = selected.and_then(|i| self.current_scene().drafts.get(i))
+                && let Some((center, matcher)) = tolerance_preview(d) {
+                let (cx, cy) = (center.x as i32, center.y as i32);
+                for y in (cy - TOLERANCE_PREVIEW_RADIUS).max(0)..(cy + TOLERANCE_PREVIEW_RADIUS + 1).min(img.height() as i32) {
+                    for x in (cx - TOLERANCE_PREVIEW_RADIUS).max(0)..(cx + TOLERANCE_PREVIEW_RADIUS + 1).min(img.width() as i32) {
+                        let p = img.get_pixel(x as u32, y as u32);
+                        if matcher([p[0], p[1], p[2]]) {
+                            let px = Pos2::new(x as f32, y as f32);
+                            painter.rect_filled(Rect::from_min_max(to_screen(px), to_screen(px + Vec2::splat(1.0))), 0.0, Color32::from_rgba_unmultiplied(0, 255, 255, 90));
+                        }
+                    }
+                }
+            }
+
+            // 鼠标下的实时颜色
+            if let Some(hover) = resp.hover_pos() {
+                let p = from_screen(hover);
+                if p.x >= 0.0 && p.y >= 0.0 && p.x < self.img_size.x && p.y < self.img_size.y {
+                    let hex = self.pick_color(p);
+                    let label = format!("{} ({}, {})", hex, p.x as i32, p.y as i32);
+                    let origin = hover + Vec2::new(16.0, 16.0);
+                    let swatch = Rect::from_min_size(origin, Vec2::splat(14.0));
+                    let galley = painter.layout_no_wrap(label, egui::FontId::monospace(12.0), Color32::WHITE);
+                    let text_rect = Rect::from_min_size(swatch.right_center() + Vec2::new(4.0, -galley.size().y / 2.0), galley.size());
+                    painter.rect_filled(swatch.union(text_rect).expand(3.0), 3.0, Color32::from_black_alpha(180));
+                    painter.rect(swatch, 2.0, hex_to_color32(&hex), Stroke::new(1.0, Color32::WHITE));
+                    painter.galley(text_rect.min, galley);
+                }
+            }
+
+            // 快速截取的记录：白色虚线框
+            if !editing {
+                for entry in &self.quick_entries {
+                    let r = entry.rect();
+                    let screen_rect = Rect::from_min_max(to_screen(r.min), to_screen(r.max));
+                    painter.rect_stroke(screen_rect.expand(1.0), 0.0, Stroke::new(1.5, Color32::WHITE));
+                }
+            }
+
+            // 候选锚点：青色细框 + 识别文字
+            for s in &self.anchor_suggestions {
+                let screen_rect = Rect::from_min_max(to_screen(s.rect.min), to_screen(s.rect.max));
+                painter.rect_stroke(screen_rect, 0.0, Stroke::new(1.0, Color32::from_rgb(0, 200, 255)));
+                painter.text(screen_rect.left_top(), egui::Align2::LEFT_BOTTOM, &s.text, egui::FontId::proportional(12.0), Color32::from_rgb(0, 200, 255));
+            }
+
+            if let (Some(start), Some(curr_raw)) = (self.start_pos, resp.interact_pointer_pos()) {
+                let curr = from_screen(curr_raw);
+                let rect = if self.is_color_picker_mode { Rect::from_min_max(curr, curr + Vec2::splat(1.0)) } else { Rect::from_two_pos(start, curr) };
+                painter.rect_stroke(Rect::from_min_max(to_screen(rect.min), to_screen(rect.max)), 0.0, Stroke::new(1.5, Color32::RED));
+                if !self.is_color_picker_mode {
+                    painter.text(to_screen(rect.min) - Vec2::new(0.0, 4.0), egui::Align2::LEFT_BOTTOM,
+                        format!("{} {}×{}", rect_text(rect), rect.width() as i32, rect.height() as i32),
+                        egui::FontId::monospace(12.0), Color32::RED);
+                }
+                if resp.drag_released_by(egui::PointerButton::Primary) { 
+                    self.current_rect = Some(rect); 
+                    self.start_pos = None; 
+                    self.ocr_test_result.clear(); 
+                }
+            }
+
+            // 截图不属于当前场景时在顶部醒目提示，避免把锚点画到别的画面上
+            if editing && self.current_scene().shot != self.shown_shot {
+                let banner = Rect::from_min_size(resp.rect.min, Vec2::new(resp.rect.width(), 24.0));
+                painter.rect_filled(banner, 0.0, Color32::from_rgba_unmultiplied(180, 30, 30, 200));
+                painter.text(banner.left_center() + Vec2::new(8.0, 0.0), egui::Align2::LEFT_CENTER,
+                    format!("⚠️ 当前截图不是场景 [{}] 的参考截图，请先截图或在左侧「设为本场景截图」", self.current_scene().id),
+                    egui::FontId::proportional(14.0), Color32::WHITE);
+            }
+
+            // 显示控制提示
+            painter.text(
+                resp.rect.left_bottom() + Vec2::new(6.0, -6.0),
+                egui::Align2::LEFT_BOTTOM,
+                format!("🖱️ 左键框选 / 拖动元素与角点 | 方向键微调 (Shift 调大小) | S 截图 A/C/B 添加 Tab 切场景 | 中键拖拽平移 | 滚轮缩放 | 中键双击复位  {:.0}%", scale * 100.0),
+                egui::FontId::proportional(13.0),
+                Color32::from_gray(220),
+            );
+        } else {
+            ui.centered_and_justified(|ui| ui.label("点击左侧『3秒延时截图』或『打开图片』开始工作"));
+        }
+    }
+    
+    fn draw_grid(&self, painter: &egui::Painter, rect: Rect) {
+        let grid_size = 20.0 * self.viz_zoom;
+        let start_x = (self.viz_pan.x % grid_size) + rect.min.x;
+        let start_y = (self.viz_pan.y % grid_size) + rect.min.y;
+        
+        for x in (start_x as i32..rect.right() as i32).step_by(grid_size as usize) {
+            painter.line_segment(
+                [Pos2::new(x as f32, rect.top()), Pos2::new(x as f32, rect.bottom())],
+                Stroke::new(0.5, Color32::from_rgb(220, 220, 220))
+            );
+        }
+        
+        for y in (start_y as i32..rect.bottom() as i32).step_by(grid_size as usize) {
+            painter.line_segment(
+                [Pos2::new(rect.left(), y as f32), Pos2::new(rect.right(), y as f32)],
+                Stroke::new(0.5, Color32::from_rgb(220, 220, 220))
+            );
+        }
+    }
+    
+    fn draw_scene_connections(&self, painter: &egui::Painter, transform: &dyn Fn(Pos2) -> Pos2) {
+        for scene in self.scenes.iter() {
+            let from_rect = Rect::from_min_size(transform(scene.viz_pos), scene.viz_size * self.viz_zoom);
+            let from_center = from_rect.center();
+            
+            for draft in &scene.drafts {
+                if let ElementKind::Button { target, .. } = &draft.kind {
+                    if let Some(target_idx) = self.scenes.iter().position(|s| s.id == *target) {
+                        let target_scene = &self.scenes[target_idx];
+                        let to_rect = Rect::from_min_size(transform(target_scene.viz_pos), target_scene.viz_size * self.viz_zoom);
+                        let to_center = to_rect.center();
+                        
+                        // 绘制连接线
+                        painter.line_segment(
+                            [from_center, to_center],
+                            Stroke::new(2.0, Color32::from_rgb(100, 100, 200))
+                        );
+                        
+                        // 绘制箭头
+                        let direction = (to_center - from_center).normalized();
+                        let arrow_size = 10.0 * self.viz_zoom;
+                        let arrow_tip = to_center - direction * (target_scene.viz_size.x * self.viz_zoom / 2.0 + 5.0);
+                        
+                        let perp = Vec2::new(-direction.y, direction.x) * (arrow_size * 0.5);
+                        painter.add(egui::Shape::convex_polygon(
+                            vec![
+                                arrow_tip,
+                                arrow_tip - direction * arrow_size + perp,
+                                arrow_tip - direction * arrow_size - perp
+                            ],
+                            Color32::from_rgb(100, 100, 200),
+                            Stroke::new(1.0, Color32::from_rgb(100, 100, 200))
+                        ));
+                    }
+                }
+            }
+        }
+    }
+} // 🔥 MapBuilderTool 实现块结束
+
+// ==========================================
+// 2.4 导出前校验
+// ==========================================
+/// 校验发现的问题；draft 为出问题的元素 (场景级问题为 None)
+struct MapIssue {
+    scene: usize,
+    draft: Option<usize>,
+    message: String,
+}
+
+/// 检查跳转目标、重复 ID、无锚点场景、颜色格式、超出截图范围的坐标 (resolution 为截图尺寸)
+fn validate_scenes(scenes: &[Scene], resolution: Option<Vec2>) -> Vec<MapIssue> {
+    let mut issues = Vec::new();
+    for (si, scene) in scenes.iter().enumerate() {
+        let issue = |draft: Option<usize>, message: String| MapIssue { scene: si, draft, message };
+        if scenes[..si].iter().any(|s| s.id == scene.id) {
+            issues.push(issue(None, format!("场景 ID [{}] 重复", scene.id)));
+        }
+        if !scene.drafts.iter().any(|d| !matches!(d.kind, ElementKind::Button { .. })) {
+            issues.push(issue(None, "没有任何锚点，无法识别".into()));
+        }
+        for (di, d) in scene.drafts.iter().enumerate() {
+            match &d.kind {
+                ElementKind::Button { target, .. } if !scenes.iter().any(|s| s.id == *target) => {
+                    issues.push(issue(Some(di), format!("跳转目标 [{}] 不存在", target)));
+                }
+                ElementKind::Button { extra, .. } if extra.action == TransitionAction::Key && extra.key.trim().is_empty() => {
+                    issues.push(issue(Some(di), "按键跳转没有填写按键".into()));
+                }
+                ElementKind::Button { extra, .. } if extra.action == TransitionAction::Scroll && extra.delta.abs() > schema::MAX_SCROLL_DELTA => {
+                    issues.push(issue(Some(di), format!("滚动 {} 格超过上限 {} (delta 为滚轮格数，不是 120 的倍数)", extra.delta, schema::MAX_SCROLL_DELTA)));
+                }
+                ElementKind::ColorAnchor { color_hex, .. } | ElementKind::HsvAnchor { color_hex, .. } => {
+                    let hex = color_hex.trim_start_matches('#');
+                    if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+                        issues.push(issue(Some(di), format!("颜色 [{}] 不是有效的 #RRGGBB", color_hex)));
+                    }
+                }
+                _ => {}
+            }
+            if let Some(size) = resolution {
+                let r = d.pos_or_rect;
+                // 按键跳转没有坐标
+                if matches!(&d.kind, ElementKind::Button { extra, .. } if extra.action == TransitionAction::Key) {
+                    continue;
+                }
+                let point = matches!(d.kind, ElementKind::ColorAnchor { .. } | ElementKind::Button { .. });
+                // 颜色与按钮只看取色点 / 点击点，区域锚点看整个矩形
+                let outside = if point {
+                    let p = if matches!(d.kind, ElementKind::Button { .. }) { r.center() } else { r.min };
+                    p.x < 0.0 || p.y < 0.0 || p.x >= size.x || p.y >= size.y
+                } else {
+                    r.min.x < 0.0 || r.min.y < 0.0 || r.max.x > size.x || r.max.y > size.y
+                };
+                if outside {
+                    issues.push(issue(Some(di), format!("坐标超出截图范围 {}x{}", size.x, size.y)));
+                }
+            }
+        }
+    }
+    issues
+}
+
+// ==========================================
+// 2.5 TOML 增量写入 (toml_edit)
+// ==========================================
+
+/// 将编辑器中的场景同步进文档树。
+/// 已存在的场景 (按 id 匹配) 原地更新，保留其注释与未知字段；新场景追加到末尾；
+/// 编辑器中已删除的场景从文档中移除。
+fn sync_scenes_into_doc(doc: &mut toml_edit::DocumentMut, scenes: &[Scene]) {
+    let mut old_tables: Vec<toml_edit::Table> = match doc.get_mut("scenes").and_then(|i| i.as_array_of_tables_mut()) {
+        Some(arr) => std::mem::take(arr).into_iter().collect(),
+        None => Vec::new(),
+    };
+
+    let mut new_arr = toml_edit::ArrayOfTables::new();
+    for scene in scenes {
+        let existing = old_tables
+            .iter()
+            .position(|t| t.get("id").and_then(|v| v.as_str()) == Some(scene.id.as_str()))
+            .map(|i| old_tables.remove(i));
+
+        let first = new_arr.is_empty();
+        let mut table = existing.unwrap_or_else(|| {
+            let mut t = toml_edit::Table::new();
+            if !first { t.decor_mut().set_prefix("\n"); }
+            t
+        });
+        write_scene_table(&mut table, scene);
+        new_arr.push(table);
+    }
+
+    doc["scenes"] = toml_edit::Item::ArrayOfTables(new_arr);
+}
+
+fn write_scene_table(table: &mut toml_edit::Table, scene: &Scene) {
+    let logic_str = if scene.logic == RecognitionLogic::AND { "and" } else { "or" };
+    set_value_keep_decor(table, "id", scene.id.as_str().into());
+    set_value_keep_decor(table, "name", scene.name.as_str().into());
+    set_value_keep_decor(table, "logic", logic_str.into());
+
+    match scene.handler.as_deref() {
+        Some(h) if !h.is_empty() => set_value_keep_decor(table, "handler", h.into()),
+        _ => { table.remove("handler"); }
+    }
+
+    // --- 锚点 ---
+    if !table.get("anchors").is_some_and(|i| i.is_table()) {
+        let mut anchors = toml_edit::Table::new();
+        anchors.decor_mut().set_prefix("\n");
+        table.insert("anchors", toml_edit::Item::Table(anchors));
+    }
+    if let Some(anchors) = table.get_mut("anchors").and_then(|i| i.as_table_mut()) {
+        // 旧数组中的元素按位置 / val 复用，保留其注释与工具不认识的字段 (val_regex、fuzzy 等)
+        let (mut old_texts, text_trailing) = take_array_items(anchors, "text");
+        let (mut old_colors, color_trailing) = take_array_items(anchors, "color");
+        let (mut old_hsvs, hsv_trailing) = take_array_items(anchors, "hsv");
+        let (mut old_images, image_trailing) = take_array_items(anchors, "image");
+        let mut texts = Vec::new();
+        let mut colors = Vec::new();
+        let mut hsvs = Vec::new();
+        let mut images = Vec::new();
+        for d in &scene.drafts {
+            match &d.kind {
+                ElementKind::TextAnchor { text } => {
+                    texts.push(merge_inline(&mut old_texts, "rect", vec![
+                        ("rect", rect_value(d.pos_or_rect)),
+                        ("val", text.as_str().into()),
+                    ]));
+                }
+                ElementKind::ColorAnchor { color_hex, tolerance } => {
+                    colors.push(merge_inline(&mut old_colors, "pos", vec![
+                        ("pos", int_array(&[d.pos_or_rect.min.x as i64, d.pos_or_rect.min.y as i64])),
+                        ("val", color_hex.as_str().into()),
+                        ("tol", (*tolerance as i64).into()),
+                    ]));
+                }
+                ElementKind::HsvAnchor { .. } => {
+                    if let Some(h) = hsv_anchor(&d.kind, d.pos_or_rect) {
+                        let round2 = |v: f32| (v as f64 * 100.0).round() / 100.0;
+                        hsvs.push(merge_inline(&mut old_hsvs, "pos", vec![
+                            ("pos", int_array(&[h.pos[0] as i64, h.pos[1] as i64])),
+                            ("val", h.val.as_str().into()),
+                            ("size", (h.size as i64).into()),
+                            ("hue_tol", round2(h.hue_tol).into()),
+                            ("sat_tol", round2(h.sat_tol).into()),
+                            ("val_tol", round2(h.val_tol).into()),
+                        ]));
+                    }
+                }
+                ElementKind::ImageAnchor { file, threshold } => {
+                    images.push(merge_inline(&mut old_images, "rect", vec![
+                        ("rect", rect_value(d.pos_or_rect)),
+                        ("file", file.as_str().into()),
+                        ("threshold", ((*threshold as f64 * 100.0).round() / 100.0).into()),
+                    ]));
+                }
+                ElementKind::Button { .. } => {}
+            }
+        }
+        set_value_keep_decor(anchors, "text", multiline_array(texts, text_trailing));
+        set_value_keep_decor(anchors, "color", multiline_array(colors, color_trailing));
+        // 区域颜色锚点与图像锚点一样，没有时不写空键
+        if hsvs.is_empty() {
+            anchors.remove("hsv");
+        } else {
+            set_value_keep_decor(anchors, "hsv", multiline_array(hsvs, hsv_trailing));
+        }
+        // 没有图像锚点的场景不写空的 image 键
+        if images.is_empty() {
+            anchors.remove("image");
+        } else {
+            set_value_keep_decor(anchors, "image", multiline_array(images, image_trailing));
+        }
+    }
+
+    // --- 跳转 ---
+    let mut old_trans: Vec<toml_edit::Table> = match table.get_mut("transitions").and_then(|i| i.as_array_of_tables_mut()) {
+        Some(arr) => std::mem::take(arr).into_iter().collect(),
+        None => Vec::new(),
+    };
+    let mut trans = toml_edit::ArrayOfTables::new();
+    for d in &scene.drafts {
+        if let ElementKind::Button { target, post_delay, extra } = &d.kind {
+            // 优先复用同目标同坐标、其次同目标的旧表，保留其上方的注释与其他字段 (action、cost、on_fail 等)
+            let coords = [d.pos_or_rect.center().x as i64, d.pos_or_rect.center().y as i64];
+            let same_target = |t: &toml_edit::Table| t.get("target").and_then(|v| v.as_str()) == Some(target.as_str());
+            let same_coords = |t: &toml_edit::Table| t.get("coords").and_then(|v| v.as_array())
+                .is_some_and(|a| a.iter().filter_map(|x| x.as_integer()).eq(coords));
+            let existing = old_trans.iter().position(|t| same_target(t) && same_coords(t))
+                .or_else(|| old_trans.iter().position(same_target))
+                .map(|i| old_trans.remove(i));
+            let first = trans.is_empty();
+            let mut t = existing.unwrap_or_else(|| {
+                let mut t = toml_edit::Table::new();
+                t.decor_mut().set_prefix(if first { "\n# --- 动作步骤 ---\n" } else { "\n" });
+                t
+            });
+            set_value_keep_decor(&mut t, "target", target.as_str().into());
+            write_transition_action(&mut t, coords, extra);
+            set_value_keep_decor(&mut t, "post_delay", (*post_delay as i64).into());
+            set_or_remove(&mut t, "pre_delay", (extra.pre_delay > 0).then(|| (extra.pre_delay as i64).into()));
+            set_or_remove(&mut t, "retries", (extra.retries > 0).then(|| (extra.retries as i64).into()));
+            trans.push(t);
+        }
+    }
+    if trans.is_empty() {
+        table.remove("transitions");
+    } else {
+        table.insert("transitions", toml_edit::Item::ArrayOfTables(trans));
+    }
+}
+
+/// 按动作写入 action 及其参数，并移除其他动作才用的键；click 不写 action (沿用文件里已有的写法)
+fn write_transition_action(t: &mut toml_edit::Table, coords: [i64; 2], extra: &TransitionExtra) {
+    let action = extra.action;
+    let keep_click = action == TransitionAction::Click && t.contains_key("action");
+    set_or_remove(t, "action", (action != TransitionAction::Click || keep_click).then(|| action.as_str().into()));
+    let uses_coords = matches!(action, TransitionAction::Click | TransitionAction::Scroll);
+    set_or_remove(t, "coords", uses_coords.then(|| int_array(&coords)));
+    set_or_remove(t, "key", (action == TransitionAction::Key).then(|| extra.key.as_str().into()));
+    let drag = action == TransitionAction::Drag;
+    set_or_remove(t, "from", drag.then(|| int_array(&coords)));
+    set_or_remove(t, "to", drag.then(|| int_array(&[extra.drag_to[0] as i64, extra.drag_to[1] as i64])));
+    set_or_remove(t, "delta", (action == TransitionAction::Scroll).then(|| (extra.delta as i64).into()));
+}
+
+fn set_or_remove(table: &mut toml_edit::Table, key: &str, value: Option<toml_edit::Value>) {
+    match value {
+        Some(v) => set_value_keep_decor(table, key, v),
+        None => { table.remove(key); }
+    }
+}
+
+/// 替换值但保留其前后缀装饰 (行尾注释等)
+fn set_value_keep_decor(table: &mut toml_edit::Table, key: &str, mut new_val: toml_edit::Value) {
+    if let Some(old) = table.get_mut(key).and_then(|i| i.as_value_mut()) {
+        *new_val.decor_mut() = old.decor().clone();
+        *old = new_val;
+    } else {
+        table.insert(key, toml_edit::Item::Value(new_val));
+    }
+}
+
+fn int_array(vals: &[i64]) -> toml_edit::Value {
+    let mut arr = toml_edit::Array::new();
+    for v in vals { arr.push(*v); }
+    toml_edit::Value::Array(arr)
+}
+
+fn rect_value(r: Rect) -> toml_edit::Value {
+    int_array(&[r.min.x as i64, r.min.y as i64, r.max.x as i64, r.max.y as i64])
+}
+
+/// 每个元素独占一行的数组，与手写风格一致；复用的旧元素保留原有前缀 (含其上方的注释)，
+/// trailing 为旧数组末尾 (最后一个元素之后) 的内容
+fn multiline_array(items: Vec<toml_edit::Value>, trailing: Option<String>) -> toml_edit::Value {
+    let mut arr = toml_edit::Array::new();
+    for mut v in items {
+        if v.decor().prefix().is_none() {
+            v.decor_mut().set_prefix("\n  ");
+        }
+        arr.push_formatted(v);
+    }
+    arr.set_trailing_comma(!arr.is_empty());
+    arr.set_trailing(trailing.unwrap_or_else(|| "\n".into()));
+    toml_edit::Value::Array(arr)
+}
+
+/// 取出表中某个数组的全部元素 (带各自的装饰)，以及数组末尾的内容；不存在时为空
+fn take_array_items(table: &mut toml_edit::Table, key: &str) -> (Vec<toml_edit::Value>, Option<String>) {
+    let Some(arr) = table.get_mut(key).and_then(|i| i.as_array_mut()) else { return (Vec::new(), None) };
+    let trailing = arr.trailing().as_str().filter(|t| t.contains('#')).map(String::from);
+    (std::mem::take(arr).into_iter().collect(), trailing)
+}
+
+/// 用于比较的值：整数数组按数值比较，字符串按内容比较
+fn value_key(v: &toml_edit::Value) -> String {
+    match v {
+        toml_edit::Value::Array(a) => a.iter().map(|x| x.as_integer().unwrap_or_default().to_string()).collect::<Vec<_>>().join(","),
+        other => other.as_str().map(String::from).unwrap_or_else(|| other.to_string().trim().to_string()),
+    }
+}
+
+/// 从旧元素中取出对应的一项复用：先按 geom_key (rect / pos) 匹配，再按 val 匹配，都没有时新建；
+/// 只覆盖 fields 中的字段，其余字段与注释原样保留
+fn merge_inline(pool: &mut Vec<toml_edit::Value>, geom_key: &str, fields: Vec<(&str, toml_edit::Value)>) -> toml_edit::Value {
+    let wanted = |key: &str| fields.iter().find(|(k, _)| *k == key).map(|(_, v)| value_key(v));
+    let matches = |item: &toml_edit::Value, key: &str| {
+        let old = item.as_inline_table().and_then(|t| t.get(key)).map(value_key);
+        old.is_some() && old == wanted(key)
+    };
+    let index = pool.iter().position(|v| matches(v, geom_key)).or_else(|| pool.iter().position(|v| matches(v, "val")));
+    let mut value = match index {
+        Some(i) => pool.remove(i),
+        None => toml_edit::Value::InlineTable(toml_edit::InlineTable::new()),
+    };
+    if let Some(t) = value.as_inline_table_mut() {
+        for (key, mut v) in fields {
+            match t.get_mut(key) {
+                Some(old) => {
+                    *v.decor_mut() = old.decor().clone();
+                    *old = v;
+                }
+                None => { t.insert(key, v); }
+            }
+        }
+    }
+    value
+}
+
+// ==========================================
+// 2.6 OCR 与锚点稳定性采样
+// ==========================================
+
+/// 区域识别使用的 OCR 后端：WinRT (可选识别语言)，Linux 上为 tesseract (可选语言包)，
+/// 或复用主程序的 PaddleOCR 后端 (feature = "paddle")
+enum RegionOcr {
+    #[cfg(feature = "windows")]
+    WinRt(OcrEngine, String),
+    /// tesseract 语言包名 (如 chi_sim)
+    #[cfg(not(feature = "windows"))]
+    Tesseract(String),
+    #[cfg(feature = "paddle")]
+    Paddle(Box<dyn nzm_cmd::ocr::OcrBackend>),
+}
+
+/// 未选择语言时 tesseract 使用的语言包
+#[cfg(not(feature = "windows"))]
+const TESSERACT_DEFAULT_LANG: &str = "chi_sim";
+
+impl RegionOcr {
+    /// choice 同 ToolSettings::ocr
+    fn create(choice: &str) -> Result<Self, String> {
+        if choice.eq_ignore_ascii_case("paddle") {
+            #[cfg(feature = "paddle")]
+            {
+                let cfg = nzm_cmd::ocr::OcrConfig { backend: "paddle".into(), ..Default::default() };
+                let backend = nzm_cmd::ocr::create_backend(&cfg);
+                // 初始化失败时 create_backend 会回退到平台默认后端
+                if backend.name() != "paddle" {
+                    return Err(format!("PaddleOCR 初始化失败，请检查 {} / {} / {}", cfg.model, cfg.dict, cfg.runtime));
+                }
+                return Ok(RegionOcr::Paddle(backend));
+            }
+            #[cfg(not(feature = "paddle"))]
+            return Err("未启用 paddle feature (cargo run --release --features paddle)".into());
+        }
+        #[cfg(feature = "windows")]
+        {
+            let engine = if choice.is_empty() {
+                OcrEngine::TryCreateFromUserProfileLanguages()
+            } else {
+                Language::CreateLanguage(&windows::core::HSTRING::from(choice)).and_then(|lang| OcrEngine::TryCreateFromLanguage(&lang))
+            };
+            let engine = engine.map_err(|e| format!("无法创建 [{}] 识别引擎: {}", if choice.is_empty() { "用户语言" } else { choice }, e))?;
+            let tag = engine.RecognizerLanguage().and_then(|l| l.LanguageTag()).map(|t| t.to_string()).unwrap_or_default();
+            Ok(RegionOcr::WinRt(engine, tag))
+        }
+        #[cfg(not(feature = "windows"))]
+        {
+            let lang = if choice.is_empty() { TESSERACT_DEFAULT_LANG } else { choice };
+            if !tesseract_languages().iter().any(|l| l == lang) {
+                return Err(format!("tesseract 不可用或未安装语言包 [{}] (如 apt install tesseract-ocr tesseract-ocr-chi-sim)", lang));
+            }
+            Ok(RegionOcr::Tesseract(lang.to_string()))
+        }
+    }
+
+    fn name(&self) -> String {
+        match self {
+            #[cfg(feature = "windows")]
+            RegionOcr::WinRt(_, tag) => format!("WinRT {}", tag),
+            #[cfg(not(feature = "windows"))]
+            RegionOcr::Tesseract(lang) => format!("Tesseract {}", lang),
+            #[cfg(feature = "paddle")]
+            RegionOcr::Paddle(_) => "PaddleOCR".into(),
+        }
+    }
+
+    /// 可选的识别语言 (标签, 显示名)：系统已安装的 WinRT 识别语言，Linux 上为 tesseract 语言包
+    fn languages() -> Vec<(String, String)> {
+        #[cfg(feature = "windows")]
+        {
+            let Ok(languages) = OcrEngine::AvailableRecognizerLanguages() else { return Vec::new() };
+            languages.into_iter()
+                .filter_map(|l| Some((l.LanguageTag().ok()?.to_string(), l.DisplayName().ok()?.to_string())))
+                .collect()
+        }
+        #[cfg(not(feature = "windows"))]
+        tesseract_languages().into_iter().filter(|l| l != "osd").map(|l| (l.clone(), format!("tesseract {}", l))).collect()
+    }
+
+    fn recognize(&self, img: &image::RgbaImage, rect: Rect) -> Result<String, String> {
+        match self {
+            #[cfg(feature = "windows")]
+            RegionOcr::WinRt(engine, _) => recognize_region(engine, img, rect),
+            #[cfg(not(feature = "windows"))]
+            RegionOcr::Tesseract(lang) => recognize_region(lang, img, rect),
+            #[cfg(feature = "paddle")]
+            RegionOcr::Paddle(backend) => {
+                let (x, y) = (rect.min.x.max(0.0) as u32, rect.min.y.max(0.0) as u32);
+                let (w, h) = (rect.width().max(1.0) as u32, rect.height().max(1.0) as u32);
+                if x + w > img.width() || y + h > img.height() {
+                    return Err("区域超出图片范围".into());
+                }
+                // 主程序使用 image 0.25，按原始像素转换
+                let sub = image::imageops::crop_imm(img, x, y, w, h).to_image();
+                let sub = image25::RgbaImage::from_raw(w, h, sub.into_raw()).ok_or("图像转换失败")?;
+                let r = [x as i32, y as i32, (x + w) as i32, (y + h) as i32];
+                Ok(backend.recognize(&image25::DynamicImage::ImageRgba8(sub), r).text)
+            }
+        }
+    }
+}
+
+/// 裁出区域并 2 倍放大 (识别小字更稳)
+fn scaled_region(img: &image::RgbaImage, rect: Rect) -> Result<image::RgbaImage, String> {
+    let x = rect.min.x.max(0.0) as u32;
+    let y = rect.min.y.max(0.0) as u32;
+    let w = rect.width().max(1.0) as u32;
+    let h = rect.height().max(1.0) as u32;
+
+    if x + w > img.width() || y + h > img.height() {
+        return Err("区域超出图片范围".into());
+    }
+
+    let sub_img = image::imageops::crop_imm(img, x, y, w, h).to_image();
+    Ok(image::imageops::resize(&sub_img, w * 2, h * 2, image::imageops::FilterType::Lanczos3))
+}
+
+/// 对截图中的区域执行 OCR (2 倍放大后识别)
+#[cfg(feature = "windows")]
+fn recognize_region(engine: &OcrEngine, img: &image::RgbaImage, rect: Rect) -> Result<String, String> {
+    let result = run_winrt_ocr(engine, &scaled_region(img, rect)?)?;
+
+    let mut text = String::new();
+    if let Ok(lines) = result.Lines() {
+        for line in lines {
+            if let Ok(h_str) = line.Text() {
+                text.push_str(&h_str.to_string());
+            }
+        }
+    }
+    Ok(text.replace(char::is_whitespace, ""))
+}
+
+/// 对截图中的区域执行 OCR (2 倍放大后按单行识别)
+#[cfg(not(feature = "windows"))]
+fn recognize_region(lang: &str, img: &image::RgbaImage, rect: Rect) -> Result<String, String> {
+    let words = run_tesseract(lang, 7, &scaled_region(img, rect)?)?;
+    Ok(words.into_iter().map(|w| w.text).collect::<String>().replace(char::is_whitespace, ""))
+}
+
+/// 把图片编码为 PNG 交给 WinRT 识别
+#[cfg(feature = "windows")]
+fn run_winrt_ocr(engine: &OcrEngine, img: &image::RgbaImage) -> Result<OcrResult, String> {
+    let mut png_buffer = Cursor::new(Vec::new());
+    if img.write_to(&mut png_buffer, image::ImageFormat::Png).is_err() {
+        return Err("图像编码失败".into());
+    }
+    let png_bytes = png_buffer.into_inner();
+
+    let run_recognition = || -> windows::core::Result<OcrResult> {
+        let stream = InMemoryRandomAccessStream::new()?;
+        let writer = DataWriter::CreateDataWriter(&stream)?;
+        writer.WriteBytes(&png_bytes)?;
+        writer.StoreAsync()?.get()?;
+        writer.FlushAsync()?.get()?;
+        stream.Seek(0)?;
+
+        let decoder = BitmapDecoder::CreateAsync(&stream)?.get()?;
+        let bmp = decoder.GetSoftwareBitmapAsync()?.get()?;
+        engine.RecognizeAsync(&bmp)?.get()
+    };
+
+    run_recognition().map_err(|e| format!("API 错误: {:?}", e))
+}
+
+/// tesseract TSV 中的一个词
+#[cfg(not(feature = "windows"))]
+struct TesseractWord {
+    /// (block, par, line)：同一行的词相同
+    line: (u32, u32, u32),
+    rect: Rect,
+    text: String,
+}
+
+/// 已安装的 tesseract 语言包；tesseract 不可用时为空
+#[cfg(not(feature = "windows"))]
+fn tesseract_languages() -> Vec<String> {
+    let Ok(output) = std::process::Command::new("tesseract").arg("--list-langs").output() else { return Vec::new() };
+    // 第一行为 "List of available languages ..."
+    String::from_utf8_lossy(&output.stdout).lines().skip(1).map(|l| l.trim().to_string()).filter(|l| !l.is_empty()).collect()
+}
+
+/// 把图片以 PNG 经 stdin 交给 tesseract，解析 TSV 的词级结果
+#[cfg(not(feature = "windows"))]
+fn run_tesseract(lang: &str, psm: u8, img: &image::RgbaImage) -> Result<Vec<TesseractWord>, String> {
+    use std::io::Write;
+    use std::process::{Command, Stdio};
+
+    let mut png = std::io::Cursor::new(Vec::new());
+    img.write_to(&mut png, image::ImageFormat::Png).map_err(|_| "图像编码失败".to_string())?;
+    let mut child = Command::new("tesseract")
+        .args(["stdin", "stdout", "-l", lang, "--psm", &psm.to_string(), "tsv"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("无法启动 tesseract: {}", e))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(png.get_ref()).map_err(|e| e.to_string())?;
+    }
+    let output = child.wait_with_output().map_err(|e| e.to_string())?;
+    if !output.status.success() {
+        return Err(format!("tesseract 错误: {}", String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    // 列：level page block par line word left top width height conf text
+    let words = String::from_utf8_lossy(&output.stdout).lines().skip(1).filter_map(|l| {
+        let cols: Vec<&str> = l.split('\t').collect();
+        if cols.len() < 12 || cols[0] != "5" || cols[11].trim().is_empty() {
+            return None;
+        }
+        let n = |i: usize| cols[i].parse::<u32>().ok();
+        let rect = Rect::from_min_size(Pos2::new(n(6)? as f32, n(7)? as f32), Vec2::new(n(8)? as f32, n(9)? as f32));
+        Some(TesseractWord { line: (n(2)?, n(3)?, n(4)?), rect, text: cols[11].trim().to_string() })
+    }).collect();
+    Ok(words)
+}
+
+/// 整图 OCR 的结果按行合并：(去除空白的文字, 行包围框 = 各词包围框的并集)
+#[cfg(feature = "windows")]
+fn ocr_lines(engine: &OcrEngine, img: &image::RgbaImage) -> Result<Vec<(String, Rect)>, String> {
+    // 超过引擎支持的最大边长时先缩小，结果坐标再换算回原图
+    let max_dim = OcrEngine::MaxImageDimension().unwrap_or(2600) as f32;
+    let factor = (max_dim / img.width().max(img.height()) as f32).min(1.0);
+    let result = if factor < 1.0 {
+        let (w, h) = ((img.width() as f32 * factor) as u32, (img.height() as f32 * factor) as u32);
+        run_winrt_ocr(engine, &image::imageops::resize(img, w, h, image::imageops::FilterType::Triangle))?
+    } else {
+        run_winrt_ocr(engine, img)?
+    };
+
+    let mut lines = Vec::new();
+    for line in result.Lines().map_err(|e| format!("API 错误: {:?}", e))? {
+        let text = line.Text().map(|t| t.to_string().replace(char::is_whitespace, "")).unwrap_or_default();
+        let Ok(words) = line.Words() else { continue };
+        let mut rect = Rect::NOTHING;
+        for word in words {
+            if let Ok(b) = word.BoundingRect() {
+                rect = rect.union(Rect::from_min_size(Pos2::new(b.X, b.Y), Vec2::new(b.Width, b.Height)));
+            }
+        }
+        if rect.is_positive() {
+            lines.push((text, Rect::from_min_max((rect.min.to_vec2() / factor).to_pos2(), (rect.max.to_vec2() / factor).to_pos2())));
+        }
+    }
+    Ok(lines)
+}
+
+#[cfg(not(feature = "windows"))]
+fn ocr_lines(lang: &str, img: &image::RgbaImage) -> Result<Vec<(String, Rect)>, String> {
+    // psm 3：自动分块，整屏截图中的文字按块 / 段 / 行分组
+    let mut lines: Vec<((u32, u32, u32), String, Rect)> = Vec::new();
+    for word in run_tesseract(lang, 3, img)? {
+        match lines.last_mut() {
+            Some((key, text, rect)) if *key == word.line => {
+                text.push_str(&word.text);
+                *rect = rect.union(word.rect);
+            }
+            _ => lines.push((word.line, word.text, word.rect)),
+        }
+    }
+    Ok(lines.into_iter().map(|(_, text, rect)| (text.replace(char::is_whitespace, ""), rect)).collect())
+}
+
+/// 自动建议的候选文字锚点
+#[derive(Clone)]
+struct AnchorSuggestion {
+    rect: Rect,
+    text: String,
+    /// 区域内亮度标准差，越大文字与背景对比越强
+    contrast: f32,
+}
+
+/// 候选锚点的最低对比度与最多保留条数
+const SUGGEST_MIN_CONTRAST: f32 = 40.0;
+const SUGGEST_MAX: usize = 12;
+
+/// 从整图 OCR 的各行中挑选适合作锚点的区域：
+/// 至少 2 个字、不含数字 (计数 / 倒计时等会变化)、对比度足够，按对比度从高到低排列
+fn suggest_anchors(lines: Vec<(String, Rect)>, img: &image::RgbaImage) -> Vec<AnchorSuggestion> {
+    let gray = image::imageops::grayscale(img);
+    let bounds = Rect::from_min_size(Pos2::ZERO, Vec2::new(img.width() as f32, img.height() as f32));
+    let mut suggestions = Vec::new();
+    for (text, rect) in lines {
+        if text.chars().count() < 2 || text.chars().any(|c| c.is_ascii_digit()) {
+            continue;
+        }
+        // 外扩几个像素，给识别留余量
+        let rect = rect.expand(4.0).intersect(bounds);
+        let rect = Rect::from_min_max(rect.min.round(), rect.max.round());
+        let contrast = luminance_stddev(&gray, rect);
+        if contrast >= SUGGEST_MIN_CONTRAST {
+            suggestions.push(AnchorSuggestion { rect, text, contrast });
+        }
+    }
+    suggestions.sort_by(|a, b| b.contrast.total_cmp(&a.contrast));
+    suggestions.truncate(SUGGEST_MAX);
+    suggestions
+}
+
+/// 区域内亮度的标准差
+fn luminance_stddev(gray: &image::GrayImage, rect: Rect) -> f32 {
+    let (x0, y0) = (rect.min.x as u32, rect.min.y as u32);
+    let (x1, y1) = ((rect.max.x as u32).min(gray.width()), (rect.max.y as u32).min(gray.height()));
+    let (mut sum, mut sum_sq, mut n) = (0.0f64, 0.0f64, 0u32);
+    for y in y0..y1 {
+        for x in x0..x1 {
+            let v = gray.get_pixel(x, y)[0] as f64;
+            sum += v;
+            sum_sq += v * v;
+            n += 1;
+        }
+    }
+    if n == 0 {
+        return 0.0;
+    }
+    let mean = sum / n as f64;
+    (sum_sq / n as f64 - mean * mean).max(0.0).sqrt() as f32
+}
+
+/// 截取指定显示器整屏；未指定或找不到时截取第一个显示器
+fn capture_screen(monitor_id: Option<u32>) -> Option<image::RgbaImage> {
+    pick_screen(monitor_id)?.capture().ok()
+}
+
+/// 按 ID 选择显示器，找不到时用第一个
+fn pick_screen(monitor_id: Option<u32>) -> Option<Screen> {
+    let screens = Screen::all().ok()?;
+    monitor_id
+        .and_then(|id| screens.iter().find(|s| s.display_info.id == id))
+        .or(screens.first())
+        .copied()
+}
+
+/// 单个锚点的采样稳定度
+#[derive(Clone)]
+struct AnchorStability {
+    draft_index: usize,
+    samples: usize,
+    /// 命中期望值 (文字包含 / 颜色在容差内) 的比例
+    match_rate: f32,
+    /// 文字：众数结果占比；颜色：1 - 归一化标准差
+    consistency: f32,
+    detail: String,
+}
+
+impl AnchorStability {
+    fn is_flaky(&self) -> bool {
+        self.match_rate < 0.9 || self.consistency < 0.8
+    }
+}
+
+enum StabilityMsg {
+    Progress(f32),
+    Done(Vec<AnchorStability>),
+}
+
+struct StabilityRun {
+    scene_index: usize,
+    /// 开始采样时的元素列表 (结果按其序号对应)
+    drafts: Vec<UIElementDraft>,
+    rx: mpsc::Receiver<StabilityMsg>,
+    progress: f32,
+}
+
+fn run_stability_sampling(anchors: Vec<(usize, ElementKind, Rect)>, settings: ToolSettings, duration: Duration, interval: Duration, tx: mpsc::Sender<StabilityMsg>) {
+    // 留出切换到游戏画面的时间
+    thread::sleep(Duration::from_secs(3));
+    let engine = RegionOcr::create(&settings.ocr).ok();
+
+    let mut texts: Vec<Vec<String>> = vec![Vec::new(); anchors.len()];
+    let mut colors: Vec<Vec<[u8; 3]>> = vec![Vec::new(); anchors.len()];
+
+    let start = Instant::now();
+    while start.elapsed() < duration {
+        let tick = Instant::now();
+        if let Some((img, _)) = capture_frame(&settings) {
+            for (slot, (_, kind, rect)) in anchors.iter().enumerate() {
+                match kind {
+                    ElementKind::TextAnchor { .. } => {
+                        if let Some(e) = &engine {
+                            texts[slot].push(e.recognize(&img, *rect).unwrap_or_default());
+                        }
+                    }
+                    ElementKind::ColorAnchor { .. } => {
+                        let (x, y) = (rect.min.x.max(0.0) as u32, rect.min.y.max(0.0) as u32);
+                        if x < img.width() && y < img.height() {
+                            let p = img.get_pixel(x, y);
+                            colors[slot].push([p[0], p[1], p[2]]);
+                        }
+                    }
+                    ElementKind::HsvAnchor { .. } => colors[slot].extend(average_rgb(&img, *rect)),
+                    ElementKind::ImageAnchor { .. } | ElementKind::Button { .. } => {}
+                }
+            }
+        }
+        let _ = tx.send(StabilityMsg::Progress(start.elapsed().as_secs_f32() / duration.as_secs_f32()));
+        thread::sleep(interval.saturating_sub(tick.elapsed()));
+    }
+
+    let mut results = Vec::new();
+    for (slot, (draft_index, kind, rect)) in anchors.iter().enumerate() {
+        match kind {
+            ElementKind::TextAnchor { text } => {
+                let samples = &texts[slot];
+                if samples.is_empty() { continue; }
+                let n = samples.len() as f32;
+                let hits = samples.iter().filter(|s| s.contains(text.as_str())).count() as f32;
+                let mut counts: std::collections::HashMap<&str, usize> = std::collections::HashMap::new();
+                for s in samples { *counts.entry(s.as_str()).or_default() += 1; }
+                let (mode, mode_count) = counts.into_iter().max_by_key(|(_, c)| *c).unwrap_or(("", 0));
+                results.push(AnchorStability {
+                    draft_index: *draft_index,
+                    samples: samples.len(),
+                    match_rate: hits / n,
+                    consistency: mode_count as f32 / n,
+                    detail: format!("众数「{}」", mode),
+                });
+            }
+            ElementKind::ColorAnchor { .. } | ElementKind::HsvAnchor { .. } => {
+                let samples = &colors[slot];
+                if samples.is_empty() { continue; }
+                let n = samples.len() as f32;
+                let hits = samples.iter().filter(|c| color_anchor_hit(kind, *rect, **c)).count() as f32;
+                // 三通道标准差的平均值
+                let mut sigma = 0.0f32;
+                for ch in 0..3 {
+                    let mean = samples.iter().map(|c| c[ch] as f32).sum::<f32>() / n;
+                    let var = samples.iter().map(|c| (c[ch] as f32 - mean).powi(2)).sum::<f32>() / n;
+                    sigma += var.sqrt() / 3.0;
+                }
+                results.push(AnchorStability {
+                    draft_index: *draft_index,
+                    samples: samples.len(),
+                    match_rate: hits / n,
+                    consistency: (1.0 - sigma / 64.0).clamp(0.0, 1.0),
+                    detail: format!("σ={:.1}", sigma),
+                });
+            }
+            ElementKind::ImageAnchor { .. } | ElementKind::Button { .. } => {}
+        }
+    }
+    let _ = tx.send(StabilityMsg::Done(results));
+}
+
+/// 颜色是否在容差内 (三通道差值之和 <= tolerance * 3)
+fn color_matches(c: [u8; 3], expected: [u8; 3], tolerance: u8) -> bool {
+    let diff: i32 = (0..3).map(|i| (c[i] as i32 - expected[i] as i32).abs()).sum();
+    diff <= tolerance as i32 * 3
+}
+
+/// 区域平均色 (超出截图的部分不计)；区域完全在截图外时为 None
+fn average_rgb(img: &image::RgbaImage, rect: Rect) -> Option<[u8; 3]> {
+    let (x1, y1) = (rect.min.x.max(0.0) as u32, rect.min.y.max(0.0) as u32);
+    let (x2, y2) = ((rect.max.x.max(0.0) as u32).min(img.width()), (rect.max.y.max(0.0) as u32).min(img.height()));
+    if x1 >= x2 || y1 >= y2 { return None; }
+    let mut sum = [0u64; 3];
+    for y in y1..y2 {
+        for x in x1..x2 {
+            let p = img.get_pixel(x, y);
+            for ch in 0..3 { sum[ch] += p[ch] as u64; }
+        }
+    }
+    let n = ((x2 - x1) * (y2 - y1)) as u64;
+    Some(sum.map(|s| (s / n) as u8))
+}
+
+/// 颜色锚点容差预览的圆心与像素判断 (区域颜色锚点以区域中心为圆心)；其他元素为 None
+fn tolerance_preview(d: &UIElementDraft) -> Option<(Pos2, PixelMatcher)> {
+    match &d.kind {
+        ElementKind::ColorAnchor { color_hex, tolerance } => {
+            let (expected, tolerance) = (parse_hex_color(color_hex), *tolerance);
+            Some((d.pos_or_rect.min, Box::new(move |c| color_matches(c, expected, tolerance))))
+        }
+        ElementKind::HsvAnchor { .. } => {
+            let anchor = hsv_anchor(&d.kind, d.pos_or_rect)?;
+            Some((d.pos_or_rect.center(), Box::new(move |c| anchor.deviation(c) <= 1.0)))
+        }
+        _ => None,
+    }
+}
+
+/// 区域颜色锚点草稿转为地图中的 HsvAnchor：pos 取区域中心，size 取较长边
+fn hsv_anchor(kind: &ElementKind, rect: Rect) -> Option<schema::HsvAnchor> {
+    let ElementKind::HsvAnchor { color_hex, hue_tol, sat_tol, val_tol } = kind else { return None };
+    let size = rect.width().max(rect.height()).round().max(1.0) as u32;
+    let pos = [rect.min.x as i32 + size as i32 / 2, rect.min.y as i32 + size as i32 / 2];
+    Some(schema::HsvAnchor { pos, val: color_hex.clone(), size, hue_tol: *hue_tol, sat_tol: *sat_tol, val_tol: *val_tol })
+}
+
+/// 颜色锚点的一次取色 (区域颜色锚点为区域平均色) 是否命中
+fn color_anchor_hit(kind: &ElementKind, rect: Rect, c: [u8; 3]) -> bool {
+    match kind {
+        ElementKind::ColorAnchor { color_hex, tolerance } => color_matches(c, parse_hex_color(color_hex), *tolerance),
+        ElementKind::HsvAnchor { .. } => hsv_anchor(kind, rect).is_some_and(|a| a.deviation(c) <= 1.0),
+        _ => false,
+    }
+}
+
+/// 实时测试中单个锚点的结果
+#[derive(Clone)]
+struct AnchorCheck {
+    draft_index: usize,
+    passed: bool,
+    /// 实际识别到的文字 / 取到的颜色
+    detail: String,
+}
+
+/// 在 rect 内滑动模板做灰度归一化互相关，返回最高得分 (0~1)
+fn template_score(img: &image::RgbaImage, rect: Rect, template: &image::GrayImage) -> f32 {
+    let (x, y) = (rect.min.x.max(0.0) as u32, rect.min.y.max(0.0) as u32);
+    if x >= img.width() || y >= img.height() {
+        return 0.0;
+    }
+    let w = (rect.width().max(1.0) as u32).min(img.width() - x);
+    let h = (rect.height().max(1.0) as u32).min(img.height() - y);
+    let region = image::DynamicImage::ImageRgba8(image::imageops::crop_imm(img, x, y, w, h).to_image()).to_luma8();
+    let (tw, th) = template.dimensions();
+    if tw == 0 || th == 0 || tw > w || th > h {
+        return 0.0;
+    }
+    let n = (tw * th) as f32;
+    let t_mean = template.pixels().map(|p| p[0] as f32).sum::<f32>() / n;
+    let t_norm = template.pixels().map(|p| (p[0] as f32 - t_mean).powi(2)).sum::<f32>().sqrt();
+    let mut best = 0.0f32;
+    for oy in 0..=(h - th) {
+        for ox in 0..=(w - tw) {
+            let pixel = |tx: u32, ty: u32| region.get_pixel(ox + tx, oy + ty)[0] as f32;
+            let mut sum = 0.0;
+            for ty in 0..th { for tx in 0..tw { sum += pixel(tx, ty); } }
+            let r_mean = sum / n;
+            let (mut cross, mut r_sq) = (0.0f32, 0.0f32);
+            for ty in 0..th {
+                for tx in 0..tw {
+                    let r = pixel(tx, ty) - r_mean;
+                    cross += r * (template.get_pixel(tx, ty)[0] as f32 - t_mean);
+                    r_sq += r * r;
+                }
+            }
+            let denom = r_sq.sqrt() * t_norm;
+            // 纯色区域与纯色模板：整体亮度一致即视为匹配
+            let score = if denom > 0.0 { cross / denom } else if (r_mean - t_mean).abs() < 8.0 { 1.0 } else { 0.0 };
+            best = best.max(score);
+        }
+    }
+    best
+}
+
+/// 按场景逻辑汇总：AND 需全部命中，OR 任一命中即可
+fn live_verdict(logic: &RecognitionLogic, checks: &[AnchorCheck]) -> bool {
+    match logic {
+        RecognitionLogic::AND => !checks.is_empty() && checks.iter().all(|c| c.passed),
+        RecognitionLogic::OR => checks.iter().any(|c| c.passed),
+    }
+}
+
+/// 实时测试：截一帧，逐个评估锚点；截图失败时返回 None
+fn run_live_test(anchors: Vec<(usize, ElementKind, Rect)>, settings: ToolSettings, tx: mpsc::Sender<Option<Vec<AnchorCheck>>>) {
+    // 留出切换到游戏画面的时间
+    thread::sleep(Duration::from_secs(3));
+    let Some((img, _)) = capture_frame(&settings) else {
+        let _ = tx.send(None);
+        return;
+    };
+    let engine = RegionOcr::create(&settings.ocr).ok();
+    let _ = tx.send(Some(check_anchors(&img, &anchors, engine.as_ref())));
+}
+
+/// 在一张截图上逐个评估锚点
+fn check_anchors(img: &image::RgbaImage, anchors: &[(usize, ElementKind, Rect)], engine: Option<&RegionOcr>) -> Vec<AnchorCheck> {
+    let mut results = Vec::new();
+    for (draft_index, kind, rect) in anchors {
+        let (draft_index, rect) = (*draft_index, *rect);
+        let (passed, detail) = match kind {
+            ElementKind::TextAnchor { text } => match engine {
+                Some(e) => match e.recognize(img, rect) {
+                    Ok(s) => (s.contains(text.as_str()), format!("识别「{}」", s)),
+                    Err(err) => (false, err),
+                },
+                None => (false, "OCR 引擎不可用".into()),
+            },
+            ElementKind::ColorAnchor { color_hex, tolerance } => {
+                let (x, y) = (rect.min.x.max(0.0) as u32, rect.min.y.max(0.0) as u32);
+                if x < img.width() && y < img.height() {
+                    let p = img.get_pixel(x, y);
+                    let c = [p[0], p[1], p[2]];
+                    (color_matches(c, parse_hex_color(color_hex), *tolerance), format!("取色 #{:02X}{:02X}{:02X}", c[0], c[1], c[2]))
+                } else {
+                    (false, "坐标超出截图范围".into())
+                }
+            }
+            ElementKind::HsvAnchor { .. } => match average_rgb(img, rect) {
+                Some(c) => {
+                    let [h, s, v] = schema::rgb_to_hsv(c);
+                    let detail = format!("平均色 #{:02X}{:02X}{:02X} (H {:.0}° S {:.2} V {:.2})", c[0], c[1], c[2], h, s, v);
+                    (color_anchor_hit(kind, rect, c), detail)
+                }
+                None => (false, "采样区域超出截图范围".into()),
+            },
+            ElementKind::ImageAnchor { file, threshold } => match image::open(file) {
+                Ok(template) => {
+                    let score = template_score(img, rect, &template.to_luma8());
+                    (score >= *threshold, format!("匹配得分 {:.2} (阈值 {:.2})", score, threshold))
+                }
+                Err(e) => (false, format!("无法读取模板 {}: {}", file, e)),
+            },
+            ElementKind::Button { .. } => continue,
+        };
+        results.push(AnchorCheck { draft_index, passed, detail });
+    }
+    results
+}
+
+/// 离线回归用的场景锚点 (场景 ID, 逻辑, 锚点)
+type SceneAnchors = (String, RecognitionLogic, Vec<(usize, ElementKind, Rect)>);
+
+/// 离线回归中一张截图的结果
+struct RegressionRow {
+    file: String,
+    /// 期望场景 ID (文件名，`@` 之后的部分忽略，便于同一场景放多张)
+    expected: String,
+    /// 按锚点判定命中的场景 ID
+    matched: Vec<String>,
+    /// 截图无法读取时的错误
+    error: Option<String>,
+}
+
+impl RegressionRow {
+    fn false_negative(&self) -> bool {
+        !self.matched.contains(&self.expected)
+    }
+
+    fn false_positives(&self) -> Vec<String> {
+        self.matched.iter().filter(|id| **id != self.expected).cloned().collect()
+    }
+
+    fn passed(&self) -> bool {
+        self.error.is_none() && !self.false_negative() && self.false_positives().is_empty()
+    }
+}
+
+enum RegressionMsg {
+    Progress(f32),
+    Done(Vec<RegressionRow>),
+}
+
+struct RegressionRun {
+    rx: mpsc::Receiver<RegressionMsg>,
+    progress: f32,
+}
+
+/// 会话回放 (主程序 --session 录制的 sessions/<时间>/)
+struct SessionReplay {
+    dir: std::path::PathBuf,
+    events: Vec<SessionEvent>,
+    /// 当前时间 (ms)
+    t_ms: u64,
+    /// 只看该类事件 (截图始终显示在上方，不进列表)
+    filter: Option<&'static str>,
+    /// 当前显示的截图文件与纹理
+    frame: Option<(String, egui::TextureHandle)>,
+}
+
+impl SessionReplay {
+    fn end_ms(&self) -> u64 {
+        self.events.last().map(|e| e.t_ms).unwrap_or(0)
+    }
+
+    /// 列表中显示的事件 (下标)
+    fn visible(&self) -> Vec<usize> {
+        (0..self.events.len())
+            .filter(|&i| {
+                let label = self.events[i].kind.label();
+                label != "截图" && self.filter.is_none_or(|f| f == label)
+            })
+            .collect()
+    }
+
+    /// 当前时间之前最近的一帧截图
+    fn frame_at(&self, t_ms: u64) -> Option<&str> {
+        self.events.iter().rev().filter(|e| e.t_ms <= t_ms).find_map(|e| match &e.kind {
+            SessionEventKind::Frame { file, .. } => Some(file.as_str()),
+            _ => None,
+        })
+    }
+
+    fn sync_frame(&mut self, ctx: &egui::Context) {
+        let Some(file) = self.frame_at(self.t_ms).map(String::from) else {
+            self.frame = None;
+            return;
+        };
+        if self.frame.as_ref().is_some_and(|(f, _)| *f == file) {
+            return;
+        }
+        // 截图被清理或写了一半时保留上一帧
+        let Ok(img) = image::open(self.dir.join(&file)) else { return };
+        let img = img.to_rgba8();
+        let color = egui::ColorImage::from_rgba_unmultiplied([img.width() as usize, img.height() as usize], img.as_flat_samples().as_slice());
+        self.frame = Some((file, ctx.load_texture("session_frame", color, egui::TextureOptions::LINEAR)));
+    }
+}
+
+fn format_ms(t_ms: u64) -> String {
+    let t = t_ms / 1000;
+    format!("{:02}:{:02}:{:02}.{:03}", t / 3600, t / 60 % 60, t % 60, t_ms % 1000)
+}
+
+/// 离线回归：每张截图都用所有场景的锚点评估一遍
+fn run_regression(scenes: Vec<SceneAnchors>, files: Vec<std::path::PathBuf>, ocr: String, tx: mpsc::Sender<RegressionMsg>) {
+    let engine = RegionOcr::create(&ocr).ok();
+    let mut rows = Vec::new();
+    for (i, path) in files.iter().enumerate() {
+        let file = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+        let stem = path.file_stem().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+        let expected = stem.split('@').next().unwrap_or_default().to_string();
+        let (matched, error) = match image::open(path) {
+            Ok(img) => {
+                let img = img.to_rgba8();
+                let matched = scenes.iter()
+                    .filter(|(_, logic, anchors)| live_verdict(logic, &check_anchors(&img, anchors, engine.as_ref())))
+                    .map(|(id, _, _)| id.clone())
+                    .collect();
+                (matched, None)
             }
+            Err(e) => (Vec::new(), Some(format!("无法读取: {}", e))),
+        };
+        rows.push(RegressionRow { file, expected, matched, error });
+        let _ = tx.send(RegressionMsg::Progress((i + 1) as f32 / files.len() as f32));
+    }
+    let _ = tx.send(RegressionMsg::Done(rows));
+}
+
+fn parse_hex_color(hex: &str) -> [u8; 3] {
+    let h = hex.trim_start_matches('#');
+    let channel = |i: usize| h.get(i..i + 2).and_then(|c| u8::from_str_radix(c, 16).ok()).unwrap_or(0);
+    [channel(0), channel(2), channel(4)]
+}
+
+// ==========================================
+// 2.7 场景图导出 (DOT / SVG)
+// ==========================================
+// DOT 交给 Graphviz 自动布局；SVG 按可视化视图中的节点位置直接输出，便于放进文档
+
+fn dot_escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn xml_escape(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+/// Graphviz DOT：节点标签为 名称 + ID (+ handler)，连线标注点击坐标与等待时间；目标不存在的跳转画成红色虚线
+fn scenes_to_dot(scenes: &[Scene]) -> String {
+    let mut out = String::from("digraph nav {\n    rankdir=LR;\n    node [shape=box, style=filled, fillcolor=\"#C8C8DC\", fontname=\"Microsoft YaHei\"];\n    edge [fontname=\"Microsoft YaHei\", fontsize=10];\n\n");
+    for scene in scenes {
+        let mut label = format!("{}\\n{}", dot_escape(&scene.name), dot_escape(&scene.id));
+        let mut attrs = String::new();
+        if let Some(handler) = &scene.handler {
+            label.push_str(&format!("\\nhandler: {}", dot_escape(handler)));
+            attrs.push_str(", fillcolor=\"#96C896\"");
         }
-        
-        // 处理平移（右键拖拽）
-        if resp.secondary_clicked() {
-            if let Some(_mouse_pos) = resp.interact_pointer_pos() {
-                self.viz_pan += resp.drag_delta();
-            }
+        out.push_str(&format!("    \"{}\" [label=\"{}\"{}];\n", dot_escape(&scene.id), label, attrs));
+    }
+    out.push('\n');
+    for scene in scenes {
+        for d in &scene.drafts {
+            let ElementKind::Button { target, post_delay, extra } = &d.kind else { continue };
+            let style = if scenes.iter().any(|s| s.id == *target) { "" } else { ", style=dashed, color=red" };
+            out.push_str(&format!(
+                "    \"{}\" -> \"{}\" [label=\"{} {}ms\"{}];\n",
+                dot_escape(&scene.id), dot_escape(target), dot_escape(&extra.describe(d.pos_or_rect.center())), post_delay, style
+            ));
         }
-        
-        // 处理缩放（滚轮）
-        let scroll_delta = ui.input(|i| i.scroll_delta);
-        let zoom_factor = 1.0 + scroll_delta.y * 0.001;
-        self.viz_zoom = (self.viz_zoom * zoom_factor).clamp(0.1, 5.0);
-        
-        // 显示控制提示
-        ui.label("🖱️ 左键拖拽场景 | 右键拖拽平移 | 滚轮缩放");
     }
-    
-    fn draw_screenshot_panel(&mut self, ui: &mut egui::Ui) {
-        let (resp, painter) = ui.allocate_painter(ui.available_size(), Sense::drag());
-        if let Some(tex) = &self.texture {
-            let painter_size = resp.rect.size();
-            let scale = (painter_size.x / self.img_size.x).min(painter_size.y / self.img_size.y);
-            let draw_size = self.img_size * scale;
-            let draw_rect = Rect::from_min_size(resp.rect.min, draw_size);
-            painter.image(tex.id(), draw_rect, Rect::from_min_max(Pos2::ZERO, Pos2::new(1.0, 1.0)), Color32::WHITE);
+    out.push_str("}\n");
+    out
+}
 
-            let to_screen = |p: Pos2| Pos2::new(
-                draw_rect.min.x + p.x * scale,
-                draw_rect.min.y + p.y * scale
-            );
-            let from_screen = |p: Pos2| Pos2::new(
-                (p.x - draw_rect.min.x) / scale,
-                (p.y - draw_rect.min.y) / scale
-            );
+/// 按可视化布局输出 SVG，配色与画布一致 (有 handler 的场景为绿色)
+fn scenes_to_svg(scenes: &[Scene]) -> String {
+    const MARGIN: f32 = 40.0;
+    let bounds = scenes.iter()
+        .map(|s| Rect::from_min_size(s.viz_pos, s.viz_size))
+        .reduce(|a, b| a.union(b))
+        .unwrap_or(Rect::from_min_size(Pos2::ZERO, Vec2::splat(100.0)));
+    let origin = bounds.min.to_vec2() - Vec2::splat(MARGIN);
+    let size = bounds.size() + Vec2::splat(MARGIN * 2.0);
+    let node = |s: &Scene| Rect::from_min_size(s.viz_pos - origin, s.viz_size);
 
-            for d in &self.current_scene().drafts {
-                let color = match d.kind {
-                    ElementKind::TextAnchor{..} => Color32::GREEN,
-                    ElementKind::ColorAnchor{..} => Color32::from_rgb(255, 165, 0),
-                    ElementKind::Button{..} => Color32::BLUE,
-                };
-                painter.rect_stroke(Rect::from_min_max(to_screen(d.pos_or_rect.min), to_screen(d.pos_or_rect.max)), 2.0, Stroke::new(2.0, color));
-            }
+    let mut out = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{:.0}\" height=\"{:.0}\" font-family=\"Microsoft YaHei, sans-serif\">\n",
+        size.x, size.y
+    );
+    out.push_str("  <defs><marker id=\"arrow\" viewBox=\"0 0 10 10\" refX=\"10\" refY=\"5\" markerWidth=\"8\" markerHeight=\"8\" orient=\"auto\"><path d=\"M0,0 L10,5 L0,10 z\" fill=\"#6464C8\"/></marker></defs>\n");
+    out.push_str(&format!("  <rect width=\"{:.0}\" height=\"{:.0}\" fill=\"white\"/>\n", size.x, size.y));
 
-            if resp.drag_started() {
-                if let Some(p) = resp.interact_pointer_pos() { self.start_pos = Some(from_screen(p)); }
-            }
-            if let (Some(start), Some(curr_raw)) = (self.start_pos, resp.interact_pointer_pos()) {
-                let curr = from_screen(curr_raw);
-                let rect = if self.is_color_picker_mode { Rect::from_min_max(curr, curr + Vec2::splat(1.0)) } else { Rect::from_two_pos(start, curr) };
-                painter.rect_stroke(Rect::from_min_max(to_screen(rect.min), to_screen(rect.max)), 0.0, Stroke::new(1.5, Color32::RED));
-                if resp.drag_released() { 
-                    self.current_rect = Some(rect); 
-                    self.start_pos = None; 
-                    self.ocr_test_result.clear(); 
-                }
-            }
-        } else {
-            ui.centered_and_justified(|ui| ui.label("点击左侧『3秒延时截图』开始工作"));
+    // 连线：从起点中心画到目标矩形边缘，箭头落在边框上
+    for scene in scenes {
+        for d in &scene.drafts {
+            let ElementKind::Button { target, .. } = &d.kind else { continue };
+            let Some(to) = scenes.iter().find(|s| s.id == *target) else { continue };
+            let (a, to_rect) = (node(scene).center(), node(to));
+            let dir = to_rect.center() - a;
+            let half = to_rect.size() / 2.0;
+            let t = (half.x / dir.x.abs().max(f32::EPSILON)).min(half.y / dir.y.abs().max(f32::EPSILON)).min(1.0);
+            let b = to_rect.center() - dir * t;
+            out.push_str(&format!(
+                "  <line x1=\"{:.1}\" y1=\"{:.1}\" x2=\"{:.1}\" y2=\"{:.1}\" stroke=\"#6464C8\" stroke-width=\"2\" marker-end=\"url(#arrow)\"/>\n",
+                a.x, a.y, b.x, b.y
+            ));
         }
     }
-    
-    fn draw_grid(&self, painter: &egui::Painter, rect: Rect) {
-        let grid_size = 20.0 * self.viz_zoom;
-        let start_x = (self.viz_pan.x % grid_size) + rect.min.x;
-        let start_y = (self.viz_pan.y % grid_size) + rect.min.y;
-        
-        for x in (start_x as i32..rect.right() as i32).step_by(grid_size as usize) {
-            painter.line_segment(
-                [Pos2::new(x as f32, rect.top()), Pos2::new(x as f32, rect.bottom())],
-                Stroke::new(0.5, Color32::from_rgb(220, 220, 220))
-            );
+    for scene in scenes {
+        let r = node(scene);
+        let fill = if scene.handler.is_some() { "#96C896" } else { "#C8C8DC" };
+        out.push_str(&format!(
+            "  <rect x=\"{:.1}\" y=\"{:.1}\" width=\"{:.1}\" height=\"{:.1}\" fill=\"{}\" stroke=\"black\" stroke-width=\"2\"/>\n",
+            r.min.x, r.min.y, r.width(), r.height(), fill
+        ));
+        out.push_str(&format!("  <text x=\"{:.1}\" y=\"{:.1}\" font-size=\"14\">{}</text>\n", r.min.x + 5.0, r.min.y + 18.0, xml_escape(&scene.name)));
+        out.push_str(&format!("  <text x=\"{:.1}\" y=\"{:.1}\" font-size=\"10\" fill=\"#505050\">ID: {}</text>\n", r.min.x + 5.0, r.min.y + 32.0, xml_escape(&scene.id)));
+        if let Some(handler) = &scene.handler {
+            out.push_str(&format!("  <text x=\"{:.1}\" y=\"{:.1}\" font-size=\"10\" fill=\"#006400\">Handler: {}</text>\n", r.min.x + 5.0, r.min.y + 46.0, xml_escape(handler)));
         }
-        
-        for y in (start_y as i32..rect.bottom() as i32).step_by(grid_size as usize) {
-            painter.line_segment(
-                [Pos2::new(rect.left(), y as f32), Pos2::new(rect.right(), y as f32)],
-                Stroke::new(0.5, Color32::from_rgb(220, 220, 220))
-            );
+    }
+    out.push_str("</svg>\n");
+    out
+}
+
+// ==========================================
+// 2.9 保存前差异对比
+// ==========================================
+/// 未改动的行超过这个数时折叠，只保留前后各 DIFF_CONTEXT 行
+const DIFF_CONTEXT: usize = 3;
+
+enum DiffLine {
+    Same(String),
+    Removed(String),
+    Added(String),
+    /// 折叠掉的未改动行数
+    Skipped(usize),
+}
+
+struct SaveReview {
+    path: std::path::PathBuf,
+    externally_modified: bool,
+    added: Vec<String>,
+    removed: Vec<String>,
+    changed: Vec<String>,
+    lines: Vec<DiffLine>,
+}
+
+impl SaveReview {
+    fn new(path: std::path::PathBuf, disk: &str, new: &str, externally_modified: bool) -> Self {
+        let (added, removed, changed) = scene_changes(disk, new);
+        Self { path, externally_modified, added, removed, changed, lines: collapse_context(line_diff(disk, new)) }
+    }
+}
+
+/// 逐行差异：去掉首尾相同的行后做最长公共子序列
+fn line_diff(old: &str, new: &str) -> Vec<DiffLine> {
+    let (a, b): (Vec<&str>, Vec<&str>) = (old.lines().collect(), new.lines().collect());
+    let prefix = a.iter().zip(&b).take_while(|(x, y)| x == y).count();
+    let suffix = a[prefix..].iter().rev().zip(b[prefix..].iter().rev()).take_while(|(x, y)| x == y).count();
+    let (am, bm) = (&a[prefix..a.len() - suffix], &b[prefix..b.len() - suffix]);
+
+    // lcs[i][j] = am[i..] 与 bm[j..] 的最长公共子序列长度
+    let mut lcs = vec![vec![0u32; bm.len() + 1]; am.len() + 1];
+    for i in (0..am.len()).rev() {
+        for j in (0..bm.len()).rev() {
+            lcs[i][j] = if am[i] == bm[j] { lcs[i + 1][j + 1] + 1 } else { lcs[i + 1][j].max(lcs[i][j + 1]) };
         }
     }
-    
-    fn draw_scene_connections(&self, painter: &egui::Painter, transform: &dyn Fn(Pos2) -> Pos2) {
-        for scene in self.scenes.iter() {
-            let from_rect = Rect::from_min_size(transform(scene.viz_pos), scene.viz_size * self.viz_zoom);
-            let from_center = from_rect.center();
-            
-            for draft in &scene.drafts {
-                if let ElementKind::Button { target, .. } = &draft.kind {
-                    if let Some(target_idx) = self.scenes.iter().position(|s| s.id == *target) {
-                        let target_scene = &self.scenes[target_idx];
-                        let to_rect = Rect::from_min_size(transform(target_scene.viz_pos), target_scene.viz_size * self.viz_zoom);
-                        let to_center = to_rect.center();
-                        
-                        // 绘制连接线
-                        painter.line_segment(
-                            [from_center, to_center],
-                            Stroke::new(2.0, Color32::from_rgb(100, 100, 200))
-                        );
-                        
-                        // 绘制箭头
-                        let direction = (to_center - from_center).normalized();
-                        let arrow_size = 10.0 * self.viz_zoom;
-                        let arrow_tip = to_center - direction * (target_scene.viz_size.x * self.viz_zoom / 2.0 + 5.0);
-                        
-                        let perp = Vec2::new(-direction.y, direction.x) * (arrow_size * 0.5);
-                        painter.add(egui::Shape::convex_polygon(
-                            vec![
-                                arrow_tip,
-                                arrow_tip - direction * arrow_size + perp,
-                                arrow_tip - direction * arrow_size - perp
-                            ],
-                            Color32::from_rgb(100, 100, 200),
-                            Stroke::new(1.0, Color32::from_rgb(100, 100, 200))
-                        ));
-                    }
-                }
-            }
+    let mut out: Vec<DiffLine> = a[..prefix].iter().map(|s| DiffLine::Same(s.to_string())).collect();
+    let (mut i, mut j) = (0, 0);
+    while i < am.len() || j < bm.len() {
+        if i < am.len() && j < bm.len() && am[i] == bm[j] {
+            out.push(DiffLine::Same(am[i].to_string()));
+            i += 1;
+            j += 1;
+        } else if i < am.len() && (j == bm.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            out.push(DiffLine::Removed(am[i].to_string()));
+            i += 1;
+        } else {
+            out.push(DiffLine::Added(bm[j].to_string()));
+            j += 1;
         }
     }
-} // 🔥 MapBuilderTool 实现块结束
+    out.extend(a[a.len() - suffix..].iter().map(|s| DiffLine::Same(s.to_string())));
+    out
+}
+
+/// 折叠大段未改动的行
+fn collapse_context(lines: Vec<DiffLine>) -> Vec<DiffLine> {
+    let mut out = Vec::new();
+    let mut run: Vec<DiffLine> = Vec::new();
+    let flush = |run: &mut Vec<DiffLine>, out: &mut Vec<DiffLine>, at_start: bool, at_end: bool| {
+        let keep_head = if at_start { 0 } else { DIFF_CONTEXT };
+        let keep_tail = if at_end { 0 } else { DIFF_CONTEXT };
+        if run.len() > keep_head + keep_tail + 1 {
+            let skipped = run.len() - keep_head - keep_tail;
+            let tail = run.split_off(run.len() - keep_tail);
+            run.truncate(keep_head);
+            out.append(run);
+            out.push(DiffLine::Skipped(skipped));
+            out.extend(tail);
+        } else {
+            out.append(run);
+        }
+    };
+    for line in lines {
+        if matches!(line, DiffLine::Same(_)) {
+            run.push(line);
+        } else {
+            let at_start = out.is_empty();
+            flush(&mut run, &mut out, at_start, false);
+            out.push(line);
+        }
+    }
+    let at_start = out.is_empty();
+    flush(&mut run, &mut out, at_start, true);
+    out
+}
+
+/// 场景级变化 (按 id 对比)：(新增, 删除, 修改)
+fn scene_changes(old: &str, new: &str) -> (Vec<String>, Vec<String>, Vec<String>) {
+    let scenes = |s: &str| -> Vec<(String, toml::Value)> {
+        let root: Option<toml::Value> = toml::from_str(s).ok();
+        root.and_then(|r| r.get("scenes").and_then(|v| v.as_array()).cloned()).unwrap_or_default().into_iter()
+            .map(|v| (v.get("id").and_then(|i| i.as_str()).unwrap_or_default().to_string(), v))
+            .collect()
+    };
+    let (old, new) = (scenes(old), scenes(new));
+    let find = |list: &[(String, toml::Value)], id: &str| list.iter().find(|(i, _)| i == id).map(|(_, v)| v.clone());
+    let added = new.iter().filter(|(id, _)| find(&old, id).is_none()).map(|(id, _)| id.clone()).collect();
+    let removed = old.iter().filter(|(id, _)| find(&new, id).is_none()).map(|(id, _)| id.clone()).collect();
+    let changed = new.iter().filter(|(id, v)| find(&old, id).is_some_and(|o| o != *v)).map(|(id, _)| id.clone()).collect();
+    (added, removed, changed)
+}
 
 // ==========================================
 // 3. UI 实现
 // ==========================================
+/// 中文字体文件与 .ttc 中的字体序号
+#[cfg(feature = "windows")]
+fn cjk_font() -> Option<(Vec<u8>, u32)> {
+    fs::read("C:\\Windows\\Fonts\\msyh.ttc").ok().map(|data| (data, 0))
+}
+
+/// Linux：经 fontconfig 查找支持简体中文的字体 (如 Noto Sans CJK / 文泉驿)
+#[cfg(not(feature = "windows"))]
+fn cjk_font() -> Option<(Vec<u8>, u32)> {
+    let output = std::process::Command::new("fc-match").args(["-f", "%{file}\n%{index}", ":lang=zh-cn"]).output().ok()?;
+    let output = String::from_utf8_lossy(&output.stdout);
+    let mut lines = output.lines();
+    let path = lines.next()?.trim();
+    let index = lines.next().and_then(|i| i.trim().parse().ok()).unwrap_or(0);
+    fs::read(path).ok().map(|data| (data, index))
+}
+
 fn setup_custom_fonts(ctx: &egui::Context) {
     let mut fonts = egui::FontDefinitions::default();
-    if let Ok(data) = fs::read("C:\\Windows\\Fonts\\msyh.ttc") {
-        fonts.font_data.insert("msyh".to_owned(), egui::FontData::from_owned(data));
+    if let Some((data, index)) = cjk_font() {
+        fonts.font_data.insert("msyh".to_owned(), egui::FontData { index, ..egui::FontData::from_owned(data) });
         fonts.families.get_mut(&egui::FontFamily::Proportional).unwrap().insert(0, "msyh".to_owned());
         fonts.families.get_mut(&egui::FontFamily::Monospace).unwrap().insert(0, "msyh".to_owned());
     }
@@ -766,6 +4171,33 @@ fn setup_custom_fonts(ctx: &egui::Context) {
 
 impl eframe::App for MapBuilderTool {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        if self.stability_run.is_some() {
+            self.poll_stability_sampling();
+            ctx.request_repaint_after(Duration::from_millis(200));
+        }
+        self.expire_stability_results();
+        if self.live_test_rx.is_some() {
+            self.poll_live_test();
+            ctx.request_repaint_after(Duration::from_millis(200));
+        }
+        if self.regression_run.is_some() {
+            self.poll_regression();
+            ctx.request_repaint_after(Duration::from_millis(200));
+        }
+
+        // 文本框有焦点时 Ctrl+Z 交给文本框自己撤销
+        if !ctx.wants_keyboard_input() {
+            let (undo, redo) = ctx.input_mut(|i| {
+                let redo = i.consume_key(egui::Modifiers::COMMAND, egui::Key::Y)
+                    || i.consume_key(egui::Modifiers::COMMAND | egui::Modifiers::SHIFT, egui::Key::Z);
+                (i.consume_key(egui::Modifiers::COMMAND, egui::Key::Z), redo)
+            });
+            if undo { self.undo(); }
+            if redo { self.redo(); }
+        }
+        self.handle_shortcuts(ctx);
+        self.poll_hotkey_captures(ctx);
+
         if let Some(start_time) = self.capture_timer {
             if start_time.elapsed().as_secs_f32() >= 3.0 {
                 self.capture_immediate(ctx);
@@ -776,17 +4208,42 @@ impl eframe::App for MapBuilderTool {
             }
         }
 
+        self.show_scene_shot(ctx);
+
         egui::SidePanel::left("side").min_width(400.0).show(ctx, |ui| {
             ui.heading("🚀 MINKE UI 建模器 (OCR测试)");
             ui.label(RichText::new(&self.status_msg).color(Color32::from_rgb(0, 255, 128))); 
+            ui.horizontal(|ui| {
+                if ui.add_enabled(!self.history.undo.is_empty(), egui::Button::new("↶ 撤销")).on_hover_text("Ctrl+Z").clicked() {
+                    self.undo();
+                }
+                if ui.add_enabled(!self.history.redo.is_empty(), egui::Button::new("↷ 重做")).on_hover_text("Ctrl+Y").clicked() {
+                    self.redo();
+                }
+            });
             ui.add_space(5.0);
             
             ui.group(|ui| {
+                self.draw_window_selector(ui);
+                if self.settings.window_title.is_none() {
+                    self.draw_monitor_selector(ui);
+                }
+                self.draw_ocr_selector(ui);
                 if self.capture_timer.is_some() {
                     let remaining = 3.0 - self.capture_timer.unwrap().elapsed().as_secs_f32();
                     ui.add(egui::ProgressBar::new(remaining / 3.0).text(format!("倒计时：{:.1}s", remaining)));
                 } else {
-                    if ui.button("📸 3秒延时截图").clicked() { self.capture_timer = Some(Instant::now()); }
+                    ui.horizontal(|ui| {
+                        if ui.button("📸 3秒延时截图").on_hover_text("S").clicked() { self.capture_timer = Some(Instant::now()); }
+                        if ui.button("📂 打开图片").clicked() { self.open_image(ctx); }
+                    });
+                }
+                self.draw_capture_queue(ui);
+                if self.raw_image.is_some() && self.current_scene().shot != self.shown_shot {
+                    ui.horizontal(|ui| {
+                        ui.label(RichText::new("⚠️ 显示的截图不属于当前场景").color(Color32::from_rgb(255, 80, 80)));
+                        if ui.button("📌 设为本场景截图").clicked() { self.associate_shown_shot(); }
+                    });
                 }
             });
 
@@ -794,11 +4251,19 @@ impl eframe::App for MapBuilderTool {
             ui.separator();
             ui.horizontal(|ui| {
                 ui.label("视图模式:");
-                ui.radio_value(&mut self.show_visualization, false, "截图编辑");
-                ui.radio_value(&mut self.show_visualization, true, "场景可视化");
+                ui.radio_value(&mut self.view, ViewMode::Edit, "截图编辑");
+                ui.radio_value(&mut self.view, ViewMode::Visualize, "场景可视化");
+                ui.radio_value(&mut self.view, ViewMode::QuickCapture, "快速截取");
             });
+            if self.view == ViewMode::Visualize && ui.button("🗺️ 导出场景图 (SVG / DOT)").on_hover_text("SVG 按当前布局输出；DOT 交给 Graphviz 自动布局").clicked() {
+                self.export_graph();
+            }
 
-            if !self.show_visualization {
+            if self.view == ViewMode::QuickCapture {
+                self.draw_quick_capture(ui);
+            }
+
+            if self.view == ViewMode::Edit {
                 // --- 场景管理 --- 
                 ui.separator();
                 ui.heading("🎬 场景管理");
@@ -811,7 +4276,7 @@ impl eframe::App for MapBuilderTool {
                 egui::ScrollArea::vertical().id_source("scene_list").max_height(150.0).show(ui, |ui| {
                     for (i, scene) in self.scenes.iter().enumerate() {
                         let is_active = i == self.current_scene_index;
-                        let mut button_text = format!("{}. {}", i + 1, scene.name);
+                        let mut button_text = format!("{}. {}{}", i + 1, scene.name, if scene.shot.is_some() { " 📷" } else { "" });
                         if scene.handler.is_some() {
                             button_text.push_str(&format!(" (handler: {})", scene.handler.as_ref().unwrap()));
                         }
@@ -837,7 +4302,14 @@ impl eframe::App for MapBuilderTool {
                         ui.radio_value(&mut current_scene.logic, RecognitionLogic::AND, "AND"); 
                         ui.radio_value(&mut current_scene.logic, RecognitionLogic::OR, "OR"); 
                     });
-                    ui.horizontal(|ui| { ui.label("Handler:"); ui.text_edit_singleline(current_scene.handler.get_or_insert(String::new())); });
+                    ui.horizontal(|ui| {
+                        ui.label("Handler:");
+                        // 只在真正输入时写回，避免选中场景就把 None 变成 Some("")
+                        let mut handler = current_scene.handler.clone().unwrap_or_default();
+                        if ui.text_edit_singleline(&mut handler).changed() {
+                            current_scene.handler = (!handler.is_empty()).then_some(handler);
+                        }
+                    });
                 }
 
                 ui.separator();
@@ -846,92 +4318,273 @@ impl eframe::App for MapBuilderTool {
                 if let Some(rect) = self.current_rect {
                     ui.group(|ui| {
                         ui.label(RichText::new("已选中目标：").color(Color32::from_rgb(0, 255, 255)).strong());
+                        // 坐标可直接粘贴到策略 JSON / TDConfig
+                        ui.horizontal(|ui| {
+                            if self.is_color_picker_mode {
+                                let pos = format!("[{}, {}]", rect.min.x as i32, rect.min.y as i32);
+                                ui.monospace(&pos);
+                                if ui.small_button("📋 复制坐标").clicked() {
+                                    ui.output_mut(|o| o.copied_text = pos.clone());
+                                    self.status_msg = format!("已复制 {}", pos);
+                                }
+                            } else {
+                                ui.monospace(format!("{} {}×{}", rect_text(rect), rect.width() as i32, rect.height() as i32));
+                                if ui.small_button("📋 复制 rect").on_hover_text("复制为 TOML 的 rect = [x1, y1, x2, y2]").clicked() {
+                                    ui.output_mut(|o| o.copied_text = rect_text(rect));
+                                    self.status_msg = format!("已复制 {}", rect_text(rect));
+                                }
+                                if ui.small_button("📋 复制中心坐标").on_hover_text("复制选区中心 [x, y] (与跳转的 coords 相同)").clicked() {
+                                    ui.output_mut(|o| o.copied_text = center_text(rect));
+                                    self.status_msg = format!("已复制 {}", center_text(rect));
+                                }
+                            }
+                        });
                         
                         if self.is_color_picker_mode {
                             let color = self.pick_color(rect.min);
                             ui.label(format!("HEX: {}", color));
-                            if ui.button("📌 添加颜色锚点").clicked() {
-                                let current_scene = self.current_scene_mut();
-                                current_scene.drafts.push(UIElementDraft { pos_or_rect: rect, kind: ElementKind::ColorAnchor { color_hex: color, tolerance: 15 } });
-                                self.current_rect = None;
+                            if ui.button("📌 添加颜色锚点").on_hover_text("C").clicked() {
+                                self.add_color_anchor(rect);
+                            }
+                            if ui.button("🎨 添加区域颜色锚点").on_hover_text("H：取选区平均色，按 HSV 分量容差比较，不怕发光动画与伽马变化").clicked() {
+                                self.add_hsv_anchor(rect);
                             }
                         } else {
                             ui.horizontal(|ui| {
-                                if ui.button("⚓ 添加 Text 锚点").clicked() {
-                                    let val = if self.ocr_test_result.is_empty() || self.ocr_test_result.contains("...") { "Text".to_string() } else { self.ocr_test_result.clone() };
-                                    let current_scene = self.current_scene_mut();
-                                    current_scene.drafts.push(UIElementDraft { pos_or_rect: rect, kind: ElementKind::TextAnchor { text: val } });
-                                    self.current_rect = None;
+                                if ui.button("⚓ 添加 Text 锚点").on_hover_text("A").clicked() {
+                                    self.add_text_anchor(rect);
                                 }
                                 if ui.button("🔍 区域 OCR 测试").clicked() {
                                     self.perform_ocr(rect);
                                 }
                             });
+                            if ui.button("🖼️ 添加图像锚点").on_hover_text("裁剪选区保存为模板 PNG，运行时在该区域内做模板匹配 (适合艺术字与图标)").clicked() {
+                                self.add_image_anchor(rect);
+                            }
                             
                             if !self.ocr_test_result.is_empty() {
                                 ui.label(RichText::new(format!("识别结果: [{}]", self.ocr_test_result)).color(Color32::BLACK));
                             }
 
-                            if ui.button("🖱️ 添加 Button 跳转").clicked() {
-                                let current_scene = self.current_scene_mut();
-                                current_scene.drafts.push(UIElementDraft { pos_or_rect: rect, kind: ElementKind::Button { target: "next".into(), post_delay: 500 } });
-                                self.current_rect = None;
+                            ui.horizontal(|ui| {
+                                ui.add(egui::DragValue::new(&mut self.digit_export_value).clamp_range(0..=9).prefix("数字:"));
+                                if ui.button("🔢 导出为数字模板").clicked() {
+                                    self.export_digit_template(rect);
+                                }
+                            });
+
+                            if ui.button("🖱️ 添加 Button 跳转").on_hover_text("B").clicked() {
+                                self.add_button(rect);
                             }
                         }
                     });
                 }
 
+                // --- 实时锚点测试 ---
+                ui.separator();
+                ui.horizontal(|ui| {
+                    if self.live_test_rx.is_some() {
+                        ui.spinner();
+                        ui.label("等待截图...");
+                    } else if ui.button("🎯 实时测试当前场景").on_hover_text("3 秒后截取当前画面，逐个评估锚点").clicked() {
+                        self.start_live_test();
+                    }
+                    if let Some((_, results)) = self.live_test_results.as_ref().filter(|(idx, _)| *idx == self.current_scene_index) {
+                        let verdict = live_verdict(&self.current_scene().logic, results);
+                        let (text, color) = if verdict { ("场景命中 ✔", Color32::from_rgb(0, 200, 100)) } else { ("场景未命中 ✘", Color32::from_rgb(255, 80, 80)) };
+                        ui.label(RichText::new(text).color(color));
+                    }
+                });
+
+                // --- 离线回归 ---
+                ui.horizontal(|ui| {
+                    if let Some(run) = &self.regression_run {
+                        ui.add(egui::ProgressBar::new(run.progress).show_percentage().desired_width(160.0));
+                    } else if ui.button("🧪 离线回归测试").on_hover_text("选择已标注的截图目录 (文件名 = 期望场景 ID)，用所有场景的锚点逐张评估").clicked() {
+                        self.start_regression();
+                    }
+                    if ui.button("🎞️ 会话回放").on_hover_text("打开主程序 --session 录制的会话目录，拖动时间轴查看截图与事件").clicked() {
+                        self.open_session_replay();
+                    }
+                });
+
+                // --- 批量重新 OCR ---
+                ui.horizontal(|ui| {
+                    if ui.add_enabled(self.raw_image.is_some(), egui::Button::new("🔁 批量重新 OCR"))
+                        .on_hover_text("用当前截图重新识别文字锚点，逐条确认是否采用新结果").clicked() {
+                        self.batch_reocr();
+                    }
+                    ui.checkbox(&mut self.reocr_all_scenes, "全部场景");
+                });
+
+                // --- 自动建议锚点 ---
+                ui.horizontal(|ui| {
+                    if ui.add_enabled(self.raw_image.is_some(), egui::Button::new("💡 自动建议锚点"))
+                        .on_hover_text("整图 OCR，挑出对比度高、不含数字的文字行作为候选").clicked() {
+                        self.auto_suggest_anchors();
+                    }
+                    if !self.anchor_suggestions.is_empty() {
+                        if ui.button("全部采用").clicked() {
+                            while !self.anchor_suggestions.is_empty() { self.accept_suggestion(0); }
+                        }
+                        if ui.button("清除").clicked() { self.anchor_suggestions.clear(); }
+                    }
+                });
+                let mut accept = None;
+                for (i, s) in self.anchor_suggestions.iter().enumerate() {
+                    ui.horizontal(|ui| {
+                        if ui.small_button("✔ 采用").clicked() { accept = Some(i); }
+                        ui.label(RichText::new(&s.text).color(Color32::from_rgb(0, 200, 255)))
+                            .on_hover_text(format!("[{}, {}, {}, {}] 对比度 {:.0}", s.rect.min.x, s.rect.min.y, s.rect.max.x, s.rect.max.y, s.contrast));
+                    });
+                }
+                if let Some(i) = accept { self.accept_suggestion(i); }
+
+                // --- 锚点稳定性采样 ---
+                ui.separator();
+                ui.collapsing("📈 锚点稳定性采样", |ui| {
+                    ui.horizontal(|ui| {
+                        ui.add(egui::DragValue::new(&mut self.stability_duration_secs).clamp_range(5..=600).suffix(" 秒"));
+                        ui.add(egui::DragValue::new(&mut self.stability_interval_ms).clamp_range(200..=10000).prefix("间隔 ").suffix(" ms"));
+                    });
+                    if let Some(run) = &self.stability_run {
+                        ui.add(egui::ProgressBar::new(run.progress).text("采样中..."));
+                    } else if ui.button("▶ 开始采样当前场景").clicked() {
+                        self.start_stability_sampling();
+                    }
+                });
+
                 // --- 元素列表 --- 
                 ui.separator();
                 ui.heading("📋 元素列表");
                 egui::ScrollArea::vertical().id_source("element_list").max_height(200.0).show(ui, |ui| {
+                    let scene_index = self.current_scene_index;
+                    let stability = match &self.stability_results {
+                        Some((idx, _, results)) if *idx == scene_index => results.clone(),
+                        _ => Vec::new(),
+                    };
+                    let live = match &self.live_test_results {
+                        Some((idx, results)) if *idx == scene_index => results.clone(),
+                        _ => Vec::new(),
+                    };
+                    let mut selected = self.selected_draft();
+                    let mut resample = None;
+                    let mut edit_transition = None;
                     let current_scene = self.current_scene_mut();
                     let mut del = None;
                     for (i, d) in current_scene.drafts.iter_mut().enumerate() {
                         ui.horizontal(|ui| {
+                            let r = d.pos_or_rect;
+                            if ui.selectable_label(selected == Some(i), format!("#{}", i + 1))
+                                .on_hover_text(format!("[{}, {}, {}, {}]", r.min.x, r.min.y, r.max.x, r.max.y))
+                                .clicked() {
+                                selected = if selected == Some(i) { None } else { Some(i) };
+                            }
+                            if let Some(st) = stability.iter().find(|s| s.draft_index == i) {
+                                let (icon, color) = if st.is_flaky() { ("⚠️", Color32::from_rgb(255, 80, 80)) } else { ("✔", Color32::from_rgb(0, 200, 100)) };
+                                ui.label(RichText::new(format!("{} {:.0}%", icon, st.match_rate * 100.0)).color(color))
+                                    .on_hover_text(format!("采样 {} 次 | 命中率 {:.0}% | 一致性 {:.0}% | {}", st.samples, st.match_rate * 100.0, st.consistency * 100.0, st.detail));
+                            }
+                            if let Some(check) = live.iter().find(|c| c.draft_index == i) {
+                                let (icon, color) = if check.passed { ("🎯✔", Color32::from_rgb(0, 200, 100)) } else { ("🎯✘", Color32::from_rgb(255, 80, 80)) };
+                                ui.label(RichText::new(icon).color(color)).on_hover_text(&check.detail);
+                            }
                             match &mut d.kind {
                                 ElementKind::TextAnchor { text } => { ui.label("⚓"); ui.text_edit_singleline(text); }
                                 ElementKind::ColorAnchor { color_hex, tolerance } => {
-                                    ui.label("🧪"); ui.label(color_hex.as_str());
+                                    ui.label("🧪");
+                                    // 色块：点击从当前截图重新取色
+                                    let (swatch, resp) = ui.allocate_exact_size(Vec2::splat(16.0), Sense::click());
+                                    ui.painter().rect(swatch, 2.0, hex_to_color32(color_hex), Stroke::new(1.0, Color32::GRAY));
+                                    if resp.on_hover_text("点击从当前截图重新取色").clicked() { resample = Some(i); }
+                                    ui.label(color_hex.as_str());
                                     ui.add(egui::DragValue::new(tolerance).prefix("T:"));
                                 }
-                                ElementKind::Button { target, post_delay } => {
+                                ElementKind::HsvAnchor { color_hex, hue_tol, sat_tol, val_tol } => {
+                                    ui.label("🎨");
+                                    let (swatch, resp) = ui.allocate_exact_size(Vec2::splat(16.0), Sense::click());
+                                    ui.painter().rect(swatch, 2.0, hex_to_color32(color_hex), Stroke::new(1.0, Color32::GRAY));
+                                    if resp.on_hover_text("点击从当前截图重新取区域平均色").clicked() { resample = Some(i); }
+                                    ui.label(color_hex.as_str());
+                                    ui.add(egui::DragValue::new(hue_tol).speed(0.5).clamp_range(0.0..=180.0).prefix("H:")).on_hover_text("色相容差 (度)");
+                                    ui.add(egui::DragValue::new(sat_tol).speed(0.01).clamp_range(0.0..=1.0).prefix("S:")).on_hover_text("饱和度容差");
+                                    ui.add(egui::DragValue::new(val_tol).speed(0.01).clamp_range(0.0..=1.0).prefix("V:")).on_hover_text("亮度容差");
+                                }
+                                ElementKind::Button { target, post_delay, extra } => {
                                     ui.label("🖱️"); ui.text_edit_singleline(target);
                                     ui.add(egui::DragValue::new(post_delay).prefix("ms:"));
+                                    if ui.small_button(extra.action.as_str()).on_hover_text("编辑动作、按键、前后等待与重试").clicked() { edit_transition = Some(i); }
+                                }
+                                ElementKind::ImageAnchor { file, threshold } => {
+                                    ui.label("🖼️"); ui.label(file.as_str());
+                                    ui.add(egui::DragValue::new(threshold).speed(0.01).clamp_range(0.5..=1.0).prefix("阈值:"));
                                 }
                             }
                             if ui.button("❌").clicked() { del = Some(i); }
                         });
                     }
-                    if let Some(i) = del { current_scene.drafts.remove(i); }
+                    if let Some(i) = resample {
+                        let rect = current_scene.drafts[i].pos_or_rect;
+                        let color = match current_scene.drafts[i].kind {
+                            ElementKind::HsvAnchor { .. } => self.average_color(rect),
+                            _ => self.pick_color(rect.min),
+                        };
+                        if let ElementKind::ColorAnchor { color_hex, .. } | ElementKind::HsvAnchor { color_hex, .. } = &mut self.current_scene_mut().drafts[i].kind {
+                            *color_hex = color;
+                        }
+                    }
+                    let current_scene = self.current_scene_mut();
+                    if let Some(i) = del {
+                        current_scene.drafts.remove(i);
+                        selected = match selected { Some(s) if s == i => None, Some(s) if s > i => Some(s - 1), s => s };
+                    }
+                    // 选中元素的坐标 (与截图上的拖动实时同步)
+                    if let Some(d) = selected.and_then(|i| current_scene.drafts.get_mut(i)) {
+                        ui.horizontal(|ui| {
+                            let r = &mut d.pos_or_rect;
+                            ui.label("区域:");
+                            ui.add(egui::DragValue::new(&mut r.min.x).speed(1.0).prefix("x1 "));
+                            ui.add(egui::DragValue::new(&mut r.min.y).speed(1.0).prefix("y1 "));
+                            ui.add(egui::DragValue::new(&mut r.max.x).speed(1.0).prefix("x2 "));
+                            ui.add(egui::DragValue::new(&mut r.max.y).speed(1.0).prefix("y2 "));
+                            *r = Rect::from_two_pos(r.min.round(), r.max.round());
+                        });
+                    }
+                    self.selected_draft = selected.map(|i| (self.current_scene_index, i));
+                    if let Some(i) = edit_transition {
+                        self.open_transition_editor(self.current_scene_index, i);
+                    }
                 });
             }
 
+            // --- 导出前校验 ---
+            ui.separator();
+            self.draw_validation(ui);
+
             // --- TOML 操作 --- 
             ui.separator();
             ui.heading("📄 TOML 操作");
             ui.horizontal(|ui| {
                 if ui.button("📤 生成 TOML").clicked() { self.build_toml(); }
                 if ui.button("📥 导入 TOML").clicked() { self.import_toml(); }
-                if ui.button("💾 保存到文件").clicked() {
-                    let file_path = "./ui_map.toml";
-                    if let Ok(_) = std::fs::write(file_path, &self.toml_content) {
-                        self.status_msg = format!("已保存到 {}", file_path).into();
-                    } else {
-                        self.status_msg = "保存文件失败".into();
-                    }
-                }
-                if ui.button("📂 加载文件").clicked() {
-                    let file_path = "./ui_map.toml";
-                    if let Ok(content) = std::fs::read_to_string(file_path) {
-                        self.toml_content = content;
-                        self.import_toml();
-                        self.status_msg = format!("已加载 {}", file_path).into();
-                    } else {
-                        self.status_msg = "加载文件失败".into();
-                    }
-                }
             });
+            ui.horizontal(|ui| {
+                if ui.button("📂 打开文件").clicked() { self.open_map_file(); }
+                if ui.button("💾 保存").on_hover_text("Ctrl+S").clicked() { self.save_map_file(false); }
+                if ui.button("💾 另存为").clicked() { self.save_map_file(true); }
+            });
+            ui.horizontal(|ui| {
+                if ui.button("📦 打开项目").on_hover_text("项目目录包含地图、场景布局、参考截图与编辑器设置").clicked() { self.open_project(ctx); }
+                if ui.button("📦 保存项目").clicked() { self.save_project(false); }
+                if ui.button("📦 项目另存为").clicked() { self.save_project(true); }
+            });
+            if let Some(dir) = &self.project_dir {
+                ui.label(RichText::new(format!("📦 {}", dir.display())).weak());
+            }
+            match &self.map_file {
+                Some((path, _)) => ui.label(RichText::new(format!("📄 {}", path.display())).weak()),
+                None => ui.label(RichText::new("📄 未关联文件 (保存时选择路径)").weak()),
+            };
             
             egui::ScrollArea::vertical().id_source("toml_scroll").show(ui, |ui| {
                 ui.add(egui::TextEdit::multiline(&mut self.toml_content).font(egui::TextStyle::Monospace).desired_width(f32::INFINITY));
@@ -939,14 +4592,23 @@ impl eframe::App for MapBuilderTool {
         });
 
         egui::CentralPanel::default().show(ctx, |ui| {
-            if self.show_visualization {
-                // 场景可视化模式
-                self.draw_visualization_panel(ui);
-            } else {
-                // 截图编辑模式
-                self.draw_screenshot_panel(ui);
+            match self.view {
+                ViewMode::Visualize => self.draw_visualization_panel(ui),
+                ViewMode::Edit => {
+                    self.nudge_selected(ctx);
+                    self.draw_screenshot_panel(ui);
+                }
+                // 快速截取只框选，不编辑场景元素
+                ViewMode::QuickCapture => self.draw_screenshot_panel(ui),
             }
         });
+        self.draw_reocr_review(ctx);
+        self.draw_save_review(ctx);
+        self.draw_regression_results(ctx);
+        self.draw_session_replay(ctx);
+
+        let interacting = ctx.input(|i| i.pointer.any_down()) || ctx.wants_keyboard_input();
+        self.history.track(&self.scenes, self.current_scene_index, interacting);
     }
 }
 